@@ -0,0 +1,24 @@
+#import bevy_sprite::mesh2d_vertex_output::VertexOutput
+
+@group(2) @binding(0)
+var light_texture: texture_2d<f32>;
+
+@group(2) @binding(1)
+var light_sampler: sampler;
+
+// Linear ambient color, added to the simulated light
+@group(2) @binding(2)
+var<uniform> ambient: vec4<f32>;
+
+// 1.0 keeps the light color, more pushes it away from gray
+@group(2) @binding(3)
+var<uniform> saturation: f32;
+
+@fragment
+fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
+    // The texture holds linear light energy, the blend state multiplies it with the scene
+    let light = textureSample(light_texture, light_sampler, in.uv).rgb + ambient.rgb;
+    let luminance = dot(light, vec3<f32>(0.2126, 0.7152, 0.0722));
+    let tinted = max(mix(vec3<f32>(luminance), light, saturation), vec3<f32>(0.0));
+    return vec4<f32>(tinted, 1.0);
+}
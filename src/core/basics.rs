@@ -1,11 +1,12 @@
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 use std::{
     fmt::Debug,
     hash::Hash,
     ops::{Add, Sub},
 };
 
-use crate::core::units::Units; // For polling tasks
+use crate::core::units::{TilesCount, Units}; // For polling tasks
 
 pub const DEFAULT_RENDER_DISTANCE_CHUNKS: usize = 3; // Load 3 chunks out from focus
 pub const GAME_WORLD_CENTER_THRESHOLD: f32 = 10.0; // Distance from 0,0 where passability becomes 0
@@ -13,7 +14,7 @@ pub const GAME_WORLD_CENTER_THRESHOLD: f32 = 10.0; // Distance from 0,0 where pa
 // --- Coordinate Structs ---
 
 /// Absolute world tile coordinates.
-#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Reflect)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default, Reflect, Serialize, Deserialize)]
 pub struct Point {
     pub x: Units,
     pub y: Units,
@@ -76,11 +77,15 @@ impl Point {
     /// 
     /// # Example
     /// ```
+    /// use bevy::math::Vec2;
+    /// use rust_sim::core::basics::Point;
+    ///
     /// let world_pos = Vec2::new(150.0, 75.0);
     /// let tile_size = 32;
     /// let tile_point = Point::from_world_pos(world_pos, tile_size);
-    /// // tile_point would be Point { x: 4, y: 2 }
+    /// assert_eq!(tile_point, Point { x: 4, y: 2 });
     /// ```
+    #[deprecated(note = "use `WorldPos::to_tile`, the DataMaps center tiles on their coordinates")]
     pub fn from_world_pos(world_pos: Vec2, tile_size: Units) -> Self {
         let tile_size_f32 = tile_size as f32;
         Self {
@@ -96,6 +101,7 @@ impl Point {
     /// 
     /// # Returns
     /// Vec2 representing the world position at the center of the tile
+    #[deprecated(note = "use `TileCoord::center`, the DataMaps center tiles on their coordinates")]
     pub fn to_world_pos(&self, tile_size: Units) -> Vec2 {
         let tile_size_f32 = tile_size as f32;
         Vec2::new(
@@ -111,6 +117,7 @@ impl Point {
     /// 
     /// # Returns
     /// Vec2 representing the world position at the top-left corner of the tile
+    #[deprecated(note = "use `TileCoord::corner`, the DataMaps center tiles on their coordinates")]
     pub fn to_world_pos_corner(&self, tile_size: Units) -> Vec2 {
         let tile_size_f32 = tile_size as f32;
         Vec2::new(
@@ -118,6 +125,59 @@ impl Point {
             self.y as f32 * tile_size_f32,
         )
     }
+
+    /// Number of orthogonal steps between two tiles.
+    pub fn manhattan_distance(&self, other: Point) -> usize {
+        self.x.abs_diff(other.x) + self.y.abs_diff(other.y)
+    }
+
+    /// Number of king moves between two tiles, diagonals included.
+    pub fn chebyshev_distance(&self, other: Point) -> usize {
+        self.x.abs_diff(other.x).max(self.y.abs_diff(other.y))
+    }
+
+    /// Right, left, up and down.
+    pub fn neighbors4(&self) -> [Point; 4] {
+        let Point { x, y } = *self;
+        [
+            Point { x: x + 1, y },
+            Point { x: x - 1, y },
+            Point { x, y: y + 1 },
+            Point { x, y: y - 1 },
+        ]
+    }
+
+    /// The `neighbors4` followed by the four diagonals.
+    pub fn neighbors8(&self) -> [Point; 8] {
+        let Point { x, y } = *self;
+        let [right, left, up, down] = self.neighbors4();
+        [
+            right,
+            left,
+            up,
+            down,
+            Point { x: x + 1, y: y + 1 },
+            Point { x: x - 1, y: y + 1 },
+            Point { x: x + 1, y: y - 1 },
+            Point { x: x - 1, y: y - 1 },
+        ]
+    }
+
+    /// Coordinates of this tile inside its chunk of `dimension` tiles, negative tiles included.
+    /// The one place tile to local chunk coordinates are computed.
+    pub fn local_in_chunk(&self, dimension: TilesCount) -> (TilesCount, TilesCount) {
+        let dimension = dimension as Units;
+        (
+            self.x.rem_euclid(dimension) as TilesCount,
+            self.y.rem_euclid(dimension) as TilesCount,
+        )
+    }
+}
+
+/// Stable random bits of a world tile. Depends only on the tile and the seed, never on which
+/// chunk or hypertile asked, so per-tile details agree across their borders and sessions.
+pub fn tile_hash(point: Point, seed: u64) -> u64 {
+    crate::core::noise::hash2(seed, point.x, point.y)
 }
 
 impl<T, U> From<(T, U)> for Point
@@ -131,3 +191,52 @@ where
         Point::new(coords.0, coords.1)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn local_in_chunk_recomposes_every_tile_around_zero() {
+        for dimension in [1, 2, 3, 8, 16, 33] {
+            let dim = dimension as Units;
+            for x in -3 * dim - 1..=3 * dim + 1 {
+                for y in [-dim - 1, -dim, -1, 0, 1, dim - 1, dim] {
+                    let (local_x, local_y) = Point { x, y }.local_in_chunk(dimension);
+                    assert!(local_x < dimension && local_y < dimension, "{x}, {y} in {dimension}");
+                    // The chunk origin plus the local offset is the tile again
+                    assert_eq!(x.div_euclid(dim) * dim + local_x as Units, x, "{x} in {dimension}");
+                    assert_eq!(y.div_euclid(dim) * dim + local_y as Units, y, "{y} in {dimension}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn local_in_chunk_counts_up_across_the_origin() {
+        let locals: Vec<TilesCount> = (-5..5).map(|x| Point { x, y: 0 }.local_in_chunk(4).0).collect();
+        assert_eq!(locals, [3, 0, 1, 2, 3, 0, 1, 2, 3, 0]);
+    }
+
+    #[test]
+    fn distances_across_negative_coordinates() {
+        let a = Point { x: -3, y: 2 };
+        let b = Point { x: 4, y: -1 };
+        assert_eq!(a.manhattan_distance(b), 10);
+        assert_eq!(a.chebyshev_distance(b), 7);
+        assert_eq!(b.manhattan_distance(a), 10);
+        assert_eq!(a.chebyshev_distance(a), 0);
+    }
+
+    #[test]
+    fn neighbors_are_distinct_and_adjacent() {
+        let center = Point { x: -1, y: 0 };
+        let around = center.neighbors8();
+        assert_eq!(around[..4], center.neighbors4());
+        for (i, p) in around.iter().enumerate() {
+            assert_eq!(center.chebyshev_distance(*p), 1);
+            assert_eq!(center.manhattan_distance(*p), if i < 4 { 1 } else { 2 });
+            assert!(!around[i + 1..].contains(p));
+        }
+    }
+}
@@ -1,16 +1,19 @@
 use bevy::{
-    platform::collections::{HashMap, HashSet},
+    platform::{
+        collections::{HashMap, HashSet},
+        time::Instant,
+    },
     prelude::*,
     tasks::{AsyncComputeTaskPool, Task},
 };
 use futures_lite::future;
-use std::{fmt::Debug, hash::Hash};
+use std::{cmp::Reverse, collections::VecDeque, fmt::Debug, fs, hash::Hash, marker::PhantomData, path::PathBuf};
 
 use crate::{
     core::{basics::{
          Point, DEFAULT_RENDER_DISTANCE_CHUNKS
-    }, constants::{DEFAULT_CHUNK_DIMENSION_TILES, TILE_SIZE_IN_UNITS}, units::{TilesCount}},
-    game::MapRevealActor,
+    }, constants::{DEFAULT_CHUNK_DIMENSION_TILES, TILE_SIZE_IN_UNITS}, units::{TileCoord, TilesCount, WorldPos}, validation::{ConfigError, MapLayout, WorldLayout, short_type_name, tiles_to_units}},
+    game::{MapRevealActor, camera::FollowCamera},
 }; // For polling tasks
 
 use std::sync::Arc;
@@ -25,7 +28,7 @@ use bevy::{
     transform::components::Transform,
 };
 
-use crate::Player;
+use crate::game::Player;
 
 /// Absolute chunk coordinates.
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Reflect, Component)]
@@ -38,13 +41,42 @@ pub struct ChunkCoords {
 impl ChunkCoords {
     /// Converts a world tile `Point` to `ChunkCoords`.
     pub fn from_point(point: Point, chunk_dimension_tiles: TilesCount) -> Self {
+        let dimension = chunk_dimension_tiles as isize;
         ChunkCoords {
-            x: (point.x as f32 / chunk_dimension_tiles as f32).floor() as isize,
-            y: (point.y as f32 / chunk_dimension_tiles as f32).floor() as isize,
+            x: point.x.div_euclid(dimension),
+            y: point.y.div_euclid(dimension),
         }
     }
 
+    /// Whether the tile at `point` belongs to this chunk.
+    pub fn contains_point(&self, point: Point, chunk_dimension_tiles: TilesCount) -> bool {
+        ChunkCoords::from_point(point, chunk_dimension_tiles) == *self
+    }
+
+    /// The 8 chunks around this one, orthogonal ones first.
+    pub fn neighbors8(&self) -> [ChunkCoords; 8] {
+        Point { x: self.x, y: self.y }
+            .neighbors8()
+            .map(|p| ChunkCoords { x: p.x, y: p.y })
+    }
+
+    /// Chunks within Chebyshev distance `radius` of `center`, the center included.
+    pub fn iter_square(center: ChunkCoords, radius: usize) -> impl Iterator<Item = ChunkCoords> {
+        required_chunks(center, LoadShape::Square, radius)
+    }
+
+    /// Chunks within the disc of `radius` around `center`, see `LoadShape::Circle`.
+    pub fn iter_circle(center: ChunkCoords, radius: usize) -> impl Iterator<Item = ChunkCoords> {
+        required_chunks(center, LoadShape::Circle, radius)
+    }
+
+    /// Chunk holding the tile at `pos`.
+    pub fn from_world(pos: WorldPos, chunk_dimension_tiles: TilesCount) -> Self {
+        Self::from_point(pos.to_tile().0, chunk_dimension_tiles)
+    }
+
     /// Converts a world unit `Vec2` to `ChunkCoords`.
+    #[deprecated(note = "use `ChunkCoords::from_world`, this floors half a tile off the tile grid")]
     pub fn from_world_pos(pos: Vec2, chunk_size_units: f32) -> Self {
         ChunkCoords {
             x: (pos.x / chunk_size_units).floor() as isize,
@@ -60,7 +92,13 @@ impl ChunkCoords {
         }
     }
 
+    /// World position of the chunk's bottom-left corner, the corner of its bottom-left tile.
+    pub fn corner(&self, chunk_dimension_tiles: TilesCount) -> WorldPos {
+        TileCoord(self.to_bottom_left_tile_point(chunk_dimension_tiles)).corner()
+    }
+
     /// Converts `ChunkCoords` to the world unit `Vec2` of its bottom-left corner.
+    #[deprecated(note = "use `ChunkCoords::corner`, this is half a tile off the tile grid")]
     pub fn to_world_pos(&self, chunk_size_units: f32) -> Vec2 {
         Vec2::new(
             self.x as f32 * chunk_size_units,
@@ -72,11 +110,68 @@ impl ChunkCoords {
 pub trait GridData: Send + Sync + 'static + Debug + Clone {
     type Item: Copy + Debug + Default; // Default trait required for new()
     fn dimension(&self) -> TilesCount;
+    /// Memory held by the cells, see `ChunkMemoryBudget`. Estimated from the item size
+    /// unless the grid knows better.
+    fn byte_size(&self) -> usize {
+        std::mem::size_of::<Self::Item>() * self.dimension() * self.dimension()
+    }
     fn get_item(&self, x: TilesCount, y: TilesCount) -> Option<&Self::Item>;
-    fn get_item_mut(&mut self, x: TilesCount, y: TilesCount) -> Option<&mut Self::Item>;
     fn set_item(&mut self, x: TilesCount, y: TilesCount, item: Self::Item) -> bool;
+    /// All cells as `(x, y, item)`, row by row starting at y = 0.
+    fn iter_indexed(&self) -> impl Iterator<Item = (TilesCount, TilesCount, &Self::Item)>;
+    fn map_in_place(&mut self, f: impl FnMut(TilesCount, TilesCount, Self::Item) -> Self::Item);
+    /// Grid with one cell per `factor` x `factor` block, set to `reducer` of the block's items
+    /// (row by row). Blocks at the far edges are partial when `factor` doesn't divide the size.
+    fn downsample(&self, factor: TilesCount, reducer: impl FnMut(&[Self::Item]) -> Self::Item) -> Self;
+    /// Copies the run of `out.len()` cells of row `y` starting at `x` into `out`.
+    /// Cells outside the grid leave their slot untouched.
+    fn read_run(&self, x: TilesCount, y: TilesCount, out: &mut [Self::Item]) {
+        for (dx, slot) in out.iter_mut().enumerate() {
+            if let Some(item) = self.get_item(x + dx, y) {
+                *slot = *item;
+            }
+        }
+    }
+    /// Writes `items` along row `y` starting at `x`, clipped to the grid.
+    fn write_run(&mut self, x: TilesCount, y: TilesCount, items: &[Self::Item]) {
+        for (dx, item) in items.iter().enumerate() {
+            self.set_item(x + dx, y, *item);
+        }
+    }
+    /// Sets `len` cells along row `y` starting at `x` to `item`, clipped to the grid.
+    fn fill_run(&mut self, x: TilesCount, y: TilesCount, len: TilesCount, item: Self::Item) {
+        for dx in 0..len {
+            self.set_item(x + dx, y, item);
+        }
+    }
+    /// Copies the `size` (width, height) rectangle of `src` at `src_origin` to `dst_origin`.
+    /// The rectangle is clipped to both grids, cells falling outside either are skipped.
+    fn copy_region_from(
+        &mut self,
+        src: &Self,
+        src_origin: (TilesCount, TilesCount),
+        dst_origin: (TilesCount, TilesCount),
+        size: (TilesCount, TilesCount),
+    ) {
+        for dy in 0..size.1 {
+            for dx in 0..size.0 {
+                let Some(item) = src.get_item(src_origin.0 + dx, src_origin.1 + dy) else {
+                    continue;
+                };
+                self.set_item(dst_origin.0 + dx, dst_origin.1 + dy, *item);
+            }
+        }
+    }
+}
+
+/// Grids storing one item per cell in a row-major `Vec`, so cells and rows can be borrowed.
+pub trait ContiguousGridData: GridData {
+    fn get_item_mut(&mut self, x: TilesCount, y: TilesCount) -> Option<&mut Self::Item>;
     fn as_slice(&self) -> &[Self::Item];
     fn as_mut_slice(&mut self) -> &mut [Self::Item];
+    /// Row `y`, or `None` if `y` is out of range.
+    fn row(&self, y: TilesCount) -> Option<&[Self::Item]>;
+    fn row_mut(&mut self, y: TilesCount) -> Option<&mut [Self::Item]>;
 }
 
 #[derive(Debug, Clone)]
@@ -85,7 +180,8 @@ where
     T: Copy + Debug + Send + Sync + 'static + Default,
 {
     data: Vec<T>,
-    dimension: TilesCount,
+    dimension: TilesCount, // Width of the grid (chunks are always square)
+    height: TilesCount,
 }
 
 impl<T> FlatGrid<T>
@@ -93,15 +189,41 @@ where
     T: Copy + Debug + Send + Sync + 'static + Default,
 {
     pub fn new(dimension: TilesCount, default_value: T) -> Self {
-        let num_elements = (dimension * dimension) as usize;
+        Self::new_rect(dimension, dimension, default_value)
+    }
+
+    /// Creates a rectangular grid, used for area reads that don't match the chunk shape.
+    pub fn new_rect(width: TilesCount, height: TilesCount, default_value: T) -> Self {
+        let num_elements = width * height;
         FlatGrid {
             data: vec![default_value; num_elements],
+            dimension: width,
+            height,
+        }
+    }
+
+    /// Creates a square grid with every cell set to `f(x, y)`.
+    pub fn from_fn(dimension: TilesCount, mut f: impl FnMut(TilesCount, TilesCount) -> T) -> Self {
+        FlatGrid {
+            data: (0..dimension)
+                .flat_map(|y| (0..dimension).map(move |x| (x, y)))
+                .map(|(x, y)| f(x, y))
+                .collect(),
             dimension,
+            height: dimension,
         }
     }
 
+    pub fn width(&self) -> TilesCount {
+        self.dimension
+    }
+
+    pub fn height(&self) -> TilesCount {
+        self.height
+    }
+
     fn calculate_index(&self, x: TilesCount, y: TilesCount) -> Option<usize> {
-        if x < self.dimension && y < self.dimension {
+        if x < self.dimension && y < self.height {
             Some((y * self.dimension + x) as TilesCount)
         } else {
             None
@@ -119,12 +241,12 @@ where
         self.dimension
     }
 
-    fn get_item(&self, x: TilesCount, y: TilesCount) -> Option<&Self::Item> {
-        self.calculate_index(x, y).map(|idx| &self.data[idx])
+    fn byte_size(&self) -> usize {
+        self.data.capacity() * std::mem::size_of::<T>()
     }
 
-    fn get_item_mut(&mut self, x: TilesCount, y: TilesCount) -> Option<&mut Self::Item> {
-        self.calculate_index(x, y).map(|idx| &mut self.data[idx])
+    fn get_item(&self, x: TilesCount, y: TilesCount) -> Option<&Self::Item> {
+        self.calculate_index(x, y).map(|idx| &self.data[idx])
     }
 
     fn set_item(&mut self, x: TilesCount, y: TilesCount, item: Self::Item) -> bool {
@@ -136,6 +258,99 @@ where
         }
     }
 
+    fn iter_indexed(&self) -> impl Iterator<Item = (TilesCount, TilesCount, &Self::Item)> {
+        let width = self.dimension;
+        self.data
+            .iter()
+            .enumerate()
+            .map(move |(idx, item)| (idx % width, idx / width, item))
+    }
+
+    fn map_in_place(&mut self, mut f: impl FnMut(TilesCount, TilesCount, Self::Item) -> Self::Item) {
+        let width = self.dimension;
+        for (idx, item) in self.data.iter_mut().enumerate() {
+            *item = f(idx % width, idx / width, *item);
+        }
+    }
+
+    fn downsample(&self, factor: TilesCount, mut reducer: impl FnMut(&[Self::Item]) -> Self::Item) -> Self {
+        let factor = factor.max(1);
+        let width = self.dimension.div_ceil(factor);
+        let height = self.height.div_ceil(factor);
+        let mut block = Vec::with_capacity(factor * factor);
+        let mut data = Vec::with_capacity(width * height);
+        for by in 0..height {
+            for bx in 0..width {
+                block.clear();
+                for y in by * factor..((by + 1) * factor).min(self.height) {
+                    let row = &self.data[y * self.dimension..(y + 1) * self.dimension];
+                    block.extend_from_slice(&row[bx * factor..((bx + 1) * factor).min(self.dimension)]);
+                }
+                data.push(reducer(&block));
+            }
+        }
+        FlatGrid {
+            data,
+            dimension: width,
+            height,
+        }
+    }
+
+    fn copy_region_from(
+        &mut self,
+        src: &Self,
+        src_origin: (TilesCount, TilesCount),
+        dst_origin: (TilesCount, TilesCount),
+        size: (TilesCount, TilesCount),
+    ) {
+        let width = size
+            .0
+            .min(src.dimension.saturating_sub(src_origin.0))
+            .min(self.dimension.saturating_sub(dst_origin.0));
+        let height = size
+            .1
+            .min(src.height.saturating_sub(src_origin.1))
+            .min(self.height.saturating_sub(dst_origin.1));
+        for dy in 0..height {
+            let src_start = (src_origin.1 + dy) * src.dimension + src_origin.0;
+            let dst_start = (dst_origin.1 + dy) * self.dimension + dst_origin.0;
+            self.data[dst_start..dst_start + width].copy_from_slice(&src.data[src_start..src_start + width]);
+        }
+    }
+
+    fn read_run(&self, x: TilesCount, y: TilesCount, out: &mut [Self::Item]) {
+        let Some(row) = self.row(y).and_then(|row| row.get(x..)) else {
+            return;
+        };
+        let len = out.len().min(row.len());
+        out[..len].copy_from_slice(&row[..len]);
+    }
+
+    fn write_run(&mut self, x: TilesCount, y: TilesCount, items: &[Self::Item]) {
+        let Some(row) = self.row_mut(y).and_then(|row| row.get_mut(x..)) else {
+            return;
+        };
+        let len = items.len().min(row.len());
+        row[..len].copy_from_slice(&items[..len]);
+    }
+
+    fn fill_run(&mut self, x: TilesCount, y: TilesCount, len: TilesCount, item: Self::Item) {
+        let Some(row) = self.row_mut(y).and_then(|row| row.get_mut(x..)) else {
+            return;
+        };
+        let len = len.min(row.len());
+        row[..len].fill(item);
+    }
+}
+
+impl<T> ContiguousGridData for FlatGrid<T>
+where
+    T: Copy + Debug + Send + Sync + 'static + Default,
+{
+    fn get_item_mut(&mut self, x: TilesCount, y: TilesCount) -> Option<&mut Self::Item> {
+        self.calculate_index(x, y).map(|idx| &mut self.data[idx])
+    }
+
     fn as_slice(&self) -> &[Self::Item] {
         &self.data
     }
@@ -143,440 +358,4186 @@ where
     fn as_mut_slice(&mut self) -> &mut [Self::Item] {
         &mut self.data
     }
+
+    fn row(&self, y: TilesCount) -> Option<&[Self::Item]> {
+        if y >= self.height {
+            return None;
+        }
+        Some(&self.data[y * self.dimension..(y + 1) * self.dimension])
+    }
+
+    fn row_mut(&mut self, y: TilesCount) -> Option<&mut [Self::Item]> {
+        if y >= self.height {
+            return None;
+        }
+        Some(&mut self.data[y * self.dimension..(y + 1) * self.dimension])
+    }
 }
 
-/// A chunk of specific map data. The manager knows its coordinates.
+const BITS_PER_WORD: usize = u64::BITS as usize;
+
+/// Grid of flags packed 64 to a `u64`, for boolean layers (masks, explored flags) that would
+/// take 8 times the memory as `FlatGrid<bool>`. Same dimension semantics as `FlatGrid`.
+/// Bits can't be borrowed, so it isn't `ContiguousGridData`.
 #[derive(Debug, Clone)]
-pub struct DataChunk<T: GridData> {
-    pub grid: T,
+pub struct BitGrid {
+    words: Vec<u64>,       // Row-major bits, the unused tail of the last word stays clear
+    dimension: TilesCount, // Width of the grid
+    height: TilesCount,
 }
 
-// Marker component for tasks in flight
-#[derive(Component)]
-pub struct ChunkGenTask<T: GridData>(pub Task<DataChunk<T>>);
+impl BitGrid {
+    pub fn new(dimension: TilesCount, default_value: bool) -> Self {
+        Self::new_rect(dimension, dimension, default_value)
+    }
 
-pub trait MapDataProducer: Send + Sync + 'static + Clone {
-    type Item: Copy + Default + Send + Sync;
-    type GridType: GridData<Item = Self::Item> + Send + Sync;
+    pub fn new_rect(width: TilesCount, height: TilesCount, default_value: bool) -> Self {
+        let mut grid = BitGrid {
+            words: vec![0; (width * height).div_ceil(BITS_PER_WORD)],
+            dimension: width,
+            height,
+        };
+        grid.fill(default_value);
+        grid
+    }
 
-    /// Returns the default value for an ungenerated tile.
-    fn default_value(&self) -> Self::Item;
+    pub fn width(&self) -> TilesCount {
+        self.dimension
+    }
 
-    /// Generates a chunk of data for the given coordinates.
-    /// Returns the DataChunk asset.
-    fn generate_chunk(
-        &self,
-        coords: ChunkCoords,
-        dimension_tiles: TilesCount,
-    ) -> DataChunk<Self::GridType>;
-}
+    pub fn height(&self) -> TilesCount {
+        self.height
+    }
 
-/// The central resource for managing a chunked map of type T.
-#[derive(Resource)]
-pub struct DataMap<P: MapDataProducer> {
-    pub loaded_chunks: HashMap<ChunkCoords, DataChunk<P::GridType>>,
-    pub requested_chunks: HashSet<ChunkCoords>,
-    // Maps chunk coords to the entity holding the generation task
-    pub pending_tasks: HashMap<ChunkCoords, Entity>,
-    pub write_queue: HashMap<Point, P::Item>, // Writes to uncreated/unloaded cells
-    pub producer: P,
-    pub chunk_dimension_tiles: TilesCount,
-    pub chunk_size_units: f32, // Derived from chunk_dimension_tiles and TILE_SIZE_IN_UNITS
-    pub render_distance_chunks: usize, // Used by the map manager system to determine loading radius
-}
+    fn len(&self) -> usize {
+        self.dimension * self.height
+    }
 
-impl<P: MapDataProducer> DataMap<P> {
-    pub fn new(producer: P, chunk_dimension_tiles: TilesCount, render_distance_chunks: usize) -> Self {
-        let chunk_size_units = chunk_dimension_tiles as f32 * TILE_SIZE_IN_UNITS;
-        Self {
-            loaded_chunks: HashMap::new(),
-            requested_chunks: HashSet::new(),
-            pending_tasks: HashMap::new(),
-            write_queue: HashMap::new(),
-            producer,
-            chunk_dimension_tiles,
-            chunk_size_units,
-            render_distance_chunks,
+    fn calculate_index(&self, x: TilesCount, y: TilesCount) -> Option<usize> {
+        if x < self.dimension && y < self.height {
+            Some(y * self.dimension + x)
+        } else {
+            None
         }
     }
 
-    // --- Public API for Game Logic ---
-
-    /// Requests and gets the data at a specific world tile Point.
-    /// Spawns a chunk generation request if the chunk is not loaded.
-    pub fn get(&mut self, point: Point) -> P::Item {
-        // Check write queue first (acts as a cache for pending writes)
-        if let Some(&queued_value) = self.write_queue.get(&point) {
-            return queued_value;
-        }
+    fn bit(&self, idx: usize) -> bool {
+        self.words[idx / BITS_PER_WORD] & (1 << (idx % BITS_PER_WORD)) != 0
+    }
 
-        let chunk_coords = ChunkCoords::from_point(point, self.chunk_dimension_tiles);
-        if let Some(chunk) = self.loaded_chunks.get(&chunk_coords) {
-            let local_x = (point.x % self.chunk_dimension_tiles as isize
-                + self.chunk_dimension_tiles as isize)
-                % self.chunk_dimension_tiles as isize;
-            let local_y = (point.y % self.chunk_dimension_tiles as isize
-                + self.chunk_dimension_tiles as isize)
-                % self.chunk_dimension_tiles as isize;
-            chunk
-                .grid
-                .get_item(local_x as TilesCount, local_y as TilesCount)
-                .copied() // Get a copy of the item
-                .unwrap_or_else(|| self.producer.default_value()) // Should not happen if logic is correct
+    fn set_bit(&mut self, idx: usize, value: bool) {
+        let mask = 1 << (idx % BITS_PER_WORD);
+        let word = &mut self.words[idx / BITS_PER_WORD];
+        if value {
+            *word |= mask;
         } else {
-            // Chunk not loaded, request it
-            self.requested_chunks.insert(chunk_coords);
-            self.producer.default_value() // Return default while waiting for generation
+            *word &= !mask;
         }
     }
 
-    /// Requests and gets the data at a specific floating-point world position.
-    /// Rounds the position to the nearest tile center.
-    /// Spawns a chunk generation request if the chunk is not loaded.
-    pub fn get_rounded(&mut self, world_pos: Vec2) -> P::Item {
-        let tile_x = (world_pos.x / TILE_SIZE_IN_UNITS).round() as isize;
-        let tile_y = (world_pos.y / TILE_SIZE_IN_UNITS).round() as isize;
-        self.get(Point {
-            x: tile_x,
-            y: tile_y,
-        })
+    /// Number of set cells.
+    pub fn count_ones(&self) -> usize {
+        self.words.iter().map(|word| word.count_ones() as usize).sum()
     }
 
-    /// Attempts to get the data at a specific world tile Point.
-    /// Returns `Some(T)` if the chunk is loaded, `None` otherwise.
-    /// Spawns a chunk generation request if the chunk is not loaded.
-    pub fn get_option(&mut self, point: Point) -> Option<P::Item> {
-        // Check write queue first
-        if let Some(&queued_value) = self.write_queue.get(&point) {
-            return Some(queued_value);
-        }
-
-        let chunk_coords = ChunkCoords::from_point(point, self.chunk_dimension_tiles);
-        if let Some(chunk) = self.loaded_chunks.get(&chunk_coords) {
-            let local_x = (point.x % self.chunk_dimension_tiles as isize
-                + self.chunk_dimension_tiles as isize)
-                % self.chunk_dimension_tiles as isize;
-            let local_y = (point.y % self.chunk_dimension_tiles as isize
-                + self.chunk_dimension_tiles as isize)
-                % self.chunk_dimension_tiles as isize;
-            chunk.grid.get_item(local_x as TilesCount, local_y as TilesCount).copied()
-        } else {
-            self.requested_chunks.insert(chunk_coords);
-            None
+    pub fn fill(&mut self, value: bool) {
+        self.words.fill(if value { u64::MAX } else { 0 });
+        let tail = self.len() % BITS_PER_WORD;
+        if let (true, Some(last)) = (tail != 0, self.words.last_mut()) {
+            *last &= (1 << tail) - 1;
         }
     }
 
-    /// Attempts to get the data at a specific floating-point world position.
-    /// Returns `Some(T)` if the chunk is loaded, `None` otherwise.
-    /// Spawns a chunk generation request if the chunk is not loaded.
-    pub fn get_rounded_option(&mut self, world_pos: Vec2) -> Option<P::Item> {
-        let tile_x = (world_pos.x / TILE_SIZE_IN_UNITS).round() as isize;
-        let tile_y = (world_pos.y / TILE_SIZE_IN_UNITS).round() as isize;
-        self.get_option(Point {
-            x: tile_x,
-            y: tile_y,
+    /// `(x, y)` of every set cell, row by row starting at y = 0. Skips empty words at once.
+    pub fn iter_ones(&self) -> impl Iterator<Item = (TilesCount, TilesCount)> + '_ {
+        let width = self.dimension;
+        self.words.iter().enumerate().flat_map(move |(word_idx, &word)| {
+            let mut bits = word;
+            std::iter::from_fn(move || {
+                if bits == 0 {
+                    return None;
+                }
+                let idx = word_idx * BITS_PER_WORD + bits.trailing_zeros() as usize;
+                bits &= bits - 1;
+                Some((idx % width, idx / width))
+            })
         })
     }
+}
 
-    /// Reads the data at a specific world tile Point without spawning any generation requests.
-    /// Returns `Some(T)` if the chunk is loaded, `None` otherwise.
-    pub fn read(&self, point: Point) -> Option<P::Item> {
-        // Check write queue first for potential cached writes
-        if let Some(&queued_value) = self.write_queue.get(&point) {
-            return Some(queued_value);
-        }
+// Items are handed out by reference, bits point at promoted constants
+fn flag_ref(value: bool) -> &'static bool {
+    if value { &true } else { &false }
+}
 
-        let chunk_coords = ChunkCoords::from_point(point, self.chunk_dimension_tiles);
-        self.loaded_chunks.get(&chunk_coords).and_then(|chunk| {
-            let local_x = (point.x % self.chunk_dimension_tiles as isize
-                + self.chunk_dimension_tiles as isize)
-                % self.chunk_dimension_tiles as isize;
-            let local_y = (point.y % self.chunk_dimension_tiles as isize
-                + self.chunk_dimension_tiles as isize)
-                % self.chunk_dimension_tiles as isize;
-            chunk.grid.get_item(local_x as TilesCount, local_y as TilesCount).copied()
-        })
+impl GridData for BitGrid {
+    type Item = bool;
+
+    fn dimension(&self) -> TilesCount {
+        self.dimension
     }
 
-    /// Reads the data at a specific floating-point world position without spawning any generation requests.
-    /// Returns `Some(T)` if the chunk is loaded, `None` otherwise.
-    pub fn read_rounded(&self, world_pos: Vec2) -> Option<P::Item> {
-        let tile_x = (world_pos.x / TILE_SIZE_IN_UNITS).round() as isize;
-        let tile_y = (world_pos.y / TILE_SIZE_IN_UNITS).round() as isize;
-        self.read(Point {
-            x: tile_x,
-            y: tile_y,
-        })
+    fn byte_size(&self) -> usize {
+        self.words.capacity() * std::mem::size_of::<u64>()
     }
 
-    /// Writes data to a specific world tile Point.
-    /// If the chunk is loaded, the write is applied immediately.
-    /// If not, the write is queued for when the chunk is generated.
-    pub fn write(&mut self, point: Point, value: P::Item) {
-        let chunk_coords = ChunkCoords::from_point(point, self.chunk_dimension_tiles);
-        if let Some(chunk) = self.loaded_chunks.get_mut(&chunk_coords) {
-            let local_x = (point.x % self.chunk_dimension_tiles as isize
-                + self.chunk_dimension_tiles as isize)
-                % self.chunk_dimension_tiles as isize;
-            let local_y = (point.y % self.chunk_dimension_tiles as isize
-                + self.chunk_dimension_tiles as isize)
-                % self.chunk_dimension_tiles as isize;
-            chunk.grid.set_item(local_x as TilesCount, local_y as TilesCount, value);
-            // Remove from write queue if it was there and is now written
-            self.write_queue.remove(&point);
+    fn get_item(&self, x: TilesCount, y: TilesCount) -> Option<&Self::Item> {
+        self.calculate_index(x, y).map(|idx| flag_ref(self.bit(idx)))
+    }
+
+    fn set_item(&mut self, x: TilesCount, y: TilesCount, item: Self::Item) -> bool {
+        if let Some(idx) = self.calculate_index(x, y) {
+            self.set_bit(idx, item);
+            true
         } else {
-            // Chunk not loaded, queue the write
-            self.write_queue.insert(point, value);
-            // Also request the chunk if it's not already
-            self.requested_chunks.insert(chunk_coords);
+            false
         }
     }
 
-    /// Initializes chunks in a Manhattan distance radius from the center (0,0).
-    /// This method is non-blocking and spawns generation requests.
-    pub fn init(&mut self, manhattan_distance_tiles: usize) {
-        let center_chunk = ChunkCoords { x: 0, y: 0 };
-        let center_point = Point { x: 0, y: 0 };
+    fn iter_indexed(&self) -> impl Iterator<Item = (TilesCount, TilesCount, &Self::Item)> {
+        let width = self.dimension;
+        (0..self.len()).map(move |idx| (idx % width, idx / width, flag_ref(self.bit(idx))))
+    }
 
-        let chunk_manhattan_distance =
-            (manhattan_distance_tiles as f32 / self.chunk_dimension_tiles as f32).ceil() as isize;
+    fn map_in_place(&mut self, mut f: impl FnMut(TilesCount, TilesCount, Self::Item) -> Self::Item) {
+        let width = self.dimension;
+        for idx in 0..self.len() {
+            let value = f(idx % width, idx / width, self.bit(idx));
+            self.set_bit(idx, value);
+        }
+    }
 
-        for x_offset in -chunk_manhattan_distance..=chunk_manhattan_distance {
-            for y_offset in -chunk_manhattan_distance..=chunk_manhattan_distance {
-                let current_chunk_coords = ChunkCoords {
-                    x: center_chunk.x + x_offset,
-                    y: center_chunk.y + y_offset,
-                };
-                // Only request if not already loaded or pending
-                if !self.loaded_chunks.contains_key(&current_chunk_coords)
-                    && !self.pending_tasks.contains_key(&current_chunk_coords)
-                {
-                    self.requested_chunks.insert(current_chunk_coords);
+    fn downsample(&self, factor: TilesCount, mut reducer: impl FnMut(&[Self::Item]) -> Self::Item) -> Self {
+        let factor = factor.max(1);
+        let mut grid = BitGrid::new_rect(
+            self.dimension.div_ceil(factor),
+            self.height.div_ceil(factor),
+            false,
+        );
+        let mut block = Vec::with_capacity(factor * factor);
+        for by in 0..grid.height {
+            for bx in 0..grid.dimension {
+                block.clear();
+                for y in by * factor..((by + 1) * factor).min(self.height) {
+                    for x in bx * factor..((bx + 1) * factor).min(self.dimension) {
+                        block.push(self.bit(y * self.dimension + x));
+                    }
                 }
+                grid.set_item(bx, by, reducer(&block));
             }
         }
-        // info!(
-        //     "DataMap<{}> init requested chunks up to Manhattan distance {} (chunks: {})",
-        //     std::any::type_name::<P::Item>(),
-        //     manhattan_distance_tiles,
-        //     chunk_manhattan_distance
-        // );
+        grid
     }
 }
 
-// System to manage loading/unloading based on a focus point (e.g., player/camera)
-pub fn data_map_load_unload_system<P: MapDataProducer>(
-    player_query: Query<&Transform, With<MapRevealActor>>,
-    mut data_map: ResMut<DataMap<P>>,
-) {
-    for player_transform in player_query.as_readonly().iter() {
-        let focus_world_pos = player_transform.translation.xy();
-        let current_focus_chunk_coords =
-            ChunkCoords::from_world_pos(focus_world_pos, data_map.chunk_size_units);
+/// Producer for boolean layers backed by `BitGrid`, every tile starts at `default`.
+/// Register one per layer with its own marker type if several are needed.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BoolProducer {
+    pub default: bool,
+}
 
-        let mut required_chunks_set: HashSet<ChunkCoords> = HashSet::new();
+impl MapDataProducer for BoolProducer {
+    type Item = bool;
+    type GridType = BitGrid;
 
-        for dx in
-            -(data_map.render_distance_chunks as isize)..=(data_map.render_distance_chunks as isize)
-        {
-            for dy in -(data_map.render_distance_chunks as isize)
-                ..=(data_map.render_distance_chunks as isize)
-            {
-                required_chunks_set.insert(ChunkCoords {
-                    x: current_focus_chunk_coords.x + dx,
-                    y: current_focus_chunk_coords.y + dy,
-                });
-            }
-        }
+    fn default_value(&self) -> Self::Item {
+        self.default
+    }
 
-        // Unload chunks that are no longer required
-        // data_map
-        //     .loaded_chunks
-        //     .retain(|coords, _| required_chunks_set.contains(coords));
+    fn generate_chunk(&self, _coords: ChunkCoords, dimension_tiles: TilesCount) -> DataChunk<Self::GridType> {
+        DataChunk::new(BitGrid::new(dimension_tiles, self.default))
+    }
 
-        // Request new chunks
-        for coords in required_chunks_set.iter() {
-            if !data_map.loaded_chunks.contains_key(coords) &&
-           !data_map.pending_tasks.contains_key(coords) && // Don't request if already pending
-           !data_map.requested_chunks.contains(coords)
-            // Don't request if already in queue
-            {
-                data_map.requested_chunks.insert(*coords);
-            }
-        }
+    /// Any set tile marks the block.
+    fn reduce_lod(&self, block: &[Self::Item]) -> Self::Item {
+        block.iter().any(|flag| *flag)
     }
 }
 
-pub fn data_map_load_unload_system_for_player<P: MapDataProducer>(
-    player_query: Query<&Transform, With<Player>>,
-    mut data_map: ResMut<DataMap<P>>,
-) {
-    for player_transform in player_query.as_readonly().iter() {
-        let focus_world_pos = player_transform.translation.xy();
-        let current_focus_chunk_coords =
-            ChunkCoords::from_world_pos(focus_world_pos, data_map.chunk_size_units);
-
-        let mut required_chunks_set: HashSet<ChunkCoords> = HashSet::new();
-
-        for dx in
-            -(data_map.render_distance_chunks as isize)..=(data_map.render_distance_chunks as isize)
-        {
-            for dy in -(data_map.render_distance_chunks as isize)
-                ..=(data_map.render_distance_chunks as isize)
-            {
-                required_chunks_set.insert(ChunkCoords {
-                    x: current_focus_chunk_coords.x + dx,
-                    y: current_focus_chunk_coords.y + dy,
-                });
-            }
-        }
+/// Versions of the chunks under an area when it was read, `None` for chunks that weren't
+/// loaded. See `DataMap::validate_versions`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ChunkVersions(pub Vec<(ChunkCoords, Option<u64>)>);
 
-        // Unload chunks that are no longer required
-        data_map
-            .loaded_chunks
-            .retain(|coords, _| required_chunks_set.contains(coords));
+/// Copy of an area taken in one go, for work spread over several frames that must not see
+/// half of a write. See `DataMap::snapshot_area`.
+#[derive(Debug, Clone)]
+pub struct AreaSnapshot<T>
+where
+    T: Copy + Debug + Send + Sync + 'static + Default,
+{
+    pub bottom_left: Point,
+    pub tiles: FlatGrid<T>, // Tiles of chunks that weren't loaded hold the producer default
+    pub versions: ChunkVersions,
+    chunk_dimension_tiles: TilesCount,
+}
 
-        // Request new chunks
-        for coords in required_chunks_set.iter() {
-            if !data_map.loaded_chunks.contains_key(coords) &&
-           !data_map.pending_tasks.contains_key(coords) && // Don't request if already pending
-           !data_map.requested_chunks.contains(coords)
-            // Don't request if already in queue
-            {
-                data_map.requested_chunks.insert(*coords);
-            }
+impl<T> AreaSnapshot<T>
+where
+    T: Copy + Debug + Send + Sync + 'static + Default,
+{
+    /// Tile at a world point, `None` outside the area or in a chunk that wasn't loaded.
+    pub fn read(&self, point: Point) -> Option<T> {
+        let (x, y) = (point.x - self.bottom_left.x, point.y - self.bottom_left.y);
+        if x < 0 || y < 0 {
+            return None;
         }
+        let item = self.tiles.get_item(x as TilesCount, y as TilesCount)?;
+        let coords = ChunkCoords::from_point(point, self.chunk_dimension_tiles);
+        let loaded = self.versions.0.iter().any(|(c, version)| *c == coords && version.is_some());
+        loaded.then_some(*item)
     }
 }
 
-// System to spawn background tasks for requested chunks
-pub fn data_map_spawn_tasks_system<P: MapDataProducer>(
-    mut commands: Commands,
-    mut data_map: ResMut<DataMap<P>>,
-) {
-    let thread_pool = AsyncComputeTaskPool::get();
+/// How `DataMap::stamp` treats the cells of a stamp.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StampMode {
+    #[default]
+    Overwrite, // Every cell is written
+    SkipDefault, // Cells holding the item's Default value are transparent, the map keeps its tile
+}
 
-    let mut new_pending_tasks = Vec::new(); // Collect tasks to add to pending_tasks map
+/// A prefabricated structure (a room, a piece of road) to be stamped into a `DataMap`.
+/// `anchor` is the cell of `grid` that lands on the stamp's origin.
+#[derive(Debug, Clone)]
+pub struct Stamp<T>
+where
+    T: Copy + Debug + Send + Sync + 'static + Default,
+{
+    pub grid: FlatGrid<T>,
+    pub anchor: (TilesCount, TilesCount),
+}
 
-    let producer = Arc::new(data_map.producer.clone());
+impl<T> Stamp<T>
+where
+    T: Copy + Debug + Send + Sync + 'static + Default,
+{
+    pub fn new(grid: FlatGrid<T>, anchor: (TilesCount, TilesCount)) -> Self {
+        Self { grid, anchor }
+    }
 
-    for coords in data_map.requested_chunks.iter() {
-        if !data_map.pending_tasks.contains_key(coords) {
-            // Double check for safety
-            let chunk_dimension = data_map.chunk_dimension_tiles;
-            let current_coords = *coords;
-            let pr = producer.clone();
+    /// World tile of the grid's bottom-left cell when stamped at `origin`.
+    pub fn bottom_left(&self, origin: Point) -> Point {
+        Point {
+            x: origin.x - self.anchor.0 as isize,
+            y: origin.y - self.anchor.1 as isize,
+        }
+    }
+}
 
-            let task = thread_pool
-                .spawn(async move { pr.generate_chunk(current_coords, chunk_dimension) });
+/// Tiles per axis covered by one item of a chunk at `lod`; 0 and 1 are both full resolution.
+pub fn lod_factor(lod: u8) -> TilesCount {
+    lod.max(1) as TilesCount
+}
 
-            let task_entity = commands
-                .spawn((
-                    current_coords, // Attach coords for easy lookup by completion system
-                    ChunkGenTask(task),
-                ))
-                .id();
+/// Shape of the area of chunks kept loaded around a focus.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LoadShape {
+    #[default]
+    Square, // Chebyshev distance, the full (2r+1)² square
+    Circle,  // Euclidean distance between chunk centers, rounded to the nearest chunk
+    Diamond, // Manhattan distance
+}
 
-            new_pending_tasks.push((current_coords, task_entity));
-            // info!(
-            //     "Spawned DataMap<{}> gen task for chunk: {:?}",
-            //     std::any::type_name::<P::Item>(),
-            //     current_coords
-            // );
+impl LoadShape {
+    /// Distance in chunks for an offset of `dx`,`dy` chunks from the focus.
+    /// A chunk is within radius `r` exactly when its distance is `<= r`.
+    pub fn distance(&self, dx: isize, dy: isize) -> usize {
+        let (dx, dy) = (dx.unsigned_abs(), dy.unsigned_abs());
+        match self {
+            LoadShape::Square => dx.max(dy),
+            LoadShape::Diamond => dx + dy,
+            LoadShape::Circle => {
+                // Smallest r with dx² + dy² < (r + 0.5)², i.e. dx² + dy² <= r² + r
+                let squared = dx * dx + dy * dy;
+                let mut r = ((squared as f64).sqrt() - 0.5).max(0.0).floor() as usize;
+                while r * r + r < squared {
+                    r += 1;
+                }
+                r
+            }
         }
     }
 
-    // Add new pending tasks to the map and clear requested chunks
-    for (coords, entity) in new_pending_tasks {
-        data_map.pending_tasks.insert(coords, entity);
+    pub fn contains(&self, dx: isize, dy: isize, radius: usize) -> bool {
+        self.distance(dx, dy) <= radius
     }
-    data_map.requested_chunks.clear();
 }
 
-// System to process completed background tasks
-pub fn data_map_process_completed_tasks_system<P: MapDataProducer>(
-    mut commands: Commands,
-    mut query: Query<(Entity, &ChunkCoords, &mut ChunkGenTask<P::GridType>)>,
-    mut data_map: ResMut<DataMap<P>>,
-) {
-    let mut completed_chunks = Vec::new();
+/// What happens to loaded chunks that no focus requires anymore.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UnloadPolicy {
+    #[default]
+    Evict, // LRU eviction beyond unload_distance_chunks, budgeted per frame (see `DataMap::evict_chunks`)
+    Immediate, // Unloaded as soon as they leave the required set
+}
 
-    for (task_entity, coords, mut gen_task) in query.iter_mut() {
-        if let Some(generated_chunk) = future::block_on(future::poll_once(&mut gen_task.0)) {
-            completed_chunks.push((*coords, generated_chunk));
-            commands.entity(task_entity).despawn(); // Remove the temporary task entity
-            data_map.pending_tasks.remove(coords); // Remove from pending map
+/// What decides which chunks of a map are loaded, see `ChunkedMapConfig::focus`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FocusPolicy {
+    /// Chunks around every `MapRevealActor`, each actor's radius capped by `radius_chunks`.
+    FollowMapRevealActors { radius_chunks: usize },
+    /// The chunks overlapping a window of `tiles` x `tiles` centered on the `FollowCamera`, for
+    /// maps only needed around what's on screen, see `data_map_camera_window_system`.
+    FollowCameraWindow { tiles: TilesCount },
+    /// Only what `DataMap::init`, explicit requests and writes load; nothing is unloaded.
+    Manual,
+}
+
+impl FocusPolicy {
+    /// Radius the map's `render_distance_chunks` starts at. For the camera window, the farthest
+    /// chunk of the window from the camera's chunk.
+    pub fn render_distance_chunks(&self, chunk_dimension_tiles: TilesCount) -> usize {
+        match *self {
+            FocusPolicy::FollowMapRevealActors { radius_chunks } => radius_chunks,
+            FocusPolicy::FollowCameraWindow { tiles } => (tiles / 2).div_ceil(chunk_dimension_tiles.max(1)) + 1,
+            FocusPolicy::Manual => DEFAULT_RENDER_DISTANCE_CHUNKS,
         }
     }
+}
 
-    // Apply completed chunks and pending writes
-    for (coords, mut chunk) in completed_chunks {
-        // info!(
-        //     "DataMap<{}> chunk {:?} generated.",
-        //     std::any::type_name::<P::Item>(),
-        //     coords
-        // );
-        // Apply any writes from the queue to this newly generated chunk
-        let chunk_bottom_left_tile =
-            coords.to_bottom_left_tile_point(data_map.chunk_dimension_tiles);
+/// Chunks overlapping the window of `tiles` x `tiles` whose center tile is `center`; for even
+/// sizes the center is the first tile of the upper half, like `simulation::window_center`.
+pub fn window_chunks(
+    center: Point,
+    tiles: TilesCount,
+    chunk_dimension_tiles: TilesCount,
+) -> impl Iterator<Item = ChunkCoords> {
+    let half = (tiles / 2) as isize;
+    let min = ChunkCoords::from_point(Point { x: center.x - half, y: center.y - half }, chunk_dimension_tiles);
+    let last = center.x - half + tiles.max(1) as isize - 1;
+    let last_y = center.y - half + tiles.max(1) as isize - 1;
+    let max = ChunkCoords::from_point(Point { x: last, y: last_y }, chunk_dimension_tiles);
+    (min.y..=max.y).flat_map(move |y| (min.x..=max.x).map(move |x| ChunkCoords { x, y }))
+}
+
+/// Chunks within `radius` of `focus`, for the given shape.
+pub fn required_chunks(
+    focus: ChunkCoords,
+    shape: LoadShape,
+    radius: usize,
+) -> impl Iterator<Item = ChunkCoords> {
+    let r = radius as isize;
+    (-r..=r)
+        .flat_map(move |dx| (-r..=r).map(move |dy| (dx, dy)))
+        .filter(move |&(dx, dy)| shape.contains(dx, dy, radius))
+        .map(move |(dx, dy)| ChunkCoords {
+            x: focus.x + dx,
+            y: focus.y + dy,
+        })
+}
 
-        // Use a temporary Vec to collect points to remove from write_queue
-        let mut points_to_remove = Vec::new();
+const PREFETCH_LOOKAHEAD_SECS: f32 = 2.0; // Prefetch covers where a focus will be this much later
+const FOCUS_MOTION_SMOOTHING: f32 = 0.2; // Weight of the newest displacement in the smoothed velocity
+const FOCUS_JUMP_UNITS: f32 = 256.0; // Displacements beyond this in one frame are teleports, not movement
 
-        let chunk_dimension_tiles = data_map.chunk_dimension_tiles;
+/// Smoothed velocity of a focus, used to prefetch chunks in its direction of travel.
+/// Required by `MapRevealActor`, updated by `track_focus_motion_system`.
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct FocusMotion {
+    pub previous: Option<Vec2>, // Position as of the previous frame
+    pub velocity: Vec2,         // Units per second
+}
 
-        // Iterate over the write_queue and identify items to apply
-        for (&point, &value) in data_map.write_queue.iter() {
-            if point.x >= chunk_bottom_left_tile.x
-                && point.x < chunk_bottom_left_tile.x + chunk_dimension_tiles as isize
-                && point.y >= chunk_bottom_left_tile.y
-                && point.y < chunk_bottom_left_tile.y + chunk_dimension_tiles as isize
-            {
-                // This write belongs to the newly generated chunk
-                let local_x = (point.x % chunk_dimension_tiles as isize
-                    + chunk_dimension_tiles as isize)
-                    % chunk_dimension_tiles as isize;
-                let local_y = (point.y % chunk_dimension_tiles as isize
-                    + chunk_dimension_tiles as isize)
-                    % chunk_dimension_tiles as isize;
-                chunk.grid.set_item(local_x as TilesCount, local_y as TilesCount, value);
-                points_to_remove.push(point); // Mark for removal
+/// Centers of the extra areas requested ahead of a focus moving at `velocity`, nearest first.
+/// One per chunk of lookahead, up to `max_chunks`, and none below `min_speed`; the focus itself is
+/// never included, so nothing is added behind it.
+pub fn prefetch_centers(
+    focus: ChunkCoords,
+    velocity: Vec2,
+    chunk_size_units: f32,
+    max_chunks: usize,
+    min_speed: f32,
+) -> Vec<ChunkCoords> {
+    let speed = velocity.length();
+    if max_chunks == 0 || speed < min_speed || speed == 0.0 {
+        return Vec::new();
+    }
+    let ahead = ((speed * PREFETCH_LOOKAHEAD_SECS / chunk_size_units).ceil() as usize).min(max_chunks);
+    let direction = velocity / speed;
+    let mut centers: Vec<ChunkCoords> = Vec::with_capacity(ahead);
+    for step in 1..=ahead {
+        let offset = (direction * step as f32).round();
+        let center = ChunkCoords {
+            x: focus.x + offset.x as isize,
+            y: focus.y + offset.y as isize,
+        };
+        if center != focus && centers.last() != Some(&center) {
+            centers.push(center);
+        }
+    }
+    centers
+}
+
+/// A chunk of specific map data. The manager knows its coordinates.
+#[derive(Debug, Clone)]
+pub struct DataChunk<T: GridData> {
+    pub grid: T,
+    pub dirty: bool, // Modified since generation/load, needs to be saved to the store
+    pub last_touched: u64, // Last DataMap frame this chunk was within render distance of an actor
+    pub from_store: bool, // Loaded from a ChunkStore rather than generated, post-processing is never re-run on it
+    pub version: u64, // Set by the DataMap whenever the chunk is inserted or written, see `DataMap::chunk_version`
+}
+
+impl<T: GridData> DataChunk<T> {
+    pub fn new(grid: T) -> Self {
+        Self {
+            grid,
+            dirty: false,
+            last_touched: 0,
+            from_store: false,
+            version: 0,
+        }
+    }
+}
+
+/// A rectangular write waiting for its chunk to be generated.
+/// Always clipped to a single chunk, coordinates are absolute world tiles.
+#[derive(Debug, Clone)]
+pub struct PendingArea<T> {
+    pub bottom_left: Point,
+    pub width: TilesCount,
+    pub height: TilesCount,
+    pub data: PendingAreaData<T>,
+}
+
+#[derive(Debug, Clone)]
+pub enum PendingAreaData<T> {
+    Fill(T),       // Same value for the whole rectangle
+    Tiles(Vec<T>), // Row-major, `width * height` items
+}
+
+impl<T: Copy> PendingArea<T> {
+    /// Returns the pending value at `point`, if the point is inside the rectangle.
+    pub fn get(&self, point: Point) -> Option<T> {
+        let local_x = point.x - self.bottom_left.x;
+        let local_y = point.y - self.bottom_left.y;
+        if local_x < 0
+            || local_y < 0
+            || local_x >= self.width as isize
+            || local_y >= self.height as isize
+        {
+            return None;
+        }
+        match &self.data {
+            PendingAreaData::Fill(value) => Some(*value),
+            PendingAreaData::Tiles(tiles) => {
+                Some(tiles[local_y as usize * self.width + local_x as usize])
             }
         }
+    }
 
-        // Now, remove the marked points from the write_queue outside the iteration
-        for point in points_to_remove {
-            data_map.write_queue.remove(&point);
+    /// Copies the rectangle into a grid whose bottom-left tile is at `grid_origin`.
+    pub fn apply_to<G: GridData<Item = T>>(&self, grid_origin: Point, target: &mut G) {
+        let x = (self.bottom_left.x - grid_origin.x) as TilesCount;
+        for row in 0..self.height {
+            let y = (self.bottom_left.y + row as isize - grid_origin.y) as TilesCount;
+            match &self.data {
+                PendingAreaData::Fill(value) => target.fill_run(x, y, self.width, *value),
+                PendingAreaData::Tiles(tiles) => {
+                    target.write_run(x, y, &tiles[row * self.width..(row + 1) * self.width])
+                }
+            }
         }
+    }
+}
+
+/// Tiles of one chunk overridden by journaled writes, as `(local index, value)` pairs sorted by
+/// index, the index being `y * chunk_dimension_tiles + x` inside the chunk. Writing a tile again
+/// replaces its pair, so a tile rewritten every frame still takes a single entry.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChunkDelta<T> {
+    overrides: Vec<(TilesCount, T)>,
+}
 
-        data_map.loaded_chunks.insert(coords, chunk);
+impl<T> Default for ChunkDelta<T> {
+    fn default() -> Self {
+        Self { overrides: Vec::new() }
     }
 }
 
-pub fn insert_chunked_plugin<P>(
-    app: &mut bevy::prelude::App,
-    producer: P,
-    manhattan_distance_tiles_init: TilesCount,
-) -> &mut bevy::prelude::App
+impl<T: Copy> ChunkDelta<T> {
+    /// Overrides the tile at `index`, replacing an older value.
+    pub fn set(&mut self, index: TilesCount, value: T) {
+        match self.overrides.binary_search_by_key(&index, |(i, _)| *i) {
+            Ok(found) => self.overrides[found].1 = value,
+            Err(slot) => self.overrides.insert(slot, (index, value)),
+        }
+    }
+
+    pub fn get(&self, index: TilesCount) -> Option<T> {
+        self.overrides
+            .binary_search_by_key(&index, |(i, _)| *i)
+            .ok()
+            .map(|found| self.overrides[found].1)
+    }
+
+    /// Every override, by increasing index.
+    pub fn iter(&self) -> impl Iterator<Item = (TilesCount, T)> + '_ {
+        self.overrides.iter().copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.overrides.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.overrides.is_empty()
+    }
+
+    /// Copies the overrides into the grid of their chunk.
+    pub fn apply_to<G: GridData<Item = T>>(&self, grid: &mut G) {
+        let dimension = grid.dimension();
+        for (index, value) in self.iter() {
+            grid.set_item(index % dimension, index / dimension, value);
+        }
+    }
+
+    /// The overrides that differ from `generated`, a freshly generated chunk, so a delta taken
+    /// after regenerating only keeps what the producer wouldn't make again.
+    pub fn compact_against<G: GridData<Item = T>>(&self, generated: &DataChunk<G>) -> Self
+    where
+        T: PartialEq,
+    {
+        let dimension = generated.grid.dimension();
+        let overrides = self
+            .iter()
+            .filter(|(index, value)| generated.grid.get_item(index % dimension, index / dimension) != Some(value))
+            .collect();
+        Self { overrides }
+    }
+}
+
+// Source of a bulk write
+enum RectSource<'a, T> {
+    Fill(T),
+    Tiles(&'a [T]), // Row-major, covering the whole rectangle
+}
+
+/// Tile items that can be read as a single number, e.g. for interpolated sampling.
+pub trait ScalarTileValue {
+    fn to_scalar(&self) -> f32;
+}
+
+// --- Persistence ---
+
+/// Fixed-size binary encoding for tile items, used by `FsChunkStore`.
+pub trait TileBytes: Sized {
+    const SIZE: usize;
+    fn write_bytes(&self, out: &mut Vec<u8>);
+    fn read_bytes(bytes: &[u8]) -> Self;
+}
+
+/// Storage backend for chunks that were modified at runtime.
+pub trait ChunkStore<G: GridData>: Send + Sync + 'static {
+    fn save_chunk(&self, coords: ChunkCoords, chunk: &DataChunk<G>);
+    /// None if nothing was saved for `coords`, or if it isn't `dimension_tiles` wide and high.
+    fn load_chunk(&self, coords: ChunkCoords, dimension_tiles: TilesCount) -> Option<DataChunk<G>>;
+}
+
+/// Stores each chunk as a separate file `<x>_<y>.chunk` under `directory`.
+/// File layout: width (u32 LE), height (u32 LE), then `TileBytes::SIZE` bytes per tile, row by row.
+#[derive(Debug, Clone)]
+pub struct FsChunkStore {
+    pub directory: PathBuf,
+}
+
+impl FsChunkStore {
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self {
+            directory: directory.into(),
+        }
+    }
+
+    fn chunk_path(&self, coords: ChunkCoords) -> PathBuf {
+        self.directory.join(format!("{}_{}.chunk", coords.x, coords.y))
+    }
+}
+
+impl<T> ChunkStore<FlatGrid<T>> for FsChunkStore
 where
-    P: MapDataProducer + Send + Sync + Clone + 'static,
-    <P as MapDataProducer>::GridType: Send + Sync,
-    <P as MapDataProducer>::Item: Send + Copy + Default + Sync,
+    T: Copy + Debug + Send + Sync + 'static + Default + TileBytes,
 {
-    app.add_systems(Startup, move |mut map: ResMut<DataMap<P>>| {
-        map.init(manhattan_distance_tiles_init)
-    });
-    app.insert_resource(DataMap::<P>::new(
-        producer,
-        DEFAULT_CHUNK_DIMENSION_TILES,
-        DEFAULT_RENDER_DISTANCE_CHUNKS,
-    ))
-    .add_systems(
-        Update,
-        (
-            data_map_load_unload_system::<P>,
-            data_map_spawn_tasks_system::<P>,
-            data_map_process_completed_tasks_system::<P>,
-        ),
-    )
+    fn save_chunk(&self, coords: ChunkCoords, chunk: &DataChunk<FlatGrid<T>>) {
+        let grid = &chunk.grid;
+        let mut bytes = Vec::with_capacity(8 + grid.as_slice().len() * T::SIZE);
+        bytes.extend_from_slice(&(grid.width() as u32).to_le_bytes());
+        bytes.extend_from_slice(&(grid.height() as u32).to_le_bytes());
+        for item in grid.as_slice() {
+            item.write_bytes(&mut bytes);
+        }
+
+        let result = fs::create_dir_all(&self.directory)
+            .and_then(|_| fs::write(self.chunk_path(coords), bytes));
+        if let Err(err) = result {
+            warn!("Failed to save chunk {:?}: {}", coords, err);
+        }
+    }
+
+    fn load_chunk(&self, coords: ChunkCoords, dimension_tiles: TilesCount) -> Option<DataChunk<FlatGrid<T>>> {
+        let bytes = fs::read(self.chunk_path(coords)).ok()?;
+        if bytes.len() < 8 {
+            warn!("Chunk file for {:?} is truncated", coords);
+            return None;
+        }
+        let width = u32::from_le_bytes(bytes[0..4].try_into().unwrap()) as TilesCount;
+        let height = u32::from_le_bytes(bytes[4..8].try_into().unwrap()) as TilesCount;
+        if (width, height) != (dimension_tiles, dimension_tiles) {
+            warn!(
+                "Chunk file for {:?} is {}x{} tiles, expected {}x{}: saved with another chunk size?",
+                coords, width, height, dimension_tiles, dimension_tiles
+            );
+            return None;
+        }
+        let payload = &bytes[8..];
+        if payload.len() != width * height * T::SIZE {
+            warn!("Chunk file for {:?} has unexpected size", coords);
+            return None;
+        }
+
+        let mut grid = FlatGrid::new_rect(width, height, T::default());
+        for (item, raw) in grid.as_mut_slice().iter_mut().zip(payload.chunks_exact(T::SIZE)) {
+            *item = T::read_bytes(raw);
+        }
+        Some(DataChunk::new(grid))
+    }
+}
+
+// --- Dormant chunks ---
+
+/// Tile items `RleCompressor` can compress, through their `TileBytes` encoding.
+pub trait CompressibleItem: TileBytes + Copy {}
+
+impl<T: TileBytes + Copy> CompressibleItem for T {}
+
+/// Turns chunk grids into bytes and back for the dormant chunks of a `DataMap`, see `DataMap::set_compressor`.
+pub trait ChunkCompressor<G: GridData>: Send + Sync + 'static {
+    fn compress(&self, grid: &G) -> Vec<u8>;
+    /// None if `bytes` weren't made by `compress`.
+    fn decompress(&self, bytes: &[u8]) -> Option<G>;
+    /// A single item, without decompressing the whole grid if the format allows it.
+    fn read_item(&self, bytes: &[u8], x: TilesCount, y: TilesCount) -> Option<G::Item> {
+        self.decompress(bytes)?.get_item(x, y).copied()
+    }
+}
+
+/// Run-length encoding of the `TileBytes` of each item. Layout: width (u32 LE), height (u32 LE),
+/// then runs of a count (u16 LE) followed by the item's bytes, row by row. Maps with large areas
+/// of one value, like passability or tile types, shrink to a fraction of their grid.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RleCompressor;
+
+const RLE_HEADER_BYTES: usize = 8;
+
+// Width, height and the runs of RleCompressor bytes
+fn rle_parts<T: CompressibleItem>(bytes: &[u8]) -> Option<(TilesCount, TilesCount, &[u8])> {
+    if bytes.len() < RLE_HEADER_BYTES {
+        return None;
+    }
+    let width = u32::from_le_bytes(bytes[0..4].try_into().ok()?) as TilesCount;
+    let height = u32::from_le_bytes(bytes[4..8].try_into().ok()?) as TilesCount;
+    let runs = &bytes[RLE_HEADER_BYTES..];
+    runs.len().is_multiple_of(2 + T::SIZE).then_some((width, height, runs))
+}
+
+// Count and item of every run
+fn rle_runs<T: CompressibleItem>(runs: &[u8]) -> impl Iterator<Item = (usize, &[u8])> {
+    runs.chunks_exact(2 + T::SIZE)
+        .map(|run| (u16::from_le_bytes([run[0], run[1]]) as usize, &run[2..]))
+}
+
+impl<T> ChunkCompressor<FlatGrid<T>> for RleCompressor
+where
+    T: Copy + Debug + Send + Sync + 'static + Default + CompressibleItem,
+{
+    fn compress(&self, grid: &FlatGrid<T>) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(RLE_HEADER_BYTES);
+        bytes.extend_from_slice(&(grid.width() as u32).to_le_bytes());
+        bytes.extend_from_slice(&(grid.height() as u32).to_le_bytes());
+        let mut run_item = Vec::with_capacity(T::SIZE);
+        let mut item = Vec::with_capacity(T::SIZE);
+        let mut count: u16 = 0;
+        for value in grid.as_slice() {
+            item.clear();
+            value.write_bytes(&mut item);
+            if count > 0 && count < u16::MAX && item == run_item {
+                count += 1;
+                continue;
+            }
+            if count > 0 {
+                bytes.extend_from_slice(&count.to_le_bytes());
+                bytes.extend_from_slice(&run_item);
+            }
+            std::mem::swap(&mut run_item, &mut item);
+            count = 1;
+        }
+        if count > 0 {
+            bytes.extend_from_slice(&count.to_le_bytes());
+            bytes.extend_from_slice(&run_item);
+        }
+        bytes
+    }
+
+    fn decompress(&self, bytes: &[u8]) -> Option<FlatGrid<T>> {
+        let (width, height, runs) = rle_parts::<T>(bytes)?;
+        let mut grid = FlatGrid::new_rect(width, height, T::default());
+        let items = grid.as_mut_slice();
+        let mut index = 0;
+        for (count, raw) in rle_runs::<T>(runs) {
+            items.get_mut(index..index + count)?.fill(T::read_bytes(raw));
+            index += count;
+        }
+        (index == items.len()).then_some(grid)
+    }
+
+    fn read_item(&self, bytes: &[u8], x: TilesCount, y: TilesCount) -> Option<T> {
+        let (width, height, runs) = rle_parts::<T>(bytes)?;
+        if x >= width || y >= height {
+            return None;
+        }
+        let mut remaining = y * width + x;
+        for (count, raw) in rle_runs::<T>(runs) {
+            if remaining < count {
+                return Some(T::read_bytes(raw));
+            }
+            remaining -= count;
+        }
+        None
+    }
+}
+
+/// A chunk evicted while still close to a focus, kept compressed instead of being dropped,
+/// see `DataMap::make_dormant`.
+#[derive(Debug, Clone)]
+pub struct CompressedChunk {
+    pub bytes: Vec<u8>, // Made by the map's `ChunkCompressor`
+    pub dirty: bool,    // Saved to the store once dropped, like a loaded chunk on unload
+    pub from_store: bool,
+}
+
+impl CompressedChunk {
+    /// Memory held by the compressed grid, see `ChunkMemoryBudget::max_dormant_bytes`.
+    pub fn byte_size(&self) -> usize {
+        self.bytes.len()
+    }
+}
+
+// --- Chunk lifecycle events ---
+
+/// Sent when a chunk of `DataMap<P>` has been generated (or loaded from the store) and inserted.
+#[derive(Event)]
+pub struct ChunkLoaded<P: MapDataProducer> {
+    pub coords: ChunkCoords,
+    _producer: PhantomData<P>,
+}
+
+/// Sent when a chunk of `DataMap<P>` has been evicted.
+#[derive(Event)]
+pub struct ChunkUnloaded<P: MapDataProducer> {
+    pub coords: ChunkCoords,
+    _producer: PhantomData<P>,
+}
+
+/// Sent (once per frame at most) when a loaded chunk of `DataMap<P>` was modified by a write.
+#[derive(Event)]
+pub struct ChunkWritten<P: MapDataProducer> {
+    pub coords: ChunkCoords,
+    _producer: PhantomData<P>,
+}
+
+/// Asks the load/unload systems of `DataMap<P>` (or `DataMapDoubleBuffered<P>`) to switch to
+/// another render distance, see `DataMap::set_render_distance`. The last one sent in a frame wins.
+#[derive(Event)]
+pub struct RenderDistanceChange<P: MapDataProducer> {
+    pub chunks: usize,
+    _producer: PhantomData<P>,
+}
+
+impl<P: MapDataProducer> RenderDistanceChange<P> {
+    pub fn new(chunks: usize) -> Self {
+        Self { chunks, _producer: PhantomData }
+    }
+}
+
+impl<P: MapDataProducer> ChunkLoaded<P> {
+    pub fn new(coords: ChunkCoords) -> Self {
+        Self { coords, _producer: PhantomData }
+    }
+}
+
+impl<P: MapDataProducer> ChunkUnloaded<P> {
+    pub fn new(coords: ChunkCoords) -> Self {
+        Self { coords, _producer: PhantomData }
+    }
+}
+
+impl<P: MapDataProducer> ChunkWritten<P> {
+    pub fn new(coords: ChunkCoords) -> Self {
+        Self { coords, _producer: PhantomData }
+    }
+}
+
+/// Results of one generation task, one per chunk so a panic only fails its own chunk.
+pub type ChunkGenBatch<T> = Vec<(ChunkCoords, Result<DataChunk<T>, ChunkGenError>)>;
+
+// Marker component for tasks in flight, each generating a batch of chunks
+#[derive(Component)]
+pub struct ChunkGenTask<T: GridData>(pub(crate) Task<ChunkGenBatch<T>>);
+
+/// A chunk generation task panicked, holds the panic message.
+#[derive(Debug, Clone)]
+pub struct ChunkGenError(pub String);
+
+/// A generated chunk waiting to be applied, with the entity of the task it came from.
+type UnappliedResult<T> = (Entity, ChunkCoords, Result<DataChunk<T>, ChunkGenError>);
+
+/// Generation tasks in flight, with when each was spawned if that is tracked.
+type ChunkGenTaskQuery<'w, 's, T> =
+    Query<'w, 's, (Entity, &'static mut ChunkGenTask<T>, Option<&'static ChunkGenStarted>)>;
+
+/// Runs `generate`, turning a panic into an error instead of a task that never completes.
+pub(crate) fn catch_generation_panic<T>(generate: impl FnOnce() -> T) -> Result<T, ChunkGenError> {
+    std::panic::catch_unwind(std::panic::AssertUnwindSafe(generate)).map_err(|payload| {
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "non-string panic payload".to_string());
+        ChunkGenError(message)
+    })
+}
+
+pub trait MapDataProducer: Send + Sync + 'static + Clone {
+    type Item: Copy + Default + Debug + Send + Sync + 'static; // Same as the grid items, see `GridData`
+    type GridType: GridData<Item = Self::Item> + Send + Sync + Clone;
+
+    /// Returns the default value for an ungenerated tile.
+    fn default_value(&self) -> Self::Item;
+
+    /// Returns a copy of the producer generating the world for `seed`.
+    /// Producers that don't depend on a seed keep the default, which ignores it.
+    fn with_seed(&self, _seed: u64) -> Self {
+        self.clone()
+    }
+
+    /// Generates a chunk of data for the given coordinates.
+    /// Returns the DataChunk asset.
+    fn generate_chunk(
+        &self,
+        coords: ChunkCoords,
+        dimension_tiles: TilesCount,
+    ) -> DataChunk<Self::GridType>;
+
+    /// Item standing for a block of tiles in LOD chunks, the bottom-left tile by default.
+    fn reduce_lod(&self, block: &[Self::Item]) -> Self::Item {
+        block.first().copied().unwrap_or_else(|| self.default_value())
+    }
+
+    /// Generates the same area as `generate_chunk` with one item per `lod` x `lod` tiles (see `lod_factor`).
+    /// The default generates the full chunk and downsamples it with `reduce_lod`; producers
+    /// that can sample their source directly should override it to skip the full resolution work.
+    fn generate_chunk_lod(
+        &self,
+        coords: ChunkCoords,
+        dimension_tiles: TilesCount,
+        lod: u8,
+    ) -> DataChunk<Self::GridType> {
+        let chunk = self.generate_chunk(coords, dimension_tiles);
+        let factor = lod_factor(lod);
+        if factor == 1 {
+            return chunk;
+        }
+        DataChunk::new(chunk.grid.downsample(factor, |block| self.reduce_lod(block)))
+    }
+}
+
+// --- Seam validation ---
+
+/// A border tile whose value depends on which chunk generated it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SeamMismatch<T> {
+    pub point: Point,
+    pub chunk: ChunkCoords, // Chunk of the pair the tile belongs to
+    pub in_chunk: T,        // Generated as part of `chunk`
+    pub standalone: T,      // Generated as a single tile chunk, i.e. with borders everywhere else
+}
+
+/// Tiles of `coords` along the edge it shares with the adjacent `other`.
+fn edge_tiles(coords: ChunkCoords, other: ChunkCoords, dimension: TilesCount) -> Vec<Point> {
+    let origin = coords.to_bottom_left_tile_point(dimension);
+    let last = dimension as isize - 1;
+    let (dx, dy) = (other.x - coords.x, other.y - coords.y);
+    (0..dimension as isize)
+        .map(|i| match (dx, dy) {
+            (1, 0) => (last, i),
+            (-1, 0) => (0, i),
+            (0, 1) => (i, last),
+            _ => (i, 0),
+        })
+        .map(|(x, y)| Point {
+            x: origin.x + x,
+            y: origin.y + y,
+        })
+        .collect()
+}
+
+/// Generates both chunks of every adjacent pair and checks that the tiles on each side of their
+/// shared edge come out the same when generated on their own. A producer that is a pure function
+/// of world coordinates never reports anything; one depending on the chunk bounds shows seams.
+/// Pairs that are not orthogonal neighbors are skipped. Meant for tests and debugging, not per frame.
+pub fn validate_producer_seams<P: MapDataProducer>(
+    producer: &P,
+    dimension: TilesCount,
+    coords_pairs: &[(ChunkCoords, ChunkCoords)],
+) -> Vec<SeamMismatch<P::Item>>
+where
+    P::Item: PartialEq + Debug,
+{
+    let mut mismatches = Vec::new();
+    for &(a, b) in coords_pairs {
+        if LoadShape::Diamond.distance(a.x - b.x, a.y - b.y) != 1 {
+            continue;
+        }
+        for (coords, other) in [(a, b), (b, a)] {
+            let chunk = producer.generate_chunk(coords, dimension);
+            for point in edge_tiles(coords, other, dimension) {
+                let local = point.local_in_chunk(dimension);
+                let Some(&in_chunk) = chunk.grid.get_item(local.0, local.1) else {
+                    continue;
+                };
+                let single = producer.generate_chunk(ChunkCoords { x: point.x, y: point.y }, 1);
+                let Some(&standalone) = single.grid.get_item(0, 0) else {
+                    continue;
+                };
+                if in_chunk != standalone {
+                    mismatches.push(SeamMismatch {
+                        point,
+                        chunk: coords,
+                        in_chunk,
+                        standalone,
+                    });
+                }
+            }
+        }
+    }
+    mismatches
+}
+
+// --- Post-processing ---
+
+/// Offsets of the 8 chunks around a chunk, in the order used by `NeighborView`.
+pub const NEIGHBOR_OFFSETS: [(isize, isize); 8] = [
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (-1, 0),
+    (1, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+];
+
+/// Read-only copies of the chunks around a chunk being post-processed, `None` where not loaded.
+pub struct NeighborView<P: MapDataProducer> {
+    pub dimension_tiles: TilesCount,
+    neighbors: [Option<P::GridType>; 8],
+}
+
+impl<P: MapDataProducer> NeighborView<P> {
+    /// Copies the loaded neighbors of `coords` out of `map`.
+    pub fn capture(map: &DataMap<P>, coords: ChunkCoords) -> Self {
+        let neighbors = NEIGHBOR_OFFSETS.map(|(dx, dy)| {
+            map.loaded_chunks
+                .get(&ChunkCoords {
+                    x: coords.x + dx,
+                    y: coords.y + dy,
+                })
+                .map(|chunk| chunk.grid.clone())
+        });
+        Self {
+            dimension_tiles: map.chunk_dimension_tiles,
+            neighbors,
+        }
+    }
+
+    /// The neighbor at offset (`dx`, `dy`), each in -1..=1.
+    pub fn neighbor(&self, dx: isize, dy: isize) -> Option<&P::GridType> {
+        let index = NEIGHBOR_OFFSETS.iter().position(|&offset| offset == (dx, dy))?;
+        self.neighbors[index].as_ref()
+    }
+
+    /// Whether all 8 neighbors were loaded.
+    pub fn is_complete(&self) -> bool {
+        self.neighbors.iter().all(Option::is_some)
+    }
+
+    /// Tile at local coordinates of the center chunk that lie outside of it, up to one chunk away.
+    /// `None` for tiles inside the center chunk and for tiles of missing neighbors.
+    pub fn item_outside(&self, x: isize, y: isize) -> Option<P::Item> {
+        let dim = self.dimension_tiles as isize;
+        let (dx, dy) = (x.div_euclid(dim), y.div_euclid(dim));
+        if (dx, dy) == (0, 0) {
+            return None;
+        }
+        let (local_x, local_y) = Point { x, y }.local_in_chunk(self.dimension_tiles);
+        self.neighbor(dx, dy)?.get_item(local_x, local_y).copied()
+    }
+}
+
+/// A pass over freshly generated chunks, run in the generation task after `generate_chunk`.
+///
+/// The result should not depend on which neighbors happen to be loaded, as that differs
+/// between runs. A pass that can't guarantee this (e.g. one blending across borders) returns
+/// true from `rerun_when_neighbors_load`: it is then run again on the main thread whenever
+/// a neighbor of the chunk loads, until all neighbors were seen. Such passes must be idempotent.
+/// Chunks loaded from the store or modified at runtime are never re-run.
+pub trait ChunkPostProcessor<P: MapDataProducer>: Send + Sync + 'static {
+    fn process(&self, coords: ChunkCoords, grid: &mut P::GridType, neighbors: &NeighborView<P>);
+
+    fn rerun_when_neighbors_load(&self) -> bool {
+        false
+    }
+}
+
+/// Seed of the generated world, applied to every DataMap producer at startup.
+/// Identical seeds generate identical worlds.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WorldSeed(pub u64);
+
+impl WorldSeed {
+    /// Takes the seed from the `WORLD_SEED` environment variable, or picks a random one.
+    pub fn from_env_or_random() -> Self {
+        let seed = std::env::var("WORLD_SEED")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or_else(rand::random);
+        info!("World seed: {seed}");
+        WorldSeed(seed)
+    }
+}
+
+/// The central resource for managing a chunked map of type T.
+#[derive(Resource)]
+pub struct DataMap<P: MapDataProducer> {
+    pub loaded_chunks: HashMap<ChunkCoords, DataChunk<P::GridType>>,
+    pub dormant_chunks: HashMap<ChunkCoords, CompressedChunk>, // Evicted but retained, see `make_dormant`
+    pub requested_chunks: HashSet<ChunkCoords>,
+    // Maps chunk coords to the entity holding the generation task, shared by the chunks of a batch
+    pub pending_tasks: HashMap<ChunkCoords, Entity>,
+    pub write_queue: HashMap<ChunkCoords, HashMap<Point, P::Item>>, // Writes to uncreated/unloaded cells, grouped by chunk
+    pub pending_areas: HashMap<ChunkCoords, Vec<PendingArea<P::Item>>>, // Bulk writes to unloaded chunks, oldest first
+    pub producer: P,
+    pub chunk_dimension_tiles: TilesCount,
+    pub chunk_size_units: f32, // Derived from chunk_dimension_tiles and TILE_SIZE_IN_UNITS
+    pub render_distance_chunks: usize, // Used by the map manager system to determine loading radius
+    pub focus_policy: FocusPolicy, // Set by `insert_chunked_plugin`, the camera window can be resized at runtime
+    pub load_shape: LoadShape,
+    pub unload_policy: UnloadPolicy,
+    pub unload_distance_chunks: usize, // Chunks are only evicted beyond this radius (hysteresis margin)
+    pub prefetch_distance_chunks: usize, // Max extra chunks requested ahead of a moving focus, 0 disables it
+    pub prefetch_min_speed: f32,         // Units per second, slower foci get the symmetric area only
+    pub max_evictions_per_frame: usize,
+    pub max_loaded_chunks: usize, // Soft cap, allows evicting chunks between render and unload distance
+    pub frame: u64, // Incremented by the load/unload system, used for LRU ordering
+    pub focus: Vec<(ChunkCoords, u8)>, // Chunk and priority of every MapRevealActor, as of the last load/unload pass
+    pub focus_centers: Vec<ChunkCoords>, // Actor chunks plus their prefetch centers, as of the last load/unload pass
+    pub cancel_irrelevant: bool, // Drop requests, tasks and results for chunks the foci moved away from (`get` requests far from all foci too)
+    pub max_tasks_in_flight: usize,
+    pub generation_batch_size: usize, // Requested chunks generated by one task, batched nearest first
+    pub max_chunks_applied_per_frame: usize, // Completed chunks beyond this wait for the next frames
+    unapplied_results: VecDeque<UnappliedResult<P::GridType>>, // Completed batches over that budget
+    pub store: Option<Arc<dyn ChunkStore<P::GridType>>>, // Persistence for modified chunks, if any
+    pub compressor: Option<Arc<dyn ChunkCompressor<P::GridType>>>, // Dormant chunks are only kept with one
+    pub retention_distance_chunks: usize, // Chunks evicted within this radius of a focus center go dormant
+    pub promoted_this_frame: Vec<ChunkCoords>, // Dormant chunks loaded again, drained into ChunkLoaded events
+    pub written_this_frame: HashSet<ChunkCoords>, // Drained into ChunkWritten events
+    pub unloaded_this_frame: Vec<ChunkCoords>,     // Drained into ChunkUnloaded events
+    pub max_generation_retries: u32, // A chunk whose generation panics is retried this many times, then given up
+    pub generation_failures: HashMap<ChunkCoords, u32>, // Panics so far, per chunk not generated yet
+    pub failed_chunks: HashSet<ChunkCoords>, // Given up, never requested again and left unloaded
+    pub post_processors: Vec<Arc<dyn ChunkPostProcessor<P>>>, // Run in order on every generated chunk
+    pub incomplete_post_processing: HashSet<ChunkCoords>, // Chunks post-processed without all of their neighbors
+    pub bootstrap_chunks: HashSet<ChunkCoords>, // Requested by `init`, see `bootstrap_progress`
+    pub journal: bool, // Off by default, `register_saveable_map` turns it on for the maps in save games
+    pub modified: HashMap<Point, P::Item>, // With `journal`, every single tile and fill write, replayed by save games
+    deltas: HashMap<ChunkCoords, ChunkDelta<P::Item>>, // The same writes since the last `drain_deltas`, by chunk
+    last_version: u64, // Last version handed to a chunk, never reused so reloaded chunks don't look unchanged
+}
+
+impl<P: MapDataProducer> DataMap<P> {
+    /// Panics with the `ConfigError` of a chunk dimension `try_new` rejects.
+    pub fn new(producer: P, chunk_dimension_tiles: TilesCount, render_distance_chunks: usize) -> Self {
+        Self::try_new(producer, chunk_dimension_tiles, render_distance_chunks)
+            .unwrap_or_else(|err| panic!("DataMap<{}>: {err}", std::any::type_name::<P>()))
+    }
+
+    /// Fails on a zero chunk dimension, or one whose size in world units isn't exact.
+    pub fn try_new(
+        producer: P,
+        chunk_dimension_tiles: TilesCount,
+        render_distance_chunks: usize,
+    ) -> Result<Self, ConfigError> {
+        let chunk_size_units = tiles_to_units("chunk_dimension_tiles", chunk_dimension_tiles)?;
+        Ok(Self {
+            loaded_chunks: HashMap::new(),
+            dormant_chunks: HashMap::new(),
+            requested_chunks: HashSet::new(),
+            pending_tasks: HashMap::new(),
+            write_queue: HashMap::new(),
+            pending_areas: HashMap::new(),
+            producer,
+            chunk_dimension_tiles,
+            chunk_size_units,
+            render_distance_chunks,
+            focus_policy: FocusPolicy::FollowMapRevealActors {
+                radius_chunks: render_distance_chunks,
+            },
+            load_shape: LoadShape::default(),
+            unload_policy: UnloadPolicy::default(),
+            unload_distance_chunks: render_distance_chunks + 2,
+            prefetch_distance_chunks: 2,
+            prefetch_min_speed: 50.0,
+            max_evictions_per_frame: 8,
+            max_loaded_chunks: usize::MAX,
+            frame: 0,
+            focus: Vec::new(),
+            focus_centers: Vec::new(),
+            cancel_irrelevant: true,
+            max_tasks_in_flight: 32,
+            generation_batch_size: 8,
+            max_chunks_applied_per_frame: 16,
+            unapplied_results: VecDeque::new(),
+            store: None,
+            compressor: None,
+            retention_distance_chunks: 0,
+            promoted_this_frame: Vec::new(),
+            written_this_frame: HashSet::new(),
+            unloaded_this_frame: Vec::new(),
+            max_generation_retries: 3,
+            generation_failures: HashMap::new(),
+            failed_chunks: HashSet::new(),
+            post_processors: Vec::new(),
+            incomplete_post_processing: HashSet::new(),
+            bootstrap_chunks: HashSet::new(),
+            journal: false,
+            modified: HashMap::new(),
+            deltas: HashMap::new(),
+            last_version: 0,
+        })
+    }
+
+    /// Re-requests a chunk whose generation panicked, or gives up on it after `max_generation_retries`.
+    pub fn record_generation_failure(&mut self, coords: ChunkCoords, err: ChunkGenError) {
+        let failures = self.generation_failures.entry(coords).or_insert(0);
+        *failures += 1;
+        if *failures <= self.max_generation_retries {
+            warn!(
+                "DataMap<{}> chunk {:?} generation panicked ({}), retry {}/{}",
+                std::any::type_name::<P>(),
+                coords,
+                err.0,
+                failures,
+                self.max_generation_retries
+            );
+            self.requested_chunks.insert(coords);
+        } else {
+            warn!(
+                "DataMap<{}> chunk {:?} generation panicked ({}), giving up",
+                std::any::type_name::<P>(),
+                coords,
+                err.0
+            );
+            self.generation_failures.remove(&coords);
+            self.failed_chunks.insert(coords);
+        }
+    }
+
+    /// Appends a pass run on every chunk generated from now on.
+    pub fn add_post_processor(&mut self, processor: impl ChunkPostProcessor<P>) {
+        self.post_processors.push(Arc::new(processor));
+    }
+
+    /// Re-runs the passes that depend on neighbors for a loaded chunk, or for one about to be inserted.
+    /// Returns whether the chunk still misses some neighbors.
+    fn rerun_post_processors(&self, coords: ChunkCoords, chunk: &mut DataChunk<P::GridType>) -> bool {
+        if chunk.dirty || chunk.from_store {
+            return false;
+        }
+        let view = NeighborView::capture(self, coords);
+        for processor in self.post_processors.iter().filter(|p| p.rerun_when_neighbors_load()) {
+            processor.process(coords, &mut chunk.grid, &view);
+        }
+        !view.is_complete()
+    }
+
+    /// Runs `rerun_post_processors` on a loaded chunk, recording the change.
+    fn rerun_post_processors_loaded(&mut self, coords: ChunkCoords) {
+        let Some(mut chunk) = self.loaded_chunks.remove(&coords) else {
+            self.incomplete_post_processing.remove(&coords);
+            return;
+        };
+        let incomplete = self.rerun_post_processors(coords, &mut chunk);
+        chunk.version = self.next_version();
+        self.loaded_chunks.insert(coords, chunk);
+        self.written_this_frame.insert(coords);
+        if !incomplete {
+            self.incomplete_post_processing.remove(&coords);
+        }
+    }
+
+    /// Inserts a generated, loaded or promoted chunk into `loaded_chunks`, with the writes queued
+    /// for it on top and the post-processors that wait for neighbors re-run, on it and around it.
+    fn insert_chunk(&mut self, coords: ChunkCoords, mut chunk: DataChunk<P::GridType>) {
+        // Apply any writes from the queue to this newly inserted chunk
+        let chunk_bottom_left_tile = coords.to_bottom_left_tile_point(self.chunk_dimension_tiles);
+
+        // Bulk writes first, single tile writes are newer and go on top
+        if let Some(areas) = self.pending_areas.remove(&coords) {
+            for area in areas.iter() {
+                area.apply_to(chunk_bottom_left_tile, &mut chunk.grid);
+            }
+            chunk.dirty = true;
+        }
+
+        // Apply the writes that belong to the newly inserted chunk, other chunks' writes are untouched
+        if let Some(writes) = self.write_queue.remove(&coords) {
+            for (point, value) in writes {
+                let (local_x, local_y) = point.local_in_chunk(self.chunk_dimension_tiles);
+                chunk.grid.set_item(local_x, local_y, value);
+            }
+            chunk.dirty = true;
+        }
+
+        // Neighbors may have loaded while the task ran
+        let rerun = self.post_processors.iter().any(|p| p.rerun_when_neighbors_load());
+        if rerun && self.rerun_post_processors(coords, &mut chunk) {
+            self.incomplete_post_processing.insert(coords);
+        }
+
+        chunk.last_touched = self.frame;
+        chunk.version = self.next_version();
+        self.loaded_chunks.insert(coords, chunk);
+
+        // This chunk may be the one its neighbors were missing
+        if rerun {
+            for (dx, dy) in NEIGHBOR_OFFSETS {
+                let neighbor = ChunkCoords {
+                    x: coords.x + dx,
+                    y: coords.y + dy,
+                };
+                if self.incomplete_post_processing.contains(&neighbor) {
+                    self.rerun_post_processors_loaded(neighbor);
+                }
+            }
+        }
+    }
+
+    /// Generates the chunk right away and inserts it like a completed task, for tests elsewhere in the crate.
+    #[cfg(test)]
+    pub(crate) fn load_generated_chunk(&mut self, coords: ChunkCoords) {
+        let chunk = self.producer.generate_chunk(coords, self.chunk_dimension_tiles);
+        self.insert_chunk(coords, chunk);
+    }
+
+    /// Attaches a chunk store: chunks are looked up there before being generated,
+    /// and dirty chunks are saved back on unload or flush.
+    pub fn set_store(&mut self, store: impl ChunkStore<P::GridType>) {
+        self.store = Some(Arc::new(store));
+    }
+
+    /// Removes a chunk from memory, saving it to the store first if it was modified.
+    pub fn unload_chunk(&mut self, coords: ChunkCoords) -> Option<DataChunk<P::GridType>> {
+        let chunk = self.loaded_chunks.remove(&coords)?;
+        self.unloaded_this_frame.push(coords);
+        self.incomplete_post_processing.remove(&coords);
+        if let Some(store) = self.store.as_ref().filter(|_| chunk.dirty) {
+            store.save_chunk(coords, &chunk);
+        }
+        Some(chunk)
+    }
+
+    /// Keeps the chunks evicted within `retention_distance_chunks` of a focus center compressed
+    /// by `compressor` in `dormant_chunks`, instead of dropping them. They cost a fraction of a
+    /// loaded chunk and come back without a generation task when needed again.
+    pub fn set_compressor(&mut self, compressor: impl ChunkCompressor<P::GridType>, retention_distance_chunks: usize) {
+        self.compressor = Some(Arc::new(compressor));
+        self.retention_distance_chunks = retention_distance_chunks;
+    }
+
+    /// Moves a loaded chunk to `dormant_chunks`, which for the rest of the map is an unload:
+    /// `ChunkUnloaded` is sent and `loaded_chunks` no longer has it. Returns whether it was
+    /// loaded and there is a compressor.
+    pub fn make_dormant(&mut self, coords: ChunkCoords) -> bool {
+        let Some(compressor) = self.compressor.clone() else {
+            return false;
+        };
+        let Some(chunk) = self.loaded_chunks.remove(&coords) else {
+            return false;
+        };
+        self.unloaded_this_frame.push(coords);
+        self.incomplete_post_processing.remove(&coords);
+        let dormant = CompressedChunk {
+            bytes: compressor.compress(&chunk.grid),
+            dirty: chunk.dirty,
+            from_store: chunk.from_store,
+        };
+        self.dormant_chunks.insert(coords, dormant);
+        true
+    }
+
+    /// Decompresses a dormant chunk back into `loaded_chunks`, with the writes queued meanwhile.
+    /// Its `ChunkLoaded` goes out with the next completed chunks. Returns whether it was dormant;
+    /// one that fails to decompress is dropped, to be generated again.
+    pub fn promote(&mut self, coords: ChunkCoords) -> bool {
+        let Some(dormant) = self.dormant_chunks.remove(&coords) else {
+            return false;
+        };
+        let grid = self.compressor.as_ref().and_then(|c| c.decompress(&dormant.bytes));
+        let Some(grid) = grid else {
+            warn!("DataMap<{}> dormant chunk {:?} is corrupt, dropping it", std::any::type_name::<P>(), coords);
+            return false;
+        };
+        let mut chunk = DataChunk::new(grid);
+        chunk.dirty = dormant.dirty;
+        chunk.from_store = dormant.from_store;
+        self.requested_chunks.remove(&coords);
+        self.insert_chunk(coords, chunk);
+        self.promoted_this_frame.push(coords);
+        true
+    }
+
+    /// Forgets a dormant chunk, saving it to the store first if it was modified. Returns the bytes freed.
+    pub fn drop_dormant(&mut self, coords: ChunkCoords) -> usize {
+        let Some(dormant) = self.dormant_chunks.remove(&coords) else {
+            return 0;
+        };
+        if dormant.dirty {
+            self.save_dormant(coords, &dormant);
+        }
+        dormant.byte_size()
+    }
+
+    fn save_dormant(&self, coords: ChunkCoords, dormant: &CompressedChunk) {
+        let (Some(store), Some(compressor)) = (&self.store, &self.compressor) else {
+            return;
+        };
+        match compressor.decompress(&dormant.bytes) {
+            Some(grid) => store.save_chunk(coords, &DataChunk::new(grid)),
+            None => warn!("DataMap<{}> dormant chunk {:?} is corrupt, not saving it", std::any::type_name::<P>(), coords),
+        }
+    }
+
+    /// Memory held by the compressed grids of the dormant chunks.
+    pub fn dormant_bytes(&self) -> usize {
+        self.dormant_chunks.values().map(CompressedChunk::byte_size).sum()
+    }
+
+    /// Evicts a loaded chunk: dormant within `retention_distance_chunks` of a focus center,
+    /// unloaded otherwise. Returns the bytes it held while loaded.
+    fn evict_chunk(&mut self, coords: ChunkCoords) -> usize {
+        let bytes = self.loaded_chunks.get(&coords).map_or(0, |chunk| chunk.grid.byte_size());
+        let retained = self.near_focus(coords, self.retention_distance_chunks) && self.make_dormant(coords);
+        if !retained {
+            self.unload_chunk(coords);
+        }
+        bytes
+    }
+
+    /// Drops the dormant chunks beyond `retention_distance_chunks` of every focus center, at most
+    /// `max_evictions_per_frame` of them. Returns how many were dropped.
+    pub fn evict_dormant(&mut self) -> usize {
+        let retention = self.retention_distance_chunks;
+        let stale: Vec<ChunkCoords> = self
+            .dormant_chunks
+            .keys()
+            .filter(|coords| !self.near_focus(**coords, retention))
+            .take(self.max_evictions_per_frame)
+            .copied()
+            .collect();
+        for coords in stale.iter() {
+            self.drop_dormant(*coords);
+        }
+        stale.len()
+    }
+
+    /// Drops the dormant chunks farthest from the focus centers until at least `bytes` are freed,
+    /// see `ChunkMemoryBudget::max_dormant_bytes`. Returns the bytes freed.
+    pub fn evict_farthest_dormant(&mut self, bytes: usize) -> usize {
+        let shape = self.load_shape;
+        let mut candidates: Vec<(usize, ChunkCoords)> = self
+            .dormant_chunks
+            .keys()
+            .map(|coords| {
+                let distance = self
+                    .focus_centers
+                    .iter()
+                    .map(|f| shape.distance(coords.x - f.x, coords.y - f.y))
+                    .min()
+                    .unwrap_or(usize::MAX);
+                (distance, *coords)
+            })
+            .collect();
+        candidates.sort_unstable_by_key(|(distance, coords)| (Reverse(*distance), coords.x, coords.y));
+
+        let mut freed = 0;
+        for (_, coords) in candidates {
+            if freed >= bytes {
+                break;
+            }
+            freed += self.drop_dormant(coords);
+        }
+        freed
+    }
+
+    /// Saves every dirty chunk to the store and clears their dirty flags, dormant ones included.
+    pub fn flush(&mut self) {
+        let Some(store) = self.store.clone() else {
+            return;
+        };
+        for (coords, chunk) in self.loaded_chunks.iter_mut() {
+            if chunk.dirty {
+                store.save_chunk(*coords, chunk);
+                chunk.dirty = false;
+            }
+        }
+        let dirty: Vec<ChunkCoords> = self
+            .dormant_chunks
+            .iter()
+            .filter(|(_, dormant)| dormant.dirty)
+            .map(|(coords, _)| *coords)
+            .collect();
+        for coords in dirty {
+            if let Some(mut dormant) = self.dormant_chunks.remove(&coords) {
+                self.save_dormant(coords, &dormant);
+                dormant.dirty = false;
+                self.dormant_chunks.insert(coords, dormant);
+            }
+        }
+    }
+
+    /// Evicts least recently touched chunks that are further than `unload_distance_chunks`
+    /// (Chebyshev distance) from every focus chunk. If more than `max_loaded_chunks` are loaded,
+    /// chunks outside `render_distance_chunks` become eligible as well.
+    /// At most `max_evictions_per_frame` chunks are unloaded (or made dormant, see `set_compressor`)
+    /// per call. Returns the coords of the evicted chunks.
+    pub fn evict_chunks(&mut self, focus_chunks: &[ChunkCoords]) -> Vec<ChunkCoords> {
+        if focus_chunks.is_empty() {
+            return Vec::new(); // Nobody is looking, nothing to decide on
+        }
+        let shape = self.load_shape;
+        let distance_to_focus = |coords: &ChunkCoords| {
+            focus_chunks
+                .iter()
+                .map(|f| shape.distance(coords.x - f.x, coords.y - f.y))
+                .min()
+                .unwrap_or(usize::MAX)
+        };
+
+        let over_cap = self.loaded_chunks.len() > self.max_loaded_chunks;
+        let mut candidates: Vec<(u64, ChunkCoords)> = self
+            .loaded_chunks
+            .iter()
+            .filter(|(coords, _)| {
+                let distance = distance_to_focus(coords);
+                distance > self.unload_distance_chunks
+                    || (over_cap && distance > self.render_distance_chunks)
+            })
+            .map(|(coords, chunk)| (chunk.last_touched, *coords))
+            .collect();
+        // Chunks left behind by the same frame (like a whole ring after a shrink) go farthest first
+        candidates.sort_unstable_by_key(|(last_touched, coords)| (*last_touched, Reverse(distance_to_focus(coords))));
+
+        let mut budget = self.max_evictions_per_frame;
+        if over_cap {
+            // Beyond the hard margin we always evict, the soft cap only adds what's needed to get back under it
+            let hard = candidates
+                .iter()
+                .filter(|(_, c)| distance_to_focus(c) > self.unload_distance_chunks)
+                .count();
+            let excess = self.loaded_chunks.len() - self.max_loaded_chunks;
+            budget = budget.min(hard.max(excess));
+        }
+
+        let evicted: Vec<ChunkCoords> = candidates
+            .into_iter()
+            .take(budget)
+            .map(|(_, coords)| coords)
+            .collect();
+        for coords in evicted.iter() {
+            self.evict_chunk(*coords);
+        }
+        evicted
+    }
+
+    /// Memory held by the grids of the loaded chunks.
+    pub fn loaded_bytes(&self) -> usize {
+        self.loaded_chunks.values().map(|chunk| chunk.grid.byte_size()).sum()
+    }
+
+    /// Unloads the chunks farthest from the focus centers until at least `bytes` of loaded chunks
+    /// are freed, the ones within `retention_distance_chunks` going dormant instead.
+    /// Chunks within `render_distance_chunks` of a focus center are never unloaded, they would
+    /// only be requested again. Returns the bytes freed, less than asked once only those are left.
+    pub fn evict_farthest(&mut self, bytes: usize) -> usize {
+        let shape = self.load_shape;
+        let mut candidates: Vec<(usize, ChunkCoords)> = self
+            .loaded_chunks
+            .keys()
+            .filter(|coords| !self.near_focus(**coords, self.render_distance_chunks))
+            .map(|coords| {
+                let distance = self
+                    .focus_centers
+                    .iter()
+                    .map(|f| shape.distance(coords.x - f.x, coords.y - f.y))
+                    .min()
+                    .unwrap_or(usize::MAX);
+                (distance, *coords)
+            })
+            .collect();
+        candidates.sort_unstable_by_key(|(distance, coords)| (Reverse(*distance), coords.x, coords.y));
+
+        let mut freed = 0;
+        for (_, coords) in candidates {
+            if freed >= bytes {
+                break;
+            }
+            freed += self.evict_chunk(coords);
+        }
+        freed
+    }
+
+    /// Changes the loading radius at runtime, keeping the margin between render and unload distance.
+    /// Growing requests the new ring on the next load/unload pass, nearest chunks first. Shrinking
+    /// evicts nothing here: the chunks now beyond the unload distance go over the next frames,
+    /// `max_evictions_per_frame` at a time and farthest first, through `evict_chunks`.
+    pub fn set_render_distance(&mut self, chunks: usize) {
+        let margin = self.unload_distance_chunks.saturating_sub(self.render_distance_chunks);
+        self.render_distance_chunks = chunks;
+        self.unload_distance_chunks = chunks + margin;
+        if let FocusPolicy::FollowMapRevealActors { radius_chunks } = &mut self.focus_policy {
+            *radius_chunks = chunks;
+        }
+    }
+
+    /// Whether the chunk is within `radius` chunks of a focus center (see `focus_centers`).
+    /// Always true while there are no foci, nobody is looking so nothing is irrelevant.
+    pub fn near_focus(&self, coords: ChunkCoords, radius: usize) -> bool {
+        self.focus_centers.is_empty()
+            || self
+                .focus_centers
+                .iter()
+                .any(|f| self.load_shape.contains(coords.x - f.x, coords.y - f.y, radius))
+    }
+
+    /// With `cancel_irrelevant`, forgets requests beyond the render distance of every focus and
+    /// pending tasks beyond the unload distance. Returns the task entities to despawn, which
+    /// drops (and so cancels) their tasks, and the number of requests dropped.
+    /// A batch task is only despawned once none of its chunks are pending anymore.
+    pub fn cancel_irrelevant_work(&mut self) -> (Vec<Entity>, usize) {
+        if !self.cancel_irrelevant || self.focus_centers.is_empty() {
+            return (Vec::new(), 0);
+        }
+        let requested_before = self.requested_chunks.len();
+        let (render, unload) = (self.render_distance_chunks, self.unload_distance_chunks);
+        let stale_requests: Vec<ChunkCoords> = self
+            .requested_chunks
+            .iter()
+            .filter(|c| !self.near_focus(**c, render))
+            .copied()
+            .collect();
+        for coords in stale_requests {
+            self.requested_chunks.remove(&coords);
+        }
+        let stale_tasks: Vec<ChunkCoords> = self
+            .pending_tasks
+            .keys()
+            .filter(|c| !self.near_focus(**c, unload))
+            .copied()
+            .collect();
+        let mut entities: Vec<Entity> = stale_tasks
+            .iter()
+            .filter_map(|coords| self.pending_tasks.remove(coords))
+            .collect();
+        entities.sort_unstable();
+        entities.dedup();
+        entities.retain(|entity| !self.pending_tasks.values().any(|e| e == entity));
+        (entities, requested_before - self.requested_chunks.len())
+    }
+
+    /// Generation tasks in flight, each holding up to `generation_batch_size` chunks.
+    pub fn tasks_in_flight(&self) -> usize {
+        self.pending_tasks.values().collect::<HashSet<_>>().len()
+    }
+
+    /// Distance in chunks (measured by `load_shape`) from the given chunk to the nearest actor,
+    /// along with that actor's priority. Lower values are serviced first.
+    pub fn request_priority(&self, coords: ChunkCoords) -> (usize, Reverse<u8>) {
+        self.focus
+            .iter()
+            .map(|(focus, priority)| {
+                let distance = self.load_shape.distance(coords.x - focus.x, coords.y - focus.y);
+                (distance, Reverse(*priority))
+            })
+            .min()
+            .unwrap_or((usize::MAX, Reverse(0)))
+    }
+
+    /// Returns up to `count` requested chunks that are neither loaded nor pending, nearest to an actor first.
+    pub fn next_requests(&self, count: usize) -> Vec<ChunkCoords> {
+        let mut requests: Vec<_> = self
+            .requested_chunks
+            .iter()
+            .filter(|c| {
+                !self.pending_tasks.contains_key(*c)
+                    && !self.loaded_chunks.contains_key(*c)
+                    && !self.dormant_chunks.contains_key(*c) // Promoted instead, see `promote_requested`
+                    && !self.failed_chunks.contains(*c)
+            })
+            .map(|c| (self.request_priority(*c), *c))
+            .collect();
+        requests.sort_unstable_by_key(|(priority, _)| *priority);
+        requests.into_iter().take(count).map(|(_, c)| c).collect()
+    }
+
+    /// Promotes up to `count` requested dormant chunks, nearest to an actor first, which is much
+    /// cheaper than generating them. Returns how many were promoted.
+    pub fn promote_requested(&mut self, count: usize) -> usize {
+        let mut requests: Vec<_> = self
+            .requested_chunks
+            .iter()
+            .filter(|c| self.dormant_chunks.contains_key(*c))
+            .map(|c| (self.request_priority(*c), *c))
+            .collect();
+        requests.sort_unstable_by_key(|(priority, _)| *priority);
+        let mut promoted = 0;
+        for (_, coords) in requests.into_iter().take(count) {
+            if self.promote(coords) {
+                promoted += 1;
+            }
+        }
+        promoted
+    }
+
+    // --- Public API for Game Logic ---
+
+    /// Requests and gets the data at a specific world tile Point.
+    /// Spawns a chunk generation request if the chunk is not loaded, a dormant one is promoted.
+    pub fn get(&mut self, point: Point) -> P::Item {
+        // Check write queue first (acts as a cache for pending writes)
+        if let Some(queued_value) = self.pending_value(point) {
+            return queued_value;
+        }
+
+        let chunk_coords = ChunkCoords::from_point(point, self.chunk_dimension_tiles);
+        self.promote(chunk_coords);
+        if let Some(chunk) = self.loaded_chunks.get(&chunk_coords) {
+            let (local_x, local_y) = point.local_in_chunk(self.chunk_dimension_tiles);
+            chunk
+                .grid
+                .get_item(local_x, local_y)
+                .copied() // Get a copy of the item
+                .unwrap_or_else(|| self.producer.default_value()) // Should not happen if logic is correct
+        } else {
+            // Chunk not loaded, request it
+            self.requested_chunks.insert(chunk_coords);
+            self.producer.default_value() // Return default while waiting for generation
+        }
+    }
+
+    /// Requests and gets the data of the tile at a world position, see `get`.
+    pub fn get_at(&mut self, pos: WorldPos) -> P::Item {
+        self.get(pos.to_tile().0)
+    }
+
+    #[deprecated(note = "use `get_at` with a `WorldPos`")]
+    pub fn get_rounded(&mut self, world_pos: Vec2) -> P::Item {
+        self.get_at(WorldPos(world_pos))
+    }
+
+    /// Attempts to get the data at a specific world tile Point.
+    /// Returns `Some(T)` if the chunk is loaded, `None` otherwise.
+    /// Spawns a chunk generation request if the chunk is not loaded, a dormant one is promoted.
+    pub fn get_option(&mut self, point: Point) -> Option<P::Item> {
+        // Check write queue first
+        if let Some(queued_value) = self.pending_value(point) {
+            return Some(queued_value);
+        }
+
+        let chunk_coords = ChunkCoords::from_point(point, self.chunk_dimension_tiles);
+        self.promote(chunk_coords);
+        if let Some(chunk) = self.loaded_chunks.get(&chunk_coords) {
+            let (local_x, local_y) = point.local_in_chunk(self.chunk_dimension_tiles);
+            chunk.grid.get_item(local_x, local_y).copied()
+        } else {
+            self.requested_chunks.insert(chunk_coords);
+            None
+        }
+    }
+
+    /// Attempts to get the data of the tile at a world position, see `get_option`.
+    pub fn get_at_option(&mut self, pos: WorldPos) -> Option<P::Item> {
+        self.get_option(pos.to_tile().0)
+    }
+
+    #[deprecated(note = "use `get_at_option` with a `WorldPos`")]
+    pub fn get_rounded_option(&mut self, world_pos: Vec2) -> Option<P::Item> {
+        self.get_at_option(WorldPos(world_pos))
+    }
+
+    fn next_version(&mut self) -> u64 {
+        self.last_version += 1;
+        self.last_version
+    }
+
+    /// Version of a loaded chunk, None if it's not loaded. Changes whenever the chunk is
+    /// (re)loaded or written, so caches of derived data can tell whether it's still current.
+    pub fn chunk_version(&self, coords: ChunkCoords) -> Option<u64> {
+        self.loaded_chunks.get(&coords).map(|chunk| chunk.version)
+    }
+
+    /// Marks a loaded chunk whose grid was changed in place as written, like `write` does: it gets
+    /// a new version, is saved to the store and sends `ChunkWritten`. Does nothing if it isn't loaded.
+    pub fn mark_chunk_written(&mut self, coords: ChunkCoords) {
+        if !self.loaded_chunks.contains_key(&coords) {
+            return;
+        }
+        let version = self.next_version();
+        if let Some(chunk) = self.loaded_chunks.get_mut(&coords) {
+            chunk.dirty = true;
+            chunk.version = version;
+        }
+        self.written_this_frame.insert(coords);
+    }
+
+    /// Reads the data at a specific world tile Point without spawning any generation requests.
+    /// Returns `Some(T)` if the chunk is loaded or dormant, `None` otherwise. Dormant chunks
+    /// stay dormant, the item is decoded from the compressed bytes.
+    pub fn read(&self, point: Point) -> Option<P::Item> {
+        // Check write queue first for potential cached writes
+        if let Some(queued_value) = self.pending_value(point) {
+            return Some(queued_value);
+        }
+
+        let chunk_coords = ChunkCoords::from_point(point, self.chunk_dimension_tiles);
+        let (local_x, local_y) = point.local_in_chunk(self.chunk_dimension_tiles);
+        if let Some(chunk) = self.loaded_chunks.get(&chunk_coords) {
+            return chunk.grid.get_item(local_x, local_y).copied();
+        }
+        let dormant = self.dormant_chunks.get(&chunk_coords)?;
+        self.compressor.as_ref()?.read_item(&dormant.bytes, local_x, local_y)
+    }
+
+    // Grid of a dormant chunk without promoting it, None if it isn't dormant or fails to decompress
+    fn dormant_grid(&self, coords: ChunkCoords) -> Option<P::GridType> {
+        let dormant = self.dormant_chunks.get(&coords)?;
+        self.compressor.as_ref()?.decompress(&dormant.bytes)
+    }
+
+    /// Reads the data of the tile at a world position without spawning any generation requests.
+    pub fn read_at(&self, pos: WorldPos) -> Option<P::Item> {
+        self.read(pos.to_tile().0)
+    }
+
+    #[deprecated(note = "use `read_at` with a `WorldPos`")]
+    pub fn read_rounded(&self, world_pos: Vec2) -> Option<P::Item> {
+        self.read_at(WorldPos(world_pos))
+    }
+
+    /// Returns the value of a queued write at `point`, if any.
+    /// Single tile writes are always newer than bulk ones, so they are checked first.
+    fn pending_value(&self, point: Point) -> Option<P::Item> {
+        let chunk_coords = ChunkCoords::from_point(point, self.chunk_dimension_tiles);
+        if let Some(&queued_value) = self.write_queue.get(&chunk_coords).and_then(|w| w.get(&point)) {
+            return Some(queued_value);
+        }
+        self.pending_areas
+            .get(&chunk_coords)?
+            .iter()
+            .rev()
+            .find_map(|area| area.get(point))
+    }
+
+    /// Number of single tile writes waiting for their chunk.
+    pub fn queued_write_count(&self) -> usize {
+        self.write_queue.values().map(HashMap::len).sum()
+    }
+
+    /// Reads a rectangular area of tiles starting at `bottom_left` without spawning any generation requests.
+    /// Returns `None` if any chunk touched by the area is neither loaded nor dormant.
+    /// Pending writes from the write queue are applied on top of the result.
+    pub fn read_area(
+        &self,
+        bottom_left: Point,
+        width: TilesCount,
+        height: TilesCount,
+    ) -> Option<FlatGrid<P::Item>>
+    where
+        P::Item: Debug + 'static,
+    {
+        let (grid, missing) = self.collect_area(bottom_left, width, height);
+        if missing.is_empty() { Some(grid) } else { None }
+    }
+
+    /// Gets a rectangular area of tiles starting at `bottom_left`.
+    /// Tiles of chunks that are not loaded are filled with the producer default,
+    /// and generation requests are spawned for those chunks.
+    pub fn get_area(
+        &mut self,
+        bottom_left: Point,
+        width: TilesCount,
+        height: TilesCount,
+    ) -> FlatGrid<P::Item>
+    where
+        P::Item: Debug + 'static,
+    {
+        let (grid, missing) = self.collect_area(bottom_left, width, height);
+        self.requested_chunks.extend(missing);
+        grid
+    }
+
+    /// Reads an area like `read_area`, together with the versions of the chunks it covers, so
+    /// work based on it can check with `validate_snapshot` whether it is still current.
+    /// Chunks that aren't loaded are neither requested nor part of the copy, see `AreaSnapshot::read`.
+    pub fn snapshot_area(&self, bottom_left: Point, size: (TilesCount, TilesCount)) -> AreaSnapshot<P::Item> {
+        let (tiles, _) = self.collect_area(bottom_left, size.0, size.1);
+        AreaSnapshot {
+            bottom_left,
+            tiles,
+            versions: self.area_versions(bottom_left, size),
+            chunk_dimension_tiles: self.chunk_dimension_tiles,
+        }
+    }
+
+    /// Versions of the chunks under an area, see `ChunkVersions`.
+    pub fn area_versions(&self, bottom_left: Point, size: (TilesCount, TilesCount)) -> ChunkVersions {
+        if size.0 == 0 || size.1 == 0 {
+            return ChunkVersions::default();
+        }
+        let top_right = Point {
+            x: bottom_left.x + size.0 as isize - 1,
+            y: bottom_left.y + size.1 as isize - 1,
+        };
+        let first = ChunkCoords::from_point(bottom_left, self.chunk_dimension_tiles);
+        let last = ChunkCoords::from_point(top_right, self.chunk_dimension_tiles);
+        ChunkVersions(
+            (first.y..=last.y)
+                .flat_map(|y| (first.x..=last.x).map(move |x| ChunkCoords { x, y }))
+                .map(|coords| (coords, self.chunk_version(coords)))
+                .collect(),
+        )
+    }
+
+    /// Whether no chunk under the snapshot was written, generated, loaded or unloaded since.
+    /// Writes queued for chunks that aren't loaded don't count, they show once the chunk loads.
+    pub fn validate_snapshot(&self, snapshot: &AreaSnapshot<P::Item>) -> bool {
+        self.validate_versions(&snapshot.versions)
+    }
+
+    /// Same as `validate_snapshot`, for consumers that only kept the versions.
+    pub fn validate_versions(&self, versions: &ChunkVersions) -> bool {
+        versions
+            .0
+            .iter()
+            .all(|(coords, version)| self.chunk_version(*coords) == *version)
+    }
+
+    /// Copies an area chunk-by-chunk (one HashMap lookup per touched chunk) and overlays the write queue.
+    /// Returns the grid together with the coords of the chunks that were neither loaded nor dormant.
+    fn collect_area(
+        &self,
+        bottom_left: Point,
+        width: TilesCount,
+        height: TilesCount,
+    ) -> (FlatGrid<P::Item>, Vec<ChunkCoords>)
+    where
+        P::Item: Debug + 'static,
+    {
+        let mut area = FlatGrid::new_rect(width, height, self.producer.default_value());
+        let mut missing = Vec::new();
+        if width == 0 || height == 0 {
+            return (area, missing);
+        }
+
+        let dim = self.chunk_dimension_tiles as isize;
+        let top_right = Point {
+            x: bottom_left.x + width as isize - 1,
+            y: bottom_left.y + height as isize - 1,
+        };
+        let first_chunk = ChunkCoords::from_point(bottom_left, self.chunk_dimension_tiles);
+        let last_chunk = ChunkCoords::from_point(top_right, self.chunk_dimension_tiles);
+
+        for chunk_y in first_chunk.y..=last_chunk.y {
+            for chunk_x in first_chunk.x..=last_chunk.x {
+                let coords = ChunkCoords { x: chunk_x, y: chunk_y };
+                // Dormant chunks stay dormant, their grid is decompressed for the copy like `read` decodes a tile
+                let dormant = if self.loaded_chunks.contains_key(&coords) { None } else { self.dormant_grid(coords) };
+                let grid = self.loaded_chunks.get(&coords).map(|chunk| &chunk.grid).or(dormant.as_ref());
+                if let Some(grid) = grid {
+                    // Intersection of the requested area and this chunk, in world tiles
+                    let chunk_origin = coords.to_bottom_left_tile_point(self.chunk_dimension_tiles);
+                    let min_x = bottom_left.x.max(chunk_origin.x);
+                    let max_x = top_right.x.min(chunk_origin.x + dim - 1);
+                    let min_y = bottom_left.y.max(chunk_origin.y);
+                    let max_y = top_right.y.min(chunk_origin.y + dim - 1);
+                    let span = (max_x - min_x + 1) as usize;
+
+                    let target = area.as_mut_slice();
+                    for world_y in min_y..=max_y {
+                        let dst_start = ((world_y - bottom_left.y) * width as isize
+                            + (min_x - bottom_left.x)) as usize;
+                        grid.read_run(
+                            (min_x - chunk_origin.x) as TilesCount,
+                            (world_y - chunk_origin.y) as TilesCount,
+                            &mut target[dst_start..dst_start + span],
+                        );
+                    }
+                } else {
+                    missing.push(coords);
+                }
+
+                // Bulk writes only exist for chunks that aren't loaded, dormant ones included
+                for pending in self.pending_areas.get(&coords).into_iter().flatten() {
+                    let clipped = clip_pending_area(pending, bottom_left, top_right);
+                    if let Some(clipped) = clipped {
+                        clipped.apply_to(bottom_left, &mut area);
+                    }
+                }
+
+                // Pending writes take precedence, same as for single tile reads. Only this chunk's
+                // are looked at, the queue is grouped by chunk.
+                for (point, value) in self.write_queue.get(&coords).into_iter().flatten() {
+                    let inside = (bottom_left.x..=top_right.x).contains(&point.x)
+                        && (bottom_left.y..=top_right.y).contains(&point.y);
+                    if inside {
+                        let local_x = (point.x - bottom_left.x) as TilesCount;
+                        let local_y = (point.y - bottom_left.y) as TilesCount;
+                        area.set_item(local_x, local_y, *value);
+                    }
+                }
+            }
+        }
+
+        (area, missing)
+    }
+
+    /// Writes a whole grid with its bottom-left tile at `bottom_left`.
+    /// Loaded chunks are written row by row, the parts that fall into unloaded chunks
+    /// are queued as one pending rectangle per chunk.
+    pub fn write_area(&mut self, bottom_left: Point, grid: &FlatGrid<P::Item>)
+    where
+        P::Item: Debug + 'static,
+    {
+        self.write_rect(
+            bottom_left,
+            grid.width(),
+            grid.height(),
+            RectSource::Tiles(grid.as_slice()),
+        );
+    }
+
+    /// Writes `stamp` with its anchor at `origin`, straddling chunk borders like `write_area`:
+    /// loaded chunks are written at once, unloaded ones get the stamp queued as pending rectangles.
+    /// With `StampMode::SkipDefault` only the runs of non-default cells are written.
+    /// Like `write_area`, stamps don't go to the `modified` journal.
+    pub fn stamp(&mut self, origin: Point, stamp: &Stamp<P::Item>, mode: StampMode)
+    where
+        P::Item: Debug + PartialEq + 'static,
+    {
+        let bottom_left = stamp.bottom_left(origin);
+        if mode == StampMode::Overwrite {
+            self.write_area(bottom_left, &stamp.grid);
+            return;
+        }
+        let transparent = P::Item::default();
+        for y in 0..stamp.grid.height() {
+            let Some(row) = stamp.grid.row(y) else {
+                continue;
+            };
+            // One rectangle per run of opaque cells, one row high
+            let mut x = 0;
+            while x < row.len() {
+                if row[x] == transparent {
+                    x += 1;
+                    continue;
+                }
+                let start = x;
+                while x < row.len() && row[x] != transparent {
+                    x += 1;
+                }
+                let run_origin = Point {
+                    x: bottom_left.x + start as isize,
+                    y: bottom_left.y + y as isize,
+                };
+                self.write_rect(run_origin, x - start, 1, RectSource::Tiles(&row[start..x]));
+            }
+        }
+    }
+
+    /// Sets every tile of a rectangle to `value`, see `write_area`.
+    /// Unlike `write_area`, the tiles are recorded in the `modified` journal (with `journal` on).
+    pub fn fill_rect(
+        &mut self,
+        bottom_left: Point,
+        width: TilesCount,
+        height: TilesCount,
+        value: P::Item,
+    ) {
+        for dy in 0..height as isize {
+            for dx in 0..width as isize {
+                let point = Point {
+                    x: bottom_left.x + dx,
+                    y: bottom_left.y + dy,
+                };
+                self.record_modified(point, value);
+            }
+        }
+        self.write_rect(bottom_left, width, height, RectSource::Fill(value));
+    }
+
+    fn write_rect(
+        &mut self,
+        bottom_left: Point,
+        width: TilesCount,
+        height: TilesCount,
+        source: RectSource<'_, P::Item>,
+    ) {
+        if width == 0 || height == 0 {
+            return;
+        }
+        let dim = self.chunk_dimension_tiles as isize;
+        let top_right = Point {
+            x: bottom_left.x + width as isize - 1,
+            y: bottom_left.y + height as isize - 1,
+        };
+        let first_chunk = ChunkCoords::from_point(bottom_left, self.chunk_dimension_tiles);
+        let last_chunk = ChunkCoords::from_point(top_right, self.chunk_dimension_tiles);
+
+        for chunk_y in first_chunk.y..=last_chunk.y {
+            for chunk_x in first_chunk.x..=last_chunk.x {
+                let coords = ChunkCoords { x: chunk_x, y: chunk_y };
+                let chunk_origin = coords.to_bottom_left_tile_point(self.chunk_dimension_tiles);
+                let min_x = bottom_left.x.max(chunk_origin.x);
+                let max_x = top_right.x.min(chunk_origin.x + dim - 1);
+                let min_y = bottom_left.y.max(chunk_origin.y);
+                let max_y = top_right.y.min(chunk_origin.y + dim - 1);
+                let span = (max_x - min_x + 1) as usize;
+                let source_start = |world_y: isize| {
+                    ((world_y - bottom_left.y) * width as isize + (min_x - bottom_left.x)) as usize
+                };
+
+                if let Some(chunk) = self.loaded_chunks.get_mut(&coords) {
+                    let local_x = (min_x - chunk_origin.x) as TilesCount;
+                    for world_y in min_y..=max_y {
+                        let local_y = (world_y - chunk_origin.y) as TilesCount;
+                        match &source {
+                            RectSource::Fill(value) => chunk.grid.fill_run(local_x, local_y, span, *value),
+                            RectSource::Tiles(tiles) => {
+                                let src_start = source_start(world_y);
+                                chunk.grid.write_run(local_x, local_y, &tiles[src_start..src_start + span])
+                            }
+                        }
+                    }
+                    chunk.dirty = true;
+                    self.last_version += 1;
+                    chunk.version = self.last_version;
+                    self.written_this_frame.insert(coords);
+                    continue;
+                }
+
+                // Chunk not loaded: queue one compact rectangle for it
+                let span_height = (max_y - min_y + 1) as usize;
+                let data = match &source {
+                    RectSource::Fill(value) => PendingAreaData::Fill(*value),
+                    RectSource::Tiles(tiles) => {
+                        let mut clipped = Vec::with_capacity(span * span_height);
+                        for world_y in min_y..=max_y {
+                            let src_start = source_start(world_y);
+                            clipped.extend_from_slice(&tiles[src_start..src_start + span]);
+                        }
+                        PendingAreaData::Tiles(clipped)
+                    }
+                };
+                // Older single tile writes inside the rectangle are superseded
+                if let Some(writes) = self.write_queue.get_mut(&coords) {
+                    writes.retain(|p, _| p.x < min_x || p.x > max_x || p.y < min_y || p.y > max_y);
+                    if writes.is_empty() {
+                        self.write_queue.remove(&coords);
+                    }
+                }
+                self.pending_areas.entry(coords).or_default().push(PendingArea {
+                    bottom_left: Point { x: min_x, y: min_y },
+                    width: span,
+                    height: span_height,
+                    data,
+                });
+                self.requested_chunks.insert(coords);
+            }
+        }
+    }
+
+    /// Writes data to a specific world tile Point.
+    /// If the chunk is loaded (or dormant, which promotes it), the write is applied immediately.
+    /// If not, the write is queued for when the chunk is generated.
+    /// Either way it is recorded in the `modified` journal if `journal` is on.
+    pub fn write(&mut self, point: Point, value: P::Item) {
+        self.record_modified(point, value);
+        let chunk_coords = ChunkCoords::from_point(point, self.chunk_dimension_tiles);
+        self.promote(chunk_coords);
+        if let Some(chunk) = self.loaded_chunks.get_mut(&chunk_coords) {
+            let (local_x, local_y) = point.local_in_chunk(self.chunk_dimension_tiles);
+            chunk.grid.set_item(local_x, local_y, value);
+            chunk.dirty = true;
+            self.last_version += 1;
+            chunk.version = self.last_version;
+            self.written_this_frame.insert(chunk_coords);
+            // Remove from write queue if it was there and is now written
+            if let Some(writes) = self.write_queue.get_mut(&chunk_coords) {
+                writes.remove(&point);
+                if writes.is_empty() {
+                    self.write_queue.remove(&chunk_coords);
+                }
+            }
+        } else {
+            // Chunk not loaded, queue the write
+            self.write_queue.entry(chunk_coords).or_default().insert(point, value);
+            // Also request the chunk if it's not already
+            self.requested_chunks.insert(chunk_coords);
+        }
+    }
+
+    // Adds a journaled write to `modified` and to the delta of its chunk. Maps nobody saves skip
+    // both, they would grow with every write for the whole session
+    fn record_modified(&mut self, point: Point, value: P::Item) {
+        if !self.journal {
+            return;
+        }
+        self.modified.insert(point, value);
+        let dim = self.chunk_dimension_tiles;
+        let (local_x, local_y) = point.local_in_chunk(dim);
+        self.deltas
+            .entry(ChunkCoords::from_point(point, dim))
+            .or_default()
+            .set(local_y * dim + local_x, value);
+    }
+
+    /// Journaled writes since the last call, one delta per chunk, leaving none behind.
+    /// Always empty without `journal`.
+    /// Unlike `modified` they are meant to be shipped and forgotten, e.g. by a network sync.
+    pub fn drain_deltas(&mut self) -> HashMap<ChunkCoords, ChunkDelta<P::Item>> {
+        std::mem::take(&mut self.deltas)
+    }
+
+    /// Replays drained deltas through `write`: loaded chunks are written at once, unloaded ones
+    /// queue the tiles. They are journaled again like any other write.
+    pub fn apply_deltas(&mut self, deltas: HashMap<ChunkCoords, ChunkDelta<P::Item>>) {
+        let dim = self.chunk_dimension_tiles;
+        for (coords, delta) in deltas {
+            for (index, value) in delta.iter() {
+                let point = Point {
+                    x: coords.x * dim as isize + (index % dim) as isize,
+                    y: coords.y * dim as isize + (index / dim) as isize,
+                };
+                self.write(point, value);
+            }
+        }
+    }
+
+    /// Requests the square of chunks within `radius_chunks` around the chunk of `center`.
+    pub fn request_around(&mut self, center: Point, radius_chunks: usize) {
+        let center_chunk = ChunkCoords::from_point(center, self.chunk_dimension_tiles);
+        for coords in required_chunks(center_chunk, LoadShape::Square, radius_chunks) {
+            if !self.loaded_chunks.contains_key(&coords)
+                && !self.pending_tasks.contains_key(&coords)
+                && !self.failed_chunks.contains(&coords)
+            {
+                self.requested_chunks.insert(coords);
+            }
+        }
+    }
+
+    /// Whether the square of chunks within `radius_chunks` around the chunk of `center` is loaded.
+    /// Chunks given up on count as loaded, nothing would change by waiting for them.
+    pub fn is_loaded_around(&self, center: Point, radius_chunks: usize) -> bool {
+        let center_chunk = ChunkCoords::from_point(center, self.chunk_dimension_tiles);
+        required_chunks(center_chunk, LoadShape::Square, radius_chunks)
+            .all(|coords| self.loaded_chunks.contains_key(&coords) || self.failed_chunks.contains(&coords))
+    }
+
+    /// Chunks of `bootstrap_chunks` still requested or generating, and their total. Chunks whose
+    /// request was cancelled aren't waited for, nothing would load them.
+    pub fn bootstrap_progress(&self) -> (usize, usize) {
+        let remaining = self
+            .bootstrap_chunks
+            .iter()
+            .filter(|c| self.requested_chunks.contains(*c) || self.pending_tasks.contains_key(*c))
+            .count();
+        (remaining, self.bootstrap_chunks.len())
+    }
+
+    /// Initializes chunks in a Manhattan distance radius from the center (0,0).
+    /// This method is non-blocking and spawns generation requests.
+    pub fn init(&mut self, manhattan_distance_tiles: usize) {
+        let center_chunk = ChunkCoords { x: 0, y: 0 };
+        let center_point = Point { x: 0, y: 0 };
+
+        let chunk_manhattan_distance =
+            (manhattan_distance_tiles as f32 / self.chunk_dimension_tiles as f32).ceil() as usize;
+
+        for current_chunk_coords in
+            required_chunks(center_chunk, self.load_shape, chunk_manhattan_distance)
+        {
+            // Only request if not already loaded or pending
+            if !self.loaded_chunks.contains_key(&current_chunk_coords)
+                && !self.pending_tasks.contains_key(&current_chunk_coords)
+            {
+                self.requested_chunks.insert(current_chunk_coords);
+            }
+            self.bootstrap_chunks.insert(current_chunk_coords);
+        }
+        // info!(
+        //     "DataMap<{}> init requested chunks up to Manhattan distance {} (chunks: {})",
+        //     std::any::type_name::<P::Item>(),
+        //     manhattan_distance_tiles,
+        //     chunk_manhattan_distance
+        // );
+    }
+}
+
+impl<P: MapDataProducer> DataMap<P>
+where
+    P::Item: ScalarTileValue,
+{
+    /// Bilinearly interpolates the values of the four tile centers around `world_pos`,
+    /// without spawning any generation requests.
+    /// Returns `None` if any tile contributing to the result is not loaded.
+    /// Tiles with a zero weight are not read, so sampling exactly on a tile center
+    /// (or on the line between two centers) only needs those tiles.
+    pub fn sample_bilinear(&self, world_pos: Vec2) -> Option<f32> {
+        let fx = world_pos.x / TILE_SIZE_IN_UNITS;
+        let fy = world_pos.y / TILE_SIZE_IN_UNITS;
+        let x0 = fx.floor();
+        let y0 = fy.floor();
+        let tx = fx - x0;
+        let ty = fy - y0;
+
+        let corners = [
+            (0, 0, (1.0 - tx) * (1.0 - ty)),
+            (1, 0, tx * (1.0 - ty)),
+            (0, 1, (1.0 - tx) * ty),
+            (1, 1, tx * ty),
+        ];
+        let mut value = 0.0;
+        for (dx, dy, weight) in corners {
+            if weight == 0.0 {
+                continue;
+            }
+            let item = self.read(Point {
+                x: x0 as isize + dx,
+                y: y0 as isize + dy,
+            })?;
+            value += item.to_scalar() * weight;
+        }
+        Some(value)
+    }
+}
+
+/// Clips a pending rectangle to the area between `bottom_left` and `top_right` (inclusive).
+fn clip_pending_area<T: Copy>(
+    area: &PendingArea<T>,
+    bottom_left: Point,
+    top_right: Point,
+) -> Option<PendingArea<T>> {
+    let min_x = area.bottom_left.x.max(bottom_left.x);
+    let min_y = area.bottom_left.y.max(bottom_left.y);
+    let max_x = (area.bottom_left.x + area.width as isize - 1).min(top_right.x);
+    let max_y = (area.bottom_left.y + area.height as isize - 1).min(top_right.y);
+    if min_x > max_x || min_y > max_y {
+        return None;
+    }
+    let width = (max_x - min_x + 1) as usize;
+    let height = (max_y - min_y + 1) as usize;
+    let data = match &area.data {
+        PendingAreaData::Fill(value) => PendingAreaData::Fill(*value),
+        PendingAreaData::Tiles(_) => PendingAreaData::Tiles(
+            (min_y..=max_y)
+                .flat_map(|y| (min_x..=max_x).map(move |x| Point { x, y }))
+                .map(|p| area.get(p).unwrap())
+                .collect(),
+        ),
+    };
+    Some(PendingArea {
+        bottom_left: Point { x: min_x, y: min_y },
+        width,
+        height,
+        data,
+    })
+}
+
+impl<P: MapDataProducer> DataMap<P> {
+    /// Requests the chunks around every focus and unloads the ones no focus needs, according to `unload_policy`.
+    /// Each focus is a chunk, a radius in chunks (capped by `render_distance_chunks`), a priority and
+    /// a velocity; moving foci also require the areas around their `prefetch_centers`.
+    pub fn update_focus(&mut self, foci: &[(ChunkCoords, usize, u8, Vec2)]) {
+        // Union of the neighborhoods of all actors, so overlapping areas are only requested once
+        // and one actor never unloads what another one still needs
+        let mut required_chunks_set: HashSet<ChunkCoords> = HashSet::new();
+        // Prefetched areas keep their chunks from being evicted right after loading, like real foci
+        let mut focus_chunks: Vec<ChunkCoords> = Vec::with_capacity(foci.len());
+        for (focus_chunk, radius, _, velocity) in foci.iter() {
+            // The map's render distance caps the actor's radius
+            let radius = (*radius).min(self.render_distance_chunks);
+            required_chunks_set.extend(required_chunks(*focus_chunk, self.load_shape, radius));
+            focus_chunks.push(*focus_chunk);
+            for center in prefetch_centers(
+                *focus_chunk,
+                *velocity,
+                self.chunk_size_units,
+                self.prefetch_distance_chunks,
+                self.prefetch_min_speed,
+            ) {
+                required_chunks_set.extend(required_chunks(center, self.load_shape, radius));
+                focus_chunks.push(center);
+            }
+        }
+        let focus = foci.iter().map(|(c, _, priority, _)| (*c, *priority)).collect();
+        self.apply_required(required_chunks_set, focus_chunks, focus);
+    }
+
+    /// Requests exactly the chunks overlapping the window of `tiles` tiles around `center`, see
+    /// `window_chunks`, and unloads the others according to `unload_policy`.
+    pub fn update_window(&mut self, center: Point, tiles: TilesCount) {
+        let required: HashSet<ChunkCoords> = window_chunks(center, tiles, self.chunk_dimension_tiles).collect();
+        let center_chunk = ChunkCoords::from_point(center, self.chunk_dimension_tiles);
+        self.apply_required(required, vec![center_chunk], vec![(center_chunk, u8::MAX)]);
+    }
+
+    /// Shared tail of the focus updates: requests the missing `required_chunks_set`, unloads
+    /// what isn't needed around `focus_chunks` and remembers the foci for request priorities.
+    fn apply_required(
+        &mut self,
+        required_chunks_set: HashSet<ChunkCoords>,
+        focus_chunks: Vec<ChunkCoords>,
+        focus: Vec<(ChunkCoords, u8)>,
+    ) {
+        self.frame += 1;
+        let frame = self.frame;
+
+        // Request new chunks, refresh the LRU stamp of the loaded ones
+        for coords in required_chunks_set.iter() {
+            if let Some(chunk) = self.loaded_chunks.get_mut(coords) {
+                chunk.last_touched = frame;
+            } else if !self.pending_tasks.contains_key(coords) && // Don't request if already pending
+               !self.requested_chunks.contains(coords)
+            // Don't request if already in queue
+            {
+                self.requested_chunks.insert(*coords);
+            }
+        }
+
+        self.focus_centers = focus_chunks.clone();
+
+        // Unload chunks that are far from every actor
+        match self.unload_policy {
+            UnloadPolicy::Evict => {
+                self.evict_chunks(&focus_chunks);
+            }
+            UnloadPolicy::Immediate if !focus.is_empty() => {
+                let unneeded: Vec<ChunkCoords> = self
+                    .loaded_chunks
+                    .keys()
+                    .filter(|c| !required_chunks_set.contains(*c))
+                    .copied()
+                    .collect();
+                for coords in unneeded {
+                    self.unload_chunk(coords);
+                }
+            }
+            UnloadPolicy::Immediate => {} // Nobody is looking, nothing to decide on
+        }
+        if !focus.is_empty() {
+            self.evict_dormant();
+        }
+        // Re-prioritizes requests that haven't been serviced yet
+        self.focus = focus;
+    }
+}
+
+// --- Metrics ---
+
+const GENERATION_TIME_SMOOTHING: f32 = 0.1; // Weight of the newest sample in the rolling average
+
+/// What the chunk systems of `DataMap<P>` did so far. Counters are cumulative,
+/// `loaded`, `dormant` and `write_queue` are as of the last system run.
+#[derive(Resource, Debug)]
+pub struct ChunkStats<P: MapDataProducer> {
+    pub requested: u64,
+    pub tasks_spawned: u64,
+    pub tasks_completed: u64, // Failed generations included
+    pub evicted: u64,
+    pub cancelled: u64, // Requests dropped and tasks cancelled before completing, see `DataMap::cancel_irrelevant`
+    pub wasted: u64,    // Chunks completed after the foci had moved away, dropped unless cancellation is off
+    pub write_queue: usize,
+    pub loaded: usize,
+    pub loaded_bytes: usize, // Of the loaded chunks' grids, see `GridData::byte_size`
+    pub dormant: usize,
+    pub dormant_bytes: usize, // Of the dormant chunks' compressed grids, see `DataMap::set_compressor`
+    pub avg_generation_secs: f32, // Per chunk, exponential moving average over tasks, store loads included
+    _producer: PhantomData<P>,
+}
+
+impl<P: MapDataProducer> Default for ChunkStats<P> {
+    fn default() -> Self {
+        Self {
+            requested: 0,
+            tasks_spawned: 0,
+            tasks_completed: 0,
+            evicted: 0,
+            cancelled: 0,
+            wasted: 0,
+            write_queue: 0,
+            loaded: 0,
+            loaded_bytes: 0,
+            dormant: 0,
+            dormant_bytes: 0,
+            avg_generation_secs: 0.0,
+            _producer: PhantomData,
+        }
+    }
+}
+
+impl<P: MapDataProducer> ChunkStats<P> {
+    fn record_generation_time(&mut self, secs: f32) {
+        if self.tasks_completed == 0 {
+            self.avg_generation_secs = secs;
+        } else {
+            self.avg_generation_secs += (secs - self.avg_generation_secs) * GENERATION_TIME_SMOOTHING;
+        }
+        self.tasks_completed += 1;
+    }
+
+    // Runs `update_focus` and counts what it requested and evicted
+    fn track_focus_update(&mut self, data_map: &mut DataMap<P>, foci: &[(ChunkCoords, usize, u8, Vec2)]) {
+        self.track_update(data_map, |map| map.update_focus(foci));
+    }
+
+    /// Counts what `update` requested and evicted.
+    fn track_update(&mut self, data_map: &mut DataMap<P>, update: impl FnOnce(&mut DataMap<P>)) {
+        let requested_before = data_map.requested_chunks.len();
+        let unloaded_before = data_map.unloaded_this_frame.len();
+        update(data_map);
+        self.requested += data_map.requested_chunks.len().saturating_sub(requested_before) as u64;
+        self.evicted += data_map.unloaded_this_frame.len().saturating_sub(unloaded_before) as u64;
+        self.record_loaded(data_map);
+    }
+
+    fn record_loaded(&mut self, data_map: &DataMap<P>) {
+        self.loaded = data_map.loaded_chunks.len();
+        self.loaded_bytes = data_map.loaded_bytes();
+        self.dormant = data_map.dormant_chunks.len();
+        self.dormant_bytes = data_map.dormant_bytes();
+    }
+}
+
+/// When the generation task of a chunk was spawned.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct ChunkGenStarted(pub Instant);
+
+/// Updates the smoothed velocity of every focus. Runs once per frame for all maps, see `FocusMotionPlugin`.
+pub fn track_focus_motion_system(time: Res<Time>, mut query: Query<(&Transform, &mut FocusMotion)>) {
+    let dt = time.delta_secs();
+    for (transform, mut motion) in query.iter_mut() {
+        let position = transform.translation.xy();
+        if let Some(previous) = motion.previous.filter(|_| dt > 0.0) {
+            let displacement = position - previous;
+            if displacement.length() > FOCUS_JUMP_UNITS {
+                motion.velocity = Vec2::ZERO;
+            } else {
+                let velocity = displacement / dt;
+                let smoothed = motion.velocity;
+                motion.velocity = smoothed + (velocity - smoothed) * FOCUS_MOTION_SMOOTHING;
+            }
+        }
+        motion.previous = Some(position);
+    }
+}
+
+/// Tracks `FocusMotion`, added once by the first `insert_chunked_plugin`.
+pub struct FocusMotionPlugin;
+
+impl Plugin for FocusMotionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(First, track_focus_motion_system);
+    }
+}
+
+// System to manage loading/unloading based on a focus point (e.g., player/camera)
+pub fn data_map_load_unload_system<P: MapDataProducer>(
+    actor_query: Query<(&Transform, &MapRevealActor, Option<&FocusMotion>)>,
+    mut data_map: ResMut<DataMap<P>>,
+    mut stats: ResMut<ChunkStats<P>>,
+    mut distance_changes: EventReader<RenderDistanceChange<P>>,
+) {
+    if let Some(change) = distance_changes.read().last() {
+        data_map.set_render_distance(change.chunks);
+    }
+    if !matches!(data_map.focus_policy, FocusPolicy::FollowMapRevealActors { .. }) {
+        return;
+    }
+    let dimension = data_map.chunk_dimension_tiles;
+    let foci: Vec<(ChunkCoords, usize, u8, Vec2)> = actor_query
+        .iter()
+        .map(|(actor_transform, actor, motion)| {
+            let focus_chunk =
+                ChunkCoords::from_world(WorldPos(actor_transform.translation.xy()), dimension);
+            let velocity = motion.map_or(Vec2::ZERO, |m| m.velocity);
+            (focus_chunk, actor.radius_chunks, actor.priority, velocity)
+        })
+        .collect();
+    stats.track_focus_update(&mut data_map, &foci);
+}
+
+/// Same as `data_map_load_unload_system`, focused on the `Player` with the full render distance.
+/// Set `unload_policy` to `UnloadPolicy::Immediate` for the old drop-everything-out-of-range behavior.
+pub fn data_map_load_unload_system_for_player<P: MapDataProducer>(
+    player_query: Query<(&Transform, Option<&FocusMotion>), With<Player>>,
+    mut data_map: ResMut<DataMap<P>>,
+    mut stats: ResMut<ChunkStats<P>>,
+    mut distance_changes: EventReader<RenderDistanceChange<P>>,
+) {
+    if let Some(change) = distance_changes.read().last() {
+        data_map.set_render_distance(change.chunks);
+    }
+    let dimension = data_map.chunk_dimension_tiles;
+    let render_distance = data_map.render_distance_chunks;
+    let foci: Vec<(ChunkCoords, usize, u8, Vec2)> = player_query
+        .iter()
+        .map(|(player_transform, motion)| {
+            let focus_chunk =
+                ChunkCoords::from_world(WorldPos(player_transform.translation.xy()), dimension);
+            let velocity = motion.map_or(Vec2::ZERO, |m| m.velocity);
+            (focus_chunk, render_distance, 0, velocity)
+        })
+        .collect();
+    stats.track_focus_update(&mut data_map, &foci);
+}
+
+/// Load/unload system of maps with `FocusPolicy::FollowCameraWindow`: keeps exactly the chunks
+/// overlapping the window around the `FollowCamera` loaded, see `DataMap::update_window`.
+/// Nothing is requested or unloaded while there is no camera.
+pub fn data_map_camera_window_system<P: MapDataProducer>(
+    camera_query: Query<&Transform, With<FollowCamera>>,
+    mut data_map: ResMut<DataMap<P>>,
+    mut stats: ResMut<ChunkStats<P>>,
+) {
+    let FocusPolicy::FollowCameraWindow { tiles } = data_map.focus_policy else {
+        return;
+    };
+    let Ok(camera_transform) = camera_query.single() else {
+        return;
+    };
+    let center = WorldPos(camera_transform.translation.xy()).to_tile().0;
+    stats.track_update(&mut data_map, |map| map.update_window(center, tiles));
+}
+
+// System to spawn background tasks for requested chunks
+pub fn data_map_spawn_tasks_system<P: MapDataProducer>(
+    mut commands: Commands,
+    mut data_map: ResMut<DataMap<P>>,
+    mut stats: ResMut<ChunkStats<P>>,
+) {
+    let thread_pool = AsyncComputeTaskPool::get();
+
+    // Whatever the foci moved away from doesn't get (or keep) a task
+    let (cancelled_tasks, dropped_requests) = data_map.cancel_irrelevant_work();
+    stats.cancelled += (cancelled_tasks.len() + dropped_requests) as u64;
+    for entity in cancelled_tasks {
+        // Batches already completed were despawned, their results wait in `unapplied_results`
+        commands.entity(entity).try_despawn();
+    }
+
+    let mut new_pending_tasks = Vec::new(); // Collect tasks to add to pending_tasks map
+
+    let producer = Arc::new(data_map.producer.clone());
+
+    // Nearest chunks first, batched in that order, without exceeding the in-flight limit;
+    // the rest waits for the next frames
+    let batch_size = data_map.generation_batch_size.max(1);
+    let free_slots = data_map
+        .max_tasks_in_flight
+        .saturating_sub(data_map.tasks_in_flight());
+    let next_requests = data_map.next_requests(free_slots * batch_size);
+
+    for batch in next_requests.chunks(batch_size) {
+        let chunk_dimension = data_map.chunk_dimension_tiles;
+        let pr = producer.clone();
+        let store = data_map.store.clone();
+        let post_processors = data_map.post_processors.clone();
+        let jobs: Vec<_> = batch
+            .iter()
+            .map(|coords| {
+                let neighbors = (!post_processors.is_empty())
+                    .then(|| NeighborView::capture(&data_map, *coords));
+                (*coords, neighbors)
+            })
+            .collect();
+
+        // Previously saved chunks take precedence over freshly generated ones
+        let task = thread_pool.spawn(async move {
+            jobs.into_iter()
+                .map(|(current_coords, neighbors)| {
+                    let result = catch_generation_panic(|| {
+                        if let Some(mut chunk) = store.as_ref().and_then(|s| s.load_chunk(current_coords, chunk_dimension)) {
+                            chunk.from_store = true;
+                            return chunk;
+                        }
+                        let mut chunk = pr.generate_chunk(current_coords, chunk_dimension);
+                        if let Some(neighbors) = neighbors.as_ref() {
+                            for processor in post_processors.iter() {
+                                processor.process(current_coords, &mut chunk.grid, neighbors);
+                            }
+                        }
+                        chunk
+                    });
+                    (current_coords, result)
+                })
+                .collect()
+        });
+
+        let task_entity = commands
+            .spawn((ChunkGenTask(task), ChunkGenStarted(Instant::now())))
+            .id();
+
+        new_pending_tasks.extend(batch.iter().map(|coords| (*coords, task_entity)));
+        stats.tasks_spawned += 1;
+        // info!(
+        //     "Spawned DataMap<{}> gen task for chunk: {:?}",
+        //     std::any::type_name::<P::Item>(),
+        //     current_coords
+        // );
+    }
+
+    // Add new pending tasks to the map and drop serviced (or already satisfied) requests
+    for (coords, entity) in new_pending_tasks {
+        data_map.pending_tasks.insert(coords, entity);
+        data_map.requested_chunks.remove(&coords);
+    }
+    let DataMap {
+        requested_chunks,
+        loaded_chunks,
+        pending_tasks,
+        failed_chunks,
+        ..
+    } = &mut *data_map;
+    requested_chunks.retain(|c| {
+        !loaded_chunks.contains_key(c) && !pending_tasks.contains_key(c) && !failed_chunks.contains(c)
+    });
+}
+
+// System to process completed background tasks
+pub fn data_map_process_completed_tasks_system<P: MapDataProducer>(
+    mut commands: Commands,
+    mut query: ChunkGenTaskQuery<P::GridType>,
+    mut data_map: ResMut<DataMap<P>>,
+    mut stats: ResMut<ChunkStats<P>>,
+    mut loaded_events: EventWriter<ChunkLoaded<P>>,
+) {
+    // The rest stay pending, so a burst of completions is spread over frames. A finished batch
+    // is taken whole, the part over the budget is applied first thing next frame.
+    let budget = data_map.max_chunks_applied_per_frame;
+    for (task_entity, mut gen_task, started) in query.iter_mut() {
+        if data_map.unapplied_results.len() >= budget {
+            break;
+        }
+        if let Some(results) = future::block_on(future::poll_once(&mut gen_task.0)) {
+            commands.entity(task_entity).despawn(); // Remove the temporary task entity
+            let secs = started.map_or(0.0, |s| s.0.elapsed().as_secs_f32());
+            stats.record_generation_time(secs / results.len().max(1) as f32);
+            let results = results.into_iter().map(|(coords, result)| (task_entity, coords, result));
+            data_map.unapplied_results.extend(results);
+        }
+    }
+
+    let mut completed_chunks = Vec::new();
+    while completed_chunks.len() < budget {
+        let Some((task_entity, coords, result)) = data_map.unapplied_results.pop_front() else {
+            break;
+        };
+        // Cancelled meanwhile, and maybe requested again from another task
+        if data_map.pending_tasks.get(&coords) != Some(&task_entity) {
+            continue;
+        }
+        data_map.pending_tasks.remove(&coords);
+        match result {
+            Ok(generated_chunk) => {
+                data_map.generation_failures.remove(&coords);
+                completed_chunks.push((coords, generated_chunk));
+            }
+            Err(err) => data_map.record_generation_failure(coords, err),
+        }
+    }
+
+    // Apply completed chunks and pending writes
+    for (coords, chunk) in completed_chunks {
+        // The foci left while it was generated, it would only be evicted again
+        let unload_distance = data_map.unload_distance_chunks;
+        if !data_map.near_focus(coords, unload_distance) {
+            stats.wasted += 1;
+            if data_map.cancel_irrelevant {
+                continue;
+            }
+        }
+        // info!(
+        //     "DataMap<{}> chunk {:?} generated.",
+        //     std::any::type_name::<P::Item>(),
+        //     coords
+        // );
+        data_map.insert_chunk(coords, chunk);
+        loaded_events.write(ChunkLoaded::new(coords));
+    }
+
+    // Dormant chunks requested again load without a task, within what's left of the budget
+    let remaining = budget.saturating_sub(data_map.promoted_this_frame.len());
+    data_map.promote_requested(remaining);
+    if !data_map.promoted_this_frame.is_empty() {
+        loaded_events.write_batch(data_map.promoted_this_frame.drain(..).map(ChunkLoaded::new));
+    }
+
+    stats.record_loaded(&data_map);
+    stats.write_queue = data_map.queued_write_count();
+}
+
+/// Logs a one-line summary of `ChunkStats<P>` every `interval_secs`. Not added by default.
+pub fn insert_chunk_stats_logging<P: MapDataProducer>(app: &mut App, interval_secs: f32) -> &mut App {
+    app.add_systems(
+        Update,
+        move |stats: Res<ChunkStats<P>>, time: Res<Time<Real>>, mut timer: Local<Option<Timer>>| {
+            let timer = timer.get_or_insert_with(|| Timer::from_seconds(interval_secs, TimerMode::Repeating));
+            if !timer.tick(time.delta()).just_finished() {
+                return;
+            }
+            info!(
+                "DataMap<{}>: loaded {} ({} KiB) dormant {} ({} KiB) requested {} spawned {} completed {} evicted {} cancelled {} wasted {} queued writes {} avg generation {:.1} ms",
+                std::any::type_name::<P::Item>(),
+                stats.loaded,
+                stats.loaded_bytes / 1024,
+                stats.dormant,
+                stats.dormant_bytes / 1024,
+                stats.requested,
+                stats.tasks_spawned,
+                stats.tasks_completed,
+                stats.evicted,
+                stats.cancelled,
+                stats.wasted,
+                stats.write_queue,
+                stats.avg_generation_secs * 1000.0
+            );
+        },
+    )
+}
+
+// System to turn the writes and evictions recorded by DataMap into events
+pub fn data_map_events_system<P: MapDataProducer>(
+    mut data_map: ResMut<DataMap<P>>,
+    mut written_events: EventWriter<ChunkWritten<P>>,
+    mut unloaded_events: EventWriter<ChunkUnloaded<P>>,
+) {
+    if !data_map.written_this_frame.is_empty() {
+        written_events.write_batch(data_map.written_this_frame.drain().map(ChunkWritten::new));
+    }
+    if !data_map.unloaded_this_frame.is_empty() {
+        unloaded_events.write_batch(data_map.unloaded_this_frame.drain(..).map(ChunkUnloaded::new));
+    }
+}
+
+// System to save all modified chunks when the app is closing
+pub fn data_map_flush_on_exit_system<P: MapDataProducer>(
+    mut exit_events: EventReader<AppExit>,
+    mut data_map: ResMut<DataMap<P>>,
+) {
+    if exit_events.read().next().is_some() {
+        data_map.flush();
+    }
+}
+
+/// Reaches a `DataMap<P>` through the World without naming `P`, see `ChunkPreloadHooks`.
+#[derive(Clone, Copy)]
+pub struct ChunkPreloadHook {
+    pub name: &'static str,
+    pub request: fn(&mut World, Point, usize), // DataMap::request_around
+    pub is_ready: fn(&World, Point, usize) -> bool, // DataMap::is_loaded_around
+    pub bootstrap_progress: fn(&World) -> (usize, usize), // DataMap::bootstrap_progress
+}
+
+/// One hook per map registered by `insert_chunked_plugin`, for preloading an area in all of them
+/// and for the startup progress of `WorldBootstrap`.
+#[derive(Resource, Default, Clone)]
+pub struct ChunkPreloadHooks(pub Vec<ChunkPreloadHook>);
+
+impl ChunkPreloadHooks {
+    pub fn request_all(&self, world: &mut World, center: Point, radius_chunks: usize) {
+        for hook in self.0.iter() {
+            (hook.request)(world, center, radius_chunks);
+        }
+    }
+
+    pub fn all_ready(&self, world: &World, center: Point, radius_chunks: usize) -> bool {
+        self.0.iter().all(|hook| (hook.is_ready)(world, center, radius_chunks))
+    }
+
+    /// Chunks requested by `DataMap::init` still missing and their total, over all maps.
+    pub fn bootstrap_progress(&self, world: &World) -> (usize, usize) {
+        self.0
+            .iter()
+            .map(|hook| (hook.bootstrap_progress)(world))
+            .fold((0, 0), |(remaining, total), (r, t)| (remaining + r, total + t))
+    }
+}
+
+fn preload_hook<P: MapDataProducer>() -> ChunkPreloadHook {
+    ChunkPreloadHook {
+        name: std::any::type_name::<P>(),
+        request: |world, center, radius_chunks| {
+            if let Some(mut map) = world.get_resource_mut::<DataMap<P>>() {
+                map.request_around(center, radius_chunks);
+            }
+        },
+        is_ready: |world, center, radius_chunks| {
+            world
+                .get_resource::<DataMap<P>>()
+                .is_none_or(|map| map.is_loaded_around(center, radius_chunks))
+        },
+        bootstrap_progress: |world| {
+            world
+                .get_resource::<DataMap<P>>()
+                .map_or((0, 0), |map| map.bootstrap_progress())
+        },
+    }
+}
+
+// --- Memory budget ---
+
+/// Upper bound on the memory of the loaded chunks of all maps together. Over it, each map
+/// evicts its farthest chunks in proportion to its share of the total, see `DataMap::evict_farthest`.
+/// Chunks within a map's render distance of a focus are never evicted, so the total may stay over.
+/// Dormant chunks are a separate pool with its own bound, their farthest are dropped the same way.
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct ChunkMemoryBudget {
+    pub max_bytes: usize,
+    pub max_dormant_bytes: usize, // Compressed, see `DataMap::set_compressor`
+}
+
+impl Default for ChunkMemoryBudget {
+    fn default() -> Self {
+        Self {
+            max_bytes: usize::MAX, // Unlimited
+            max_dormant_bytes: usize::MAX,
+        }
+    }
+}
+
+/// Reaches a `DataMap<P>` through the World without naming `P`, see `ChunkMemoryHooks`.
+#[derive(Clone, Copy)]
+pub struct ChunkMemoryHook {
+    pub name: &'static str,
+    pub loaded_bytes: fn(&World) -> usize,          // DataMap::loaded_bytes
+    pub evict_farthest: fn(&mut World, usize) -> usize, // DataMap::evict_farthest, counted in ChunkStats
+    pub dormant_bytes: fn(&World) -> usize,                 // DataMap::dormant_bytes
+    pub evict_farthest_dormant: fn(&mut World, usize) -> usize, // DataMap::evict_farthest_dormant
+}
+
+/// One hook per map registered by `insert_chunked_plugin`, for enforcing the `ChunkMemoryBudget`.
+#[derive(Resource, Default, Clone)]
+pub struct ChunkMemoryHooks(pub Vec<ChunkMemoryHook>);
+
+fn memory_hook<P: MapDataProducer>() -> ChunkMemoryHook {
+    ChunkMemoryHook {
+        name: std::any::type_name::<P>(),
+        loaded_bytes: |world| world.get_resource::<DataMap<P>>().map_or(0, |map| map.loaded_bytes()),
+        evict_farthest: |world, bytes| {
+            let Some(mut map) = world.get_resource_mut::<DataMap<P>>() else {
+                return 0;
+            };
+            let loaded_before = map.loaded_chunks.len();
+            let freed = map.evict_farthest(bytes);
+            let evicted = loaded_before - map.loaded_chunks.len();
+            let (dormant, dormant_bytes) = (map.dormant_chunks.len(), map.dormant_bytes());
+            let (loaded, loaded_bytes) = (map.loaded_chunks.len(), map.loaded_bytes());
+            if let Some(mut stats) = world.get_resource_mut::<ChunkStats<P>>() {
+                stats.evicted += evicted as u64;
+                stats.loaded = loaded;
+                stats.loaded_bytes = loaded_bytes;
+                stats.dormant = dormant;
+                stats.dormant_bytes = dormant_bytes;
+            }
+            freed
+        },
+        dormant_bytes: |world| world.get_resource::<DataMap<P>>().map_or(0, |map| map.dormant_bytes()),
+        evict_farthest_dormant: |world, bytes| {
+            let Some(mut map) = world.get_resource_mut::<DataMap<P>>() else {
+                return 0;
+            };
+            let freed = map.evict_farthest_dormant(bytes);
+            let (dormant, dormant_bytes) = (map.dormant_chunks.len(), map.dormant_bytes());
+            if let Some(mut stats) = world.get_resource_mut::<ChunkStats<P>>() {
+                stats.dormant = dormant;
+                stats.dormant_bytes = dormant_bytes;
+            }
+            freed
+        },
+    }
+}
+
+/// Evicts chunks of every map while their total memory is over the `ChunkMemoryBudget`.
+/// Each map frees the part of the excess matching its part of the total. Loaded chunks go
+/// first, the dormant ones they turn into are counted in the dormant pool right after.
+pub fn chunk_memory_budget_system(world: &mut World) {
+    let Some(budget) = world.get_resource::<ChunkMemoryBudget>().copied() else {
+        return;
+    };
+    let Some(hooks) = world.get_resource::<ChunkMemoryHooks>().cloned() else {
+        return;
+    };
+    let loaded: Vec<_> = hooks.0.iter().map(|hook| (hook.loaded_bytes, hook.evict_farthest)).collect();
+    enforce_memory_pool(world, &loaded, budget.max_bytes);
+    let dormant: Vec<_> = hooks
+        .0
+        .iter()
+        .map(|hook| (hook.dormant_bytes, hook.evict_farthest_dormant))
+        .collect();
+    enforce_memory_pool(world, &dormant, budget.max_dormant_bytes);
+}
+
+// Shares the excess of one pool over `max_bytes` between the maps, given their usage and eviction hooks
+#[allow(clippy::type_complexity)]
+fn enforce_memory_pool(
+    world: &mut World,
+    pool: &[(fn(&World) -> usize, fn(&mut World, usize) -> usize)],
+    max_bytes: usize,
+) {
+    let usage: Vec<usize> = pool.iter().map(|(bytes, _)| bytes(world)).collect();
+    let total: usize = usage.iter().sum();
+    if total <= max_bytes {
+        return;
+    }
+    let excess = total - max_bytes;
+    for ((_, evict), bytes) in pool.iter().zip(usage) {
+        // Rounded up, so the shares add up to at least the excess
+        let share = (excess as u128 * bytes as u128).div_ceil(total as u128) as usize;
+        if share > 0 {
+            evict(world, share);
+        }
+    }
+}
+
+/// Enforces the `ChunkMemoryBudget`, added once by the first `insert_chunked_plugin`. Runs in
+/// `Last`, the evicted chunks' `ChunkUnloaded` events go out with the next frame's.
+pub struct ChunkMemoryBudgetPlugin;
+
+impl Plugin for ChunkMemoryBudgetPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ChunkMemoryBudget>()
+            .init_resource::<ChunkMemoryHooks>()
+            .add_systems(Last, chunk_memory_budget_system);
+    }
+}
+
+/// How `insert_chunked_plugin` sets up a `DataMap<P>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkedMapConfig {
+    pub chunk_dimension_tiles: TilesCount,
+    pub focus: FocusPolicy, // Which chunks get loaded, the radius of the actors included
+    pub init_radius_tiles: TilesCount, // Manhattan distance around the origin requested at startup
+    pub unload: UnloadPolicy,
+}
+
+impl ChunkedMapConfig {
+    /// Same checks as `DataMap::try_new`.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        tiles_to_units("chunk_dimension_tiles", self.chunk_dimension_tiles).map(|_| ())
+    }
+}
+
+impl Default for ChunkedMapConfig {
+    fn default() -> Self {
+        Self {
+            chunk_dimension_tiles: DEFAULT_CHUNK_DIMENSION_TILES,
+            focus: FocusPolicy::FollowMapRevealActors {
+                radius_chunks: DEFAULT_RENDER_DISTANCE_CHUNKS,
+            },
+            init_radius_tiles: 50,
+            unload: UnloadPolicy::default(),
+        }
+    }
+}
+
+/// Load, generation and event systems of `DataMap<P>`, for ordering other systems after them.
+/// The chunk systems run in PreUpdate, so everything in Update already sees the chunks completed
+/// this frame; systems in PreUpdate that read the map should be `.after(ChunkSystems::<P>::default())`.
+#[derive(SystemSet)]
+pub struct ChunkSystems<P: MapDataProducer>(PhantomData<P>);
+
+impl<P: MapDataProducer> Default for ChunkSystems<P> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<P: MapDataProducer> Clone for ChunkSystems<P> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<P: MapDataProducer> Copy for ChunkSystems<P> {}
+
+impl<P: MapDataProducer> PartialEq for ChunkSystems<P> {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl<P: MapDataProducer> Eq for ChunkSystems<P> {}
+
+impl<P: MapDataProducer> Hash for ChunkSystems<P> {
+    fn hash<H: std::hash::Hasher>(&self, _state: &mut H) {}
+}
+
+impl<P: MapDataProducer> Debug for ChunkSystems<P> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ChunkSystems<{}>", std::any::type_name::<P>())
+    }
+}
+
+/// Registers `DataMap<P>` built from `config`, with its systems in `ChunkSystems::<P>`.
+/// Debug layers are added by the game, see `game::debug::insert_chunked_plugin_with_debug`.
+/// The config is the single source of truth: a `DataMap<P>` inserted earlier is replaced.
+/// Panics on a config `ChunkedMapConfig::validate` rejects.
+pub fn insert_chunked_plugin<P>(
+    app: &mut bevy::prelude::App,
+    producer: P,
+    config: ChunkedMapConfig,
+) -> &mut bevy::prelude::App
+where
+    P: MapDataProducer + Send + Sync + Clone + 'static,
+    <P as MapDataProducer>::GridType: Send + Sync,
+    <P as MapDataProducer>::Item: Send + Copy + Default + Sync,
+{
+    if let Err(err) = config.validate() {
+        panic!("insert_chunked_plugin::<{}>: {err}", std::any::type_name::<P>());
+    }
+    if app.world().contains_resource::<DataMap<P>>() {
+        warn!(
+            "DataMap<{}> was inserted before insert_chunked_plugin, replacing it with the configured one",
+            std::any::type_name::<P>()
+        );
+    }
+    if !app.is_plugin_added::<FocusMotionPlugin>() {
+        app.add_plugins(FocusMotionPlugin);
+    }
+    if !app.is_plugin_added::<ChunkMemoryBudgetPlugin>() {
+        app.add_plugins(ChunkMemoryBudgetPlugin);
+    }
+    let init_radius_tiles = config.init_radius_tiles;
+    app.add_systems(
+        Startup,
+        move |mut map: ResMut<DataMap<P>>, seed: Option<Res<WorldSeed>>| {
+            if let Some(seed) = seed {
+                map.producer = map.producer.with_seed(seed.0);
+            }
+            map.init(init_radius_tiles)
+        },
+    );
+    let mut data_map = DataMap::<P>::new(
+        producer,
+        config.chunk_dimension_tiles,
+        config.focus.render_distance_chunks(config.chunk_dimension_tiles),
+    );
+    data_map.unload_policy = config.unload;
+    data_map.focus_policy = config.focus;
+    app.init_resource::<WorldLayout>();
+    app.world_mut().resource_mut::<WorldLayout>().maps.push(MapLayout {
+        name: short_type_name::<P>(),
+        chunk_dimension_tiles: data_map.chunk_dimension_tiles,
+        chunk_size_units: data_map.chunk_size_units,
+        render_distance_chunks: data_map.render_distance_chunks,
+    });
+    app.init_resource::<ChunkPreloadHooks>();
+    app.world_mut().resource_mut::<ChunkPreloadHooks>().0.push(preload_hook::<P>());
+    app.world_mut().resource_mut::<ChunkMemoryHooks>().0.push(memory_hook::<P>());
+    app.insert_resource(data_map)
+    .init_resource::<ChunkStats<P>>()
+    .add_event::<ChunkLoaded<P>>()
+    .add_event::<ChunkUnloaded<P>>()
+    .add_event::<ChunkWritten<P>>()
+    .add_event::<RenderDistanceChange<P>>()
+    // Completed chunks are inserted before anything in Update reads the map, and requests made
+    // during the previous frame (by game systems or the new foci) are spawned in the same pass
+    .add_systems(
+        PreUpdate,
+        (
+            data_map_process_completed_tasks_system::<P>,
+            // Each returns right away unless the map's `focus_policy` is its own
+            data_map_load_unload_system::<P>,
+            data_map_camera_window_system::<P>,
+            data_map_spawn_tasks_system::<P>,
+        )
+            .chain()
+            .in_set(ChunkSystems::<P>::default()),
+    )
+    .add_systems(PostUpdate, data_map_events_system::<P>.in_set(ChunkSystems::<P>::default()))
+    .add_systems(Last, data_map_flush_on_exit_system::<P>)
+}
+
+// --- Entities living as long as their chunk ---
+
+/// Spawns the entities of a chunk of `DataMap<P>` when it loads. They are despawned when the chunk
+/// unloads, see `insert_chunk_spawner_plugin`.
+pub trait ChunkSpawner<P: MapDataProducer>: Send + Sync + 'static {
+    fn spawn_for_chunk(
+        &self,
+        commands: &mut Commands,
+        coords: ChunkCoords,
+        chunk: &DataChunk<P::GridType>,
+    ) -> Vec<Entity>;
+}
+
+/// The spawners of `DataMap<P>`. Spawners pushed after chunks were loaded catch up on those chunks
+/// the next time `chunk_entities_system` runs.
+#[derive(Resource)]
+pub struct ChunkSpawners<P: MapDataProducer> {
+    pub spawners: Vec<Box<dyn ChunkSpawner<P>>>,
+    caught_up: usize, // Spawners before this already ran on every loaded chunk
+}
+
+impl<P: MapDataProducer> Default for ChunkSpawners<P> {
+    fn default() -> Self {
+        Self {
+            spawners: Vec::new(),
+            caught_up: 0,
+        }
+    }
+}
+
+impl<P: MapDataProducer> ChunkSpawners<P> {
+    pub fn add(&mut self, spawner: impl ChunkSpawner<P>) {
+        self.spawners.push(Box::new(spawner));
+    }
+}
+
+/// Entities spawned for each loaded chunk of `DataMap<P>` by its `ChunkSpawners<P>`.
+#[derive(Resource)]
+pub struct ChunkEntities<P: MapDataProducer> {
+    pub entities: HashMap<ChunkCoords, Vec<Entity>>,
+    _producer: PhantomData<P>,
+}
+
+impl<P: MapDataProducer> Default for ChunkEntities<P> {
+    fn default() -> Self {
+        Self {
+            entities: HashMap::new(),
+            _producer: PhantomData,
+        }
+    }
+}
+
+impl<P: MapDataProducer> ChunkEntities<P> {
+    pub fn get(&self, coords: ChunkCoords) -> &[Entity] {
+        self.entities.get(&coords).map_or(&[], Vec::as_slice)
+    }
+
+    /// Despawns and forgets the entities of a chunk.
+    fn despawn(&mut self, commands: &mut Commands, coords: ChunkCoords) {
+        for entity in self.entities.remove(&coords).into_iter().flatten() {
+            // Game logic may have despawned it already
+            commands.entity(entity).try_despawn();
+        }
+    }
+
+    fn spawn(
+        &mut self,
+        commands: &mut Commands,
+        spawners: &[Box<dyn ChunkSpawner<P>>],
+        coords: ChunkCoords,
+        chunk: &DataChunk<P::GridType>,
+    ) {
+        let entities = self.entities.entry(coords).or_default();
+        for spawner in spawners {
+            entities.extend(spawner.spawn_for_chunk(commands, coords, chunk));
+        }
+    }
+}
+
+/// Spawns the entities of chunks loaded this frame and despawns the ones of unloaded chunks.
+/// Events are checked against the map, so a chunk unloaded and loaded again in between is
+/// spawned once, and a chunk inserted again (e.g. when its post-processors re-run) is respawned.
+pub fn chunk_entities_system<P: MapDataProducer>(
+    mut commands: Commands,
+    data_map: Res<DataMap<P>>,
+    mut spawners: ResMut<ChunkSpawners<P>>,
+    mut chunk_entities: ResMut<ChunkEntities<P>>,
+    mut loaded_events: EventReader<ChunkLoaded<P>>,
+    mut unloaded_events: EventReader<ChunkUnloaded<P>>,
+) {
+    for event in unloaded_events.read() {
+        if !data_map.loaded_chunks.contains_key(&event.coords) {
+            chunk_entities.despawn(&mut commands, event.coords);
+        }
+    }
+    let ChunkSpawners { spawners, caught_up } = &mut *spawners;
+    for event in loaded_events.read() {
+        let Some(chunk) = data_map.loaded_chunks.get(&event.coords) else {
+            continue; // Evicted right away, its unload event is still to come
+        };
+        chunk_entities.despawn(&mut commands, event.coords);
+        chunk_entities.spawn(&mut commands, &spawners[..*caught_up], event.coords, chunk);
+    }
+    if *caught_up < spawners.len() {
+        for (coords, chunk) in data_map.loaded_chunks.iter() {
+            chunk_entities.spawn(&mut commands, &spawners[*caught_up..], *coords, chunk);
+        }
+        *caught_up = spawners.len();
+    }
+}
+
+/// Adds a spawner for the chunks of `DataMap<P>`, which must be registered with `insert_chunked_plugin`.
+/// The first spawner of a map also registers `ChunkEntities<P>` and `chunk_entities_system::<P>`.
+pub fn insert_chunk_spawner_plugin<P: MapDataProducer>(
+    app: &mut App,
+    spawner: impl ChunkSpawner<P>,
+) -> &mut App {
+    if !app.world().contains_resource::<ChunkSpawners<P>>() {
+        app.init_resource::<ChunkSpawners<P>>()
+            .init_resource::<ChunkEntities<P>>()
+            .add_systems(
+                PreUpdate,
+                chunk_entities_system::<P>.after(ChunkSystems::<P>::default()),
+            );
+    }
+    app.world_mut().resource_mut::<ChunkSpawners<P>>().add(spawner);
+    app
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIMENSION: TilesCount = 8;
+
+    /// Every generated tile holds its own world coordinates, so misplaced copies show.
+    #[derive(Clone, Default)]
+    struct CoordsProducer;
+
+    impl MapDataProducer for CoordsProducer {
+        type Item = (isize, isize);
+        type GridType = FlatGrid<(isize, isize)>;
+
+        fn default_value(&self) -> (isize, isize) {
+            (isize::MIN, isize::MIN)
+        }
+
+        fn generate_chunk(&self, coords: ChunkCoords, dimension_tiles: TilesCount) -> DataChunk<Self::GridType> {
+            let origin = coords.to_bottom_left_tile_point(dimension_tiles);
+            DataChunk::new(grid_from_fn(dimension_tiles, |x, y| {
+                (origin.x + x as isize, origin.y + y as isize)
+            }))
+        }
+    }
+
+    fn map() -> DataMap<CoordsProducer> {
+        DataMap::new(CoordsProducer, DIMENSION, 1)
+    }
+
+    fn grid_from_fn<T: Copy + Debug + Default + Send + Sync + 'static>(
+        dimension: TilesCount,
+        f: impl Fn(TilesCount, TilesCount) -> T,
+    ) -> FlatGrid<T> {
+        let mut grid = FlatGrid::new(dimension, T::default());
+        for y in 0..dimension {
+            for x in 0..dimension {
+                grid.set_item(x, y, f(x, y));
+            }
+        }
+        grid
+    }
+
+    /// Generates and inserts a chunk, applying the queued writes like the completion system does.
+    fn load(map: &mut DataMap<CoordsProducer>, coords: ChunkCoords) {
+        let chunk = map.producer.generate_chunk(coords, map.chunk_dimension_tiles);
+        map.insert_chunk(coords, chunk);
+    }
+
+    fn chunk(x: isize, y: isize) -> ChunkCoords {
+        ChunkCoords { x, y }
+    }
+
+    fn tile(x: isize, y: isize) -> Point {
+        Point { x, y }
+    }
+
+    #[test]
+    fn read_area_inside_one_chunk() {
+        let mut map = map();
+        load(&mut map, chunk(0, 0));
+        let area = map.read_area(tile(2, 3), 4, 2).unwrap();
+        assert_eq!((area.width(), area.height()), (4, 2));
+        assert_eq!(area.get_item(0, 0), Some(&(2, 3)));
+        assert_eq!(area.get_item(3, 1), Some(&(5, 4)));
+    }
+
+    #[test]
+    fn read_area_across_chunk_boundaries_with_writes() {
+        let mut map = map();
+        for coords in [chunk(-1, -1), chunk(0, -1), chunk(-1, 0), chunk(0, 0)] {
+            load(&mut map, coords);
+        }
+        map.write(tile(10, 10), (7, 7)); // Queued, its chunk isn't loaded
+        map.loaded_chunks.remove(&chunk(0, 0));
+        map.write(tile(1, 1), (9, 9)); // Queued over the now unloaded chunk
+        load(&mut map, chunk(0, 0));
+
+        let area = map.read_area(tile(-4, -4), 8, 8).unwrap();
+        for y in 0..8 {
+            for x in 0..8 {
+                let expected = if (x, y) == (5, 5) { (9, 9) } else { (x as isize - 4, y as isize - 4) };
+                assert_eq!(area.get_item(x, y), Some(&expected), "tile {x}, {y}");
+            }
+        }
+
+        // A write still queued for an unloaded chunk shows in `get_area`
+        let area = map.get_area(tile(8, 8), 4, 4);
+        assert_eq!(area.get_item(2, 2), Some(&(7, 7)));
+    }
+
+    #[test]
+    fn read_area_with_an_unloaded_chunk() {
+        let mut map = map();
+        load(&mut map, chunk(0, 0));
+        assert!(map.read_area(tile(4, 0), 8, 2).is_none());
+        assert!(map.requested_chunks.is_empty());
+
+        let area = map.get_area(tile(4, 0), 8, 2);
+        assert_eq!(area.get_item(3, 1), Some(&(7, 1)));
+        assert_eq!(area.get_item(4, 1), Some(&CoordsProducer.default_value()));
+        assert_eq!(map.requested_chunks.len(), 1);
+        assert!(map.requested_chunks.contains(&chunk(1, 0)));
+    }
+
+    impl TileBytes for u8 {
+        const SIZE: usize = 1;
+
+        fn write_bytes(&self, out: &mut Vec<u8>) {
+            out.push(*self);
+        }
+
+        fn read_bytes(bytes: &[u8]) -> Self {
+            bytes[0]
+        }
+    }
+
+    #[test]
+    fn fs_store_rejects_chunks_saved_with_another_dimension() {
+        let directory = std::env::temp_dir().join(format!("rust_sim_fs_store_{}", std::process::id()));
+        let store = FsChunkStore::new(&directory);
+        let saved = DataChunk::new(grid_from_fn(DIMENSION, |x, y| (x + y * DIMENSION) as u8));
+        store.save_chunk(chunk(1, -1), &saved);
+
+        let loaded: Option<DataChunk<FlatGrid<u8>>> = store.load_chunk(chunk(1, -1), DIMENSION);
+        assert_eq!(loaded.map(|chunk| chunk.grid.as_slice().to_vec()), Some(saved.grid.as_slice().to_vec()));
+        let resized: Option<DataChunk<FlatGrid<u8>>> = store.load_chunk(chunk(1, -1), 2 * DIMENSION);
+        assert!(resized.is_none(), "loaded a chunk saved at another size");
+        let missing: Option<DataChunk<FlatGrid<u8>>> = store.load_chunk(chunk(0, 0), DIMENSION);
+        assert!(missing.is_none());
+        let _ = fs::remove_dir_all(&directory);
+    }
+
+    /// What the load/unload system does for one actor standing in `center`.
+    fn focus(map: &mut DataMap<CoordsProducer>, center: ChunkCoords, radius: usize) {
+        map.frame += 1;
+        let radius = radius as isize;
+        for y in center.y - radius..=center.y + radius {
+            for x in center.x - radius..=center.x + radius {
+                match map.loaded_chunks.get_mut(&chunk(x, y)) {
+                    Some(loaded) => loaded.last_touched = map.frame,
+                    None => {
+                        map.requested_chunks.insert(chunk(x, y));
+                    }
+                }
+            }
+        }
+        map.evict_chunks(&[center]);
+        map.focus = vec![(center, 0)];
+    }
+
+    /// One load/unload pass around a still actor, then every requested chunk is generated.
+    fn step(map: &mut DataMap<CoordsProducer>, center: ChunkCoords) {
+        focus(map, center, map.render_distance_chunks);
+        for coords in std::mem::take(&mut map.requested_chunks) {
+            load(map, coords);
+        }
+    }
+
+    #[test]
+    fn requests_follow_the_focus_that_moved_before_they_were_serviced() {
+        let mut map = DataMap::new(CoordsProducer, DIMENSION, 2);
+        focus(&mut map, chunk(0, 0), 2);
+        let next = map.next_requests(25);
+        assert_eq!(next.len(), 25);
+        assert_eq!(next[0], chunk(0, 0));
+        assert!(next[..9].iter().all(|c| c.x.abs() <= 1 && c.y.abs() <= 1), "{next:?}");
+
+        // Nothing was generated yet, the new neighborhood goes first and the old one last
+        let far = chunk(100, 0);
+        focus(&mut map, far, 2);
+        let next = map.next_requests(usize::MAX);
+        assert_eq!(next.len(), 50);
+        assert_eq!(next[0], far);
+        for coords in &next[..25] {
+            assert!((coords.x - far.x).abs() <= 2 && coords.y.abs() <= 2, "{coords:?} before the new neighborhood");
+        }
+    }
+
+    #[test]
+    fn chunks_at_render_distance_are_not_thrashed() {
+        let mut map = map();
+        step(&mut map, chunk(0, 0));
+        step(&mut map, chunk(1, 0));
+        let loaded = map.loaded_chunks.len();
+        assert_eq!(loaded, 12); // Two overlapping 3x3 squares
+
+        // Walking back and forth over a chunk border keeps everything, nothing is requested again
+        for frame in 0..10 {
+            focus(&mut map, chunk(frame % 2, 0), 1);
+            assert!(map.requested_chunks.is_empty(), "frame {frame}");
+            assert_eq!(map.loaded_chunks.len(), loaded, "frame {frame}");
+        }
+    }
+
+    #[test]
+    fn chunks_beyond_unload_distance_go_a_few_per_frame() {
+        let mut map = map();
+        map.max_evictions_per_frame = 4;
+        step(&mut map, chunk(0, 0));
+        assert_eq!(map.loaded_chunks.len(), 9);
+
+        let far = chunk(map.unload_distance_chunks as isize + 2, 0);
+        focus(&mut map, far, 1);
+        assert_eq!(map.loaded_chunks.len(), 5);
+        focus(&mut map, far, 1);
+        assert_eq!(map.loaded_chunks.len(), 1);
+        focus(&mut map, far, 1);
+        assert!(map.loaded_chunks.is_empty());
+    }
+
+    #[test]
+    fn max_loaded_chunks_evicts_inside_the_unload_distance() {
+        let mut map = map();
+        step(&mut map, chunk(0, 0));
+        load(&mut map, chunk(2, 0)); // Within the unload distance, kept without the cap
+        load(&mut map, chunk(2, 1));
+        step(&mut map, chunk(0, 0));
+        assert_eq!(map.loaded_chunks.len(), 11);
+
+        map.max_loaded_chunks = 9;
+        step(&mut map, chunk(0, 0));
+        assert_eq!(map.loaded_chunks.len(), 9);
+        assert!(!map.loaded_chunks.contains_key(&chunk(2, 0)));
+        assert!(!map.loaded_chunks.contains_key(&chunk(2, 1)));
+    }
+
+    #[test]
+    fn shrinking_the_render_distance_evicts_over_several_frames() {
+        let mut map = DataMap::new(CoordsProducer, DIMENSION, 6);
+        step(&mut map, chunk(0, 0));
+        assert_eq!(map.loaded_chunks.len(), 13 * 13);
+
+        map.set_render_distance(3);
+        assert_eq!(map.loaded_chunks.len(), 13 * 13, "nothing goes before the next pass");
+        assert_eq!(map.unload_distance_chunks, 5); // The margin of 2 is kept
+        let mut frames = 0;
+        let mut farthest_left = 6;
+        while map.loaded_chunks.len() > 11 * 11 {
+            let before: HashSet<ChunkCoords> = map.loaded_chunks.keys().copied().collect();
+            step(&mut map, chunk(0, 0));
+            frames += 1;
+            let evicted: Vec<ChunkCoords> = before.into_iter().filter(|c| !map.loaded_chunks.contains_key(c)).collect();
+            assert!(!evicted.is_empty() && evicted.len() <= map.max_evictions_per_frame, "{evicted:?}");
+            for coords in evicted {
+                let distance = coords.x.abs().max(coords.y.abs());
+                assert!(distance > 5, "{coords:?} is within the unload distance");
+                assert!(distance <= farthest_left, "{coords:?} went after nearer chunks");
+                farthest_left = distance;
+            }
+        }
+        assert_eq!(frames, 6, "48 chunks, 8 per frame");
+        assert!(required_chunks(chunk(0, 0), LoadShape::Square, 3).all(|c| map.loaded_chunks.contains_key(&c)));
+    }
+
+    /// Runs `data_map_load_unload_system` once around actors at the given chunks and radii.
+    fn requested_around(render_distance: usize, actors: &[(ChunkCoords, usize)]) -> HashSet<ChunkCoords> {
+        let mut app = App::new();
+        app.insert_resource(DataMap::new(CoordsProducer, DIMENSION, render_distance))
+            .init_resource::<ChunkStats<CoordsProducer>>()
+            .add_event::<RenderDistanceChange<CoordsProducer>>()
+            .add_systems(Update, data_map_load_unload_system::<CoordsProducer>);
+        for (coords, radius) in actors {
+            let position = coords.corner(DIMENSION).0 + Vec2::ONE; // Inside the chunk, off its border
+            app.world_mut().spawn((
+                Transform::from_translation(position.extend(0.0)),
+                MapRevealActor {
+                    radius_chunks: *radius,
+                    priority: 0,
+                },
+            ));
+        }
+        app.update();
+        app.world().resource::<DataMap<CoordsProducer>>().requested_chunks.clone()
+    }
+
+    #[test]
+    fn far_apart_actors_request_both_neighborhoods() {
+        let requested = requested_around(2, &[(chunk(0, 0), 2), (chunk(40, -40), 1)]);
+        assert_eq!(requested.len(), 25 + 9);
+        for (center, radius) in [(chunk(0, 0), 2), (chunk(40, -40), 1)] {
+            for y in center.y - radius..=center.y + radius {
+                for x in center.x - radius..=center.x + radius {
+                    assert!(requested.contains(&chunk(x, y)), "{:?} missing", chunk(x, y));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn actor_radius_is_capped_and_overlaps_are_requested_once() {
+        // The second radius is capped by the render distance of 1
+        let requested = requested_around(1, &[(chunk(0, 0), 1), (chunk(1, 0), 5)]);
+        assert_eq!(requested.len(), 12);
+    }
+
+    const WRITTEN: (isize, isize) = (isize::MAX, 0); // Never generated
+
+    #[test]
+    fn fill_rect_over_loaded_and_unloaded_chunks() {
+        let mut map = map();
+        load(&mut map, chunk(0, 0));
+        load(&mut map, chunk(1, 1));
+        map.fill_rect(tile(4, 4), 8, 8, WRITTEN); // The inner 4x4 corners of four chunks
+
+        // One rectangle per unloaded chunk, no single tile writes
+        assert!(map.write_queue.is_empty());
+        assert_eq!(map.pending_areas.len(), 2);
+        for coords in [chunk(1, 0), chunk(0, 1)] {
+            let areas = &map.pending_areas[&coords];
+            assert_eq!(areas.len(), 1);
+            assert_eq!((areas[0].width, areas[0].height), (4, 4));
+            assert!(map.requested_chunks.contains(&coords));
+        }
+        assert_eq!(map.read(tile(9, 5)), Some(WRITTEN)); // Pending, read through the queue
+
+        load(&mut map, chunk(1, 0));
+        load(&mut map, chunk(0, 1));
+        assert!(map.pending_areas.is_empty());
+        let area = map.read_area(tile(0, 0), 16, 16).unwrap();
+        for y in 0..16 {
+            for x in 0..16 {
+                let inside = (4..12).contains(&x) && (4..12).contains(&y);
+                let expected = if inside { WRITTEN } else { (x as isize, y as isize) };
+                assert_eq!(area.get_item(x, y), Some(&expected), "tile {x}, {y}");
+            }
+        }
+    }
+
+    #[test]
+    fn write_area_queues_a_clipped_copy_under_newer_single_writes() {
+        let mut map = map();
+        load(&mut map, chunk(0, 0));
+        let grid = grid_from_fn(4, |x, y| (100 + x as isize, 100 + y as isize));
+        map.write_area(tile(6, 0), &grid); // Two columns in the loaded chunk, two in chunk (1, 0)
+        map.write(tile(9, 3), WRITTEN); // Newer than the queued rectangle
+
+        assert_eq!(map.read(tile(6, 0)), Some((100, 100)));
+        assert_eq!(map.read(tile(8, 2)), Some((102, 102)));
+        load(&mut map, chunk(1, 0));
+        assert_eq!(map.read(tile(7, 1)), Some((101, 101)));
+        assert_eq!(map.read(tile(9, 1)), Some((103, 101)));
+        assert_eq!(map.read(tile(9, 3)), Some(WRITTEN));
+        assert_eq!(map.read(tile(10, 0)), Some((10, 0)));
+    }
+
+    #[test]
+    fn load_shapes_have_the_expected_counts_and_symmetries() {
+        let focus = chunk(5, -7);
+        for (shape, counts) in [
+            (LoadShape::Square, [9, 25, 49, 81]),
+            (LoadShape::Diamond, [5, 13, 25, 41]),
+            (LoadShape::Circle, [9, 21, 37, 69]),
+        ] {
+            for (radius, count) in (1..=4).zip(counts) {
+                let set: HashSet<ChunkCoords> = required_chunks(focus, shape, radius).collect();
+                assert_eq!(set.len(), count, "{shape:?} of radius {radius}");
+                for coords in set.iter() {
+                    let (dx, dy) = (coords.x - focus.x, coords.y - focus.y);
+                    assert!(shape.distance(dx, dy) <= radius);
+                    for (mx, my) in [(-dx, dy), (dx, -dy), (dy, dx)] {
+                        let mirrored = chunk(focus.x + mx, focus.y + my);
+                        assert!(set.contains(&mirrored), "{shape:?} of radius {radius} misses {mirrored:?}");
+                    }
+                }
+            }
+        }
+    }
+
+    // 5 wide and 3 high, so mixing up x and y shows
+    fn wide_grid() -> FlatGrid<(isize, isize)> {
+        let mut grid = FlatGrid::new_rect(5, 3, (0, 0));
+        grid.map_in_place(|x, y, _| (x as isize, y as isize));
+        grid
+    }
+
+    #[test]
+    fn rows_of_a_non_square_grid() {
+        let mut grid = wide_grid();
+        for y in 0..3 {
+            let row = grid.row(y).unwrap();
+            assert_eq!(row.len(), 5);
+            for (x, item) in row.iter().enumerate() {
+                assert_eq!(Some(item), grid.get_item(x, y));
+            }
+        }
+        // Out of range is None everywhere, never another row
+        assert!(grid.row(3).is_none());
+        assert!(grid.row_mut(3).is_none());
+        assert!(grid.get_item(0, 3).is_none());
+        assert!(grid.get_item(5, 0).is_none());
+        assert!(!grid.set_item(5, 0, (9, 9)));
+
+        grid.row_mut(1).unwrap()[4] = (9, 9);
+        assert_eq!(grid.get_item(4, 1), Some(&(9, 9)));
+        assert_eq!(grid.get_item(0, 2), Some(&(0, 2)));
+    }
+
+    #[test]
+    fn iter_indexed_is_row_major_and_matches_get_item() {
+        let grid = wide_grid();
+        let cells: Vec<_> = grid.iter_indexed().collect();
+        assert_eq!(cells.len(), 15);
+        for (i, (x, y, item)) in cells.into_iter().enumerate() {
+            assert_eq!((x, y), (i % 5, i / 5));
+            assert_eq!(*item, (x as isize, y as isize));
+        }
+
+        let square = FlatGrid::from_fn(4, |x, y| (x as isize, y as isize));
+        assert_eq!(square.height(), 4);
+        assert!(square.iter_indexed().all(|(x, y, item)| *item == (x as isize, y as isize)));
+    }
+
+    // Map of 16x16 chunks with 100 writes queued for each of `chunks` chunks along x
+    fn queued_writes_map(chunks: isize) -> DataMap<CoordsProducer> {
+        let mut map = DataMap::new(CoordsProducer, 16, 1);
+        for cx in 0..chunks {
+            for i in 0..100 {
+                map.write(tile(cx * 16 + i % 16, i / 16), WRITTEN);
+            }
+        }
+        map
+    }
+
+    #[test]
+    fn get_area_shows_only_the_queued_writes_inside_it() {
+        let mut map = queued_writes_map(3); // Tiles 0 to 15 of rows 0 to 6 written in chunks 0 to 2
+        map.write(tile(20, 20), WRITTEN); // In chunk (1, 1), above the area
+        let area = map.get_area(tile(14, 0), 4, 2);
+        for x in 0..4 {
+            for y in 0..2 {
+                assert_eq!(area.get_item(x, y), Some(&WRITTEN), "tile {x}, {y}");
+            }
+        }
+        let area = map.get_area(tile(18, 18), 4, 4);
+        assert_eq!(area.get_item(2, 2), Some(&WRITTEN));
+        assert_eq!(area.as_slice().iter().filter(|&&item| item == WRITTEN).count(), 1);
+    }
+
+    // Fastest of a few loads of chunk (0, 0) into fresh maps from `build`
+    fn fastest_insert(build: impl Fn() -> DataMap<CoordsProducer>) -> std::time::Duration {
+        (0..20)
+            .map(|_| {
+                let mut map = build();
+                let start = std::time::Instant::now();
+                load(&mut map, chunk(0, 0));
+                start.elapsed()
+            })
+            .min()
+            .unwrap()
+    }
+
+    #[test]
+    fn inserting_a_chunk_does_not_scan_the_writes_of_others() {
+        let mut map = queued_writes_map(100);
+        assert_eq!(map.queued_write_count(), 10_000);
+        assert_eq!(map.write_queue.len(), 100); // Grouped by chunk
+        load(&mut map, chunk(0, 0));
+        assert_eq!(map.queued_write_count(), 9_900);
+        assert_eq!(map.read(tile(3, 2)), Some(WRITTEN));
+        assert_eq!(map.write_queue[&chunk(99, 0)].len(), 100);
+
+        // Grouped by chunk, 10k writes queued elsewhere cost next to nothing
+        let alone = fastest_insert(|| queued_writes_map(1));
+        let crowded = fastest_insert(|| queued_writes_map(100));
+        assert!(
+            crowded < alone * 5 + std::time::Duration::from_micros(50),
+            "{crowded:?} with 10k writes queued, {alone:?} with only its own"
+        );
+    }
+
+    /// Copies the last tile of the south-west neighbor into the first tile, a pass that
+    /// depends on neighbors the way a border blending filter does.
+    struct CopyDiagonalCorner;
+
+    impl ChunkPostProcessor<CoordsProducer> for CopyDiagonalCorner {
+        fn process(&self, _coords: ChunkCoords, grid: &mut FlatGrid<(isize, isize)>, neighbors: &NeighborView<CoordsProducer>) {
+            let corner = neighbors.item_outside(-1, -1).unwrap_or(CoordsProducer.default_value());
+            grid.set_item(0, 0, corner);
+        }
+
+        fn rerun_when_neighbors_load(&self) -> bool {
+            true
+        }
+    }
+
+    /// Generates and post-processes a chunk the way the generation task does, then inserts it.
+    fn load_processed(map: &mut DataMap<CoordsProducer>, coords: ChunkCoords) {
+        let mut chunk = map.producer.generate_chunk(coords, DIMENSION);
+        let view = NeighborView::capture(map, coords);
+        for processor in map.post_processors.clone() {
+            processor.process(coords, &mut chunk.grid, &view);
+        }
+        map.insert_chunk(coords, chunk);
+    }
+
+    #[test]
+    fn post_processing_does_not_depend_on_the_neighbor_load_order() {
+        let around: Vec<ChunkCoords> = NEIGHBOR_OFFSETS.iter().map(|&(dx, dy)| chunk(dx, dy)).collect();
+        let mut orders = vec![around.clone(), around.iter().rev().copied().collect::<Vec<_>>()];
+        for order in orders.iter_mut() {
+            order.insert(0, chunk(0, 0)); // Center first, processed with no neighbors at all
+        }
+        let mut last = around.clone();
+        last.push(chunk(0, 0)); // Center last, processed with all of them
+        orders.push(last);
+
+        for order in orders {
+            let mut map = map();
+            map.add_post_processor(CopyDiagonalCorner);
+            for &coords in order.iter() {
+                load_processed(&mut map, coords);
+            }
+            assert_eq!(map.read(tile(0, 0)), Some((-1, -1)), "order {order:?}");
+            assert!(!map.incomplete_post_processing.contains(&chunk(0, 0)), "order {order:?}");
+        }
+    }
+
+    #[test]
+    fn post_processing_is_rerun_until_all_neighbors_loaded() {
+        let mut map = map();
+        map.add_post_processor(CopyDiagonalCorner);
+        load_processed(&mut map, chunk(0, 0));
+        assert_eq!(map.read(tile(0, 0)), Some(CoordsProducer.default_value()));
+        assert!(map.incomplete_post_processing.contains(&chunk(0, 0)));
+
+        load_processed(&mut map, chunk(-1, -1));
+        assert_eq!(map.read(tile(0, 0)), Some((-1, -1)));
+        assert!(map.incomplete_post_processing.contains(&chunk(0, 0)), "still misses 7 neighbors");
+
+        // Modified chunks keep their writes, they are never processed again
+        map.write(tile(0, 0), WRITTEN);
+        for (dx, dy) in NEIGHBOR_OFFSETS.into_iter().filter(|&offset| offset != (-1, -1)) {
+            load_processed(&mut map, chunk(dx, dy));
+        }
+        assert_eq!(map.read(tile(0, 0)), Some(WRITTEN));
+    }
+
+    /// Broken on purpose: tiles hold their coordinates inside the chunk, so every chunk looks alike.
+    #[derive(Clone, Default)]
+    struct LocalCoordsProducer;
+
+    impl MapDataProducer for LocalCoordsProducer {
+        type Item = (isize, isize);
+        type GridType = FlatGrid<(isize, isize)>;
+
+        fn default_value(&self) -> (isize, isize) {
+            (isize::MIN, isize::MIN)
+        }
+
+        fn generate_chunk(&self, _coords: ChunkCoords, dimension_tiles: TilesCount) -> DataChunk<Self::GridType> {
+            DataChunk::new(FlatGrid::from_fn(dimension_tiles, |x, y| (x as isize, y as isize)))
+        }
+    }
+
+    #[test]
+    fn seamless_producer_has_no_seam_mismatches() {
+        let pairs = [(chunk(0, 0), chunk(1, 0)), (chunk(0, 0), chunk(0, -1)), (chunk(-3, 2), chunk(-3, 3))];
+        assert!(validate_producer_seams(&CoordsProducer, DIMENSION, &pairs).is_empty());
+    }
+
+    #[test]
+    fn broken_producer_reports_its_seams() {
+        let mismatches = validate_producer_seams(&LocalCoordsProducer, DIMENSION, &[(chunk(0, 0), chunk(1, 0))]);
+        assert!(!mismatches.is_empty());
+        assert!(mismatches.iter().all(|m| m.point.x == 7 || m.point.x == 8), "{mismatches:?}");
+        let east_edge = mismatches.iter().find(|m| m.point == tile(7, 3)).unwrap();
+        assert_eq!(east_edge.chunk, chunk(0, 0));
+        assert_eq!((east_edge.in_chunk, east_edge.standalone), ((7, 3), (0, 0)));
+
+        // Only orthogonal neighbors share an edge
+        let diagonal = [(chunk(0, 0), chunk(1, 1)), (chunk(0, 0), chunk(2, 0))];
+        assert!(validate_producer_seams(&LocalCoordsProducer, DIMENSION, &diagonal).is_empty());
+    }
+
+    #[test]
+    fn chunk_coords_contain_their_tiles_at_negative_coordinates() {
+        for x in -20..20 {
+            for y in [-9, -8, -1, 0, 7, 8] {
+                let point = tile(x, y);
+                let coords = ChunkCoords::from_point(point, DIMENSION);
+                assert!(coords.contains_point(point, DIMENSION), "{point:?}");
+                assert!(!chunk(coords.x + 1, coords.y).contains_point(point, DIMENSION), "{point:?}");
+                let origin = coords.to_bottom_left_tile_point(DIMENSION);
+                let (local_x, local_y) = point.local_in_chunk(DIMENSION);
+                assert_eq!(origin + tile(local_x as isize, local_y as isize), point);
+            }
+        }
+        assert_eq!(ChunkCoords::from_point(tile(-1, -8), DIMENSION), chunk(-1, -1));
+        assert_eq!(ChunkCoords::from_point(tile(-9, 0), DIMENSION), chunk(-2, 0));
+    }
+
+    #[test]
+    fn chunk_coords_square_and_circle_ranges() {
+        let center = chunk(-2, 3);
+        let square: Vec<ChunkCoords> = ChunkCoords::iter_square(center, 2).collect();
+        assert_eq!(square.len(), 25);
+        assert!(square.contains(&chunk(-4, 1)) && square.contains(&chunk(0, 5)));
+
+        let circle: Vec<ChunkCoords> = ChunkCoords::iter_circle(center, 2).collect();
+        assert_eq!(circle.len(), 21); // The square without its 4 corners
+        assert!(circle.contains(&chunk(-1, 5)) && !circle.contains(&chunk(0, 5)));
+
+        let around = center.neighbors8();
+        assert_eq!(around.len(), 8);
+        assert!(around.iter().all(|c| c != &center && square.contains(c)));
+    }
+
+    /// A 9x7 house: walls all around, the default (transparent) value inside.
+    fn house() -> Stamp<(isize, isize)> {
+        let mut grid = FlatGrid::new_rect(9, 7, Default::default());
+        for y in 0..7 {
+            for x in 0..9 {
+                if x == 0 || y == 0 || x == 8 || y == 6 {
+                    grid.set_item(x, y, WRITTEN);
+                }
+            }
+        }
+        Stamp::new(grid, (4, 0)) // Anchored at the middle of the bottom wall
+    }
+
+    fn assert_house(map: &DataMap<CoordsProducer>, interior: impl Fn(isize, isize) -> (isize, isize)) {
+        // Stamped at (8, 0): spans tiles 4..=12, over the border between chunks (0, 0) and (1, 0)
+        for y in 0..8 {
+            for x in 0..16 {
+                let inside = (4..=12).contains(&x) && y <= 6;
+                let wall = inside && (x == 4 || x == 12 || y == 0 || y == 6);
+                let expected = match (inside, wall) {
+                    (true, true) => WRITTEN,
+                    (true, false) => interior(x, y),
+                    _ => (x, y),
+                };
+                assert_eq!(map.read(tile(x, y)), Some(expected), "tile {x}, {y}");
+            }
+        }
+    }
+
+    #[test]
+    fn stamp_a_hollow_house_across_a_chunk_border() {
+        let mut map = map();
+        load(&mut map, chunk(0, 0));
+        map.stamp(tile(8, 0), &house(), StampMode::SkipDefault);
+        load(&mut map, chunk(1, 0)); // Gets its half of the stamp from the queue
+        assert_house(&map, |x, y| (x, y));
+    }
+
+    #[test]
+    fn overwriting_stamp_clears_the_interior() {
+        let mut map = map();
+        load(&mut map, chunk(1, 0));
+        map.stamp(tile(8, 0), &house(), StampMode::Overwrite);
+        load(&mut map, chunk(0, 0));
+        assert_house(&map, |_, _| (0, 0));
+    }
+
+    #[test]
+    fn copy_region_from_is_clipped_to_both_grids() {
+        let src = FlatGrid::from_fn(4, |x, y| (x as isize, y as isize));
+        let mut dst = FlatGrid::new(4, (-1, -1));
+        dst.copy_region_from(&src, (1, 2), (2, 0), (3, 3));
+        // 2 columns fit in dst, 2 rows are left in src
+        for y in 0..4 {
+            for x in 0..4 {
+                let copied = x >= 2 && y < 2;
+                let expected = if copied { (x as isize - 1, y as isize + 2) } else { (-1, -1) };
+                assert_eq!(dst.get_item(x, y), Some(&expected), "cell {x}, {y}");
+            }
+        }
+    }
+
+    #[derive(Component)]
+    struct ChunkMarker(ChunkCoords);
+
+    struct MarkerSpawner;
+
+    impl ChunkSpawner<CoordsProducer> for MarkerSpawner {
+        fn spawn_for_chunk(
+            &self,
+            commands: &mut Commands,
+            coords: ChunkCoords,
+            _chunk: &DataChunk<FlatGrid<(isize, isize)>>,
+        ) -> Vec<Entity> {
+            vec![commands.spawn(ChunkMarker(coords)).id()]
+        }
+    }
+
+    // Chunks of the markers alive, sorted
+    fn markers(app: &mut App) -> Vec<(isize, isize)> {
+        let world = app.world_mut();
+        let mut chunks: Vec<(isize, isize)> =
+            world.query::<&ChunkMarker>().iter(world).map(|marker| (marker.0.x, marker.0.y)).collect();
+        chunks.sort_unstable();
+        chunks
+    }
+
+    #[test]
+    fn chunk_entities_follow_load_unload_and_reload() {
+        let mut app = App::new();
+        let mut data_map = map();
+        load(&mut data_map, chunk(0, 0)); // Before the spawner, caught up on
+        app.insert_resource(data_map)
+            .add_event::<ChunkLoaded<CoordsProducer>>()
+            .add_event::<ChunkUnloaded<CoordsProducer>>()
+            .init_resource::<ChunkSpawners<CoordsProducer>>()
+            .init_resource::<ChunkEntities<CoordsProducer>>()
+            .add_systems(Update, chunk_entities_system::<CoordsProducer>);
+        app.world_mut().resource_mut::<ChunkSpawners<CoordsProducer>>().add(MarkerSpawner);
+        app.update();
+        assert_eq!(markers(&mut app), [(0, 0)]);
+
+        let load_chunk = |app: &mut App, coords: ChunkCoords| {
+            load(&mut app.world_mut().resource_mut::<DataMap<CoordsProducer>>(), coords);
+            app.world_mut().send_event(ChunkLoaded::<CoordsProducer>::new(coords));
+            app.update();
+        };
+        load_chunk(&mut app, chunk(1, 0));
+        assert_eq!(markers(&mut app), [(0, 0), (1, 0)]);
+        let first = app.world().resource::<ChunkEntities<CoordsProducer>>().get(chunk(1, 0)).to_vec();
+        assert_eq!(first.len(), 1);
+
+        app.world_mut().resource_mut::<DataMap<CoordsProducer>>().unload_chunk(chunk(1, 0));
+        app.world_mut().send_event(ChunkUnloaded::<CoordsProducer>::new(chunk(1, 0)));
+        app.update();
+        assert_eq!(markers(&mut app), [(0, 0)]);
+        assert!(app.world().get_entity(first[0]).is_err());
+        assert!(app.world().resource::<ChunkEntities<CoordsProducer>>().get(chunk(1, 0)).is_empty());
+
+        // Loaded again, even twice over, it still has a single marker
+        load_chunk(&mut app, chunk(1, 0));
+        load_chunk(&mut app, chunk(1, 0));
+        assert_eq!(markers(&mut app), [(0, 0), (1, 0)]);
+        assert_eq!(app.world().resource::<ChunkEntities<CoordsProducer>>().get(chunk(1, 0)).len(), 1);
+
+        // Unloaded and loaded back before the system ran: nothing to despawn or respawn
+        let kept = app.world().resource::<ChunkEntities<CoordsProducer>>().get(chunk(0, 0)).to_vec();
+        app.world_mut().resource_mut::<DataMap<CoordsProducer>>().unload_chunk(chunk(0, 0));
+        app.world_mut().send_event(ChunkUnloaded::<CoordsProducer>::new(chunk(0, 0)));
+        load(&mut app.world_mut().resource_mut::<DataMap<CoordsProducer>>(), chunk(0, 0));
+        app.update();
+        assert_eq!(app.world().resource::<ChunkEntities<CoordsProducer>>().get(chunk(0, 0)), kept);
+    }
+
+    #[test]
+    fn bit_grid_cells_across_word_boundaries() {
+        let mut grid = BitGrid::new(10, false); // 100 cells, the second word is partial
+        for (x, y) in [(3, 6), (4, 6), (9, 9)] {
+            assert!(grid.set_item(x, y, true)); // Cells 63, 64 and 99
+        }
+        assert!(!grid.set_item(10, 0, true));
+        assert_eq!(grid.get_item(3, 6), Some(&true));
+        assert_eq!(grid.get_item(4, 6), Some(&true));
+        assert_eq!(grid.get_item(5, 6), Some(&false));
+        assert_eq!(grid.get_item(9, 9), Some(&true));
+        assert_eq!(grid.get_item(0, 10), None);
+        assert_eq!(grid.count_ones(), 3);
+        assert_eq!(grid.iter_ones().collect::<Vec<_>>(), [(3, 6), (4, 6), (9, 9)]);
+
+        grid.set_item(4, 6, false);
+        assert_eq!(grid.iter_ones().collect::<Vec<_>>(), [(3, 6), (9, 9)]);
+    }
+
+    #[test]
+    fn bit_grid_fill_leaves_the_tail_of_the_last_word_clear() {
+        let mut grid = BitGrid::new_rect(7, 3, true);
+        assert_eq!(grid.count_ones(), 21);
+        assert_eq!(grid.iter_ones().last(), Some((6, 2)));
+        grid.fill(false);
+        assert_eq!(grid.count_ones(), 0);
+        grid.fill(true);
+        assert_eq!(grid.iter_ones().count(), 21);
+        assert_eq!(grid.words.len(), 1); // 21 bits in a single word
+    }
+
+    #[test]
+    fn bit_grid_chunks_get_queued_bulk_writes() {
+        let mut map = DataMap::new(BoolProducer::default(), DIMENSION, 1);
+        let flags = FlatGrid::from_fn(4, |x, y| (x + y) % 2 == 0);
+        map.write_area(tile(6, 6), &flags); // Over four unloaded chunks
+        map.fill_rect(tile(-2, -2), 2, 2, true);
+        for coords in [chunk(-1, -1), chunk(0, 0), chunk(1, 0), chunk(0, 1), chunk(1, 1)] {
+            let generated = map.producer.generate_chunk(coords, DIMENSION);
+            map.insert_chunk(coords, generated);
+        }
+        for y in 0..4 {
+            for x in 0..4 {
+                let expected = (x + y) % 2 == 0;
+                assert_eq!(map.read(tile(6 + x, 6 + y)), Some(expected), "tile {x}, {y}");
+            }
+        }
+        assert_eq!(map.read(tile(-1, -1)), Some(true));
+        assert_eq!(map.read(tile(-3, -1)), Some(false));
+        assert_eq!(map.loaded_chunks[&chunk(1, 1)].grid.count_ones(), 2);
+    }
+
+    /// A world with a coordinates map and a flags map, both holding chunks (0, 0) to (9, 0)
+    /// around a focus at the origin, under a budget of `max_bytes`.
+    fn budget_world(max_bytes: usize) -> World {
+        let mut world = World::new();
+        let mut coords_map = map();
+        let mut flags_map = DataMap::new(BoolProducer::default(), DIMENSION, 1);
+        for x in 0..10 {
+            load(&mut coords_map, chunk(x, 0));
+            let flags = flags_map.producer.generate_chunk(chunk(x, 0), DIMENSION);
+            flags_map.insert_chunk(chunk(x, 0), flags);
+        }
+        coords_map.focus_centers = vec![chunk(0, 0)];
+        flags_map.focus_centers = vec![chunk(0, 0)];
+        world.insert_resource(coords_map);
+        world.insert_resource(flags_map);
+        world.insert_resource(ChunkMemoryBudget {
+            max_bytes,
+            ..Default::default()
+        });
+        world.insert_resource(ChunkMemoryHooks(vec![memory_hook::<CoordsProducer>(), memory_hook::<BoolProducer>()]));
+        world
+    }
+
+    fn loaded_x(world: &World, chunks: impl Fn(&World) -> Vec<ChunkCoords>) -> Vec<isize> {
+        let mut xs: Vec<isize> = chunks(world).iter().map(|c| c.x).collect();
+        xs.sort_unstable();
+        xs
+    }
+
+    fn coords_chunks(world: &World) -> Vec<ChunkCoords> {
+        world.resource::<DataMap<CoordsProducer>>().loaded_chunks.keys().copied().collect()
+    }
+
+    fn flags_chunks(world: &World) -> Vec<ChunkCoords> {
+        world.resource::<DataMap<BoolProducer>>().loaded_chunks.keys().copied().collect()
+    }
+
+    #[test]
+    fn memory_budget_evicts_each_map_in_proportion() {
+        let coords_bytes = map().producer.generate_chunk(chunk(0, 0), DIMENSION).grid.byte_size();
+        let flags_bytes = BitGrid::new(DIMENSION, false).byte_size();
+        assert!(coords_bytes > 10 * flags_bytes, "the maps should differ in size");
+
+        // Half of the total: both maps give up half of their chunks, the farthest ones
+        let total = 10 * (coords_bytes + flags_bytes);
+        let mut world = budget_world(total / 2);
+        chunk_memory_budget_system(&mut world);
+        assert_eq!(loaded_x(&world, coords_chunks), [0, 1, 2, 3, 4]);
+        assert_eq!(loaded_x(&world, flags_chunks), [0, 1, 2, 3, 4]);
+
+        // Under the budget, nothing goes
+        chunk_memory_budget_system(&mut world);
+        assert_eq!(coords_chunks(&world).len(), 5);
+    }
+
+    #[test]
+    fn memory_budget_keeps_the_chunks_around_the_focus() {
+        let mut world = budget_world(0);
+        chunk_memory_budget_system(&mut world);
+        // Within the render distance of 1, the total stays over the budget
+        assert_eq!(loaded_x(&world, coords_chunks), [0, 1]);
+        assert_eq!(loaded_x(&world, flags_chunks), [0, 1]);
+    }
+
+    fn journaled_map() -> DataMap<CoordsProducer> {
+        let mut map = map();
+        map.journal = true;
+        map
+    }
+
+    #[test]
+    fn repeated_writes_collapse_into_one_delta_entry() {
+        let mut map = journaled_map();
+        load(&mut map, chunk(0, 0));
+        for frame in 0..100 {
+            map.write(tile(3, 2), (frame, frame));
+        }
+        map.write(tile(-1, 0), WRITTEN); // Unloaded chunk, queued and journaled all the same
+
+        let deltas = map.drain_deltas();
+        assert_eq!(deltas.len(), 2);
+        let delta = &deltas[&chunk(0, 0)];
+        assert_eq!(delta.len(), 1);
+        assert_eq!(delta.get(2 * DIMENSION + 3), Some((99, 99)));
+        assert_eq!(deltas[&chunk(-1, 0)].iter().collect::<Vec<_>>(), [(DIMENSION - 1, WRITTEN)]);
+        assert!(map.drain_deltas().is_empty(), "drained");
+    }
+
+    #[test]
+    fn deltas_round_trip_into_another_map() {
+        let mut source = journaled_map();
+        load(&mut source, chunk(0, 0));
+        source.write(tile(1, 1), WRITTEN);
+        source.fill_rect(tile(6, 6), 4, 1, (5, 5)); // Over the border into chunk (1, 0)
+        source.write(tile(7, 6), (6, 6)); // Newer than the fill
+        let deltas = source.drain_deltas();
+
+        let mut target = map();
+        load(&mut target, chunk(1, 0));
+        target.apply_deltas(deltas.clone());
+        load(&mut target, chunk(0, 0));
+        assert_eq!(target.read(tile(1, 1)), Some(WRITTEN));
+        assert_eq!(target.read(tile(6, 6)), Some((5, 5)));
+        assert_eq!(target.read(tile(7, 6)), Some((6, 6)));
+        assert_eq!(target.read(tile(9, 6)), Some((5, 5)));
+        assert_eq!(target.read(tile(10, 6)), Some((10, 6)));
+
+        // Without a journal the target kept nothing to drain, with one it gets the same deltas back
+        assert!(target.drain_deltas().is_empty());
+        let mut journaled = journaled_map();
+        journaled.apply_deltas(deltas.clone());
+        assert_eq!(journaled.drain_deltas(), deltas);
+    }
+
+    #[test]
+    fn compacted_delta_keeps_only_what_generation_would_not_make() {
+        let mut delta = ChunkDelta::default();
+        delta.set(0, (0, 0)); // What chunk (0, 0) generates there anyway
+        delta.set(9, WRITTEN);
+        delta.set(DIMENSION + 2, (2, 1)); // Also the generated value
+        let generated = CoordsProducer.generate_chunk(chunk(0, 0), DIMENSION);
+        let compact = delta.compact_against(&generated);
+        assert_eq!(compact.iter().collect::<Vec<_>>(), [(9, WRITTEN)]);
+
+        let mut grid = generated.grid.clone();
+        compact.apply_to(&mut grid);
+        assert_eq!(grid.get_item(1, 1), Some(&WRITTEN));
+    }
 }
@@ -4,53 +4,73 @@ use bevy::{
     tasks::AsyncComputeTaskPool,
 };
 use futures_lite::future;
-use std::mem;
 
 use crate::{
     core::{
         basics::{DEFAULT_RENDER_DISTANCE_CHUNKS, Point},
-        chunks::{ChunkCoords, ChunkGenTask, DataChunk, GridData, MapDataProducer},
-        constants::{DEFAULT_CHUNK_DIMENSION_TILES, TILE_SIZE_IN_UNITS},
-        units::TilesCount,
+        chunks::{
+            ChunkCoords, ChunkGenError, ChunkGenTask, DataChunk, FocusMotion, GridData, LoadShape, MapDataProducer,
+            RenderDistanceChange, catch_generation_panic, prefetch_centers, required_chunks,
+        },
+        constants::DEFAULT_CHUNK_DIMENSION_TILES,
+        units::{TilesCount, WorldPos},
+        validation::tiles_to_units,
     },
     game::MapRevealActor,
 }; // For polling tasks
 
-use std::sync::Arc;
+use std::{cmp::Reverse, sync::Arc};
 
 use bevy::prelude::Vec3Swizzles;
 use bevy::{
     ecs::{
         entity::Entity,
-        query::With,
+        query::{QueryFilter, With},
         system::{Commands, Query, ResMut},
     },
     transform::components::Transform,
 };
 
-use crate::Player;
+use crate::game::Player;
 
 /// The central resource for managing a chunked map of type T using double buffering.
+///
+/// The write buffer is the authoritative store: it persists across frames and receives
+/// all writes and generation results. The read buffer is a snapshot of it, refreshed by
+/// `swap_buffers` for the chunks changed since the previous swap. Writes made during the
+/// frame are visible to reads right away, before the snapshot catches up.
 #[derive(Resource)]
 pub struct DataMapDoubleBuffered<P: MapDataProducer> {
     /// The front buffer, for reading data (e.g., by rendering systems).
-    /// This represents the complete, consistent state of the world for the current frame.
-    /// All read operations should use this buffer.
+    /// A consistent snapshot of the world as of the last swap.
     read_buffer: HashMap<ChunkCoords, DataChunk<P::GridType>>,
 
-    /// The back buffer, for writing new data (e.g., from generation tasks).
-    /// This is prepared during the frame to become the next `read_buffer`.
+    /// The back buffer, holding the authoritative chunks.
     /// All write operations and generation results are directed here.
     write_buffer: HashMap<ChunkCoords, DataChunk<P::GridType>>,
 
+    // Chunks inserted, modified or unloaded in the write buffer since the last swap
+    changed_chunks: HashSet<ChunkCoords>,
+    // Writes applied to the write buffer since the last swap, overlaid on reads
+    frame_writes: HashMap<Point, P::Item>,
+
     pub requested_chunks: HashSet<ChunkCoords>,
     // Maps chunk coords to the entity holding the generation task
     pub pending_tasks: HashMap<ChunkCoords, Entity>,
-    pub write_queue: HashMap<Point, P::Item>, // Writes to uncreated/unloaded cells
+    pub write_queue: HashMap<ChunkCoords, HashMap<Point, P::Item>>, // Writes to uncreated/unloaded cells, grouped by chunk
     pub producer: P,
     pub chunk_dimension_tiles: TilesCount,
     pub chunk_size_units: f32, // Derived from chunk_dimension_tiles and TILE_SIZE_IN_UNITS
     pub render_distance_chunks: usize, // Used by the map manager system to determine loading radius
+    pub load_shape: LoadShape,
+    pub prefetch_distance_chunks: usize, // Max extra chunks requested ahead of a moving focus, 0 disables it
+    pub prefetch_min_speed: f32,         // Units per second, slower foci get the symmetric area only
+    pub max_chunks_applied_per_frame: usize, // Completed tasks beyond this wait for the next frames
+    pub max_shrink_evictions_per_frame: usize, // Chunks dropped per frame after `set_render_distance` shrank the radius
+    shrinking_from: Option<usize>, // Previous render distance while its outer chunks are still being dropped
+    pub max_generation_retries: u32, // A chunk whose generation panics is retried this many times, then given up
+    pub generation_failures: HashMap<ChunkCoords, u32>, // Panics so far, per chunk not generated yet
+    pub failed_chunks: HashSet<ChunkCoords>, // Given up, never requested again and left unloaded
 }
 
 impl<P: MapDataProducer> DataMapDoubleBuffered<P> {
@@ -59,10 +79,13 @@ impl<P: MapDataProducer> DataMapDoubleBuffered<P> {
         chunk_dimension_tiles: TilesCount,
         render_distance_chunks: usize,
     ) -> Self {
-        let chunk_size_units = chunk_dimension_tiles as f32 * TILE_SIZE_IN_UNITS;
+        let chunk_size_units = tiles_to_units("chunk_dimension_tiles", chunk_dimension_tiles)
+            .unwrap_or_else(|err| panic!("DataMapDoubleBuffered<{}>: {err}", std::any::type_name::<P>()));
         Self {
             read_buffer: HashMap::new(),
             write_buffer: HashMap::new(),
+            changed_chunks: HashSet::new(),
+            frame_writes: HashMap::new(),
             requested_chunks: HashSet::new(),
             pending_tasks: HashMap::new(),
             write_queue: HashMap::new(),
@@ -70,15 +93,56 @@ impl<P: MapDataProducer> DataMapDoubleBuffered<P> {
             chunk_dimension_tiles,
             chunk_size_units,
             render_distance_chunks,
+            load_shape: LoadShape::default(),
+            prefetch_distance_chunks: 2,
+            prefetch_min_speed: 50.0,
+            max_chunks_applied_per_frame: 16,
+            max_shrink_evictions_per_frame: 8,
+            shrinking_from: None,
+            max_generation_retries: 3,
+            generation_failures: HashMap::new(),
+            failed_chunks: HashSet::new(),
         }
     }
 
-    /// Swaps the read and write buffers.
-    /// This is typically called once per frame/tick (e.g., in `PostUpdate`) after all
-    /// generation and updates for the frame have been applied to the `write_buffer`.
-    /// This makes the newly prepared state available for reading in the next frame.
-    pub fn swap_buffers(&mut self) {
-        mem::swap(&mut self.read_buffer, &mut self.write_buffer);
+    /// Changes the loading radius at runtime. Growing requests the new ring right away; shrinking
+    /// drops the chunks outside the new radius over the next frames, farthest first, see
+    /// `max_shrink_evictions_per_frame`.
+    pub fn set_render_distance(&mut self, chunks: usize) {
+        if chunks < self.render_distance_chunks {
+            // A shrink during a shrink keeps draining from the widest radius still loaded
+            self.shrinking_from = Some(self.shrinking_from.unwrap_or(self.render_distance_chunks));
+        }
+        self.render_distance_chunks = chunks;
+        if self.shrinking_from.is_some_and(|from| from <= chunks) {
+            self.shrinking_from = None;
+        }
+    }
+
+    /// Re-requests a chunk whose generation panicked, or gives up on it after `max_generation_retries`.
+    pub fn record_generation_failure(&mut self, coords: ChunkCoords, err: ChunkGenError) {
+        let failures = self.generation_failures.entry(coords).or_insert(0);
+        *failures += 1;
+        if *failures <= self.max_generation_retries {
+            warn!(
+                "DataMapDoubleBuffered<{}> chunk {:?} generation panicked ({}), retry {}/{}",
+                std::any::type_name::<P>(),
+                coords,
+                err.0,
+                failures,
+                self.max_generation_retries
+            );
+            self.requested_chunks.insert(coords);
+        } else {
+            warn!(
+                "DataMapDoubleBuffered<{}> chunk {:?} generation panicked ({}), giving up",
+                std::any::type_name::<P>(),
+                coords,
+                err.0
+            );
+            self.generation_failures.remove(&coords);
+            self.failed_chunks.insert(coords);
+        }
     }
 
     // --- Public API for Game Logic ---
@@ -88,22 +152,159 @@ impl<P: MapDataProducer> DataMapDoubleBuffered<P> {
         &self.read_buffer
     }
 
-    /// Provides mutable access to the write buffer for chunks. For modifying state.
-    pub fn write_chunks(&mut self) -> &mut HashMap<ChunkCoords, DataChunk<P::GridType>> {
-        &mut self.write_buffer
+    /// Edits a chunk of the write buffer in place, false if it isn't loaded. Like `write`, the
+    /// edited tiles are visible to reads right away and published to the read buffer on the next swap.
+    pub fn write_chunk(&mut self, coords: ChunkCoords, edit: impl FnOnce(&mut DataChunk<P::GridType>)) -> bool {
+        let Some(chunk) = self.write_buffer.get_mut(&coords) else {
+            return false;
+        };
+        edit(chunk);
+        chunk.dirty = true;
+        self.changed_chunks.insert(coords);
+        // The edit could have touched any tile, the whole chunk goes through the frame overlay
+        let origin = coords.to_bottom_left_tile_point(self.chunk_dimension_tiles);
+        for (x, y, value) in chunk.grid.iter_indexed() {
+            let point = Point {
+                x: origin.x + x as isize,
+                y: origin.y + y as isize,
+            };
+            self.frame_writes.insert(point, *value);
+        }
+        true
+    }
+
+    /// A value written since the last swap, or still waiting for its chunk.
+    fn recent_write(&self, point: Point) -> Option<P::Item> {
+        let chunk_coords = ChunkCoords::from_point(point, self.chunk_dimension_tiles);
+        self.frame_writes
+            .get(&point)
+            .or_else(|| self.write_queue.get(&chunk_coords)?.get(&point))
+            .copied()
+    }
+
+    /// Chunks within `radius` of a focus at `world_pos`, plus the ones ahead of it when moving.
+    fn required_around(&self, world_pos: Vec2, velocity: Vec2, radius: usize) -> HashSet<ChunkCoords> {
+        let focus = ChunkCoords::from_world(WorldPos(world_pos), self.chunk_dimension_tiles);
+        let ahead = prefetch_centers(
+            focus,
+            velocity,
+            self.chunk_size_units,
+            self.prefetch_distance_chunks,
+            self.prefetch_min_speed,
+        );
+        std::iter::once(focus)
+            .chain(ahead)
+            .flat_map(|center| required_chunks(center, self.load_shape, radius))
+            .collect()
+    }
+
+    /// Chunks the foci, as (position, velocity), keep loaded this frame, with the ones they need
+    /// loaded. While shrinking, up to `max_shrink_evictions_per_frame` chunks between the old and
+    /// new radius are let go, farthest from every focus first, until none are left.
+    fn kept_around(&mut self, foci: &[(Vec2, Vec2)]) -> (HashSet<ChunkCoords>, HashSet<ChunkCoords>) {
+        let required: HashSet<ChunkCoords> = foci
+            .iter()
+            .flat_map(|(world_pos, velocity)| self.required_around(*world_pos, *velocity, self.render_distance_chunks))
+            .collect();
+        let Some(from) = self.shrinking_from else {
+            return (required.clone(), required);
+        };
+        let mut kept: HashSet<ChunkCoords> = foci
+            .iter()
+            .flat_map(|(world_pos, velocity)| self.required_around(*world_pos, *velocity, from))
+            .collect();
+        let centers: Vec<ChunkCoords> = foci
+            .iter()
+            .map(|(world_pos, _)| ChunkCoords::from_world(WorldPos(*world_pos), self.chunk_dimension_tiles))
+            .collect();
+        let mut outer: Vec<ChunkCoords> = kept
+            .iter()
+            .filter(|c| !required.contains(*c) && self.write_buffer.contains_key(*c))
+            .copied()
+            .collect();
+        if outer.is_empty() {
+            self.shrinking_from = None;
+            return (required.clone(), required);
+        }
+        let shape = self.load_shape;
+        let nearest_focus = |c: &ChunkCoords| {
+            centers
+                .iter()
+                .map(|focus| shape.distance(c.x - focus.x, c.y - focus.y))
+                .min()
+        };
+        outer.sort_unstable_by_key(|c| Reverse(nearest_focus(c)));
+        for coords in outer.iter().take(self.max_shrink_evictions_per_frame) {
+            kept.remove(coords);
+        }
+        (kept, required)
+    }
+
+    /// Keeps the chunks around all the foci, as (position, velocity), loaded and unloads the
+    /// rest of the write buffer. Without any focus nothing is decided.
+    pub fn update_foci(&mut self, foci: &[(Vec2, Vec2)]) {
+        if foci.is_empty() {
+            return;
+        }
+        let (kept, required) = self.kept_around(foci);
+        self.retain_required(&kept, &required);
+    }
+
+    /// Unloads write buffer chunks outside of `kept`, requesting the missing `required` ones.
+    fn retain_required(&mut self, kept: &HashSet<ChunkCoords>, required: &HashSet<ChunkCoords>) {
+        let Self {
+            write_buffer,
+            changed_chunks,
+            ..
+        } = self;
+        write_buffer.retain(|coords, _| {
+            let keep = kept.contains(coords);
+            if !keep {
+                changed_chunks.insert(*coords);
+            }
+            keep
+        });
+
+        for coords in required.iter() {
+            if !self.write_buffer.contains_key(coords)
+                && !self.pending_tasks.contains_key(coords)
+                && !self.requested_chunks.contains(coords)
+                && !self.failed_chunks.contains(coords)
+            {
+                self.requested_chunks.insert(*coords);
+            }
+        }
+    }
+
+    /// Puts a freshly generated chunk into the write buffer, applying queued writes to it.
+    /// If the chunk is already there, it is kept and the generated copy dropped,
+    /// so writes made to it are never overwritten.
+    pub fn insert_generated(&mut self, coords: ChunkCoords, mut chunk: DataChunk<P::GridType>) {
+        if self.write_buffer.contains_key(&coords) {
+            return;
+        }
+        if let Some(writes) = self.write_queue.remove(&coords) {
+            for (point, value) in writes {
+                let (local_x, local_y) = point.local_in_chunk(self.chunk_dimension_tiles);
+                chunk.grid.set_item(local_x, local_y, value);
+                self.frame_writes.insert(point, value); // Visible until the next swap
+            }
+        }
+
+        self.write_buffer.insert(coords, chunk);
+        self.changed_chunks.insert(coords);
     }
 
     /// Gets the data at a specific world tile Point from the **read buffer**.
     /// If the chunk is not loaded, it spawns a chunk generation request and returns a default value.
     pub fn get(&mut self, point: Point) -> P::Item {
-        if let Some(&queued_value) = self.write_queue.get(&point) {
+        if let Some(queued_value) = self.recent_write(point) {
             return queued_value;
         }
 
         let chunk_coords = ChunkCoords::from_point(point, self.chunk_dimension_tiles);
         if let Some(chunk) = self.read_buffer.get(&chunk_coords) {
-            let local_x = (point.x.rem_euclid(self.chunk_dimension_tiles as isize)) as TilesCount;
-            let local_y = (point.y.rem_euclid(self.chunk_dimension_tiles as isize)) as TilesCount;
+            let (local_x, local_y) = point.local_in_chunk(self.chunk_dimension_tiles);
             chunk
                 .grid
                 .get_item(local_x, local_y)
@@ -115,27 +316,26 @@ impl<P: MapDataProducer> DataMapDoubleBuffered<P> {
         }
     }
 
-    /// Gets the data at a specific floating-point world position from the **read buffer**.
+    /// Gets the data of the tile at a world position from the **read buffer**.
+    pub fn get_at(&mut self, pos: WorldPos) -> P::Item {
+        self.get(pos.to_tile().0)
+    }
+
+    #[deprecated(note = "use `get_at` with a `WorldPos`")]
     pub fn get_rounded(&mut self, world_pos: Vec2) -> P::Item {
-        let tile_x = (world_pos.x / TILE_SIZE_IN_UNITS).round() as isize;
-        let tile_y = (world_pos.y / TILE_SIZE_IN_UNITS).round() as isize;
-        self.get(Point {
-            x: tile_x,
-            y: tile_y,
-        })
+        self.get_at(WorldPos(world_pos))
     }
 
     /// Attempts to get data from the **read buffer**. Returns `None` if not loaded.
     /// Spawns a chunk generation request if the chunk is not loaded.
     pub fn get_option(&mut self, point: Point) -> Option<P::Item> {
-        if let Some(&queued_value) = self.write_queue.get(&point) {
+        if let Some(queued_value) = self.recent_write(point) {
             return Some(queued_value);
         }
 
         let chunk_coords = ChunkCoords::from_point(point, self.chunk_dimension_tiles);
         if let Some(chunk) = self.read_buffer.get(&chunk_coords) {
-            let local_x = (point.x.rem_euclid(self.chunk_dimension_tiles as isize)) as TilesCount;
-            let local_y = (point.y.rem_euclid(self.chunk_dimension_tiles as isize)) as TilesCount;
+            let (local_x, local_y) = point.local_in_chunk(self.chunk_dimension_tiles);
             chunk.grid.get_item(local_x, local_y).copied()
         } else {
             self.requested_chunks.insert(chunk_coords);
@@ -143,52 +343,59 @@ impl<P: MapDataProducer> DataMapDoubleBuffered<P> {
         }
     }
 
-    /// Attempts to get data from a rounded world position from the **read buffer**.
+    /// Attempts to get the data of the tile at a world position from the **read buffer**.
+    pub fn get_at_option(&mut self, pos: WorldPos) -> Option<P::Item> {
+        self.get_option(pos.to_tile().0)
+    }
+
+    #[deprecated(note = "use `get_at_option` with a `WorldPos`")]
     pub fn get_rounded_option(&mut self, world_pos: Vec2) -> Option<P::Item> {
-        let tile_x = (world_pos.x / TILE_SIZE_IN_UNITS).round() as isize;
-        let tile_y = (world_pos.y / TILE_SIZE_IN_UNITS).round() as isize;
-        self.get_option(Point {
-            x: tile_x,
-            y: tile_y,
-        })
+        self.get_at_option(WorldPos(world_pos))
     }
 
     /// Reads data from the **read buffer** without spawning generation requests.
     pub fn read(&self, point: Point) -> Option<P::Item> {
-        if let Some(&queued_value) = self.write_queue.get(&point) {
+        if let Some(queued_value) = self.recent_write(point) {
             return Some(queued_value);
         }
 
         let chunk_coords = ChunkCoords::from_point(point, self.chunk_dimension_tiles);
         self.read_buffer.get(&chunk_coords).and_then(|chunk| {
-            let local_x = (point.x.rem_euclid(self.chunk_dimension_tiles as isize)) as TilesCount;
-            let local_y = (point.y.rem_euclid(self.chunk_dimension_tiles as isize)) as TilesCount;
+            let (local_x, local_y) = point.local_in_chunk(self.chunk_dimension_tiles);
             chunk.grid.get_item(local_x, local_y).copied()
         })
     }
 
-    /// Reads data from a rounded world position from the **read buffer** without generation requests.
+    /// Reads the data of the tile at a world position from the **read buffer** without generation requests.
+    pub fn read_at(&self, pos: WorldPos) -> Option<P::Item> {
+        self.read(pos.to_tile().0)
+    }
+
+    #[deprecated(note = "use `read_at` with a `WorldPos`")]
     pub fn read_rounded(&self, world_pos: Vec2) -> Option<P::Item> {
-        let tile_x = (world_pos.x / TILE_SIZE_IN_UNITS).round() as isize;
-        let tile_y = (world_pos.y / TILE_SIZE_IN_UNITS).round() as isize;
-        self.read(Point {
-            x: tile_x,
-            y: tile_y,
-        })
+        self.read_at(WorldPos(world_pos))
     }
 
     /// Writes data to a specific world tile Point, targeting the **write buffer**.
     /// If the chunk is loaded in the write buffer, it's modified immediately.
     /// If not, the write is queued for when the chunk is generated.
+    /// Either way, reads return the new value from now on.
     pub fn write(&mut self, point: Point, value: P::Item) {
         let chunk_coords = ChunkCoords::from_point(point, self.chunk_dimension_tiles);
         if let Some(chunk) = self.write_buffer.get_mut(&chunk_coords) {
-            let local_x = (point.x.rem_euclid(self.chunk_dimension_tiles as isize)) as TilesCount;
-            let local_y = (point.y.rem_euclid(self.chunk_dimension_tiles as isize)) as TilesCount;
+            let (local_x, local_y) = point.local_in_chunk(self.chunk_dimension_tiles);
             chunk.grid.set_item(local_x, local_y, value);
-            self.write_queue.remove(&point);
+            chunk.dirty = true;
+            self.changed_chunks.insert(chunk_coords);
+            self.frame_writes.insert(point, value);
+            if let Some(writes) = self.write_queue.get_mut(&chunk_coords) {
+                writes.remove(&point);
+                if writes.is_empty() {
+                    self.write_queue.remove(&chunk_coords);
+                }
+            }
         } else {
-            self.write_queue.insert(point, value);
+            self.write_queue.entry(chunk_coords).or_default().insert(point, value);
             self.requested_chunks.insert(chunk_coords);
         }
     }
@@ -197,109 +404,77 @@ impl<P: MapDataProducer> DataMapDoubleBuffered<P> {
     pub fn init(&mut self, manhattan_distance_tiles: usize) {
         let center_chunk = ChunkCoords { x: 0, y: 0 };
         let chunk_manhattan_distance =
-            (manhattan_distance_tiles as f32 / self.chunk_dimension_tiles as f32).ceil() as isize;
-
-        for x_offset in -chunk_manhattan_distance..=chunk_manhattan_distance {
-            for y_offset in -chunk_manhattan_distance..=chunk_manhattan_distance {
-                let current_chunk_coords = ChunkCoords {
-                    x: center_chunk.x + x_offset,
-                    y: center_chunk.y + y_offset,
-                };
-                if !self.read_buffer.contains_key(&current_chunk_coords)
-                    && !self.write_buffer.contains_key(&current_chunk_coords)
-                    && !self.pending_tasks.contains_key(&current_chunk_coords)
-                {
-                    self.requested_chunks.insert(current_chunk_coords);
+            (manhattan_distance_tiles as f32 / self.chunk_dimension_tiles as f32).ceil() as usize;
+
+        for current_chunk_coords in
+            required_chunks(center_chunk, self.load_shape, chunk_manhattan_distance)
+        {
+            if !self.read_buffer.contains_key(&current_chunk_coords)
+                && !self.write_buffer.contains_key(&current_chunk_coords)
+                && !self.pending_tasks.contains_key(&current_chunk_coords)
+                && !self.failed_chunks.contains(&current_chunk_coords)
+            {
+                self.requested_chunks.insert(current_chunk_coords);
+            }
+        }
+    }
+}
+
+impl<P: MapDataProducer> DataMapDoubleBuffered<P>
+where
+    P::GridType: Clone,
+{
+    /// Publishes the write buffer to the read buffer.
+    /// This is typically called once per frame/tick (e.g., in `PostUpdate`) after all
+    /// generation and updates for the frame have been applied to the `write_buffer`.
+    /// Only chunks changed since the previous swap are copied; the write buffer stays
+    /// authoritative, so no write is ever lost to a stale copy.
+    pub fn swap_buffers(&mut self) {
+        for coords in self.changed_chunks.drain() {
+            match self.write_buffer.get(&coords) {
+                Some(chunk) => {
+                    self.read_buffer.insert(coords, chunk.clone());
+                }
+                None => {
+                    self.read_buffer.remove(&coords);
                 }
             }
         }
+        self.frame_writes.clear();
     }
 }
 
 // System to manage loading/unloading based on a focus point (e.g., player/camera)
 // This system now prepares the WRITE BUFFER for the next frame.
 pub fn data_map_db_load_unload_system<P: MapDataProducer>(
-    player_query: Query<&Transform, With<MapRevealActor>>,
+    player_query: Query<(&Transform, Option<&FocusMotion>), With<MapRevealActor>>,
     mut data_map: ResMut<DataMapDoubleBuffered<P>>,
+    mut distance_changes: EventReader<RenderDistanceChange<P>>,
 ) {
-    for player_transform in player_query.as_readonly().iter() {
-        let focus_world_pos = player_transform.translation.xy();
-        let current_focus_chunk_coords =
-            ChunkCoords::from_world_pos(focus_world_pos, data_map.chunk_size_units);
-
-        let mut required_chunks_set: HashSet<ChunkCoords> = HashSet::new();
-
-        for dx in
-            -(data_map.render_distance_chunks as isize)..=(data_map.render_distance_chunks as isize)
-        {
-            for dy in -(data_map.render_distance_chunks as isize)
-                ..=(data_map.render_distance_chunks as isize)
-            {
-                required_chunks_set.insert(ChunkCoords {
-                    x: current_focus_chunk_coords.x + dx,
-                    y: current_focus_chunk_coords.y + dy,
-                });
-            }
-        }
-
-        // Unload chunks from the write buffer that are no longer required.
-        // After a swap, the write_buffer contains the old read_buffer's state.
-        // We trim it down to only what's needed for the next frame.
-        data_map
-            .write_buffer
-            .retain(|coords, _| required_chunks_set.contains(coords));
-
-        // Request new chunks that are required but not yet in the write buffer or pending.
-        for coords in required_chunks_set.iter() {
-            if !data_map.write_buffer.contains_key(coords)
-                && !data_map.pending_tasks.contains_key(coords)
-                && !data_map.requested_chunks.contains(coords)
-            {
-                data_map.requested_chunks.insert(*coords);
-            }
-        }
+    if let Some(change) = distance_changes.read().last() {
+        data_map.set_render_distance(change.chunks);
     }
+    // One pass over the union, so an actor never unloads what another one still needs
+    data_map.update_foci(&collect_foci(&player_query));
 }
 
 pub fn data_map_db_load_unload_system_for_player<P: MapDataProducer>(
-    player_query: Query<&Transform, With<Player>>,
+    player_query: Query<(&Transform, Option<&FocusMotion>), With<Player>>,
     mut data_map: ResMut<DataMapDoubleBuffered<P>>,
+    mut distance_changes: EventReader<RenderDistanceChange<P>>,
 ) {
-    for player_transform in player_query.as_readonly().iter() {
-        let focus_world_pos = player_transform.translation.xy();
-        let current_focus_chunk_coords =
-            ChunkCoords::from_world_pos(focus_world_pos, data_map.chunk_size_units);
-
-        let mut required_chunks_set: HashSet<ChunkCoords> = HashSet::new();
-
-        for dx in
-            -(data_map.render_distance_chunks as isize)..=(data_map.render_distance_chunks as isize)
-        {
-            for dy in -(data_map.render_distance_chunks as isize)
-                ..=(data_map.render_distance_chunks as isize)
-            {
-                required_chunks_set.insert(ChunkCoords {
-                    x: current_focus_chunk_coords.x + dx,
-                    y: current_focus_chunk_coords.y + dy,
-                });
-            }
-        }
-
-        // Unload chunks from the write buffer that are no longer required.
-        data_map
-            .write_buffer
-            .retain(|coords, _| required_chunks_set.contains(coords));
-
-        // Request new chunks.
-        for coords in required_chunks_set.iter() {
-            if !data_map.write_buffer.contains_key(coords)
-                && !data_map.pending_tasks.contains_key(coords)
-                && !data_map.requested_chunks.contains(coords)
-            {
-                data_map.requested_chunks.insert(*coords);
-            }
-        }
+    if let Some(change) = distance_changes.read().last() {
+        data_map.set_render_distance(change.chunks);
     }
+    data_map.update_foci(&collect_foci(&player_query));
+}
+
+// Position and smoothed velocity of every focus entity
+fn collect_foci<F: QueryFilter>(query: &Query<(&Transform, Option<&FocusMotion>), F>) -> Vec<(Vec2, Vec2)> {
+    query
+        .iter()
+        .map(|(transform, motion)| (transform.translation.xy(), motion.map_or(Vec2::ZERO, |m| m.velocity)))
+        .collect()
 }
 
 // System to spawn background tasks for requested chunks
@@ -314,21 +489,20 @@ pub fn data_map_spawn_tasks_system<P: MapDataProducer>(
     let producer = Arc::new(data_map.producer.clone());
 
     for coords in data_map.requested_chunks.iter() {
-        if !data_map.pending_tasks.contains_key(coords) {
+        // Reads and writes request chunks too, the ones given up on stay unloaded
+        if !data_map.pending_tasks.contains_key(coords) && !data_map.failed_chunks.contains(coords) {
             // Double check for safety
             let chunk_dimension = data_map.chunk_dimension_tiles;
             let current_coords = *coords;
             let pr = producer.clone();
 
-            let task = thread_pool
-                .spawn(async move { pr.generate_chunk(current_coords, chunk_dimension) });
+            // A batch of one, this map doesn't batch its requests
+            let task = thread_pool.spawn(async move {
+                let result = catch_generation_panic(|| pr.generate_chunk(current_coords, chunk_dimension));
+                vec![(current_coords, result)]
+            });
 
-            let task_entity = commands
-                .spawn((
-                    current_coords, // Attach coords for easy lookup by completion system
-                    ChunkGenTask(task),
-                ))
-                .id();
+            let task_entity = commands.spawn(ChunkGenTask(task)).id();
 
             new_pending_tasks.push((current_coords, task_entity));
             // info!(
@@ -349,44 +523,37 @@ pub fn data_map_spawn_tasks_system<P: MapDataProducer>(
 // System to process completed background tasks, inserting results into the WRITE BUFFER
 pub fn data_map_process_completed_tasks_system<P: MapDataProducer>(
     mut commands: Commands,
-    mut query: Query<(Entity, &ChunkCoords, &mut ChunkGenTask<P::GridType>)>,
+    mut query: Query<(Entity, &mut ChunkGenTask<P::GridType>)>,
     mut data_map: ResMut<DataMapDoubleBuffered<P>>,
 ) {
-    for (task_entity, coords, mut gen_task) in query.iter_mut() {
-        if let Some(mut generated_chunk) = future::block_on(future::poll_once(&mut gen_task.0)) {
-            // Apply any writes from the queue to this newly generated chunk
-            let chunk_bottom_left_tile =
-                coords.to_bottom_left_tile_point(data_map.chunk_dimension_tiles);
-            let chunk_dimension_tiles = data_map.chunk_dimension_tiles;
-
-            data_map.write_queue.retain(|&point, value| {
-                if point.x >= chunk_bottom_left_tile.x
-                    && point.x < chunk_bottom_left_tile.x + chunk_dimension_tiles as isize
-                    && point.y >= chunk_bottom_left_tile.y
-                    && point.y < chunk_bottom_left_tile.y + chunk_dimension_tiles as isize
-                {
-                    let local_x =
-                        (point.x.rem_euclid(chunk_dimension_tiles as isize)) as TilesCount;
-                    let local_y =
-                        (point.y.rem_euclid(chunk_dimension_tiles as isize)) as TilesCount;
-                    generated_chunk.grid.set_item(local_x, local_y, *value);
-                    false // Remove from queue
-                } else {
-                    true // Keep in queue
+    let mut applied = 0;
+    for (task_entity, mut gen_task) in query.iter_mut() {
+        if applied >= data_map.max_chunks_applied_per_frame {
+            break; // The rest stay pending, so a burst of completions is spread over frames
+        }
+        if let Some(results) = future::block_on(future::poll_once(&mut gen_task.0)) {
+            for (coords, result) in results {
+                applied += 1;
+                data_map.pending_tasks.remove(&coords);
+                match result {
+                    // Queued writes are applied; a chunk already in the write buffer is kept
+                    Ok(generated_chunk) => {
+                        data_map.generation_failures.remove(&coords);
+                        data_map.insert_generated(coords, generated_chunk);
+                    }
+                    Err(err) => data_map.record_generation_failure(coords, err),
                 }
-            });
-
-            // Insert the completed chunk into the write buffer
-            data_map.write_buffer.insert(*coords, generated_chunk);
-
+            }
             commands.entity(task_entity).despawn();
-            data_map.pending_tasks.remove(coords);
         }
     }
 }
 
 /// System that calls swap_buffers at the end of the frame.
-pub fn swap_map_buffers_system<P: MapDataProducer>(mut data_map: ResMut<DataMapDoubleBuffered<P>>) {
+pub fn swap_map_buffers_system<P: MapDataProducer>(mut data_map: ResMut<DataMapDoubleBuffered<P>>)
+where
+    P::GridType: Clone,
+{
     data_map.swap_buffers();
 }
 
@@ -397,7 +564,7 @@ pub fn insert_chunked_double_buffered_plugin<P>(
 ) -> &mut bevy::prelude::App
 where
     P: MapDataProducer + Send + Sync + Clone + 'static,
-    <P as MapDataProducer>::GridType: Send + Sync,
+    <P as MapDataProducer>::GridType: Send + Sync + Clone,
     <P as MapDataProducer>::Item: Send + Copy + Default + Sync,
 {
     app.add_systems(Startup, move |mut map: ResMut<DataMapDoubleBuffered<P>>| {
@@ -408,6 +575,7 @@ where
         DEFAULT_CHUNK_DIMENSION_TILES,
         DEFAULT_RENDER_DISTANCE_CHUNKS,
     ))
+    .add_event::<RenderDistanceChange<P>>()
     .add_systems(
         Update,
         (
@@ -422,3 +590,131 @@ where
 
     app
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::chunks::FlatGrid;
+
+    const DIMENSION: TilesCount = 8;
+    const ORIGIN: ChunkCoords = ChunkCoords { x: 0, y: 0 };
+    const POINT: Point = Point { x: 3, y: 5 }; // In ORIGIN
+
+    /// Every generated tile is 1, so a 7 can only come from a write.
+    #[derive(Clone, Default)]
+    struct OnesProducer;
+
+    impl MapDataProducer for OnesProducer {
+        type Item = u8;
+        type GridType = FlatGrid<u8>;
+
+        fn default_value(&self) -> u8 {
+            0
+        }
+
+        fn generate_chunk(&self, _coords: ChunkCoords, dimension_tiles: TilesCount) -> DataChunk<FlatGrid<u8>> {
+            DataChunk::new(FlatGrid::new(dimension_tiles, 1))
+        }
+    }
+
+    fn map() -> DataMapDoubleBuffered<OnesProducer> {
+        DataMapDoubleBuffered::new(OnesProducer, DIMENSION, 1)
+    }
+
+    fn generate(map: &mut DataMapDoubleBuffered<OnesProducer>, coords: ChunkCoords) {
+        let chunk = map.producer.generate_chunk(coords, DIMENSION);
+        map.insert_generated(coords, chunk);
+    }
+
+    fn published(map: &DataMapDoubleBuffered<OnesProducer>, point: Point) -> Option<u8> {
+        let chunk = map.read_chunks().get(&ChunkCoords::from_point(point, DIMENSION))?;
+        let x = point.x.rem_euclid(DIMENSION as isize) as TilesCount;
+        let y = point.y.rem_euclid(DIMENSION as isize) as TilesCount;
+        chunk.grid.get_item(x, y).copied()
+    }
+
+    #[test]
+    fn write_then_read_in_the_same_frame() {
+        let mut map = map();
+        generate(&mut map, ORIGIN);
+        map.swap_buffers();
+        assert_eq!(map.read(POINT), Some(1));
+
+        map.write(POINT, 7);
+        assert_eq!(map.read(POINT), Some(7));
+        assert_eq!(map.get(POINT), 7);
+        assert_eq!(published(&map, POINT), Some(1)); // The snapshot only changes on swap
+    }
+
+    #[test]
+    fn write_survives_two_swaps() {
+        let mut map = map();
+        generate(&mut map, ORIGIN);
+        map.swap_buffers();
+        map.write(POINT, 7);
+
+        map.swap_buffers();
+        assert_eq!(map.read(POINT), Some(7));
+        assert_eq!(published(&map, POINT), Some(7));
+        map.swap_buffers();
+        assert_eq!(map.read(POINT), Some(7));
+        assert_eq!(published(&map, POINT), Some(7));
+    }
+
+    #[test]
+    fn write_racing_generation_is_not_lost() {
+        // Written before the chunk exists: queued, then applied to the generated chunk
+        let mut map = map();
+        map.write(POINT, 7);
+        assert_eq!(map.read(POINT), Some(7));
+        assert!(map.requested_chunks.contains(&ORIGIN));
+        generate(&mut map, ORIGIN);
+        assert_eq!(map.read(POINT), Some(7));
+        map.swap_buffers();
+        assert_eq!(map.read(POINT), Some(7));
+
+        // A second generation result for a chunk already written to doesn't replace it
+        map.write(POINT, 8);
+        generate(&mut map, ORIGIN);
+        map.swap_buffers();
+        assert_eq!(map.read(POINT), Some(8));
+        assert_eq!(published(&map, POINT), Some(8));
+    }
+
+    #[test]
+    fn write_chunk_edits_are_read_in_the_same_frame() {
+        let mut map = map();
+        generate(&mut map, ORIGIN);
+        map.swap_buffers();
+
+        assert!(map.write_chunk(ORIGIN, |chunk| {
+            chunk.grid.set_item(1, 2, 9);
+        }));
+        assert_eq!(map.read(Point { x: 1, y: 2 }), Some(9));
+        assert_eq!(map.read(Point { x: 2, y: 2 }), Some(1));
+        map.swap_buffers();
+        assert_eq!(published(&map, Point { x: 1, y: 2 }), Some(9));
+
+        assert!(!map.write_chunk(ChunkCoords { x: 5, y: 5 }, |_| {}));
+    }
+
+    #[test]
+    fn foci_keep_each_others_chunks() {
+        let mut map = map();
+        let far = ChunkCoords { x: 10, y: 0 };
+        let stray = ChunkCoords { x: 5, y: 5 };
+        for coords in [ORIGIN, far, stray] {
+            generate(&mut map, coords);
+        }
+        let center = |coords: ChunkCoords| coords.corner(map.chunk_dimension_tiles).0 + Vec2::splat(map.chunk_size_units / 2.0);
+        let foci = [(center(ORIGIN), Vec2::ZERO), (center(far), Vec2::ZERO)];
+
+        map.update_foci(&foci);
+        assert!(map.write_buffer.contains_key(&ORIGIN));
+        assert!(map.write_buffer.contains_key(&far));
+        assert!(!map.write_buffer.contains_key(&stray));
+        // The neighbors of both are requested
+        assert!(map.requested_chunks.contains(&ChunkCoords { x: 1, y: 0 }));
+        assert!(map.requested_chunks.contains(&ChunkCoords { x: 11, y: 0 }));
+    }
+}
@@ -0,0 +1,178 @@
+use std::marker::PhantomData;
+
+use bevy::{
+    platform::collections::{HashMap, HashSet},
+    prelude::*,
+    tasks::{AsyncComputeTaskPool, Task},
+};
+use futures_lite::future;
+
+use crate::core::{
+    basics::Point,
+    chunks::{
+        ChunkCoords, ChunkGenError, DataChunk, DataMap, GridData, MapDataProducer,
+        catch_generation_panic, lod_factor,
+    },
+    constants::DEFAULT_CHUNK_DIMENSION_TILES,
+    units::TilesCount,
+};
+
+/// Chunk coords and level of detail of a LOD chunk.
+pub type LodKey = (ChunkCoords, u8);
+
+/// Sent when a LOD chunk of `LodDataMap<P>` has been generated and inserted.
+#[derive(Event)]
+pub struct LodChunkLoaded<P: MapDataProducer> {
+    pub coords: ChunkCoords,
+    pub lod: u8,
+    _producer: PhantomData<P>,
+}
+
+impl<P: MapDataProducer> LodChunkLoaded<P> {
+    pub fn new(coords: ChunkCoords, lod: u8) -> Self {
+        Self {
+            coords,
+            lod,
+            _producer: PhantomData,
+        }
+    }
+}
+
+/// Generates one downsampled chunk, a panic in the producer becomes the error.
+type LodGenTask<T> = Task<Result<DataChunk<T>, ChunkGenError>>;
+
+/// Downsampled chunks of `DataMap<P>` for views that don't need every tile, such as far away
+/// hypertiles. Chunks use the DataMap's coords and dimension, each item covering `lod_factor(lod)`
+/// tiles per axis. They are generated straight from the producer, so they skip post-processing,
+/// the chunk store and writes: read-only and approximate by design.
+#[derive(Resource)]
+pub struct LodDataMap<P: MapDataProducer> {
+    pub loaded_chunks: HashMap<LodKey, DataChunk<P::GridType>>,
+    pub requested_chunks: HashSet<LodKey>,
+    pending_tasks: HashMap<LodKey, LodGenTask<P::GridType>>,
+    pub failed_chunks: HashSet<LodKey>, // Generation panicked, never requested again
+    pub chunk_dimension_tiles: TilesCount, // Follows the DataMap, see `lod_spawn_tasks_system`
+    pub frame: u64,
+    pub max_tasks_in_flight: usize,
+    pub evict_after_frames: u64, // Chunks not read through `ensure` for this long are dropped
+}
+
+impl<P: MapDataProducer> Default for LodDataMap<P> {
+    fn default() -> Self {
+        Self {
+            loaded_chunks: HashMap::new(),
+            requested_chunks: HashSet::new(),
+            pending_tasks: HashMap::new(),
+            failed_chunks: HashSet::new(),
+            chunk_dimension_tiles: DEFAULT_CHUNK_DIMENSION_TILES,
+            frame: 0,
+            max_tasks_in_flight: 8,
+            evict_after_frames: 600,
+        }
+    }
+}
+
+impl<P: MapDataProducer> LodDataMap<P> {
+    /// Whether the chunk can be read (or never will be, as it failed). Requests it otherwise,
+    /// and keeps it from being evicted either way.
+    pub fn ensure(&mut self, coords: ChunkCoords, lod: u8) -> bool {
+        let key = (coords, lod);
+        if let Some(chunk) = self.loaded_chunks.get_mut(&key) {
+            chunk.last_touched = self.frame;
+            return true;
+        }
+        if self.failed_chunks.contains(&key) {
+            return true;
+        }
+        if !self.pending_tasks.contains_key(&key) {
+            self.requested_chunks.insert(key);
+        }
+        false
+    }
+
+    /// Item covering the tile at `point`, or None if its LOD chunk is not loaded.
+    pub fn read(&self, point: Point, lod: u8) -> Option<P::Item> {
+        let coords = ChunkCoords::from_point(point, self.chunk_dimension_tiles);
+        let chunk = self.loaded_chunks.get(&(coords, lod))?;
+        let factor = lod_factor(lod);
+        let (local_x, local_y) = point.local_in_chunk(self.chunk_dimension_tiles);
+        chunk.grid.get_item(local_x / factor, local_y / factor).copied()
+    }
+}
+
+/// Spawns generation tasks for requested LOD chunks and evicts the ones nobody read lately.
+pub fn lod_spawn_tasks_system<P: MapDataProducer>(
+    data_map: Res<DataMap<P>>,
+    mut lod_map: ResMut<LodDataMap<P>>,
+) {
+    lod_map.frame += 1;
+    // The DataMap may be reconfigured after this map was created, and chunks of another size are useless
+    if lod_map.chunk_dimension_tiles != data_map.chunk_dimension_tiles {
+        lod_map.chunk_dimension_tiles = data_map.chunk_dimension_tiles;
+        lod_map.loaded_chunks.clear();
+    }
+    let (frame, evict_after) = (lod_map.frame, lod_map.evict_after_frames);
+    lod_map
+        .loaded_chunks
+        .retain(|_, chunk| frame.saturating_sub(chunk.last_touched) <= evict_after);
+
+    let free_slots = lod_map.max_tasks_in_flight.saturating_sub(lod_map.pending_tasks.len());
+    let next: Vec<LodKey> = lod_map.requested_chunks.iter().take(free_slots).copied().collect();
+    let thread_pool = AsyncComputeTaskPool::get();
+    let dimension = lod_map.chunk_dimension_tiles;
+    for key in next {
+        lod_map.requested_chunks.remove(&key);
+        // Carries the seed applied to the DataMap's producer
+        let producer = data_map.producer.clone();
+        let task = thread_pool.spawn(async move {
+            catch_generation_panic(|| producer.generate_chunk_lod(key.0, dimension, key.1))
+        });
+        lod_map.pending_tasks.insert(key, task);
+    }
+}
+
+/// Inserts finished LOD chunks and sends `LodChunkLoaded` for them.
+pub fn lod_process_completed_tasks_system<P: MapDataProducer>(
+    mut lod_map: ResMut<LodDataMap<P>>,
+    mut loaded_events: EventWriter<LodChunkLoaded<P>>,
+) {
+    let mut completed = Vec::new();
+    for (key, task) in lod_map.pending_tasks.iter_mut() {
+        if let Some(result) = future::block_on(future::poll_once(task)) {
+            completed.push((*key, result));
+        }
+    }
+    for (key, result) in completed {
+        lod_map.pending_tasks.remove(&key);
+        match result {
+            Ok(mut chunk) => {
+                chunk.last_touched = lod_map.frame;
+                lod_map.loaded_chunks.insert(key, chunk);
+                loaded_events.write(LodChunkLoaded::new(key.0, key.1));
+            }
+            Err(err) => {
+                warn!(
+                    "LodDataMap<{}> chunk {:?} at LOD {} generation panicked ({}), giving up",
+                    std::any::type_name::<P>(),
+                    key.0,
+                    key.1,
+                    err.0
+                );
+                lod_map.failed_chunks.insert(key);
+            }
+        }
+    }
+}
+
+/// Adds `LodDataMap<P>` and its systems, once. `DataMap<P>` has to be registered too (see `insert_chunked_plugin`).
+pub fn insert_lod_map<P: MapDataProducer>(app: &mut App) -> &mut App {
+    if app.world().contains_resource::<LodDataMap<P>>() {
+        return app;
+    }
+    app.init_resource::<LodDataMap<P>>()
+        .add_event::<LodChunkLoaded<P>>()
+        .add_systems(
+            PreUpdate,
+            (lod_process_completed_tasks_system::<P>, lod_spawn_tasks_system::<P>).chain(),
+        )
+}
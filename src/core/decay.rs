@@ -0,0 +1,281 @@
+use std::{collections::VecDeque, marker::PhantomData};
+
+use bevy::{platform::collections::HashMap, prelude::*};
+
+use crate::core::chunks::{ChunkCoords, ChunkLoaded, ChunkWritten, DataMap, GridData, MapDataProducer};
+
+const REST_EPSILON: f32 = 1e-3; // Values closer than this to the baseline snap to it
+
+/// Tile items fading toward a baseline, see `DecayPlugin`.
+pub trait Decayable: Copy {
+    /// The item after `dt` seconds of decay at `rate`. One call for a long `dt` gives the same
+    /// result as many short ones, so chunks left alone for a while catch up in one go.
+    fn decay(self, dt: f32, rate: f32) -> Self;
+    /// At the baseline, decaying doesn't change it anymore.
+    fn is_at_rest(&self) -> bool;
+}
+
+/// Exponential decay toward 0.0, `rate` being the fraction lost per second on a log scale:
+/// after `1.0 / rate` seconds about 37% of the value is left.
+impl Decayable for f32 {
+    fn decay(self, dt: f32, rate: f32) -> Self {
+        let value = self * (-rate * dt.max(0.0)).exp();
+        if value.abs() < REST_EPSILON { 0.0 } else { value }
+    }
+
+    fn is_at_rest(&self) -> bool {
+        *self == 0.0
+    }
+}
+
+/// How fast the items of `DataMap<P>` decay, see `DecayPlugin`.
+#[derive(Resource, Debug, Clone)]
+pub struct DecaySettings<P: MapDataProducer> {
+    pub rate: f32,              // Passed to `Decayable::decay`
+    pub chunks_per_tick: usize, // Active chunks decayed per fixed tick, the others catch up on their turn
+    _producer: PhantomData<fn() -> P>,
+}
+
+/// Chunks of `DataMap<P>` holding items that aren't at rest, with the fixed time they were last
+/// decayed at. A written or loaded chunk joins, a chunk leaves once all of its items are at rest.
+#[derive(Resource, Debug)]
+pub struct DecayState<P: MapDataProducer> {
+    pub last_decayed_secs: HashMap<ChunkCoords, f32>,
+    pub queue: VecDeque<ChunkCoords>, // Visiting order of the active chunks
+    _producer: PhantomData<fn() -> P>,
+}
+
+impl<P: MapDataProducer> Default for DecayState<P> {
+    fn default() -> Self {
+        Self {
+            last_decayed_secs: HashMap::new(),
+            queue: VecDeque::new(),
+            _producer: PhantomData,
+        }
+    }
+}
+
+impl<P: MapDataProducer> DecayState<P> {
+    /// Queues `coords` unless it's already active, its decay counting from `now`.
+    pub fn activate(&mut self, coords: ChunkCoords, now: f32) {
+        if !self.last_decayed_secs.contains_key(&coords) {
+            self.last_decayed_secs.insert(coords, now);
+            self.queue.push_back(coords);
+        }
+    }
+}
+
+/// Decays every item of `DataMap<P>` toward its baseline over time, without walking every loaded
+/// chunk each tick: only chunks written or loaded since they last came to rest are visited, at
+/// most `chunks_per_tick` of them per fixed tick, each getting all the decay since its last visit.
+/// Tiles written between two visits decay from the earlier one, so fresh values lose up to a
+/// round of the queue too early.
+pub struct DecayPlugin<P: MapDataProducer> {
+    pub rate: f32,
+    pub chunks_per_tick: usize,
+    _producer: PhantomData<fn() -> P>,
+}
+
+impl<P: MapDataProducer> DecayPlugin<P> {
+    pub fn new(rate: f32) -> Self {
+        Self {
+            rate,
+            chunks_per_tick: 4,
+            _producer: PhantomData,
+        }
+    }
+}
+
+impl<P: MapDataProducer> Plugin for DecayPlugin<P>
+where
+    P::Item: Decayable,
+{
+    fn build(&self, app: &mut App) {
+        app.insert_resource(DecaySettings::<P> {
+            rate: self.rate,
+            chunks_per_tick: self.chunks_per_tick,
+            _producer: PhantomData,
+        })
+        .init_resource::<DecayState<P>>()
+        // Events are read every frame, fixed ticks may skip frames
+        .add_systems(Update, decay_activate_system::<P>)
+        .add_systems(FixedUpdate, decay_system::<P>);
+    }
+}
+
+// Activates the chunks written or loaded this frame, on the fixed clock the decay runs on
+fn decay_activate_system<P: MapDataProducer>(
+    time: Res<Time<Fixed>>,
+    mut written_events: EventReader<ChunkWritten<P>>,
+    mut loaded_events: EventReader<ChunkLoaded<P>>,
+    mut state: ResMut<DecayState<P>>,
+) {
+    let now = time.elapsed_secs();
+    for coords in written_events
+        .read()
+        .map(|event| event.coords)
+        .chain(loaded_events.read().map(|event| event.coords))
+    {
+        state.activate(coords, now);
+    }
+}
+
+/// Decays the next `chunks_per_tick` active chunks by the time since their last visit.
+pub fn decay_system<P: MapDataProducer>(
+    time: Res<Time>,
+    settings: Res<DecaySettings<P>>,
+    mut state: ResMut<DecayState<P>>,
+    mut data_map: ResMut<DataMap<P>>,
+) where
+    P::Item: Decayable,
+{
+    let now = time.elapsed_secs();
+    let budget = settings.chunks_per_tick.min(state.queue.len());
+    for _ in 0..budget {
+        let Some(coords) = state.queue.pop_front() else {
+            break;
+        };
+        let Some(last) = state.last_decayed_secs.get(&coords).copied() else {
+            continue;
+        };
+        // Unloaded chunks leave, they join again with their ChunkLoaded
+        let Some(chunk) = data_map.loaded_chunks.get_mut(&coords) else {
+            state.last_decayed_secs.remove(&coords);
+            continue;
+        };
+        let dt = now - last;
+        let (mut changed, mut at_rest) = (false, true);
+        chunk.grid.map_in_place(|_, _, item| {
+            if item.is_at_rest() {
+                return item;
+            }
+            changed = true;
+            let decayed = item.decay(dt, settings.rate);
+            at_rest &= decayed.is_at_rest();
+            decayed
+        });
+        if changed {
+            data_map.mark_chunk_written(coords);
+        }
+        if at_rest {
+            state.last_decayed_secs.remove(&coords);
+        } else {
+            state.last_decayed_secs.insert(coords, now);
+            state.queue.push_back(coords);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{
+        core::{basics::Point, chunks::MapDataProducer},
+        game::world::heat::{Heat, HeatProducer},
+    };
+
+    const RATE: f32 = 0.2;
+    const DIMENSION: usize = 4;
+    const A: ChunkCoords = ChunkCoords { x: 0, y: 0 };
+    const B: ChunkCoords = ChunkCoords { x: 1, y: 0 };
+    const TILE_A: Point = Point { x: 1, y: 1 };
+    const TILE_B: Point = Point { x: 5, y: 1 };
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!((actual - expected).abs() <= expected.abs() * 1e-4, "{actual} != {expected}");
+    }
+
+    #[test]
+    fn one_long_decay_matches_many_short_ones() {
+        let mut stepped = 100.0f32;
+        for _ in 0..100 {
+            stepped = stepped.decay(0.1, RATE);
+        }
+        let caught_up = 100.0f32.decay(10.0, RATE);
+        assert_close(caught_up, 100.0 * (-RATE * 10.0).exp());
+        assert_close(stepped, caught_up);
+        assert_eq!(100.0f32.decay(-1.0, RATE), 100.0, "time never runs backwards");
+        assert_eq!(100.0f32.decay(0.0, RATE), 100.0);
+    }
+
+    #[test]
+    fn values_near_the_baseline_snap_to_rest() {
+        let faded = 100.0f32.decay(100.0, RATE); // e^-20 of it left
+        assert_eq!(faded, 0.0);
+        assert!(faded.is_at_rest());
+        assert!(!REST_EPSILON.is_at_rest());
+        assert_eq!((REST_EPSILON / 2.0).decay(0.0, RATE), 0.0);
+        assert_close((-100.0f32).decay(10.0, RATE), -100.0 * (-RATE * 10.0).exp()); // From below too
+    }
+
+    fn app(chunks_per_tick: usize) -> App {
+        let mut app = App::new();
+        let mut map = DataMap::new(HeatProducer, DIMENSION, 1);
+        for coords in [A, B] {
+            let chunk = map.producer.generate_chunk(coords, DIMENSION);
+            map.loaded_chunks.insert(coords, chunk);
+        }
+        map.write(TILE_A, Heat(100.0));
+        map.write(TILE_B, Heat(100.0));
+        let mut state = DecayState::<HeatProducer>::default();
+        state.activate(A, 0.0);
+        state.activate(B, 0.0);
+        app.insert_resource(map)
+            .insert_resource(state)
+            .insert_resource(DecaySettings::<HeatProducer> {
+                rate: RATE,
+                chunks_per_tick,
+                _producer: PhantomData,
+            })
+            .insert_resource(Time::<()>::default())
+            .add_systems(Update, decay_system::<HeatProducer>);
+        app
+    }
+
+    fn advance_and_update(app: &mut App, secs: u64) {
+        app.world_mut().resource_mut::<Time>().advance_by(Duration::from_secs(secs));
+        app.update();
+    }
+
+    fn heat(app: &App, tile: Point) -> f32 {
+        app.world().resource::<DataMap<HeatProducer>>().read(tile).unwrap().0
+    }
+
+    #[test]
+    fn idle_chunks_catch_up_on_all_the_decay_since_their_last_visit() {
+        let mut app = app(1); // One chunk per tick, the other waits its turn
+        advance_and_update(&mut app, 10);
+        assert_close(heat(&app, TILE_A), 100.0 * (-RATE * 10.0).exp());
+        assert_eq!(heat(&app, TILE_B), 100.0);
+
+        advance_and_update(&mut app, 5);
+        assert_close(heat(&app, TILE_B), 100.0 * (-RATE * 15.0).exp());
+        assert_close(heat(&app, TILE_A), 100.0 * (-RATE * 10.0).exp());
+
+        // A's second visit only applies the time since its first one
+        app.update();
+        assert_close(heat(&app, TILE_A), 100.0 * (-RATE * 15.0).exp());
+        let state = app.world().resource::<DecayState<HeatProducer>>();
+        assert_eq!(state.last_decayed_secs.get(&A), Some(&15.0));
+        assert_eq!(state.queue, VecDeque::from([B, A]));
+    }
+
+    #[test]
+    fn chunks_at_rest_leave_the_active_set() {
+        let mut app = app(2);
+        advance_and_update(&mut app, 100);
+        assert_eq!((heat(&app, TILE_A), heat(&app, TILE_B)), (0.0, 0.0));
+        let state = app.world().resource::<DecayState<HeatProducer>>();
+        assert!(state.last_decayed_secs.is_empty());
+        assert!(state.queue.is_empty());
+
+        // Written again, a chunk joins again and decays from then on
+        app.world_mut().resource_mut::<DataMap<HeatProducer>>().write(TILE_A, Heat(50.0));
+        app.world_mut().resource_mut::<DecayState<HeatProducer>>().activate(A, 100.0);
+        advance_and_update(&mut app, 5);
+        assert_close(heat(&app, TILE_A), 50.0 * (-RATE * 5.0).exp());
+        assert_eq!(app.world().resource::<DecayState<HeatProducer>>().queue, VecDeque::from([A]));
+    }
+}
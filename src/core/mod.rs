@@ -1,6 +1,13 @@
 pub mod basics;
 pub mod chunks;
 pub mod chunks_double_buf;
+pub mod chunks_lod;
+pub mod decay;
 pub mod layered;
+pub mod noise;
+pub mod raycast;
+pub mod rng;
+pub mod savegame;
 pub mod units;
+pub mod validation;
 pub mod constants;
@@ -0,0 +1,52 @@
+// --- Seeded noise for world generation ---
+// Pure functions of (seed, coordinates), so chunks generated independently agree on every tile.
+
+/// Mixes a seed and lattice coordinates into a well distributed 64 bit value (xorshift-multiply rounds).
+pub fn hash2(seed: u64, x: isize, y: isize) -> u64 {
+    let mut h = seed ^ 0x9E37_79B9_7F4A_7C15;
+    h ^= (x as u64).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    h = (h ^ (h >> 31)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    h ^= (y as u64).wrapping_mul(0xD6E8_FEB8_6659_FD93);
+    h = (h ^ (h >> 29)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    h ^ (h >> 32)
+}
+
+/// `hash2` mapped to [0, 1).
+pub fn hash2_unit(seed: u64, x: isize, y: isize) -> f32 {
+    (hash2(seed, x, y) >> 40) as f32 / (1u64 << 24) as f32
+}
+
+/// Value noise in [0, 1): random values on the integer lattice, smoothly interpolated in between.
+pub fn value_noise(seed: u64, x: f32, y: f32) -> f32 {
+    let x0 = x.floor();
+    let y0 = y.floor();
+    let (ix, iy) = (x0 as isize, y0 as isize);
+    let smooth = |t: f32| t * t * (3.0 - 2.0 * t);
+    let tx = smooth(x - x0);
+    let ty = smooth(y - y0);
+
+    let v00 = hash2_unit(seed, ix, iy);
+    let v10 = hash2_unit(seed, ix + 1, iy);
+    let v01 = hash2_unit(seed, ix, iy + 1);
+    let v11 = hash2_unit(seed, ix + 1, iy + 1);
+    let bottom = v00 + (v10 - v00) * tx;
+    let top = v01 + (v11 - v01) * tx;
+    bottom + (top - bottom) * ty
+}
+
+/// Fractal value noise in [0, 1): `octaves` layers, each at twice the frequency and half the amplitude.
+pub fn fbm(seed: u64, x: f32, y: f32, octaves: u32) -> f32 {
+    let mut total = 0.0;
+    let mut amplitude = 1.0;
+    let mut frequency = 1.0;
+    let mut norm = 0.0;
+    for octave in 0..octaves.max(1) {
+        // Every octave gets its own lattice, otherwise they line up at the origin
+        let octave_seed = seed.wrapping_add(octave as u64).wrapping_mul(0x2545_F491_4F6C_DD1D);
+        total += value_noise(octave_seed, x * frequency, y * frequency) * amplitude;
+        norm += amplitude;
+        amplitude *= 0.5;
+        frequency *= 2.0;
+    }
+    total / norm
+}
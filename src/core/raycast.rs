@@ -0,0 +1,315 @@
+use bevy::math::Vec2;
+
+use crate::core::{
+    basics::Point,
+    chunks::{DataMap, MapDataProducer},
+    constants::TILE_SIZE_IN_UNITS,
+};
+
+/// Outcome of a line-of-sight check, see `tile_raycast`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RaycastResult {
+    Clear,
+    Blocked {
+        at: Point,
+        distance_tiles: usize, // Steps from the start tile
+    },
+    Unknown {
+        at: Point, // First tile of the ray that isn't loaded, met before any blocker
+    },
+}
+
+impl RaycastResult {
+    pub fn is_clear(&self) -> bool {
+        matches!(self, RaycastResult::Clear)
+    }
+
+    /// Tile the ray stopped at, blocked or unloaded.
+    pub fn stop(&self) -> Option<Point> {
+        match *self {
+            RaycastResult::Clear => None,
+            RaycastResult::Blocked { at, .. } | RaycastResult::Unknown { at } => Some(at),
+        }
+    }
+}
+
+/// Tiles on the Bresenham line from `from` to `to`, both included. Consecutive tiles touch by
+/// a side or a corner, a zero-length ray yields its single tile.
+#[derive(Debug, Clone)]
+pub struct RayTiles {
+    current: Point,
+    end: Point,
+    delta: Point, // Absolute distances along each axis
+    step: Point,  // -1 or 1 along each axis
+    error: isize,
+    done: bool,
+}
+
+impl Iterator for RayTiles {
+    type Item = Point;
+
+    fn next(&mut self) -> Option<Point> {
+        if self.done {
+            return None;
+        }
+        let tile = self.current;
+        if tile == self.end {
+            self.done = true;
+            return Some(tile);
+        }
+        let doubled = 2 * self.error;
+        if doubled > -self.delta.y {
+            self.error -= self.delta.y;
+            self.current.x += self.step.x;
+        }
+        if doubled < self.delta.x {
+            self.error += self.delta.x;
+            self.current.y += self.step.y;
+        }
+        Some(tile)
+    }
+}
+
+/// Tiles from `from` to `to` for line of sight, usable without a map (debug lines, previews).
+pub fn ray_tiles(from: Point, to: Point) -> RayTiles {
+    let delta = Point {
+        x: (to.x - from.x).abs(),
+        y: (to.y - from.y).abs(),
+    };
+    RayTiles {
+        current: from,
+        end: to,
+        delta,
+        step: Point {
+            x: if to.x >= from.x { 1 } else { -1 },
+            y: if to.y >= from.y { 1 } else { -1 },
+        },
+        error: delta.x - delta.y,
+        done: false,
+    }
+}
+
+/// Tiles the segment between two world positions passes through, in order, including the tiles
+/// holding both ends. Unlike `ray_tiles` it follows the exact segment, so two rays starting in
+/// the same tile at different offsets can cross different tiles. Tiles touching the segment
+/// only at a corner are skipped.
+#[derive(Debug, Clone)]
+pub struct WorldRayTiles {
+    current: Point,
+    end: Point,
+    step: Point,
+    t_max: Vec2,   // Ray fraction at which the next tile border is crossed along each axis
+    t_delta: Vec2, // Ray fraction between two borders along each axis
+    done: bool,
+}
+
+impl Iterator for WorldRayTiles {
+    type Item = Point;
+
+    fn next(&mut self) -> Option<Point> {
+        if self.done {
+            return None;
+        }
+        let tile = self.current;
+        if tile == self.end || self.t_max.min_element() > 1.0 {
+            self.done = true; // The float walk may stop a border short of the end tile
+            return Some(tile);
+        }
+        if self.t_max.x < self.t_max.y {
+            self.current.x += self.step.x;
+            self.t_max.x += self.t_delta.x;
+        } else {
+            self.current.y += self.step.y;
+            self.t_max.y += self.t_delta.y;
+        }
+        Some(tile)
+    }
+}
+
+/// See `WorldRayTiles`.
+pub fn world_ray_tiles(from: Vec2, to: Vec2) -> WorldRayTiles {
+    // In tile space tile n covers [n, n + 1), matching `WorldPos::to_tile` rounding to centers
+    let start = from / TILE_SIZE_IN_UNITS + Vec2::splat(0.5);
+    let end = to / TILE_SIZE_IN_UNITS + Vec2::splat(0.5);
+    let direction = end - start;
+    let tile_of = |p: Vec2| Point {
+        x: p.x.floor() as isize,
+        y: p.y.floor() as isize,
+    };
+    let current = tile_of(start);
+    let axis = |d: f32, s: f32, tile: isize| -> (isize, f32, f32) {
+        if d == 0.0 {
+            return (0, f32::INFINITY, f32::INFINITY);
+        }
+        let border = if d > 0.0 { tile as f32 + 1.0 - s } else { s - tile as f32 };
+        (d.signum() as isize, border / d.abs(), 1.0 / d.abs())
+    };
+    let (step_x, t_max_x, t_delta_x) = axis(direction.x, start.x, current.x);
+    let (step_y, t_max_y, t_delta_y) = axis(direction.y, start.y, current.y);
+    WorldRayTiles {
+        current,
+        end: tile_of(end),
+        step: Point { x: step_x, y: step_y },
+        t_max: Vec2::new(t_max_x, t_max_y),
+        t_delta: Vec2::new(t_delta_x, t_delta_y),
+        done: false,
+    }
+}
+
+/// Walks `tiles`, stopping at the first one that is unloaded or `blocks`. The first tile is
+/// where the ray starts from, it has to be loaded but never blocks.
+fn raycast_along<P, F>(map: &DataMap<P>, tiles: impl Iterator<Item = Point>, blocks: F) -> RaycastResult
+where
+    P: MapDataProducer,
+    F: Fn(P::Item) -> bool,
+{
+    for (distance_tiles, at) in tiles.enumerate() {
+        let Some(item) = map.read(at) else {
+            return RaycastResult::Unknown { at };
+        };
+        if distance_tiles > 0 && blocks(item) {
+            return RaycastResult::Blocked { at, distance_tiles };
+        }
+    }
+    RaycastResult::Clear
+}
+
+/// Whether `from` sees `to` across `map`, following `ray_tiles`. The start tile never blocks,
+/// the end tile does: a blocking target is `Blocked { at: to, .. }`, which callers that only
+/// care about what's in between can accept.
+pub fn tile_raycast<P, F>(map: &DataMap<P>, from: Point, to: Point, blocks: F) -> RaycastResult
+where
+    P: MapDataProducer,
+    F: Fn(P::Item) -> bool,
+{
+    raycast_along(map, ray_tiles(from, to), blocks)
+}
+
+/// `tile_raycast` between two world positions, following `world_ray_tiles`.
+pub fn world_raycast<P, F>(map: &DataMap<P>, from: Vec2, to: Vec2, blocks: F) -> RaycastResult
+where
+    P: MapDataProducer,
+    F: Fn(P::Item) -> bool,
+{
+    raycast_along(map, world_ray_tiles(from, to), blocks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{
+        chunks::{BitGrid, BoolProducer, ChunkCoords, DataChunk, GridData},
+        units::TilesCount,
+    };
+
+    const DIMENSION: TilesCount = 8;
+
+    fn point(x: isize, y: isize) -> Point {
+        Point { x, y }
+    }
+
+    fn tiles(from: Point, to: Point) -> Vec<Point> {
+        ray_tiles(from, to).collect()
+    }
+
+    /// Flags map with chunks (0, 0) and (1, 0) loaded, set flags are `walls`.
+    fn map(walls: &[(isize, isize)]) -> DataMap<BoolProducer> {
+        let mut map = DataMap::new(BoolProducer::default(), DIMENSION, 1);
+        for x in 0..2 {
+            let mut grid = BitGrid::new(DIMENSION, false);
+            for &(wall_x, wall_y) in walls {
+                let wall = point(wall_x, wall_y);
+                if ChunkCoords::from_point(wall, DIMENSION) == (ChunkCoords { x, y: 0 }) {
+                    let (local_x, local_y) = wall.local_in_chunk(DIMENSION);
+                    grid.set_item(local_x, local_y, true);
+                }
+            }
+            map.loaded_chunks.insert(ChunkCoords { x, y: 0 }, DataChunk::new(grid));
+        }
+        map
+    }
+
+    fn wall(item: bool) -> bool {
+        item
+    }
+
+    #[test]
+    fn rays_along_axes() {
+        assert_eq!(tiles(point(0, 0), point(3, 0)), [point(0, 0), point(1, 0), point(2, 0), point(3, 0)]);
+        assert_eq!(tiles(point(2, 1), point(2, -1)), [point(2, 1), point(2, 0), point(2, -1)]);
+        assert_eq!(tiles(point(-1, 5), point(-4, 5)).len(), 4);
+    }
+
+    #[test]
+    fn rays_along_diagonals() {
+        assert_eq!(tiles(point(0, 0), point(3, 3)), [point(0, 0), point(1, 1), point(2, 2), point(3, 3)]);
+        assert_eq!(tiles(point(0, 0), point(-2, 2)), [point(0, 0), point(-1, 1), point(-2, 2)]);
+        assert_eq!(tiles(point(1, -1), point(-1, -3)), [point(1, -1), point(0, -2), point(-1, -3)]);
+    }
+
+    #[test]
+    fn zero_length_ray_is_its_tile() {
+        assert_eq!(tiles(point(-3, 7), point(-3, 7)), [point(-3, 7)]);
+        assert_eq!(tile_raycast(&map(&[]), point(2, 2), point(2, 2), wall), RaycastResult::Clear);
+    }
+
+    #[test]
+    fn every_ray_steps_one_tile_at_a_time_from_start_to_end() {
+        let from = point(1, -2);
+        for x in -6..=6 {
+            for y in -6..=6 {
+                let to = point(x, y);
+                let ray = tiles(from, to);
+                assert_eq!(ray.first(), Some(&from));
+                assert_eq!(ray.last(), Some(&to));
+                assert_eq!(ray.len(), from.chebyshev_distance(to) + 1, "to {to:?}");
+                assert!(ray.windows(2).all(|w| w[0].chebyshev_distance(w[1]) == 1), "to {to:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn raycast_is_blocked_by_the_first_wall() {
+        let map = map(&[(4, 1), (6, 1)]);
+        let result = tile_raycast(&map, point(1, 1), point(7, 1), wall);
+        assert_eq!(result, RaycastResult::Blocked { at: point(4, 1), distance_tiles: 3 });
+        assert_eq!(tile_raycast(&map, point(1, 2), point(7, 2), wall), RaycastResult::Clear);
+        // The start tile never blocks, the target does
+        assert!(tile_raycast(&map, point(4, 1), point(4, 3), wall).is_clear());
+        assert_eq!(tile_raycast(&map, point(4, 3), point(4, 1), wall).stop(), Some(point(4, 1)));
+    }
+
+    #[test]
+    fn raycast_across_chunk_borders() {
+        // Chunk (1, 0) is loaded, (1, 1) and (2, 0) aren't
+        let map = map(&[(10, 3)]);
+        let result = tile_raycast(&map, point(2, 3), point(14, 3), wall);
+        assert_eq!(result, RaycastResult::Blocked { at: point(10, 3), distance_tiles: 8 });
+        assert!(tile_raycast(&map, point(2, 4), point(15, 4), wall).is_clear());
+        assert_eq!(tile_raycast(&map, point(2, 4), point(18, 4), wall), RaycastResult::Unknown { at: point(16, 4) });
+        // A wall before the unloaded chunk wins
+        assert_eq!(tile_raycast(&map, point(2, 3), point(20, 3), wall).stop(), Some(point(10, 3)));
+        assert_eq!(tile_raycast(&map, point(9, 6), point(9, 9), wall), RaycastResult::Unknown { at: point(9, 8) });
+    }
+
+    #[test]
+    fn world_rays_follow_the_exact_segment() {
+        let tile = TILE_SIZE_IN_UNITS;
+        let world = |x: f32, y: f32| Vec2::new(x * tile, y * tile);
+        let straight: Vec<Point> = world_ray_tiles(world(0.0, 0.4), world(2.0, 0.4)).collect();
+        assert_eq!(straight, [point(0, 0), point(1, 0), point(2, 0)]);
+
+        // Crosses into the row above partway, each step is to a side neighbor
+        let rising: Vec<Point> = world_ray_tiles(world(0.0, 0.4), world(2.0, 0.6)).collect();
+        assert_eq!(rising.first(), Some(&point(0, 0)));
+        assert_eq!(rising.last(), Some(&point(2, 1)));
+        assert!(rising.windows(2).all(|w| w[0].manhattan_distance(w[1]) == 1), "{rising:?}");
+
+        let inside: Vec<Point> = world_ray_tiles(world(-0.3, 0.2), world(0.3, -0.2)).collect();
+        assert_eq!(inside, [point(0, 0)]);
+
+        let map = map(&[(2, 1)]);
+        assert!(world_raycast(&map, world(0.0, 0.4), world(3.0, 0.4), wall).is_clear());
+        assert_eq!(world_raycast(&map, world(0.0, 0.6), world(3.0, 0.6), wall).stop(), Some(point(2, 1)));
+    }
+}
@@ -0,0 +1,24 @@
+use bevy::prelude::*;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+use crate::core::chunks::WorldSeed;
+
+const SIM_RNG_SEED_SALT: u64 = 0x5E_ED0F_5111; // Gameplay randomness doesn't repeat the terrain noise
+
+/// Randomness of gameplay systems. Seeded from the `WorldSeed`, so runs with the same seed
+/// and inputs are reproducible; use it instead of `rand::rng()` outside of world setup.
+#[derive(Resource, Debug, Clone)]
+pub struct SimRng(pub ChaCha8Rng);
+
+impl SimRng {
+    pub fn from_seed(seed: u64) -> Self {
+        Self(ChaCha8Rng::seed_from_u64(seed ^ SIM_RNG_SEED_SALT))
+    }
+}
+
+impl FromWorld for SimRng {
+    fn from_world(world: &mut World) -> Self {
+        Self::from_seed(world.get_resource::<WorldSeed>().map_or(0, |seed| seed.0))
+    }
+}
@@ -0,0 +1,294 @@
+use std::{collections::BTreeMap, fs, path::PathBuf};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+use crate::{
+    core::{
+        basics::Point,
+        chunks::{DataMap, MapDataProducer, WorldSeed},
+    },
+    game::{
+        Player,
+        physix::{PrevXY, Velocity},
+    },
+};
+
+const SAVE_FILE_NAME: &str = "savegame.ron";
+
+/// Opt-in for DataMaps whose modified tiles are stored in save games.
+pub trait SaveableProducer: MapDataProducer<Item: Serialize + DeserializeOwned> {
+    /// Unique key of the map's journal inside the save file.
+    const SAVE_NAME: &'static str;
+}
+
+/// Everything a save game restores; the terrain itself is regenerated from the seed.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct SaveGame {
+    pub player_translation: [f32; 3],
+    pub world_seed: u64,
+    // RON encoded `Vec<(Point, Item)>` of every saveable map, by `SaveableProducer::SAVE_NAME`
+    pub tile_overrides: BTreeMap<String, String>,
+}
+
+impl SaveGame {
+    pub fn to_ron(&self) -> Result<String, ron::Error> {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+    }
+
+    pub fn from_ron(text: &str) -> Result<Self, ron::error::SpannedError> {
+        ron::from_str(text)
+    }
+
+    /// Stores the journal of `map`, replacing an older one of the same producer.
+    pub fn store_map<P: SaveableProducer>(&mut self, map: &DataMap<P>) -> Result<(), ron::Error> {
+        let mut tiles: Vec<(Point, P::Item)> = map.modified.iter().map(|(p, v)| (*p, *v)).collect();
+        tiles.sort_by_key(|(p, _)| (p.y, p.x)); // Stable output for identical journals
+        self.tile_overrides.insert(P::SAVE_NAME.to_string(), ron::to_string(&tiles)?);
+        Ok(())
+    }
+
+    /// Replays the stored journal of `P` into `map` via `write`, so unloaded chunks queue the tiles.
+    pub fn restore_map<P: SaveableProducer>(
+        &self,
+        map: &mut DataMap<P>,
+    ) -> Result<(), ron::error::SpannedError> {
+        let Some(text) = self.tile_overrides.get(P::SAVE_NAME) else {
+            return Ok(());
+        };
+        let tiles: Vec<(Point, P::Item)> = ron::from_str(text)?;
+        for (point, value) in tiles {
+            map.write(point, value);
+        }
+        Ok(())
+    }
+}
+
+// Functions storing and restoring one DataMap, registered by `register_saveable_map`
+struct SaveableMapHooks {
+    store: fn(&World, &mut SaveGame),
+    restore: fn(&mut World, &SaveGame),
+}
+
+/// Every DataMap included in save games.
+#[derive(Resource, Default)]
+pub struct SaveableMaps(Vec<SaveableMapHooks>);
+
+/// Where save games are written, one file per world seed.
+#[derive(Resource, Debug, Clone)]
+pub struct SaveGameSettings {
+    pub directory: PathBuf,
+    pub save_key: KeyCode,
+    pub load_key: KeyCode,
+}
+
+impl Default for SaveGameSettings {
+    fn default() -> Self {
+        Self {
+            directory: PathBuf::from("saves"),
+            save_key: KeyCode::F5,
+            load_key: KeyCode::F9,
+        }
+    }
+}
+
+impl SaveGameSettings {
+    fn save_path(&self, seed: u64) -> PathBuf {
+        self.directory.join(seed.to_string()).join(SAVE_FILE_NAME)
+    }
+}
+
+pub struct SaveGamePlugin;
+
+impl Plugin for SaveGamePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SaveGameSettings>()
+            .init_resource::<SaveableMaps>()
+            .add_systems(Update, (save_game_system, load_game_system));
+    }
+}
+
+/// Includes the modified tiles of `DataMap<P>` in save games, turning on its `journal`.
+pub fn register_saveable_map<P: SaveableProducer>(app: &mut App) -> &mut App {
+    // At startup, the map may be inserted after this
+    app.add_systems(Startup, |map: Option<ResMut<DataMap<P>>>| {
+        if let Some(mut map) = map {
+            map.journal = true;
+        }
+    });
+    app.init_resource::<SaveableMaps>();
+    app.world_mut().resource_mut::<SaveableMaps>().0.push(SaveableMapHooks {
+        store: |world, save| {
+            let Some(map) = world.get_resource::<DataMap<P>>() else {
+                return;
+            };
+            if let Err(err) = save.store_map(map) {
+                warn!("Failed to save {}: {}", P::SAVE_NAME, err);
+            }
+        },
+        restore: |world, save| {
+            let Some(mut map) = world.get_resource_mut::<DataMap<P>>() else {
+                return;
+            };
+            if let Err(err) = save.restore_map(&mut map) {
+                warn!("Failed to load {}: {}", P::SAVE_NAME, err);
+            }
+        },
+    });
+    app
+}
+
+fn world_seed(world: &World) -> u64 {
+    world.get_resource::<WorldSeed>().map(|seed| seed.0).unwrap_or_default()
+}
+
+/// Writes the player position and the journals of all saveable maps on F5.
+pub fn save_game_system(world: &mut World) {
+    let settings = world.resource::<SaveGameSettings>().clone();
+    if !world.resource::<ButtonInput<KeyCode>>().just_pressed(settings.save_key) {
+        return;
+    }
+    let mut player_query = world.query_filtered::<&Transform, With<Player>>();
+    let Ok(player_transform) = player_query.single(world) else {
+        return;
+    };
+
+    let mut save = SaveGame {
+        player_translation: player_transform.translation.to_array(),
+        world_seed: world_seed(world),
+        ..default()
+    };
+    for hooks in world.resource::<SaveableMaps>().0.iter() {
+        (hooks.store)(world, &mut save);
+    }
+
+    let path = settings.save_path(save.world_seed);
+    let result = save
+        .to_ron()
+        .map_err(|err| err.to_string())
+        .and_then(|text| {
+            fs::create_dir_all(path.parent().unwrap_or(&settings.directory))
+                .and_then(|_| fs::write(&path, text))
+                .map_err(|err| err.to_string())
+        });
+    match result {
+        Ok(()) => info!("Game saved to {}", path.display()),
+        Err(err) => warn!("Failed to save the game to {}: {}", path.display(), err),
+    }
+}
+
+/// Restores the player position and replays the saved tiles into the maps on F9.
+pub fn load_game_system(world: &mut World) {
+    let settings = world.resource::<SaveGameSettings>().clone();
+    if !world.resource::<ButtonInput<KeyCode>>().just_pressed(settings.load_key) {
+        return;
+    }
+    let path = settings.save_path(world_seed(world));
+    let save = match fs::read_to_string(&path)
+        .map_err(|err| err.to_string())
+        .and_then(|text| SaveGame::from_ron(&text).map_err(|err| err.to_string()))
+    {
+        Ok(save) => save,
+        Err(err) => {
+            warn!("Failed to load the game from {}: {}", path.display(), err);
+            return;
+        }
+    };
+    if save.world_seed != world_seed(world) {
+        warn!(
+            "Save game {} belongs to world seed {}, not {}",
+            path.display(),
+            save.world_seed,
+            world_seed(world)
+        );
+        return;
+    }
+
+    let mut player_query = world.query_filtered::<
+        (&mut Transform, Option<&mut PrevXY>, Option<&mut Velocity>),
+        With<Player>,
+    >();
+    if let Ok((mut player_transform, prev, velocity)) = player_query.single_mut(world) {
+        player_transform.translation = Vec3::from_array(save.player_translation);
+        // Collision handling must not pull the player back to the old position
+        if let Some(mut prev) = prev {
+            prev.0 = player_transform.translation;
+        }
+        if let Some(mut velocity) = velocity {
+            velocity.0 = Vec2::ZERO;
+        }
+    }
+    world.resource_scope(|world, maps: Mut<SaveableMaps>| {
+        for hooks in maps.0.iter() {
+            (hooks.restore)(world, &save);
+        }
+    });
+    info!("Game loaded from {}", path.display());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{
+        chunks::{ChunkCoords, DataChunk, FlatGrid},
+        units::TilesCount,
+    };
+
+    #[derive(Clone, Default)]
+    struct ZerosProducer;
+
+    impl MapDataProducer for ZerosProducer {
+        type Item = u8;
+        type GridType = FlatGrid<u8>;
+
+        fn default_value(&self) -> u8 {
+            0
+        }
+
+        fn generate_chunk(&self, _coords: ChunkCoords, dimension_tiles: TilesCount) -> DataChunk<FlatGrid<u8>> {
+            DataChunk::new(FlatGrid::new(dimension_tiles, 0))
+        }
+    }
+
+    impl SaveableProducer for ZerosProducer {
+        const SAVE_NAME: &'static str = "zeros";
+    }
+
+    fn journaled_map() -> DataMap<ZerosProducer> {
+        let mut map = DataMap::new(ZerosProducer, 8, 1);
+        map.journal = true;
+        map
+    }
+
+    #[test]
+    fn journal_survives_a_save_and_load() {
+        let mut map = journaled_map();
+        map.write(Point::new(3, 5), 1);
+        map.write(Point::new(3, 5), 2); // Only the last write is kept
+        map.write(Point::new(-9, -17), 7);
+        map.fill_rect(Point::new(20, 20), 2, 2, 4);
+
+        let mut save = SaveGame { world_seed: 42, ..default() };
+        save.store_map(&map).unwrap();
+        let loaded = SaveGame::from_ron(&save.to_ron().unwrap()).unwrap();
+        assert_eq!(loaded, save);
+
+        let mut restored = journaled_map();
+        loaded.restore_map(&mut restored).unwrap();
+        assert_eq!(restored.modified, map.modified);
+        assert_eq!(restored.modified.get(&Point::new(3, 5)), Some(&2));
+        assert_eq!(restored.modified.len(), 6);
+    }
+
+    #[test]
+    fn maps_without_journal_record_nothing() {
+        let mut map = DataMap::new(ZerosProducer, 8, 1);
+        map.write(Point::new(3, 5), 1);
+        map.fill_rect(Point::new(0, 0), 3, 3, 2);
+        assert!(map.modified.is_empty());
+
+        let mut save = SaveGame::default();
+        save.store_map(&map).unwrap();
+        assert_eq!(save.tile_overrides.get("zeros").map(String::as_str), Some("[]"));
+    }
+}
@@ -1,3 +1,193 @@
+use std::ops::{Add, AddAssign, Sub, SubAssign};
+
+use bevy::math::Vec2;
+
+use crate::core::{basics::Point, constants::TILE_SIZE_IN_UNITS};
+
 pub type Units = isize;
 pub type TilesCount = usize;
 pub type LayerId = usize;
+
+/// A position in world units, as in a `Transform`. Not pixels: those only exist inside images.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct WorldPos(pub Vec2);
+
+/// A tile of the DataMaps. Tile `n` is centered on `n * TILE_SIZE_IN_UNITS` world units,
+/// so it spans half a tile to each side of that.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct TileCoord(pub Point);
+
+impl WorldPos {
+    pub fn new(x: f32, y: f32) -> Self {
+        Self(Vec2::new(x, y))
+    }
+
+    /// Tile this position lies on.
+    pub fn to_tile(self) -> TileCoord {
+        let tile = (self.0 / TILE_SIZE_IN_UNITS).round();
+        TileCoord(Point {
+            x: tile.x as isize,
+            y: tile.y as isize,
+        })
+    }
+}
+
+impl TileCoord {
+    pub fn new(x: isize, y: isize) -> Self {
+        Self(Point { x, y })
+    }
+
+    /// World position of the tile's center.
+    pub fn center(self) -> WorldPos {
+        WorldPos(Vec2::new(self.0.x as f32, self.0.y as f32) * TILE_SIZE_IN_UNITS)
+    }
+
+    /// World position of the tile's bottom-left corner.
+    pub fn corner(self) -> WorldPos {
+        self.center() - Vec2::splat(TILE_SIZE_IN_UNITS / 2.0)
+    }
+
+    /// Tile whose bottom-left corner is at `corner`, the inverse of `corner`.
+    pub fn from_corner(corner: WorldPos) -> Self {
+        (corner + Vec2::splat(TILE_SIZE_IN_UNITS / 2.0)).to_tile()
+    }
+}
+
+impl From<WorldPos> for Vec2 {
+    fn from(pos: WorldPos) -> Self {
+        pos.0
+    }
+}
+
+impl From<TileCoord> for Point {
+    fn from(tile: TileCoord) -> Self {
+        tile.0
+    }
+}
+
+impl From<Point> for TileCoord {
+    fn from(point: Point) -> Self {
+        Self(point)
+    }
+}
+
+// Offsets are plain Vec2s, only positions are wrapped
+impl Add<Vec2> for WorldPos {
+    type Output = WorldPos;
+
+    fn add(self, offset: Vec2) -> WorldPos {
+        WorldPos(self.0 + offset)
+    }
+}
+
+impl Sub<Vec2> for WorldPos {
+    type Output = WorldPos;
+
+    fn sub(self, offset: Vec2) -> WorldPos {
+        WorldPos(self.0 - offset)
+    }
+}
+
+impl Sub for WorldPos {
+    type Output = Vec2;
+
+    fn sub(self, other: WorldPos) -> Vec2 {
+        self.0 - other.0
+    }
+}
+
+impl AddAssign<Vec2> for WorldPos {
+    fn add_assign(&mut self, offset: Vec2) {
+        self.0 += offset;
+    }
+}
+
+impl SubAssign<Vec2> for WorldPos {
+    fn sub_assign(&mut self, offset: Vec2) {
+        self.0 -= offset;
+    }
+}
+
+// Same for tiles, offsets in tiles are Points
+impl Add<Point> for TileCoord {
+    type Output = TileCoord;
+
+    fn add(self, offset: Point) -> TileCoord {
+        TileCoord(self.0 + offset)
+    }
+}
+
+impl Sub<Point> for TileCoord {
+    type Output = TileCoord;
+
+    fn sub(self, offset: Point) -> TileCoord {
+        TileCoord(self.0 - offset)
+    }
+}
+
+impl Sub for TileCoord {
+    type Output = Point;
+
+    fn sub(self, other: TileCoord) -> Point {
+        self.0 - other.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::chunks::ChunkCoords;
+
+    const TILES: [(isize, isize); 6] = [(0, 0), (-1, 0), (0, -1), (-1, -1), (-17, 5), (-1000, -999)];
+
+    #[test]
+    fn tile_center_round_trips_at_negative_coordinates() {
+        for (x, y) in TILES {
+            let tile = TileCoord::new(x, y);
+            assert_eq!(tile.center().to_tile(), tile);
+        }
+    }
+
+    #[test]
+    fn tile_corner_round_trips_at_negative_coordinates() {
+        for (x, y) in TILES {
+            let tile = TileCoord::new(x, y);
+            assert_eq!(TileCoord::from_corner(tile.corner()), tile);
+            assert_eq!(tile.center() - tile.corner(), Vec2::splat(TILE_SIZE_IN_UNITS / 2.0));
+        }
+    }
+
+    #[test]
+    fn positions_inside_a_negative_tile_map_to_it() {
+        let tile = TileCoord::new(-3, -2);
+        let quarter = TILE_SIZE_IN_UNITS / 4.0;
+        for offset in [Vec2::ZERO, Vec2::splat(quarter), Vec2::splat(-quarter), Vec2::new(quarter, -quarter)] {
+            assert_eq!((tile.center() + offset).to_tile(), tile);
+        }
+        // Just past the edge is the neighbor
+        let past_edge = tile.corner() - Vec2::splat(0.01);
+        assert_eq!(past_edge.to_tile(), tile - Point { x: 1, y: 1 });
+    }
+
+    #[test]
+    fn negative_tiles_belong_to_negative_chunks() {
+        assert_eq!(ChunkCoords::from_world(TileCoord::new(-1, -1).center(), 16), ChunkCoords { x: -1, y: -1 });
+        assert_eq!(ChunkCoords::from_world(TileCoord::new(-16, 0).center(), 16), ChunkCoords { x: -1, y: 0 });
+        assert_eq!(ChunkCoords::from_world(TileCoord::new(-17, 15).center(), 16), ChunkCoords { x: -2, y: 0 });
+        let chunk = ChunkCoords { x: -2, y: -3 };
+        assert_eq!(ChunkCoords::from_world(chunk.corner(16) + Vec2::splat(1.0), 16), chunk);
+    }
+
+    #[test]
+    fn arithmetic_keeps_positions_and_offsets_apart() {
+        let pos = WorldPos::new(-10.0, 4.0);
+        let mut moved = pos + Vec2::new(-6.0, -8.0);
+        assert_eq!(moved, WorldPos::new(-16.0, -4.0));
+        assert_eq!(moved - pos, Vec2::new(-6.0, -8.0));
+        moved -= Vec2::new(-6.0, -8.0);
+        assert_eq!(moved, pos);
+        let tile = TileCoord::new(-2, 3) + Point { x: -1, y: -5 };
+        assert_eq!(tile, TileCoord::new(-3, -2));
+        assert_eq!(tile - TileCoord::new(-2, 3), Point { x: -1, y: -5 });
+    }
+}
@@ -0,0 +1,352 @@
+use std::fmt;
+
+use bevy::prelude::*;
+
+use crate::core::{
+    constants::{TILE_SIZE_IN_UNITS, TILE_SIZE_IN_UNITS_USIZE},
+    units::TilesCount,
+};
+
+/// A size setting that would leave chunks, hypertiles or overlays misaligned.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigError {
+    ZeroDimension {
+        what: &'static str,
+    },
+    OddTiles {
+        what: &'static str,
+        tiles: TilesCount, // Even counts keep the tile grid centered on whole tiles
+    },
+    PixelsNotDivisible {
+        what: &'static str,
+        image_size_px: u32,
+        tile_size_px: u32,
+    },
+    ImpreciseUnits {
+        what: &'static str,
+        tiles: TilesCount, // Their size in world units doesn't fit an f32 exactly
+    },
+    TileSizeMismatch {
+        what: String,
+        tile_size_units: f32,
+    },
+    MisalignedHypertiles {
+        layer: String,
+        hypertile_tiles: TilesCount,
+        chunk_tiles: TilesCount, // One of the two has to divide the other
+    },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::ZeroDimension { what } => write!(f, "{what} is zero"),
+            ConfigError::OddTiles { what, tiles } => write!(f, "{what} is {tiles} tiles, it has to be even"),
+            ConfigError::PixelsNotDivisible { what, image_size_px, tile_size_px } => write!(
+                f,
+                "{what}: the image size {image_size_px}px is not a multiple of the tile size {tile_size_px}px"
+            ),
+            ConfigError::ImpreciseUnits { what, tiles } => write!(
+                f,
+                "{what}: {tiles} tiles of {TILE_SIZE_IN_UNITS} units can't be represented exactly in world units"
+            ),
+            ConfigError::TileSizeMismatch { what, tile_size_units } => write!(
+                f,
+                "{what} uses tiles of {tile_size_units} units, the world uses {TILE_SIZE_IN_UNITS}"
+            ),
+            ConfigError::MisalignedHypertiles { layer, hypertile_tiles, chunk_tiles } => write!(
+                f,
+                "{layer}: hypertiles of {hypertile_tiles} tiles and chunks of {chunk_tiles} tiles don't divide each other"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Fails if `tiles` is zero.
+pub fn check_nonzero(what: &'static str, tiles: TilesCount) -> Result<(), ConfigError> {
+    match tiles {
+        0 => Err(ConfigError::ZeroDimension { what }),
+        _ => Ok(()),
+    }
+}
+
+/// Fails if `tiles` is zero or odd.
+pub fn check_even(what: &'static str, tiles: TilesCount) -> Result<(), ConfigError> {
+    check_nonzero(what, tiles)?;
+    match tiles % 2 {
+        0 => Ok(()),
+        _ => Err(ConfigError::OddTiles { what, tiles }),
+    }
+}
+
+/// World size of `tiles` tiles, failing if it's zero or not exact as an f32.
+pub fn tiles_to_units(what: &'static str, tiles: TilesCount) -> Result<f32, ConfigError> {
+    check_nonzero(what, tiles)?;
+    let units = tiles as f32 * TILE_SIZE_IN_UNITS;
+    let exact = tiles.checked_mul(TILE_SIZE_IN_UNITS_USIZE);
+    match exact {
+        Some(exact) if units as usize == exact => Ok(units),
+        _ => Err(ConfigError::ImpreciseUnits { what, tiles }),
+    }
+}
+
+/// Chunk layout of a registered chunked map, see `WorldLayout`.
+#[derive(Debug, Clone)]
+pub struct MapLayout {
+    pub name: String,
+    pub chunk_dimension_tiles: TilesCount,
+    pub chunk_size_units: f32,
+    pub render_distance_chunks: usize,
+}
+
+/// Hypertile layout of a registered tilemap render layer, see `WorldLayout`.
+#[derive(Debug, Clone)]
+pub struct RenderLayerLayout {
+    pub name: String,
+    pub map: String, // Name of the map it draws
+    pub image_size_px: u32,
+    pub tile_size_px: u32,
+    pub hypertile_tiles: TilesCount,
+    pub hypertile_size_units: f32,
+}
+
+/// Every chunked map and render layer registered in the app, filled by `insert_chunked_plugin`
+/// and the tilemap render plugin, cross-checked by `validate_world_config`.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct WorldLayout {
+    pub maps: Vec<MapLayout>,
+    pub render_layers: Vec<RenderLayerLayout>,
+}
+
+impl WorldLayout {
+    /// Maps use the world tile size and every render layer's hypertiles line up with the chunks of its map.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        for map in self.maps.iter() {
+            let tile_size_units = map.chunk_size_units / map.chunk_dimension_tiles as f32;
+            if tile_size_units != TILE_SIZE_IN_UNITS {
+                return Err(ConfigError::TileSizeMismatch {
+                    what: map.name.clone(),
+                    tile_size_units,
+                });
+            }
+        }
+        for layer in self.render_layers.iter() {
+            let tile_size_units = layer.hypertile_size_units / layer.hypertile_tiles as f32;
+            if tile_size_units != TILE_SIZE_IN_UNITS {
+                return Err(ConfigError::TileSizeMismatch {
+                    what: layer.name.clone(),
+                    tile_size_units,
+                });
+            }
+            let Some(map) = self.maps.iter().find(|map| map.name == layer.map) else {
+                continue;
+            };
+            let (hypertile_tiles, chunk_tiles) = (layer.hypertile_tiles, map.chunk_dimension_tiles);
+            if hypertile_tiles % chunk_tiles != 0 && chunk_tiles % hypertile_tiles != 0 {
+                return Err(ConfigError::MisalignedHypertiles {
+                    layer: layer.name.clone(),
+                    hypertile_tiles,
+                    chunk_tiles,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Table of the effective sizes, one line per map and layer.
+    pub fn table(&self) -> String {
+        let mut lines = vec![format!("tile size: {TILE_SIZE_IN_UNITS} units")];
+        lines.push(format!("{:<48} {:>8} {:>10} {:>10}", "map", "tiles", "units", "distance"));
+        for map in self.maps.iter() {
+            lines.push(format!(
+                "{:<48} {:>8} {:>10} {:>10}",
+                map.name, map.chunk_dimension_tiles, map.chunk_size_units, map.render_distance_chunks
+            ));
+        }
+        lines.push(format!("{:<48} {:>8} {:>10} {:>10}", "render layer", "tiles", "units", "px/tile"));
+        for layer in self.render_layers.iter() {
+            lines.push(format!(
+                "{:<48} {:>8} {:>10} {:>10}",
+                layer.name, layer.hypertile_tiles, layer.hypertile_size_units, layer.tile_size_px
+            ));
+        }
+        lines.join("\n")
+    }
+}
+
+/// Short name of a type for the layout table, without its module path.
+pub(crate) fn short_type_name<T>() -> String {
+    let name = std::any::type_name::<T>();
+    let base = name.split('<').next().unwrap_or(name);
+    let short = base.rsplit("::").next().unwrap_or(base);
+    format!("{short}{}", &name[base.len()..])
+}
+
+/// Logs the effective layout of every chunked map and render layer registered in `app`, and checks
+/// that they line up. Call it once everything is added, before `App::run`.
+pub fn validate_world_config(app: &App) -> Result<(), ConfigError> {
+    let Some(layout) = app.world().get_resource::<WorldLayout>() else {
+        return Ok(());
+    };
+    info!("World layout:\n{}", layout.table());
+    layout.validate()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        core::chunks::{ChunkedMapConfig, DataMap},
+        game::render::{
+            light_sim::{lighting::LightingConfig, lights_map::LightsMapProducer},
+            tilemap_render::TilemapLayerSettings,
+        },
+    };
+
+    fn map(chunk_dimension_tiles: TilesCount) -> MapLayout {
+        MapLayout {
+            name: "map".to_string(),
+            chunk_dimension_tiles,
+            chunk_size_units: chunk_dimension_tiles as f32 * TILE_SIZE_IN_UNITS,
+            render_distance_chunks: 2,
+        }
+    }
+
+    fn layer(hypertile_tiles: TilesCount) -> RenderLayerLayout {
+        RenderLayerLayout {
+            name: "layer".to_string(),
+            map: "map".to_string(),
+            image_size_px: hypertile_tiles as u32 * 16,
+            tile_size_px: 16,
+            hypertile_tiles,
+            hypertile_size_units: hypertile_tiles as f32 * TILE_SIZE_IN_UNITS,
+        }
+    }
+
+    #[test]
+    fn tile_counts_are_checked_for_zero_odd_and_imprecise() {
+        assert_eq!(check_nonzero("size", 0), Err(ConfigError::ZeroDimension { what: "size" }));
+        assert_eq!(check_even("size", 0), Err(ConfigError::ZeroDimension { what: "size" }));
+        assert_eq!(check_even("size", 7), Err(ConfigError::OddTiles { what: "size", tiles: 7 }));
+        assert_eq!(check_even("size", 8), Ok(()));
+        assert_eq!(tiles_to_units("size", 16), Ok(16.0 * TILE_SIZE_IN_UNITS));
+        // Past 2^24 units an f32 skips whole units, past usize the product overflows
+        for tiles in [(1 << 24) + 1, usize::MAX] {
+            assert_eq!(tiles_to_units("size", tiles), Err(ConfigError::ImpreciseUnits { what: "size", tiles }));
+        }
+    }
+
+    #[test]
+    fn bad_chunk_dimensions_are_rejected() {
+        let zero = ConfigError::ZeroDimension {
+            what: "chunk_dimension_tiles",
+        };
+        assert_eq!(DataMap::try_new(LightsMapProducer, 0, 2).err(), Some(zero.clone()));
+        let config = ChunkedMapConfig {
+            chunk_dimension_tiles: 0,
+            ..Default::default()
+        };
+        assert_eq!(config.validate(), Err(zero));
+        assert!(DataMap::try_new(LightsMapProducer, 16, 2).is_ok());
+        assert_eq!(ChunkedMapConfig::default().validate(), Ok(()));
+    }
+
+    #[test]
+    #[should_panic(expected = "chunk_dimension_tiles is zero")]
+    fn data_map_new_panics_with_the_error() {
+        DataMap::new(LightsMapProducer, 0, 2);
+    }
+
+    #[test]
+    fn bad_hypertile_settings_are_rejected() {
+        let settings = |image_size_px, tile_size_px| TilemapLayerSettings {
+            image_size_px,
+            tile_size_px,
+            ..Default::default()
+        };
+        assert_eq!(settings(64, 0).validate(), Err(ConfigError::ZeroDimension { what: "tile_size_px" }));
+        assert_eq!(
+            settings(60, 16).validate(),
+            Err(ConfigError::PixelsNotDivisible {
+                what: "hypertile",
+                image_size_px: 60,
+                tile_size_px: 16,
+            })
+        );
+        assert_eq!(settings(48, 16).validate(), Err(ConfigError::OddTiles { what: "hypertile", tiles: 3 }));
+        assert_eq!(TilemapLayerSettings::default().validate(), Ok(()));
+    }
+
+    #[test]
+    fn bad_lighting_configs_are_rejected() {
+        let simulated = |tiles, pixels_per_tile| LightingConfig::Simulated {
+            tiles,
+            steps: 10,
+            pixels_per_tile,
+            apron_tiles: 0,
+        };
+        assert_eq!(simulated(31, 1).validate(), Err(ConfigError::OddTiles { what: "light overlay", tiles: 31 }));
+        assert_eq!(simulated(0, 1).validate(), Err(ConfigError::ZeroDimension { what: "light overlay" }));
+        assert_eq!(
+            simulated(32, 0).validate(),
+            Err(ConfigError::ZeroDimension {
+                what: "light overlay pixels_per_tile",
+            })
+        );
+        let static_overlay = LightingConfig::StaticOverlay {
+            size_units: 0.0,
+            color: Color::WHITE,
+        };
+        assert_eq!(
+            static_overlay.validate(),
+            Err(ConfigError::ZeroDimension {
+                what: "static overlay size_units",
+            })
+        );
+        assert_eq!(LightingConfig::default().validate(), Ok(()));
+    }
+
+    #[test]
+    fn layout_rejects_other_tile_sizes_and_misaligned_hypertiles() {
+        let mut layout = WorldLayout {
+            maps: vec![map(16)],
+            render_layers: vec![layer(4), layer(32)],
+        };
+        assert_eq!(layout.validate(), Ok(()));
+
+        layout.render_layers.push(layer(6));
+        assert_eq!(
+            layout.validate(),
+            Err(ConfigError::MisalignedHypertiles {
+                layer: "layer".to_string(),
+                hypertile_tiles: 6,
+                chunk_tiles: 16,
+            })
+        );
+
+        layout.render_layers.clear();
+        layout.maps[0].chunk_size_units = 16.0 * 10.0;
+        assert_eq!(
+            layout.validate(),
+            Err(ConfigError::TileSizeMismatch {
+                what: "map".to_string(),
+                tile_size_units: 10.0,
+            })
+        );
+        let mut stretched = layer(4);
+        stretched.hypertile_size_units *= 2.0;
+        let layout = WorldLayout {
+            maps: vec![map(16)],
+            render_layers: vec![stretched],
+        };
+        assert_eq!(
+            layout.validate(),
+            Err(ConfigError::TileSizeMismatch {
+                what: "layer".to_string(),
+                tile_size_units: 2.0 * TILE_SIZE_IN_UNITS,
+            })
+        );
+    }
+}
@@ -0,0 +1,715 @@
+use bevy::{
+    color::palettes::css::{ANTIQUE_WHITE, GOLD, LIMEGREEN},
+    platform::collections::HashMap,
+    prelude::*,
+    time::common_conditions::on_timer,
+};
+use rand::Rng;
+use std::time::Duration;
+
+use crate::{
+    Pallete,
+    core::{
+        basics::Point,
+        chunks::{
+            ChunkCoords, ChunkedMapConfig, DataChunk, DataMap, FlatGrid, GridData, MapDataProducer,
+        },
+        constants::TILE_SIZE_IN_UNITS,
+        rng::SimRng,
+        units::{TilesCount, WorldPos},
+    },
+    game::{
+        MapRevealActor,
+        debug::insert_chunked_plugin_with_debug,
+        physix::{Collider, move_and_collide},
+        render::RenderLayerZ,
+        spawn::{find_spawn_tile, is_free_spot, tile_to_world},
+        world::passability::PassabilityProducer,
+    },
+};
+
+const ANT_SPEED: f32 = 60.0;
+const ANT_ROTATION_SPEED: f32 = 3.5;
+const ANT_SIGHT_DISTANCE: f32 = 2.5 * TILE_SIZE_IN_UNITS;
+const ANT_SIGHT_ANGLE: f32 = std::f32::consts::FRAC_PI_4; // 45 degrees
+const ANT_RADIUS: f32 = 3.0;
+const CONE_SAMPLES: usize = 10; // Points sampled across the sight cone, plus one
+
+const PHEROMONE_SPRAY_COOLDOWN: f32 = 0.1;
+const ESCAPE_COOLDOWN: f32 = 5.0;
+const ESCAPE_DURATION: f32 = 2.0;
+
+// Pheromone intensities
+const HOME_PHEROMONE_INTENSITY: f32 = 1.0;
+const PATH_PHEROMONE_INTENSITY: f32 = 0.8;
+const SUCCESS_PHEROMONE_INTENSITY: f32 = 1.0;
+const DANGER_PHEROMONE_INTENSITY: f32 = 1.0;
+const PHEROMONE_EPSILON: f32 = 0.01; // Weaker pheromones aren't sensed
+
+const NEST_REVEAL_RADIUS_CHUNKS: usize = 2; // Kept loaded around the queen, so the colony works away from the player
+const FOOD_PICK_ATTEMPTS: usize = 8;
+
+/// Knobs of the ant colony, see `AntsPlugin`.
+#[derive(Resource, Debug, Clone)]
+pub struct AntSettings {
+    pub nest: Vec2,                // The queen sits on the free tile nearest to it
+    pub ants: usize,
+    pub food_count: usize,
+    pub food_min_distance_tiles: isize, // From the nest
+    pub food_max_distance_tiles: isize,
+    pub decay_per_sec: f32,             // Intensity every pheromone loses per second
+    pub decay_chunks_per_frame: usize,  // Loaded pheromone chunks decayed per frame, round robin
+}
+
+impl Default for AntSettings {
+    fn default() -> Self {
+        Self {
+            nest: Vec2::ZERO,
+            ants: 150,
+            food_count: 20,
+            food_min_distance_tiles: 10,
+            food_max_distance_tiles: 30,
+            decay_per_sec: 0.1,
+            decay_chunks_per_frame: 16,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PheromoneType {
+    Home,
+    Path,
+    Success,
+    Danger,
+}
+
+impl PheromoneType {
+    pub const ALL: [PheromoneType; 4] = [
+        PheromoneType::Success,
+        PheromoneType::Path,
+        PheromoneType::Home,
+        PheromoneType::Danger,
+    ];
+}
+
+/// Pheromone intensities of one tile, each in 0..1.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PheromoneCell {
+    pub home: f32,
+    pub path: f32,
+    pub success: f32,
+    pub danger: f32,
+}
+
+impl PheromoneCell {
+    pub fn get(&self, p_type: PheromoneType) -> f32 {
+        match p_type {
+            PheromoneType::Home => self.home,
+            PheromoneType::Path => self.path,
+            PheromoneType::Success => self.success,
+            PheromoneType::Danger => self.danger,
+        }
+    }
+
+    pub fn get_mut(&mut self, p_type: PheromoneType) -> &mut f32 {
+        match p_type {
+            PheromoneType::Home => &mut self.home,
+            PheromoneType::Path => &mut self.path,
+            PheromoneType::Success => &mut self.success,
+            PheromoneType::Danger => &mut self.danger,
+        }
+    }
+
+    /// Every channel lowered by `amount`, never below zero.
+    pub fn decayed(&self, amount: f32) -> Self {
+        Self {
+            home: (self.home - amount).max(0.0),
+            path: (self.path - amount).max(0.0),
+            success: (self.success - amount).max(0.0),
+            danger: (self.danger - amount).max(0.0),
+        }
+    }
+}
+
+/// Pheromones laid by the ants, one cell per tile. Chunks start without any; pheromones of
+/// unloaded chunks are forgotten, the trails fade anyway.
+#[derive(Debug, Default, Clone)]
+pub struct PheromoneProducer;
+
+impl MapDataProducer for PheromoneProducer {
+    type Item = PheromoneCell;
+    type GridType = FlatGrid<PheromoneCell>;
+
+    fn default_value(&self) -> Self::Item {
+        PheromoneCell::default()
+    }
+
+    fn generate_chunk(&self, _coords: ChunkCoords, dimension_tiles: TilesCount) -> DataChunk<Self::GridType> {
+        DataChunk::new(FlatGrid::new(dimension_tiles, self.default_value()))
+    }
+}
+
+/// Adds `value` to the pheromone at `world_pos`, capped at 1. Ignored in unloaded chunks,
+/// writing there would queue the write and request the chunk.
+pub fn add_pheromone(
+    pheromones: &mut DataMap<PheromoneProducer>,
+    world_pos: Vec2,
+    p_type: PheromoneType,
+    value: f32,
+) {
+    let tile = WorldPos(world_pos).to_tile().0;
+    let Some(mut cell) = pheromones.read(tile) else {
+        return;
+    };
+    let channel = cell.get_mut(p_type);
+    *channel = (*channel + value).min(1.0);
+    pheromones.write(tile, cell);
+}
+
+/// Senses pheromones in a cone in front of the ant, sampling points across it at `distance`.
+/// A pheromone an ant picked up, with the center of its tile.
+pub type SensedPheromone = Option<(PheromoneType, Vec2)>;
+
+/// Returns the strongest non-danger pheromone (success ones weighted double) and the strongest
+/// danger one, each with the center of its tile.
+pub fn sense_in_cone(
+    pheromones: &DataMap<PheromoneProducer>,
+    pos: Vec2,
+    dir: Vec2,
+    angle: f32,
+    distance: f32,
+) -> (SensedPheromone, SensedPheromone) {
+    let mut best_target: Option<(PheromoneType, Vec2, f32)> = None;
+    let mut danger_target: Option<(PheromoneType, Vec2, f32)> = None;
+
+    // The cells under the sampled points, read once for all types
+    let cells: Vec<(Point, PheromoneCell)> = (0..=CONE_SAMPLES)
+        .filter_map(|i| {
+            let check_angle = angle * ((i as f32 / CONE_SAMPLES as f32) - 0.5); // from -angle/2 to +angle/2
+            let check_dir = Vec2::from_angle(check_angle).rotate(dir);
+            let tile = WorldPos(pos + check_dir * distance).to_tile().0;
+            pheromones.read(tile).map(|cell| (tile, cell))
+        })
+        .collect();
+
+    for p_type in PheromoneType::ALL {
+        for (tile, cell) in cells.iter() {
+            let intensity = cell.get(p_type);
+            if intensity <= PHEROMONE_EPSILON {
+                continue;
+            }
+            let target_pos = tile_to_world(*tile);
+            if p_type == PheromoneType::Danger {
+                if danger_target.is_none_or(|(_, _, best)| intensity > best) {
+                    danger_target = Some((p_type, target_pos, intensity));
+                }
+            } else {
+                // Prioritize success pheromones
+                let priority = if p_type == PheromoneType::Success { 2.0 } else { 1.0 };
+                let weighted_intensity = intensity * priority;
+                if best_target.is_none_or(|(_, _, best)| weighted_intensity > best) {
+                    best_target = Some((p_type, target_pos, weighted_intensity));
+                }
+            }
+        }
+    }
+    (
+        best_target.map(|(t, p, _)| (t, p)),
+        danger_target.map(|(t, p, _)| (t, p)),
+    )
+}
+
+// --- Components ---
+
+#[derive(Component)]
+pub struct Ant;
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AntState {
+    Seeking,
+    CarryingFood,
+    Escaping,
+}
+
+#[derive(Component)]
+pub struct AntTarget(pub Vec2);
+
+/// Normalized direction the ant is facing.
+#[derive(Component)]
+pub struct Heading(pub Vec2);
+
+#[derive(Component)]
+pub struct PheromoneSprayCooldown(pub Timer);
+
+#[derive(Component)]
+pub struct EscapeState {
+    pub timer: Timer,
+    pub cooldown: Timer,
+}
+
+#[derive(Component)]
+pub struct Food;
+
+/// The nest. Keeps the chunks around it loaded, see `NEST_REVEAL_RADIUS_CHUNKS`.
+#[derive(Component)]
+pub struct Queen;
+
+#[derive(Component)]
+pub struct CarriedBy(pub Entity);
+
+/// Ants foraging around a nest in the generated world, steered by pheromones kept in a
+/// `DataMap<PheromoneProducer>`. Needs the `DataMap<PassabilityProducer>` registered.
+pub struct AntsPlugin {
+    pub nest: Vec2,
+    pub ants: usize,
+}
+
+impl Default for AntsPlugin {
+    fn default() -> Self {
+        let settings = AntSettings::default();
+        Self {
+            nest: settings.nest,
+            ants: settings.ants,
+        }
+    }
+}
+
+impl Plugin for AntsPlugin {
+    fn build(&self, app: &mut App) {
+        insert_chunked_plugin_with_debug(
+            app,
+            PheromoneProducer,
+            ChunkedMapConfig {
+                init_radius_tiles: 0, // Loaded around the foci like any map
+                ..Default::default()
+            },
+        );
+        app.insert_resource(AntSettings {
+            nest: self.nest,
+            ants: self.ants,
+            ..default()
+        })
+        .init_resource::<SimRng>()
+        .init_resource::<PheromoneDecayState>()
+        .add_systems(Startup, setup_nest)
+        .add_systems(
+            Update,
+            (
+                spawn_colony,
+                ant_decision_system,
+                ant_movement_system,
+                ant_state_transition_system,
+                pheromone_spraying_system,
+                pheromone_decay_system,
+                queen_emits_pheromones.run_if(on_timer(Duration::from_secs_f32(0.5))),
+            )
+                .chain(),
+        );
+    }
+}
+
+/// Colors of the colony, added to the `Pallete` unless already there.
+fn colony_material(
+    pallete: &mut Pallete,
+    materials: &mut Assets<ColorMaterial>,
+    name: &str,
+    color: Color,
+) -> Handle<ColorMaterial> {
+    pallete
+        .colors
+        .entry(name.to_string())
+        .or_insert_with(|| materials.add(ColorMaterial::from_color(color)))
+        .clone()
+}
+
+/// Spawns the queen at the configured nest, so its chunks start loading.
+fn setup_nest(
+    mut commands: Commands,
+    settings: Res<AntSettings>,
+    mut pallete: ResMut<Pallete>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let material = colony_material(&mut pallete, &mut materials, "queen", Color::from(GOLD));
+    commands.spawn((
+        Queen,
+        MapRevealActor {
+            radius_chunks: NEST_REVEAL_RADIUS_CHUNKS,
+            ..default()
+        },
+        Mesh2d(meshes.add(Circle::new(15.0))),
+        MeshMaterial2d(material),
+        Transform::from_translation(settings.nest.extend(RenderLayerZ::Actors.z())),
+    ));
+}
+
+/// Random free tile between `min` and `max` tiles away from `center` (Chebyshev distance).
+fn random_free_tile_in_ring(
+    passability: &DataMap<PassabilityProducer>,
+    center: Point,
+    (min, max): (isize, isize),
+    threshold: u8,
+    rng: &mut impl Rng,
+) -> Option<Point> {
+    (0..FOOD_PICK_ATTEMPTS)
+        .map(|_| Point {
+            x: center.x + rng.random_range(-max as i64..=max as i64) as isize, // rand can't sample isize
+            y: center.y + rng.random_range(-max as i64..=max as i64) as isize,
+        })
+        .filter(|tile| (tile.x - center.x).abs().max((tile.y - center.y).abs()) >= min)
+        .find(|&tile| is_free_spot(passability, tile, threshold))
+}
+
+/// Once the nest area is loaded, moves the queen onto a free tile and spawns the ants and food.
+#[allow(clippy::too_many_arguments)]
+fn spawn_colony(
+    mut commands: Commands,
+    mut spawned: Local<bool>,
+    settings: Res<AntSettings>,
+    passability: Res<DataMap<PassabilityProducer>>,
+    mut rng: ResMut<SimRng>,
+    mut pallete: ResMut<Pallete>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut queen_query: Query<&mut Transform, With<Queen>>,
+) {
+    if *spawned {
+        return;
+    }
+    let Ok(mut queen_transform) = queen_query.single_mut() else {
+        return;
+    };
+    let nest_tile = WorldPos(settings.nest).to_tile().0;
+    if !passability.is_loaded_around(nest_tile, NEST_REVEAL_RADIUS_CHUNKS) {
+        return;
+    }
+    *spawned = true;
+    let collider = Collider {
+        radius: ANT_RADIUS,
+        ..default()
+    };
+    let Some(queen_tile) = find_spawn_tile(&passability, nest_tile, collider.threshold) else {
+        warn!("No free tile for the ant nest near {:?}", settings.nest);
+        return;
+    };
+    let queen_pos = tile_to_world(queen_tile);
+    queen_transform.translation = queen_pos.extend(queen_transform.translation.z);
+
+    // --- Ants ---
+    let ant_mesh = meshes.add(Triangle2d::new(
+        Vec2::new(0.0, 6.0),
+        Vec2::new(-4.0, -6.0),
+        Vec2::new(4.0, -6.0),
+    ));
+    let ant_material = colony_material(&mut pallete, &mut materials, "ant", Color::from(ANTIQUE_WHITE));
+    for _ in 0..settings.ants {
+        // Within the queen's tile, which is free together with its neighbors
+        let start_pos = queen_pos + Vec2::new(rng.0.random_range(-8.0..8.0), rng.0.random_range(-8.0..8.0));
+        let start_dir = Vec2::from_angle(rng.0.random_range(0.0..std::f32::consts::TAU));
+        commands.spawn((
+            Ant,
+            AntState::Seeking,
+            Heading(start_dir),
+            AntTarget(start_pos + start_dir * 50.0),
+            PheromoneSprayCooldown(Timer::from_seconds(PHEROMONE_SPRAY_COOLDOWN, TimerMode::Repeating)),
+            EscapeState {
+                timer: Timer::from_seconds(ESCAPE_DURATION, TimerMode::Once),
+                cooldown: Timer::from_seconds(ESCAPE_COOLDOWN, TimerMode::Once),
+            },
+            collider,
+            Mesh2d(ant_mesh.clone()),
+            MeshMaterial2d(ant_material.clone()),
+            Transform::from_translation(start_pos.extend(RenderLayerZ::Actors.z_with(1.0))), // Over the queen
+        ));
+    }
+
+    // --- Food ---
+    let food_mesh = meshes.add(Circle::new(5.0));
+    let food_material = colony_material(&mut pallete, &mut materials, "food", Color::from(LIMEGREEN));
+    let ring = (settings.food_min_distance_tiles, settings.food_max_distance_tiles);
+    let mut placed = 0;
+    for _ in 0..settings.food_count {
+        let Some(tile) = random_free_tile_in_ring(&passability, queen_tile, ring, collider.threshold, &mut rng.0)
+        else {
+            continue;
+        };
+        commands.spawn((
+            Food,
+            Mesh2d(food_mesh.clone()),
+            MeshMaterial2d(food_material.clone()),
+            Transform::from_translation(tile_to_world(tile).extend(RenderLayerZ::Props.z())),
+        ));
+        placed += 1;
+    }
+    info!("Spawned {} ants and {} food around the nest at {:?}", settings.ants, placed, queen_pos);
+}
+
+// The main "brain" of the ant. It decides what to do based on its state and senses.
+fn ant_decision_system(
+    mut ant_query: Query<(&mut AntTarget, &Transform, &Heading, &AntState, &EscapeState)>,
+    pheromones: Res<DataMap<PheromoneProducer>>,
+    queen_query: Query<&Transform, (With<Queen>, Without<Ant>)>,
+    mut rng: ResMut<SimRng>,
+) {
+    let Ok(queen_transform) = queen_query.single() else {
+        return;
+    };
+    let queen_pos = queen_transform.translation.truncate();
+
+    for (mut target, transform, heading, state, escape_state) in ant_query.iter_mut() {
+        // Don't make new decisions while escaping
+        if *state == AntState::Escaping && !escape_state.timer.finished() {
+            continue;
+        }
+
+        let pos = transform.translation.truncate();
+        let dir = heading.0;
+
+        // --- Sense the environment ---
+        let (best_pheromone, danger_pheromone) =
+            sense_in_cone(&pheromones, pos, dir, ANT_SIGHT_ANGLE, ANT_SIGHT_DISTANCE);
+
+        // --- State-based Decision Making ---
+        // High-priority: React to danger if not on cooldown
+        if let Some((_, danger_pos)) = danger_pheromone.filter(|_| escape_state.cooldown.finished()) {
+            // New target is away from the danger
+            let away_dir = (pos - danger_pos).normalize_or_zero();
+            *target = AntTarget(pos + away_dir * 50.0);
+            // State transition will be handled in another system
+            continue; // Skip other logic for this frame
+        }
+
+        match state {
+            AntState::Seeking => {
+                if let Some((p_type, p_pos)) = best_pheromone {
+                    // Seeking ants prioritize success pheromones
+                    if p_type == PheromoneType::Success {
+                        *target = AntTarget(p_pos);
+                    }
+                    // If no success pheromones, they might follow path pheromones away from home
+                    else if p_type == PheromoneType::Path {
+                        // Only follow path if it leads away from the nest
+                        if (p_pos - queen_pos).length_squared() > (pos - queen_pos).length_squared() {
+                            *target = AntTarget(p_pos);
+                        }
+                    }
+                } else if (pos - target.0).length_squared() < 10.0 * 10.0 {
+                    // No pheromones found and the target is reached: pick a new random point in a small cone
+                    let angle_offset = rng.0.random_range(-0.5..0.5) * ANT_SIGHT_ANGLE;
+                    let new_dir = Vec2::from_angle(angle_offset).rotate(dir);
+                    *target = AntTarget(pos + new_dir * 100.0);
+                }
+            }
+            AntState::CarryingFood => {
+                // When carrying food, prioritize home pheromones, else head directly to the queen
+                *target = match best_pheromone {
+                    Some((PheromoneType::Home, p_pos)) => AntTarget(p_pos),
+                    _ => AntTarget(queen_pos),
+                };
+            }
+            AntState::Escaping => {
+                // Logic is handled by the danger check above and state transition system
+            }
+        }
+    }
+}
+
+// Handles state changes based on environmental interactions and timers.
+#[allow(clippy::type_complexity)]
+fn ant_state_transition_system(
+    mut commands: Commands,
+    mut ant_query: Query<(Entity, &mut AntState, &mut EscapeState, &Transform, Option<&CarriedBy>), With<Ant>>,
+    food_query: Query<(Entity, &Transform), (With<Food>, Without<CarriedBy>)>,
+    queen_query: Query<&Transform, With<Queen>>,
+    pheromones: Res<DataMap<PheromoneProducer>>,
+    time: Res<Time>,
+) {
+    let Ok(queen_transform) = queen_query.single() else {
+        return;
+    };
+    let queen_pos = queen_transform.translation.truncate();
+
+    for (ant_entity, mut state, mut escape_state, ant_transform, carried_by) in ant_query.iter_mut() {
+        let ant_pos = ant_transform.translation.truncate();
+
+        // --- Handle Escape State Timer and Cooldown ---
+        if *state == AntState::Escaping {
+            escape_state.timer.tick(time.delta());
+            if escape_state.timer.just_finished() {
+                *state = AntState::Seeking; // Revert to seeking after escaping
+                escape_state.cooldown.reset(); // Start cooldown
+            }
+        } else {
+            // Cooldown ticks even when not escaping
+            escape_state.cooldown.tick(time.delta());
+        }
+
+        // --- Check for Danger ---
+        // This check is separate to allow immediate reaction
+        let ant_dir = (ant_transform.rotation * Vec3::Y).truncate();
+        let danger = sense_in_cone(&pheromones, ant_pos, ant_dir, ANT_SIGHT_ANGLE, ANT_SIGHT_DISTANCE).1;
+        if danger.is_some() && escape_state.cooldown.finished() && *state != AntState::Escaping {
+            *state = AntState::Escaping;
+            escape_state.timer.reset();
+            if let Some(carried_comp) = carried_by {
+                commands.entity(carried_comp.0).remove::<CarriedBy>();
+            }
+            commands.entity(ant_entity).remove::<CarriedBy>();
+        }
+
+        // --- State-specific transitions ---
+        match *state {
+            AntState::Seeking => {
+                // Check for nearby food
+                for (food_entity, food_transform) in food_query.iter() {
+                    if (ant_pos - food_transform.translation.truncate()).length_squared() < 10.0 * 10.0 {
+                        *state = AntState::CarryingFood;
+                        commands.entity(ant_entity).insert(CarriedBy(food_entity));
+                        commands.entity(food_entity).insert(CarriedBy(ant_entity));
+                        break; // Pick up one food item at a time
+                    }
+                }
+            }
+            AntState::CarryingFood => {
+                // Check if near the queen to drop food
+                let Some(carried_comp) = carried_by else {
+                    continue;
+                };
+                if (ant_pos - queen_pos).length_squared() < 20.0 * 20.0 {
+                    // Despawn food and remove carrying components
+                    commands.entity(carried_comp.0).despawn();
+                    commands.entity(ant_entity).remove::<CarriedBy>();
+                    *state = AntState::Seeking;
+                }
+            }
+            AntState::Escaping => { /* Handled above */ }
+        }
+    }
+}
+
+// Moves and rotates ants towards their target, colliding with the generated walls.
+#[allow(clippy::type_complexity)]
+fn ant_movement_system(
+    mut ant_query: Query<
+        (&mut Transform, &mut Heading, &mut AntTarget, &Collider, &AntState, Option<&CarriedBy>),
+        With<Ant>,
+    >,
+    mut food_transform_query: Query<&mut Transform, (With<Food>, Without<Ant>)>,
+    time: Res<Time>,
+    passability: Res<DataMap<PassabilityProducer>>,
+) {
+    for (mut transform, mut heading, mut target, collider, state, carried_by) in ant_query.iter_mut() {
+        let pos = transform.translation.truncate();
+        let to_target = (target.0 - pos).normalize_or_zero();
+        if to_target.length_squared() == 0.0 {
+            continue;
+        }
+
+        // --- Rotation ---
+        let target_angle = to_target.to_angle();
+        let current_angle = heading.0.to_angle();
+        // Slerp the angle for smooth rotation
+        let new_angle = slerp_angle(current_angle, target_angle, ANT_ROTATION_SPEED * time.delta_secs());
+        transform.rotation = Quat::from_rotation_z(new_angle - std::f32::consts::FRAC_PI_2); // Adjust for sprite orientation
+        heading.0 = Vec2::from_angle(new_angle);
+
+        // --- Translation, sliding along walls; unloaded tiles block like walls ---
+        let delta = heading.0 * ANT_SPEED * time.delta_secs();
+        let moved = move_and_collide(pos, delta, &passability, collider);
+        if moved.distance_squared(pos) < (delta.length() * 0.1).powi(2) {
+            // Stuck against a wall: turn around rather than pushing into it
+            *target = AntTarget(pos - heading.0 * 50.0);
+        }
+        transform.translation = moved.extend(transform.translation.z);
+
+        // --- Move Carried Food ---
+        let carried = carried_by.filter(|_| *state == AntState::CarryingFood);
+        if let Some(mut food_transform) = carried.and_then(|c| food_transform_query.get_mut(c.0).ok()) {
+            food_transform.translation = transform.translation + heading.0.extend(0.0) * 10.0;
+        }
+    }
+}
+
+// Sprays pheromones based on the ant's current state.
+fn pheromone_spraying_system(
+    mut ant_query: Query<(&Transform, &AntState, &mut PheromoneSprayCooldown)>,
+    mut pheromones: ResMut<DataMap<PheromoneProducer>>,
+    time: Res<Time>,
+) {
+    for (transform, state, mut cooldown) in ant_query.iter_mut() {
+        cooldown.0.tick(time.delta());
+        if !cooldown.0.just_finished() {
+            continue;
+        }
+        let pos = transform.translation.truncate();
+        let (p_type, intensity) = match state {
+            AntState::Seeking => (PheromoneType::Path, PATH_PHEROMONE_INTENSITY),
+            AntState::CarryingFood => (PheromoneType::Success, SUCCESS_PHEROMONE_INTENSITY),
+            AntState::Escaping => (PheromoneType::Danger, DANGER_PHEROMONE_INTENSITY),
+        };
+        add_pheromone(&mut pheromones, pos, p_type, intensity);
+    }
+}
+
+// The queen constantly emits home pheromones.
+fn queen_emits_pheromones(
+    queen_query: Query<&Transform, With<Queen>>,
+    mut pheromones: ResMut<DataMap<PheromoneProducer>>,
+) {
+    let Ok(queen_transform) = queen_query.single() else {
+        return;
+    };
+    add_pheromone(
+        &mut pheromones,
+        queen_transform.translation.truncate(),
+        PheromoneType::Home,
+        HOME_PHEROMONE_INTENSITY,
+    );
+}
+
+/// When each loaded pheromone chunk was last decayed, for `pheromone_decay_system`.
+#[derive(Resource, Debug, Default)]
+pub struct PheromoneDecayState {
+    pub cursor: usize,
+    pub last_decayed_secs: HashMap<ChunkCoords, f32>,
+}
+
+/// Decays up to `AntSettings::decay_chunks_per_frame` loaded pheromone chunks per frame, going
+/// round the loaded chunks. Each one loses what it would have since its previous turn, so the
+/// rate doesn't depend on how many chunks are loaded. Chunks that are not loaded don't cost anything.
+fn pheromone_decay_system(
+    settings: Res<AntSettings>,
+    time: Res<Time>,
+    mut state: ResMut<PheromoneDecayState>,
+    mut pheromones: ResMut<DataMap<PheromoneProducer>>,
+) {
+    let now = time.elapsed_secs();
+    let state = &mut *state;
+    state.last_decayed_secs.retain(|coords, _| pheromones.loaded_chunks.contains_key(coords));
+    // Sorted, so the cursor walks a stable order while chunks load and unload
+    let mut loaded: Vec<ChunkCoords> = pheromones.loaded_chunks.keys().copied().collect();
+    if loaded.is_empty() {
+        return;
+    }
+    loaded.sort_unstable_by_key(|coords| (coords.x, coords.y));
+    let budget = settings.decay_chunks_per_frame.clamp(1, loaded.len());
+    for i in 0..budget {
+        let coords = loaded[(state.cursor + i) % loaded.len()];
+        let last = *state.last_decayed_secs.entry(coords).or_insert(now); // Fresh chunks hold nothing yet
+        let amount = settings.decay_per_sec * (now - last);
+        state.last_decayed_secs.insert(coords, now);
+        if amount <= 0.0 {
+            continue;
+        }
+        if let Some(chunk) = pheromones.loaded_chunks.get_mut(&coords) {
+            chunk.grid.map_in_place(|_, _, cell| cell.decayed(amount));
+        }
+    }
+    state.cursor = (state.cursor + budget) % loaded.len();
+}
+
+// Spherically interpolates between two angles.
+fn slerp_angle(a: f32, b: f32, t: f32) -> f32 {
+    let diff = (b - a + std::f32::consts::PI).rem_euclid(std::f32::consts::TAU) - std::f32::consts::PI;
+    a + diff * t.min(1.0)
+}
@@ -0,0 +1,196 @@
+use bevy::prelude::*;
+
+use crate::{
+    core::chunks::ChunkPreloadHooks,
+    game::render::tilemap_render::HypertileBudget,
+};
+
+const LOADING_HYPERTILE_BUDGET: usize = 32; // Images drawn per frame while nobody sees the world yet
+
+/// Startup phase of the world. The app starts `Loading`: the chunks every map requests in `init`
+/// are generated behind a progress screen, with the player frozen and the camera still. Once they
+/// are all in (or `BootstrapSettings::timeout_secs` passed) it's `Playing` for good.
+#[derive(States, Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum WorldBootstrap {
+    #[default]
+    Loading,
+    Playing,
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct BootstrapSettings {
+    pub timeout_secs: f32, // Starts playing with whatever is loaded by then
+}
+
+impl Default for BootstrapSettings {
+    fn default() -> Self {
+        Self { timeout_secs: 20.0 }
+    }
+}
+
+/// Startup chunks of every map still missing, see `ChunkPreloadHooks::bootstrap_progress`.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct BootstrapProgress {
+    pub remaining: usize,
+    pub total: usize,
+    pub elapsed_secs: f32, // Real time spent loading
+    restore_budget: Option<usize>, // Hypertile budget before loading raised it
+}
+
+impl BootstrapProgress {
+    /// From 0.0 to 1.0, 1.0 when there's nothing to load.
+    pub fn fraction(&self) -> f32 {
+        if self.total == 0 {
+            return 1.0;
+        }
+        1.0 - self.remaining as f32 / self.total as f32
+    }
+}
+
+#[derive(Component)]
+struct BootstrapScreen;
+
+#[derive(Component)]
+struct BootstrapText;
+
+#[derive(Component)]
+struct BootstrapBar;
+
+pub struct BootstrapPlugin;
+
+impl Plugin for BootstrapPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_state::<WorldBootstrap>()
+            .init_resource::<BootstrapSettings>()
+            .init_resource::<BootstrapProgress>()
+            .init_resource::<ChunkPreloadHooks>()
+            .add_systems(Startup, (setup_bootstrap_screen, raise_hypertile_budget))
+            .add_systems(
+                Update,
+                (bootstrap_progress_system, bootstrap_screen_system)
+                    .chain()
+                    .run_if(in_state(WorldBootstrap::Loading)),
+            )
+            .add_systems(OnEnter(WorldBootstrap::Playing), finish_bootstrap);
+    }
+}
+
+/// Run condition of everything the player shouldn't see or do while the world is generated.
+pub fn bootstrapped(state: Option<Res<State<WorldBootstrap>>>) -> bool {
+    state.is_none_or(|state| *state.get() == WorldBootstrap::Playing)
+}
+
+// The loading screen hides the world, so the hypertiles around the spawn are drawn much faster
+fn raise_hypertile_budget(budget: Option<ResMut<HypertileBudget>>, mut progress: ResMut<BootstrapProgress>) {
+    let Some(mut budget) = budget else {
+        return;
+    };
+    progress.restore_budget = Some(budget.max_images_per_frame);
+    budget.max_images_per_frame = budget.max_images_per_frame.max(LOADING_HYPERTILE_BUDGET);
+}
+
+// Exclusive, the hooks read the DataMaps straight from the world
+fn bootstrap_progress_system(world: &mut World) {
+    let Some(hooks) = world.get_resource::<ChunkPreloadHooks>().cloned() else {
+        return;
+    };
+    let (remaining, total) = hooks.bootstrap_progress(world);
+    let delta_secs = world.resource::<Time<Real>>().delta_secs();
+    let timeout_secs = world.resource::<BootstrapSettings>().timeout_secs;
+    let mut progress = world.resource_mut::<BootstrapProgress>();
+    progress.remaining = remaining;
+    progress.total = total;
+    progress.elapsed_secs += delta_secs;
+    let elapsed_secs = progress.elapsed_secs;
+    if remaining == 0 {
+        info!("World generated around the spawn in {elapsed_secs:.1} s ({total} chunks)");
+    } else if elapsed_secs > timeout_secs {
+        warn!(
+            "World generation still misses {remaining} of {total} startup chunks after {timeout_secs} s, starting anyway"
+        );
+    } else {
+        return;
+    }
+    world
+        .resource_mut::<NextState<WorldBootstrap>>()
+        .set(WorldBootstrap::Playing);
+}
+
+fn setup_bootstrap_screen(mut commands: Commands) {
+    commands
+        .spawn((
+            BootstrapScreen,
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(12.0),
+                ..default()
+            },
+            BackgroundColor(Color::srgb(0.06, 0.06, 0.08)),
+            GlobalZIndex(i32::MAX), // Over the debug texts too
+        ))
+        .with_children(|screen| {
+            screen.spawn((
+                BootstrapText,
+                Text::new("Generating the world"),
+                TextFont {
+                    font_size: 20.0,
+                    ..default()
+                },
+            ));
+            screen
+                .spawn((
+                    Node {
+                        width: Val::Px(320.0),
+                        height: Val::Px(12.0),
+                        ..default()
+                    },
+                    BackgroundColor(Color::srgb(0.2, 0.2, 0.25)),
+                ))
+                .with_children(|bar| {
+                    bar.spawn((
+                        BootstrapBar,
+                        Node {
+                            width: Val::Percent(0.0),
+                            height: Val::Percent(100.0),
+                            ..default()
+                        },
+                        BackgroundColor(Color::srgb(0.4, 0.8, 0.4)),
+                    ));
+                });
+        });
+}
+
+fn bootstrap_screen_system(
+    progress: Res<BootstrapProgress>,
+    mut text_query: Query<&mut Text, With<BootstrapText>>,
+    mut bar_query: Query<&mut Node, With<BootstrapBar>>,
+) {
+    if let Ok(mut text) = text_query.single_mut() {
+        let done = progress.total - progress.remaining;
+        let summary = format!("Generating the world: {done} / {} chunks", progress.total);
+        if text.0 != summary {
+            text.0 = summary;
+        }
+    }
+    if let Ok(mut bar) = bar_query.single_mut() {
+        bar.width = Val::Percent(progress.fraction() * 100.0);
+    }
+}
+
+fn finish_bootstrap(
+    mut commands: Commands,
+    screen_query: Query<Entity, With<BootstrapScreen>>,
+    budget: Option<ResMut<HypertileBudget>>,
+    mut progress: ResMut<BootstrapProgress>,
+) {
+    for entity in screen_query.iter() {
+        commands.entity(entity).despawn();
+    }
+    if let (Some(mut budget), Some(max_images_per_frame)) = (budget, progress.restore_budget.take()) {
+        budget.max_images_per_frame = max_images_per_frame;
+    }
+}
@@ -0,0 +1,363 @@
+use bevy::{
+    ecs::{
+        component::Component,
+        event::EventReader,
+        query::{With, Without},
+        resource::Resource,
+        system::{Query, Res, ResMut},
+    },
+    input::{
+        ButtonInput,
+        keyboard::KeyCode,
+        mouse::{MouseScrollUnit, MouseWheel},
+    },
+    math::{Vec2, Vec3, Vec3Swizzles},
+    render::camera::Projection,
+    time::{Real, Time},
+    transform::components::Transform,
+};
+
+use crate::{
+    core::noise::value_noise,
+    game::{
+        Player,
+        physix::{RenderInterpolated, Velocity},
+    },
+};
+
+/// World position under a point of the window, for an unrotated orthographic camera at
+/// `camera_translation` with projection `scale`. Window coordinates start at the top-left
+/// corner with y growing downwards, world y grows upwards.
+pub fn screen_to_world(screen: Vec2, window_size: Vec2, camera_translation: Vec2, scale: f32) -> Vec2 {
+    let from_center = screen - window_size / 2.0;
+    camera_translation + Vec2::new(from_center.x, -from_center.y) * scale
+}
+
+/// Inverse of `screen_to_world`.
+pub fn world_to_screen(world: Vec2, window_size: Vec2, camera_translation: Vec2, scale: f32) -> Vec2 {
+    let from_camera = (world - camera_translation) / scale;
+    window_size / 2.0 + Vec2::new(from_camera.x, -from_camera.y)
+}
+
+const KEY_ZOOM_STEPS_PER_SECOND: f32 = 8.0; // Holding +/- zooms like this many wheel steps per second
+const PIXELS_PER_WHEEL_STEP: f32 = 100.0; // Touchpads report pixels instead of lines
+
+#[derive(Component)]
+#[require(CameraFocus)]
+pub struct FollowCamera {
+    pub smoothing: f32, // Higher values = smoother but slower following
+    pub offset: Vec3,   // Optional offset from player position
+    pub zoom: f32,      // Target projection scale, the actual one eases towards it
+    pub zoom_speed: f32, // Relative scale change per wheel step
+    pub zoom_range: (f32, f32), // Min and max projection scale
+    pub deadzone: Vec2, // World units box around the camera center the player moves in freely, zero disables it
+    pub look_ahead: CameraLookAhead,
+    pub shake: CameraShakeSettings,
+}
+
+impl Default for FollowCamera {
+    fn default() -> Self {
+        Self {
+            smoothing: 2.0,
+            offset: Vec3::ZERO,
+            zoom: 1.0,
+            zoom_speed: 0.1,
+            zoom_range: (0.25, 4.0),
+            deadzone: Vec2::ZERO,
+            look_ahead: CameraLookAhead::default(),
+            shake: CameraShakeSettings::default(),
+        }
+    }
+}
+
+/// Shifts the follow target ahead of the player's movement.
+#[derive(Debug, Clone, Copy)]
+pub struct CameraLookAhead {
+    pub distance: f32,   // World units ahead at full speed, zero disables it
+    pub full_speed: f32, // Player speed giving the whole distance, slower movement leads less
+    pub smoothing: f32,  // Same as `FollowCamera::smoothing`, for the lead itself
+}
+
+impl Default for CameraLookAhead {
+    fn default() -> Self {
+        Self {
+            distance: 0.0,
+            full_speed: 200.0, // Player move speed
+            smoothing: 1.5,
+        }
+    }
+}
+
+impl CameraLookAhead {
+    /// Lead for a player moving at `velocity`, before smoothing.
+    pub fn target(&self, velocity: Vec2) -> Vec2 {
+        if self.distance <= 0.0 || self.full_speed <= 0.0 {
+            return Vec2::ZERO;
+        }
+        let speed = (velocity.length() / self.full_speed).min(1.0);
+        velocity.normalize_or_zero() * self.distance * speed
+    }
+}
+
+/// How `CameraShake` trauma turns into camera motion.
+#[derive(Debug, Clone, Copy)]
+pub struct CameraShakeSettings {
+    pub max_offset: Vec2, // World units at full trauma, zero disables shaking
+    pub frequency: f32,   // Noise lattice cells per second, higher is more jittery
+    pub decay: f32,       // Trauma lost per second
+}
+
+impl Default for CameraShakeSettings {
+    fn default() -> Self {
+        Self {
+            max_offset: Vec2::splat(12.0),
+            frequency: 15.0,
+            decay: 1.5,
+        }
+    }
+}
+
+/// Where the camera looks before shaking. Chunk focus, the light and fog overlays and anything
+/// else that shouldn't jitter with the screen follow this instead of the camera `Transform`.
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct CameraFocus {
+    pub position: Vec3,
+    look_ahead: Vec2, // Smoothed lead, see `CameraLookAhead`
+}
+
+/// Screen shake, see `add_trauma`.
+#[derive(Resource, Debug, Clone, Copy, Default)]
+pub struct CameraShake {
+    pub trauma: f32,   // 0.0 to 1.0, decays over time
+    elapsed_secs: f32, // Drives the noise while shaking
+}
+
+impl CameraShake {
+    /// Shakes the camera, `amount` of 1.0 is the strongest shake. Trauma adds up and is capped at 1.0.
+    pub fn add_trauma(&mut self, amount: f32) {
+        self.trauma = (self.trauma + amount).clamp(0.0, 1.0);
+    }
+
+    /// Shake strength for the current trauma, squared so small hits stay subtle.
+    pub fn intensity(&self) -> f32 {
+        self.trauma * self.trauma
+    }
+
+    /// Advances the noise and decays the trauma, returning the offset to apply this frame.
+    fn advance(&mut self, settings: &CameraShakeSettings, delta_secs: f32) -> Vec2 {
+        if self.trauma <= 0.0 {
+            self.elapsed_secs = 0.0;
+            return Vec2::ZERO;
+        }
+        self.elapsed_secs += delta_secs;
+        let t = self.elapsed_secs * settings.frequency;
+        // Two uncorrelated noise tracks in [-1, 1)
+        let noise = Vec2::new(value_noise(1, t, 0.0), value_noise(2, t, 0.0)) * 2.0 - Vec2::ONE;
+        let offset = noise * settings.max_offset * self.intensity();
+        self.trauma = decayed_trauma(self.trauma, settings.decay, delta_secs);
+        offset
+    }
+}
+
+/// Trauma left after `delta_secs`, decreasing linearly at `decay` per second.
+pub fn decayed_trauma(trauma: f32, decay: f32, delta_secs: f32) -> f32 {
+    (trauma - decay.max(0.0) * delta_secs).clamp(0.0, 1.0)
+}
+
+/// Point the camera center has to move to so `target` is inside the `size` box around it.
+/// The center stays put while it is, otherwise it moves just enough to put `target` on the edge.
+pub fn deadzone_goal(center: Vec2, target: Vec2, size: Vec2) -> Vec2 {
+    let half = size.max(Vec2::ZERO) / 2.0;
+    let offset = target - center;
+    center + offset - offset.clamp(-half, half)
+}
+
+/// Current projection scale of the follow camera, for systems that cover the visible area.
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct CameraZoom(pub f32);
+
+impl Default for CameraZoom {
+    fn default() -> Self {
+        CameraZoom(1.0)
+    }
+}
+
+/// Fraction of the remaining distance covered in `delta_secs` by exponential smoothing at `rate`.
+/// Clamped, so long frames and odd rates never push past the target or away from it.
+pub fn smoothing_factor(rate: f32, delta_secs: f32) -> f32 {
+    let factor = 1.0 - (-rate * delta_secs).exp();
+    if factor.is_nan() { 1.0 } else { factor.clamp(0.0, 1.0) }
+}
+
+type FollowedPlayerQuery<'w, 's> = Query<
+    'w,
+    's,
+    (&'static Transform, Option<&'static RenderInterpolated>, Option<&'static Velocity>),
+    (With<Player>, Without<FollowCamera>),
+>;
+
+/// Moves `CameraFocus` after the player (look-ahead and deadzone included), then places the
+/// camera on it with the shake offset on top. Follows where the player is drawn, see `RenderInterpolated`.
+pub fn camera_follow_system(
+    player_query: FollowedPlayerQuery,
+    mut camera_query: Query<(&mut Transform, &mut CameraFocus, &FollowCamera), Without<Player>>,
+    mut shake: ResMut<CameraShake>,
+    time: Res<Time<Real>>, // Keeps following while the simulation is paused
+) {
+    let delta_secs = time.delta_secs();
+    let player = player_query.single().ok();
+    for (mut camera_transform, mut focus, follow_camera) in camera_query.iter_mut() {
+        if let Some((player_transform, interpolated, velocity)) = player {
+            let lead = follow_camera.look_ahead.target(velocity.map_or(Vec2::ZERO, |v| v.0));
+            let lead_factor = smoothing_factor(follow_camera.look_ahead.smoothing, delta_secs);
+            focus.look_ahead = focus.look_ahead.lerp(lead, lead_factor);
+
+            let player_position = interpolated.map_or(player_transform.translation, |i| i.translation);
+            let target_position = player_position + follow_camera.offset + focus.look_ahead.extend(0.0);
+            let goal = deadzone_goal(focus.position.xy(), target_position.xy(), follow_camera.deadzone)
+                .extend(target_position.z);
+
+            // Smooth interpolation using exponential decay
+            let factor = smoothing_factor(follow_camera.smoothing, delta_secs);
+            focus.position = focus.position.lerp(goal, factor);
+        }
+        let shake_offset = shake.advance(&follow_camera.shake, delta_secs);
+        camera_transform.translation = focus.position + shake_offset.extend(0.0);
+    }
+}
+
+/// Mouse wheel and +/- change the target zoom, the projection scale eases towards it.
+pub fn camera_zoom_system(
+    mut camera_query: Query<(&mut Projection, &mut FollowCamera)>,
+    mut wheel_events: EventReader<MouseWheel>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    time: Res<Time<Real>>, // Keeps following while the simulation is paused
+    mut camera_zoom: ResMut<CameraZoom>,
+) {
+    // Positive steps zoom in
+    let mut steps: f32 = wheel_events
+        .read()
+        .map(|event| match event.unit {
+            MouseScrollUnit::Line => event.y,
+            MouseScrollUnit::Pixel => event.y / PIXELS_PER_WHEEL_STEP,
+        })
+        .sum();
+    if keyboard_input.any_pressed([KeyCode::Equal, KeyCode::NumpadAdd]) {
+        steps += KEY_ZOOM_STEPS_PER_SECOND * time.delta_secs();
+    }
+    if keyboard_input.any_pressed([KeyCode::Minus, KeyCode::NumpadSubtract]) {
+        steps -= KEY_ZOOM_STEPS_PER_SECOND * time.delta_secs();
+    }
+
+    for (mut projection, mut follow_camera) in camera_query.iter_mut() {
+        let (min, max) = follow_camera.zoom_range;
+        follow_camera.zoom =
+            (follow_camera.zoom * (1.0 + follow_camera.zoom_speed).powf(-steps)).clamp(min, max);
+
+        if let Projection::Orthographic(ortho) = projection.as_mut() {
+            let factor = smoothing_factor(follow_camera.smoothing * 4.0, time.delta_secs());
+            ortho.scale = (ortho.scale + (follow_camera.zoom - ortho.scale) * factor)
+                .clamp(min, max);
+            camera_zoom.0 = ortho.scale;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WINDOW: Vec2 = Vec2::new(800.0, 600.0);
+
+    #[test]
+    fn screen_to_world_with_a_translated_camera() {
+        let camera = Vec2::new(100.0, -50.0);
+        assert_eq!(screen_to_world(WINDOW / 2.0, WINDOW, camera, 1.0), camera);
+        assert_eq!(screen_to_world(Vec2::ZERO, WINDOW, camera, 1.0), Vec2::new(-300.0, 250.0)); // Top left
+        assert_eq!(screen_to_world(WINDOW, WINDOW, camera, 1.0), Vec2::new(500.0, -350.0)); // Bottom right
+    }
+
+    #[test]
+    fn screen_to_world_with_a_scaled_camera() {
+        let camera = Vec2::new(16.0, 32.0);
+        // Zoomed out twice as far, a pixel covers two world units
+        assert_eq!(screen_to_world(Vec2::new(410.0, 290.0), WINDOW, camera, 2.0), Vec2::new(36.0, 52.0));
+        assert_eq!(screen_to_world(Vec2::new(410.0, 290.0), WINDOW, camera, 0.5), Vec2::new(21.0, 37.0));
+    }
+
+    #[test]
+    fn world_to_screen_inverts_screen_to_world() {
+        for (camera, scale) in [(Vec2::ZERO, 1.0), (Vec2::new(-123.5, 77.0), 2.5), (Vec2::new(9.0, -3.0), 0.25)] {
+            for screen in [Vec2::ZERO, Vec2::new(13.0, 587.0), WINDOW] {
+                let world = screen_to_world(screen, WINDOW, camera, scale);
+                let back = world_to_screen(world, WINDOW, camera, scale);
+                assert!(back.abs_diff_eq(screen, 1e-3), "{screen} came back as {back}");
+            }
+        }
+    }
+
+    #[test]
+    fn deadzone_moves_the_center_only_past_its_edge() {
+        let center = Vec2::new(100.0, 50.0);
+        let size = Vec2::new(40.0, 20.0);
+        // Inside and right on the edge, nothing moves
+        assert_eq!(deadzone_goal(center, center + Vec2::new(15.0, -5.0), size), center);
+        assert_eq!(deadzone_goal(center, center + Vec2::new(20.0, 10.0), size), center);
+        assert_eq!(deadzone_goal(center, center + Vec2::new(-20.0, -10.0), size), center);
+        // Past it, just enough to put the target back on the edge, per axis
+        assert_eq!(deadzone_goal(center, center + Vec2::new(25.0, 0.0), size), center + Vec2::new(5.0, 0.0));
+        assert_eq!(deadzone_goal(center, center + Vec2::new(-30.0, 13.0), size), center + Vec2::new(-10.0, 3.0));
+        // Without a deadzone the center goes straight to the target
+        let target = Vec2::new(-7.0, 3.0);
+        assert_eq!(deadzone_goal(center, target, Vec2::ZERO), target);
+        assert_eq!(deadzone_goal(center, target, Vec2::splat(-5.0)), target);
+    }
+
+    #[test]
+    fn trauma_decays_linearly_to_zero_and_shakes_quadratically() {
+        assert!((decayed_trauma(1.0, 1.5, 0.2) - 0.7).abs() < 1e-6);
+        assert_eq!(decayed_trauma(0.3, 1.5, 1.0), 0.0); // Never below zero
+        assert_eq!(decayed_trauma(0.5, -1.0, 1.0), 0.5); // Never grows back
+
+        let mut shake = CameraShake::default();
+        shake.add_trauma(0.6);
+        shake.add_trauma(0.6);
+        assert_eq!(shake.trauma, 1.0);
+        shake.trauma = 0.5;
+        assert_eq!(shake.intensity(), 0.25);
+
+        // Full trauma is gone after 1 / decay seconds, whatever the frame rate
+        let settings = CameraShakeSettings::default();
+        for frame_hz in [30.0, 60.0, 144.0] {
+            let mut shake = CameraShake::default();
+            shake.add_trauma(1.0);
+            let frames = (frame_hz / settings.decay) as usize;
+            for _ in 0..frames - 1 {
+                let offset = shake.advance(&settings, 1.0 / frame_hz);
+                assert!(offset.abs().cmple(settings.max_offset).all(), "{offset} beyond the max offset");
+            }
+            assert!(shake.trauma > 0.0);
+            shake.advance(&settings, 1.0 / frame_hz);
+            shake.advance(&settings, 1.0 / frame_hz);
+            assert_eq!(shake.trauma, 0.0, "{frame_hz} Hz");
+            assert_eq!(shake.advance(&settings, 1.0 / frame_hz), Vec2::ZERO);
+        }
+    }
+
+    #[test]
+    fn smoothing_factor_stays_between_zero_and_one() {
+        assert_eq!(smoothing_factor(2.0, 0.0), 0.0);
+        assert!(smoothing_factor(2.0, 10.0) <= 1.0); // A very long frame lands on the target
+        assert!(smoothing_factor(1000.0, 1.0) <= 1.0);
+        assert_eq!(smoothing_factor(-5.0, 1.0), 0.0); // Never away from the target
+        assert_eq!(smoothing_factor(f32::INFINITY, 0.0), 1.0);
+    }
+
+    #[test]
+    fn smoothing_covers_the_same_distance_at_any_frame_rate() {
+        // What is left of the distance after a second at each frame rate
+        let remaining = |frame_hz: f32| (1.0 - smoothing_factor(2.0, 1.0 / frame_hz)).powi(frame_hz as i32);
+        assert!((remaining(20.0) - remaining(144.0)).abs() < 1e-3);
+        assert!((remaining(144.0) - (-2.0f32).exp()).abs() < 1e-3);
+    }
+}
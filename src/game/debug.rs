@@ -0,0 +1,508 @@
+use std::{collections::BTreeMap, fmt::Debug, marker::PhantomData};
+
+use bevy::{prelude::*, window::PrimaryWindow};
+
+use crate::{
+    core::{
+        basics::Point,
+        chunks::{
+            insert_chunked_plugin, ChunkCoords, ChunkedMapConfig, ChunkLoaded, ChunkMemoryBudget, ChunkStats, DataMap,
+            MapDataProducer,
+            RenderDistanceChange, validate_producer_seams,
+        },
+        constants::TILE_SIZE_IN_UNITS,
+        raycast::{RaycastResult, world_ray_tiles, world_raycast},
+        units::{TileCoord, WorldPos},
+    },
+    game::{
+        Player,
+        camera::{FollowCamera, screen_to_world},
+        inspector::{ChunkInspectorPlugin, register_inspectable_map},
+        physix::Collider,
+        sim_speed::SimulationSpeed,
+        render::{
+            culling::CullingStats,
+            light_sim::{
+                lighting::{DayNightCycle, sync_light_emitters},
+                lights::LightModulation,
+                simulation::LightSimStats,
+            },
+            render_enabled,
+        },
+        world::passability::PassabilityProducer,
+    },
+};
+
+const PASSABILITY_RADIUS_TILES: isize = 8; // Tiles around the player colored by the F3 layer
+const SEAM_RADIUS_CHUNKS: isize = 1; // Chunks around the player checked by the F4 layer
+const RAYCAST_KEY: KeyCode = KeyCode::KeyL; // Held to draw the line of sight from the player to the cursor
+
+/// Which debug layers are drawn, toggled with F1-F4, and whether the F7 chunk inspector is on.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct DebugOverlaySettings {
+    pub show_loaded_chunks: bool,    // F1
+    pub show_requested_chunks: bool, // F2, pending tasks included
+    pub show_passability: bool,      // F3
+    pub show_seams: bool,            // F4, border tiles depending on the chunk they were generated in
+    pub show_chunk_inspector: bool,  // F7, clicks select a chunk instead of painting walls
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MapStats {
+    pub loaded: usize,
+    pub requested: usize,
+    pub pending: usize,
+    pub write_queue: usize,
+    pub completed_this_frame: usize,
+    pub failed: usize, // Chunks whose generation kept panicking
+    pub bytes: usize,  // Of the loaded chunks
+    pub evicted: u64,  // This and the fields below come from ChunkStats
+    pub tasks_spawned: u64,
+    pub cancelled: u64,
+    pub wasted: u64,
+    pub avg_generation_ms: f32,
+}
+
+/// Stats of every DataMap registered with `register_debug_map`, by producer name.
+#[derive(Resource, Debug, Default)]
+pub struct DebugMapStats(pub BTreeMap<&'static str, MapStats>);
+
+#[derive(Component)]
+struct DebugStatsText;
+
+pub struct DebugOverlayPlugin;
+
+impl Plugin for DebugOverlayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DebugOverlaySettings>()
+            .init_resource::<DebugMapStats>()
+            .init_resource::<LightModulation>()
+            .add_plugins(ChunkInspectorPlugin)
+            .add_systems(Startup, setup_debug_stats_text)
+            .add_systems(
+                Update,
+                (
+                    debug_overlay_toggle_system,
+                    debug_render_distance_key_system,
+                    debug_light_pulse_system.before(sync_light_emitters),
+                    debug_passability_gizmos_system.run_if(render_enabled),
+                    debug_raycast_gizmos_system.run_if(render_enabled),
+                    debug_stats_text_system,
+                ),
+            );
+    }
+}
+
+/// `insert_chunked_plugin` followed by `register_debug_map`, what the game uses for all its maps.
+pub fn insert_chunked_plugin_with_debug<P: MapDataProducer>(
+    app: &mut App,
+    producer: P,
+    config: ChunkedMapConfig,
+) -> &mut App {
+    insert_chunked_plugin(app, producer, config);
+    register_debug_map::<P>(app)
+}
+
+/// Adds the chunk gizmo layers and the stats panel entry for `DataMap<P>`, in the chunk
+/// inspector too. Called by `insert_chunked_plugin_with_debug`.
+pub fn register_debug_map<P: MapDataProducer>(app: &mut App) -> &mut App {
+    register_inspectable_map::<P>(app);
+    app.init_resource::<DebugOverlaySettings>()
+        .init_resource::<DebugMapStats>()
+        .add_systems(
+            Update,
+            (
+                debug_chunk_gizmos_system::<P>.run_if(render_enabled),
+                debug_collect_stats_system::<P>,
+            ),
+        )
+}
+
+/// Mismatching seam tiles of `P` around the player, recomputed when the player changes chunk.
+#[derive(Resource)]
+struct SeamDebugCache<P: MapDataProducer> {
+    center: Option<ChunkCoords>, // None until computed, or after the layer was hidden
+    tiles: Vec<Point>,
+    _marker: PhantomData<P>,
+}
+
+impl<P: MapDataProducer> Default for SeamDebugCache<P> {
+    fn default() -> Self {
+        Self {
+            center: None,
+            tiles: Vec::new(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+/// Adds the F4 layer outlining border tiles of `DataMap<P>` that differ from the same tile
+/// generated on its own. Regenerates the chunks around the player synchronously, so it's
+/// opt-in per producer rather than part of `register_debug_map`.
+pub fn register_seam_debug<P: MapDataProducer>(app: &mut App) -> &mut App
+where
+    P::Item: PartialEq + Debug,
+{
+    app.init_resource::<DebugOverlaySettings>()
+        .init_resource::<SeamDebugCache<P>>()
+        .add_systems(Update, debug_seam_gizmos_system::<P>.run_if(render_enabled))
+}
+
+pub(crate) fn producer_name<P>() -> &'static str {
+    let full = std::any::type_name::<P>();
+    full.rsplit("::").next().unwrap_or(full)
+}
+
+fn debug_overlay_toggle_system(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut settings: ResMut<DebugOverlaySettings>,
+) {
+    if keyboard_input.just_pressed(KeyCode::F1) {
+        settings.show_loaded_chunks = !settings.show_loaded_chunks;
+    }
+    if keyboard_input.just_pressed(KeyCode::F2) {
+        settings.show_requested_chunks = !settings.show_requested_chunks;
+    }
+    if keyboard_input.just_pressed(KeyCode::F3) {
+        settings.show_passability = !settings.show_passability;
+    }
+    if keyboard_input.just_pressed(KeyCode::F4) {
+        settings.show_seams = !settings.show_seams;
+    }
+    if keyboard_input.just_pressed(KeyCode::F7) {
+        settings.show_chunk_inspector = !settings.show_chunk_inspector;
+    }
+}
+
+// [ and ] shrink and grow the passability map's render distance, applied by its load/unload system
+fn debug_render_distance_key_system(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    passability: Res<DataMap<PassabilityProducer>>,
+    mut changes: EventWriter<RenderDistanceChange<PassabilityProducer>>,
+) {
+    let current = passability.render_distance_chunks;
+    let chunks = if keyboard_input.just_pressed(KeyCode::BracketLeft) {
+        current.saturating_sub(1)
+    } else if keyboard_input.just_pressed(KeyCode::BracketRight) {
+        current + 1
+    } else {
+        return;
+    };
+    if chunks != current {
+        info!("Passability render distance: {} -> {} chunks", current, chunks);
+        changes.write(RenderDistanceChange::new(chunks));
+    }
+}
+
+/// F6 toggles a sine wave on `LightModulation::global_scale`, an example of an external light input.
+#[derive(Default)]
+struct LightPulseDemo {
+    enabled: bool,
+    elapsed_secs: f32,
+}
+
+const LIGHT_PULSE_PERIOD_SECS: f32 = 2.0;
+
+fn debug_light_pulse_system(
+    time: Res<Time>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut modulation: ResMut<LightModulation>,
+    mut demo: Local<LightPulseDemo>,
+) {
+    if keyboard_input.just_pressed(KeyCode::F6) {
+        demo.enabled = !demo.enabled;
+        demo.elapsed_secs = 0.0;
+        if !demo.enabled {
+            modulation.global_scale = 1.0;
+        }
+    }
+    if !demo.enabled {
+        return;
+    }
+    demo.elapsed_secs += time.delta_secs();
+    let phase = demo.elapsed_secs / LIGHT_PULSE_PERIOD_SECS * std::f32::consts::TAU;
+    modulation.global_scale = 1.0 + 0.75 * phase.sin(); // 0.25..1.75
+}
+
+fn debug_chunk_gizmos_system<P: MapDataProducer>(
+    mut gizmos: Gizmos,
+    settings: Res<DebugOverlaySettings>,
+    data_map: Res<DataMap<P>>,
+) {
+    let size = Vec2::splat(data_map.chunk_size_units);
+    let center_of = |coords: &ChunkCoords| {
+        coords.corner(data_map.chunk_dimension_tiles).0 + size / 2.0
+    };
+
+    if settings.show_loaded_chunks {
+        for coords in data_map.loaded_chunks.keys() {
+            gizmos.rect_2d(center_of(coords), size, Color::srgba(0.0, 1.0, 0.0, 0.1)); // Semi-transparent green
+        }
+    }
+    if settings.show_requested_chunks {
+        for coords in data_map.requested_chunks.iter() {
+            gizmos.rect_2d(center_of(coords), size, Color::srgba(1.0, 1.0, 0.0, 0.2)); // Semi-transparent yellow
+        }
+        for coords in data_map.pending_tasks.keys() {
+            gizmos.rect_2d(center_of(coords), size, Color::srgba(1.0, 0.5, 0.0, 0.2)); // Semi-transparent orange
+        }
+        for coords in data_map.failed_chunks.iter() {
+            gizmos.rect_2d(center_of(coords), size, Color::srgba(1.0, 0.0, 0.0, 0.6)); // Red
+        }
+    }
+}
+
+/// While L is held, draws the tiles between the player and the cursor, the one stopping the ray
+/// in red if a wall does and in orange if it isn't loaded.
+fn debug_raycast_gizmos_system(
+    mut gizmos: Gizmos,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    passability: Res<DataMap<PassabilityProducer>>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Transform, &Projection), With<FollowCamera>>,
+    player_query: Query<&Transform, With<Player>>,
+) {
+    if !keyboard_input.pressed(RAYCAST_KEY) {
+        return;
+    }
+    let (Ok(window), Ok((camera_transform, projection)), Ok(player_transform)) =
+        (window_query.single(), camera_query.single(), player_query.single())
+    else {
+        return;
+    };
+    let Some(cursor) = window.cursor_position() else {
+        return;
+    };
+    let scale = match projection {
+        Projection::Orthographic(ortho) => ortho.scale,
+        _ => 1.0,
+    };
+    let from = player_transform.translation.xy();
+    let to = screen_to_world(cursor, window.size(), camera_transform.translation.xy(), scale);
+    let threshold = Collider::default().threshold;
+    let result = world_raycast(&passability, from, to, |p| p.0 < threshold);
+    let tile_size = Vec2::splat(TILE_SIZE_IN_UNITS);
+    for tile in world_ray_tiles(from, to).take_while(|tile| Some(*tile) != result.stop()) {
+        gizmos.rect_2d(TileCoord(tile).center().0, tile_size, Color::srgba(1.0, 1.0, 1.0, 0.3));
+    }
+    let (end, color) = match result {
+        RaycastResult::Clear => (to, Color::srgb(0.0, 1.0, 0.0)),
+        RaycastResult::Blocked { at, .. } => (TileCoord(at).center().0, Color::srgb(1.0, 0.0, 0.0)),
+        RaycastResult::Unknown { at } => (TileCoord(at).center().0, Color::srgb(1.0, 0.5, 0.0)),
+    };
+    if let Some(at) = result.stop() {
+        gizmos.rect_2d(TileCoord(at).center().0, tile_size, color);
+    }
+    gizmos.line_2d(from, end, color);
+}
+
+fn debug_passability_gizmos_system(
+    mut gizmos: Gizmos,
+    settings: Res<DebugOverlaySettings>,
+    passability: Res<DataMap<PassabilityProducer>>,
+    player_query: Query<&Transform, With<Player>>,
+) {
+    if !settings.show_passability {
+        return;
+    }
+    let Ok(player_transform) = player_query.single() else {
+        return;
+    };
+    let center = WorldPos(player_transform.translation.xy()).to_tile().0;
+    let tile_size = Vec2::splat(TILE_SIZE_IN_UNITS);
+    for dx in -PASSABILITY_RADIUS_TILES..=PASSABILITY_RADIUS_TILES {
+        for dy in -PASSABILITY_RADIUS_TILES..=PASSABILITY_RADIUS_TILES {
+            let point = Point {
+                x: center.x + dx,
+                y: center.y + dy,
+            };
+            let Some(p) = passability.read(point) else {
+                continue;
+            };
+            let t = p.0 as f32 / 255.0;
+            gizmos.rect_2d(TileCoord(point).center().0, tile_size, Color::srgba(1.0 - t, t, 0.0, 0.5));
+        }
+    }
+}
+
+fn debug_seam_gizmos_system<P: MapDataProducer>(
+    mut gizmos: Gizmos,
+    settings: Res<DebugOverlaySettings>,
+    data_map: Res<DataMap<P>>,
+    mut cache: ResMut<SeamDebugCache<P>>,
+    player_query: Query<&Transform, With<Player>>,
+) where
+    P::Item: PartialEq + Debug,
+{
+    if !settings.show_seams {
+        cache.center = None;
+        return;
+    }
+    let Ok(player_transform) = player_query.single() else {
+        return;
+    };
+    let dimension = data_map.chunk_dimension_tiles;
+    let player_tile = WorldPos(player_transform.translation.xy()).to_tile().0;
+    let center = ChunkCoords::from_point(player_tile, dimension);
+
+    if cache.center != Some(center) {
+        let mut pairs = Vec::new();
+        for dx in -SEAM_RADIUS_CHUNKS..=SEAM_RADIUS_CHUNKS {
+            for dy in -SEAM_RADIUS_CHUNKS..=SEAM_RADIUS_CHUNKS {
+                let coords = ChunkCoords {
+                    x: center.x + dx,
+                    y: center.y + dy,
+                };
+                if dx < SEAM_RADIUS_CHUNKS {
+                    pairs.push((coords, ChunkCoords { x: coords.x + 1, ..coords }));
+                }
+                if dy < SEAM_RADIUS_CHUNKS {
+                    pairs.push((coords, ChunkCoords { y: coords.y + 1, ..coords }));
+                }
+            }
+        }
+        let mismatches = validate_producer_seams(&data_map.producer, dimension, &pairs);
+        if !mismatches.is_empty() {
+            warn!(
+                "{}: {} seam mismatches around chunk {:?}, first {:?}",
+                producer_name::<P>(),
+                mismatches.len(),
+                center,
+                mismatches[0]
+            );
+        }
+        cache.tiles = mismatches.into_iter().map(|m| m.point).collect();
+        cache.center = Some(center);
+    }
+
+    let tile_size = Vec2::splat(TILE_SIZE_IN_UNITS);
+    for point in cache.tiles.iter() {
+        gizmos.rect_2d(TileCoord(*point).center().0, tile_size, Color::srgb(1.0, 0.0, 0.0));
+    }
+}
+
+fn debug_collect_stats_system<P: MapDataProducer>(
+    data_map: Res<DataMap<P>>,
+    chunk_stats: Option<Res<ChunkStats<P>>>,
+    mut loaded_events: EventReader<ChunkLoaded<P>>,
+    mut stats: ResMut<DebugMapStats>,
+) {
+    let chunk_stats = chunk_stats.as_deref();
+    stats.0.insert(
+        producer_name::<P>(),
+        MapStats {
+            loaded: data_map.loaded_chunks.len(),
+            requested: data_map.requested_chunks.len(),
+            pending: data_map.pending_tasks.len(),
+            write_queue: data_map.queued_write_count(),
+            completed_this_frame: loaded_events.read().count(),
+            failed: data_map.failed_chunks.len(),
+            bytes: chunk_stats.map_or_else(|| data_map.loaded_bytes(), |c| c.loaded_bytes),
+            evicted: chunk_stats.map_or(0, |c| c.evicted),
+            tasks_spawned: chunk_stats.map_or(0, |c| c.tasks_spawned),
+            cancelled: chunk_stats.map_or(0, |c| c.cancelled),
+            wasted: chunk_stats.map_or(0, |c| c.wasted),
+            avg_generation_ms: chunk_stats.map_or(0.0, |c| c.avg_generation_secs * 1000.0),
+        },
+    );
+}
+
+fn setup_debug_stats_text(mut commands: Commands) {
+    commands.spawn((
+        DebugStatsText,
+        Text::new(""),
+        TextFont {
+            font_size: 14.0,
+            ..default()
+        },
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(8.0),
+            left: Val::Px(8.0),
+            ..default()
+        },
+    ));
+}
+
+fn kib(bytes: usize) -> f32 {
+    bytes as f32 / 1024.0
+}
+
+#[allow(clippy::too_many_arguments)]
+fn debug_stats_text_system(
+    stats: Res<DebugMapStats>,
+    memory_budget: Option<Res<ChunkMemoryBudget>>,
+    settings: Res<DebugOverlaySettings>,
+    cycle: Option<Res<DayNightCycle>>,
+    culling: Option<Res<CullingStats>>,
+    light_sim: Option<Res<LightSimStats>>,
+    speed: Option<Res<SimulationSpeed>>,
+    mut text_query: Query<&mut Text, With<DebugStatsText>>,
+) {
+    let Ok(mut text) = text_query.single_mut() else {
+        return;
+    };
+    let mut panel = format!(
+        "F1 loaded [{}]  F2 requested [{}]  F3 passability [{}]  F4 seams [{}]  F7 inspector [{}]\n",
+        if settings.show_loaded_chunks { "x" } else { " " },
+        if settings.show_requested_chunks { "x" } else { " " },
+        if settings.show_passability { "x" } else { " " },
+        if settings.show_seams { "x" } else { " " },
+        if settings.show_chunk_inspector { "x" } else { " " },
+    );
+    if let Some(speed) = speed {
+        panel.push_str(&match *speed {
+            SimulationSpeed::Paused => "Simulation paused (N steps a tick)\n".to_string(),
+            speed => format!("Simulation speed x{}\n", speed.factor()),
+        });
+    }
+    if let Some(cycle) = cycle {
+        panel.push_str(&format!(
+            "Time of day {:.2} ({:?}){}\n",
+            cycle.time_of_day(),
+            cycle.phase(),
+            if cycle.paused { ", paused" } else { "" }
+        ));
+    }
+    if let Some(culling) = culling {
+        panel.push_str(&format!(
+            "Culled sprites: {} visible {} hidden\n",
+            culling.visible, culling.culled
+        ));
+    }
+    if let Some(light_sim) = light_sim {
+        panel.push_str(&format!(
+            "Light sim: {} steps {}/{} skipped frames {}\n",
+            if light_sim.ran_this_frame { "ran" } else { "idle" },
+            light_sim.steps_done,
+            light_sim.steps_total,
+            light_sim.skipped_frames
+        ));
+    }
+    let total_bytes: usize = stats.0.values().map(|s| s.bytes).sum();
+    panel.push_str(&match memory_budget.map(|budget| budget.max_bytes) {
+        Some(max_bytes) if max_bytes != usize::MAX => {
+            format!("Chunk memory: {:.0} KiB of {:.0} KiB\n", kib(total_bytes), kib(max_bytes))
+        }
+        _ => format!("Chunk memory: {:.0} KiB\n", kib(total_bytes)),
+    });
+    for (name, s) in stats.0.iter() {
+        panel.push_str(&format!(
+            "{name}: loaded {} ({:.0} KiB) requested {} pending {} queued writes {} completed {} failed {}\n  \
+             spawned {} evicted {} cancelled {} wasted {} avg generation {:.1} ms\n",
+            s.loaded,
+            kib(s.bytes),
+            s.requested,
+            s.pending,
+            s.write_queue,
+            s.completed_this_frame,
+            s.failed,
+            s.tasks_spawned,
+            s.evicted,
+            s.cancelled,
+            s.wasted,
+            s.avg_generation_ms
+        ));
+    }
+    text.0 = panel;
+}
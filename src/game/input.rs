@@ -0,0 +1,187 @@
+use bevy::{
+    ecs::{
+        component::Component,
+        query::{With, Without},
+        resource::Resource,
+        system::{Query, Res},
+    },
+    input::{
+        ButtonInput,
+        gamepad::{Gamepad, GamepadAxis, GamepadButton},
+        keyboard::KeyCode,
+    },
+    math::Vec2,
+    platform::collections::HashMap,
+};
+
+use crate::game::{Player, spawn::Dormant};
+
+/// Logical actions, decoupled from the keys and buttons triggering them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InputAction {
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    Interact,
+    ToggleDebug,
+    ToggleMinimap,
+}
+
+/// Everything that triggers one action. Any of them is enough.
+#[derive(Debug, Clone, Default)]
+pub struct ActionBinding {
+    pub keys: Vec<KeyCode>,
+    pub gamepad_buttons: Vec<GamepadButton>,
+}
+
+impl ActionBinding {
+    pub fn new(keys: &[KeyCode], gamepad_buttons: &[GamepadButton]) -> Self {
+        Self {
+            keys: keys.to_vec(),
+            gamepad_buttons: gamepad_buttons.to_vec(),
+        }
+    }
+}
+
+/// Key and gamepad bindings, edit at startup to remap.
+#[derive(Resource, Debug, Clone)]
+pub struct InputBindings {
+    pub actions: HashMap<InputAction, ActionBinding>,
+    pub move_stick: Option<(GamepadAxis, GamepadAxis)>, // X and Y axes giving analog movement
+    pub stick_deadzone: f32, // Stick deflection below this is ignored, the rest is rescaled to 0.0..1.0
+}
+
+impl Default for InputBindings {
+    fn default() -> Self {
+        let actions = [
+            (
+                InputAction::MoveUp,
+                ActionBinding::new(&[KeyCode::KeyW, KeyCode::ArrowUp], &[GamepadButton::DPadUp]),
+            ),
+            (
+                InputAction::MoveDown,
+                ActionBinding::new(&[KeyCode::KeyS, KeyCode::ArrowDown], &[GamepadButton::DPadDown]),
+            ),
+            (
+                InputAction::MoveLeft,
+                ActionBinding::new(&[KeyCode::KeyA, KeyCode::ArrowLeft], &[GamepadButton::DPadLeft]),
+            ),
+            (
+                InputAction::MoveRight,
+                ActionBinding::new(&[KeyCode::KeyD, KeyCode::ArrowRight], &[GamepadButton::DPadRight]),
+            ),
+            (
+                InputAction::Interact,
+                ActionBinding::new(&[KeyCode::KeyE], &[GamepadButton::South]),
+            ),
+            (
+                InputAction::ToggleDebug,
+                ActionBinding::new(&[KeyCode::Backquote], &[GamepadButton::Select]),
+            ),
+            (
+                InputAction::ToggleMinimap,
+                ActionBinding::new(&[KeyCode::KeyM], &[GamepadButton::North]),
+            ),
+        ];
+        Self {
+            actions: actions.into_iter().collect(),
+            move_stick: Some((GamepadAxis::LeftStickX, GamepadAxis::LeftStickY)),
+            stick_deadzone: 0.15,
+        }
+    }
+}
+
+impl InputBindings {
+    /// Whether any key or gamepad button bound to `action` is held.
+    pub fn pressed<'a>(
+        &self,
+        action: InputAction,
+        keys: &ButtonInput<KeyCode>,
+        gamepads: impl IntoIterator<Item = &'a Gamepad>,
+    ) -> bool {
+        let Some(binding) = self.actions.get(&action) else {
+            return false;
+        };
+        keys.any_pressed(binding.keys.iter().copied())
+            || gamepads
+                .into_iter()
+                .any(|gamepad| gamepad.any_pressed(binding.gamepad_buttons.iter().copied()))
+    }
+
+    /// Whether any key or gamepad button bound to `action` was pressed this frame.
+    pub fn just_pressed<'a>(
+        &self,
+        action: InputAction,
+        keys: &ButtonInput<KeyCode>,
+        gamepads: impl IntoIterator<Item = &'a Gamepad>,
+    ) -> bool {
+        let Some(binding) = self.actions.get(&action) else {
+            return false;
+        };
+        keys.any_just_pressed(binding.keys.iter().copied())
+            || gamepads
+                .into_iter()
+                .any(|gamepad| gamepad.any_just_pressed(binding.gamepad_buttons.iter().copied()))
+    }
+
+    /// Stick deflection of `gamepad` with the deadzone applied, at most 1.0 long.
+    pub fn stick(&self, gamepad: &Gamepad) -> Vec2 {
+        let Some((axis_x, axis_y)) = self.move_stick else {
+            return Vec2::ZERO;
+        };
+        let raw = Vec2::new(
+            gamepad.get(axis_x).unwrap_or(0.0),
+            gamepad.get(axis_y).unwrap_or(0.0),
+        );
+        let length = raw.length();
+        if length <= self.stick_deadzone {
+            return Vec2::ZERO;
+        }
+        let scaled = ((length - self.stick_deadzone) / (1.0 - self.stick_deadzone)).min(1.0);
+        raw / length * scaled
+    }
+}
+
+/// Where the entity wants to move, at most 1.0 long. Digital input is always full length.
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct MoveIntent(pub Vec2);
+
+// Resolves the bindings into the player's MoveIntent, the stronger of keyboard/buttons and stick wins
+pub fn resolve_move_intent(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    gamepads: Query<&Gamepad>,
+    bindings: Res<InputBindings>,
+    mut player_query: Query<&mut MoveIntent, (With<Player>, Without<Dormant>)>,
+) {
+    let pressed = |action| bindings.pressed(action, &keyboard_input, gamepads.iter());
+    let mut digital = Vec2::ZERO;
+    if pressed(InputAction::MoveUp) {
+        digital.y += 1.0;
+    }
+    if pressed(InputAction::MoveDown) {
+        digital.y -= 1.0;
+    }
+    if pressed(InputAction::MoveLeft) {
+        digital.x -= 1.0;
+    }
+    if pressed(InputAction::MoveRight) {
+        digital.x += 1.0;
+    }
+    let digital = digital.normalize_or_zero();
+
+    let analog = gamepads
+        .iter()
+        .map(|gamepad| bindings.stick(gamepad))
+        .max_by(|a, b| a.length_squared().total_cmp(&b.length_squared()))
+        .unwrap_or(Vec2::ZERO);
+
+    let intent = if digital.length_squared() >= analog.length_squared() {
+        digital
+    } else {
+        analog
+    };
+    for mut move_intent in player_query.iter_mut() {
+        move_intent.0 = intent;
+    }
+}
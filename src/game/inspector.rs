@@ -0,0 +1,360 @@
+use bevy::{prelude::*, window::PrimaryWindow};
+
+use crate::{
+    core::{
+        chunks::{ChunkCoords, DataMap, GridData, MapDataProducer},
+        constants::TILE_SIZE_IN_UNITS,
+        units::{TilesCount, WorldPos},
+    },
+    game::{
+        camera::{FollowCamera, screen_to_world},
+        debug::{DebugOverlaySettings, producer_name},
+        render::render_enabled,
+    },
+};
+
+const NEXT_MAP_KEY: KeyCode = KeyCode::Tab;
+const REQUEST_KEY: KeyCode = KeyCode::KeyR; // Requests the selected chunk when it isn't loaded
+const HISTOGRAM_BUCKETS: usize = 8;
+
+/// Tile items the chunk inspector summarizes with their min, max and a histogram,
+/// see `register_chunk_summary`.
+pub trait DebugSummarize {
+    /// Split into the histogram buckets, values outside count in the first or last one.
+    const DEBUG_RANGE: (f32, f32);
+    fn debug_value(&self) -> f32;
+}
+
+// Type-erased access to one DataMap, see `register_inspectable_map`
+#[derive(Clone, Copy)]
+struct InspectableMap {
+    name: &'static str,
+    dimension: fn(&World) -> Option<TilesCount>,
+    describe: fn(&World, ChunkCoords) -> String,
+    request: fn(&mut World, ChunkCoords),
+}
+
+/// F7 debug tool: a click selects the chunk under the cursor in one of the DataMaps, which is
+/// outlined and described on screen. Tab cycles the maps, R requests an unloaded chunk.
+#[derive(Resource, Default)]
+pub struct ChunkInspector {
+    maps: Vec<InspectableMap>,
+    pub current: usize,         // Inspected map, in registration order
+    pub selected: Option<Vec2>, // World position clicked, its chunk depends on the map's dimension
+    pub outline: Option<Rect>,  // Of the selected chunk, in world units
+    pub summary: String,
+    request: bool, // R was pressed, applied with the next description
+    log: bool,     // The selection or the map changed, the next description is logged too
+}
+
+#[derive(Component)]
+struct ChunkInspectorText;
+
+pub struct ChunkInspectorPlugin;
+
+impl Plugin for ChunkInspectorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DebugOverlaySettings>()
+            .init_resource::<ChunkInspector>()
+            .add_systems(Startup, setup_chunk_inspector_text)
+            .add_systems(
+                Update,
+                (
+                    chunk_inspector_input_system,
+                    chunk_inspector_describe_system,
+                    (
+                        chunk_inspector_gizmos_system.run_if(render_enabled),
+                        chunk_inspector_text_system,
+                    ),
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// Makes `DataMap<P>` selectable in the chunk inspector, described by its load state.
+/// Called by `register_debug_map`, so every map of the game shows up.
+pub fn register_inspectable_map<P: MapDataProducer>(app: &mut App) -> &mut App {
+    app.init_resource::<ChunkInspector>();
+    let mut inspector = app.world_mut().resource_mut::<ChunkInspector>();
+    if !inspector.maps.iter().any(|map| map.name == producer_name::<P>()) {
+        inspector.maps.push(InspectableMap {
+            name: producer_name::<P>(),
+            dimension: |world| {
+                world
+                    .get_resource::<DataMap<P>>()
+                    .map(|map| map.chunk_dimension_tiles)
+            },
+            describe: describe_chunk::<P>,
+            request: request_chunk::<P>,
+        });
+    }
+    app
+}
+
+/// Adds the min, max and histogram of the loaded tiles of `DataMap<P>` to its description in
+/// the chunk inspector.
+pub fn register_chunk_summary<P: MapDataProducer>(app: &mut App) -> &mut App
+where
+    P::Item: DebugSummarize,
+{
+    register_inspectable_map::<P>(app);
+    let mut inspector = app.world_mut().resource_mut::<ChunkInspector>();
+    if let Some(map) = inspector.maps.iter_mut().find(|map| map.name == producer_name::<P>()) {
+        map.describe = describe_summarized_chunk::<P>;
+    }
+    app
+}
+
+fn describe_chunk<P: MapDataProducer>(world: &World, coords: ChunkCoords) -> String {
+    let Some(map) = world.get_resource::<DataMap<P>>() else {
+        return String::new();
+    };
+    let dimension = map.chunk_dimension_tiles;
+    let state = if let Some(chunk) = map.loaded_chunks.get(&coords) {
+        format!(
+            "loaded, version {}{}{}",
+            chunk.version,
+            if chunk.dirty { ", modified" } else { "" },
+            if chunk.from_store { ", from the store" } else { "" }
+        )
+    } else if let Some(dormant) = map.dormant_chunks.get(&coords) {
+        format!(
+            "dormant, {} bytes compressed{}",
+            dormant.byte_size(),
+            if dormant.dirty { ", modified" } else { "" }
+        )
+    } else if map.pending_tasks.contains_key(&coords) {
+        "generating".to_string()
+    } else if map.requested_chunks.contains(&coords) {
+        "requested".to_string()
+    } else if map.failed_chunks.contains(&coords) {
+        "failed, given up".to_string()
+    } else {
+        format!("not loaded, {REQUEST_KEY:?} requests it")
+    };
+    let queued = map.write_queue.get(&coords).map_or(0, |writes| writes.len());
+    format!(
+        "{} chunk ({}, {}), {dimension}x{dimension} tiles\n{state}, {queued} queued writes\n",
+        producer_name::<P>(),
+        coords.x,
+        coords.y
+    )
+}
+
+fn describe_summarized_chunk<P: MapDataProducer>(world: &World, coords: ChunkCoords) -> String
+where
+    P::Item: DebugSummarize,
+{
+    let mut text = describe_chunk::<P>(world, coords);
+    let chunk = world
+        .get_resource::<DataMap<P>>()
+        .and_then(|map| map.loaded_chunks.get(&coords));
+    if let Some(chunk) = chunk {
+        text.push_str(&summarize_grid(&chunk.grid));
+    }
+    text
+}
+
+/// Min, max and a histogram over `DebugSummarize::DEBUG_RANGE` of every tile of `grid`.
+pub fn summarize_grid<G: GridData>(grid: &G) -> String
+where
+    G::Item: DebugSummarize,
+{
+    let (low, high) = G::Item::DEBUG_RANGE;
+    let mut buckets = [0usize; HISTOGRAM_BUCKETS];
+    let (mut min, mut max) = (f32::INFINITY, f32::NEG_INFINITY);
+    for (_, _, item) in grid.iter_indexed() {
+        let value = item.debug_value();
+        min = min.min(value);
+        max = max.max(value);
+        let bucket = ((value - low) / (high - low) * HISTOGRAM_BUCKETS as f32).floor();
+        buckets[(bucket.max(0.0) as usize).min(HISTOGRAM_BUCKETS - 1)] += 1;
+    }
+    let width = (high - low) / HISTOGRAM_BUCKETS as f32;
+    let mut text = format!("min {min:.2} max {max:.2}\n");
+    for (i, count) in buckets.iter().enumerate() {
+        let from = low + width * i as f32;
+        text.push_str(&format!("  {:>7.2} .. {:<7.2} {count}\n", from, from + width));
+    }
+    text
+}
+
+fn request_chunk<P: MapDataProducer>(world: &mut World, coords: ChunkCoords) {
+    let Some(mut map) = world.get_resource_mut::<DataMap<P>>() else {
+        return;
+    };
+    let dimension = map.chunk_dimension_tiles;
+    // Dropped again later if it's far from every focus and the map cancels irrelevant requests
+    map.request_around(coords.to_bottom_left_tile_point(dimension), 0);
+}
+
+fn chunk_inspector_input_system(
+    settings: Res<DebugOverlaySettings>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Transform, &Projection), With<FollowCamera>>,
+    mut inspector: ResMut<ChunkInspector>,
+) {
+    if !settings.show_chunk_inspector {
+        return;
+    }
+    if keyboard_input.just_pressed(NEXT_MAP_KEY) && !inspector.maps.is_empty() {
+        inspector.current = (inspector.current + 1) % inspector.maps.len();
+        inspector.log = true;
+    }
+    if keyboard_input.just_pressed(REQUEST_KEY) {
+        inspector.request = true;
+    }
+    if !mouse.just_pressed(MouseButton::Left) {
+        return;
+    }
+    let (Ok(window), Ok((camera_transform, projection))) = (window_query.single(), camera_query.single()) else {
+        return;
+    };
+    let Some(cursor) = window.cursor_position() else {
+        return;
+    };
+    let scale = match projection {
+        Projection::Orthographic(ortho) => ortho.scale,
+        _ => 1.0,
+    };
+    inspector.selected = Some(screen_to_world(cursor, window.size(), camera_transform.translation.xy(), scale));
+    inspector.log = true;
+}
+
+// Exclusive, the registered hooks read the DataMaps straight from the world
+fn chunk_inspector_describe_system(world: &mut World) {
+    let active = world
+        .get_resource::<DebugOverlaySettings>()
+        .is_some_and(|settings| settings.show_chunk_inspector);
+    if !active {
+        return;
+    }
+    world.resource_scope(|world, mut inspector: Mut<ChunkInspector>| {
+        let Some(map) = inspector.maps.get(inspector.current).copied() else {
+            inspector.summary = "Chunk inspector: no chunked maps\n".to_string();
+            return;
+        };
+        let (Some(selected), Some(dimension)) = (inspector.selected, (map.dimension)(world)) else {
+            inspector.outline = None;
+            inspector.summary = format!("Chunk inspector: {} ({NEXT_MAP_KEY:?} cycles), click a chunk\n", map.name);
+            return;
+        };
+        let coords = ChunkCoords::from_point(WorldPos(selected).to_tile().0, dimension);
+        if std::mem::take(&mut inspector.request) {
+            (map.request)(world, coords);
+            inspector.log = true;
+        }
+        let corner = coords.corner(dimension).0;
+        let size = Vec2::splat(dimension as f32 * TILE_SIZE_IN_UNITS);
+        inspector.outline = Some(Rect::from_corners(corner, corner + size));
+        inspector.summary = (map.describe)(world, coords);
+        if std::mem::take(&mut inspector.log) {
+            info!("Chunk inspector: {}", inspector.summary.trim_end());
+        }
+    });
+}
+
+fn chunk_inspector_gizmos_system(
+    mut gizmos: Gizmos,
+    settings: Res<DebugOverlaySettings>,
+    inspector: Res<ChunkInspector>,
+) {
+    let Some(outline) = inspector.outline.filter(|_| settings.show_chunk_inspector) else {
+        return;
+    };
+    gizmos.rect_2d(outline.center(), outline.size(), Color::srgb(0.0, 1.0, 1.0));
+}
+
+fn setup_chunk_inspector_text(mut commands: Commands) {
+    commands.spawn((
+        ChunkInspectorText,
+        Text::new(""),
+        TextFont {
+            font_size: 14.0,
+            ..default()
+        },
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(8.0),
+            left: Val::Px(8.0),
+            ..default()
+        },
+    ));
+}
+
+fn chunk_inspector_text_system(
+    settings: Res<DebugOverlaySettings>,
+    inspector: Res<ChunkInspector>,
+    mut text_query: Query<&mut Text, With<ChunkInspectorText>>,
+) {
+    let Ok(mut text) = text_query.single_mut() else {
+        return;
+    };
+    let summary = if settings.show_chunk_inspector { inspector.summary.as_str() } else { "" };
+    if text.0 != summary {
+        text.0 = summary.to_string();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        core::chunks::FlatGrid,
+        game::world::passability::{Passability, PassabilityProducer},
+    };
+
+    #[test]
+    fn summary_buckets_values_over_the_debug_range() {
+        let mut grid = FlatGrid::new(4, Passability(0));
+        grid.set_item(1, 0, Passability(40));
+        grid.set_item(2, 0, Passability(255));
+        let summary = summarize_grid(&grid);
+        let lines: Vec<&str> = summary.lines().collect();
+        assert_eq!(lines[0], "min 0.00 max 255.00");
+        assert_eq!(lines.len(), 1 + HISTOGRAM_BUCKETS);
+        // 32 wide buckets from 0 to 256
+        assert!(lines[1].ends_with(" 14"), "{}", lines[1]);
+        assert!(lines[2].ends_with(" 1"), "{}", lines[2]);
+        assert!(lines[HISTOGRAM_BUCKETS].ends_with(" 1"), "{}", lines[HISTOGRAM_BUCKETS]);
+    }
+
+    fn summary(app: &mut App) -> String {
+        app.update();
+        app.world().resource::<ChunkInspector>().summary.clone()
+    }
+
+    #[test]
+    fn selected_chunk_is_described_requested_and_summarized() {
+        let mut app = App::new();
+        app.insert_resource(DataMap::new(PassabilityProducer::default(), 16, 1))
+            .insert_resource(DebugOverlaySettings {
+                show_chunk_inspector: true,
+                ..default()
+            })
+            .add_systems(Update, chunk_inspector_describe_system);
+        register_chunk_summary::<PassabilityProducer>(&mut app);
+        assert!(summary(&mut app).contains("click a chunk"));
+
+        // Tile 20 is in the second chunk of 16
+        let coords = ChunkCoords { x: 1, y: 0 };
+        app.world_mut().resource_mut::<ChunkInspector>().selected = Some(Vec2::new(20.0, 4.0) * TILE_SIZE_IN_UNITS);
+        let text = summary(&mut app);
+        assert!(text.contains("chunk (1, 0), 16x16 tiles"), "{text}");
+        assert!(text.contains("not loaded"), "{text}");
+        let outline = app.world().resource::<ChunkInspector>().outline.unwrap();
+        assert_eq!(outline.min, coords.corner(16).0);
+
+        app.world_mut().resource_mut::<ChunkInspector>().request = true;
+        assert!(summary(&mut app).contains("requested"));
+        assert!(app.world().resource::<DataMap<PassabilityProducer>>().requested_chunks.contains(&coords));
+
+        app.world_mut().resource_mut::<DataMap<PassabilityProducer>>().load_generated_chunk(coords);
+        let text = summary(&mut app);
+        assert!(text.contains("loaded, version"), "{text}");
+        assert!(text.contains("min "), "no histogram: {text}");
+    }
+}
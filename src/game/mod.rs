@@ -1,63 +1,102 @@
 use bevy::{
     ecs::{
+        change_detection::DetectChangesMut,
         component::Component,
-        query::With,
+        query::{With, Without},
         system::{Query, Res},
     },
-    input::{ButtonInput, keyboard::KeyCode},
-    math::{Vec3, Vec3Swizzles},
+    math::Vec3Swizzles,
     sprite::{ColorMaterial, MeshMaterial2d},
     transform::components::Transform,
 };
 
-use crate::{core::chunks::DataMap, game::{physix::PrevXY, world::passability::PassabilityProducer}, Pallete};
+use crate::{core::{basics::DEFAULT_RENDER_DISTANCE_CHUNKS, chunks::{DataMap, FocusMotion}}, game::{input::MoveIntent, physix::Velocity, render::light_sim::directions::Direction, teleport::Teleporting, world::passability::PassabilityProducer}, Pallete};
 
 pub mod render;
 pub mod world;
 pub mod physix;
+pub mod camera;
+pub mod debug;
+pub mod input;
+pub mod inspector;
+pub mod ants;
+pub mod bootstrap;
+pub mod npc;
+pub mod projectile;
+pub mod required;
+pub mod replay;
+pub mod sim_speed;
+pub mod spawn;
+pub mod teleport;
 
 // --- Player Component for focus point ---
 #[derive(Component)]
+#[require(Facing)]
 pub struct Player;
 
-#[derive(Component)]
-pub struct MapRevealActor;
+/// Where the entity looks, the direction of its last nonzero movement input. For directional
+/// features like a cone light or projectiles fired ahead.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Facing(pub Direction);
+
+impl Default for Facing {
+    fn default() -> Self {
+        Self(Direction::S) // Toward the viewer
+    }
+}
+
+/// Keeps the chunks around the entity loaded, and the ones ahead of it while it moves.
+#[derive(Component, Debug, Clone, Copy)]
+#[require(FocusMotion)]
+pub struct MapRevealActor {
+    pub radius_chunks: usize, // Capped by each DataMap's own render distance
+    pub priority: u8,         // Higher priority actors get their chunks generated first
+}
+
+impl Default for MapRevealActor {
+    fn default() -> Self {
+        Self {
+            radius_chunks: DEFAULT_RENDER_DISTANCE_CHUNKS,
+            priority: 0,
+        }
+    }
+}
+
+type MovingPlayerQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static Transform,
+        &'static MoveIntent,
+        &'static mut Velocity,
+        &'static mut MeshMaterial2d<ColorMaterial>,
+        &'static mut Facing,
+    ),
+    (With<Player>, Without<Teleporting>),
+>;
 
 // --- Example Player movement system ---
 pub fn player_movement(
-    mut player_query: Query<(&mut Transform, &mut PrevXY, &mut MeshMaterial2d<ColorMaterial>), With<Player>>,
+    mut player_query: MovingPlayerQuery,
     passability: Res<DataMap<PassabilityProducer>>,
-    keyboard_input: Res<ButtonInput<KeyCode>>,
-    time: Res<bevy::time::Time>,
     pallete: Res<Pallete>,
 ) {
-    let (mut transform, mut prev, mut material) = player_query.single_mut().unwrap();
-    let pass = passability.read_rounded(transform.translation.xy());
+    let Ok((transform, intent, mut velocity, mut material, mut facing)) = player_query.single_mut() else {
+        return; // Not spawned yet
+    };
+    // Standing still keeps the last direction
+    if let Some(direction) = Direction::from_vec(intent.0) {
+        facing.set_if_neq(Facing(direction));
+    }
+    let pass = passability.sample_bilinear(transform.translation.xy());
     if let Some(p) = pass {
-        if p.0 < 200 {
-            material.0 = pallete.colors.get("red").unwrap().clone();
-        } else {
-            material.0 = pallete.colors.get("limegreen").unwrap().clone();
+        let color = if p < 200.0 { "red" } else { "limegreen" };
+        if let Some(handle) = pallete.colors.get(color) {
+            material.0 = handle.clone();
         }
     }
     let move_speed = 200.0; // units per second
-    let mut direction = Vec3::ZERO;
 
-    if keyboard_input.pressed(KeyCode::KeyW) {
-        direction.y += 1.0;
-    }
-    if keyboard_input.pressed(KeyCode::KeyS) {
-        direction.y -= 1.0;
-    }
-    if keyboard_input.pressed(KeyCode::KeyA) {
-        direction.x -= 1.0;
-    }
-    if keyboard_input.pressed(KeyCode::KeyD) {
-        direction.x += 1.0;
-    }
-
-    if direction != Vec3::ZERO {
-        prev.0 = transform.translation.clone();
-        transform.translation += direction.normalize() * move_speed * time.delta_secs();
-    }
+    // Movement itself happens in physix::apply_velocity
+    velocity.0 = intent.0 * move_speed;
 }
@@ -0,0 +1,255 @@
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::{
+    Pallete,
+    core::{
+        basics::Point,
+        chunks::{ChunkCoords, DataMap},
+        constants::TILE_SIZE_IN_UNITS,
+        rng::SimRng,
+        units::WorldPos,
+    },
+    game::{
+        physix::{Collider, PrevXY, SpatialGrid, SpatialGridPlugin, SpatiallyIndexed, Velocity},
+        render::{RenderLayerZ, lit_sprite::LitSprite},
+        spawn::{is_free_spot, tile_to_world},
+        world::{
+            passability::PassabilityProducer,
+            pathfinding::{PathOptions, PathResult, find_path},
+        },
+    },
+};
+
+const WANDERER_RADIUS: f32 = 3.0;
+const WAYPOINT_REACHED_DISTANCE: f32 = TILE_SIZE_IN_UNITS / 4.0;
+const TARGET_PICK_ATTEMPTS: usize = 8;
+const BLOCKED_SPEED_FRACTION: f32 = 0.1; // Moving slower than this part of the speed counts as blocked
+
+/// Knobs of the NPC wanderers.
+#[derive(Resource, Debug, Clone)]
+pub struct WanderSettings {
+    pub count: usize,
+    pub spawn_radius_tiles: isize,  // Around the origin
+    pub wander_radius_tiles: isize, // Around the wanderer's current tile
+    pub speed: f32,                 // Units per second
+    pub repick_secs: f32,           // A new target is picked this often
+    pub blocked_repick_secs: f32,   // Or after being stuck this long
+    pub crowd_radius_tiles: f32,    // Targets are picked away from other wanderers this close
+}
+
+impl Default for WanderSettings {
+    fn default() -> Self {
+        Self {
+            count: 8,
+            spawn_radius_tiles: 16,
+            wander_radius_tiles: 10,
+            speed: 60.0,
+            repick_secs: 4.0,
+            blocked_repick_secs: 1.0,
+            crowd_radius_tiles: 6.0,
+        }
+    }
+}
+
+/// NPC walking between random reachable tiles. Not a `MapRevealActor`, so it never
+/// loads chunks by itself and idles while standing in an unloaded one.
+#[derive(Component, Debug, Clone, Default)]
+pub struct Wanderer {
+    pub path: Vec<Point>, // Remaining waypoints, next first
+    pub since_pick: f32,
+    pub blocked_for: f32,
+    pub last_position: Vec2, // As of the previous frame, for detecting being stuck
+}
+
+pub struct WanderPlugin;
+
+impl Plugin for WanderPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<SpatialGridPlugin>() {
+            app.add_plugins(SpatialGridPlugin);
+        }
+        app.init_resource::<WanderSettings>()
+            .init_resource::<SimRng>()
+            .add_systems(Update, (spawn_wanderers, wander_system).chain());
+    }
+}
+
+/// Random free tile within `radius` of `center`, or None if none was hit in a few attempts.
+fn random_free_tile(
+    passability: &DataMap<PassabilityProducer>,
+    center: Point,
+    radius: isize,
+    threshold: u8,
+    rng: &mut impl Rng,
+) -> Option<Point> {
+    (0..TARGET_PICK_ATTEMPTS)
+        .map(|_| Point {
+            x: center.x + rng.random_range(-radius as i64..=radius as i64) as isize, // rand can't sample isize
+            y: center.y + rng.random_range(-radius as i64..=radius as i64) as isize,
+        })
+        .find(|&tile| is_free_spot(passability, tile, threshold))
+}
+
+/// Free tile within `radius` of `center` that is farthest from the centroid of `crowd`, so
+/// wanderers spread out instead of clustering. Plain `random_free_tile` with no crowd.
+fn free_tile_away_from(
+    passability: &DataMap<PassabilityProducer>,
+    center: Point,
+    radius: isize,
+    threshold: u8,
+    crowd: &[Vec2],
+    rng: &mut impl Rng,
+) -> Option<Point> {
+    if crowd.is_empty() {
+        return random_free_tile(passability, center, radius, threshold, rng);
+    }
+    let centroid = crowd.iter().sum::<Vec2>() / crowd.len() as f32;
+    (0..TARGET_PICK_ATTEMPTS)
+        .filter_map(|_| random_free_tile(passability, center, radius, threshold, rng))
+        .max_by(|a, b| {
+            let da = tile_to_world(*a).distance_squared(centroid);
+            let db = tile_to_world(*b).distance_squared(centroid);
+            da.total_cmp(&db)
+        })
+}
+
+/// Spawns the wanderers once, as soon as the chunk of the origin is loaded.
+pub fn spawn_wanderers(
+    mut commands: Commands,
+    mut spawned: Local<bool>,
+    settings: Res<WanderSettings>,
+    passability: Res<DataMap<PassabilityProducer>>,
+    pallete: Res<Pallete>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut rng: ResMut<SimRng>,
+) {
+    if *spawned {
+        return;
+    }
+    let origin = Point { x: 0, y: 0 };
+    let origin_chunk = ChunkCoords::from_point(origin, passability.chunk_dimension_tiles);
+    if !passability.loaded_chunks.contains_key(&origin_chunk) {
+        return;
+    }
+    *spawned = true;
+
+    let collider = Collider {
+        radius: WANDERER_RADIUS,
+        ..default()
+    };
+    let mesh = meshes.add(Circle::new(WANDERER_RADIUS));
+    let material = pallete.colors.get("wanderer").cloned().unwrap_or_default();
+    let mut placed = 0;
+    // Free tiles are common, so a few attempts per wanderer find enough of them
+    for _ in 0..settings.count * TARGET_PICK_ATTEMPTS {
+        if placed == settings.count {
+            break;
+        }
+        let Some(tile) = random_free_tile(
+            &passability,
+            origin,
+            settings.spawn_radius_tiles,
+            collider.threshold,
+            &mut rng.0,
+        ) else {
+            continue;
+        };
+        let translation = tile_to_world(tile).extend(RenderLayerZ::Actors.z());
+        commands.spawn((
+            Wanderer {
+                since_pick: settings.repick_secs, // Pick the first target right away
+                last_position: translation.xy(),
+                ..default()
+            },
+            collider,
+            Velocity::default(),
+            PrevXY(translation),
+            Transform::from_translation(translation),
+            Mesh2d(mesh.clone()),
+            MeshMaterial2d(material.clone()),
+            LitSprite::default(),
+            SpatiallyIndexed,
+        ));
+        placed += 1;
+    }
+    info!("Spawned {placed} wanderers");
+}
+
+/// Walks every wanderer along its path, picking a new reachable target when the old one
+/// is reached, too old, or blocked for too long. New targets lead away from the other
+/// wanderers nearby, as indexed in the `SpatialGrid`.
+pub fn wander_system(
+    settings: Res<WanderSettings>,
+    passability: Res<DataMap<PassabilityProducer>>,
+    grid: Res<SpatialGrid>,
+    time: Res<Time>,
+    mut rng: ResMut<SimRng>,
+    mut query: Query<(Entity, &Transform, &Collider, &mut Wanderer, &mut Velocity)>,
+    others: Query<(), With<Wanderer>>,
+) {
+    let dt = time.delta_secs();
+    let crowd_radius = settings.crowd_radius_tiles * TILE_SIZE_IN_UNITS;
+    for (entity, transform, collider, mut wanderer, mut velocity) in query.iter_mut() {
+        let position = transform.translation.xy();
+        let tile = WorldPos(position).to_tile().0;
+        if passability.read(tile).is_none() {
+            // Standing in an unloaded chunk: wait until it is loaded again
+            wanderer.path.clear();
+            velocity.0 = Vec2::ZERO;
+            continue;
+        }
+
+        wanderer.since_pick += dt;
+        // Velocity is integrated in FixedUpdate, so look at how far the wanderer actually went
+        if velocity.0 != Vec2::ZERO {
+            let blocked = position.distance(wanderer.last_position) < settings.speed * BLOCKED_SPEED_FRACTION * dt;
+            wanderer.blocked_for = if blocked { wanderer.blocked_for + dt } else { 0.0 };
+        }
+        wanderer.last_position = position;
+
+        while wanderer
+            .path
+            .first()
+            .is_some_and(|&next| tile_to_world(next).distance(position) < WAYPOINT_REACHED_DISTANCE)
+        {
+            wanderer.path.remove(0);
+        }
+
+        let needs_target = wanderer.since_pick >= settings.repick_secs
+            || wanderer.blocked_for > settings.blocked_repick_secs;
+        if needs_target {
+            wanderer.path.clear();
+            wanderer.since_pick = 0.0;
+            wanderer.blocked_for = 0.0;
+            let crowd: Vec<Vec2> = grid
+                .query_radius(position, crowd_radius)
+                .filter(|other| *other != entity && others.contains(*other))
+                .filter_map(|other| grid.position(other))
+                .collect();
+            let goal = free_tile_away_from(
+                &passability,
+                tile,
+                settings.wander_radius_tiles,
+                collider.threshold,
+                &crowd,
+                &mut rng.0,
+            );
+            let opts = PathOptions {
+                threshold: collider.threshold,
+                unloaded_is_blocked: true,
+                max_explored_nodes: 2_000,
+                ..default()
+            };
+            let result = goal.map(|goal| find_path(tile, goal, &passability, opts));
+            if let Some(PathResult::Found { path, .. }) = result {
+                wanderer.path = path.into_iter().skip(1).collect(); // The first point is the current tile
+            }
+        }
+
+        velocity.0 = match wanderer.path.first() {
+            Some(&next) => (tile_to_world(next) - position).normalize_or_zero() * settings.speed,
+            None => Vec2::ZERO,
+        };
+    }
+}
@@ -0,0 +1,125 @@
+use bevy::{prelude::*, transform::TransformSystem};
+
+use crate::core::constants::TILE_SIZE_IN_UNITS;
+
+const SNAP_DISTANCE_UNITS: f32 = 8.0 * TILE_SIZE_IN_UNITS; // Longer jumps in one tick are teleports, not movement
+
+/// Translation of the entity when the last fixed tick started, see `RenderInterpolated`.
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct PreviousFixedTransform(pub Vec3);
+
+/// Draws the entity between its positions of the last two fixed ticks instead of where the last
+/// tick left it, so it moves smoothly at any frame rate. `Transform` stays the simulated position
+/// every system reads, only `GlobalTransform` is moved, after the transform propagation.
+#[derive(Component, Debug, Clone, Copy, Default)]
+#[require(PreviousFixedTransform)]
+pub struct RenderInterpolated {
+    pub translation: Vec3, // Where the entity is drawn this frame, also what the camera follows
+}
+
+pub struct RenderInterpolationPlugin;
+
+impl Plugin for RenderInterpolationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(FixedFirst, record_previous_fixed_transforms)
+            .add_systems(Update, interpolate_fixed_transforms)
+            .add_systems(
+                PostUpdate,
+                apply_render_interpolation.after(TransformSystem::TransformPropagate),
+            );
+    }
+}
+
+/// Position at `overstep` (0.0 at the previous fixed tick, 1.0 at the current one) between the
+/// two. Jumps longer than `SNAP_DISTANCE_UNITS` are drawn at once.
+pub fn interpolated_translation(previous: Vec3, current: Vec3, overstep: f32) -> Vec3 {
+    if previous.xy().distance(current.xy()) > SNAP_DISTANCE_UNITS {
+        return current;
+    }
+    previous.lerp(current, overstep.clamp(0.0, 1.0))
+}
+
+fn record_previous_fixed_transforms(mut query: Query<(&Transform, &mut PreviousFixedTransform)>) {
+    for (transform, mut previous) in query.iter_mut() {
+        previous.0 = transform.translation;
+    }
+}
+
+/// Updates `RenderInterpolated::translation` with how far the fixed clock got into its next tick.
+pub fn interpolate_fixed_transforms(
+    time: Res<Time<Fixed>>,
+    mut query: Query<(&Transform, &PreviousFixedTransform, &mut RenderInterpolated)>,
+) {
+    let overstep = time.overstep_fraction();
+    for (transform, previous, mut interpolated) in query.iter_mut() {
+        interpolated.translation = interpolated_translation(previous.0, transform.translation, overstep);
+    }
+}
+
+// Root entities only, children would need their parent's interpolated transform
+fn apply_render_interpolation(
+    mut query: Query<(&Transform, &RenderInterpolated, &mut GlobalTransform), Without<ChildOf>>,
+) {
+    for (transform, interpolated, mut global) in query.iter_mut() {
+        *global = GlobalTransform::from(transform.with_translation(interpolated.translation));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXED_HZ: f32 = 30.0;
+
+    #[test]
+    fn interpolation_at_synthetic_oversteps() {
+        let (previous, current) = (Vec3::new(0.0, 0.0, 5.0), Vec3::new(8.0, -4.0, 5.0));
+        assert_eq!(interpolated_translation(previous, current, 0.0), previous);
+        assert_eq!(interpolated_translation(previous, current, 0.25), Vec3::new(2.0, -1.0, 5.0));
+        assert_eq!(interpolated_translation(previous, current, 0.5), Vec3::new(4.0, -2.0, 5.0));
+        assert_eq!(interpolated_translation(previous, current, 1.0), current);
+        // A late frame never extrapolates, an early one never goes back
+        assert_eq!(interpolated_translation(previous, current, 1.7), current);
+        assert_eq!(interpolated_translation(previous, current, -0.2), previous);
+    }
+
+    #[test]
+    fn teleports_are_not_interpolated() {
+        let far = Vec3::new(SNAP_DISTANCE_UNITS + 1.0, 0.0, 0.0);
+        assert_eq!(interpolated_translation(Vec3::ZERO, far, 0.5), far);
+        let near = Vec3::new(SNAP_DISTANCE_UNITS, 0.0, 0.0);
+        assert_eq!(interpolated_translation(Vec3::ZERO, near, 0.5), near / 2.0);
+    }
+
+    // Positions drawn over a second of steady movement at `frame_hz`, with a 30 Hz fixed clock
+    fn drawn_positions(frame_hz: f32, speed: f32) -> Vec<f32> {
+        let (period, frame) = (1.0 / FIXED_HZ, 1.0 / frame_hz);
+        let (mut previous, mut current, mut accumulated) = (0.0, 0.0, 0.0);
+        (0..frame_hz as usize)
+            .map(|_| {
+                accumulated += frame;
+                while accumulated >= period {
+                    accumulated -= period;
+                    previous = current;
+                    current += speed * period;
+                }
+                let overstep = accumulated / period;
+                interpolated_translation(Vec3::X * previous, Vec3::X * current, overstep).x
+            })
+            .collect()
+    }
+
+    #[test]
+    fn steady_movement_is_drawn_steadily_at_any_frame_rate() {
+        let speed = 200.0;
+        for frame_hz in [20.0, 144.0] {
+            let positions = drawn_positions(frame_hz, speed);
+            let expected_step = speed / frame_hz;
+            // After the first tick, every frame moves by the same distance, whenever the ticks land
+            for pair in positions.windows(2).skip(frame_hz as usize / 10) {
+                let step = pair[1] - pair[0];
+                assert!((step - expected_step).abs() < 0.01, "{step} instead of {expected_step} at {frame_hz} Hz");
+            }
+        }
+    }
+}
@@ -1,21 +1,182 @@
 use bevy::prelude::*;
 
 use crate::{
-    core::chunks::DataMap,
+    core::{chunks::DataMap, constants::TILE_SIZE_IN_UNITS},
     game::world::passability::PassabilityProducer,
 };
 
+pub mod interpolation;
+pub mod spatial;
+
+pub use interpolation::{PreviousFixedTransform, RenderInterpolated, RenderInterpolationPlugin};
+pub use spatial::{SpatialGrid, SpatialGridPlugin, SpatiallyIndexed};
+
 #[derive(Component, Default)]
 pub struct PrevXY(pub Vec3);
 
+/// Velocity in world units per second, integrated by `apply_velocity`.
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct Velocity(pub Vec2);
+
+/// Circle that collides with tiles whose passability is below `threshold`.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Collider {
+    pub radius: f32,
+    pub threshold: u8,
+}
+
+impl Default for Collider {
+    fn default() -> Self {
+        Self {
+            radius: 0.0,
+            threshold: 10,
+        }
+    }
+}
+
+impl Collider {
+    /// Center and the eight compass points on the collider's edge.
+    fn sample_points(&self, position: Vec2) -> impl Iterator<Item = Vec2> {
+        let diagonal = self.radius * std::f32::consts::FRAC_1_SQRT_2;
+        let radius = self.radius;
+        [
+            Vec2::ZERO,
+            Vec2::new(0.0, radius),
+            Vec2::new(diagonal, diagonal),
+            Vec2::new(radius, 0.0),
+            Vec2::new(diagonal, -diagonal),
+            Vec2::new(0.0, -radius),
+            Vec2::new(-diagonal, -diagonal),
+            Vec2::new(-radius, 0.0),
+            Vec2::new(-diagonal, diagonal),
+        ]
+        .into_iter()
+        .map(move |offset| position + offset)
+    }
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct PhysixSettings {
+    pub passability_threshold: f32, // Interpolated passability below this is impassable
+}
+
+impl Default for PhysixSettings {
+    fn default() -> Self {
+        Self {
+            passability_threshold: 10.0,
+        }
+    }
+}
+
+/// Unloaded tiles count as impassable, so nothing walks into terrain that is not generated yet.
+fn is_passable(passability: &DataMap<PassabilityProducer>, position: Vec2, collider: &Collider) -> bool {
+    collider.sample_points(position).all(|point| {
+        passability
+            .sample_bilinear(point)
+            .is_some_and(|p| p >= collider.threshold as f32)
+    })
+}
+
+/// Moves along `delta` in steps of at most half a tile and stops before the first impassable one.
+/// Sampled bilinearly, a one tile wall only blocks a band `2 * threshold / 255` tiles wide, so
+/// the steps are no longer than half of that.
+pub fn sweep(
+    position: Vec2,
+    delta: Vec2,
+    passability: &DataMap<PassabilityProducer>,
+    collider: &Collider,
+) -> Vec2 {
+    let thinnest_wall = TILE_SIZE_IN_UNITS * 2.0 * collider.threshold as f32 / 255.0;
+    let max_step = match collider.threshold {
+        0 => TILE_SIZE_IN_UNITS / 2.0, // Nothing blocks
+        _ => (thinnest_wall / 2.0).min(TILE_SIZE_IN_UNITS / 2.0),
+    };
+    let steps = (delta.length() / max_step).ceil().max(1.0) as usize;
+    let step = delta / steps as f32;
+    let mut current = position;
+    for _ in 0..steps {
+        let next = current + step;
+        if !is_passable(passability, next, collider) {
+            break;
+        }
+        current = next;
+    }
+    current
+}
+
+/// Returns the position reached when moving from `position` by `delta` without crossing impassable tiles.
+/// When blocked, the rest of the movement is retried per axis so the entity slides along walls.
+/// Entities already overlapping an impassable tile (e.g. after a teleport) move freely,
+/// `resolve_tile_collisions` takes care of them.
+pub fn move_and_collide(
+    position: Vec2,
+    delta: Vec2,
+    passability: &DataMap<PassabilityProducer>,
+    collider: &Collider,
+) -> Vec2 {
+    if !is_passable(passability, position, collider) {
+        return position + delta;
+    }
+    let target = position + delta;
+    let reached = sweep(position, delta, passability, collider);
+    if reached == target {
+        return reached;
+    }
+    let remaining = target - reached;
+    let slid_x = sweep(reached, Vec2::new(remaining.x, 0.0), passability, collider);
+    sweep(slid_x, Vec2::new(0.0, remaining.y), passability, collider)
+}
+
+/// Integrates `Velocity`, sweeping entities with a `Collider` against the passability map.
+pub fn apply_velocity(
+    q: Query<(&mut Transform, &Velocity, Option<&Collider>, Option<&mut PrevXY>)>,
+    passability: Res<DataMap<PassabilityProducer>>,
+    time: Res<Time>,
+) {
+    for (mut transform, velocity, collider, prev) in q {
+        if velocity.0 == Vec2::ZERO {
+            continue;
+        }
+        if let Some(mut prev) = prev {
+            prev.0 = transform.translation;
+        }
+        let position = transform.translation.xy();
+        let delta = velocity.0 * time.delta_secs();
+        let moved = match collider {
+            Some(collider) => move_and_collide(position, delta, &passability, collider),
+            None => position + delta,
+        };
+        transform.translation = moved.extend(transform.translation.z);
+    }
+}
+
+/// Restores `PrevXY` for colliders that ended up overlapping impassable tiles.
+pub fn resolve_tile_collisions(
+    q: Query<(&mut Transform, &Collider, &PrevXY)>,
+    passability: Res<DataMap<PassabilityProducer>>,
+) {
+    for (mut transform, collider, prevxy) in q {
+        let overlaps_wall = collider.sample_points(transform.translation.xy()).any(|point| {
+            passability
+                .sample_bilinear(point)
+                .is_some_and(|p| p < collider.threshold as f32)
+        });
+        if overlaps_wall {
+            transform.translation = prevxy.0;
+        }
+    }
+}
+
+/// Fallback for teleports and other movement that does not go through `move_and_collide`.
 pub fn bounce_back(
-    q: Query<(&mut Transform, &PrevXY)>,
+    q: Query<(&mut Transform, &PrevXY), Without<Collider>>,
     passability: Res<DataMap<PassabilityProducer>>,
+    settings: Res<PhysixSettings>,
 ) {
     for (mut transform, prevxy) in q {
-        let pass = passability.read_rounded(transform.translation.xy());
+        let pass = passability.sample_bilinear(transform.translation.xy());
         if let Some(p) = pass {
-            if p.0 < 10 {
+            if p < settings.passability_threshold {
                 // impassable
                 transform.translation = prevxy.0;
             } else {
@@ -24,3 +185,71 @@ pub fn bounce_back(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        core::{
+            basics::Point,
+            chunks::{ChunkCoords, DataChunk, FlatGrid},
+        },
+        game::world::passability::Passability,
+    };
+
+    const DIMENSION: usize = 16;
+    const WALL: isize = 3; // Tile column or row of the walls
+    const PLAYER: Collider = Collider {
+        radius: 5.0,
+        threshold: 10,
+    };
+
+    /// Free tiles from -16 to 31 on both axes, with the given tiles walled.
+    fn map(walls: impl Fn(isize, isize) -> bool) -> DataMap<PassabilityProducer> {
+        let mut map = DataMap::new(PassabilityProducer::default(), DIMENSION, 1);
+        for y in -1..=1 {
+            for x in -1..=1 {
+                let chunk = DataChunk::new(FlatGrid::new(DIMENSION, Passability::FREE));
+                map.loaded_chunks.insert(ChunkCoords { x, y }, chunk);
+            }
+        }
+        for y in -16..32 {
+            for x in -16..32 {
+                if walls(x, y) {
+                    map.write(Point { x, y }, Passability::IMPASSABLE);
+                }
+            }
+        }
+        map
+    }
+
+    fn wall_edge() -> f32 {
+        WALL as f32 * TILE_SIZE_IN_UNITS - PLAYER.radius
+    }
+
+    #[test]
+    fn fast_movement_does_not_tunnel_through_a_thin_wall() {
+        let map = map(|x, _| x == WALL);
+        let reached = move_and_collide(Vec2::ZERO, Vec2::new(20.0 * TILE_SIZE_IN_UNITS, 0.0), &map, &PLAYER);
+        assert!(reached.x > TILE_SIZE_IN_UNITS, "stopped too early: {reached}");
+        assert!(reached.x < wall_edge(), "went through the wall: {reached}");
+        assert_eq!(reached.y, 0.0);
+    }
+
+    #[test]
+    fn diagonal_movement_slides_along_a_wall() {
+        let map = map(|x, _| x == WALL);
+        let delta = Vec2::splat(6.0 * TILE_SIZE_IN_UNITS);
+        let reached = move_and_collide(Vec2::ZERO, delta, &map, &PLAYER);
+        assert!(reached.x < wall_edge(), "went through the wall: {reached}");
+        assert!((reached.y - delta.y).abs() < 1e-3, "didn't slide: {reached}"); // The blocked x part doesn't hold back y
+    }
+
+    #[test]
+    fn diagonal_movement_into_a_corner_stops_in_it() {
+        let map = map(|x, y| x == WALL || y == WALL);
+        let reached = move_and_collide(Vec2::ZERO, Vec2::splat(6.0 * TILE_SIZE_IN_UNITS), &map, &PLAYER);
+        assert!(reached.x < wall_edge() && reached.y < wall_edge(), "went through a wall: {reached}");
+        assert!(reached.x > TILE_SIZE_IN_UNITS && reached.y > TILE_SIZE_IN_UNITS, "stopped too early: {reached}");
+    }
+}
@@ -0,0 +1,194 @@
+use bevy::{platform::collections::HashMap, prelude::*};
+
+use crate::core::constants::{DEFAULT_CHUNK_DIMENSION_TILES, TILE_SIZE_IN_UNITS};
+
+/// Puts the entity into the `SpatialGrid`, so proximity queries find it.
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct SpatiallyIndexed;
+
+/// Uniform hash grid of the `SpatiallyIndexed` entities, for "what is near here" queries
+/// without going over every entity. Positions are those of the last `update_spatial_grid_system`.
+#[derive(Resource, Debug, Clone)]
+pub struct SpatialGrid {
+    cell_size: f32,
+    cells: HashMap<IVec2, Vec<Entity>>,
+    entities: HashMap<Entity, (IVec2, Vec2)>, // Cell and position of every indexed entity
+}
+
+impl Default for SpatialGrid {
+    fn default() -> Self {
+        Self::new(DEFAULT_CHUNK_DIMENSION_TILES as f32 * TILE_SIZE_IN_UNITS) // One chunk per cell
+    }
+}
+
+impl SpatialGrid {
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size: cell_size.max(f32::EPSILON),
+            cells: HashMap::new(),
+            entities: HashMap::new(),
+        }
+    }
+
+    pub fn cell_size(&self) -> f32 {
+        self.cell_size
+    }
+
+    /// Cell holding `position`. Cells are half-open, a position on a border belongs to the
+    /// cell above and to the right of it.
+    pub fn cell_of(&self, position: Vec2) -> IVec2 {
+        (position / self.cell_size).floor().as_ivec2()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+
+    /// Position `entity` had when it was last indexed.
+    pub fn position(&self, entity: Entity) -> Option<Vec2> {
+        self.entities.get(&entity).map(|(_, position)| *position)
+    }
+
+    /// Indexes `entity` at `position`, moving it to another bucket if its cell changed.
+    pub fn insert(&mut self, entity: Entity, position: Vec2) {
+        let cell = self.cell_of(position);
+        match self.entities.insert(entity, (cell, position)) {
+            Some((old_cell, _)) if old_cell == cell => {}
+            Some((old_cell, _)) => {
+                self.remove_from_cell(old_cell, entity);
+                self.cells.entry(cell).or_default().push(entity);
+            }
+            None => self.cells.entry(cell).or_default().push(entity),
+        }
+    }
+
+    pub fn remove(&mut self, entity: Entity) {
+        if let Some((cell, _)) = self.entities.remove(&entity) {
+            self.remove_from_cell(cell, entity);
+        }
+    }
+
+    fn remove_from_cell(&mut self, cell: IVec2, entity: Entity) {
+        let Some(bucket) = self.cells.get_mut(&cell) else {
+            return;
+        };
+        bucket.retain(|e| *e != entity);
+        if bucket.is_empty() {
+            self.cells.remove(&cell); // Empty buckets would pile up along every path walked
+        }
+    }
+
+    /// Entities inside the rectangle from `min` to `max`, borders included.
+    pub fn query_aabb(&self, min: Vec2, max: Vec2) -> impl Iterator<Item = Entity> + '_ {
+        let (first, last) = (self.cell_of(min), self.cell_of(max));
+        (first.y..=last.y)
+            .flat_map(move |y| (first.x..=last.x).map(move |x| IVec2::new(x, y)))
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .copied()
+            .filter(move |entity| {
+                self.position(*entity)
+                    .is_some_and(|p| p.cmpge(min).all() && p.cmple(max).all())
+            })
+    }
+
+    /// Entities within `radius` of `center`, the border included.
+    pub fn query_radius(&self, center: Vec2, radius: f32) -> impl Iterator<Item = Entity> + '_ {
+        let radius = radius.max(0.0);
+        self.query_aabb(center - Vec2::splat(radius), center + Vec2::splat(radius))
+            .filter(move |entity| {
+                self.position(*entity)
+                    .is_some_and(|p| p.distance_squared(center) <= radius * radius)
+            })
+    }
+}
+
+type MovedIndexedQuery<'w, 's> =
+    Query<'w, 's, (Entity, &'static Transform), (With<SpatiallyIndexed>, Changed<Transform>)>;
+
+/// Indexes the `SpatiallyIndexed` entities whose Transform changed, and drops the ones that
+/// were despawned or lost the marker.
+pub fn update_spatial_grid_system(
+    mut grid: ResMut<SpatialGrid>,
+    moved: MovedIndexedQuery,
+    mut removed: RemovedComponents<SpatiallyIndexed>,
+) {
+    for entity in removed.read() {
+        grid.remove(entity);
+    }
+    for (entity, transform) in moved.iter() {
+        grid.insert(entity, transform.translation.xy());
+    }
+}
+
+/// Keeps the `SpatialGrid` up to date. Runs in `PostUpdate`, after everything that moves
+/// entities this frame, so the next frame's queries see where they ended up.
+pub struct SpatialGridPlugin;
+
+impl Plugin for SpatialGridPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SpatialGrid>()
+            .add_systems(PostUpdate, update_spatial_grid_system);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bucket(grid: &SpatialGrid, x: i32, y: i32) -> Vec<Entity> {
+        grid.cells.get(&IVec2::new(x, y)).cloned().unwrap_or_default()
+    }
+
+    #[test]
+    fn entities_change_buckets_at_cell_borders() {
+        let mut grid = SpatialGrid::new(10.0);
+        let entity = Entity::from_raw(1);
+        grid.insert(entity, Vec2::new(9.99, 0.0));
+        assert_eq!(bucket(&grid, 0, 0), [entity]);
+
+        grid.insert(entity, Vec2::new(10.0, 0.0)); // On the border, the cell to the right
+        assert_eq!(bucket(&grid, 1, 0), [entity]);
+        assert!(!grid.cells.contains_key(&IVec2::ZERO), "the old bucket was left behind");
+
+        grid.insert(entity, Vec2::new(15.0, 5.0)); // Same cell, same bucket
+        assert_eq!(bucket(&grid, 1, 0), [entity]);
+        grid.insert(entity, Vec2::new(-0.01, -10.0));
+        assert_eq!(bucket(&grid, -1, -1), [entity]);
+        assert_eq!((grid.cells.len(), grid.len()), (1, 1));
+
+        // Queries across the border see the latest position only
+        assert_eq!(grid.query_aabb(Vec2::new(-0.01, -10.0), Vec2::ZERO).collect::<Vec<_>>(), [entity]);
+        assert_eq!(grid.query_aabb(Vec2::new(0.0, -10.0), Vec2::new(20.0, 10.0)).count(), 0);
+        assert_eq!(grid.query_radius(Vec2::new(-0.01, -5.0), 5.0).collect::<Vec<_>>(), [entity]);
+        assert_eq!(grid.query_radius(Vec2::new(-0.01, -5.0), 4.9).count(), 0);
+    }
+
+    #[test]
+    fn despawned_and_unmarked_entities_leave_the_grid() {
+        let mut app = App::new();
+        app.add_plugins(SpatialGridPlugin);
+        let kept = app.world_mut().spawn((Transform::from_xyz(5.0, 5.0, 0.0), SpatiallyIndexed)).id();
+        let despawned = app.world_mut().spawn((Transform::from_xyz(6.0, 5.0, 0.0), SpatiallyIndexed)).id();
+        let unmarked = app.world_mut().spawn((Transform::from_xyz(700.0, 5.0, 0.0), SpatiallyIndexed)).id();
+        app.update();
+        assert_eq!(app.world().resource::<SpatialGrid>().len(), 3);
+
+        app.world_mut().despawn(despawned);
+        app.world_mut().entity_mut(unmarked).remove::<SpatiallyIndexed>();
+        app.update();
+        let grid = app.world().resource::<SpatialGrid>();
+        assert_eq!(grid.len(), 1);
+        assert_eq!(grid.query_radius(Vec2::new(5.0, 5.0), 1000.0).collect::<Vec<_>>(), [kept]);
+        assert_eq!(grid.cells.len(), 1, "an emptied bucket is still there");
+
+        // Moving again doesn't bring a removed entity back
+        app.world_mut().entity_mut(unmarked).insert(Transform::from_xyz(5.0, 5.0, 0.0));
+        app.update();
+        assert_eq!(app.world().resource::<SpatialGrid>().position(unmarked), None);
+    }
+}
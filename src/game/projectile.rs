@@ -0,0 +1,190 @@
+use bevy::{color::palettes::css, prelude::*};
+
+use crate::{
+    Pallete,
+    core::{chunks::DataMap, constants::TILE_SIZE_IN_UNITS, units::WorldPos},
+    game::{
+        Player,
+        input::MoveIntent,
+        physix::{Collider, sweep},
+        render::{
+            RenderLayerZ,
+            light_sim::lights::{LightAnimation, LightDefinition, LightEmitter},
+        },
+        spawn::Dormant,
+        world::passability::PassabilityProducer,
+    },
+};
+
+const PROJECTILE_RADIUS: f32 = 1.5;
+
+/// Knobs of the debug bolts the player fires.
+#[derive(Resource, Debug, Clone)]
+pub struct ProjectileSettings {
+    pub fire_key: KeyCode,
+    pub speed: f32,        // Units per second
+    pub max_distance: f32, // Units flown before the bolt fizzles out
+    pub light: Option<LightDefinition>,
+    pub flash: Option<LightDefinition>, // Left at the impact point, fading out
+    pub flash_secs: f32,
+    pub scorch: bool, // Rewrites the hit wall tile unchanged, marking its chunk as written
+}
+
+impl Default for ProjectileSettings {
+    fn default() -> Self {
+        Self {
+            fire_key: KeyCode::KeyF,
+            speed: 400.0,
+            max_distance: 30.0 * TILE_SIZE_IN_UNITS,
+            light: Some(LightDefinition::from(css::ORANGE).scale(0.8)),
+            flash: Some(LightDefinition::from(css::LIGHT_YELLOW).scale(1.5)),
+            flash_secs: 0.3,
+            scorch: false,
+        }
+    }
+}
+
+/// A bolt flying straight along `direction` until it hits a tile it can't pass or has flown
+/// `max_distance`. Its light, if any, moves along as a `LightEmitter`.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Projectile {
+    pub speed: f32,
+    pub max_distance: f32,
+    pub light: Option<LightDefinition>,
+    pub direction: Vec2, // Normalized
+    pub traveled: f32,
+}
+
+/// Light left where a projectile hit, despawned once its fade is over.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct ImpactFlash {
+    pub despawn_at_secs: f32, // Virtual time
+}
+
+/// F fires a glowing bolt in the player's last movement direction.
+pub struct ProjectilePlugin;
+
+impl Plugin for ProjectilePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ProjectileSettings>()
+            .add_systems(Update, (fire_projectile_system, impact_flash_system))
+            .add_systems(FixedUpdate, projectile_movement_system);
+    }
+}
+
+type ShooterQuery<'w, 's> = Query<'w, 's, (&'static Transform, &'static MoveIntent), (With<Player>, Without<Dormant>)>;
+
+/// Spawns a projectile at the player when the fire key is pressed. Standing still fires
+/// where the player last went, right before the first move.
+#[allow(clippy::too_many_arguments)]
+pub fn fire_projectile_system(
+    mut commands: Commands,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    settings: Res<ProjectileSettings>,
+    pallete: Res<Pallete>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut mesh: Local<Option<Handle<Mesh>>>,
+    mut facing: Local<Option<Vec2>>,
+    player_query: ShooterQuery,
+) {
+    let Ok((transform, intent)) = player_query.single() else {
+        return;
+    };
+    if intent.0 != Vec2::ZERO {
+        *facing = Some(intent.0.normalize());
+    }
+    if !keyboard_input.just_pressed(settings.fire_key) {
+        return;
+    }
+    let direction = facing.unwrap_or(Vec2::X);
+    let mesh = mesh.get_or_insert_with(|| meshes.add(Circle::new(PROJECTILE_RADIUS))).clone();
+    let material = pallete.colors.get("projectile").cloned().unwrap_or_default();
+    let mut projectile = commands.spawn((
+        Projectile {
+            speed: settings.speed,
+            max_distance: settings.max_distance,
+            light: settings.light,
+            direction,
+            traveled: 0.0,
+        },
+        Collider {
+            radius: PROJECTILE_RADIUS,
+            ..default()
+        },
+        Transform::from_translation(transform.translation.xy().extend(RenderLayerZ::Projectiles.z())),
+        Mesh2d(mesh),
+        MeshMaterial2d(material),
+    ));
+    if let Some(light) = settings.light {
+        projectile.insert(LightEmitter {
+            color: light,
+            ..default()
+        });
+    }
+}
+
+/// Advances the projectiles, sweeping them against the passability map. A bolt that got
+/// stopped short or flew its full distance is despawned, which also takes its light out of
+/// the lights map. Tiles that aren't loaded stop bolts like walls do.
+pub fn projectile_movement_system(
+    mut commands: Commands,
+    settings: Res<ProjectileSettings>,
+    time: Res<Time>,
+    virtual_time: Res<Time<Virtual>>,
+    mut passability: ResMut<DataMap<PassabilityProducer>>,
+    mut query: Query<(Entity, &mut Transform, &Collider, &mut Projectile)>,
+) {
+    let dt = time.delta_secs();
+    for (entity, mut transform, collider, mut projectile) in query.iter_mut() {
+        let position = transform.translation.xy();
+        let distance = (projectile.speed * dt).min(projectile.max_distance - projectile.traveled);
+        let delta = projectile.direction * distance.max(0.0);
+        let reached = sweep(position, delta, &passability, collider);
+        let moved = reached.distance(position);
+        let hit = moved + 1e-3 < delta.length(); // Stopped short of the full step
+        projectile.traveled += moved;
+        transform.translation = reached.extend(transform.translation.z);
+        if hit {
+            if settings.scorch {
+                let tile = WorldPos(reached + projectile.direction * TILE_SIZE_IN_UNITS / 2.0).to_tile().0;
+                if let Some(value) = passability.read(tile) {
+                    passability.write(tile, value);
+                }
+            }
+            if let Some(flash) = settings.flash {
+                let start_secs = virtual_time.elapsed_secs();
+                commands.spawn((
+                    ImpactFlash {
+                        despawn_at_secs: start_secs + settings.flash_secs,
+                    },
+                    LightEmitter {
+                        color: flash,
+                        animation: LightAnimation::Fade {
+                            start_secs,
+                            duration_secs: settings.flash_secs,
+                        },
+                        ..default()
+                    },
+                    Transform::from_translation(reached.extend(RenderLayerZ::Projectiles.z())),
+                ));
+            }
+        }
+        if hit || projectile.traveled >= projectile.max_distance {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+/// Despawns impact flashes whose light has faded.
+pub fn impact_flash_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    flashes: Query<(Entity, &ImpactFlash)>,
+) {
+    let now = time.elapsed_secs();
+    for (entity, flash) in flashes.iter() {
+        if now >= flash.despawn_at_secs {
+            commands.entity(entity).despawn();
+        }
+    }
+}
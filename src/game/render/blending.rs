@@ -132,3 +132,49 @@ impl Material2d for MultiplyBlendMaterial {
         Ok(())
     }
 }
+
+/// Multiplies the scene with `ambient + light`, the light texture holding linear energy.
+/// Unlike `MultiplyBlendMaterial`, colored lights tint what is underneath instead of only darkening it.
+#[derive(AsBindGroup, Debug, Clone, Asset, TypePath)]
+pub struct TintedLightMaterial {
+    #[texture(0)]
+    #[sampler(1)]
+    pub texture: Handle<Image>,
+    #[uniform(2)]
+    pub ambient: LinearRgba,
+    #[uniform(3)]
+    pub saturation: f32,
+}
+
+impl Material2d for TintedLightMaterial {
+    fn alpha_mode(&self) -> AlphaMode2d {
+        AlphaMode2d::Blend
+    }
+
+    fn fragment_shader() -> ShaderRef {
+        "shaders/tinted_light.wgsl".into()
+    }
+
+    fn specialize(
+        descriptor: &mut RenderPipelineDescriptor,
+        _layout: &MeshVertexBufferLayoutRef,
+        _key: Material2dKey<Self>,
+    ) -> Result<(), SpecializedMeshPipelineError> {
+        let fragment = descriptor.fragment.as_mut();
+        if let Some(target) = fragment.and_then(|fragment| fragment.targets.get_mut(0)).and_then(Option::as_mut) {
+            target.blend = Some(BlendState {
+                color: BlendComponent {
+                    src_factor: BlendFactor::Zero,
+                    dst_factor: BlendFactor::Src,
+                    operation: BlendOperation::Add,
+                },
+                alpha: BlendComponent {
+                    src_factor: BlendFactor::Zero,
+                    dst_factor: BlendFactor::One,
+                    operation: BlendOperation::Add,
+                },
+            });
+        }
+        Ok(())
+    }
+}
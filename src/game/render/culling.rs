@@ -0,0 +1,104 @@
+use bevy::{
+    prelude::*,
+    render::{camera::Projection, view::VisibilitySystems},
+    window::PrimaryWindow,
+};
+
+use crate::game::{
+    camera::{FollowCamera, screen_to_world},
+    render::render_enabled,
+};
+
+/// World size of an entity hidden whenever it's entirely outside the camera view.
+/// The entity's `Visibility` belongs to the culling system, it's never despawned by it.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct CullBounds {
+    pub size: Vec2, // Centered on the entity's translation
+}
+
+impl CullBounds {
+    pub fn square(size: f32) -> Self {
+        Self {
+            size: Vec2::splat(size),
+        }
+    }
+}
+
+/// World rect visible through the follow camera, None until there is a camera and a window.
+#[derive(Resource, Debug, Clone, Copy, Default)]
+pub struct CameraViewRect(pub Option<Rect>);
+
+impl CameraViewRect {
+    /// Whether a `size` rect centered at `center` is at least partly visible. Without a view everything is.
+    pub fn overlaps(&self, center: Vec2, size: Vec2) -> bool {
+        self.0
+            .is_none_or(|view| !view.intersect(Rect::from_center_size(center, size)).is_empty())
+    }
+}
+
+/// Entities with `CullBounds` shown and hidden by the last culling pass.
+#[derive(Resource, Debug, Clone, Copy, Default)]
+pub struct CullingStats {
+    pub visible: usize,
+    pub culled: usize,
+}
+
+pub struct CullingPlugin;
+
+impl Plugin for CullingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CameraViewRect>()
+            .init_resource::<CullingStats>()
+            // After the camera moved in Update, before Bevy propagates visibility
+            .add_systems(
+                PostUpdate,
+                (update_camera_view_rect, cull_system)
+                    .chain()
+                    .before(VisibilitySystems::VisibilityPropagate)
+                    .run_if(render_enabled),
+            );
+    }
+}
+
+/// Window corners in world space, so the projection scale (the zoom) is accounted for.
+pub fn update_camera_view_rect(
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Transform, &Projection), With<FollowCamera>>,
+    mut view: ResMut<CameraViewRect>,
+) {
+    let (Ok(window), Ok((camera_transform, projection))) = (window_query.single(), camera_query.single())
+    else {
+        view.0 = None;
+        return;
+    };
+    let scale = match projection {
+        Projection::Orthographic(ortho) => ortho.scale,
+        _ => 1.0,
+    };
+    let camera = camera_transform.translation.xy();
+    let corner_a = screen_to_world(Vec2::ZERO, window.size(), camera, scale);
+    let corner_b = screen_to_world(window.size(), window.size(), camera, scale);
+    view.0 = Some(Rect::from_corners(corner_a, corner_b));
+}
+
+/// Hides `CullBounds` entities outside the camera view and shows them again once back in it.
+pub fn cull_system(
+    view: Res<CameraViewRect>,
+    mut stats: ResMut<CullingStats>,
+    mut query: Query<(&Transform, &CullBounds, &mut Visibility)>,
+) {
+    let mut counts = CullingStats::default();
+    for (transform, bounds, mut visibility) in query.iter_mut() {
+        let visible = view.overlaps(transform.translation.xy(), bounds.size);
+        let wanted = if visible {
+            counts.visible += 1;
+            Visibility::Inherited
+        } else {
+            counts.culled += 1;
+            Visibility::Hidden
+        };
+        // Only writes on change, so change detection keeps meaning something
+        visibility.set_if_neq(wanted);
+    }
+    *stats = counts;
+}
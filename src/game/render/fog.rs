@@ -0,0 +1,186 @@
+use bevy::{
+    asset::RenderAssetUsages,
+    color::ColorToPacked,
+    image::ImageSampler,
+    prelude::*,
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+    sprite::Material2dPlugin,
+};
+
+use crate::{
+    core::{
+        basics::Point,
+        chunks::DataMap,
+        constants::TILE_SIZE_IN_UNITS,
+        units::{TileCoord, TilesCount, WorldPos},
+    },
+    game::{
+        Player,
+        camera::CameraFocus,
+        render::{
+            blending::MultiplyBlendMaterial,
+            culling::CullBounds,
+            light_sim::simulation::window_center,
+            RenderLayerZ, render_enabled, render_enabled_in,
+        },
+        world::exploration::{ExplorationPlugin, ExplorationProducer, ExplorationSettings, UNEXPLORED},
+    },
+};
+
+
+/// Darkness of the fog of war overlay. Tiles in sight of the player are clear, explored ones
+/// are dimmed (the less explored the darker), never explored ones are close to black.
+#[derive(Resource, Debug, Clone)]
+pub struct FogSettings {
+    pub window_tiles: TilesCount, // Side of the fogged area around the camera
+    pub unexplored_brightness: f32,
+    pub explored_brightness: (f32, f32), // Barely and fully explored tiles out of sight
+}
+
+impl Default for FogSettings {
+    fn default() -> Self {
+        Self {
+            window_tiles: 64,
+            unexplored_brightness: 0.03,
+            explored_brightness: (0.25, 0.45),
+        }
+    }
+}
+
+impl FogSettings {
+    /// Linear brightness of a tile, see `FogSettings`.
+    pub fn brightness(&self, level: u8, visible: bool) -> f32 {
+        if visible {
+            1.0
+        } else if level == UNEXPLORED {
+            self.unexplored_brightness
+        } else {
+            let (min, max) = self.explored_brightness;
+            min + (max - min) * level as f32 / u8::MAX as f32
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct FogOverlay;
+
+#[derive(Resource)]
+pub struct FogOverlayTextureHandle(pub Handle<Image>);
+
+/// Darkens what the player hasn't explored, with an overlay like the light one.
+pub struct FogOfWarPlugin;
+
+impl Plugin for FogOfWarPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<ExplorationPlugin>() {
+            app.add_plugins(ExplorationPlugin);
+        }
+        if render_enabled_in(app) && !app.is_plugin_added::<Material2dPlugin<MultiplyBlendMaterial>>() {
+            app.add_plugins(Material2dPlugin::<MultiplyBlendMaterial>::default());
+        }
+        app.init_resource::<FogSettings>()
+            .add_systems(Startup, setup_fog_overlay.run_if(render_enabled))
+            .add_systems(Update, fog_overlay_system.run_if(render_enabled));
+    }
+}
+
+fn fog_image(tiles: TilesCount) -> Image {
+    let mut image = Image::new_fill(
+        Extent3d {
+            width: tiles as u32,
+            height: tiles as u32,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &[0, 0, 0, 255],
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::RENDER_WORLD | RenderAssetUsages::MAIN_WORLD,
+    );
+    // One texel per tile, smoothed into soft edges
+    image.sampler = ImageSampler::linear();
+    image
+}
+
+fn setup_fog_overlay(
+    mut commands: Commands,
+    settings: Res<FogSettings>,
+    mut images: ResMut<Assets<Image>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<MultiplyBlendMaterial>>,
+) {
+    let tiles = settings.window_tiles.max(1);
+    let size_units = tiles as f32 * TILE_SIZE_IN_UNITS;
+    let handle = images.add(fog_image(tiles));
+    let material = materials.add(MultiplyBlendMaterial { texture: handle.clone() });
+    commands.spawn((
+        FogOverlay,
+        MeshMaterial2d(material),
+        Mesh2d(meshes.add(Rectangle::new(size_units, size_units))),
+        Transform::from_xyz(0.0, 0.0, RenderLayerZ::FogOverlay.z()),
+        CullBounds::square(size_units),
+    ));
+    commands.insert_resource(FogOverlayTextureHandle(handle));
+}
+
+/// Snaps the overlay to the camera like the light overlay, and redraws the window from the
+/// exploration map. Only the tiles under the window are read.
+#[allow(clippy::too_many_arguments)]
+fn fog_overlay_system(
+    settings: Res<FogSettings>,
+    exploration_settings: Res<ExplorationSettings>,
+    exploration: Res<DataMap<ExplorationProducer>>,
+    texture_handle: Option<Res<FogOverlayTextureHandle>>,
+    mut images: ResMut<Assets<Image>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut overlay: Query<(&mut Transform, &mut Mesh2d, &mut CullBounds), With<FogOverlay>>,
+    camera_query: Query<&CameraFocus>,
+    player_query: Query<&Transform, (With<Player>, Without<FogOverlay>)>,
+) {
+    let (Some(texture_handle), Ok(camera)) = (texture_handle, camera_query.single()) else {
+        return;
+    };
+    let Some(image) = images.get_mut(&texture_handle.0) else {
+        return;
+    };
+    let tiles = settings.window_tiles.max(1);
+    let size_units = tiles as f32 * TILE_SIZE_IN_UNITS;
+    if image.width() != tiles as u32 {
+        *image = fog_image(tiles);
+        for (_, mut mesh, mut bounds) in overlay.iter_mut() {
+            mesh.0 = meshes.add(Rectangle::new(size_units, size_units));
+            *bounds = CullBounds::square(size_units);
+        }
+    }
+
+    let center = window_center(WorldPos(camera.position.xy()).to_tile(), tiles);
+    for (mut transform, _, _) in overlay.iter_mut() {
+        transform.translation = center.0.extend(transform.translation.z);
+    }
+    let bottom_left = TileCoord::from_corner(center - Vec2::splat(size_units / 2.0)).0;
+    let player_tile = player_query
+        .single()
+        .ok()
+        .map(|transform| WorldPos(transform.translation.xy()).to_tile().0);
+
+    let Some(pixels) = image.data.as_deref_mut() else {
+        return;
+    };
+    for y in 0..tiles {
+        for x in 0..tiles {
+            let point = bottom_left
+                + Point {
+                    x: x as isize,
+                    y: y as isize,
+                };
+            let level = exploration.read(point).unwrap_or(UNEXPLORED);
+            let visible = player_tile.is_some_and(|player| exploration_settings.in_sight(point - player));
+            let brightness = settings.brightness(level, visible);
+            let texel = Color::linear_rgb(brightness, brightness, brightness)
+                .to_srgba()
+                .to_u8_array();
+            // image rows grow downwards
+            let index = ((tiles - 1 - y) * tiles + x) * 4;
+            pixels[index..index + 4].copy_from_slice(&texel);
+        }
+    }
+}
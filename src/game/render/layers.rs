@@ -0,0 +1,77 @@
+use bevy::{ecs::system::EntityCommands, prelude::*};
+
+/// Z distance between two layers. Entities of one layer may use offsets below it, see `RenderLayerZ::z_with`.
+pub const LAYER_SPACING: f32 = 100.0;
+
+/// Draw order of everything in the world, bottom to top. 2D sprites and meshes sort by z, so
+/// every spawn site takes its z from here instead of a number of its own. The whole stack stays
+/// well inside the 2D camera's depth range around the actors it follows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum RenderLayerZ {
+    Background,    // Terrain hypertiles
+    TerrainDetail, // Hypertile layers drawn over the terrain
+    Props,
+    Actors, // Player, NPCs, ants
+    Projectiles,
+    FogOverlay,   // Darkens what wasn't explored, under the light
+    LightOverlay, // Multiplied over everything below
+    DebugGizmos,
+}
+
+impl RenderLayerZ {
+    pub const ALL: [RenderLayerZ; 8] = [
+        RenderLayerZ::Background,
+        RenderLayerZ::TerrainDetail,
+        RenderLayerZ::Props,
+        RenderLayerZ::Actors,
+        RenderLayerZ::Projectiles,
+        RenderLayerZ::FogOverlay,
+        RenderLayerZ::LightOverlay,
+        RenderLayerZ::DebugGizmos,
+    ];
+
+    pub fn z(self) -> f32 {
+        match self {
+            RenderLayerZ::Background => 0.0,
+            RenderLayerZ::TerrainDetail => LAYER_SPACING,
+            RenderLayerZ::Props => 2.0 * LAYER_SPACING,
+            RenderLayerZ::Actors => 3.0 * LAYER_SPACING,
+            RenderLayerZ::Projectiles => 4.0 * LAYER_SPACING,
+            RenderLayerZ::FogOverlay => 5.0 * LAYER_SPACING,
+            RenderLayerZ::LightOverlay => 6.0 * LAYER_SPACING,
+            RenderLayerZ::DebugGizmos => 7.0 * LAYER_SPACING,
+        }
+    }
+
+    /// Z `offset` above the layer, for ordering within it. Clamped so it never reaches the next layer.
+    pub fn z_with(self, offset: f32) -> f32 {
+        self.z() + offset.clamp(0.0, LAYER_SPACING - 1.0)
+    }
+}
+
+/// Spawns `bundle` at `world_pos` on `layer`, the Transform included.
+pub fn spawn_at_layer<'a>(
+    commands: &'a mut Commands,
+    bundle: impl Bundle,
+    layer: RenderLayerZ,
+    world_pos: Vec2,
+) -> EntityCommands<'a> {
+    commands.spawn((bundle, Transform::from_translation(world_pos.extend(layer.z()))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layers_are_strictly_increasing_and_spaced() {
+        for pair in RenderLayerZ::ALL.windows(2) {
+            let (below, above) = (pair[0], pair[1]);
+            assert!(below < above, "{below:?} listed after {above:?}");
+            assert!(above.z() - below.z() >= LAYER_SPACING, "{below:?} and {above:?} too close");
+            // Offsets within a layer never reach the next one
+            assert!(below.z_with(f32::MAX) < above.z());
+            assert_eq!(below.z_with(-1.0), below.z());
+        }
+    }
+}
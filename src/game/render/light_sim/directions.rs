@@ -1,3 +1,9 @@
+use bevy::math::Vec2;
+
+const TAN_PI_8: f32 = std::f32::consts::SQRT_2 - 1.0; // Slope of the bisector between an axis and a diagonal
+
+/// One of the 8 compass directions. N is +y everywhere: in world coordinates and on the grids
+/// of the light simulation, whose rows go up with the world tiles.
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 #[repr(u8)]
 pub enum Direction {
@@ -23,6 +29,10 @@ impl Direction {
         Direction::NW,
     ];
 
+    pub const ALL_ORTHOGONAL: [Direction; 4] = [Direction::N, Direction::E, Direction::S, Direction::W];
+
+    pub const ALL_DIAGONAL: [Direction; 4] = [Direction::NE, Direction::SE, Direction::SW, Direction::NW];
+
     /// Checks if the direction is orthogonal (North, East, South, West).
     ///
     /// # Returns
@@ -30,7 +40,7 @@ impl Direction {
     ///
     /// # Examples
     /// ```
-    /// use crate::Direction; // Assuming Direction is in the same crate
+    /// use rust_sim::game::render::light_sim::directions::Direction;
     /// assert_eq!(Direction::N.is_orthogonal(), true);
     /// assert_eq!(Direction::NE.is_orthogonal(), false);
     /// ```
@@ -45,7 +55,7 @@ impl Direction {
     ///
     /// # Examples
     /// ```
-    /// use crate::Direction; // Assuming Direction is in the same crate
+    /// use rust_sim::game::render::light_sim::directions::Direction;
     /// assert_eq!(Direction::NE.is_diagonal(), true);
     /// assert_eq!(Direction::E.is_diagonal(), false);
     /// ```
@@ -69,23 +79,23 @@ impl Direction {
     ///
     /// # Examples
     /// ```
-    /// use crate::Direction; // Assuming Direction is in the same crate
+    /// use rust_sim::game::render::light_sim::directions::Direction;
     /// // Orthogonal
-    /// assert_eq!(Direction::N.get_next_from(10, 10), [(10, 9), (10, 9)]);
+    /// assert_eq!(Direction::N.get_next_from(10, 10), [(10, 11), (10, 11)]);
     /// assert_eq!(Direction::E.get_next_from(10, 10), [(11, 10), (11, 10)]);
     ///
     /// // Diagonal
-    /// assert_eq!(Direction::NE.get_next_from(10, 10), [(10, 9), (11, 10)]); // North and East components
-    /// assert_eq!(Direction::SW.get_next_from(10, 10), [(10, 11), (9, 10)]); // South and West components
+    /// assert_eq!(Direction::NE.get_next_from(10, 10), [(10, 11), (11, 10)]); // North and East components
+    /// assert_eq!(Direction::SW.get_next_from(10, 10), [(10, 9), (9, 10)]); // South and West components
     /// ```
     pub fn get_next_from(&self, x: usize, y: usize) -> [(usize, usize); 2] {
         match self {
             Direction::N => {
-                let next_y = y.saturating_sub(1);
+                let next_y = y + 1;
                 [(x, next_y), (x, next_y)]
             }
             Direction::S => {
-                let next_y = y + 1;
+                let next_y = y.saturating_sub(1);
                 [(x, next_y), (x, next_y)]
             }
             Direction::E => {
@@ -97,22 +107,22 @@ impl Direction {
                 [(next_x, y), (next_x, y)]
             }
             Direction::NE => {
-                let north = (x, y.saturating_sub(1));
+                let north = (x, y + 1);
                 let east = (x + 1, y);
                 [north, east]
             }
             Direction::SE => {
-                let south = (x, y + 1);
+                let south = (x, y.saturating_sub(1));
                 let east = (x + 1, y);
                 [south, east]
             }
             Direction::SW => {
-                let south = (x, y + 1);
+                let south = (x, y.saturating_sub(1));
                 let west = (x.saturating_sub(1), y);
                 [south, west]
             }
             Direction::NW => {
-                let north = (x, y.saturating_sub(1));
+                let north = (x, y + 1);
                 let west = (x.saturating_sub(1), y);
                 [north, west]
             }
@@ -131,20 +141,20 @@ impl Direction {
     ///
     /// # Examples
     /// ```
-    /// use crate::Direction; // Assuming Direction is in the same crate
-    /// assert_eq!(Direction::N.get_direct_next_point(10, 10), (10, 9));
-    /// assert_eq!(Direction::NE.get_direct_next_point(10, 10), (11, 9));
+    /// use rust_sim::game::render::light_sim::directions::Direction;
+    /// assert_eq!(Direction::N.get_direct_next_point(10, 10), (10, 11));
+    /// assert_eq!(Direction::NE.get_direct_next_point(10, 10), (11, 11));
     /// ```
     pub fn get_direct_next_point(&self, x: usize, y: usize) -> (usize, usize) {
         match self {
-            Direction::N => (x, y.saturating_sub(1)),
-            Direction::NE => (x + 1, y.saturating_sub(1)),
+            Direction::N => (x, y + 1),
+            Direction::NE => (x + 1, y + 1),
             Direction::E => (x + 1, y),
-            Direction::SE => (x + 1, y + 1),
-            Direction::S => (x, y + 1),
-            Direction::SW => (x.saturating_sub(1), y + 1),
+            Direction::SE => (x + 1, y.saturating_sub(1)),
+            Direction::S => (x, y.saturating_sub(1)),
+            Direction::SW => (x.saturating_sub(1), y.saturating_sub(1)),
             Direction::W => (x.saturating_sub(1), y),
-            Direction::NW => (x.saturating_sub(1), y.saturating_sub(1)),
+            Direction::NW => (x.saturating_sub(1), y + 1),
         }
     }
 
@@ -157,7 +167,7 @@ impl Direction {
     ///
     /// # Examples
     /// ```
-    /// use crate::Direction; // Assuming Direction is in the same crate
+    /// use rust_sim::game::render::light_sim::directions::Direction;
     /// assert_eq!(Direction::N.orthogonal_components(), (Some(Direction::N), None));
     /// assert_eq!(Direction::NE.orthogonal_components(), (Some(Direction::N), Some(Direction::E)));
     /// ```
@@ -175,6 +185,152 @@ impl Direction {
     }
 }
 
+impl Direction {
+    /// Returns this direction and its neighbors up to `spread` 45° steps away on each side,
+    /// together with the number of steps. Each direction is listed only once.
+    ///
+    /// # Examples
+    /// ```
+    /// use rust_sim::game::render::light_sim::directions::Direction;
+    /// assert_eq!(Direction::N.neighbors_within(0), vec![(Direction::N, 0)]);
+    /// assert_eq!(
+    ///     Direction::N.neighbors_within(1),
+    ///     vec![(Direction::N, 0), (Direction::NW, 1), (Direction::NE, 1)]
+    /// );
+    /// ```
+    pub fn neighbors_within(&self, spread: u8) -> Vec<(Direction, u8)> {
+        let spread = spread.min(4);
+        let mut result = vec![(*self, 0)];
+        for step in 1..=spread {
+            let ccw = Direction::try_from((*self as usize + 8 - step as usize) % 8).unwrap();
+            let cw = Direction::try_from((*self as usize + step as usize) % 8).unwrap();
+            result.push((ccw, step));
+            if cw != ccw {
+                result.push((cw, step)); // opposite direction is reached from both sides at spread 4
+            }
+        }
+        result
+    }
+
+    /// Returns the direction pointing the other way (rotated by 180°).
+    ///
+    /// # Examples
+    /// ```
+    /// use rust_sim::game::render::light_sim::directions::Direction;
+    /// assert_eq!(Direction::N.opposite(), Direction::S);
+    /// assert_eq!(Direction::NE.opposite(), Direction::SW);
+    /// ```
+    pub fn opposite(&self) -> Direction {
+        Direction::try_from((*self as usize + 4) % 8).unwrap()
+    }
+
+    /// Returns the next direction clockwise (rotated by 45°).
+    ///
+    /// # Examples
+    /// ```
+    /// use rust_sim::game::render::light_sim::directions::Direction;
+    /// assert_eq!(Direction::N.rotate_cw(), Direction::NE);
+    /// assert_eq!(Direction::NW.rotate_cw(), Direction::N);
+    /// ```
+    pub fn rotate_cw(&self) -> Direction {
+        Direction::try_from((*self as usize + 1) % 8).unwrap()
+    }
+
+    /// Returns the next direction counter-clockwise (rotated by -45°).
+    ///
+    /// # Examples
+    /// ```
+    /// use rust_sim::game::render::light_sim::directions::Direction;
+    /// assert_eq!(Direction::N.rotate_ccw(), Direction::NW);
+    /// assert_eq!(Direction::NE.rotate_ccw(), Direction::N);
+    /// ```
+    pub fn rotate_ccw(&self) -> Direction {
+        Direction::try_from((*self as usize + 7) % 8).unwrap()
+    }
+
+    /// Returns the direction as a world space unit vector, +y being north.
+    ///
+    /// # Examples
+    /// ```
+    /// use bevy::math::Vec2;
+    /// use rust_sim::game::render::light_sim::directions::Direction;
+    /// assert_eq!(Direction::N.to_unit_vec(), Vec2::Y);
+    /// assert!(Direction::SW.to_unit_vec().abs_diff_eq(Vec2::new(-1.0, -1.0).normalize(), 1e-6));
+    /// ```
+    pub fn to_unit_vec(&self) -> Vec2 {
+        let d = std::f32::consts::FRAC_1_SQRT_2;
+        match self {
+            Direction::N => Vec2::new(0.0, 1.0),
+            Direction::NE => Vec2::new(d, d),
+            Direction::E => Vec2::new(1.0, 0.0),
+            Direction::SE => Vec2::new(d, -d),
+            Direction::S => Vec2::new(0.0, -1.0),
+            Direction::SW => Vec2::new(-d, -d),
+            Direction::W => Vec2::new(-1.0, 0.0),
+            Direction::NW => Vec2::new(-d, d),
+        }
+    }
+
+    /// Returns the nearest of the 8 directions to a world space vector, `None` for a zero or
+    /// non-finite one. A vector exactly between two directions goes to the orthogonal one.
+    ///
+    /// # Examples
+    /// ```
+    /// use bevy::math::Vec2;
+    /// use rust_sim::game::render::light_sim::directions::Direction;
+    /// assert_eq!(Direction::from_vec(Vec2::new(3.0, 0.5)), Some(Direction::E));
+    /// assert_eq!(Direction::from_vec(Vec2::new(-2.0, 2.0)), Some(Direction::NW));
+    /// assert_eq!(Direction::from_vec(Vec2::ZERO), None);
+    /// ```
+    pub fn from_vec(v: Vec2) -> Option<Direction> {
+        if v == Vec2::ZERO || !v.is_finite() {
+            return None;
+        }
+        let (ax, ay) = (v.x.abs(), v.y.abs());
+        // Strictly past the bisector only, so the ties stay orthogonal
+        let diagonal = ax.min(ay) > ax.max(ay) * TAN_PI_8;
+        let direction = match (diagonal, v.x > 0.0, v.y > 0.0) {
+            (true, true, true) => Direction::NE,
+            (true, true, false) => Direction::SE,
+            (true, false, false) => Direction::SW,
+            (true, false, true) => Direction::NW,
+            (false, east, north) => {
+                if ax > ay {
+                    if east { Direction::E } else { Direction::W }
+                } else if north {
+                    Direction::N
+                } else {
+                    Direction::S
+                }
+            }
+        };
+        Some(direction)
+    }
+
+    /// Returns the angle of `to_unit_vec` counter-clockwise from east, in (-π, π] like `Vec2::to_angle`.
+    ///
+    /// # Examples
+    /// ```
+    /// use rust_sim::game::render::light_sim::directions::Direction;
+    /// assert_eq!(Direction::E.angle_radians(), 0.0);
+    /// assert_eq!(Direction::N.angle_radians(), std::f32::consts::FRAC_PI_2);
+    /// assert_eq!(Direction::W.angle_radians(), std::f32::consts::PI);
+    /// ```
+    pub fn angle_radians(&self) -> f32 {
+        use std::f32::consts::{FRAC_PI_2, FRAC_PI_4, PI};
+        match self {
+            Direction::E => 0.0,
+            Direction::NE => FRAC_PI_4,
+            Direction::N => FRAC_PI_2,
+            Direction::NW => 3.0 * FRAC_PI_4,
+            Direction::W => PI,
+            Direction::SW => -3.0 * FRAC_PI_4,
+            Direction::S => -FRAC_PI_2,
+            Direction::SE => -FRAC_PI_4,
+        }
+    }
+}
+
 impl From<Direction> for usize {
     fn from(dir: Direction) -> usize {
         dir as usize
@@ -198,3 +354,91 @@ impl TryFrom<usize> for Direction {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn opposite_is_half_a_turn() {
+        for d in Direction::ALL {
+            assert_eq!(d.opposite().opposite(), d);
+            assert_ne!(d.opposite(), d);
+            assert_eq!(d.opposite().to_unit_vec(), -d.to_unit_vec());
+        }
+        assert_eq!(Direction::W.opposite(), Direction::E);
+        assert_eq!(Direction::SE.opposite(), Direction::NW);
+    }
+
+    #[test]
+    fn rotations_wrap_around() {
+        assert_eq!(Direction::NW.rotate_cw(), Direction::N);
+        assert_eq!(Direction::N.rotate_ccw(), Direction::NW);
+        for d in Direction::ALL {
+            assert_eq!(d.rotate_cw().rotate_ccw(), d);
+            let mut turned = d;
+            for _ in 0..8 {
+                turned = turned.rotate_cw();
+            }
+            assert_eq!(turned, d);
+            assert_eq!(d.rotate_cw().rotate_cw().rotate_cw().rotate_cw(), d.opposite());
+        }
+    }
+
+    #[test]
+    fn orthogonal_and_diagonal_split_all_directions() {
+        assert!(Direction::ALL_ORTHOGONAL.iter().all(Direction::is_orthogonal));
+        assert!(Direction::ALL_DIAGONAL.iter().all(Direction::is_diagonal));
+        for d in Direction::ALL {
+            assert_ne!(Direction::ALL_ORTHOGONAL.contains(&d), Direction::ALL_DIAGONAL.contains(&d));
+        }
+    }
+
+    #[test]
+    fn vectors_and_angles_agree() {
+        for d in Direction::ALL {
+            let v = d.to_unit_vec();
+            assert!((v.length() - 1.0).abs() < 1e-6, "{d:?}");
+            assert_eq!(Direction::from_vec(v), Some(d));
+            assert_eq!(Direction::from_vec(v * 50.0), Some(d));
+            assert!((v.to_angle() - d.angle_radians()).abs() < 1e-6, "{d:?}");
+            // 45° steps, counter-clockwise being the other way
+            let cw = d.rotate_cw().angle_radians() - d.angle_radians();
+            assert!((cw.rem_euclid(std::f32::consts::TAU) - 7.0 * std::f32::consts::FRAC_PI_4).abs() < 1e-5, "{d:?}");
+        }
+    }
+
+    #[test]
+    fn grid_steps_go_where_the_vectors_point() {
+        for d in Direction::ALL {
+            let (x, y) = d.get_direct_next_point(10, 10);
+            let step = Vec2::new(x as f32 - 10.0, y as f32 - 10.0);
+            assert_eq!(Direction::from_vec(step), Some(d), "{d:?}");
+            for (x, y) in d.get_next_from(10, 10) {
+                let step = Vec2::new(x as f32 - 10.0, y as f32 - 10.0);
+                assert!(step.dot(d.to_unit_vec()) > 0.0, "{d:?} steps to {x}, {y}");
+            }
+        }
+    }
+
+    #[test]
+    fn from_vec_picks_the_nearest_and_breaks_ties_toward_orthogonal() {
+        assert_eq!(Direction::from_vec(Vec2::new(1.0, 0.3)), Some(Direction::E));
+        assert_eq!(Direction::from_vec(Vec2::new(1.0, 0.5)), Some(Direction::NE));
+        assert_eq!(Direction::from_vec(Vec2::new(-0.2, -1.0)), Some(Direction::S));
+        // Exactly between E and NE, and every other such bisector by symmetry
+        for d in Direction::ALL_ORTHOGONAL {
+            let along = d.to_unit_vec();
+            let side = d.rotate_cw().rotate_cw().to_unit_vec();
+            for sign in [1.0, -1.0] {
+                let between = along + side * sign * TAN_PI_8;
+                assert_eq!(Direction::from_vec(between), Some(d), "{d:?} {sign}");
+                let past = along + side * sign * (TAN_PI_8 + 1e-3);
+                assert!(Direction::from_vec(past).unwrap().is_diagonal(), "{d:?} {sign}");
+            }
+        }
+        assert_eq!(Direction::from_vec(Vec2::ZERO), None);
+        assert_eq!(Direction::from_vec(Vec2::new(f32::NAN, 1.0)), None);
+        assert_eq!(Direction::from_vec(Vec2::new(f32::INFINITY, 0.0)), None);
+    }
+}
@@ -0,0 +1,172 @@
+use bevy::{
+    color::{Srgba, palettes::css},
+    ecs::{
+        event::EventReader,
+        resource::Resource,
+        system::{Res, ResMut},
+    },
+    platform::collections::HashSet,
+};
+
+use crate::{
+    core::{
+        basics::Point,
+        chunks::{ChunkCoords, ChunkLoaded, ChunkUnloaded, ChunkWritten, DataMap},
+        units::TilesCount,
+    },
+    game::{
+        render::light_sim::{
+            lights::{LightDefinition, UndirectedLightEmitter},
+            lights_map::LightsMapProducer,
+        },
+        world::passability::{Passability, PassabilityProducer},
+    },
+};
+
+const NEIGHBORS: [(isize, isize); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+
+/// Glow of the wall surface: impassable tiles next to walkable ones emit light, derived from
+/// the passability map instead of placed by hand.
+#[derive(Resource, Debug, Clone)]
+pub struct LavaBorderSettings {
+    pub enabled: bool, // Applies to the chunks evaluated from then on
+    pub color: Srgba,
+    pub intensity: f32,
+    pub max_wall_level: u8, // Tiles at or below this passability can glow
+}
+
+impl Default for LavaBorderSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            color: css::ORANGE_RED,
+            intensity: 0.8,
+            max_wall_level: Passability::IMPASSABLE.0,
+        }
+    }
+}
+
+impl LavaBorderSettings {
+    fn light(&self) -> LightDefinition {
+        LightDefinition::from(self.color).scale(self.intensity)
+    }
+}
+
+/// Tiles currently lit by `lava_border_system`, so only its own emitters get removed.
+#[derive(Resource, Debug, Default)]
+pub struct LavaBorderState {
+    lit: HashSet<Point>,
+}
+
+/// Whether `tile` is a wall surface. Neighbors in chunks that aren't loaded don't count.
+fn is_wall_surface(passability: &DataMap<PassabilityProducer>, settings: &LavaBorderSettings, tile: Point) -> bool {
+    let Some(level) = passability.read(tile) else {
+        return false;
+    };
+    level.0 <= settings.max_wall_level
+        && NEIGHBORS.iter().any(|(dx, dy)| {
+            passability
+                .read(Point { x: tile.x + dx, y: tile.y + dy })
+                .is_some_and(|neighbor| !neighbor.is_wall())
+        })
+}
+
+/// Adds or removes the glow of the tiles in the `width` x `height` rectangle at `bottom_left`.
+fn update_rect(
+    passability: &DataMap<PassabilityProducer>,
+    lights: &mut DataMap<LightsMapProducer>,
+    settings: &LavaBorderSettings,
+    state: &mut LavaBorderState,
+    bottom_left: Point,
+    (width, height): (TilesCount, TilesCount),
+) {
+    let light = settings.light();
+    for dy in 0..height as isize {
+        for dx in 0..width as isize {
+            let tile = Point {
+                x: bottom_left.x + dx,
+                y: bottom_left.y + dy,
+            };
+            let surface = settings.enabled && is_wall_surface(passability, settings, tile);
+            let lit = state.lit.contains(&tile);
+            if surface == lit {
+                continue;
+            }
+            let Some(mut cell) = lights.read(tile) else {
+                continue; // Done when its lights chunk loads, rather than queued until then
+            };
+            if surface {
+                if cell.undirected_lights.is_some() {
+                    continue; // Someone else's light, left alone
+                }
+                cell.undirected_lights = Some(UndirectedLightEmitter { props: light });
+                state.lit.insert(tile);
+            } else {
+                cell.undirected_lights = None;
+                state.lit.remove(&tile);
+            }
+            lights.write(tile, cell);
+        }
+    }
+}
+
+/// Re-evaluates the wall surface of every passability chunk that loaded or was written, and
+/// the facing edge of its loaded neighbors, whose surface depends on it. Lights chunks that
+/// load again get their glow back, the regenerated chunk doesn't have it.
+#[allow(clippy::too_many_arguments)]
+pub fn lava_border_system(
+    settings: Res<LavaBorderSettings>,
+    mut state: ResMut<LavaBorderState>,
+    mut passability_loaded: EventReader<ChunkLoaded<PassabilityProducer>>,
+    mut passability_written: EventReader<ChunkWritten<PassabilityProducer>>,
+    mut lights_loaded: EventReader<ChunkLoaded<LightsMapProducer>>,
+    mut lights_unloaded: EventReader<ChunkUnloaded<LightsMapProducer>>,
+    passability: Res<DataMap<PassabilityProducer>>,
+    mut lights: ResMut<DataMap<LightsMapProducer>>,
+) {
+    let state = &mut *state;
+    let lights_dimension = lights.chunk_dimension_tiles as isize;
+    for event in lights_unloaded.read() {
+        let origin = event.coords.to_bottom_left_tile_point(lights.chunk_dimension_tiles);
+        state.lit.retain(|tile| {
+            tile.x < origin.x
+                || tile.y < origin.y
+                || tile.x >= origin.x + lights_dimension
+                || tile.y >= origin.y + lights_dimension
+        });
+    }
+    for event in lights_loaded.read() {
+        let dimension = lights.chunk_dimension_tiles;
+        let origin = event.coords.to_bottom_left_tile_point(dimension);
+        update_rect(&passability, &mut lights, &settings, state, origin, (dimension, dimension));
+    }
+
+    let dimension = passability.chunk_dimension_tiles;
+    let changed = passability_loaded
+        .read()
+        .map(|e| e.coords)
+        .chain(passability_written.read().map(|e| e.coords));
+    for coords in changed {
+        let origin = coords.to_bottom_left_tile_point(dimension);
+        update_rect(&passability, &mut lights, &settings, state, origin, (dimension, dimension));
+        // The neighbor's row or column touching this chunk
+        for (dx, dy) in NEIGHBORS {
+            let neighbor = ChunkCoords {
+                x: coords.x + dx,
+                y: coords.y + dy,
+            };
+            if !passability.loaded_chunks.contains_key(&neighbor) {
+                continue;
+            }
+            let neighbor_origin = neighbor.to_bottom_left_tile_point(dimension);
+            let last = dimension as isize - 1;
+            let (edge, size) = match (dx, dy) {
+                (1, _) => (neighbor_origin, (1, dimension)),
+                (-1, _) => (Point { x: neighbor_origin.x + last, ..neighbor_origin }, (1, dimension)),
+                (_, 1) => (neighbor_origin, (dimension, 1)),
+                _ => (Point { y: neighbor_origin.y + last, ..neighbor_origin }, (dimension, 1)),
+            };
+            update_rect(&passability, &mut lights, &settings, state, edge, size);
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use bevy::{
     asset::RenderAssetUsages,
     color::palettes::css,
+    image::ImageSampler,
     prelude::*,
     render::render_resource::{Extent3d, TextureDimension, TextureFormat, TextureUsages},
     sprite::{Material2d, Material2dPlugin},
@@ -9,58 +10,415 @@ use bevy::{
 use crate::{
     core::{
         basics::Point,
-        chunks::insert_chunked_plugin,
-        constants::TILE_SIZE_IN_UNITS_UNITS,
-        units::{TilesCount, Units},
-    }, game::render::{
-        blending::{AdditiveMaterial, MultiplyBlendMaterial, ScreenBlendMaterial},
-        light_sim::{lights_map::LightsMapProducer, pbr_cell::PbrCellProducer, simulation},
-    }, FollowCamera
+        chunks::{ChunkLoaded, ChunkedMapConfig, DataMap, FocusPolicy, UnloadPolicy},
+        constants::TILE_SIZE_IN_UNITS,
+        units::{TilesCount, WorldPos},
+        validation::{ConfigError, check_even, check_nonzero},
+    }, game::{Player, required::require_single, camera::{CameraFocus, FollowCamera}, debug::insert_chunked_plugin_with_debug, physix::Velocity, render::{
+        RenderLayerZ, render_enabled, render_enabled_in,
+        culling::{CullBounds, cull_system},
+        lit_sprite::lit_sprite_system,
+        blending::{AdditiveMaterial, MultiplyBlendMaterial, ScreenBlendMaterial, TintedLightMaterial},
+        light_sim::{
+            lights::{
+                DirectedLightEmitter, LightAnimation, LightEmitter, LightModulation, LightTag,
+                UndirectedLightEmitter,
+            },
+            lights_map::{LightEmitterCell, LightsMapProducer},
+            lava_border::{LavaBorderSettings, LavaBorderState, lava_border_system},
+            pbr_cell::{PbrCellProducer, sync_pbr_from_passability_system},
+            simulation,
+        },
+    }},
 };
+use bevy::platform::collections::HashMap;
 
-pub struct Lighting;
-
+/// Default side of the lit window, see `LightSimSettings::overlay_tiles`.
 pub const LIGHTING_OVERLAY_TILES: TilesCount = 32;
-pub const OVERLAY_IMAGE_SIZE_SCALED: Units = LIGHTING_OVERLAY_TILES as isize * TILE_SIZE_IN_UNITS_UNITS;
+/// Default tiles simulated around the visible overlay on each side, see `LightSimSettings::apron_tiles`.
+pub const LIGHTING_APRON_TILES: TilesCount = 2;
 
 #[derive(Component)]
 pub struct OverlayImage(Handle<Image>);
 
-impl Plugin for Lighting {
+/// What the light overlay shows. Chosen once, when `LightingPlugin` is built.
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub enum LightingConfig {
+    StaticOverlay {
+        size_units: f32, // Side of the overlay in world units
+        color: Color,    // The whole overlay, multiplied over the scene
+    },
+    Simulated {
+        tiles: TilesCount,       // Initial `LightSimSettings::overlay_tiles`
+        steps: usize,            // Initial `LightSimSettings::steps`
+        pixels_per_tile: u32,    // Initial `LightSimSettings::pixels_per_tile`
+        apron_tiles: TilesCount, // Initial `LightSimSettings::apron_tiles`
+    },
+}
+
+impl Default for LightingConfig {
+    fn default() -> Self {
+        LightingConfig::Simulated {
+            tiles: LIGHTING_OVERLAY_TILES,
+            steps: 10,
+            pixels_per_tile: 1,
+            apron_tiles: LIGHTING_APRON_TILES,
+        }
+    }
+}
+
+impl LightingConfig {
+    pub fn is_simulated(&self) -> bool {
+        matches!(self, LightingConfig::Simulated { .. })
+    }
+
+    /// The overlay has a size, and a simulated one an even number of tiles so it stays centered on tile borders.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        match *self {
+            LightingConfig::StaticOverlay { size_units, .. } if size_units > 0.0 => Ok(()),
+            LightingConfig::StaticOverlay { .. } => Err(ConfigError::ZeroDimension {
+                what: "static overlay size_units",
+            }),
+            LightingConfig::Simulated {
+                tiles, pixels_per_tile, ..
+            } => {
+                check_even("light overlay", tiles)?;
+                check_nonzero("light overlay pixels_per_tile", pixels_per_tile as TilesCount)
+            }
+        }
+    }
+}
+
+/// The light maps, emitters and day cycle, and an overlay following the player that is either
+/// simulated from them or a fixed color, see `LightingConfig`. The static overlay keeps the maps,
+/// other plugins write into them.
+#[derive(Default)]
+pub struct LightingPlugin {
+    pub config: LightingConfig,
+}
+
+/// Adds `Material2dPlugin::<M>` unless another plugin already did, adding it twice panics.
+fn add_material_plugin<M: Material2d>(app: &mut App)
+where
+    M::Data: PartialEq + Eq + std::hash::Hash + Clone,
+{
+    if !app.is_plugin_added::<Material2dPlugin<M>>() {
+        app.add_plugins(Material2dPlugin::<M>::default());
+    }
+}
+
+impl Plugin for LightingPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins((
-            Material2dPlugin::<AdditiveMaterial>::default(),
-            Material2dPlugin::<ScreenBlendMaterial>::default(),
-            Material2dPlugin::<MultiplyBlendMaterial>::default(),
-        ));
+        if let Err(err) = self.config.validate() {
+            panic!("LightingPlugin: {err}");
+        }
+        // Headless apps keep the simulated maps and the day cycle, without the overlay
+        if render_enabled_in(app) {
+            add_material_plugin::<AdditiveMaterial>(app);
+            add_material_plugin::<ScreenBlendMaterial>(app);
+            add_material_plugin::<MultiplyBlendMaterial>(app);
+            add_material_plugin::<TintedLightMaterial>(app);
+        }
         // Register systems, resources, events, etc.
-        app.add_systems(Update, overlay_texture_follow_camera);
+        app.insert_resource(self.config);
+        app.init_resource::<simulation::LightFieldSnapshot>();
+        app.init_resource::<GlobalAmbientLight>();
+        app.init_resource::<DayNightCycle>();
+        app.init_resource::<LightingMode>();
+        app.init_resource::<LightOverlayFollow>();
+        app.init_resource::<LightModulation>();
+        app.add_systems(
+            Update,
+            (
+                overlay_texture_follow_system.run_if(render_enabled),
+                (animate_light_emitters, sync_light_emitters).chain(),
+                day_night_cycle_system,
+                (apply_lighting_mode, sync_tinted_light_material)
+                    .chain()
+                    .run_if(render_enabled),
+            ),
+        );
         setup_directional_lights(app);
-        app.add_systems(Startup, setup_overlay);
+        if let LightingConfig::Simulated {
+            tiles,
+            steps,
+            pixels_per_tile,
+            apron_tiles,
+        } = self.config
+        {
+            app.insert_resource(simulation::LightSimSettings {
+                overlay_tiles: tiles,
+                steps,
+                pixels_per_tile,
+                apron_tiles,
+                ..default()
+            });
+            app.init_resource::<simulation::LightSimState>();
+            app.init_resource::<simulation::LightSimStats>();
+            setup_light_simulation(app);
+        }
+        app.add_systems(Startup, setup_overlay.run_if(render_enabled));
+        if render_enabled_in(app) {
+            require_single::<OverlayImage>(app, "light overlay", "It is spawned by LightingPlugin's setup_overlay.");
+        }
     }
 }
 
+/// Side of the window the light maps are loaded in, see `FocusPolicy::FollowCameraWindow`. The
+/// simulated overlay and its apron fit with room for resizing and for the overlay leading the camera.
+pub const LIGHT_MAPS_WINDOW_TILES: TilesCount = LIGHTING_OVERLAY_TILES * 2;
+
 fn setup_directional_lights(app: &mut App) {
-    insert_chunked_plugin(app, LightsMapProducer::default(), 100);
-    insert_chunked_plugin(app, PbrCellProducer::default(), 100);
-    app.add_systems(PostUpdate, simulation::run_lights_simulation);
+    let config = ChunkedMapConfig {
+        focus: FocusPolicy::FollowCameraWindow {
+            tiles: LIGHT_MAPS_WINDOW_TILES,
+        },
+        init_radius_tiles: 0, // The window loads what's needed once there is a camera
+        unload: UnloadPolicy::Immediate,
+        ..Default::default()
+    };
+    insert_chunked_plugin_with_debug(app, LightsMapProducer, config);
+    insert_chunked_plugin_with_debug(app, PbrCellProducer, config);
+    app.add_systems(Update, sync_pbr_from_passability_system);
+    // The wall surface glows, see `LavaBorderSettings`
+    app.init_resource::<LavaBorderSettings>();
+    app.init_resource::<LavaBorderState>();
+    app.add_systems(Update, lava_border_system);
+}
+
+fn setup_light_simulation(app: &mut App) {
+    app.add_systems(
+        Update,
+        resize_overlay_system
+            .before(overlay_texture_follow_system)
+            .run_if(render_enabled),
+    );
+    app.add_systems(
+        PostUpdate,
+        (
+            simulation::run_lights_simulation.after(cull_system),
+            swap_overlay_textures_system,
+            lit_sprite_system,
+        )
+            .chain()
+            .run_if(render_enabled),
+    );
+}
+
+/// Light every tile gets before emitters, added to the simulated light.
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct GlobalAmbientLight {
+    pub color: Color,
+    pub intensity: f32,
+}
+
+impl Default for GlobalAmbientLight {
+    fn default() -> Self {
+        Self {
+            color: Color::WHITE,
+            intensity: 0.3,
+        }
+    }
+}
+
+impl GlobalAmbientLight {
+    /// Ambient energy per channel, in the same units as the simulation buffers.
+    pub fn energy(&self) -> Vec3 {
+        Vec3::from_array(self.color.to_srgba().to_f32_array_no_alpha()) * self.intensity
+    }
+}
+
+/// Shape of the ambient brightness over one day.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DayNightCurve {
+    #[default]
+    Sine,     // Smooth, lingers around noon and midnight
+    Triangle, // Constant rate from midnight to noon and back
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DayPhase {
+    Night,
+    Dawn,
+    Day,
+    Dusk,
+}
+
+/// Animates `GlobalAmbientLight` over time. Time of day runs from 0.0 (midnight) through 0.5 (noon) to 1.0.
+#[derive(Resource, Debug, Clone)]
+pub struct DayNightCycle {
+    pub period_secs: f32, // Length of a full day
+    pub curve: DayNightCurve,
+    pub paused: bool,
+    pub day_color: Color,
+    pub night_color: Color,
+    pub day_intensity: f32,
+    pub night_intensity: f32,
+    time_of_day: f32,
+}
+
+impl Default for DayNightCycle {
+    fn default() -> Self {
+        Self {
+            period_secs: 120.0,
+            curve: DayNightCurve::Sine,
+            paused: false,
+            day_color: Color::srgb(1.0, 0.95, 0.85),
+            night_color: Color::srgb(0.35, 0.4, 0.8),
+            day_intensity: 0.8,
+            night_intensity: 0.1,
+            time_of_day: 0.3,
+        }
+    }
+}
+
+impl DayNightCycle {
+    pub fn time_of_day(&self) -> f32 {
+        self.time_of_day
+    }
+
+    /// Jumps to a time of day, wrapped into 0.0..1.0.
+    pub fn set_time_of_day(&mut self, time_of_day: f32) {
+        self.time_of_day = time_of_day.rem_euclid(1.0);
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    /// 0.0 at midnight, 1.0 at noon.
+    pub fn brightness(&self) -> f32 {
+        match self.curve {
+            DayNightCurve::Sine => 0.5 - 0.5 * (self.time_of_day * std::f32::consts::TAU).cos(),
+            DayNightCurve::Triangle => 1.0 - (2.0 * self.time_of_day - 1.0).abs(),
+        }
+    }
+
+    pub fn phase(&self) -> DayPhase {
+        match self.time_of_day {
+            t if t < 0.2 => DayPhase::Night,
+            t if t < 0.3 => DayPhase::Dawn,
+            t if t < 0.7 => DayPhase::Day,
+            t if t < 0.8 => DayPhase::Dusk,
+            _ => DayPhase::Night,
+        }
+    }
+
+    /// Ambient light for the current time of day.
+    pub fn ambient(&self) -> GlobalAmbientLight {
+        let brightness = self.brightness();
+        let night = Vec3::from_array(self.night_color.to_srgba().to_f32_array_no_alpha());
+        let day = Vec3::from_array(self.day_color.to_srgba().to_f32_array_no_alpha());
+        let color = night.lerp(day, brightness);
+        GlobalAmbientLight {
+            color: Color::srgb(color.x, color.y, color.z),
+            intensity: self.night_intensity + (self.day_intensity - self.night_intensity) * brightness,
+        }
+    }
+}
+
+/// Advances the day/night cycle and writes its ambient light. Without a `DayNightCycle`
+/// resource the ambient light is left as set.
+pub fn day_night_cycle_system(
+    time: Res<Time>,
+    cycle: Option<ResMut<DayNightCycle>>,
+    mut ambient: ResMut<GlobalAmbientLight>,
+) {
+    let Some(mut cycle) = cycle else {
+        return;
+    };
+    if !cycle.paused && cycle.period_secs > 0.0 {
+        let time_of_day = cycle.time_of_day + time.delta_secs() / cycle.period_secs;
+        cycle.set_time_of_day(time_of_day);
+    }
+    *ambient = cycle.ambient();
+}
+
+/// World size of an overlay `tiles` wide.
+fn overlay_size_units(tiles: TilesCount) -> f32 {
+    tiles as f32 * TILE_SIZE_IN_UNITS
+}
+
+/// What the lit window is centered on. The camera may trail a fast player (see
+/// `FollowCamera::smoothing`), so the default leans toward where the player is heading.
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub enum LightOverlayFollow {
+    Camera,
+    Player,
+    Lead {
+        player_weight: f32,  // 0.0 is the camera, 1.0 the player's projected position
+        lookahead_secs: f32, // The player's position is projected this far along its velocity
+    },
 }
 
+impl Default for LightOverlayFollow {
+    fn default() -> Self {
+        LightOverlayFollow::Lead {
+            player_weight: 0.75,
+            lookahead_secs: 0.15,
+        }
+    }
+}
+
+/// The two overlay images. The materials show `front`, the simulation only writes `back`, and
+/// `swap_overlay_textures_system` exchanges them once it's complete, so no partial write is ever shown.
 #[derive(Resource)]
-pub struct LightOverlayTextureHandle(pub Handle<Image>);
+pub struct LightOverlayTextureHandle {
+    pub front: Handle<Image>,
+    pub back: Handle<Image>,
+}
 
 #[derive(Resource)]
 pub struct LightOverlayMaterialHandle(pub Handle<MultiplyBlendMaterial>);
 
+#[derive(Resource)]
+pub struct LightOverlayTintedMaterialHandle(pub Handle<TintedLightMaterial>);
+
+/// How the light overlay is composed with the scene underneath. Can be switched at runtime.
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq)]
+pub enum LightingMode {
+    #[default]
+    MultiplyOverlay, // Scene darkened by the light texture, ambient light baked into it
+    TintedOverlay {
+        saturation: f32, // 1.0 keeps the light colors, more makes the tint stronger
+    },
+}
+
+impl LightingMode {
+    /// Whether the overlay texture holds linear light energy without ambient, see `TintedLightMaterial`.
+    pub fn is_tinted(&self) -> bool {
+        matches!(self, LightingMode::TintedOverlay { .. })
+    }
+}
+
 fn setup_overlay(
     mut commands: Commands,
     mut images: ResMut<Assets<Image>>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<MultiplyBlendMaterial>>,
+    mut tinted_materials: ResMut<Assets<TintedLightMaterial>>,
+    config: Res<LightingConfig>,
+    settings: Option<Res<simulation::LightSimSettings>>,
 ) {
-    let color = css::AQUAMARINE.to_u8_array();
-    let size_unscaled = (OVERLAY_IMAGE_SIZE_SCALED / TILE_SIZE_IN_UNITS_UNITS) as u32;
-    let image = Image::new_fill(
+    // Texels per side; the mesh below stays the same size in world units
+    let (color, size_unscaled, size_units) = match (*config, settings) {
+        (LightingConfig::StaticOverlay { size_units, color }, _) => (color.to_srgba().to_u8_array(), 1, size_units),
+        (LightingConfig::Simulated { .. }, Some(settings)) => (
+            css::AQUAMARINE.to_u8_array(), // Overwritten by the first simulation
+            settings.overlay_tiles as u32 * settings.pixels_per_tile.max(1),
+            overlay_size_units(settings.overlay_tiles),
+        ),
+        (LightingConfig::Simulated { tiles, pixels_per_tile, .. }, None) => (
+            css::AQUAMARINE.to_u8_array(),
+            tiles as u32 * pixels_per_tile.max(1),
+            overlay_size_units(tiles),
+        ),
+    };
+    let mut image = Image::new_fill(
         // 2D image of size
         Extent3d {
             width: size_unscaled as u32,
@@ -74,42 +432,636 @@ fn setup_overlay(
         TextureFormat::Rgba8UnormSrgb,
         RenderAssetUsages::RENDER_WORLD | RenderAssetUsages::MAIN_WORLD,
     );
+    image.sampler = ImageSampler::linear();
+    let back = images.add(image.clone());
     let handle = images.add(image);
 
     // Additive Blend
     let additive_material = MultiplyBlendMaterial {
         texture: handle.clone(),
     };
-    let mesh = meshes.add(Rectangle::new(
-        OVERLAY_IMAGE_SIZE_SCALED as f32,
-        OVERLAY_IMAGE_SIZE_SCALED as f32,
-    ));
+    let mesh = meshes.add(Rectangle::new(size_units, size_units));
     let material_handle = materials.add(additive_material);
+    let tinted_handle = tinted_materials.add(TintedLightMaterial {
+        texture: handle.clone(),
+        ambient: LinearRgba::BLACK,
+        saturation: 1.0,
+    });
     commands.spawn((
         OverlayImage(handle.clone()),
         MeshMaterial2d(material_handle.clone()),
         Mesh2d(mesh),
-        Transform::from_xyz(0.0, 0.0, 100000.0),
+        Transform::from_xyz(0.0, 0.0, RenderLayerZ::LightOverlay.z()),
+        CullBounds::square(size_units),
     ));
-    commands.insert_resource(LightOverlayTextureHandle(handle));
+    commands.insert_resource(LightOverlayTextureHandle { front: handle, back });
     commands.insert_resource(LightOverlayMaterialHandle(material_handle));
+    commands.insert_resource(LightOverlayTintedMaterialHandle(tinted_handle));
+}
+
+/// Swaps the material of the overlay entity when `LightingMode` changes.
+fn apply_lighting_mode(
+    mut commands: Commands,
+    mode: Res<LightingMode>,
+    overlay: Query<Entity, With<OverlayImage>>,
+    multiply_handle: Option<Res<LightOverlayMaterialHandle>>,
+    tinted_handle: Option<Res<LightOverlayTintedMaterialHandle>>,
+) {
+    if !mode.is_changed() {
+        return;
+    }
+    let (Some(multiply_handle), Some(tinted_handle)) = (multiply_handle, tinted_handle) else {
+        return; // Overlay not set up yet, the change is still pending next frame
+    };
+    for entity in overlay.iter() {
+        let mut entity = commands.entity(entity);
+        if mode.is_tinted() {
+            entity
+                .remove::<MeshMaterial2d<MultiplyBlendMaterial>>()
+                .insert(MeshMaterial2d(tinted_handle.0.clone()));
+        } else {
+            entity
+                .remove::<MeshMaterial2d<TintedLightMaterial>>()
+                .insert(MeshMaterial2d(multiply_handle.0.clone()));
+        }
+    }
+}
+
+/// Feeds the ambient light and saturation into the tinted material.
+fn sync_tinted_light_material(
+    mode: Res<LightingMode>,
+    ambient: Option<Res<GlobalAmbientLight>>,
+    tinted_handle: Option<Res<LightOverlayTintedMaterialHandle>>,
+    mut materials: ResMut<Assets<TintedLightMaterial>>,
+) {
+    let LightingMode::TintedOverlay { saturation } = *mode else {
+        return;
+    };
+    let Some(material) = tinted_handle.and_then(|handle| materials.get_mut(&handle.0)) else {
+        return;
+    };
+    let energy = ambient.map_or(Vec3::ZERO, |ambient| ambient.energy());
+    material.ambient = LinearRgba::rgb(energy.x, energy.y, energy.z);
+    material.saturation = saturation;
+}
+
+/// Shows the overlay image `run_lights_simulation` wrote this frame: both materials switch to it
+/// and the overlay moves to the window it was drawn for. Done together, so the texture on
+/// screen and its position never disagree for a frame.
+fn swap_overlay_textures_system(
+    mut state: ResMut<simulation::LightSimState>,
+    textures: Option<ResMut<LightOverlayTextureHandle>>,
+    multiply_handle: Option<Res<LightOverlayMaterialHandle>>,
+    tinted_handle: Option<Res<LightOverlayTintedMaterialHandle>>,
+    mut multiply_materials: ResMut<Assets<MultiplyBlendMaterial>>,
+    mut tinted_materials: ResMut<Assets<TintedLightMaterial>>,
+    mut overlay: Query<(&mut Transform, &mut OverlayImage)>,
+) {
+    let (Some(mut textures), Some(center)) = (textures, state.take_pending_swap()) else {
+        return;
+    };
+    let textures = &mut *textures;
+    std::mem::swap(&mut textures.front, &mut textures.back);
+    // Mutating the material assets rebinds the texture, the entity keeps its material handle
+    if let Some(material) = multiply_handle.and_then(|handle| multiply_materials.get_mut(&handle.0)) {
+        material.texture = textures.front.clone();
+    }
+    if let Some(material) = tinted_handle.and_then(|handle| tinted_materials.get_mut(&handle.0)) {
+        material.texture = textures.front.clone();
+    }
+    for (mut transform, mut image) in overlay.iter_mut() {
+        transform.translation = center;
+        image.0 = textures.front.clone();
+    }
+}
+
+/// Reallocates the overlay mesh and image when `LightSimSettings::overlay_tiles` changes.
+/// The simulation buffers follow on their own, they are sized by each snapshot.
+#[allow(clippy::too_many_arguments)]
+fn resize_overlay_system(
+    settings: Res<simulation::LightSimSettings>,
+    mut state: ResMut<simulation::LightSimState>,
+    mode: Res<LightingMode>,
+    ambient: Res<GlobalAmbientLight>,
+    texture_handle: Option<Res<LightOverlayTextureHandle>>,
+    mut images: ResMut<Assets<Image>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut overlay: Query<(&mut Mesh2d, &mut CullBounds), With<OverlayImage>>,
+) {
+    let tiles = settings.overlay_tiles.max(1);
+    if !settings.is_changed() || state.window_tiles() == tiles {
+        return;
+    }
+    let size_units = overlay_size_units(tiles);
+    for (mut mesh, mut bounds) in overlay.iter_mut() {
+        mesh.0 = meshes.add(Rectangle::new(size_units, size_units));
+        *bounds = CullBounds::square(size_units);
+    }
+    // Ambient only until the first result for the new size, rather than the old image stretched
+    if let Some(textures) = texture_handle {
+        let (energy, encoding) = simulation::overlay_ambient(&mode, &ambient);
+        let size_px = tiles as u32 * settings.pixels_per_tile.max(1);
+        for handle in [&textures.front, &textures.back] {
+            if let Some(image) = images.get_mut(handle) {
+                simulation::fill_overlay_image(image, size_px, energy, encoding);
+            }
+        }
+    }
+    state.reset_window(tiles);
+    info!("Light overlay resized to {} tiles", tiles);
 }
 
-fn overlay_texture_follow_camera(
-    mut overlay_image_q: Query<&mut Transform, (With<OverlayImage>, Without<FollowCamera>)>,
-    camera_query: Query<&Transform, (With<FollowCamera>, Without<OverlayImage>)>,
+type OverlayTransformQuery<'w, 's> =
+    Query<'w, 's, &'static mut Transform, (With<OverlayImage>, Without<FollowCamera>, Without<Player>)>;
+type OverlayTargetQuery<'w, 's> =
+    Query<'w, 's, (&'static Transform, Option<&'static Velocity>), (With<Player>, Without<OverlayImage>)>;
+
+/// Points the simulated overlay at the tile `LightOverlayFollow` picks, `run_lights_simulation` takes
+/// it from there. The static overlay has no tiles to line up with and just follows the target.
+fn overlay_texture_follow_system(
+    follow: Res<LightOverlayFollow>,
+    mut overlay_image_q: OverlayTransformQuery,
+    camera_query: Query<&CameraFocus>,
+    player_query: OverlayTargetQuery,
+    state: Option<ResMut<simulation::LightSimState>>,
 ) {
-    if let Ok(camera_transform) = camera_query.single() {
-        for mut transform in overlay_image_q.iter_mut() {
-            let target_position =
-                Point::from_world_pos(camera_transform.translation.xy(), TILE_SIZE_IN_UNITS_UNITS)
-                    .to_world_pos(TILE_SIZE_IN_UNITS_UNITS);
-
-            transform.translation = Vec3 {
-                x: target_position.x,
-                y: target_position.y,
-                z: 0.0,
+    let camera = camera_query.single().ok().map(|focus| focus.position.xy());
+    let player = player_query
+        .single()
+        .ok()
+        .map(|(t, velocity)| (t.translation.xy(), velocity.map_or(Vec2::ZERO, |v| v.0)));
+    let target = match (*follow, camera, player) {
+        (LightOverlayFollow::Player, _, Some((position, _))) => position,
+        (
+            LightOverlayFollow::Lead {
+                player_weight,
+                lookahead_secs,
+            },
+            Some(camera),
+            Some((position, velocity)),
+        ) => camera.lerp(position + velocity * lookahead_secs, player_weight),
+        (_, Some(camera), _) => camera,
+        (_, None, Some((position, _))) => position,
+        (_, None, None) => return,
+    };
+    // Simulated, the overlay moves with the texture that matches the window, see `swap_overlay_textures_system`
+    if let Some(mut state) = state {
+        let center = simulation::window_center(WorldPos(target).to_tile(), state.window_tiles()).0;
+        state.set_target_center(center);
+        return;
+    }
+    for mut transform in overlay_image_q.iter_mut() {
+        transform.translation = target.extend(transform.translation.z); // Stays on its layer
+    }
+}
+
+/// Updates the animation factor of every animated `LightEmitter`.
+/// Uses virtual time, so pausing it freezes the animations.
+pub fn animate_light_emitters(time: Res<Time>, mut emitters: Query<(Entity, &mut LightEmitter)>) {
+    let now = time.elapsed_secs();
+    for (entity, mut emitter) in emitters.iter_mut() {
+        if emitter.animation == LightAnimation::Static {
+            continue;
+        }
+        // Seeded per entity, so torches don't flicker in sync
+        let scale = emitter.animation.scale_at(now, entity.to_bits());
+        emitter.animation_scale = scale;
+    }
+}
+
+/// What `sync_light_emitters` wrote into the lights map during the previous frame.
+#[derive(Default)]
+pub struct EmitterSyncState {
+    applied: HashMap<Point, LightEmitterCell>, // Emitter contributions only, without the original cell
+    originals: HashMap<Point, LightEmitterCell>, // Cells as they were before an emitter moved in, loaded ones only
+}
+
+/// Writes every `LightEmitter` into the lights map at its entity's tile, scaled by `LightModulation`.
+/// Omni emitters sharing a tile are summed, for cone emitters the last one wins.
+/// Tiles left by all emitters get their original cell back. Emitters on unloaded tiles wait for
+/// their chunk, there is no original cell to keep yet.
+pub fn sync_light_emitters(
+    emitters: Query<(&Transform, &LightEmitter, Option<&LightTag>)>,
+    modulation: Res<LightModulation>,
+    mut lights: ResMut<DataMap<LightsMapProducer>>,
+    mut loaded_events: EventReader<ChunkLoaded<LightsMapProducer>>,
+    mut state: Local<EmitterSyncState>,
+) {
+    // Chunks loaded again came back without the emitters written into them: rewrite those tiles,
+    // from the originals kept, as the reloaded cells may already hold a queued write
+    let dimension = lights.chunk_dimension_tiles;
+    for event in loaded_events.read() {
+        state.applied.retain(|tile, _| !event.coords.contains_point(*tile, dimension));
+    }
+
+    let mut current: HashMap<Point, LightEmitterCell> = HashMap::new();
+    for (transform, emitter, tag) in emitters.iter() {
+        let tile = WorldPos(transform.translation.xy()).to_tile().0;
+        let light = emitter.light_definition().scale(modulation.factor(tag));
+        let cell = current.entry(tile).or_default();
+        match emitter.direction {
+            Some(direction) => {
+                cell.directed_lights = Some(DirectedLightEmitter {
+                    props: light,
+                    direction,
+                    spread: emitter.spread,
+                });
+            }
+            None => {
+                let props = match cell.undirected_lights {
+                    Some(existing) => existing.props.saturating_add(&light),
+                    None => light,
+                };
+                cell.undirected_lights = Some(UndirectedLightEmitter { props });
+            }
+        }
+    }
+
+    let EmitterSyncState { applied, originals } = &mut *state;
+
+    // Tiles without emitters anymore (moved away or despawned)
+    for tile in applied.keys() {
+        if current.contains_key(tile) {
+            continue;
+        }
+        if let Some(original) = originals.remove(tile) {
+            lights.write(*tile, original);
+        }
+    }
+
+    // Only rewrite tiles whose emitters changed
+    let mut written = HashMap::new();
+    for (tile, contribution) in current.iter() {
+        if applied.get(tile) == Some(contribution) {
+            written.insert(*tile, *contribution);
+            continue;
+        }
+        let original = match originals.get(tile) {
+            Some(original) => *original,
+            None => {
+                let Some(loaded) = lights.read(*tile) else {
+                    continue; // Retried every frame until the chunk is loaded
+                };
+                originals.insert(*tile, loaded);
+                loaded
+            }
+        };
+        let mut cell = original;
+        if let Some(light) = contribution.undirected_lights {
+            let props = match original.undirected_lights {
+                Some(existing) => existing.props.saturating_add(&light.props),
+                None => light.props,
+            };
+            cell.undirected_lights = Some(UndirectedLightEmitter { props });
+        }
+        if contribution.directed_lights.is_some() {
+            cell.directed_lights = contribution.directed_lights;
+        }
+        lights.write(*tile, cell);
+        written.insert(*tile, *contribution);
+    }
+
+    originals.retain(|tile, _| written.contains_key(tile)); // Also those of reloaded tiles left meanwhile
+    *applied = written;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::chunks::{ChunkCoords, ContiguousGridData, GridData, MapDataProducer};
+    use crate::game::render::light_sim::directions::Direction;
+    use crate::game::render::light_sim::lights::LIGHT_MODULATION_MAX;
+    use crate::game::spawn::tile_to_world;
+
+    const DIMENSION: TilesCount = 8;
+    const LIT_TILE: Point = Point { x: 0, y: 0 }; // Generated with a light, see `LightsMapProducer`
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.insert_resource(DataMap::new(LightsMapProducer, DIMENSION, 1))
+            .init_resource::<LightModulation>()
+            .add_event::<ChunkLoaded<LightsMapProducer>>()
+            .add_systems(Update, sync_light_emitters);
+        app
+    }
+
+    fn load(app: &mut App, coords: ChunkCoords) {
+        let mut lights = app.world_mut().resource_mut::<DataMap<LightsMapProducer>>();
+        let chunk = lights.producer.generate_chunk(coords, DIMENSION);
+        lights.loaded_chunks.insert(coords, chunk);
+    }
+
+    fn read(app: &App, tile: Point) -> Option<LightEmitterCell> {
+        app.world().resource::<DataMap<LightsMapProducer>>().read(tile)
+    }
+
+    #[test]
+    fn emitter_waits_for_its_chunk_and_restores_the_generated_cell() {
+        let mut app = app();
+        let generated = LightsMapProducer
+            .generate_chunk(ChunkCoords { x: 0, y: 0 }, DIMENSION)
+            .grid
+            .get_item(0, 0)
+            .copied()
+            .unwrap();
+        let emitter = app.world_mut().spawn((Transform::from_xyz(1.0, 1.0, 0.0), LightEmitter::default())).id();
+
+        // Nothing is queued over the unloaded tile, it would replace the generated light
+        app.update();
+        assert!(app.world().resource::<DataMap<LightsMapProducer>>().write_queue.is_empty());
+
+        load(&mut app, ChunkCoords { x: 0, y: 0 });
+        app.update();
+        let lit = read(&app, LIT_TILE).unwrap();
+        assert_ne!(lit, generated);
+
+        app.world_mut().despawn(emitter);
+        app.update();
+        assert_eq!(read(&app, LIT_TILE), Some(generated));
+    }
+
+    #[test]
+    fn pausing_virtual_time_freezes_light_animations() {
+        let mut app = App::new();
+        app.add_plugins(bevy::time::TimePlugin)
+            .insert_resource(bevy::time::TimeUpdateStrategy::ManualDuration(std::time::Duration::from_millis(50)))
+            .add_systems(Update, animate_light_emitters);
+        let torch = app
+            .world_mut()
+            .spawn(LightEmitter {
+                animation: LightAnimation::Pulse { period: 1.0, min: 0.0, max: 1.0 },
+                ..default()
+            })
+            .id();
+        let scale = |app: &App| app.world().get::<LightEmitter>(torch).unwrap().animation_scale;
+        for _ in 0..3 {
+            app.update();
+        }
+        let running = scale(&app);
+        app.update();
+        assert_ne!(scale(&app), running, "not animated");
+
+        app.world_mut().resource_mut::<Time<Virtual>>().pause();
+        let paused = scale(&app);
+        for _ in 0..5 {
+            app.update();
+            assert_eq!(scale(&app), paused, "animated while paused");
+        }
+        app.world_mut().resource_mut::<Time<Virtual>>().unpause();
+        app.update();
+        app.update();
+        assert_ne!(scale(&app), paused);
+    }
+
+    #[test]
+    fn seeded_emitters_scale_linearly_with_the_modulation() {
+        let mut app = app();
+        load(&mut app, ChunkCoords { x: 0, y: 0 });
+        let tile = Point { x: 3, y: 3 }; // Dark when generated, so the cell holds the emitter only
+        let position = tile_to_world(tile).extend(0.0); // Tiles are centered on their coordinates
+        app.world_mut()
+            .spawn((Transform::from_translation(position), LightEmitter::default(), LightTag("music".into())));
+
+        let mut seeded = |global_scale: f32, music: Option<f32>| {
+            let mut modulation = app.world_mut().resource_mut::<LightModulation>();
+            modulation.global_scale = global_scale;
+            modulation.per_tag.clear();
+            modulation.per_tag.extend(music.map(|scale| ("music".to_string(), scale)));
+            app.update(); // Written the same frame
+            read(&app, tile).unwrap().undirected_lights.unwrap().props.color[0]
+        };
+        let base = seeded(1.0, None);
+        assert_eq!(base, 1.0);
+        for (global_scale, music, factor) in [
+            (0.5, None, 0.5),
+            (2.0, None, 2.0),
+            (0.0, None, 0.0),
+            (1.0, Some(1.5), 1.5),
+            (0.5, Some(3.0), 1.5),
+            (3.0, Some(2.0), LIGHT_MODULATION_MAX), // Clamped
+            (-1.0, None, 0.0),
+        ] {
+            let value = seeded(global_scale, music);
+            assert!((value - base * factor).abs() < 1e-5, "{global_scale} x {music:?} gave {value}");
+        }
+    }
+
+    // Whether the light was simulated in each of `frames` updates
+    fn simulated_frames(app: &mut App, frames: usize) -> Vec<bool> {
+        (0..frames)
+            .map(|_| {
+                app.update();
+                app.world().resource::<simulation::LightSimStats>().ran_this_frame
+            })
+            .collect()
+    }
+
+    // An 8 tile overlay at the origin simulated synchronously, with both maps loaded under it
+    fn simulated_scene() -> App {
+        let mut app = app();
+        let mut images = Assets::<Image>::default();
+        let blank = Image::new_fill(
+            Extent3d { width: 2, height: 2, depth_or_array_layers: 1 },
+            TextureDimension::D2,
+            &[0, 0, 0, 255],
+            TextureFormat::Rgba8UnormSrgb,
+            RenderAssetUsages::MAIN_WORLD,
+        );
+        let (front, back) = (images.add(blank.clone()), images.add(blank));
+        let mut materials = Assets::<MultiplyBlendMaterial>::default();
+        let material = materials.add(MultiplyBlendMaterial { texture: front.clone() });
+        let mut state = simulation::LightSimState::default();
+        state.reset_window(8);
+        app.insert_resource(images)
+            .insert_resource(materials)
+            .insert_resource(LightOverlayTextureHandle { front: front.clone(), back })
+            .insert_resource(LightOverlayMaterialHandle(material))
+            .insert_resource(DataMap::new(PbrCellProducer, DIMENSION, 1))
+            .insert_resource(simulation::LightSimSettings {
+                async_simulation: false,
+                overlay_tiles: 8,
+                ..default()
+            })
+            .insert_resource(state)
+            .init_resource::<simulation::LightSimStats>()
+            .init_resource::<simulation::LightFieldSnapshot>()
+            .init_resource::<GlobalAmbientLight>()
+            .init_resource::<LightingMode>()
+            .add_systems(PostUpdate, simulation::run_lights_simulation);
+        // Tiles -6 to 5 are simulated, over chunks -1 and 0 of both maps
+        for coords in ChunkCoords::iter_square(ChunkCoords { x: 0, y: 0 }, 1) {
+            load(&mut app, coords);
+            app.world_mut().resource_mut::<DataMap<PbrCellProducer>>().load_generated_chunk(coords);
+        }
+        app.world_mut().spawn((Transform::default(), Visibility::Inherited, OverlayImage(front)));
+        app
+    }
+
+    #[test]
+    fn stationary_scene_is_simulated_once_until_an_emitter_moves() {
+        let mut app = simulated_scene();
+        let emitter = app.world_mut().spawn((Transform::from_xyz(40.0, 8.0, 0.0), LightEmitter::default())).id();
+
+        assert_eq!(simulated_frames(&mut app, 10), [vec![true], vec![false; 9]].concat());
+        assert_eq!(app.world().resource::<simulation::LightSimStats>().skipped_frames, 9);
+
+        app.world_mut().entity_mut(emitter).insert(Transform::from_xyz(-40.0, 8.0, 0.0));
+        assert_eq!(simulated_frames(&mut app, 5), [vec![true], vec![false; 4]].concat());
+    }
+
+    #[test]
+    fn cone_lights_shine_ahead_on_the_overlay_in_world_tiles() {
+        let source = Point { x: -2, y: -2 };
+        for (direction, ahead) in [(Direction::N, Point { x: 0, y: 1 }), (Direction::E, Point { x: 1, y: 0 })] {
+            let mut app = simulated_scene();
+            app.insert_resource(GlobalAmbientLight { intensity: 0.0, ..default() });
+            let mut lights = app.world_mut().resource_mut::<DataMap<LightsMapProducer>>();
+            for chunk in lights.loaded_chunks.values_mut() {
+                chunk.grid.as_mut_slice().fill(default()); // Without the generated lights, the cone shines alone
             }
+            let emitter = LightEmitter {
+                direction: Some(direction),
+                spread: 0,
+                ..default()
+            };
+            app.world_mut()
+                .spawn((Transform::from_translation(tile_to_world(source).extend(0.0)), emitter));
+            app.update();
+
+            // The 8 tile overlay covers tiles -4 to 3, one texel each; image rows grow downwards
+            let world = app.world();
+            let image = world.resource::<Assets<Image>>().get(&world.resource::<LightOverlayTextureHandle>().back).unwrap();
+            let lit = |tile: Point| {
+                let (x, y) = ((tile.x + 4) as usize, (3 - tile.y) as usize);
+                image.data.as_ref().unwrap()[(y * 8 + x) * 4] > 0
+            };
+            let beam = source + Point { x: 4 * ahead.x, y: 4 * ahead.y };
+            assert!(lit(beam), "{direction:?}: {beam:?} is dark");
+            for x in -4..4 {
+                for y in -4..4 {
+                    let tile = Point { x, y };
+                    let forward = (tile.x - source.x) * ahead.x + (tile.y - source.y) * ahead.y;
+                    assert!(forward >= 0 || !lit(tile), "{direction:?}: {tile:?} is lit behind the light");
+                }
+            }
+        }
+    }
+
+    // The overlay `setup_overlay` makes for `config`: its image size and world size
+    fn overlay_for(config: LightingConfig) -> (UVec2, Vec2) {
+        let mut app = App::new();
+        app.insert_resource(config)
+            .init_resource::<Assets<Image>>()
+            .init_resource::<Assets<Mesh>>()
+            .init_resource::<Assets<MultiplyBlendMaterial>>()
+            .init_resource::<Assets<TintedLightMaterial>>()
+            .add_systems(Startup, setup_overlay);
+        app.update();
+        let world = app.world_mut();
+        let (overlay, bounds) = world.query::<(&OverlayImage, &CullBounds)>().single(world).unwrap();
+        let textures = world.resource::<LightOverlayTextureHandle>();
+        assert_eq!(overlay.0, textures.front);
+        let images = world.resource::<Assets<Image>>();
+        let size = images.get(&textures.front).unwrap().size();
+        assert_eq!(images.get(&textures.back).unwrap().size(), size);
+        assert!(world.contains_resource::<LightOverlayMaterialHandle>());
+        assert!(world.contains_resource::<LightOverlayTintedMaterialHandle>());
+        (size, bounds.size)
+    }
+
+    #[test]
+    fn overlay_is_sized_by_the_lighting_config() {
+        let static_overlay = LightingConfig::StaticOverlay {
+            size_units: 120.0,
+            color: Color::WHITE,
+        };
+        // A single texel stretched over the whole overlay
+        assert_eq!(overlay_for(static_overlay), (UVec2::ONE, Vec2::splat(120.0)));
+
+        let simulated = LightingConfig::Simulated {
+            tiles: 32,
+            steps: 10,
+            pixels_per_tile: 2,
+            apron_tiles: 2,
+        };
+        assert_eq!(overlay_for(simulated), (UVec2::splat(64), Vec2::splat(32.0 * TILE_SIZE_IN_UNITS)));
+    }
+
+    #[test]
+    fn static_overlay_stays_on_its_layer_while_following() {
+        let mut app = App::new();
+        app.insert_resource(LightOverlayFollow::Player)
+            .add_systems(Update, overlay_texture_follow_system);
+        let z = RenderLayerZ::LightOverlay.z();
+        let overlay = app
+            .world_mut()
+            .spawn((Transform::from_xyz(0.0, 0.0, z), OverlayImage(Handle::default())))
+            .id();
+        let player = app.world_mut().spawn((Player, Transform::from_xyz(100.0, 50.0, 0.0))).id();
+        for target in [Vec2::new(100.0, 50.0), Vec2::new(-300.0, 20.0)] {
+            app.world_mut().get_mut::<Transform>(player).unwrap().translation = target.extend(0.0);
+            app.update();
+            assert_eq!(app.world().get::<Transform>(overlay).unwrap().translation, target.extend(z));
+        }
+    }
+
+    // First byte of an overlay image, stamped with the frame that wrote it
+    fn written_frame(app: &App, handle: &Handle<Image>) -> u8 {
+        app.world().resource::<Assets<Image>>().get(handle).unwrap().data.as_ref().unwrap()[0]
+    }
+
+    #[test]
+    fn overlay_material_only_switches_to_a_completely_written_image() {
+        let mut app = App::new();
+        let mut images = Assets::<Image>::default();
+        let blank = Image::new_fill(
+            Extent3d { width: 2, height: 2, depth_or_array_layers: 1 },
+            TextureDimension::D2,
+            &[0, 0, 0, 255],
+            TextureFormat::Rgba8UnormSrgb,
+            RenderAssetUsages::MAIN_WORLD,
+        );
+        let (front, back) = (images.add(blank.clone()), images.add(blank));
+        let mut multiply = Assets::<MultiplyBlendMaterial>::default();
+        let multiply_handle = multiply.add(MultiplyBlendMaterial { texture: front.clone() });
+        let mut tinted = Assets::<TintedLightMaterial>::default();
+        let tinted_handle = tinted.add(TintedLightMaterial {
+            texture: front.clone(),
+            ambient: LinearRgba::BLACK,
+            saturation: 1.0,
+        });
+        app.insert_resource(images)
+            .insert_resource(multiply)
+            .insert_resource(tinted)
+            .insert_resource(LightOverlayTextureHandle { front: front.clone(), back })
+            .insert_resource(LightOverlayMaterialHandle(multiply_handle.clone()))
+            .insert_resource(LightOverlayTintedMaterialHandle(tinted_handle.clone()))
+            .init_resource::<simulation::LightSimState>()
+            .add_systems(Update, swap_overlay_textures_system);
+        let overlay = app.world_mut().spawn((Transform::default(), OverlayImage(front.clone()))).id();
+
+        let mut shown = (front, 0, Vec3::ZERO); // Image, the frame that wrote it, its window center
+        for frame in 1..=8u8 {
+            // Like `run_lights_simulation`, every other frame: the back image is written whole, then marked
+            if frame % 2 == 1 {
+                let back = app.world().resource::<LightOverlayTextureHandle>().back.clone();
+                let mut images = app.world_mut().resource_mut::<Assets<Image>>();
+                images.get_mut(&back).unwrap().data.as_mut().unwrap().fill(frame);
+                let center = Vec3::new(frame as f32 * TILE_SIZE_IN_UNITS, 0.0, 0.0);
+                app.world_mut().resource_mut::<simulation::LightSimState>().set_pending_swap(center);
+                shown = (back, frame, center);
+            }
+            app.update();
+
+            let world = app.world();
+            let texture = &world.resource::<Assets<MultiplyBlendMaterial>>().get(&multiply_handle).unwrap().texture;
+            assert_eq!(texture, &shown.0, "frame {frame}");
+            assert_eq!(&world.resource::<Assets<TintedLightMaterial>>().get(&tinted_handle).unwrap().texture, texture);
+            assert_eq!(&world.resource::<LightOverlayTextureHandle>().front, texture);
+            assert_eq!(written_frame(&app, texture), shown.1, "frame {frame}");
+            // The window moves with the texture, never a frame apart
+            assert_eq!(world.get::<Transform>(overlay).unwrap().translation, shown.2, "frame {frame}");
+            assert_eq!(&world.get::<OverlayImage>(overlay).unwrap().0, texture);
         }
     }
 }
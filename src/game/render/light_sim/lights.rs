@@ -1,10 +1,56 @@
-use bevy::color::{Color, ColorToComponents, Srgba};
+use bevy::{
+    color::{ColorToComponents, Srgba},
+    ecs::{component::Component, resource::Resource},
+    math::Vec3,
+    platform::collections::HashMap,
+};
 
+use crate::{core::noise::value_noise, game::render::light_sim::directions::Direction};
+
+/// Light energy per channel, in linear space. 1.0 is a fully lit tile, more is allowed.
 #[derive(Debug, Clone, Copy, Default, PartialEq)]
 pub struct LightDefinition {
     pub color: [f32; 3],
 }
 
+impl LightDefinition {
+    pub const DARK: LightDefinition = LightDefinition { color: [0.0; 3] };
+
+    /// Energy below this on every channel is treated as no light.
+    pub const DARK_EPSILON: f32 = 1e-3;
+
+    /// Sum of both lights, saturating at `f32::MAX` instead of overflowing to infinity.
+    pub fn saturating_add(&self, other: &LightDefinition) -> LightDefinition {
+        LightDefinition {
+            color: std::array::from_fn(|i| (self.color[i] + other.color[i]).min(f32::MAX)),
+        }
+    }
+
+    /// Light scaled by `factor`, never negative.
+    pub fn scale(&self, factor: f32) -> LightDefinition {
+        LightDefinition {
+            color: self.color.map(|c| (c * factor).clamp(0.0, f32::MAX)),
+        }
+    }
+
+    /// Brightest of both lights, per channel.
+    pub fn max(&self, other: &LightDefinition) -> LightDefinition {
+        LightDefinition {
+            color: std::array::from_fn(|i| self.color[i].max(other.color[i])),
+        }
+    }
+
+    pub fn is_dark(&self) -> bool {
+        self.color.iter().all(|&c| c < Self::DARK_EPSILON)
+    }
+}
+
+impl From<LightDefinition> for Vec3 {
+    fn from(light: LightDefinition) -> Self {
+        Vec3::from_array(light.color)
+    }
+}
+
 impl From<[u8; 4]> for LightDefinition {
     fn from(rgba: [u8; 4]) -> Self {
         let [r, g, b, a] = rgba;
@@ -30,10 +76,9 @@ impl From<[u8; 3]> for LightDefinition {
 
 impl From<Srgba> for LightDefinition {
     fn from(srgba: Srgba) -> Self {
-        let color: Color = srgba.into(); // convert Srgba -> Color
-        let [r, g, b, a] = color.to_srgba().to_f32_array(); // now extract linear values
+        let [r, g, b, a] = srgba.to_f32_array();
         Self {
-            color: [r * 1.0, g * 1.0, b * 1.0], // premultiplied alpha
+            color: [r * a, g * a, b * a], // premultiplied alpha, same as for [u8; 4]
         }
     }
 }
@@ -42,3 +87,226 @@ impl From<Srgba> for LightDefinition {
 pub struct UndirectedLightEmitter {
     pub props: LightDefinition,
 }
+
+/// Emits into `direction` and, with reduced energy, into up to `spread` neighboring directions on each side.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DirectedLightEmitter {
+    pub props: LightDefinition,
+    pub direction: Direction,
+    pub spread: u8,
+}
+
+impl DirectedLightEmitter {
+    /// Energy factor for a direction `steps` away from the main one: halved per step.
+    pub fn falloff(steps: u8) -> f32 {
+        0.5f32.powi(steps as i32)
+    }
+}
+
+/// How the intensity of a `LightEmitter` changes over time, as a factor on `intensity`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum LightAnimation {
+    #[default]
+    Static,
+    Flicker {
+        amplitude: f32, // Largest deviation from 1.0
+        speed: f32,     // Noise lattice cells per second, higher is more nervous
+    },
+    Pulse {
+        period: f32, // Seconds from min to max and back
+        min: f32,
+        max: f32,
+    },
+    Fade {
+        start_secs: f32,    // Virtual time the light is at full intensity
+        duration_secs: f32, // Dark from then on
+    },
+}
+
+impl LightAnimation {
+    /// Intensity factor at `time_secs`. Flicker noise is seeded with `seed`, so lights with
+    /// different seeds flicker independently.
+    pub fn scale_at(&self, time_secs: f32, seed: u64) -> f32 {
+        match *self {
+            LightAnimation::Static => 1.0,
+            LightAnimation::Flicker { amplitude, speed } => {
+                let noise = value_noise(seed, time_secs * speed, 0.0) * 2.0 - 1.0;
+                (1.0 + amplitude * noise).max(0.0)
+            }
+            LightAnimation::Pulse { period, min, max } => {
+                if period <= 0.0 {
+                    return max;
+                }
+                let phase = (time_secs / period).fract();
+                let t = 0.5 - 0.5 * (phase * std::f32::consts::TAU).cos(); // 0 at the start, 1 halfway
+                min + (max - min) * t
+            }
+            LightAnimation::Fade {
+                start_secs,
+                duration_secs,
+            } => {
+                if duration_secs <= 0.0 {
+                    return 0.0;
+                }
+                (1.0 - (time_secs - start_secs) / duration_secs).clamp(0.0, 1.0)
+            }
+        }
+    }
+}
+
+/// A light attached to an entity. Synced into the lights map at the entity's tile every frame.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct LightEmitter {
+    pub color: LightDefinition,
+    pub intensity: f32,
+    pub radius: Option<f32>, // Hint in tiles, not used by the simulation yet
+    pub direction: Option<Direction>, // Some makes this a cone light
+    pub spread: u8,                   // Cone width in 45° steps to each side, see DirectedLightEmitter
+    pub animation: LightAnimation,
+    pub animation_scale: f32, // Current factor of `animation`, set by animate_light_emitters
+}
+
+impl Default for LightEmitter {
+    fn default() -> Self {
+        Self {
+            color: LightDefinition {
+                color: [1.0, 1.0, 1.0],
+            },
+            intensity: 1.0,
+            radius: None,
+            direction: None,
+            spread: 1,
+            animation: LightAnimation::Static,
+            animation_scale: 1.0,
+        }
+    }
+}
+
+impl LightEmitter {
+    /// Color scaled by intensity and the current animation factor, as written into the lights map.
+    pub fn light_definition(&self) -> LightDefinition {
+        self.color.scale(self.intensity * self.animation_scale)
+    }
+}
+
+/// Largest factor `LightModulation` applies, so a runaway input can't blow out the whole screen.
+pub const LIGHT_MODULATION_MAX: f32 = 4.0;
+
+/// Groups emitters for `LightModulation::per_tag`.
+#[derive(Component, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LightTag(pub String);
+
+/// Intensity factors for external inputs (music, gameplay events) on top of each emitter's own.
+/// Applied by `sync_light_emitters`, so systems writing it before that one take effect the same frame.
+#[derive(Resource, Debug, Clone)]
+pub struct LightModulation {
+    pub global_scale: f32,
+    pub per_tag: HashMap<String, f32>, // Tags missing here are at 1.0
+}
+
+impl Default for LightModulation {
+    fn default() -> Self {
+        Self {
+            global_scale: 1.0,
+            per_tag: HashMap::new(),
+        }
+    }
+}
+
+impl LightModulation {
+    /// Factor of an emitter with the given tag, clamped to 0..`LIGHT_MODULATION_MAX`.
+    pub fn factor(&self, tag: Option<&LightTag>) -> f32 {
+        let tag_scale = tag
+            .and_then(|tag| self.per_tag.get(&tag.0))
+            .copied()
+            .unwrap_or(1.0);
+        (self.global_scale * tag_scale).clamp(0.0, LIGHT_MODULATION_MAX)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn light(r: f32, g: f32, b: f32) -> LightDefinition {
+        LightDefinition { color: [r, g, b] }
+    }
+
+    #[test]
+    fn two_half_lights_add_up_to_a_full_one() {
+        let half = LightDefinition::from(Srgba::WHITE).scale(0.5);
+        let sum = half.saturating_add(&half);
+        let full = LightDefinition::from(Srgba::WHITE);
+        for (s, f) in sum.color.iter().zip(full.color) {
+            assert!((s - f).abs() < 1e-6, "{sum:?}");
+        }
+    }
+
+    #[test]
+    fn arithmetic_saturates_instead_of_overflowing() {
+        let huge = light(f32::MAX, 1.0, 0.0);
+        let sum = huge.saturating_add(&huge);
+        assert_eq!(sum.color, [f32::MAX, 2.0, 0.0]);
+        assert_eq!(huge.scale(4.0).color, [f32::MAX, 4.0, 0.0]);
+        assert_eq!(light(0.5, 1.0, 0.0).scale(-1.0), LightDefinition::DARK);
+    }
+
+    #[test]
+    fn max_is_per_channel() {
+        let warm = light(1.0, 0.5, 0.0);
+        let cold = light(0.0, 0.5, 1.0);
+        assert_eq!(warm.max(&cold), light(1.0, 0.5, 1.0));
+    }
+
+    #[test]
+    fn dark_below_the_epsilon_on_every_channel() {
+        assert!(LightDefinition::DARK.is_dark());
+        assert!(light(1e-4, 0.0, 5e-4).is_dark());
+        assert!(!light(0.0, 0.0, 0.01).is_dark());
+    }
+
+    #[test]
+    fn srgba_alpha_is_premultiplied_like_bytes() {
+        let from_srgba = LightDefinition::from(Srgba::new(1.0, 0.5, 0.0, 0.5));
+        let from_bytes = LightDefinition::from([255u8, 128, 0, 128]);
+        for (a, b) in from_srgba.color.iter().zip(from_bytes.color) {
+            assert!((a - b).abs() < 0.01, "{from_srgba:?} vs {from_bytes:?}");
+        }
+        assert_eq!(Vec3::from(from_srgba), Vec3::new(0.5, 0.25, 0.0));
+    }
+
+    const TORCH: LightAnimation = LightAnimation::Flicker { amplitude: 0.3, speed: 4.0 };
+
+    #[test]
+    fn flickering_lights_vary_independently_and_smoothly() {
+        let frame = 1.0 / 60.0;
+        let samples = |seed| (0..600).map(|i| TORCH.scale_at(i as f32 * frame, seed)).collect::<Vec<f32>>();
+        let (first, second) = (samples(1), samples(2));
+        let apart = first.iter().zip(second.iter()).filter(|(a, b)| (*a - *b).abs() > 0.05).count();
+        assert!(apart > 100, "the two torches flicker in sync");
+        for scales in [&first, &second] {
+            assert!(scales.iter().all(|s| (0.7..=1.3).contains(s)));
+            // Value noise changes at most 1.5 times its range per lattice cell
+            let max_step = 1.5 * 2.0 * 0.3 * 4.0 * frame;
+            for pair in scales.windows(2) {
+                assert!((pair[1] - pair[0]).abs() <= max_step + 1e-4, "jumped from {} to {}", pair[0], pair[1]);
+            }
+        }
+    }
+
+    #[test]
+    fn pulse_oscillates_between_min_and_max_over_its_period() {
+        let pulse = LightAnimation::Pulse { period: 2.0, min: 0.25, max: 1.5 };
+        for start in [0.0, 2.0, 10.0] {
+            assert!((pulse.scale_at(start, 0) - 0.25).abs() < 1e-4);
+            assert!((pulse.scale_at(start + 1.0, 0) - 1.5).abs() < 1e-4);
+        }
+        let mut previous = pulse.scale_at(0.0, 0);
+        for i in 1..=50 {
+            let scale = pulse.scale_at(i as f32 * 0.02, 0); // Rising during the first half
+            assert!(scale >= previous && scale <= 1.5);
+            previous = scale;
+        }
+        assert!((0..200).all(|i| (0.25..=1.5).contains(&pulse.scale_at(i as f32 * 0.037, 0))));
+    }
+}
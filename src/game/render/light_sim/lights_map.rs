@@ -7,7 +7,7 @@ use crate::{
         chunks::{ChunkCoords, DataChunk, FlatGrid, GridData, MapDataProducer},
         units::TilesCount,
     },
-    game::render::light_sim::lights::UndirectedLightEmitter,
+    game::render::light_sim::lights::{DirectedLightEmitter, UndirectedLightEmitter},
 };
 
 #[derive(Default, Clone)]
@@ -16,6 +16,7 @@ pub struct LightsMapProducer;
 #[derive(Debug, Clone, Copy, Default, PartialEq)]
 pub struct LightEmitterCell {
     pub undirected_lights: Option<UndirectedLightEmitter>,
+    pub directed_lights: Option<DirectedLightEmitter>,
 }
 
 impl MapDataProducer for LightsMapProducer {
@@ -52,6 +53,7 @@ impl MapDataProducer for LightsMapProducer {
                             undirected_lights: Some(UndirectedLightEmitter {
                                 props: color.into(),
                             }),
+                            directed_lights: None,
                         },
                     );
                 }
@@ -59,6 +61,6 @@ impl MapDataProducer for LightsMapProducer {
             }
         }
 
-        DataChunk { grid }
+        DataChunk::new(grid)
     }
 }
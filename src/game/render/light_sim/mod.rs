@@ -4,4 +4,5 @@ pub mod lights;
 pub mod lights_map;
 pub mod simulation;
 pub mod color_utils;
-pub mod pbr_cell;
\ No newline at end of file
+pub mod pbr_cell;
+pub mod lava_border;
\ No newline at end of file
@@ -1,8 +1,25 @@
-use bevy::log::info;
+use bevy::{
+    ecs::{
+        event::EventReader,
+        system::{Res, ResMut},
+    },
+    log::info,
+};
 
-use crate::core::{
-    chunks::{ChunkCoords, DataChunk, FlatGrid, GridData, MapDataProducer},
-    units::TilesCount,
+use crate::{
+    core::{
+        basics::Point,
+        chunks::{
+            ChunkCoords, ChunkLoaded, ChunkWritten, DataChunk, DataMap, FlatGrid, GridData,
+            MapDataProducer,
+        },
+        units::TilesCount,
+    },
+    game::{
+        inspector::DebugSummarize,
+        render::tilemap_render::TileColorizer,
+        world::passability::{Passability, PassabilityProducer},
+    },
 };
 
 #[derive(Default, Clone)]
@@ -20,13 +37,21 @@ impl Default for PbrCell {
     fn default() -> Self {
         Self {
             transparent: true,
-            absorbtion: 0.9,
+            absorbtion: 0.1, // Open air, most of the energy passes
             reflection: 0.0,
             scattering: 0.1,
         }
     }
 }
 
+impl DebugSummarize for PbrCell {
+    const DEBUG_RANGE: (f32, f32) = (0.0, 1.0);
+
+    fn debug_value(&self) -> f32 {
+        self.absorbtion // Walls are near 1.0, open air near 0.1
+    }
+}
+
 impl PbrCell {
     /// Represents a semi-transparent glass material.
     pub const SEMI_TRANSPARENT_GLASS: PbrCell = PbrCell {
@@ -69,6 +94,72 @@ impl PbrCell {
     };
 }
 
+impl PbrCell {
+    /// Light material of terrain: walls are solid, the falloff band around them absorbs
+    /// more the closer it is to a wall, free tiles are open air.
+    pub fn from_passability(passability: Passability) -> PbrCell {
+        if passability.is_wall() {
+            return PbrCell::SOLID_WALL;
+        }
+        let air = PbrCell::default();
+        let wall_threshold = Passability::WALL_THRESHOLD as f32;
+        let openness = (passability.0 as f32 - wall_threshold) / (Passability::FREE.0 as f32 - wall_threshold);
+        let densest = 0.9; // Absorbtion right next to a wall
+        PbrCell {
+            absorbtion: densest + (air.absorbtion - densest) * openness,
+            ..air
+        }
+    }
+}
+
+// Keeps the light materials in step with the terrain, for every generated, loaded or modified passability chunk
+pub fn sync_pbr_from_passability_system(
+    mut loaded_events: EventReader<ChunkLoaded<PassabilityProducer>>,
+    mut written_events: EventReader<ChunkWritten<PassabilityProducer>>,
+    mut pbr_loaded_events: EventReader<ChunkLoaded<PbrCellProducer>>,
+    passability: Res<DataMap<PassabilityProducer>>,
+    mut pbr_cells: ResMut<DataMap<PbrCellProducer>>,
+) {
+    let dimension = passability.chunk_dimension_tiles;
+    let pbr_dimension = pbr_cells.chunk_dimension_tiles;
+    // PbrCell chunks unloaded and loaded again (see `FocusPolicy::FollowCameraWindow`) come back
+    // generated, without the cells copied from passability
+    let reloaded: Vec<ChunkCoords> = pbr_loaded_events
+        .read()
+        .map(|e| ChunkCoords::from_point(e.coords.to_bottom_left_tile_point(pbr_dimension), dimension))
+        .collect();
+    let changed = loaded_events
+        .read()
+        .map(|e| e.coords)
+        .chain(written_events.read().map(|e| e.coords))
+        .chain(reloaded);
+    for coords in changed {
+        let Some(chunk) = passability.loaded_chunks.get(&coords) else {
+            continue; // Unloaded again before we got to it
+        };
+        let origin = coords.to_bottom_left_tile_point(dimension);
+        if pbr_cells.read(origin).is_none() {
+            continue; // Copied when the PbrCell chunk loads, rather than queued until then
+        }
+        let cells = FlatGrid::from_fn(dimension, |x, y| {
+            let tile = chunk.grid.get_item(x, y).copied().unwrap_or_default();
+            PbrCell::from_passability(tile)
+        });
+        pbr_cells.write_area(origin, &cells);
+    }
+}
+
+/// Debug tilemap layer showing fog: cells scattering more than open air get a gray veil.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PbrFogColorizer;
+
+impl TileColorizer<PbrCellProducer> for PbrFogColorizer {
+    fn color(&self, item: PbrCell, _point: Point) -> [u8; 4] {
+        let density = (item.scattering - PbrCell::default().scattering).max(0.0);
+        [200, 200, 210, (density * 255.0) as u8]
+    }
+}
+
 impl MapDataProducer for PbrCellProducer {
     type Item = PbrCell;
     type GridType = FlatGrid<PbrCell>;
@@ -99,6 +190,38 @@ impl MapDataProducer for PbrCellProducer {
             }
         }
 
-        DataChunk { grid }
+        DataChunk::new(grid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn absorbtion(passability: u8) -> f32 {
+        PbrCell::from_passability(Passability(passability)).absorbtion
+    }
+
+    #[test]
+    fn passability_maps_to_light_materials_at_the_boundaries() {
+        assert_eq!(PbrCell::from_passability(Passability(0)), PbrCell::SOLID_WALL);
+        assert_eq!(PbrCell::from_passability(Passability(Passability::WALL_THRESHOLD - 1)), PbrCell::SOLID_WALL);
+
+        // First tile of the falloff band lets some light in, but far less than air
+        let band = PbrCell::from_passability(Passability(Passability::WALL_THRESHOLD));
+        assert!(band.transparent);
+        assert!((band.absorbtion - 0.9).abs() < 1e-6, "{}", band.absorbtion);
+
+        let air = PbrCell::default();
+        assert!(absorbtion(250) > air.absorbtion && absorbtion(250) < air.absorbtion + 0.02, "{}", absorbtion(250));
+        let free = PbrCell::from_passability(Passability::FREE);
+        assert!((free.absorbtion - air.absorbtion).abs() < 1e-6, "{}", free.absorbtion);
+        assert_eq!(PbrCell { absorbtion: air.absorbtion, ..free }, air);
+    }
+
+    #[test]
+    fn absorbtion_falls_off_monotonically_away_from_walls() {
+        let values: Vec<f32> = (Passability::WALL_THRESHOLD..=255).map(absorbtion).collect();
+        assert!(values.windows(2).all(|w| w[0] >= w[1]));
     }
 }
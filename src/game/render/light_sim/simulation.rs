@@ -7,50 +7,204 @@ use rand::{Rng, random_bool};
 
 use bevy::prelude::*;
 
+use bevy::render::render_resource::Extent3d;
+use bevy::tasks::{AsyncComputeTaskPool, Task};
+use futures_lite::future;
+
 use crate::{
-    core::{basics::Point, chunks::DataMap, constants::TILE_SIZE_IN_UNITS_UNITS},
-    game::render::{
-        blending::MultiplyBlendMaterial,
-        light_sim::{
-            color_utils,
-            directions::Direction,
-            lighting::{
-                LIGHTING_OVERLAY_TILES, LightOverlayMaterialHandle, LightOverlayTextureHandle,
-                OverlayImage,
+    core::{
+        basics::Point,
+        chunks::{ChunkCoords, DataMap, FlatGrid, GridData, MapDataProducer},
+        constants::{DEFAULT_CHUNK_DIMENSION_TILES, TILE_SIZE_IN_UNITS},
+        units::{TileCoord, TilesCount, WorldPos},
+    },
+    game::{
+        render::{
+            light_sim::{
+                color_utils,
+                directions::Direction,
+                lighting::{
+                    GlobalAmbientLight, LIGHTING_APRON_TILES, LIGHTING_OVERLAY_TILES,
+                    LightOverlayTextureHandle, LightingMode, OverlayImage,
+                },
+                lights::DirectedLightEmitter,
+                lights_map::{LightEmitterCell, LightsMapProducer},
+                pbr_cell::{PbrCell, PbrCellProducer},
             },
-            lights_map::LightsMapProducer,
-            pbr_cell::PbrCellProducer,
         },
+        required::WarnOnce,
     },
 };
 
+/// How the eight directional buffers are combined into one color per tile.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LightCompositeMode {
+    #[default]
+    Sum, // Sum of all directions, clamped to 1.0 per channel
+    Max, // Brightest direction per channel
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct LightSimSettings {
+    pub composite_mode: LightCompositeMode,
+    pub steps: usize,           // Steps of a full simulation, run only when its inputs changed
+    pub incremental: bool,      // Runs `steps_per_frame` steps per frame on the main thread until `steps` are done
+    pub steps_per_frame: usize, // Only with `incremental`, for windows too large to simulate in one go
+    pub async_simulation: bool, // false runs the simulation synchronously in PostUpdate, for debugging
+    pub max_age_frames: u64,    // In-flight simulations older than this are dropped once the camera moved a chunk
+    pub pixels_per_tile: u32,   // Overlay texels per tile (1, 2 or 4), interpolated from the per-tile results
+    pub overlay_tiles: TilesCount, // Side of the lit window; changing it reallocates the overlay
+    pub apron_tiles: TilesCount,   // Simulated around the window on each side, so lights just outside still shine in
+}
+
+impl Default for LightSimSettings {
+    fn default() -> Self {
+        Self {
+            composite_mode: LightCompositeMode::Sum,
+            steps: 10,
+            incremental: false,
+            steps_per_frame: 2,
+            async_simulation: true,
+            max_age_frames: 30,
+            pixels_per_tile: 1,
+            overlay_tiles: LIGHTING_OVERLAY_TILES,
+            apron_tiles: LIGHTING_APRON_TILES,
+        }
+    }
+}
+
+/// Composited light of the last simulation result, per tile and without ambient light,
+/// for CPU-side readers like `LitSprite`. Covers the simulated area, apron included.
+#[derive(Resource, Debug, Clone)]
+pub struct LightFieldSnapshot {
+    pub origin: Point, // World tile of the bottom-left cell
+    pub data: FlatGrid<[f32; 3]>,
+}
+
+impl Default for LightFieldSnapshot {
+    fn default() -> Self {
+        Self {
+            origin: Point { x: 0, y: 0 },
+            data: FlatGrid::new(0, [0.0; 3]),
+        }
+    }
+}
+
+impl LightFieldSnapshot {
+    /// Light of a tile, None outside the snapshot.
+    pub fn read(&self, tile: Point) -> Option<Vec3> {
+        let (x, y) = (tile.x - self.origin.x, tile.y - self.origin.y);
+        if x < 0 || y < 0 {
+            return None;
+        }
+        self.data.get_item(x as usize, y as usize).map(|rgb| Vec3::from_array(*rgb))
+    }
+
+    /// Light at a world position, bilinearly interpolated between tile centers.
+    /// Tiles outside the snapshot count as dark.
+    pub fn sample(&self, pos: WorldPos) -> Option<Vec3> {
+        let f = pos.0 / TILE_SIZE_IN_UNITS;
+        let (x0, y0) = (f.x.floor() as isize, f.y.floor() as isize);
+        let (tx, ty) = (f.x - f.x.floor(), f.y - f.y.floor());
+        let at = |dx: isize, dy: isize| self.read(Point { x: x0 + dx, y: y0 + dy });
+        if [at(0, 0), at(1, 0), at(0, 1), at(1, 1)].iter().all(Option::is_none) {
+            return None;
+        }
+        let at = |dx, dy| at(dx, dy).unwrap_or(Vec3::ZERO);
+        let bottom = at(0, 0).lerp(at(1, 0), tx);
+        let top = at(0, 1).lerp(at(1, 1), tx);
+        Some(bottom.lerp(top, ty))
+    }
+}
+
+/// Inclusive rectangle of buffer cells.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MinMaxRect {
+    pub min: (usize, usize),
+    pub max: (usize, usize),
+}
+
+impl MinMaxRect {
+    pub fn point(x: usize, y: usize) -> Self {
+        Self {
+            min: (x, y),
+            max: (x, y),
+        }
+    }
+
+    pub fn include(&mut self, x: usize, y: usize) {
+        self.min = (self.min.0.min(x), self.min.1.min(y));
+        self.max = (self.max.0.max(x), self.max.1.max(y));
+    }
+}
+
+/// Grows the active rect of a direction plane to cover a cell.
+#[inline(always)]
+fn mark_active(bounds: &mut Option<MinMaxRect>, x: usize, y: usize) {
+    match bounds {
+        Some(rect) => rect.include(x, y),
+        None => *bounds = Some(MinMaxRect::point(x, y)),
+    }
+}
+
+/// Adds energy to a cell of a direction plane, keeping its active rect up to date.
+#[inline(always)]
+fn add_energy(
+    write: &mut [Vec<Vec<Vec3>>; 8],
+    write_bounds: &mut [Option<MinMaxRect>; 8],
+    direction: Direction,
+    (x, y): (usize, usize),
+    energy: Vec3,
+) {
+    if energy == Vec3::ZERO {
+        return;
+    }
+    write[direction as usize][x][y] += energy;
+    mark_active(&mut write_bounds[direction as usize], x, y);
+}
+
 #[derive(Resource)]
 pub struct LightingBuffers {
-    pub read: [Vec<Vec<glam::Vec3>>; 8],
-    pub write: [Vec<Vec<glam::Vec3>>; 8],
+    pub read: [Vec<Vec<Vec3>>; 8],
+    pub write: [Vec<Vec<Vec3>>; 8],
+    // Per direction, the rect outside of which the plane is all zeros; None for an empty plane
+    pub read_bounds: [Option<MinMaxRect>; 8],
+    pub write_bounds: [Option<MinMaxRect>; 8],
+    pub lit: [Vec<Vec<Vec3>>; 8], // Energy that passed through each cell so far, per direction; what gets drawn
+    pub escaped: [Vec3; 8], // Energy that left the simulated area this frame, per direction
     pub initialized: bool,
 }
 
 impl LightingBuffers {
     /// Initializes LightingBuffers with correctly sized zeroed buffers.
     pub fn init(&mut self, write_size: usize) {
-        let blank_tile = || vec![vec![glam::Vec3::ZERO; write_size]; write_size];
+        let blank_tile = || vec![vec![Vec3::ZERO; write_size]; write_size];
         self.read = std::array::from_fn(|_| blank_tile());
         self.write = std::array::from_fn(|_| blank_tile());
+        self.lit = std::array::from_fn(|_| blank_tile());
+        self.read_bounds = [None; 8];
+        self.write_bounds = [None; 8];
         self.initialized = true;
-        info!("Double buffer for light sim initialized");
+    }
+
+    /// Adds energy to the write buffer.
+    pub fn add(&mut self, direction: Direction, x: usize, y: usize, energy: Vec3) {
+        add_energy(&mut self.write, &mut self.write_bounds, direction, (x, y), energy);
     }
 
     /// Swaps read/write and zeroes out the write buffer in-place.
+    /// Only the active rects are cleared, the rest of each plane is zero already.
     #[inline(always)]
     pub fn swap_buffers_clear_write(&mut self) {
         std::mem::swap(&mut self.read, &mut self.write);
+        std::mem::swap(&mut self.read_bounds, &mut self.write_bounds);
 
-        for dir_buf in self.write.iter_mut() {
-            for row in dir_buf.iter_mut() {
-                for px in row.iter_mut() {
-                    *px = glam::Vec3::ZERO;
-                }
+        for (dir_buf, bounds) in self.write.iter_mut().zip(self.write_bounds.iter_mut()) {
+            let Some(rect) = bounds.take() else {
+                continue;
+            };
+            for column in &mut dir_buf[rect.min.0..=rect.max.0] {
+                column[rect.min.1..=rect.max.1].fill(Vec3::ZERO);
             }
         }
     }
@@ -61,95 +215,620 @@ impl Default for LightingBuffers {
         Self {
             read: std::array::from_fn(|_| vec![]),
             write: std::array::from_fn(|_| vec![]),
+            read_bounds: [None; 8],
+            write_bounds: [None; 8],
+            lit: std::array::from_fn(|_| vec![]),
+            escaped: [Vec3::ZERO; 8],
             initialized: false,
         }
     }
 }
 
-pub fn run_lights_simulation(
-    light_texture_handle: Res<LightOverlayTextureHandle>,
-    mut images: ResMut<Assets<Image>>,
-    lightsources: Res<DataMap<LightsMapProducer>>,
-    pbr_cells: Res<DataMap<PbrCellProducer>>,
-    texture_world_position: Query<&Transform, With<OverlayImage>>,
-    mut buffer: Local<LightingBuffers>,
-    light_material_handle: Res<LightOverlayMaterialHandle>,
-    mut materials: ResMut<Assets<MultiplyBlendMaterial>>,
-) {
-    // initialize buffers (if not yet initialized)
-    if !buffer.initialized {
-        buffer.init(LIGHTING_OVERLAY_TILES);
-    }
-    // read lights map and produce a buffer for all directions
-    let world_position_opt = texture_world_position.single();
-    if let Ok(texture_position) = world_position_opt {
-        // Convert overlay texture center world position (Vec3) to tile coordinates
-        let center_world_x = texture_position.translation.x as isize;
-        let center_world_y = texture_position.translation.y as isize;
-
-        let center_tile = Point {
-            x: center_world_x / TILE_SIZE_IN_UNITS_UNITS,
-            y: center_world_y / TILE_SIZE_IN_UNITS_UNITS,
-        };
-
-        // Calculate top-left tile of the overlay area
-        let half_tiles = (LIGHTING_OVERLAY_TILES / 2) as isize;
-        let top_left = Point {
-            x: center_tile.x - half_tiles,
-            y: center_tile.y - half_tiles,
-        };
+/// Everything the simulation needs, copied out of the DataMaps so it can run off the main thread.
+pub struct LightSimSnapshot {
+    pub origin: Point, // Bottom-left tile of the simulated area (overlay plus apron)
+    pub size: usize,
+    pub emitters: Vec<Vec<LightEmitterCell>>,
+    pub pbr: Vec<Vec<PbrCell>>,
+}
 
-        // Fill the 2D overlay buffer
-        for x in 0..LIGHTING_OVERLAY_TILES {
-            for y in 0..LIGHTING_OVERLAY_TILES {
+impl LightSimSnapshot {
+    pub fn capture(
+        lightsources: &DataMap<LightsMapProducer>,
+        pbr_cells: &DataMap<PbrCellProducer>,
+        origin: Point,
+        size: usize,
+    ) -> Self {
+        let mut emitters = vec![vec![LightEmitterCell::default(); size]; size];
+        let mut pbr = vec![vec![PbrCell::default(); size]; size];
+        for x in 0..size {
+            for y in 0..size {
                 let tile_position = Point {
-                    x: top_left.x + x as isize,
-                    y: top_left.y + y as isize,
+                    x: origin.x + x as isize,
+                    y: origin.y + y as isize,
                 };
+                emitters[x][y] = lightsources.read(tile_position).unwrap_or_default();
+                pbr[x][y] = pbr_cells.read(tile_position).unwrap_or_default();
+            }
+        }
+        Self {
+            origin,
+            size,
+            emitters,
+            pbr,
+        }
+    }
+
+    /// Seeds fresh buffers with the emitters and runs `steps` simulation steps.
+    pub fn simulate(&self, steps: usize) -> LightingBuffers {
+        let mut buffer = self.seed();
+        simulate_directions(&mut buffer, steps, &self.pbr);
+        buffer
+    }
 
-                let center_tile_lightdata_cell =
-                    lightsources.read(tile_position).unwrap_or_default();
+    /// Fresh buffers holding the emitters' light, before any simulation step.
+    pub fn seed(&self) -> LightingBuffers {
+        let mut buffer = LightingBuffers::default();
+        buffer.init(self.size);
 
-                let center_tile_lightdata = center_tile_lightdata_cell.undirected_lights;
+        for x in 0..self.size {
+            for y in 0..self.size {
+                let cell = self.emitters[x][y];
 
-                if let Some(light) = center_tile_lightdata {
-                    // info!("Light detected: {x},{y}: {:?}", light.props.color);
+                if let Some(light) = cell.undirected_lights {
                     for dir in Direction::ALL {
-                        buffer.write[dir as usize][x][y] = light.props.color.into();
+                        buffer.add(dir, x, y, Vec3::from(light.props));
+                    }
+                }
+
+                if let Some(light) = cell.directed_lights {
+                    let energy = Vec3::from(light.props);
+                    for (dir, steps) in light.direction.neighbors_within(light.spread) {
+                        buffer.add(dir, x, y, energy * DirectedLightEmitter::falloff(steps));
                     }
                 }
             }
         }
         buffer.swap_buffers_clear_write();
+        buffer
+    }
+}
+
+/// Everything a simulation result depends on. While it doesn't change, neither would the result.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LightSimKey {
+    origin: Point,
+    size: usize,
+    steps: usize,
+    chunk_versions: Vec<Option<u64>>, // Lights map chunks, then PbrCell chunks, under the simulated area
+}
+
+impl LightSimKey {
+    pub fn capture(
+        lightsources: &DataMap<LightsMapProducer>,
+        pbr_cells: &DataMap<PbrCellProducer>,
+        origin: Point,
+        size: usize,
+        steps: usize,
+    ) -> Self {
+        let mut chunk_versions = Vec::new();
+        window_chunk_versions(lightsources, origin, size, &mut chunk_versions);
+        window_chunk_versions(pbr_cells, origin, size, &mut chunk_versions);
+        Self {
+            origin,
+            size,
+            steps,
+            chunk_versions,
+        }
+    }
+}
+
+// Versions of the chunks under a `size` wide square at `origin`, row by row
+fn window_chunk_versions<P: MapDataProducer>(
+    map: &DataMap<P>,
+    origin: Point,
+    size: usize,
+    versions: &mut Vec<Option<u64>>,
+) {
+    let dimension = map.chunk_dimension_tiles;
+    let first = ChunkCoords::from_point(origin, dimension);
+    let last = ChunkCoords::from_point(
+        Point {
+            x: origin.x + size as isize - 1,
+            y: origin.y + size as isize - 1,
+        },
+        dimension,
+    );
+    for y in first.y..=last.y {
+        for x in first.x..=last.x {
+            versions.push(map.chunk_version(ChunkCoords { x, y }));
+        }
+    }
+}
+
+// What the overlay image was composited with, besides the simulation result
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct OverlayInputs {
+    ambient: Vec3,
+    encoding: OverlayEncoding,
+    composite_mode: LightCompositeMode,
+    pixels_per_tile: u32,
+}
+
+// Simulation run a few steps per frame, see `LightSimSettings::incremental`
+struct IncrementalSim {
+    snapshot: LightSimSnapshot,
+    buffers: LightingBuffers,
+    steps_done: usize,
+    center: Vec3,
+}
+
+/// Whether the light simulation ran in the last frame, and how far the current one got.
+#[derive(Resource, Debug, Clone, Copy, Default)]
+pub struct LightSimStats {
+    pub ran_this_frame: bool, // A simulation was started, or stepped in incremental mode
+    pub steps_done: usize,
+    pub steps_total: usize,
+    pub skipped_frames: u64, // Frames whose inputs hadn't changed, so nothing was simulated
+}
+
+#[derive(Debug, Clone, Copy)]
+struct LightSimJob {
+    center: Vec3, // Overlay position the snapshot was taken for
+    origin: Point,
+    tiles: TilesCount, // Window size, results for another size are dropped
+    frame: u64,
+}
+
+/// Simulation in flight and the window currently shown in the overlay.
+#[derive(Resource)]
+pub struct LightSimState {
+    task: Option<(Task<LightingBuffers>, LightSimJob)>,
+    displayed_center: Option<Vec3>, // Where the overlay image content belongs, None while it holds nothing valid
+    window_tiles: TilesCount,       // Side of the overlay mesh and image, follows `LightSimSettings::overlay_tiles`
+    frame: u64,
+    simulated_key: Option<LightSimKey>, // Inputs of the last simulation started, nothing is simulated while they hold
+    last_result: Option<(LightingBuffers, Vec3)>, // Kept to recomposite the image when only the ambient light changes
+    drawn_inputs: Option<OverlayInputs>,
+    incremental: Option<IncrementalSim>,
+    target_center: Option<Vec2>, // Where the window should be, set by overlay_texture_follow_system
+    pending_swap: Option<Vec3>,  // The back image was written this frame for a window centered here
+}
+
+impl Default for LightSimState {
+    fn default() -> Self {
+        Self {
+            task: None,
+            displayed_center: None,
+            window_tiles: LIGHTING_OVERLAY_TILES,
+            frame: 0,
+            simulated_key: None,
+            last_result: None,
+            drawn_inputs: None,
+            incremental: None,
+            target_center: None,
+            pending_swap: None,
+        }
+    }
+}
+
+impl LightSimState {
+    pub fn window_tiles(&self) -> TilesCount {
+        self.window_tiles
+    }
+
+    /// Switches to a window of another size: the simulation in flight and the displayed image are dropped.
+    pub fn reset_window(&mut self, tiles: TilesCount) {
+        self.task = None;
+        self.displayed_center = None;
+        self.window_tiles = tiles;
+        self.simulated_key = None;
+        self.last_result = None;
+        self.drawn_inputs = None;
+        self.incremental = None;
+        self.pending_swap = None;
+    }
+
+    /// Moves the window to `center`, on the next simulation frame.
+    pub fn set_target_center(&mut self, center: Vec2) {
+        self.target_center = Some(center);
+    }
+
+    /// Marks the back image as complete, for a window centered at `center`. Shown by the next swap.
+    pub fn set_pending_swap(&mut self, center: Vec3) {
+        self.pending_swap = Some(center);
+    }
+
+    /// Window center of the back image if it was written since the last swap, see `LightOverlayTextureHandle`.
+    pub fn take_pending_swap(&mut self) -> Option<Vec3> {
+        self.pending_swap.take()
+    }
+}
+
+/// Center of a `tiles` wide overlay around `center_tile`, so that its edges fall on tile edges.
+/// With an even width `center_tile` is the one up and right of the middle.
+pub fn window_center(center_tile: TileCoord, tiles: TilesCount) -> WorldPos {
+    let half_tiles = (tiles / 2) as isize;
+    let bottom_left = center_tile - Point { x: half_tiles, y: half_tiles };
+    bottom_left.corner() + Vec2::splat(tiles as f32 * TILE_SIZE_IN_UNITS / 2.0)
+}
 
-        // dummy simulation logic - do nothing for now
-        simulate_directions(&mut buffer, 10, &pbr_cells);
-
-        // render result
-        let image = images
-            .get_mut(&light_texture_handle.0)
-            .expect("Image not found");
-        for x in 0..LIGHTING_OVERLAY_TILES {
-            for y in 0..LIGHTING_OVERLAY_TILES {
-                let total_px = LIGHTING_OVERLAY_TILES as usize;
-                let color = Color::from(Srgba::from_f32_array_no_alpha(
-                    buffer.read[Direction::E as usize][x][y].into(),
-                ));
-                let x_coords = x;
-                let y_coords: isize = total_px as isize - (y as isize + 1);
-                image
-                    .set_color_at(x_coords as u32, y_coords as u32, color)
-                    .unwrap();
+/// Bottom-left tile of the simulated area for a `tiles` wide overlay centered at `center`.
+fn simulation_origin(center: Vec3, tiles: TilesCount, apron: usize) -> Point {
+    let half_size = Vec2::splat(tiles as f32 * TILE_SIZE_IN_UNITS / 2.0);
+    let bottom_left = TileCoord::from_corner(WorldPos(center.xy()) - half_size);
+    let apron = apron as isize;
+    (bottom_left - Point { x: apron, y: apron }).0
+}
+
+/// Simulates the light around the overlay and writes it into the back overlay image, only when the
+/// emitters, the PbrCells or the window moved since the last simulation. The image on screen is
+/// never touched, `swap_overlay_textures_system` shows the back one once it's complete.
+#[allow(clippy::too_many_arguments)]
+pub fn run_lights_simulation(
+    textures: Option<Res<LightOverlayTextureHandle>>,
+    mut images: ResMut<Assets<Image>>,
+    lightsources: Res<DataMap<LightsMapProducer>>,
+    pbr_cells: Res<DataMap<PbrCellProducer>>,
+    mut texture_world_position: Query<(&mut Transform, &Visibility), With<OverlayImage>>,
+    settings: Res<LightSimSettings>,
+    ambient: Res<GlobalAmbientLight>,
+    mode: Res<LightingMode>,
+    mut state: ResMut<LightSimState>,
+    mut stats: ResMut<LightSimStats>,
+    mut light_field: ResMut<LightFieldSnapshot>,
+    mut missing_overlay: Local<WarnOnce>,
+) {
+    stats.ran_this_frame = false;
+    state.frame += 1;
+    let frame = state.frame;
+
+    // the simulated area is the visible overlay plus an apron on each side
+    let apron = settings.apron_tiles;
+    let tiles = state.window_tiles;
+    let sim_tiles = tiles + 2 * apron;
+
+    let Ok((mut texture_transform, visibility)) = texture_world_position.single_mut() else {
+        return; // Reported by verify_required_entities
+    };
+    let Some(textures) = textures else {
+        missing_overlay.warn(|| "The light overlay handles are missing, is setup_overlay running?".to_string());
+        return;
+    };
+    if !images.contains(&textures.front) || !images.contains(&textures.back) {
+        missing_overlay.warn(|| "The light overlay image assets are gone, the light won't be drawn".to_string());
+        return;
+    }
+    // Set by overlay_texture_follow_system this frame
+    let z = texture_transform.translation.z;
+    let target_center = state.target_center.map_or(texture_transform.translation, |center| center.extend(z));
+    if *visibility == Visibility::Hidden {
+        texture_transform.translation = target_center; // Nothing shown, culling still needs it to follow
+        return; // Culled, nobody would see the result
+    }
+    let origin = simulation_origin(target_center, tiles, apron);
+    if !settings.incremental && state.incremental.take().is_some() {
+        state.simulated_key = None; // Switched modes half way, the other mode starts over
+    }
+    let key = LightSimKey::capture(&lightsources, &pbr_cells, origin, sim_tiles, settings.steps);
+    let inputs_changed = state.simulated_key.as_ref() != Some(&key);
+
+    let mut finished = None;
+    let mut ran = false;
+    stats.steps_total = settings.steps;
+    if settings.incremental {
+        state.task = None;
+        if inputs_changed {
+            // Restarted from scratch, a half converged result for other inputs is worthless
+            let snapshot = LightSimSnapshot::capture(&lightsources, &pbr_cells, origin, sim_tiles);
+            state.incremental = Some(IncrementalSim {
+                buffers: snapshot.seed(),
+                snapshot,
+                steps_done: 0,
+                center: target_center,
+            });
+            state.simulated_key = Some(key);
+        }
+        if let Some(sim) = state.incremental.as_mut() {
+            let steps = settings.steps_per_frame.max(1).min(settings.steps.saturating_sub(sim.steps_done));
+            simulate_directions(&mut sim.buffers, steps, &sim.snapshot.pbr);
+            sim.steps_done += steps;
+            stats.steps_done = sim.steps_done;
+            ran = true;
+            if sim.steps_done >= settings.steps {
+                finished = state.incremental.take().map(|sim| (sim.buffers, sim.center));
+            }
+        }
+    } else if settings.async_simulation {
+        let mut drop_task = false;
+        if let Some((task, job)) = state.task.as_mut() {
+            if let Some(buffers) = future::block_on(future::poll_once(task)) {
+                // Simulated for a window of another size, useless now
+                if job.tiles == tiles {
+                    finished = Some((buffers, job.center));
+                }
+                drop_task = true;
+            } else {
+                // Too old and for an area the camera has already left
+                let age = frame - job.frame;
+                let moved = (job.origin.x - origin.x)
+                    .abs()
+                    .max((job.origin.y - origin.y).abs());
+                if age > settings.max_age_frames && moved > DEFAULT_CHUNK_DIMENSION_TILES as isize
+                {
+                    drop_task = true; // dropping a Task cancels it
+                }
+            }
+        }
+        if drop_task {
+            state.task = None;
+        }
+
+        if state.task.is_none() && inputs_changed {
+            let snapshot = LightSimSnapshot::capture(&lightsources, &pbr_cells, origin, sim_tiles);
+            let steps = settings.steps;
+            let task = AsyncComputeTaskPool::get().spawn(async move { snapshot.simulate(steps) });
+            let job = LightSimJob {
+                center: target_center,
+                origin,
+                tiles,
+                frame,
+            };
+            state.task = Some((task, job));
+            state.simulated_key = Some(key);
+            ran = true;
+        }
+        // Tasks don't report progress, they're either running or done
+        stats.steps_done = if state.task.is_some() { 0 } else { settings.steps };
+    } else if inputs_changed {
+        let snapshot = LightSimSnapshot::capture(&lightsources, &pbr_cells, origin, sim_tiles);
+        finished = Some((snapshot.simulate(settings.steps), target_center));
+        state.simulated_key = Some(key);
+        stats.steps_done = settings.steps;
+        ran = true;
+    }
+    stats.ran_this_frame = ran;
+    if !ran && finished.is_none() {
+        stats.skipped_frames += 1;
+    }
+
+    // render result, or the previous one again if only the compositing inputs changed
+    let (ambient, encoding) = overlay_ambient(&mode, &ambient);
+    let inputs = OverlayInputs {
+        ambient,
+        encoding,
+        composite_mode: settings.composite_mode,
+        pixels_per_tile: settings.pixels_per_tile,
+    };
+    let redraw = finished.is_some() || state.drawn_inputs != Some(inputs);
+    if finished.is_some() {
+        state.last_result = finished;
+    }
+    let state = &mut *state;
+    let mut back_written = false;
+    if let (true, Some((buffers, center))) = (redraw, state.last_result.as_ref()) {
+        let Some(image) = images.get_mut(&textures.back) else {
+            return; // Checked above
+        };
+        write_overlay_image(
+            &buffers.lit,
+            (apron, apron),
+            tiles,
+            settings.pixels_per_tile,
+            settings.composite_mode,
+            ambient,
+            encoding,
+            image,
+        );
+        back_written = true;
+        state.displayed_center = Some(*center);
+        state.drawn_inputs = Some(inputs);
+        light_field.origin = simulation_origin(*center, tiles, apron);
+        light_field.data = FlatGrid::from_fn(buffers.lit[0].len(), |x, y| {
+            composite_tile(&buffers.lit, x, y, settings.composite_mode).to_array()
+        });
+    }
+
+    // The window follows its target right away: the displayed light is scrolled along so it
+    // stays on its tiles, and what comes into view is plain ambient until the next result
+    if let Some(displayed) = state.displayed_center {
+        let delta = ((target_center - displayed).xy() / TILE_SIZE_IN_UNITS).round();
+        if delta != Vec2::ZERO {
+            if !back_written {
+                copy_front_to_back(&mut images, &textures);
+            }
+            let Some(image) = images.get_mut(&textures.back) else {
+                return;
+            };
+            let delta = (delta.x as isize, delta.y as isize);
+            scroll_overlay_image(image, delta, settings.pixels_per_tile, ambient, encoding);
+            back_written = true;
+        }
+        state.displayed_center = Some(target_center);
+    } else {
+        texture_transform.translation = target_center; // Nothing valid shown yet, no content to line up with
+    }
+    if back_written {
+        state.set_pending_swap(target_center);
+    }
+}
+
+/// Makes the back overlay image a copy of the front one, which is a swap ahead of it.
+fn copy_front_to_back(images: &mut Assets<Image>, textures: &LightOverlayTextureHandle) {
+    let Some(front) = images.get(&textures.front).cloned() else {
+        return;
+    };
+    if let Some(back) = images.get_mut(&textures.back) {
+        *back = front;
+    }
+}
+
+/// Ambient light baked into the overlay texture for a lighting mode, and how it's encoded.
+/// The tinted material adds the ambient light itself.
+pub fn overlay_ambient(mode: &LightingMode, ambient: &GlobalAmbientLight) -> (Vec3, OverlayEncoding) {
+    if mode.is_tinted() {
+        (Vec3::ZERO, OverlayEncoding::Linear)
+    } else {
+        (ambient.energy(), OverlayEncoding::Raw)
+    }
+}
+
+/// Combines the directional energies of a single tile.
+pub fn composite_tile(
+    buffers: &[Vec<Vec<Vec3>>; 8],
+    x: usize,
+    y: usize,
+    mode: LightCompositeMode,
+) -> Vec3 {
+    match mode {
+        LightCompositeMode::Sum => buffers
+            .iter()
+            .map(|dir_buf| dir_buf[x][y])
+            .sum::<Vec3>()
+            .min(Vec3::ONE),
+        LightCompositeMode::Max => buffers
+            .iter()
+            .map(|dir_buf| dir_buf[x][y])
+            .fold(Vec3::ZERO, Vec3::max),
+    }
+}
+
+/// How light energy is stored in the sRGB overlay texture.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverlayEncoding {
+    Raw,    // Energy written as the sRGB byte value, for the plain multiply overlay
+    Linear, // Energy encoded so the sampled value is the linear energy
+}
+
+/// Composites a `size`x`size` window of the buffers, starting at `offset`, into the overlay image,
+/// adding the ambient light. With more than one pixel per tile, texels are bilinearly interpolated
+/// between tile centers, using the tiles around the window (the apron) at the edges.
+/// The image is resized if it doesn't match `size * pixels_per_tile`.
+#[allow(clippy::too_many_arguments)]
+pub fn write_overlay_image(
+    buffers: &[Vec<Vec<Vec3>>; 8],
+    offset: (usize, usize),
+    size: usize,
+    pixels_per_tile: u32,
+    mode: LightCompositeMode,
+    ambient: Vec3,
+    encoding: OverlayEncoding,
+    image: &mut Image,
+) {
+    let pixels_per_tile = pixels_per_tile.max(1) as usize;
+    let size_px = size * pixels_per_tile;
+    if image.width() as usize != size_px || image.height() as usize != size_px {
+        image.resize(Extent3d {
+            width: size_px as u32,
+            height: size_px as u32,
+            depth_or_array_layers: 1,
+        });
+    }
+
+    // Composited tiles of the window plus one tile on each side, as far as the buffers reach
+    let bound = buffers[0].len();
+    let first = (offset.0.saturating_sub(1), offset.1.saturating_sub(1));
+    let last = ((offset.0 + size).min(bound - 1), (offset.1 + size).min(bound - 1));
+    let width = last.0 - first.0 + 1;
+    let mut tiles = Vec::with_capacity(width * (last.1 - first.1 + 1));
+    for y in first.1..=last.1 {
+        for x in first.0..=last.0 {
+            tiles.push((ambient + composite_tile(buffers, x, y, mode)).min(Vec3::ONE));
+        }
+    }
+    let tile_at = |x: isize, y: isize| {
+        let x = (x + offset.0 as isize).clamp(first.0 as isize, last.0 as isize) as usize - first.0;
+        let y = (y + offset.1 as isize).clamp(first.1 as isize, last.1 as isize) as usize - first.1;
+        tiles[y * width + x]
+    };
+
+    let Some(data) = image.data.as_mut() else {
+        return;
+    };
+    for py in 0..size_px {
+        // Window tile coordinates of the texel center, relative to the tile centers
+        let v = (py as f32 + 0.5) / pixels_per_tile as f32 - 0.5;
+        let (y0, ty) = (v.floor() as isize, v - v.floor());
+        for px in 0..size_px {
+            let u = (px as f32 + 0.5) / pixels_per_tile as f32 - 0.5;
+            let (x0, tx) = (u.floor() as isize, u - u.floor());
+            let bottom = tile_at(x0, y0).lerp(tile_at(x0 + 1, y0), tx);
+            let top = tile_at(x0, y0 + 1).lerp(tile_at(x0 + 1, y0 + 1), tx);
+            let energy = bottom.lerp(top, ty);
+
+            // image rows grow downwards
+            let index = ((size_px - 1 - py) * size_px + px) * 4;
+            data[index..index + 4].copy_from_slice(&encode_texel(energy, encoding));
+        }
+    }
+}
+
+/// Overlay texel bytes for a light energy.
+fn encode_texel(energy: Vec3, encoding: OverlayEncoding) -> [u8; 4] {
+    let energy = match encoding {
+        OverlayEncoding::Raw => energy,
+        OverlayEncoding::Linear => {
+            let srgb = Srgba::from(LinearRgba::rgb(energy.x, energy.y, energy.z));
+            Vec3::from_array(srgb.to_f32_array_no_alpha())
+        }
+    };
+    let [r, g, b] = energy.to_array().map(|c| (c * 255.0).round() as u8);
+    [r, g, b, 255]
+}
+
+/// Fills the whole overlay image with one energy, resizing it to `size_px` first if needed.
+pub fn fill_overlay_image(image: &mut Image, size_px: u32, energy: Vec3, encoding: OverlayEncoding) {
+    if image.width() != size_px || image.height() != size_px {
+        image.resize(Extent3d {
+            width: size_px,
+            height: size_px,
+            depth_or_array_layers: 1,
+        });
+    }
+    let texel = encode_texel(energy, encoding);
+    if let Some(data) = image.data.as_mut() {
+        for chunk in data.chunks_exact_mut(4) {
+            chunk.copy_from_slice(&texel);
+        }
+    }
+}
+
+/// Moves the overlay content by the `delta_tiles` the window moved, so it stays on the same
+/// world tiles. Texels coming from outside the previous window are set to `fill`.
+pub fn scroll_overlay_image(
+    image: &mut Image,
+    delta_tiles: (isize, isize),
+    pixels_per_tile: u32,
+    fill: Vec3,
+    encoding: OverlayEncoding,
+) {
+    let pixels_per_tile = pixels_per_tile.max(1) as isize;
+    let (dx, dy) = (delta_tiles.0 * pixels_per_tile, delta_tiles.1 * pixels_per_tile);
+    let (width, height) = (image.width() as isize, image.height() as isize);
+    let fill = encode_texel(fill, encoding);
+    let Some(data) = image.data.as_mut() else {
+        return;
+    };
+    let previous = data.clone();
+    for row in 0..height {
+        for px in 0..width {
+            // image rows grow downwards, so moving up shifts the content down
+            let (from_px, from_row) = (px + dx, row - dy);
+            let index = ((row * width + px) * 4) as usize;
+            if (0..width).contains(&from_px) && (0..height).contains(&from_row) {
+                let from = ((from_row * width + from_px) * 4) as usize;
+                data[index..index + 4].copy_from_slice(&previous[from..from + 4]);
+            } else {
+                data[index..index + 4].copy_from_slice(&fill);
             }
         }
     }
-    materials.get_mut(&light_material_handle.0);
 }
 
 fn simulate_directions(
     mut buffer: &mut LightingBuffers,
     steps: usize,
-    pbr: &DataMap<PbrCellProducer>,
+    pbr: &[Vec<PbrCell>],
 ) {
     let bound_x = buffer.read[Direction::N as usize].len();
     let bound_y = bound_x; // assume we are in square area
@@ -157,7 +836,11 @@ fn simulate_directions(
         simulate_directions_step(
             step,
             &buffer.read,
+            &buffer.read_bounds,
             &mut buffer.write,
+            &mut buffer.write_bounds,
+            &mut buffer.lit,
+            &mut buffer.escaped,
             pbr,
             (bound_x, bound_y),
         );
@@ -165,48 +848,410 @@ fn simulate_directions(
     }
 }
 
+/// Adds energy to a neighbor cell, or to the escaped accumulator if the neighbor is outside the area.
+/// `get_next_from` saturates at 0, so a neighbor equal to the source cell also means "outside".
+/// A reflective neighbor sends its `reflection` share back into the opposite direction at the
+/// source cell, and a non-transparent neighbor blocks the rest.
+#[inline(always)]
+#[allow(clippy::too_many_arguments)]
+fn propagate_to(
+    write: &mut [Vec<Vec<Vec3>>; 8],
+    write_bounds: &mut [Option<MinMaxRect>; 8],
+    escaped: &mut [Vec3; 8],
+    pbr: &[Vec<PbrCell>],
+    direction: Direction,
+    from: (usize, usize),
+    to: (usize, usize),
+    bounds: (usize, usize),
+    energy: Vec3,
+) {
+    if to == from || to.0 >= bounds.0 || to.1 >= bounds.1 {
+        escaped[direction as usize] += energy;
+        return;
+    }
+    let target = pbr[to.0][to.1];
+    let reflected = energy * target.reflection;
+    add_energy(write, write_bounds, direction.opposite(), from, reflected);
+    add_energy(write, write_bounds, direction, to, energy - reflected);
+}
+
 const MIN_CUTOFF: f32 = 0.1;
 
-fn simulate_directions_step<'a>(
-    step: usize,
-    read: &[Vec<Vec<glam::Vec3>>; 8],
-    write: &mut [Vec<Vec<glam::Vec3>>; 8],
-    pbr: &DataMap<PbrCellProducer>,
+/// One step over the active rect of every direction plane; empty planes are skipped.
+/// Moves energy without creating any: what a cell doesn't absorb is scattered or passed on, so
+/// the planes never hold more than before the step. Where it went through is recorded in `lit`.
+#[allow(clippy::too_many_arguments)]
+fn simulate_directions_step(
+    _step: usize,
+    read: &[Vec<Vec<Vec3>>; 8],
+    read_bounds: &[Option<MinMaxRect>; 8],
+    write: &mut [Vec<Vec<Vec3>>; 8],
+    write_bounds: &mut [Option<MinMaxRect>; 8],
+    lit: &mut [Vec<Vec<Vec3>>; 8],
+    escaped: &mut [Vec3; 8],
+    pbr: &[Vec<PbrCell>],
     bounds: (usize, usize),
 ) {
     for direction in Direction::ALL {
+        let Some(active) = read_bounds[direction as usize] else {
+            continue;
+        };
         let current_direction_read = &read[direction as usize];
-        for x in 0..bounds.0 {
-            for y in 0..bounds.1 {
+        for x in active.min.0..=active.max.0 {
+            for y in active.min.1..=active.max.1 {
                 let current_energy = current_direction_read[x][y];
                 if current_energy.element_sum() < MIN_CUTOFF {
                     continue;
                 }
-                let cell = pbr.read((x, y).into()).unwrap_or_default();
+                let cell = pbr[x][y];
+                lit[direction as usize][x][y] += current_energy;
 
-                let absorbtion = cell.absorbtion;
                 // pass non-absorbed energy to next
-                let non_absorbed = current_energy * absorbtion;
-                write[direction as usize][x][y] += non_absorbed; // update self
+                let non_absorbed = current_energy * (1.0 - cell.absorbtion);
+                // scattered part turns 45° to both sides, the remainder keeps going forward
+                let scattered = non_absorbed * cell.scattering;
+                let forward = non_absorbed - scattered;
+                add_energy(write, write_bounds, direction.rotate_cw(), (x, y), scattered / 2.0);
+                add_energy(write, write_bounds, direction.rotate_ccw(), (x, y), scattered / 2.0);
+                if !cell.transparent {
+                    continue; // opaque cells are lit but cast a shadow, the forward part ends here
+                }
 
                 let nb = direction.get_next_from(x, y);
                 if direction.is_diagonal() {
-                    let divided = non_absorbed / 2.0;
-                    for (nx, ny) in nb {
+                    let divided = forward / 2.0;
+                    for next in nb {
                         // distribute across 2 points
-                        if (nx < bounds.0) && (ny < bounds.1) {
-                            write[direction as usize][nx][ny] += divided;
-                        }
+                        propagate_to(write, write_bounds, escaped, pbr, direction, (x, y), next, bounds, divided);
                     }
                 } else {
                     // For orthogonal directions, get_next_from returns the same neighbor twice.
                     // We only need to process it once.
-                    let (nx, ny) = nb[0]; // Take the first (and only unique) neighbor
-                    if (nx < bounds.0) && (ny < bounds.1) {
-                        write[direction as usize][nx][ny] += non_absorbed;
-                    }
+                    propagate_to(
+                        write,
+                        write_bounds,
+                        escaped,
+                        pbr,
+                        direction,
+                        (x, y),
+                        nb[0], // Take the first (and only unique) neighbor
+                        bounds,
+                        forward,
+                    );
                 }
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+    use crate::game::render::light_sim::lights::{LightDefinition, UndirectedLightEmitter};
+    use bevy::render::render_resource::Extent3d;
+
+    fn total_energy(buffers: &LightingBuffers) -> f32 {
+        buffers.read.iter().flatten().flatten().map(|energy| energy.element_sum()).sum()
+    }
+
+    /// A `size`x`size` field of `cell` with an omni light in the center, simulated for `steps`.
+    fn simulated_omni_light(size: usize, cell: PbrCell, steps: usize) -> LightingBuffers {
+        let mut buffers = LightingBuffers::default();
+        buffers.init(size);
+        for dir in Direction::ALL {
+            buffers.add(dir, size / 2, size / 2, Vec3::ONE);
+        }
+        buffers.swap_buffers_clear_write();
+        simulate_directions(&mut buffers, steps, &vec![vec![cell; size]; size]);
+        buffers
+    }
+
+    fn blank_image(size: usize) -> Image {
+        Image::new_fill(
+            Extent3d { width: size as u32, height: size as u32, depth_or_array_layers: 1 },
+            bevy::render::render_resource::TextureDimension::D2,
+            &[0, 0, 0, 255],
+            bevy::render::render_resource::TextureFormat::Rgba8UnormSrgb,
+            bevy::asset::RenderAssetUsages::MAIN_WORLD,
+        )
+    }
+
+    // Red byte of the texel at `x`,`y` counted from the bottom left, like the buffers
+    fn texel(image: &Image, x: usize, y: usize) -> u8 {
+        let size = image.width() as usize;
+        image.data.as_ref().unwrap()[((size - 1 - y) * size + x) * 4]
+    }
+
+    #[test]
+    fn omni_light_composites_into_a_symmetric_image() {
+        let size = 17;
+        let center = size / 2;
+        let buffers = simulated_omni_light(size, PbrCell::default(), 30);
+        let mut image = blank_image(size);
+        write_overlay_image(&buffers.lit, (0, 0), size, 1, LightCompositeMode::Sum, Vec3::ZERO, OverlayEncoding::Raw, &mut image);
+
+        for distance in 1..center {
+            // Every direction shows up, not only the light going east
+            let east = texel(&image, center + distance, center);
+            assert!(east > 0, "dark {distance} tiles from the light");
+            let mirrored = [
+                (center - distance, center),
+                (center, center + distance),
+                (center, center - distance),
+            ];
+            for (x, y) in mirrored {
+                assert!(texel(&image, x, y).abs_diff(east) <= 1, "{x}, {y} differs from {east} east");
+            }
+            let diagonal = texel(&image, center + distance, center + distance);
+            for (x, y) in [
+                (center - distance, center + distance),
+                (center + distance, center - distance),
+                (center - distance, center - distance),
+            ] {
+                assert!(texel(&image, x, y).abs_diff(diagonal) <= 1, "{x}, {y} differs from {diagonal}");
+            }
+            assert!(texel(&image, center + distance + 1, center) <= east, "brighter away from the light");
+        }
+    }
+
+    // Passes everything straight on, so only the walls under test change the light
+    const CLEAR: PbrCell = PbrCell {
+        transparent: true,
+        absorbtion: 0.0,
+        reflection: 0.0,
+        scattering: 0.0,
+    };
+
+    // A white light in the middle of a field of `cell`
+    fn field_snapshot(size: usize, cell: PbrCell) -> LightSimSnapshot {
+        let mut emitters = vec![vec![LightEmitterCell::default(); size]; size];
+        emitters[size / 2][size / 2].undirected_lights = Some(UndirectedLightEmitter {
+            props: LightDefinition { color: [1.0; 3] },
+        });
+        LightSimSnapshot {
+            origin: Point { x: 0, y: 0 },
+            size,
+            emitters,
+            pbr: vec![vec![cell; size]; size],
+        }
+    }
+
+    #[test]
+    fn mirror_sends_light_back_toward_the_source() {
+        let size = 9;
+        let mut pbr = vec![vec![CLEAR; size]; size];
+        for cell in pbr[6].iter_mut() {
+            *cell = PbrCell::REFLECTIVE_WALL;
+        }
+        let mut buffers = LightingBuffers::default();
+        buffers.init(size);
+        buffers.add(Direction::E, 3, 4, Vec3::ONE);
+        buffers.swap_buffers_clear_write();
+
+        // Three steps to the mirror, three back
+        simulate_directions(&mut buffers, 6, &pbr);
+
+        let reflection = PbrCell::REFLECTIVE_WALL.reflection;
+        assert!(buffers.lit[Direction::W as usize][3][4].abs_diff_eq(Vec3::splat(reflection), 1e-6));
+        for x in 7..size {
+            assert_eq!(buffers.lit[Direction::E as usize][x][4], Vec3::ZERO, "light behind the mirror at x {x}");
+        }
+    }
+
+    #[test]
+    fn solid_wall_casts_a_hard_shadow() {
+        let size = 12;
+        let mut snapshot = field_snapshot(size, CLEAR);
+        for cell in snapshot.pbr[8].iter_mut() {
+            *cell = PbrCell::SOLID_WALL;
+        }
+        let buffers = snapshot.simulate(30);
+
+        assert!(buffers.lit[Direction::E as usize][7][size / 2].element_sum() > 0.0);
+        for plane in buffers.lit.iter() {
+            for (x, column) in plane.iter().enumerate().skip(9) {
+                for (y, energy) in column.iter().enumerate() {
+                    assert_eq!(*energy, Vec3::ZERO, "light behind the wall at {x}, {y}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn step_in_fog_never_adds_energy() {
+        for fog in [PbrCell::MEDIUM_FOG, PbrCell::HEAVY_FOG] {
+            let snapshot = field_snapshot(16, fog);
+            let mut buffers = snapshot.seed();
+            let mut previous = total_energy(&buffers);
+            assert!(previous > 0.0);
+            for step in 0..20 {
+                simulate_directions(&mut buffers, 1, &snapshot.pbr);
+                let total = total_energy(&buffers);
+                assert!(
+                    total <= previous + 1e-4,
+                    "energy grew from {previous} to {total} in step {step} with {fog:?}"
+                );
+                previous = total;
+            }
+        }
+    }
+
+    #[test]
+    fn step_splits_incoming_into_absorbed_scattered_and_forwarded() {
+        let size = 8;
+        let fog = PbrCell::MEDIUM_FOG;
+        let pbr = vec![vec![fog; size]; size];
+        let mut buffers = LightingBuffers::default();
+        buffers.init(size);
+        buffers.add(Direction::N, 4, 4, Vec3::ONE);
+        buffers.swap_buffers_clear_write();
+
+        simulate_directions(&mut buffers, 1, &pbr);
+
+        let non_absorbed = 1.0 - fog.absorbtion;
+        let scattered = non_absorbed * fog.scattering / 2.0;
+        let forward = non_absorbed - 2.0 * scattered;
+        let read = |direction: Direction, x: usize, y: usize| buffers.read[direction as usize][x][y];
+        assert!(read(Direction::NE, 4, 4).abs_diff_eq(Vec3::splat(scattered), 1e-6));
+        assert!(read(Direction::NW, 4, 4).abs_diff_eq(Vec3::splat(scattered), 1e-6));
+        assert_eq!(read(Direction::N, 4, 4), Vec3::ZERO); // Nothing stays behind
+        let passed = read(Direction::N, 4, 5) + read(Direction::S, 4, 4); // Passed on north (y + 1) or reflected back
+        assert!(passed.abs_diff_eq(Vec3::splat(forward), 1e-6));
+        assert!((total_energy(&buffers) - 3.0 * non_absorbed).abs() < 1e-5);
+        assert_eq!(buffers.lit[Direction::N as usize][4][4], Vec3::ONE);
+    }
+
+    // Same steps as `simulate_directions`, over every cell of every plane instead of the active rects
+    fn simulate_everywhere(buffers: &mut LightingBuffers, steps: usize, pbr: &[Vec<PbrCell>]) {
+        let size = pbr.len();
+        let whole = Some(MinMaxRect { min: (0, 0), max: (size - 1, size - 1) });
+        for step in 0..steps {
+            simulate_directions_step(
+                step,
+                &buffers.read,
+                &[whole; 8],
+                &mut buffers.write,
+                &mut buffers.write_bounds,
+                &mut buffers.lit,
+                &mut buffers.escaped,
+                pbr,
+                (size, size),
+            );
+            buffers.swap_buffers_clear_write();
+        }
+    }
+
+    // Lights and materials sprinkled over a 32x32 area
+    fn random_snapshot(seed: u64) -> LightSimSnapshot {
+        let size = 32;
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        let materials = [CLEAR, CLEAR, PbrCell::MEDIUM_FOG, PbrCell::SOLID_WALL, PbrCell::REFLECTIVE_WALL];
+        let pbr = (0..size)
+            .map(|_| (0..size).map(|_| materials[rng.random_range(0..materials.len())]).collect())
+            .collect();
+        let mut emitters = vec![vec![LightEmitterCell::default(); size]; size];
+        for _ in 0..6 {
+            let cell = &mut emitters[rng.random_range(0..size)][rng.random_range(0..size)];
+            let props = LightDefinition { color: [rng.random(), rng.random(), rng.random()] };
+            if rng.random_bool(0.5) {
+                cell.undirected_lights = Some(UndirectedLightEmitter { props });
+            } else {
+                cell.directed_lights = Some(DirectedLightEmitter {
+                    props,
+                    direction: Direction::ALL[rng.random_range(0..8)],
+                    spread: rng.random_range(0..3),
+                });
+            }
+        }
+        LightSimSnapshot {
+            origin: Point { x: 0, y: 0 },
+            size,
+            emitters,
+            pbr,
+        }
+    }
+
+    #[test]
+    fn active_rects_give_the_same_result_as_iterating_everything() {
+        for seed in 0..8 {
+            let snapshot = random_snapshot(seed);
+            let active = snapshot.simulate(40);
+            let mut everywhere = snapshot.seed();
+            simulate_everywhere(&mut everywhere, 40, &snapshot.pbr);
+            assert!(active.lit == everywhere.lit, "lit planes differ with seed {seed}");
+            assert!(active.read == everywhere.read, "energy left differs with seed {seed}");
+            assert_eq!(active.escaped, everywhere.escaped, "seed {seed}");
+        }
+    }
+
+    #[test]
+    fn two_narrow_lights_simulate_faster_within_their_rects() {
+        let size = 32;
+        let mut emitters = vec![vec![LightEmitterCell::default(); size]; size];
+        for y in [8, 24] {
+            emitters[0][y].directed_lights = Some(DirectedLightEmitter {
+                props: LightDefinition { color: [1.0; 3] },
+                direction: Direction::E,
+                spread: 0,
+            });
+        }
+        let snapshot = LightSimSnapshot {
+            origin: Point { x: 0, y: 0 },
+            size,
+            emitters,
+            pbr: vec![vec![CLEAR; size]; size],
+        };
+        let fastest = |simulate: &dyn Fn()| {
+            (0..5)
+                .map(|_| {
+                    let start = std::time::Instant::now();
+                    simulate();
+                    start.elapsed()
+                })
+                .min()
+                .unwrap()
+        };
+        let active = fastest(&|| {
+            snapshot.simulate(30);
+        });
+        let everywhere = fastest(&|| {
+            simulate_everywhere(&mut snapshot.seed(), 30, &snapshot.pbr);
+        });
+        assert!(active * 3 < everywhere, "{active:?} within the active rects, {everywhere:?} everywhere");
+    }
+
+    #[test]
+    fn subpixels_interpolate_between_tile_centers() {
+        // A 4 tile window at (1, 1) with a one tile apron, every row the same
+        let row = [0.0, 0.0, 0.8, 0.2, 0.4, 0.4];
+        let mut buffers: [Vec<Vec<Vec3>>; 8] = std::array::from_fn(|_| vec![vec![Vec3::ZERO; 6]; 6]);
+        for (x, energy) in row.iter().enumerate() {
+            buffers[Direction::E as usize][x] = vec![Vec3::splat(*energy); 6];
+        }
+        let draw = |pixels_per_tile| {
+            let mut image = blank_image(4);
+            let mode = LightCompositeMode::Sum;
+            write_overlay_image(&buffers, (1, 1), 4, pixels_per_tile, mode, Vec3::ZERO, OverlayEncoding::Raw, &mut image);
+            image
+        };
+
+        let blocky = draw(1);
+        let tiles: Vec<u8> = (0..4).map(|x| texel(&blocky, x, 2)).collect();
+        assert_eq!(tiles, vec![0, 204, 51, 102]);
+
+        // Texel centers a quarter tile apart, blended from the two nearest tile centers
+        let smooth = draw(4);
+        assert_eq!(smooth.width(), 16);
+        let expected = [
+            0.0, 0.0, 0.1, 0.3, 0.5, 0.7, 0.725, 0.575, 0.425, 0.275, 0.225, 0.275, 0.325, 0.375, 0.4, 0.4,
+        ];
+        for y in [0, 7, 15] {
+            for (x, energy) in expected.iter().enumerate() {
+                let wanted = (energy * 255.0f32).round() as u8;
+                assert!(texel(&smooth, x, y).abs_diff(wanted) <= 1, "texel {x}, {y} is {} instead of {wanted}", texel(&smooth, x, y));
+            }
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use bevy::prelude::*;
+
+use crate::{
+    core::units::WorldPos,
+    game::render::light_sim::{lighting::GlobalAmbientLight, simulation::LightFieldSnapshot},
+};
+
+/// Makes an entity drawn above the light overlay pick up the light of its tile.
+/// The entity gets its own copy of its `ColorMaterial`, shared Pallete materials are only
+/// read for the base color. Assigning another material later is fine, it becomes the new base.
+#[derive(Component, Debug, Clone, Default)]
+pub struct LitSprite {
+    pub base_color: Option<Color>,           // Unlit color, taken from the assigned material
+    instance: Option<Handle<ColorMaterial>>, // Per-entity material the lit color is written to
+}
+
+impl LitSprite {
+    /// Handle of the per-entity material, once created.
+    pub fn instance(&self) -> Option<&Handle<ColorMaterial>> {
+        self.instance.as_ref()
+    }
+}
+
+/// Multiplies the light at each `LitSprite` (plus ambient) into its material color.
+/// Outside the simulated area only the ambient light applies.
+pub fn lit_sprite_system(
+    light_field: Res<LightFieldSnapshot>,
+    ambient: Res<GlobalAmbientLight>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut query: Query<(&GlobalTransform, &mut LitSprite, &mut MeshMaterial2d<ColorMaterial>)>,
+) {
+    let ambient = ambient.energy();
+    for (transform, mut lit, mut material) in query.iter_mut() {
+        if lit.instance.as_ref() != Some(&material.0) {
+            // A shared material was (re)assigned, e.g. the player changing color
+            let Some(color) = materials.get(&material.0).map(|m| m.color) else {
+                continue;
+            };
+            lit.base_color = Some(color);
+            let instance = match lit.instance.clone() {
+                Some(instance) => instance,
+                None => materials.add(ColorMaterial::from_color(color)),
+            };
+            lit.instance = Some(instance.clone());
+            material.0 = instance;
+        }
+        let (Some(base), Some(instance)) = (lit.base_color, lit.instance.as_ref()) else {
+            continue;
+        };
+
+        let light = light_field
+            .sample(WorldPos(transform.translation().xy()))
+            .unwrap_or(Vec3::ZERO);
+        let factor = (ambient + light).min(Vec3::ONE);
+        let base = base.to_linear();
+        let lit_color = Color::LinearRgba(LinearRgba::new(
+            base.red * factor.x,
+            base.green * factor.y,
+            base.blue * factor.z,
+            base.alpha,
+        ));
+        // Mutable access marks the asset changed, skip it when the color holds
+        if materials.get(instance).is_none_or(|m| m.color == lit_color) {
+            continue;
+        }
+        if let Some(m) = materials.get_mut(instance) {
+            m.color = lit_color;
+        }
+    }
+}
@@ -0,0 +1,259 @@
+use bevy::{
+    asset::RenderAssetUsages,
+    image::ImageSampler,
+    platform::collections::HashSet,
+    prelude::*,
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+};
+
+use crate::{
+    core::{
+        basics::Point,
+        chunks::{ChunkCoords, ChunkLoaded, ChunkUnloaded, ChunkWritten, DataMap},
+        units::{TilesCount, WorldPos},
+    },
+    game::{
+        Player,
+        input::{InputAction, InputBindings},
+        render::{render_enabled, utils},
+        world::passability::{Passability, PassabilityProducer},
+    },
+};
+
+const UNKNOWN_COLOR: [u8; 4] = [60, 20, 60, 255];
+const WALL_COLOR: [u8; 4] = [30, 30, 35, 255];
+const FREE_COLOR: [u8; 4] = [170, 200, 150, 255];
+const PLAYER_COLOR: [u8; 4] = [255, 40, 40, 255];
+
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct MinimapSettings {
+    pub size_px: u32,               // Square image, also its size on screen
+    pub tiles_per_pixel: TilesCount, // Each pixel shows the least passable tile of its block
+    pub margin_px: f32,             // Distance from the top-right corner of the window
+    pub visible: bool,
+}
+
+impl Default for MinimapSettings {
+    fn default() -> Self {
+        Self {
+            size_px: 128,
+            tiles_per_pixel: 2,
+            margin_px: 8.0,
+            visible: true,
+        }
+    }
+}
+
+impl MinimapSettings {
+    /// Tiles covered by the minimap along each side.
+    pub fn tiles(&self) -> TilesCount {
+        self.size_px as TilesCount * self.tiles_per_pixel
+    }
+}
+
+#[derive(Resource)]
+pub struct MinimapState {
+    pub image: Handle<Image>,
+    bottom_left: Option<Point>, // Tile at the bottom-left pixel, moves in whole blocks of pixels
+    player_pixel: Option<(usize, usize)>,
+}
+
+#[derive(Component)]
+struct MinimapNode;
+
+pub struct MinimapPlugin;
+
+impl Plugin for MinimapPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MinimapSettings>()
+            .init_resource::<InputBindings>()
+            .add_systems(Startup, setup_minimap.run_if(render_enabled))
+            .add_systems(
+                Update,
+                (minimap_toggle_system, minimap_redraw_system)
+                    .chain()
+                    .run_if(render_enabled),
+            );
+    }
+}
+
+fn setup_minimap(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    settings: Res<MinimapSettings>,
+) {
+    let mut image = Image::new_fill(
+        Extent3d {
+            width: settings.size_px,
+            height: settings.size_px,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &UNKNOWN_COLOR,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::RENDER_WORLD | RenderAssetUsages::MAIN_WORLD,
+    );
+    image.sampler = ImageSampler::nearest(); // Crisp pixels
+    let handle = images.add(image);
+
+    commands.spawn((
+        MinimapNode,
+        ImageNode::new(handle.clone()),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(settings.margin_px),
+            right: Val::Px(settings.margin_px),
+            width: Val::Px(settings.size_px as f32),
+            height: Val::Px(settings.size_px as f32),
+            ..default()
+        },
+        if settings.visible {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        },
+    ));
+    commands.insert_resource(MinimapState {
+        image: handle,
+        bottom_left: None,
+        player_pixel: None,
+    });
+}
+
+fn minimap_toggle_system(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    gamepads: Query<&Gamepad>,
+    bindings: Res<InputBindings>,
+    mut settings: ResMut<MinimapSettings>,
+    mut node_query: Query<&mut Visibility, With<MinimapNode>>,
+) {
+    if !bindings.just_pressed(InputAction::ToggleMinimap, &keyboard_input, gamepads.iter()) {
+        return;
+    }
+    settings.visible = !settings.visible;
+    for mut visibility in node_query.iter_mut() {
+        *visibility = if settings.visible {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
+
+/// Color of the pixel whose block starts at `block`: the least passable loaded tile,
+/// unknown if any tile of the block is not loaded.
+fn block_color(map: &DataMap<PassabilityProducer>, block: Point, tiles_per_pixel: TilesCount) -> [u8; 4] {
+    let mut least = Passability::FREE;
+    for dx in 0..tiles_per_pixel as isize {
+        for dy in 0..tiles_per_pixel as isize {
+            let Some(p) = map.read(Point {
+                x: block.x + dx,
+                y: block.y + dy,
+            }) else {
+                return UNKNOWN_COLOR;
+            };
+            least = Passability(least.0.min(p.0));
+        }
+    }
+    if least.is_wall() {
+        WALL_COLOR
+    } else {
+        FREE_COLOR
+    }
+}
+
+fn draw_pixel(image: &mut Image, size_px: usize, x: usize, y: usize, color: [u8; 4]) {
+    // image rows grow downwards
+    utils::draw_rect_on_image(image, x, size_px - 1 - y, 1, 1, color);
+}
+
+// Recenters the minimap on the player and redraws only the pixels of chunks that changed
+#[allow(clippy::too_many_arguments)]
+fn minimap_redraw_system(
+    settings: Res<MinimapSettings>,
+    mut state: ResMut<MinimapState>,
+    mut images: ResMut<Assets<Image>>,
+    passability: Res<DataMap<PassabilityProducer>>,
+    player_query: Query<&Transform, With<Player>>,
+    mut loaded_events: EventReader<ChunkLoaded<PassabilityProducer>>,
+    mut written_events: EventReader<ChunkWritten<PassabilityProducer>>,
+    mut unloaded_events: EventReader<ChunkUnloaded<PassabilityProducer>>,
+) {
+    let changed: HashSet<ChunkCoords> = loaded_events
+        .read()
+        .map(|e| e.coords)
+        .chain(written_events.read().map(|e| e.coords))
+        .chain(unloaded_events.read().map(|e| e.coords))
+        .collect();
+    if !settings.visible {
+        state.bottom_left = None; // Full redraw once shown again
+        return;
+    }
+    let Ok(player_transform) = player_query.single() else {
+        return;
+    };
+    let Some(image) = images.get_mut(&state.image) else {
+        return;
+    };
+
+    let size_px = settings.size_px as usize;
+    let block = settings.tiles_per_pixel.max(1) as isize;
+    let player_tile = WorldPos(player_transform.translation.xy()).to_tile().0;
+    // Recenter in steps of a chunk, so the map doesn't shift (and fully redraw) on every tile
+    let step = (passability.chunk_dimension_tiles as isize).max(block) / block * block;
+    let half = settings.tiles() as isize / 2;
+    let bottom_left = Point {
+        x: (player_tile.x - half).div_euclid(step) * step,
+        y: (player_tile.y - half).div_euclid(step) * step,
+    };
+    let dim = passability.chunk_dimension_tiles;
+
+    let redraw_pixel = |image: &mut Image, x: usize, y: usize| {
+        let tile = Point {
+            x: bottom_left.x + x as isize * block,
+            y: bottom_left.y + y as isize * block,
+        };
+        draw_pixel(image, size_px, x, y, block_color(&passability, tile, block as TilesCount));
+    };
+
+    if state.bottom_left != Some(bottom_left) {
+        for x in 0..size_px {
+            for y in 0..size_px {
+                redraw_pixel(image, x, y);
+            }
+        }
+        state.bottom_left = Some(bottom_left);
+        state.player_pixel = None;
+    } else {
+        for coords in changed.iter() {
+            let chunk_bottom_left = coords.to_bottom_left_tile_point(dim);
+            let first_x = (chunk_bottom_left.x - bottom_left.x).div_euclid(block);
+            let first_y = (chunk_bottom_left.y - bottom_left.y).div_euclid(block);
+            let last_x = (chunk_bottom_left.x + dim as isize - 1 - bottom_left.x).div_euclid(block);
+            let last_y = (chunk_bottom_left.y + dim as isize - 1 - bottom_left.y).div_euclid(block);
+            for x in first_x.max(0)..=last_x.min(size_px as isize - 1) {
+                for y in first_y.max(0)..=last_y.min(size_px as isize - 1) {
+                    redraw_pixel(image, x as usize, y as usize);
+                }
+            }
+        }
+    }
+
+    // Player marker, the pixel it covered gets its map color back
+    let player_pixel = (
+        (player_tile.x - bottom_left.x).div_euclid(block),
+        (player_tile.y - bottom_left.y).div_euclid(block),
+    );
+    let inside = (0..size_px as isize).contains(&player_pixel.0)
+        && (0..size_px as isize).contains(&player_pixel.1);
+    let player_pixel = inside.then_some((player_pixel.0 as usize, player_pixel.1 as usize));
+    if state.player_pixel != player_pixel {
+        if let Some((x, y)) = state.player_pixel {
+            redraw_pixel(image, x, y);
+        }
+        state.player_pixel = player_pixel;
+    }
+    if let Some((x, y)) = player_pixel {
+        draw_pixel(image, size_px, x, y, PLAYER_COLOR);
+    }
+}
@@ -1,4 +1,36 @@
+use bevy::{app::App, ecs::{resource::Resource, system::Res}};
+
 pub mod tilemap_render;
 pub mod utils;
 pub mod light_sim;
-pub mod blending;
\ No newline at end of file
+pub mod blending;
+pub mod minimap;
+pub mod culling;
+pub mod fog;
+pub mod lit_sprite;
+pub mod layers;
+
+pub use layers::{RenderLayerZ, spawn_at_layer};
+
+/// Whether the systems drawing images, gizmos and materials run. Without this resource they
+/// all do; `HeadlessPlugins` disables them so the world logic runs without a window or GPU.
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct RenderFeatures {
+    pub enabled: bool,
+}
+
+impl Default for RenderFeatures {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
+/// Run condition for render-side systems, see `RenderFeatures`.
+pub fn render_enabled(features: Option<Res<RenderFeatures>>) -> bool {
+    features.is_none_or(|f| f.enabled)
+}
+
+/// Same as `render_enabled`, for plugins deciding what to add while being built.
+pub fn render_enabled_in(app: &App) -> bool {
+    app.world().get_resource::<RenderFeatures>().is_none_or(|f| f.enabled)
+}
@@ -1,14 +1,21 @@
+use std::marker::PhantomData;
+
 use bevy::{
-    asset::{Handle, RenderAssetUsages},
-    color::{ColorToPacked, palettes::css},
+    app::{App, Update},
+    asset::{Assets, Handle, RenderAssetUsages},
+    color::{ColorToPacked, Hsla, Hue, Srgba, palettes::css},
     ecs::{
+        change_detection::DetectChanges,
+        entity::Entity,
+        event::EventReader,
         query::With,
         resource::Resource,
-        system::{Commands, Query, ResMut},
+        schedule::IntoScheduleConfigs,
+        system::{Commands, Query, Res, ResMut},
     },
     image::Image,
-    math::Vec3Swizzles,
-    platform::collections::HashSet,
+    math::{Vec2, Vec3Swizzles},
+    platform::collections::{HashMap, HashSet},
     render::render_resource::{Extent3d, TextureDimension, TextureFormat},
     sprite::Sprite,
     transform::components::Transform,
@@ -16,153 +23,1149 @@ use bevy::{
 
 use crate::{
     core::{
-        basics::Point,
-        chunks::{ChunkCoords, DataMap},
-        constants::TILE_SIZE_IN_UNITS_UNITS,
-        units::TilesCount,
-    },
-    game::{
-        MapRevealActor,
-        render::utils,
-        world::passability::PassabilityProducer,
+        basics::{Point, tile_hash},
+        chunks::{
+            AreaSnapshot, ChunkCoords, ChunkLoaded, ChunkVersions, ChunkWritten, DataMap, LoadShape,
+            MapDataProducer, WorldSeed, lod_factor, required_chunks,
+        },
+        chunks_lod::{LodChunkLoaded, LodDataMap, insert_lod_map},
+        constants::{TILE_SIZE_IN_UNITS, TILE_SIZE_IN_UNITS_UNITS},
+        units::{TilesCount, WorldPos},
+        validation::{ConfigError, RenderLayerLayout, WorldLayout, check_even, check_nonzero, short_type_name, tiles_to_units},
     },
+    game::{MapRevealActor, camera::CameraZoom, render::{RenderLayerZ, culling::CullBounds, render_enabled, utils}, world::{passability::{Passability, PassabilityProducer}, tile_types::{TileType, TileTypeProducer}}},
 };
 
-const IMAGE_WIDTH_PX: u32 = 64;
-const IMAGE_HEIGHT_PX: u32 = IMAGE_WIDTH_PX;
-const IMAGE_WIDTH_TILES: TilesCount = IMAGE_WIDTH_PX as usize / TILE_SIZE_IN_UNITS_UNITS as usize;
-const IMAGE_HEIGHT_TILES: TilesCount = IMAGE_HEIGHT_PX as usize / TILE_SIZE_IN_UNITS_UNITS as usize;
-const MAP_RENDER_DISTANCE: isize = 2;
+/// Turns a single map item into the color of its tile on a debug tilemap layer.
+pub trait TileColorizer<P: MapDataProducer>: Send + Sync + 'static {
+    fn color(&self, item: P::Item, point: Point) -> [u8; 4];
+
+    /// Color of tiles whose chunk is not loaded yet.
+    fn missing_color(&self) -> [u8; 4] {
+        [0, 0, 0, 0]
+    }
+
+    /// Called once with the layer settings, before the first tile is colored.
+    fn configure(&mut self, _settings: &TilemapLayerSettings) {}
+
+    /// Whether the item blocks movement, for `EdgeShading`. Layers returning None aren't shaded.
+    fn is_wall(&self, _item: P::Item) -> Option<bool> {
+        None
+    }
+}
+
+/// Colors passability tiles with a gradient repeating every hypertile, brighter blue for high values.
+#[derive(Debug, Clone, Copy)]
+pub struct PassabilityColorizer {
+    tiles_per_image: TilesCount, // Period of the gradient, see `TilemapLayerSettings::tiles_per_image`
+}
+
+impl Default for PassabilityColorizer {
+    fn default() -> Self {
+        Self {
+            tiles_per_image: TilemapLayerSettings::default().tiles_per_image(),
+        }
+    }
+}
+
+impl TileColorizer<PassabilityProducer> for PassabilityColorizer {
+    fn color(&self, item: Passability, point: Point) -> [u8; 4] {
+        let tiles = self.tiles_per_image.max(1) as isize;
+        let i = (point.x.rem_euclid(tiles) * 64 / tiles) as u8;
+        let j = (point.y.rem_euclid(tiles) * 64 / tiles) as u8;
+        if item.0 > 10 {
+            [i, j, 128, 255]
+        } else {
+            [i, j, 42, 255]
+        }
+    }
+
+    fn missing_color(&self) -> [u8; 4] {
+        css::BEIGE.to_u8_array()
+    }
+
+    fn configure(&mut self, settings: &TilemapLayerSettings) {
+        self.tiles_per_image = settings.tiles_per_image();
+    }
+
+    fn is_wall(&self, item: Passability) -> Option<bool> {
+        Some(item.is_wall())
+    }
+}
+
+/// Colors terrain kinds, with a slight per-tile shade so the grid stays readable.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TileTypeColorizer;
+
+impl TileColorizer<TileTypeProducer> for TileTypeColorizer {
+    fn color(&self, item: TileType, point: Point) -> [u8; 4] {
+        let shade = ((point.x + point.y).rem_euclid(2) * 8) as u8;
+        match item {
+            TileType::Grass => [70 + shade, 140 + shade, 60, 255],
+            TileType::Water => [40, 80 + shade, 170 + shade, 255],
+            TileType::Sand => [210 + shade, 190 + shade, 130, 255],
+            TileType::Rock => [100 + shade, 100 + shade, 105 + shade, 255],
+            TileType::Road => [150 + shade, 115 + shade, 75, 255],
+        }
+    }
+
+    fn missing_color(&self) -> [u8; 4] {
+        css::BEIGE.to_u8_array()
+    }
+
+    // Water and rock are exactly the passability walls
+    fn is_wall(&self, item: TileType) -> Option<bool> {
+        Some(!item.is_passable())
+    }
+}
+
+const EDGE_SHADING_DIAGONAL_WEIGHT: f32 = 0.5; // Corner walls darken half as much as side walls
+
+/// Darkens passable tiles next to walls on every tilemap layer whose colorizer knows walls.
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct EdgeShading {
+    pub strength: f32, // Darkening of a tile surrounded by walls, 0..1
+    pub enabled: bool,
+}
+
+impl Default for EdgeShading {
+    fn default() -> Self {
+        Self {
+            strength: 0.35,
+            enabled: true,
+        }
+    }
+}
+
+/// Slight random brightness and hue changes per world tile on every tilemap layer, so the
+/// colorizers' patterns don't repeat visibly. Zero amounts turn it off.
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct TileDetail {
+    pub brightness_jitter: f32, // Largest brightness change, as a fraction of the color
+    pub hue_jitter: f32,        // Largest hue rotation, in degrees
+}
+
+impl Default for TileDetail {
+    fn default() -> Self {
+        Self {
+            brightness_jitter: 0.06,
+            hue_jitter: 4.0,
+        }
+    }
+}
+
+impl TileDetail {
+    /// `color` varied for the world tile at `point`. The same tile and seed always give the same color.
+    pub fn apply(&self, color: [u8; 4], point: Point, seed: u64) -> [u8; 4] {
+        let hash = tile_hash(point, seed);
+        // Two independent values in -1..1 from the high and low halves of the hash
+        let signed = |bits: u64| (bits & 0xFF_FFFF) as f32 / 0xFF_FFFF as f32 * 2.0 - 1.0;
+        let mut color = color;
+        if self.hue_jitter != 0.0 {
+            let hsla = Hsla::from(Srgba::from_u8_array(color)).rotate_hue(signed(hash >> 32) * self.hue_jitter);
+            color = Srgba::from(hsla).to_u8_array();
+        }
+        let brightness = 1.0 + signed(hash) * self.brightness_jitter;
+        for channel in color.iter_mut().take(3) {
+            *channel = (*channel as f32 * brightness).clamp(0.0, 255.0) as u8;
+        }
+        color
+    }
+}
+
+/// Brightness factor of a passable tile with the given wall neighbors, 1 without any.
+pub fn edge_shading_factor(side_walls: usize, corner_walls: usize, strength: f32) -> f32 {
+    let max = 4.0 * (1.0 + EDGE_SHADING_DIAGONAL_WEIGHT);
+    let walls = side_walls as f32 + corner_walls as f32 * EDGE_SHADING_DIAGONAL_WEIGHT;
+    1.0 - strength.clamp(0.0, 1.0) * (walls / max).min(1.0)
+}
+
+// Wall neighbors of a tile as (sides, corners). Unloaded neighbors are unknown and never
+// count, so no dark halo appears along chunks that are still loading.
+fn wall_neighbors<P: MapDataProducer, C: TileColorizer<P>>(
+    snapshot: &AreaSnapshot<P::Item>,
+    colorizer: &C,
+    point: Point,
+) -> (usize, usize) {
+    let is_wall = |neighbor: &Point| {
+        snapshot
+            .read(*neighbor)
+            .and_then(|item| colorizer.is_wall(item))
+            .unwrap_or(false)
+    };
+    let neighbors = point.neighbors8();
+    let (sides, corners) = neighbors.split_at(4);
+    (sides.iter().filter(|n| is_wall(n)).count(), corners.iter().filter(|n| is_wall(n)).count())
+}
+
+/// New hypertile images drawn per frame and layer, nearest to a `MapRevealActor` first.
+/// Requests over the budget wait for the next frames instead of drawing them all at once.
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct HypertileBudget {
+    pub max_images_per_frame: usize,
+}
+
+impl Default for HypertileBudget {
+    fn default() -> Self {
+        Self {
+            max_images_per_frame: 3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct TilemapLayerSettings {
+    pub image_size_px: u32, // Square image per hypertile
+    pub tile_size_px: u32,  // Pixels per tile inside the image
+    pub z: f32,
+    pub render_distance: usize, // In hypertiles around every MapRevealActor
+    pub unload_margin: usize,   // Extra hypertiles kept around the render distance before despawning
+    pub shape: LoadShape,
+    pub max_redraws_per_frame: usize,
+    pub lod_distance: Option<usize>, // Hypertiles farther than this from every actor are drawn from `far_lod` data
+    pub far_lod: u8,                 // See `lod_factor`, 2 means one sample per 2x2 tiles
+}
+
+impl Default for TilemapLayerSettings {
+    fn default() -> Self {
+        Self {
+            image_size_px: 64,
+            tile_size_px: TILE_SIZE_IN_UNITS_UNITS as u32,
+            z: RenderLayerZ::Background.z(),
+            render_distance: 2,
+            unload_margin: 2,
+            shape: LoadShape::Square,
+            max_redraws_per_frame: 16,
+            lod_distance: Some(4),
+            far_lod: 2,
+        }
+    }
+}
+
+impl TilemapLayerSettings {
+    /// Images hold a whole, even number of tiles whose world size is exact.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        check_nonzero("image_size_px", self.image_size_px as TilesCount)?;
+        check_nonzero("tile_size_px", self.tile_size_px as TilesCount)?;
+        if !self.image_size_px.is_multiple_of(self.tile_size_px) {
+            return Err(ConfigError::PixelsNotDivisible {
+                what: "hypertile",
+                image_size_px: self.image_size_px,
+                tile_size_px: self.tile_size_px,
+            });
+        }
+        check_even("hypertile", self.tiles_per_image())?;
+        tiles_to_units("hypertile", self.tiles_per_image()).map(|_| ())
+    }
+
+    pub fn tiles_per_image(&self) -> TilesCount {
+        (self.image_size_px / self.tile_size_px) as TilesCount
+    }
+
+    /// World size of a hypertile sprite, independent of the image resolution.
+    pub fn hypertile_size_units(&self) -> f32 {
+        self.tiles_per_image() as f32 * TILE_SIZE_IN_UNITS
+    }
+
+    /// LOD of a hypertile `distance` hypertiles away from the nearest actor. Full resolution
+    /// when LOD blocks wouldn't line up with both the hypertiles and the data chunks.
+    pub fn lod_for_distance(&self, distance: usize, chunk_dimension_tiles: TilesCount) -> u8 {
+        let factor = lod_factor(self.far_lod);
+        let aligned = self.tiles_per_image().is_multiple_of(factor) && chunk_dimension_tiles.is_multiple_of(factor);
+        match self.lod_distance {
+            Some(lod_distance) if aligned && distance > lod_distance => self.far_lod,
+            _ => 0,
+        }
+    }
+}
 
+/// Colorizer and settings of the tilemap layer rendering `DataMap<P>`.
 #[derive(Resource)]
-struct BackgroundHypertile(ChunkCoords, Handle<Image>);
+pub struct TilemapLayer<P: MapDataProducer, C: TileColorizer<P>> {
+    pub colorizer: C,
+    pub settings: TilemapLayerSettings,
+    _producer: PhantomData<P>,
+}
 
 #[derive(Resource)]
-pub struct BackgroundHypertileTracker {
+pub struct HypertileTracker<P: MapDataProducer> {
     pub spawned: HashSet<ChunkCoords>,
     pub requested: HashSet<ChunkCoords>,
+    pub waiting: HashSet<ChunkCoords>, // Requested, but the map data is not loaded yet
+    _producer: PhantomData<P>,
+}
+
+impl<P: MapDataProducer> Default for HypertileTracker<P> {
+    fn default() -> Self {
+        Self {
+            spawned: HashSet::new(),
+            requested: HashSet::new(),
+            waiting: HashSet::new(),
+            _producer: PhantomData,
+        }
+    }
 }
 
-impl BackgroundHypertileTracker {
+impl<P: MapDataProducer> HypertileTracker<P> {
     pub fn require(&mut self, coords: ChunkCoords) {
-        if self.spawned.contains(&coords) || self.requested.contains(&coords) {
+        if self.spawned.contains(&coords)
+            || self.requested.contains(&coords)
+            || self.waiting.contains(&coords)
+        {
             return; // already exists or already requested
         }
         self.requested.insert(coords);
     }
 
-    pub fn mark_all_requests_as_completed(&mut self) {
-        self.spawned.extend(self.requested.drain());
+    /// Moves the hypertiles covering a freshly loaded data chunk back to `requested`.
+    pub fn wake_up(
+        &mut self,
+        data_chunk: ChunkCoords,
+        chunk_dimension_tiles: TilesCount,
+        tiles_per_image: TilesCount,
+    ) {
+        for coords in hypertiles_covering(data_chunk, chunk_dimension_tiles, tiles_per_image) {
+            if self.waiting.remove(&coords) {
+                self.requested.insert(coords);
+            }
+        }
+    }
+
+    pub fn mark_completed(&mut self, coords: ChunkCoords) {
+        if self.requested.remove(&coords) {
+            self.spawned.insert(coords);
+        }
+    }
+
+    /// Moves a requested hypertile to `waiting`, until a load event wakes it up.
+    pub fn defer(&mut self, coords: ChunkCoords) {
+        if self.requested.remove(&coords) {
+            self.waiting.insert(coords);
+        }
     }
 }
 
-pub fn background_load_unload_system(
-    player_query: Query<&Transform, With<MapRevealActor>>,
-    mut tracker: ResMut<BackgroundHypertileTracker>,
+/// Sprites and images of the spawned hypertiles, so they can be redrawn in place.
+#[derive(Resource)]
+pub struct HypertileCache<P: MapDataProducer> {
+    pub hypertiles: HashMap<ChunkCoords, (Entity, Handle<Image>)>,
+    pub dirty: HashSet<ChunkCoords>, // Underlying data changed, waiting for a redraw
+    pub lods: HashMap<ChunkCoords, u8>, // LOD each hypertile is drawn at, only ever lowered by approaching it
+    pub drawn_versions: HashMap<ChunkCoords, ChunkVersions>, // Data chunks a full resolution image was drawn from
+    _producer: PhantomData<P>,
+}
+
+impl<P: MapDataProducer> Default for HypertileCache<P> {
+    fn default() -> Self {
+        Self {
+            hypertiles: HashMap::new(),
+            dirty: HashSet::new(),
+            lods: HashMap::new(),
+            drawn_versions: HashMap::new(),
+            _producer: PhantomData,
+        }
+    }
+}
+
+impl<P: MapDataProducer> HypertileCache<P> {
+    /// Marks the spawned hypertiles covering a data chunk for a redraw.
+    pub fn invalidate(
+        &mut self,
+        data_chunk: ChunkCoords,
+        chunk_dimension_tiles: TilesCount,
+        tiles_per_image: TilesCount,
+    ) {
+        for coords in hypertiles_covering(data_chunk, chunk_dimension_tiles, tiles_per_image) {
+            if self.hypertiles.contains_key(&coords) {
+                self.dirty.insert(coords);
+            }
+        }
+    }
+
+    /// Same as `invalidate`, also covering the hypertiles whose edge shading reads the chunk.
+    pub fn invalidate_with_border(
+        &mut self,
+        data_chunk: ChunkCoords,
+        chunk_dimension_tiles: TilesCount,
+        tiles_per_image: TilesCount,
+    ) {
+        for coords in hypertiles_bordering(data_chunk, chunk_dimension_tiles, tiles_per_image) {
+            if self.hypertiles.contains_key(&coords) {
+                self.dirty.insert(coords);
+            }
+        }
+    }
+}
+
+/// Hypertile coords within one tile of the given data chunk, the ones whose edge shading reads it.
+fn hypertiles_bordering(
+    data_chunk: ChunkCoords,
+    chunk_dimension_tiles: TilesCount,
+    tiles_per_image: TilesCount,
+) -> impl Iterator<Item = ChunkCoords> {
+    let bottom_left = data_chunk.to_bottom_left_tile_point(chunk_dimension_tiles);
+    let first = ChunkCoords::from_point(
+        Point {
+            x: bottom_left.x - 1,
+            y: bottom_left.y - 1,
+        },
+        tiles_per_image,
+    );
+    let last = ChunkCoords::from_point(
+        Point {
+            x: bottom_left.x + chunk_dimension_tiles as isize,
+            y: bottom_left.y + chunk_dimension_tiles as isize,
+        },
+        tiles_per_image,
+    );
+    (first.x..=last.x).flat_map(move |x| (first.y..=last.y).map(move |y| ChunkCoords { x, y }))
+}
+
+/// Cells of a grid with `to_dimension` tiles per cell overlapping the cell `from` of a grid
+/// with `from_dimension` tiles per cell. Works both ways, for cells larger or smaller than the other grid's.
+fn cells_overlapping(
+    from: ChunkCoords,
+    from_dimension: TilesCount,
+    to_dimension: TilesCount,
+) -> impl Iterator<Item = ChunkCoords> {
+    let bottom_left = from.to_bottom_left_tile_point(from_dimension);
+    let top_right = Point {
+        x: bottom_left.x + from_dimension as isize - 1,
+        y: bottom_left.y + from_dimension as isize - 1,
+    };
+    let first = ChunkCoords::from_point(bottom_left, to_dimension);
+    let last = ChunkCoords::from_point(top_right, to_dimension);
+    (first.x..=last.x).flat_map(move |x| (first.y..=last.y).map(move |y| ChunkCoords { x, y }))
+}
+
+/// Hypertile coords overlapping the given data chunk.
+fn hypertiles_covering(
+    data_chunk: ChunkCoords,
+    chunk_dimension_tiles: TilesCount,
+    tiles_per_image: TilesCount,
+) -> impl Iterator<Item = ChunkCoords> {
+    cells_overlapping(data_chunk, chunk_dimension_tiles, tiles_per_image)
+}
+
+/// Data chunk coords overlapping the given hypertile.
+fn data_chunks_covering(
+    hypertile: ChunkCoords,
+    tiles_per_image: TilesCount,
+    chunk_dimension_tiles: TilesCount,
+) -> impl Iterator<Item = ChunkCoords> {
+    cells_overlapping(hypertile, tiles_per_image, chunk_dimension_tiles)
+}
+
+/// Whether every data chunk under the hypertile is loaded; the missing ones are requested.
+/// Chunks given up on don't hold the hypertile back, their tiles get the missing color.
+fn hypertile_ready<P: MapDataProducer>(
+    data_map: &mut DataMap<P>,
+    hypertile: ChunkCoords,
+    tiles_per_image: TilesCount,
+) -> bool {
+    let mut ready = true;
+    for coords in data_chunks_covering(hypertile, tiles_per_image, data_map.chunk_dimension_tiles) {
+        if !data_map.loaded_chunks.contains_key(&coords) && !data_map.failed_chunks.contains(&coords) {
+            data_map.requested_chunks.insert(coords);
+            ready = false;
+        }
+    }
+    ready
+}
+
+/// Same as `hypertile_ready` for the LOD chunks under the hypertile.
+fn hypertile_lod_ready<P: MapDataProducer>(
+    lod_map: &mut LodDataMap<P>,
+    hypertile: ChunkCoords,
+    tiles_per_image: TilesCount,
+    lod: u8,
+) -> bool {
+    let mut ready = true;
+    for coords in data_chunks_covering(hypertile, tiles_per_image, lod_map.chunk_dimension_tiles) {
+        ready &= lod_map.ensure(coords, lod);
+    }
+    ready
+}
+
+/// Full resolution tiles under a hypertile and the border its edge shading reads, copied at once
+/// so a drawn image never shows half of a write.
+fn hypertile_snapshot<P: MapDataProducer>(
+    data_map: &DataMap<P>,
+    hypertile: ChunkCoords,
+    tiles_per_image: TilesCount,
+) -> AreaSnapshot<P::Item> {
+    let origin = hypertile.to_bottom_left_tile_point(tiles_per_image);
+    let bottom_left = Point {
+        x: origin.x - 1,
+        y: origin.y - 1,
+    };
+    data_map.snapshot_area(bottom_left, (tiles_per_image + 2, tiles_per_image + 2))
+}
+
+/// Draws a hypertile onto its image, one block of rows per tile, or per block of tiles above LOD 1.
+/// Loaded tiles are varied by `detail`, keyed on their world coordinates.
+/// Full resolution tiles come from `snapshot` (see `hypertile_snapshot`) and get `edge_shading`
+/// (the strength), if any.
+#[allow(clippy::too_many_arguments)]
+fn draw_hypertile<P: MapDataProducer, C: TileColorizer<P>>(
+    snapshot: Option<&AreaSnapshot<P::Item>>,
+    lod_map: &LodDataMap<P>,
+    layer: &TilemapLayer<P, C>,
+    hypertile: ChunkCoords,
+    lod: u8,
+    edge_shading: Option<f32>,
+    (detail, seed): (&TileDetail, u64),
+    image: &mut Image,
 ) {
-    for player_transform in player_query.as_readonly().iter() {
-        let focus_world_pos = player_transform.translation.xy();
-        let current_focus_chunk_coords =
-            ChunkCoords::from_world_pos(focus_world_pos, IMAGE_HEIGHT_PX as f32);
-
-        for dx in -(MAP_RENDER_DISTANCE)..=(MAP_RENDER_DISTANCE) {
-            for dy in -(MAP_RENDER_DISTANCE)..=(MAP_RENDER_DISTANCE) {
-                tracker.require(ChunkCoords {
-                    x: current_focus_chunk_coords.x + dx,
-                    y: current_focus_chunk_coords.y + dy,
-                });
+    let tiles = layer.settings.tiles_per_image();
+    let factor = lod_factor(lod);
+    let tile_px = layer.settings.tile_size_px as usize;
+    let total_px = layer.settings.image_size_px as usize;
+    let tiles_coords_of_a_chunk = hypertile.to_bottom_left_tile_point(tiles);
+    let image_width = image.width() as usize;
+    let Some(pixels) = image.data.as_deref_mut() else {
+        return;
+    };
+    for i in (0..tiles).step_by(factor) {
+        for j in (0..tiles).step_by(factor) {
+            let point = Point {
+                x: i as isize + tiles_coords_of_a_chunk.x,
+                y: j as isize + tiles_coords_of_a_chunk.y,
+            };
+            let item = if factor == 1 {
+                snapshot.and_then(|snapshot| snapshot.read(point))
+            } else {
+                lod_map.read(point, lod)
+            };
+            let mut color_exact = match item {
+                Some(item) => detail.apply(layer.colorizer.color(item, point), point, seed),
+                None => layer.colorizer.missing_color(),
+            };
+            let passable = item.and_then(|item| layer.colorizer.is_wall(item)) == Some(false);
+            if let (Some(strength), Some(snapshot)) = (edge_shading.filter(|_| passable), snapshot) {
+                let (sides, corners) = wall_neighbors(snapshot, &layer.colorizer, point);
+                let shade = edge_shading_factor(sides, corners, strength);
+                for channel in color_exact.iter_mut().take(3) {
+                    *channel = (*channel as f32 * shade) as u8;
+                }
             }
+            // image rows grow downwards
+            utils::fill_rect_rows(
+                pixels,
+                image_width,
+                i * tile_px,
+                total_px - (j + factor) * tile_px,
+                tile_px * factor,
+                tile_px * factor,
+                color_exact,
+            );
+        }
+    }
+}
+
+/// Hypertile coords of every `MapRevealActor`.
+fn focus_hypertiles(
+    player_query: &Query<&Transform, With<MapRevealActor>>,
+    tiles_per_image: TilesCount,
+) -> Vec<ChunkCoords> {
+    player_query
+        .iter()
+        .map(|transform| ChunkCoords::from_world(WorldPos(transform.translation.xy()), tiles_per_image))
+        .collect()
+}
+
+/// Distance in hypertiles to the nearest focus, `usize::MAX` without any.
+fn focus_distance(focus: &[ChunkCoords], shape: LoadShape, coords: ChunkCoords) -> usize {
+    focus
+        .iter()
+        .map(|f| shape.distance(coords.x - f.x, coords.y - f.y))
+        .min()
+        .unwrap_or(usize::MAX)
+}
+
+/// Render distance grown with the camera zoom, so zooming out still shows a covered area.
+fn zoomed_render_distance(render_distance: usize, camera_zoom: Option<Res<CameraZoom>>) -> usize {
+    let zoom = camera_zoom.map_or(1.0, |zoom| zoom.0.max(1.0));
+    (render_distance as f32 * zoom).ceil() as usize
+}
+
+pub fn background_load_unload_system<P: MapDataProducer, C: TileColorizer<P>>(
+    player_query: Query<&Transform, With<MapRevealActor>>,
+    layer: Res<TilemapLayer<P, C>>,
+    lod_map: Res<LodDataMap<P>>,
+    mut tracker: ResMut<HypertileTracker<P>>,
+    mut cache: ResMut<HypertileCache<P>>,
+    camera_zoom: Option<Res<CameraZoom>>,
+) {
+    let render_distance = zoomed_render_distance(layer.settings.render_distance, camera_zoom);
+    let focus = focus_hypertiles(&player_query, layer.settings.tiles_per_image());
+    for current_focus_chunk_coords in focus.iter() {
+        for coords in required_chunks(*current_focus_chunk_coords, layer.settings.shape, render_distance)
+        {
+            tracker.require(coords);
+        }
+    }
+
+    // Approached LOD hypertiles are redrawn at the finer LOD once its data is ready
+    let HypertileCache { lods, dirty, .. } = &mut *cache;
+    for (coords, lod) in lods.iter_mut() {
+        let distance = focus_distance(&focus, layer.settings.shape, *coords);
+        let wanted = layer.settings.lod_for_distance(distance, lod_map.chunk_dimension_tiles);
+        if wanted < *lod {
+            *lod = wanted;
+            dirty.insert(*coords);
         }
     }
 }
 
-pub fn background_load_required_chunks_system(
-    mut passability_map: ResMut<DataMap<PassabilityProducer>>,
-    mut tracker: ResMut<BackgroundHypertileTracker>,
+#[allow(clippy::too_many_arguments)]
+pub fn background_load_required_chunks_system<P: MapDataProducer, C: TileColorizer<P>>(
+    player_query: Query<&Transform, With<MapRevealActor>>,
+    mut data_map: ResMut<DataMap<P>>,
+    mut lod_map: ResMut<LodDataMap<P>>,
+    layer: Res<TilemapLayer<P, C>>,
+    mut tracker: ResMut<HypertileTracker<P>>,
+    mut cache: ResMut<HypertileCache<P>>,
     mut commands: Commands,
-    mut images: ResMut<bevy::asset::Assets<Image>>,
+    mut images: ResMut<Assets<Image>>,
+    mut loaded_events: EventReader<ChunkLoaded<P>>,
+    mut lod_loaded_events: EventReader<LodChunkLoaded<P>>,
+    edge_shading: Res<EdgeShading>,
+    detail: Res<TileDetail>,
+    seed: Option<Res<WorldSeed>>,
+    budget: Res<HypertileBudget>,
 ) {
+    let tiles_per_image = layer.settings.tiles_per_image();
+    let seed = seed.map_or(0, |seed| seed.0);
+    let edge_shading = edge_shading.enabled.then_some(edge_shading.strength);
+    for event in loaded_events.read() {
+        tracker.wake_up(event.coords, data_map.chunk_dimension_tiles, tiles_per_image);
+    }
+    for event in lod_loaded_events.read() {
+        tracker.wake_up(event.coords, lod_map.chunk_dimension_tiles, tiles_per_image);
+    }
     if tracker.requested.is_empty() {
         return;
     }
-    let mut deferred = HashSet::new();
-    let process_requested_chunk = |requested_chunk: &ChunkCoords| {
-        let real_coords_bottom_left = requested_chunk.to_world_pos(IMAGE_WIDTH_PX as f32); // our chunk size is image size
-        let tiles_coords_of_a_chunk = requested_chunk.to_bottom_left_tile_point(IMAGE_WIDTH_TILES);
-        let passability = passability_map.get_rounded_option(real_coords_bottom_left);
-        if passability.is_none() {
-            deferred.insert(requested_chunk.clone());
-            return;
-        }
-        let color = if passability.is_none() {
-            css::BEIGE.to_u8_array()
+    let hypertile_size = layer.settings.hypertile_size_units();
+    let focus = focus_hypertiles(&player_query, tiles_per_image);
+    // Nearest first, so the budget goes to what the player is about to see
+    let mut ordered: Vec<(usize, ChunkCoords)> = tracker
+        .requested
+        .iter()
+        .map(|coords| (focus_distance(&focus, layer.settings.shape, *coords), *coords))
+        .collect();
+    ordered.sort_unstable_by_key(|(distance, coords)| (*distance, coords.x, coords.y));
+
+    let mut drawn = 0;
+    for (distance, requested_chunk) in ordered {
+        if drawn >= budget.max_images_per_frame {
+            break;
+        }
+        let lod = layer.settings.lod_for_distance(distance, lod_map.chunk_dimension_tiles);
+        // A hypertile may straddle several data chunks, or a data chunk several hypertiles
+        let ready = if lod_factor(lod) == 1 {
+            hypertile_ready(&mut data_map, requested_chunk, tiles_per_image)
         } else {
-            if passability.unwrap().0 > 0 {
-                css::BLUE_VIOLET.to_u8_array()
-            } else {
-                css::STEEL_BLUE.to_u8_array()
-            }
+            hypertile_lod_ready(&mut lod_map, requested_chunk, tiles_per_image, lod)
         };
+        if !ready {
+            // Retried once a ChunkLoaded event arrives, without using up the budget
+            tracker.defer(requested_chunk);
+            continue;
+        }
 
         let mut image = Image::new_fill(
             // 2D image of size
             Extent3d {
-                width: IMAGE_WIDTH_PX,
-                height: IMAGE_HEIGHT_PX,
+                width: layer.settings.image_size_px,
+                height: layer.settings.image_size_px,
                 depth_or_array_layers: 1,
             },
             TextureDimension::D2,
-            // Initialize it with a beige color
-            &(color),
+            &layer.colorizer.missing_color(),
             // Use the same encoding as the color we set
             TextureFormat::Rgba8UnormSrgb,
-            RenderAssetUsages::RENDER_WORLD,
+            RenderAssetUsages::RENDER_WORLD | RenderAssetUsages::MAIN_WORLD,
         );
 
-        let tiles = IMAGE_WIDTH_TILES;
-        for i in 0..tiles {
-            for j in 0..tiles {
-                let p = passability_map.get_option(Point {
-                    x: i as isize + tiles_coords_of_a_chunk.x,
-                    y: j as isize + tiles_coords_of_a_chunk.y,
-                });
-                let color_exact = if p.is_none() {
-                    css::BEIGE.to_u8_array()
-                } else {
-                    if p.unwrap().0 > 10 {
-                        (i as u8 * 16, j as u8 * 16, 128, 255 as u8).into()
-                    } else {
-                        (i as u8 * 16, j as u8 * 16, 42, 255 as u8).into()
-                    }
-                };
-                let texture_y_px = j * TILE_SIZE_IN_UNITS_UNITS as usize;
-                let total_px = IMAGE_WIDTH_PX as usize;
-                utils::draw_rect_on_image(
-                    &mut image,
-                    i * TILE_SIZE_IN_UNITS_UNITS as usize,
-                    total_px - (texture_y_px as isize + TILE_SIZE_IN_UNITS_UNITS) as usize,
-                    TILE_SIZE_IN_UNITS_UNITS as usize,
-                    TILE_SIZE_IN_UNITS_UNITS as usize,
-                    color_exact,
-                );
-            }
-        }
+        let snapshot = (lod_factor(lod) == 1).then(|| hypertile_snapshot(&data_map, requested_chunk, tiles_per_image));
+        draw_hypertile(
+            snapshot.as_ref(),
+            &lod_map,
+            &layer,
+            requested_chunk,
+            lod,
+            edge_shading,
+            (&detail, seed),
+            &mut image,
+        );
+        match snapshot {
+            Some(snapshot) => cache.drawn_versions.insert(requested_chunk, snapshot.versions),
+            None => cache.drawn_versions.remove(&requested_chunk),
+        };
 
         let handle = images.add(image);
-        let offset: f32 = (IMAGE_WIDTH_TILES as f32 / 2.0 - 0.5) * TILE_SIZE_IN_UNITS_UNITS as f32;
-        let x = requested_chunk.x as f32 * IMAGE_HEIGHT_PX as f32 + offset;
-        let y = requested_chunk.y as f32 * IMAGE_WIDTH_PX as f32 + offset;
-        commands.spawn((
-            Sprite::from_image(handle),
-            Transform::from_xyz(x, y, -1.0),
-        ));
-    };
+        // tiles are centered on their coordinates, the sprite on its transform
+        let center = requested_chunk.corner(tiles_per_image) + Vec2::splat(hypertile_size / 2.0);
+        let mut sprite = Sprite::from_image(handle.clone());
+        sprite.custom_size = Some(Vec2::splat(hypertile_size));
+        let entity = commands
+            .spawn((
+                sprite,
+                Transform::from_translation(center.0.extend(layer.settings.z)),
+                CullBounds::square(hypertile_size),
+            ))
+            .id();
+        cache.hypertiles.insert(requested_chunk, (entity, handle));
+        cache.lods.insert(requested_chunk, lod);
+        tracker.mark_completed(requested_chunk);
+        drawn += 1;
+    }
+}
 
-    let _: Vec<_> = tracker
-        .requested
+/// Despawns hypertile sprites (and drops their images) once they are far from every `MapRevealActor`.
+pub fn background_unload_system<P: MapDataProducer, C: TileColorizer<P>>(
+    player_query: Query<&Transform, With<MapRevealActor>>,
+    layer: Res<TilemapLayer<P, C>>,
+    mut tracker: ResMut<HypertileTracker<P>>,
+    mut cache: ResMut<HypertileCache<P>>,
+    mut images: ResMut<Assets<Image>>,
+    mut commands: Commands,
+    camera_zoom: Option<Res<CameraZoom>>,
+) {
+    let focus = focus_hypertiles(&player_query, layer.settings.tiles_per_image());
+    if focus.is_empty() {
+        return; // nothing to measure against, keep everything
+    }
+    let keep_distance = zoomed_render_distance(layer.settings.render_distance, camera_zoom)
+        + layer.settings.unload_margin;
+    let shape = layer.settings.shape;
+    let is_far = |coords: &ChunkCoords| focus_distance(&focus, shape, *coords) > keep_distance;
+
+    let far: Vec<ChunkCoords> = cache.hypertiles.keys().filter(|&&c| is_far(&c)).copied().collect();
+    for coords in far {
+        if let Some((entity, handle)) = cache.hypertiles.remove(&coords) {
+            commands.entity(entity).despawn();
+            images.remove(&handle);
+        }
+        cache.dirty.remove(&coords);
+        cache.lods.remove(&coords);
+        cache.drawn_versions.remove(&coords);
+        tracker.spawned.remove(&coords);
+    }
+    tracker.waiting.retain(|coords| !is_far(coords));
+    tracker.requested.retain(|coords| !is_far(coords));
+}
+
+/// Redraws spawned hypertiles whose map data changed, without respawning their sprites.
+/// Hypertiles whose data at their LOD is not loaded keep their image until a load event marks them again.
+#[allow(clippy::too_many_arguments)]
+pub fn background_redraw_system<P: MapDataProducer, C: TileColorizer<P>>(
+    mut data_map: ResMut<DataMap<P>>,
+    mut lod_map: ResMut<LodDataMap<P>>,
+    layer: Res<TilemapLayer<P, C>>,
+    mut cache: ResMut<HypertileCache<P>>,
+    mut images: ResMut<Assets<Image>>,
+    mut loaded_events: EventReader<ChunkLoaded<P>>,
+    mut written_events: EventReader<ChunkWritten<P>>,
+    mut lod_loaded_events: EventReader<LodChunkLoaded<P>>,
+    edge_shading: Res<EdgeShading>,
+    detail: Res<TileDetail>,
+    seed: Option<Res<WorldSeed>>,
+) {
+    let chunk_dimension_tiles = data_map.chunk_dimension_tiles;
+    let tiles_per_image = layer.settings.tiles_per_image();
+    // Same data, drawn differently
+    let restyled = (edge_shading.is_changed() && !edge_shading.is_added()) || (detail.is_changed() && !detail.is_added());
+    if restyled {
+        let all: Vec<ChunkCoords> = cache.hypertiles.keys().copied().collect();
+        cache.dirty.extend(all);
+        cache.drawn_versions.clear();
+    }
+    let shading = edge_shading.enabled.then_some(edge_shading.strength);
+    let seed = seed.map_or(0, |seed| seed.0);
+    for event in loaded_events.read() {
+        // A neighbor chunk loading completes the shading along the edge of the ones already drawn
+        if shading.is_some() {
+            cache.invalidate_with_border(event.coords, chunk_dimension_tiles, tiles_per_image);
+        } else {
+            cache.invalidate(event.coords, chunk_dimension_tiles, tiles_per_image);
+        }
+    }
+    for event in written_events.read() {
+        cache.invalidate(event.coords, chunk_dimension_tiles, tiles_per_image);
+    }
+    for event in lod_loaded_events.read() {
+        cache.invalidate(event.coords, lod_map.chunk_dimension_tiles, tiles_per_image);
+    }
+
+    // Budgeted, the rest waits for the next frames
+    let batch: Vec<ChunkCoords> = cache
+        .dirty
         .iter()
-        .map(process_requested_chunk)
+        .take(layer.settings.max_redraws_per_frame)
+        .copied()
         .collect();
-    tracker.mark_all_requests_as_completed();
-    tracker.requested = deferred;
+    for coords in batch {
+        cache.dirty.remove(&coords);
+        let Some((_, handle)) = cache.hypertiles.get(&coords) else {
+            continue;
+        };
+        let lod = cache.lods.get(&coords).copied().unwrap_or(0);
+        let ready = if lod_factor(lod) == 1 {
+            hypertile_ready(&mut data_map, coords, tiles_per_image)
+        } else {
+            hypertile_lod_ready(&mut lod_map, coords, tiles_per_image, lod)
+        };
+        if !ready {
+            continue;
+        }
+        let full_resolution = lod_factor(lod) == 1;
+        // Marked dirty by an event that didn't change what this image was drawn from
+        let current = cache.drawn_versions.get(&coords);
+        if full_resolution && current.is_some_and(|versions| data_map.validate_versions(versions)) {
+            continue;
+        }
+        let snapshot = full_resolution.then(|| hypertile_snapshot(&data_map, coords, tiles_per_image));
+        if let Some(image) = images.get_mut(handle) {
+            draw_hypertile(snapshot.as_ref(), &lod_map, &layer, coords, lod, shading, (&detail, seed), image);
+        }
+        match snapshot {
+            Some(snapshot) => cache.drawn_versions.insert(coords, snapshot.versions),
+            None => cache.drawn_versions.remove(&coords),
+        };
+    }
+}
+
+/// Registers a debug tilemap layer for `DataMap<P>` at the given z, with default settings.
+/// The DataMap itself has to be registered separately (see `insert_chunked_plugin`).
+pub fn insert_tilemap_render_plugin<P, C>(app: &mut App, colorizer: C, layer: RenderLayerZ) -> &mut App
+where
+    P: MapDataProducer,
+    C: TileColorizer<P>,
+{
+    let settings = TilemapLayerSettings {
+        z: layer.z(),
+        ..Default::default()
+    };
+    insert_tilemap_render_plugin_with_settings::<P, C>(app, colorizer, settings)
+}
+
+/// Same as `insert_tilemap_render_plugin`, with custom image and tile sizes.
+/// Only one layer per producer type is supported, as trackers are keyed by the producer.
+pub fn insert_tilemap_render_plugin_with_settings<P, C>(
+    app: &mut App,
+    mut colorizer: C,
+    settings: TilemapLayerSettings,
+) -> &mut App
+where
+    P: MapDataProducer,
+    C: TileColorizer<P>,
+{
+    if let Err(err) = settings.validate() {
+        panic!("Tilemap layer {}: {err}", std::any::type_name::<C>());
+    }
+    app.init_resource::<WorldLayout>();
+    app.world_mut().resource_mut::<WorldLayout>().render_layers.push(RenderLayerLayout {
+        name: short_type_name::<C>(),
+        map: short_type_name::<P>(),
+        image_size_px: settings.image_size_px,
+        tile_size_px: settings.tile_size_px,
+        hypertile_tiles: settings.tiles_per_image(),
+        hypertile_size_units: settings.hypertile_size_units(),
+    });
+    colorizer.configure(&settings);
+    insert_lod_map::<P>(app);
+    app.init_resource::<EdgeShading>()
+        .init_resource::<TileDetail>()
+        .init_resource::<HypertileBudget>();
+    app.insert_resource(TilemapLayer::<P, C> {
+        colorizer,
+        settings,
+        _producer: PhantomData,
+    })
+    .insert_resource(HypertileTracker::<P>::default())
+    .insert_resource(HypertileCache::<P>::default())
+    .add_systems(
+        Update,
+        (
+            background_load_required_chunks_system::<P, C>,
+            background_load_unload_system::<P, C>,
+            background_redraw_system::<P, C>,
+            background_unload_system::<P, C>,
+        )
+            .run_if(render_enabled),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn passability_gradient_repeats_every_hypertile() {
+        let settings = TilemapLayerSettings {
+            image_size_px: 128, // 8 tiles of 16 px
+            tile_size_px: 16,
+            ..Default::default()
+        };
+        let mut colorizer = PassabilityColorizer::default();
+        colorizer.configure(&settings);
+        let color = |x, y| colorizer.color(Passability(0), Point { x, y });
+        assert_eq!(color(0, 0), color(8, -8));
+        assert_eq!(color(-1, 3), color(7, 11));
+        assert_ne!(color(0, 0), color(4, 0)); // Would repeat here with the default 4 tiles
+    }
+
+    #[test]
+    fn edge_shading_darkens_with_the_weighted_wall_count() {
+        let strength = 0.6;
+        assert_eq!(edge_shading_factor(0, 0, strength), 1.0);
+        // Out of a weight of 6, sides count 1 and corners 0.5
+        let close = |a: f32, b: f32| (a - b).abs() < 1e-6;
+        assert!(close(edge_shading_factor(3, 0, strength), 1.0 - strength * 3.0 / 6.0));
+        assert!(close(edge_shading_factor(2, 1, strength), 1.0 - strength * 2.5 / 6.0));
+        assert!(close(edge_shading_factor(0, 3, strength), 1.0 - strength * 1.5 / 6.0));
+        assert!(close(edge_shading_factor(4, 4, strength), 1.0 - strength));
+        assert!(close(edge_shading_factor(4, 4, 3.0), 0.0), "strength is capped at 1");
+    }
+
+    // Texel colors of every tile of `hypertile` drawn `tiles` tiles wide, by world tile
+    fn drawn_tiles(
+        map: &DataMap<TileTypeProducer>,
+        tiles: TilesCount,
+        hypertile: ChunkCoords,
+        seed: u64,
+    ) -> HashMap<(isize, isize), [u8; 4]> {
+        let settings = TilemapLayerSettings {
+            image_size_px: tiles as u32 * 16,
+            tile_size_px: 16,
+            lod_distance: None,
+            ..Default::default()
+        };
+        let layer = TilemapLayer::<TileTypeProducer, _> {
+            colorizer: TileTypeColorizer,
+            settings,
+            _producer: PhantomData,
+        };
+        let size = settings.image_size_px;
+        let mut image = Image::new_fill(
+            Extent3d { width: size, height: size, depth_or_array_layers: 1 },
+            TextureDimension::D2,
+            &[0, 0, 0, 0],
+            TextureFormat::Rgba8UnormSrgb,
+            RenderAssetUsages::MAIN_WORLD,
+        );
+        let snapshot = hypertile_snapshot(map, hypertile, tiles);
+        let lod_map = LodDataMap::<TileTypeProducer>::default();
+        draw_hypertile(Some(&snapshot), &lod_map, &layer, hypertile, 0, None, (&TileDetail::default(), seed), &mut image);
+
+        let origin = hypertile.to_bottom_left_tile_point(tiles);
+        let pixels = image.data.as_ref().unwrap();
+        let mut colors = HashMap::new();
+        for y in 0..tiles {
+            for x in 0..tiles {
+                let (px, row) = (x * 16 + 8, size as usize - y * 16 - 8); // Rows grow downwards
+                let at = (row * size as usize + px) * 4;
+                let color = pixels[at..at + 4].try_into().unwrap();
+                colors.insert((origin.x + x as isize, origin.y + y as isize), color);
+            }
+        }
+        colors
+    }
+
+    #[test]
+    fn tile_detail_depends_on_the_world_tile_and_the_seed_only() {
+        let mut map = DataMap::new(TileTypeProducer::default(), 16, 1);
+        for coords in ChunkCoords::iter_square(ChunkCoords { x: 0, y: 0 }, 1) {
+            map.load_generated_chunk(coords);
+        }
+        // Tiles 3 and 4 are on the edges of two hypertiles of 4, in the middle of one of 8
+        let mut small = drawn_tiles(&map, 4, ChunkCoords { x: 0, y: 0 }, 7);
+        small.extend(drawn_tiles(&map, 4, ChunkCoords { x: 1, y: 0 }, 7));
+        let large = drawn_tiles(&map, 8, ChunkCoords { x: 0, y: 0 }, 7);
+        for (tile, color) in small.iter() {
+            assert_eq!(large[tile], *color, "tile {tile:?}");
+        }
+        // Drawn the same again, whatever was drawn before
+        assert_eq!(drawn_tiles(&map, 8, ChunkCoords { x: 0, y: 0 }, 7), large);
+
+        let other_seed = drawn_tiles(&map, 8, ChunkCoords { x: 0, y: 0 }, 8);
+        let changed = large.iter().filter(|(tile, color)| other_seed[*tile] != **color).count();
+        assert!(changed > large.len() / 2, "only {changed} tiles changed with the seed");
+    }
+
+    #[test]
+    fn far_hypertiles_are_drawn_from_lod_data() {
+        let settings = TilemapLayerSettings::default(); // 4 tiles per hypertile, LOD 2 beyond 4 hypertiles
+        assert_eq!(settings.lod_for_distance(0, 16), 0);
+        assert_eq!(settings.lod_for_distance(4, 16), 0);
+        assert_eq!(settings.lod_for_distance(5, 16), 2);
+        assert_eq!(settings.lod_for_distance(50, 16), 2);
+        // LOD blocks that would straddle a chunk border fall back to full resolution
+        assert_eq!(settings.lod_for_distance(5, 15), 0);
+        let always_full = TilemapLayerSettings {
+            lod_distance: None,
+            ..settings
+        };
+        assert_eq!(always_full.lod_for_distance(50, 16), 0);
+    }
+
+    // Draws and redraws the hypertiles `tracker` requires over `map`, without any actor
+    fn hypertile_app(
+        map: DataMap<PassabilityProducer>,
+        settings: TilemapLayerSettings,
+        tracker: HypertileTracker<PassabilityProducer>,
+    ) -> App {
+        let mut colorizer = PassabilityColorizer::default();
+        colorizer.configure(&settings);
+        let mut app = App::new();
+        app.insert_resource(map)
+            .init_resource::<LodDataMap<PassabilityProducer>>()
+            .insert_resource(TilemapLayer::<PassabilityProducer, _> {
+                colorizer,
+                settings,
+                _producer: PhantomData,
+            })
+            .insert_resource(tracker)
+            .init_resource::<HypertileCache<PassabilityProducer>>()
+            .init_resource::<Assets<Image>>()
+            .init_resource::<EdgeShading>()
+            .init_resource::<TileDetail>()
+            .init_resource::<HypertileBudget>()
+            .add_event::<ChunkLoaded<PassabilityProducer>>()
+            .add_event::<ChunkWritten<PassabilityProducer>>()
+            .add_event::<LodChunkLoaded<PassabilityProducer>>()
+            .add_systems(
+                Update,
+                (
+                    background_load_required_chunks_system::<PassabilityProducer, PassabilityColorizer>,
+                    background_redraw_system::<PassabilityProducer, PassabilityColorizer>,
+                )
+                    .chain(),
+            );
+        app
+    }
+
+    #[test]
+    fn a_write_after_drawing_redraws_the_hypertile_once() {
+        let settings = TilemapLayerSettings {
+            lod_distance: None,
+            ..Default::default()
+        };
+        let mut map = DataMap::new(PassabilityProducer::default(), 16, 1);
+        for coords in ChunkCoords::iter_square(ChunkCoords { x: 0, y: 0 }, 1) {
+            map.load_generated_chunk(coords);
+        }
+        let hypertile = ChunkCoords { x: 0, y: 0 };
+        let mut tracker = HypertileTracker::<PassabilityProducer>::default();
+        tracker.require(hypertile);
+        let mut app = hypertile_app(map, settings, tracker);
+        app.update();
+
+        let drawn = |app: &App| {
+            let cache = app.world().resource::<HypertileCache<PassabilityProducer>>();
+            let (_, handle) = &cache.hypertiles[&hypertile];
+            let image = app.world().resource::<Assets<Image>>().get(handle).unwrap();
+            // Texel in tile 1, 1 of the 64 px image, rows counted from the top
+            let pixel = image.data.as_ref().unwrap()[(40 * 64 + 24) * 4..][..4].to_vec();
+            (cache.drawn_versions[&hypertile].clone(), pixel)
+        };
+        let (first_versions, free_pixel) = drawn(&app);
+
+        // Events that don't change what the image was drawn from are skipped
+        app.world_mut().send_event(ChunkLoaded::<PassabilityProducer>::new(ChunkCoords { x: -1, y: 0 }));
+        app.world_mut().send_event(ChunkWritten::<PassabilityProducer>::new(hypertile));
+        app.update();
+        assert_eq!(drawn(&app), (first_versions.clone(), free_pixel.clone()));
+
+        // Written after the snapshot the image was drawn from
+        let mut map = app.world_mut().resource_mut::<DataMap<PassabilityProducer>>();
+        map.write(Point { x: 1, y: 1 }, Passability::IMPASSABLE);
+        app.world_mut().send_event(ChunkWritten::<PassabilityProducer>::new(hypertile));
+        let mut redraws = 0;
+        let mut last = drawn(&app);
+        for _ in 0..5 {
+            app.update();
+            let current = drawn(&app);
+            if current.0 != last.0 {
+                redraws += 1;
+            }
+            last = current;
+        }
+        assert_eq!(redraws, 1);
+        assert_ne!(last.1, free_pixel, "the wall isn't drawn");
+        assert!(app.world().resource::<DataMap<PassabilityProducer>>().validate_versions(&last.0));
+    }
+
+    #[test]
+    fn a_hundred_requested_hypertiles_are_drawn_over_budgeted_frames() {
+        let settings = TilemapLayerSettings {
+            lod_distance: None, // Only full resolution data, loaded below
+            ..Default::default()
+        };
+        let mut map = DataMap::new(PassabilityProducer::default(), 16, 1);
+        // The 10x10 hypertiles of 4 tiles around the origin, over 4x4 chunks
+        for coords in ChunkCoords::iter_square(ChunkCoords { x: 0, y: 0 }, 2) {
+            map.load_generated_chunk(coords);
+        }
+        let mut tracker = HypertileTracker::<PassabilityProducer>::default();
+        for y in -5..5 {
+            for x in -5..5 {
+                tracker.require(ChunkCoords { x, y });
+            }
+        }
+        // Over chunks that aren't loaded, sorted first without an actor, and deferred for free
+        for y in -5..5 {
+            tracker.require(ChunkCoords { x: -100, y });
+        }
+        let mut app = hypertile_app(map, settings, tracker);
+
+        let budget = HypertileBudget::default().max_images_per_frame;
+        app.update();
+        let tracker = app.world().resource::<HypertileTracker<PassabilityProducer>>();
+        assert_eq!(tracker.spawned.len(), budget, "the deferred hypertiles used up the budget");
+        assert_eq!(tracker.waiting.len(), 10);
+        assert_eq!(tracker.requested.len(), 100 - budget);
+
+        let frames = 100usize.div_ceil(budget);
+        for _ in 1..frames - 1 {
+            app.update();
+        }
+        assert!(!app.world().resource::<HypertileTracker<PassabilityProducer>>().requested.is_empty());
+        app.update();
+        let tracker = app.world().resource::<HypertileTracker<PassabilityProducer>>();
+        assert!(tracker.requested.is_empty(), "not done after {frames} frames");
+        assert_eq!(tracker.spawned.len(), 100);
+        assert_eq!(app.world().resource::<Assets<Image>>().len(), 100);
+    }
+
+    #[test]
+    fn hypertile_over_a_missing_chunk_waits_for_it() {
+        // Hypertiles of 6 tiles over chunks of 16: hypertile 2 spans tiles 12 to 17, over two chunks
+        let tiles_per_image = 6;
+        let hypertile = ChunkCoords { x: 2, y: 0 };
+        let (left, right) = (ChunkCoords { x: 0, y: 0 }, ChunkCoords { x: 1, y: 0 });
+        let covering: Vec<ChunkCoords> = data_chunks_covering(hypertile, tiles_per_image, 16).collect();
+        assert_eq!(covering, vec![left, right]);
+
+        let mut map = DataMap::new(PassabilityProducer::default(), 16, 1);
+        map.load_generated_chunk(left);
+        assert!(!hypertile_ready(&mut map, hypertile, tiles_per_image));
+        assert!(map.requested_chunks.contains(&right), "the missing chunk isn't requested");
+
+        // Deferred instead of drawn with placeholders, until the other chunk's load event
+        let mut tracker = HypertileTracker::<PassabilityProducer>::default();
+        tracker.require(hypertile);
+        tracker.defer(hypertile);
+        assert!(tracker.waiting.contains(&hypertile) && tracker.requested.is_empty());
+        tracker.require(hypertile);
+        assert!(tracker.requested.is_empty(), "required again while waiting");
+
+        map.load_generated_chunk(right);
+        tracker.wake_up(right, 16, tiles_per_image);
+        assert!(tracker.requested.contains(&hypertile) && tracker.waiting.is_empty());
+        assert!(hypertile_ready(&mut map, hypertile, tiles_per_image));
+    }
 }
@@ -29,3 +29,28 @@ pub fn draw_rect_on_image(
         }
     }
 }
+
+/// Same as `draw_rect_on_image` over raw RGBA8 pixels of an image `image_width` pixels wide,
+/// one slice per row. Meant for drawing many rects in a row without re-borrowing the image.
+pub fn fill_rect_rows(
+    pixels: &mut [u8],
+    image_width: usize,
+    rect_x: usize,
+    rect_y: usize,
+    rect_width: usize,
+    rect_height: usize,
+    color: [u8; 4],
+) {
+    let image_height = pixels.len() / 4 / image_width.max(1);
+    let max_x = (rect_x + rect_width).min(image_width);
+    if rect_x >= max_x {
+        return;
+    }
+    for y in rect_y..(rect_y + rect_height).min(image_height) {
+        let row_start = (y * image_width + rect_x) * 4;
+        let row_end = (y * image_width + max_x) * 4;
+        for pixel in pixels[row_start..row_end].chunks_exact_mut(4) {
+            pixel.copy_from_slice(&color);
+        }
+    }
+}
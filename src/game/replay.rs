@@ -0,0 +1,340 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    core::{
+        chunks::{DataMap, WorldSeed},
+        units::WorldPos,
+    },
+    game::{
+        Player,
+        input::MoveIntent,
+        physix::{PrevXY, Velocity, bounce_back},
+        player_movement,
+        spawn::Dormant,
+        teleport::TeleportRequest,
+        world::passability::PassabilityProducer,
+    },
+};
+
+const REPLAY_PRELOAD_RADIUS_CHUNKS: usize = 1; // Playback waits for these around the start position
+
+/// Debug actions that change the world besides movement, replayed on the tick they were recorded.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ReplayAction {
+    Teleport { destination: [f32; 2] },
+}
+
+/// Input of one fixed tick.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct ReplayTick {
+    pub intent: [f32; 2], // The player's MoveIntent
+    pub actions: Vec<ReplayAction>,
+}
+
+/// Player position after a tick, compared during playback to detect divergence.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ReplayCheckpoint {
+    pub tick: u32,
+    pub position: [f32; 2],
+}
+
+/// A recorded run. The world is regenerated from the seed, so only the input is stored;
+/// tiles edited before the recording started aren't, replay from a fresh world.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct Replay {
+    pub world_seed: u64,
+    pub start_translation: [f32; 3],
+    pub ticks: Vec<ReplayTick>,
+    pub checkpoints: Vec<ReplayCheckpoint>,
+}
+
+impl Replay {
+    pub fn to_ron(&self) -> Result<String, ron::Error> {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+    }
+
+    pub fn from_ron(text: &str) -> Result<Self, ron::error::SpannedError> {
+        ron::from_str(text)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        fs::read_to_string(path)
+            .map_err(|err| err.to_string())
+            .and_then(|text| Replay::from_ron(&text).map_err(|err| err.to_string()))
+    }
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct ReplaySettings {
+    pub record_key: KeyCode, // Starts a recording, pressed again saves it
+    pub directory: PathBuf,  // Recordings go to `<directory>/<seed>/<unix secs>.ron`
+    pub checkpoint_interval_ticks: u32,
+    pub divergence_epsilon: f32, // World units a checkpoint may be off before it's reported
+}
+
+impl Default for ReplaySettings {
+    fn default() -> Self {
+        Self {
+            record_key: KeyCode::F10,
+            directory: PathBuf::from("replays"),
+            checkpoint_interval_ticks: 30, // Once a second at the default fixed rate
+            divergence_epsilon: 0.5,
+        }
+    }
+}
+
+/// Records the player's input each fixed tick while armed, see `ReplaySettings::record_key`.
+#[derive(Resource, Debug, Default)]
+pub struct ReplayRecorder {
+    pub recording: Option<Replay>,
+}
+
+impl ReplayRecorder {
+    pub fn is_armed(&self) -> bool {
+        self.recording.is_some()
+    }
+}
+
+/// Feeds a recording into the player instead of the live input. Removed when the recording
+/// ends, the live input takes over from there.
+#[derive(Resource, Debug)]
+pub struct ReplayPlayer {
+    pub replay: Replay,
+    pub tick: u32,
+    pub started: bool, // Player placed at the start position and its chunks loaded
+    pub divergences: u32,
+    pub exit_when_done: bool, // Quits the app after the last tick, for headless runs
+}
+
+impl ReplayPlayer {
+    pub fn new(replay: Replay) -> Self {
+        Self {
+            replay,
+            tick: 0,
+            started: false,
+            divergences: 0,
+            exit_when_done: false,
+        }
+    }
+}
+
+/// Run condition: a recording drives the player, its live input is ignored.
+pub fn replaying(player: Option<Res<ReplayPlayer>>) -> bool {
+    player.is_some()
+}
+
+/// F10 records the player's input, `ReplayPlayer` plays it back. The movement systems have to
+/// run in `FixedUpdate` and read the input only from `MoveIntent`, see `main`.
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ReplaySettings>()
+            .init_resource::<ReplayRecorder>()
+            .add_systems(Update, replay_record_key_system.run_if(not(replaying)))
+            .add_systems(
+                FixedUpdate,
+                (
+                    replay_input_system.before(player_movement),
+                    (record_tick_system, replay_checkpoint_system).after(bounce_back),
+                ),
+            );
+    }
+}
+
+fn player_position(transform: &Transform) -> [f32; 2] {
+    transform.translation.xy().to_array()
+}
+
+/// Starts and stops recordings. A stopped recording is written right away.
+fn replay_record_key_system(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    settings: Res<ReplaySettings>,
+    seed: Option<Res<WorldSeed>>,
+    mut recorder: ResMut<ReplayRecorder>,
+    player_query: Query<&Transform, (With<Player>, Without<Dormant>)>,
+) {
+    if !keyboard_input.just_pressed(settings.record_key) {
+        return;
+    }
+    if let Some(replay) = recorder.recording.take() {
+        save_replay(&settings, &replay);
+        return;
+    }
+    let Ok(transform) = player_query.single() else {
+        warn!("No player to record");
+        return;
+    };
+    recorder.recording = Some(Replay {
+        world_seed: seed.map(|seed| seed.0).unwrap_or_default(),
+        start_translation: transform.translation.to_array(),
+        ..default()
+    });
+    info!("Recording input, press {:?} again to stop", settings.record_key);
+}
+
+fn save_replay(settings: &ReplaySettings, replay: &Replay) {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    let path = settings
+        .directory
+        .join(replay.world_seed.to_string())
+        .join(format!("{secs}.ron"));
+    let result = replay
+        .to_ron()
+        .map_err(|err| err.to_string())
+        .and_then(|text| {
+            fs::create_dir_all(path.parent().unwrap_or(&settings.directory))
+                .and_then(|_| fs::write(&path, text))
+                .map_err(|err| err.to_string())
+        });
+    match result {
+        Ok(()) => info!("Recorded {} ticks to {}", replay.ticks.len(), path.display()),
+        Err(err) => warn!("Failed to write the recording to {}: {}", path.display(), err),
+    }
+}
+
+/// Appends the tick's intent and debug actions, and a checkpoint every few ticks.
+/// Runs after the physics so checkpoints hold the position the tick ended at.
+fn record_tick_system(
+    settings: Res<ReplaySettings>,
+    mut recorder: ResMut<ReplayRecorder>,
+    mut teleports: EventReader<TeleportRequest>,
+    player_query: Query<(&Transform, &MoveIntent), With<Player>>,
+) {
+    let actions: Vec<ReplayAction> = teleports
+        .read()
+        .map(|request| ReplayAction::Teleport {
+            destination: request.destination.to_array(),
+        })
+        .collect();
+    let (Some(replay), Ok((transform, intent))) = (recorder.recording.as_mut(), player_query.single()) else {
+        return;
+    };
+    let tick = replay.ticks.len() as u32;
+    replay.ticks.push(ReplayTick {
+        intent: intent.0.to_array(),
+        actions,
+    });
+    if (tick + 1).is_multiple_of(settings.checkpoint_interval_ticks.max(1)) {
+        replay.checkpoints.push(ReplayCheckpoint {
+            tick,
+            position: player_position(transform),
+        });
+    }
+}
+
+/// Places the player at the recorded start, waits for the chunks around it, then sets the
+/// player's `MoveIntent` from the recording one tick at a time.
+#[allow(clippy::type_complexity)]
+fn replay_input_system(
+    mut commands: Commands,
+    replay_player: Option<ResMut<ReplayPlayer>>,
+    seed: Option<Res<WorldSeed>>,
+    mut passability: ResMut<DataMap<PassabilityProducer>>,
+    mut teleports: EventWriter<TeleportRequest>,
+    mut exit: EventWriter<AppExit>,
+    mut player_query: Query<
+        (&mut Transform, &mut MoveIntent, Option<&mut PrevXY>, Option<&mut Velocity>),
+        (With<Player>, Without<Dormant>),
+    >,
+) {
+    let Some(mut replay_player) = replay_player else {
+        return;
+    };
+    let Ok((mut transform, mut intent, prev, velocity)) = player_query.single_mut() else {
+        return; // Not spawned yet
+    };
+
+    if !replay_player.started {
+        let seed = seed.map(|seed| seed.0).unwrap_or_default();
+        if seed != replay_player.replay.world_seed {
+            warn!(
+                "Replaying a recording of world seed {} in world {}, positions will diverge",
+                replay_player.replay.world_seed, seed
+            );
+        }
+        transform.translation = Vec3::from_array(replay_player.replay.start_translation);
+        // Collision handling must not pull the player back to the old position
+        if let Some(mut prev) = prev {
+            prev.0 = transform.translation;
+        }
+        if let Some(mut velocity) = velocity {
+            velocity.0 = Vec2::ZERO;
+        }
+        intent.0 = Vec2::ZERO;
+        let start = WorldPos(transform.translation.xy()).to_tile().0;
+        if !passability.is_loaded_around(start, REPLAY_PRELOAD_RADIUS_CHUNKS) {
+            passability.request_around(start, REPLAY_PRELOAD_RADIUS_CHUNKS);
+            return;
+        }
+        replay_player.started = true;
+        info!("Replaying {} ticks", replay_player.replay.ticks.len());
+    }
+
+    let Some(tick) = replay_player.replay.ticks.get(replay_player.tick as usize) else {
+        info!(
+            "Replay finished at {:?} with {} diverged checkpoints",
+            transform.translation.xy(),
+            replay_player.divergences
+        );
+        intent.0 = Vec2::ZERO;
+        if replay_player.exit_when_done {
+            exit.write(AppExit::Success);
+        }
+        commands.remove_resource::<ReplayPlayer>();
+        return;
+    };
+    intent.0 = Vec2::from_array(tick.intent);
+    for action in tick.actions.iter() {
+        match *action {
+            ReplayAction::Teleport { destination } => {
+                teleports.write(TeleportRequest {
+                    destination: Vec2::from_array(destination),
+                });
+            }
+        }
+    }
+}
+
+/// Compares the position the tick ended at with the recorded checkpoint, if there is one.
+fn replay_checkpoint_system(
+    settings: Res<ReplaySettings>,
+    replay_player: Option<ResMut<ReplayPlayer>>,
+    player_query: Query<&Transform, (With<Player>, Without<Dormant>)>,
+) {
+    let Some(mut replay_player) = replay_player else {
+        return;
+    };
+    if !replay_player.started {
+        return;
+    }
+    let tick = replay_player.tick;
+    replay_player.tick += 1;
+    let Ok(transform) = player_query.single() else {
+        return;
+    };
+    // Recorded in tick order
+    let checkpoints = &replay_player.replay.checkpoints;
+    let Ok(index) = checkpoints.binary_search_by_key(&tick, |c| c.tick) else {
+        return;
+    };
+    let checkpoint = checkpoints[index];
+    let position = transform.translation.xy();
+    let distance = position.distance(Vec2::from_array(checkpoint.position));
+    if distance > settings.divergence_epsilon {
+        replay_player.divergences += 1;
+        warn!(
+            "Replay diverged at tick {}: player at {:?}, recorded {:?} ({:.2} units off)",
+            tick, position, checkpoint.position, distance
+        );
+    }
+}
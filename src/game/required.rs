@@ -0,0 +1,132 @@
+use bevy::prelude::*;
+
+/// Logs a warning the first time only, for systems that skip their work while something they
+/// need is missing. Kept as a `Local` so each system warns once instead of every frame.
+#[derive(Debug, Default)]
+pub struct WarnOnce(bool);
+
+impl WarnOnce {
+    /// Logs `message` unless this already warned.
+    pub fn warn(&mut self, message: impl FnOnce() -> String) {
+        if !self.0 {
+            self.0 = true;
+            let message = message(); // warn! skips its arguments when nothing listens
+            warn!("{message}");
+        }
+    }
+
+    pub fn has_warned(&self) -> bool {
+        self.0
+    }
+}
+
+/// A singleton the app expects once Startup is done, see `require_single`.
+#[derive(Clone)]
+pub struct RequiredEntity {
+    pub name: &'static str,
+    pub hint: &'static str, // Where it should have been spawned, for the error
+    count: fn(&mut World) -> usize,
+}
+
+/// Every singleton registered with `require_single`, checked once by `verify_required_entities`.
+#[derive(Resource, Clone, Default)]
+pub struct RequiredEntities {
+    pub entities: Vec<RequiredEntity>,
+    pub reported: Vec<&'static str>, // Names missing or duplicated after Startup, each logged once
+}
+
+fn count_with<C: Component>(world: &mut World) -> usize {
+    world.query_filtered::<(), With<C>>().iter(world).count()
+}
+
+/// Expects exactly one entity with `C` once Startup is done. A missing or duplicated one is
+/// logged as an error naming `hint`, systems reading it skip their work instead of panicking.
+pub fn require_single<C: Component>(app: &mut App, name: &'static str, hint: &'static str) {
+    if !app.world().contains_resource::<RequiredEntities>() {
+        app.init_resource::<RequiredEntities>();
+        app.add_systems(PostStartup, verify_required_entities);
+    }
+    app.world_mut()
+        .resource_mut::<RequiredEntities>()
+        .entities
+        .push(RequiredEntity {
+            name,
+            hint,
+            count: count_with::<C>,
+        });
+}
+
+/// Runs once after Startup, when the commands of every Startup system are applied.
+pub fn verify_required_entities(world: &mut World) {
+    let Some(required) = world.get_resource::<RequiredEntities>().cloned() else {
+        return;
+    };
+    let mut reported = Vec::new();
+    for entity in required.entities.iter() {
+        match (entity.count)(world) {
+            1 => continue,
+            0 => error!(
+                "No {} after Startup, systems that need it will do nothing. {}",
+                entity.name, entity.hint
+            ),
+            count => error!(
+                "{count} entities are a {} after Startup, expected one. {}",
+                entity.name, entity.hint
+            ),
+        }
+        reported.push(entity.name);
+    }
+    if let Some(mut required) = world.get_resource_mut::<RequiredEntities>() {
+        required.reported = reported;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+    use crate::{
+        Pallete,
+        core::chunks::DataMap,
+        game::{
+            Player, player_movement,
+            camera::FollowCamera,
+            world::passability::{PassabilityProducer, check_player_passability},
+        },
+    };
+
+    #[test]
+    fn warn_once_logs_the_first_time_only() {
+        let mut warn_once = WarnOnce::default();
+        let calls = Cell::new(0);
+        for _ in 0..5 {
+            warn_once.warn(|| {
+                calls.set(calls.get() + 1);
+                "missing".to_string()
+            });
+        }
+        assert_eq!(calls.get(), 1);
+        assert!(warn_once.has_warned());
+    }
+
+    #[test]
+    fn missing_player_is_reported_once_and_nothing_panics() {
+        let mut app = App::new();
+        app.insert_resource(DataMap::new(PassabilityProducer::default(), 16, 1))
+            .init_resource::<Pallete>()
+            .add_systems(Update, (player_movement, check_player_passability));
+        require_single::<Player>(&mut app, "player", "It is spawned by setup_game.");
+        require_single::<FollowCamera>(&mut app, "follow camera", "Spawned twice on purpose.");
+        app.add_systems(Startup, |mut commands: Commands| {
+            commands.spawn(FollowCamera::default());
+            commands.spawn(FollowCamera::default());
+        });
+
+        for _ in 0..10 {
+            app.update(); // The player systems skip their work without a player
+        }
+        let required = app.world().resource::<RequiredEntities>();
+        assert_eq!(required.reported, ["player", "follow camera"]);
+    }
+}
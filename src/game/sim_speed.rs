@@ -0,0 +1,172 @@
+use bevy::{
+    app::{FixedMain, RunFixedMainLoop, RunFixedMainLoopSystem},
+    prelude::*,
+};
+
+/// How fast the world runs. Everything reading `Res<Time>` follows it: the physics in
+/// `FixedUpdate`, NPCs, the tide, light animations and the day-night cycle. Chunk loading
+/// and the camera don't, so generation can be inspected while paused.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Default)]
+pub enum SimulationSpeed {
+    Paused,
+    #[default]
+    Normal,
+    Slow(f32),        // Factor below 1.0
+    FastForward(f32), // Factor above 1.0
+}
+
+impl SimulationSpeed {
+    /// Speed running at `factor` times the normal speed.
+    pub fn from_factor(factor: f32) -> Self {
+        if factor <= 0.0 {
+            SimulationSpeed::Paused
+        } else if factor < 1.0 {
+            SimulationSpeed::Slow(factor)
+        } else if factor > 1.0 {
+            SimulationSpeed::FastForward(factor)
+        } else {
+            SimulationSpeed::Normal
+        }
+    }
+
+    /// Multiplier applied to the virtual clock, 0.0 when paused.
+    pub fn factor(&self) -> f32 {
+        match *self {
+            SimulationSpeed::Paused => 0.0,
+            SimulationSpeed::Normal => 1.0,
+            SimulationSpeed::Slow(factor) | SimulationSpeed::FastForward(factor) => factor.max(0.0),
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.factor() == 0.0
+    }
+
+    /// The only place the speed reaches the clocks: `Time<Virtual>` drives `Res<Time>` in
+    /// `Update` and the accumulator of `Time<Fixed>`, so nothing else has to check for pauses.
+    pub fn apply_to(&self, time: &mut Time<Virtual>) {
+        let factor = self.factor();
+        if factor == 0.0 {
+            time.pause();
+        } else {
+            time.set_relative_speed(factor);
+            time.unpause();
+        }
+    }
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct SimulationSpeedSettings {
+    pub pause_key: KeyCode,  // Toggles between paused and the last running speed
+    pub slower_key: KeyCode,
+    pub faster_key: KeyCode,
+    pub step_key: KeyCode,   // Runs a single fixed tick while paused
+    pub factors: Vec<f32>,   // Running speeds the slower and faster keys step through, ascending
+}
+
+impl Default for SimulationSpeedSettings {
+    fn default() -> Self {
+        Self {
+            pause_key: KeyCode::Space,
+            slower_key: KeyCode::Comma,
+            faster_key: KeyCode::Period,
+            step_key: KeyCode::KeyN,
+            factors: vec![0.125, 0.25, 0.5, 1.0, 2.0, 4.0, 8.0],
+        }
+    }
+}
+
+/// Fixed ticks requested by the step key, run on the next frame.
+#[derive(Resource, Debug, Clone, Copy, Default)]
+pub struct PendingSimulationSteps(pub u32);
+
+pub struct SimulationSpeedPlugin;
+
+impl Plugin for SimulationSpeedPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SimulationSpeed>()
+            .init_resource::<SimulationSpeedSettings>()
+            .init_resource::<PendingSimulationSteps>()
+            .add_systems(
+                Update,
+                (
+                    simulation_speed_controls,
+                    apply_simulation_speed.run_if(resource_changed::<SimulationSpeed>),
+                )
+                    .chain(),
+            )
+            .add_systems(
+                RunFixedMainLoop,
+                run_pending_simulation_steps.in_set(RunFixedMainLoopSystem::AfterFixedMainLoop),
+            );
+    }
+}
+
+/// Space pauses and resumes, comma and period step through `SimulationSpeedSettings::factors`,
+/// N queues a single fixed tick while paused.
+pub fn simulation_speed_controls(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    settings: Res<SimulationSpeedSettings>,
+    mut speed: ResMut<SimulationSpeed>,
+    mut pending: ResMut<PendingSimulationSteps>,
+    mut resume: Local<Option<SimulationSpeed>>,
+) {
+    if keyboard_input.just_pressed(settings.pause_key) {
+        if speed.is_paused() {
+            *speed = resume.take().unwrap_or_default();
+        } else {
+            *resume = Some(*speed);
+            *speed = SimulationSpeed::Paused;
+        }
+        info!("Simulation speed: {:?}", *speed);
+    }
+
+    let slower = keyboard_input.just_pressed(settings.slower_key);
+    let faster = keyboard_input.just_pressed(settings.faster_key);
+    if slower != faster && !settings.factors.is_empty() {
+        // While paused the change applies to the speed resumed with
+        let current = if speed.is_paused() {
+            resume.unwrap_or_default().factor()
+        } else {
+            speed.factor()
+        };
+        let factors = &settings.factors;
+        let next = if faster {
+            factors.iter().copied().find(|&f| f > current).unwrap_or(factors[factors.len() - 1])
+        } else {
+            factors.iter().rev().copied().find(|&f| f < current).unwrap_or(factors[0])
+        };
+        let next = SimulationSpeed::from_factor(next);
+        if speed.is_paused() {
+            *resume = Some(next);
+        } else {
+            *speed = next;
+        }
+        info!("Simulation speed: {:?}", next);
+    }
+
+    if speed.is_paused() && keyboard_input.just_pressed(settings.step_key) {
+        pending.0 += 1;
+    }
+}
+
+fn apply_simulation_speed(speed: Res<SimulationSpeed>, mut time: ResMut<Time<Virtual>>) {
+    speed.apply_to(&mut time);
+}
+
+/// Runs the fixed schedule once per pending step. The paused virtual clock doesn't feed the
+/// fixed accumulator, so these are the only ticks happening meanwhile.
+fn run_pending_simulation_steps(world: &mut World) {
+    let steps = std::mem::take(&mut world.resource_mut::<PendingSimulationSteps>().0);
+    if steps == 0 || !world.resource::<SimulationSpeed>().is_paused() {
+        return;
+    }
+    for _ in 0..steps {
+        // Systems in FixedUpdate see the fixed clock as `Res<Time>`, same as in a regular tick
+        let fixed = world.resource::<Time<Fixed>>().as_generic();
+        *world.resource_mut::<Time>() = fixed;
+        world.run_schedule(FixedMain);
+    }
+    let virtual_time = world.resource::<Time<Virtual>>().as_generic();
+    *world.resource_mut::<Time>() = virtual_time;
+}
@@ -0,0 +1,121 @@
+use bevy::{
+    ecs::{
+        component::Component,
+        entity::Entity,
+        query::With,
+        resource::Resource,
+        system::{Commands, Query, Res},
+    },
+    log::{info, warn},
+    math::Vec2,
+    render::view::Visibility,
+    state::state::State,
+    transform::components::Transform,
+};
+
+use crate::{
+    core::{
+        basics::Point,
+        chunks::{ChunkCoords, DataMap},
+        units::{TileCoord, TilesCount},
+    },
+    game::{
+        Player,
+        bootstrap::{WorldBootstrap, bootstrapped},
+        physix::{Collider, PrevXY},
+        world::{
+            passability::{Passability, PassabilityProducer},
+            region::find_nearest_point,
+        },
+    },
+};
+
+const SPAWN_SEARCH_RADIUS_TILES: TilesCount = 32;
+
+/// Tile the player should spawn at, or on the nearest free tile around it.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpawnPoint(pub Point);
+
+impl Default for SpawnPoint {
+    fn default() -> Self {
+        Self(Point::new(0, 0)) // World origin
+    }
+}
+
+/// Player not placed yet: hidden and ignoring input until `finalize_spawn` finds it a free tile.
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct Dormant;
+
+pub fn tile_to_world(tile: Point) -> Vec2 {
+    TileCoord(tile).center().0
+}
+
+/// Whether the tile and all 8 around it are loaded and at least `threshold`, so a collider fits.
+pub fn is_free_spot(passability: &DataMap<PassabilityProducer>, tile: Point, threshold: u8) -> bool {
+    (-1..=1).all(|dx| {
+        (-1..=1).all(|dy| {
+            passability
+                .read(Point {
+                    x: tile.x + dx,
+                    y: tile.y + dy,
+                })
+                .is_some_and(|p| p.0 >= threshold)
+        })
+    })
+}
+
+/// Nearest free spot to `center` within the search radius.
+/// Used by spawning, teleports and the tides, see `find_nearest_point`.
+pub fn find_spawn_tile(
+    passability: &DataMap<PassabilityProducer>,
+    center: Point,
+    threshold: u8,
+) -> Option<Point> {
+    find_nearest_point(center, SPAWN_SEARCH_RADIUS_TILES, |tile| {
+        is_free_spot(passability, tile, threshold)
+    })
+}
+
+type DormantPlayerQuery<'w, 's> = Query<
+    'w,
+    's,
+    (Entity, &'static mut Transform, &'static mut Visibility, Option<&'static Collider>, Option<&'static mut PrevXY>),
+    (With<Player>, With<Dormant>),
+>;
+
+// Places dormant players once the chunk of the spawn point is loaded and the world bootstrap
+// is over, then wakes them up
+pub fn finalize_spawn(
+    mut commands: Commands,
+    spawn_point: Res<SpawnPoint>,
+    bootstrap: Option<Res<State<WorldBootstrap>>>,
+    passability: Res<DataMap<PassabilityProducer>>,
+    mut query: DormantPlayerQuery,
+) {
+    let spawn_chunk = ChunkCoords::from_point(spawn_point.0, passability.chunk_dimension_tiles);
+    let spawn_loaded = passability.loaded_chunks.contains_key(&spawn_chunk) && bootstrapped(bootstrap);
+
+    for (entity, mut transform, mut visibility, collider, prev) in query.iter_mut() {
+        let mut target = tile_to_world(spawn_point.0);
+        if spawn_loaded {
+            let threshold = collider.map_or(Passability::WALL_THRESHOLD, |c| c.threshold);
+            match find_spawn_tile(&passability, spawn_point.0, threshold) {
+                Some(tile) => {
+                    info!("Player spawned at tile {:?}", tile);
+                    target = tile_to_world(tile);
+                }
+                None => warn!(
+                    "No free tile within {} tiles of spawn point {:?}, spawning there anyway",
+                    SPAWN_SEARCH_RADIUS_TILES, spawn_point.0
+                ),
+            }
+            *visibility = Visibility::Inherited;
+            commands.entity(entity).remove::<Dormant>();
+        }
+        // While waiting, stay at the spawn point so its chunks get loaded around the player
+        transform.translation = target.extend(transform.translation.z);
+        if let Some(mut prev) = prev {
+            prev.0 = transform.translation;
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use bevy::prelude::*;
+
+use crate::{
+    Pallete,
+    core::{
+        basics::Point,
+        chunks::{ChunkPreloadHooks, DataMap},
+        units::WorldPos,
+    },
+    game::{
+        MapRevealActor, Player,
+        physix::{Collider, PrevXY, Velocity},
+        spawn::{find_spawn_tile, tile_to_world},
+        world::passability::{Passability, PassabilityProducer},
+    },
+};
+
+const PRELOAD_RADIUS_CHUNKS: usize = 1; // The 3x3 chunks around the destination
+
+/// Moves the player to `destination` once the chunks around it are loaded in every map.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct TeleportRequest {
+    pub destination: Vec2,
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct TeleportSettings {
+    pub timeout_secs: f32, // Teleports still waiting for chunks after this are cancelled
+    pub debug_key: KeyCode,
+    pub debug_offset: Vec2, // Added to the player position by the debug key
+}
+
+impl Default for TeleportSettings {
+    fn default() -> Self {
+        Self {
+            timeout_secs: 10.0,
+            debug_key: KeyCode::KeyT,
+            debug_offset: Vec2::new(500.0, 500.0),
+        }
+    }
+}
+
+/// Player frozen until its destination is loaded. The anchor keeps the destination chunks loaded meanwhile.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Teleporting {
+    pub destination: Point,
+    pub anchor: Entity,
+    pub started_secs: f32,
+}
+
+pub struct TeleportPlugin;
+
+impl Plugin for TeleportPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TeleportSettings>()
+            .init_resource::<ChunkPreloadHooks>()
+            .add_event::<TeleportRequest>()
+            .add_systems(Update, (debug_teleport_key_system, teleport_system).chain());
+    }
+}
+
+fn debug_teleport_key_system(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    settings: Res<TeleportSettings>,
+    player_query: Query<&Transform, With<Player>>,
+    mut requests: EventWriter<TeleportRequest>,
+) {
+    if !keyboard_input.just_pressed(settings.debug_key) {
+        return;
+    }
+    if let Ok(transform) = player_query.single() {
+        requests.write(TeleportRequest {
+            destination: transform.translation.xy() + settings.debug_offset,
+        });
+    }
+}
+
+// Freezes the player and preloads the destination in every DataMap
+fn start_teleport(world: &mut World, request: TeleportRequest) {
+    let mut player_query =
+        world.query_filtered::<(Entity, Option<&Teleporting>), With<Player>>();
+    let Ok((player, previous)) = player_query.single(world) else {
+        return;
+    };
+    if let Some(previous) = previous.copied() {
+        world.despawn(previous.anchor); // The newest request wins
+    }
+
+    let destination = WorldPos(request.destination).to_tile().0;
+    let anchor = world
+        .spawn((
+            Transform::from_translation(tile_to_world(destination).extend(0.0)),
+            MapRevealActor {
+                radius_chunks: PRELOAD_RADIUS_CHUNKS,
+                priority: u8::MAX,
+            },
+        ))
+        .id();
+    let hooks = world.resource::<ChunkPreloadHooks>().clone();
+    hooks.request_all(world, destination, PRELOAD_RADIUS_CHUNKS);
+
+    let started_secs = world.resource::<Time>().elapsed_secs();
+    let frozen = world.resource::<Pallete>().colors.get("frozen").cloned();
+    let mut player_entity = world.entity_mut(player);
+    player_entity.insert(Teleporting {
+        destination,
+        anchor,
+        started_secs,
+    });
+    if let Some(mut velocity) = player_entity.get_mut::<Velocity>() {
+        velocity.0 = Vec2::ZERO;
+    }
+    if let (Some(frozen), Some(mut material)) =
+        (frozen, player_entity.get_mut::<MeshMaterial2d<ColorMaterial>>())
+    {
+        material.0 = frozen;
+    }
+    info!("Teleporting to tile {:?}, waiting for its chunks", destination);
+}
+
+// Places the player on the nearest free tile around the destination, like the spawn logic
+fn finish_teleport(world: &mut World, player: Entity, teleporting: Teleporting) {
+    let threshold = world
+        .get::<Collider>(player)
+        .map_or(Passability::WALL_THRESHOLD, |c| c.threshold);
+    let tile = find_spawn_tile(
+        world.resource::<DataMap<PassabilityProducer>>(),
+        teleporting.destination,
+        threshold,
+    );
+    let tile = tile.unwrap_or_else(|| {
+        warn!("No free tile around teleport destination {:?}, placing the player there anyway", teleporting.destination);
+        teleporting.destination
+    });
+
+    let mut player_entity = world.entity_mut(player);
+    if let Some(mut transform) = player_entity.get_mut::<Transform>() {
+        transform.translation = tile_to_world(tile).extend(transform.translation.z);
+        let translation = transform.translation;
+        if let Some(mut prev) = player_entity.get_mut::<PrevXY>() {
+            prev.0 = translation;
+        }
+    }
+    player_entity.remove::<Teleporting>();
+    world.despawn(teleporting.anchor);
+    info!("Teleported to tile {:?}", tile);
+}
+
+/// Starts requested teleports, then completes or cancels the pending one.
+pub fn teleport_system(world: &mut World) {
+    let requests: Vec<TeleportRequest> =
+        world.resource_mut::<Events<TeleportRequest>>().drain().collect();
+    for request in requests {
+        start_teleport(world, request);
+    }
+
+    let mut player_query = world.query_filtered::<(Entity, &Teleporting), With<Player>>();
+    let Ok((player, teleporting)) = player_query.single(world) else {
+        return;
+    };
+    let teleporting = *teleporting;
+
+    let hooks = world.resource::<ChunkPreloadHooks>().clone();
+    if hooks.all_ready(world, teleporting.destination, PRELOAD_RADIUS_CHUNKS) {
+        finish_teleport(world, player, teleporting);
+        return;
+    }
+
+    let waited = world.resource::<Time>().elapsed_secs() - teleporting.started_secs;
+    if waited > world.resource::<TeleportSettings>().timeout_secs {
+        warn!(
+            "Teleport to tile {:?} cancelled, its chunks were not loaded after {:.1} s",
+            teleporting.destination, waited
+        );
+        world.entity_mut(player).remove::<Teleporting>();
+        world.despawn(teleporting.anchor);
+    }
+}
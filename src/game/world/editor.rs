@@ -0,0 +1,109 @@
+use bevy::{
+    app::{App, Plugin, Update},
+    ecs::{
+        query::With,
+        system::{Local, Query, Res, ResMut},
+    },
+    input::{ButtonInput, keyboard::KeyCode, mouse::MouseButton},
+    math::Vec3Swizzles,
+    render::camera::Projection,
+    transform::components::Transform,
+    window::{PrimaryWindow, Window},
+};
+
+use crate::{
+    core::{basics::Point, chunks::DataMap, units::WorldPos},
+    game::{
+        camera::{FollowCamera, screen_to_world},
+        debug::DebugOverlaySettings,
+        render::light_sim::pbr_cell::{PbrCell, PbrCellProducer},
+        world::{
+            passability::{Passability, PassabilityProducer},
+            tile_types::{TileType, TileTypeProducer},
+        },
+    },
+};
+
+/// Paints walls with the mouse: left button places them, right button clears them,
+/// holding shift paints 3x3 tiles at once.
+pub struct TileEditorPlugin;
+
+impl Plugin for TileEditorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, tile_editor_system);
+    }
+}
+
+/// Tile under the cursor, `None` when the cursor is outside the window.
+fn cursor_tile(
+    window: &Window,
+    camera_transform: &Transform,
+    projection: &Projection,
+) -> Option<Point> {
+    let cursor = window.cursor_position()?;
+    let scale = match projection {
+        Projection::Orthographic(ortho) => ortho.scale,
+        _ => 1.0,
+    };
+    let world = screen_to_world(cursor, window.size(), camera_transform.translation.xy(), scale);
+    Some(WorldPos(world).to_tile().0)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn tile_editor_system(
+    mouse: Res<ButtonInput<MouseButton>>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Transform, &Projection), With<FollowCamera>>,
+    mut passability: ResMut<DataMap<PassabilityProducer>>,
+    mut pbr_cells: ResMut<DataMap<PbrCellProducer>>,
+    mut tile_types: ResMut<DataMap<TileTypeProducer>>,
+    mut last_painted: Local<Option<(Point, bool, bool)>>, // Tile, wall, brush
+    debug_settings: Option<Res<DebugOverlaySettings>>,
+) {
+    if debug_settings.is_some_and(|settings| settings.show_chunk_inspector) {
+        *last_painted = None;
+        return; // Clicks select chunks for the inspector
+    }
+    let wall = if mouse.pressed(MouseButton::Left) {
+        true
+    } else if mouse.pressed(MouseButton::Right) {
+        false
+    } else {
+        *last_painted = None;
+        return;
+    };
+    let (Ok(window), Ok((camera_transform, projection))) =
+        (window_query.single(), camera_query.single())
+    else {
+        return;
+    };
+    let Some(tile) = cursor_tile(window, camera_transform, projection) else {
+        return;
+    };
+    let brush = keyboard_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    if *last_painted == Some((tile, wall, brush)) {
+        return; // Holding the button still, nothing new to paint
+    }
+    *last_painted = Some((tile, wall, brush));
+
+    // All maps at once, so collisions, lighting and the background agree right away
+    let (passability_value, pbr_value, tile_type) = if wall {
+        (Passability::IMPASSABLE, PbrCell::SOLID_WALL, TileType::Rock)
+    } else {
+        (Passability::FREE, PbrCell::default(), TileType::Grass)
+    };
+    if brush {
+        let bottom_left = Point {
+            x: tile.x - 1,
+            y: tile.y - 1,
+        };
+        passability.fill_rect(bottom_left, 3, 3, passability_value);
+        pbr_cells.fill_rect(bottom_left, 3, 3, pbr_value);
+        tile_types.fill_rect(bottom_left, 3, 3, tile_type);
+    } else {
+        passability.write(tile, passability_value);
+        pbr_cells.write(tile, pbr_value);
+        tile_types.write(tile, tile_type);
+    }
+}
@@ -0,0 +1,119 @@
+use bevy::prelude::*;
+
+use crate::{
+    core::{
+        basics::Point,
+        chunks::{ChunkCoords, ChunkedMapConfig, DataChunk, DataMap, FlatGrid, MapDataProducer},
+        savegame::SaveableProducer,
+        units::{TilesCount, WorldPos},
+    },
+    game::{Player, debug::insert_chunked_plugin_with_debug, spawn::Dormant},
+};
+
+pub const UNEXPLORED: u8 = 0;
+pub const FULLY_EXPLORED: u8 = u8::MAX;
+
+/// How far the player sees and how fast what it sees becomes explored.
+#[derive(Resource, Debug, Clone)]
+pub struct ExplorationSettings {
+    pub sight_radius_tiles: TilesCount,
+    pub gain_per_tick: u8,          // Added to the level of every visible tile each fixed tick
+    pub max_writes_per_tick: usize, // Nearest tiles first, the rest catch up on the next ticks
+}
+
+impl Default for ExplorationSettings {
+    fn default() -> Self {
+        Self {
+            sight_radius_tiles: 8,
+            gain_per_tick: 32,
+            max_writes_per_tick: 256,
+        }
+    }
+}
+
+impl ExplorationSettings {
+    /// Whether a tile `offset` away from the player is within sight.
+    pub fn in_sight(&self, offset: Point) -> bool {
+        let radius = self.sight_radius_tiles as isize;
+        offset.x * offset.x + offset.y * offset.y <= radius * radius
+    }
+}
+
+// Exploration DataProducer, every tile starts unexplored
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ExplorationProducer;
+
+impl MapDataProducer for ExplorationProducer {
+    type Item = u8;
+    type GridType = FlatGrid<u8>;
+
+    fn default_value(&self) -> Self::Item {
+        UNEXPLORED
+    }
+
+    fn generate_chunk(&self, _coords: ChunkCoords, dimension_tiles: TilesCount) -> DataChunk<Self::GridType> {
+        DataChunk::new(FlatGrid::new(dimension_tiles, UNEXPLORED))
+    }
+}
+
+impl SaveableProducer for ExplorationProducer {
+    const SAVE_NAME: &'static str = "exploration";
+}
+
+/// Registers the exploration map and raises the exploration level around the player.
+pub struct ExplorationPlugin;
+
+impl Plugin for ExplorationPlugin {
+    fn build(&self, app: &mut App) {
+        insert_chunked_plugin_with_debug(app, ExplorationProducer, ChunkedMapConfig::default());
+        app.init_resource::<ExplorationSettings>()
+            .add_systems(FixedUpdate, explore_around_player_system);
+    }
+}
+
+/// Offsets within sight, nearest first.
+fn sight_offsets(settings: &ExplorationSettings) -> Vec<Point> {
+    let radius = settings.sight_radius_tiles as isize;
+    let mut offsets: Vec<Point> = (-radius..=radius)
+        .flat_map(|x| (-radius..=radius).map(move |y| Point { x, y }))
+        .filter(|offset| settings.in_sight(*offset))
+        .collect();
+    offsets.sort_by_key(|offset| (offset.x * offset.x + offset.y * offset.y, offset.x, offset.y));
+    offsets
+}
+
+/// Raises the exploration level of the loaded tiles within sight of the player, up to
+/// `max_writes_per_tick` of them. Fully explored tiles aren't written again.
+pub fn explore_around_player_system(
+    settings: Res<ExplorationSettings>,
+    mut exploration: ResMut<DataMap<ExplorationProducer>>,
+    player_query: Query<&Transform, (With<Player>, Without<Dormant>)>,
+    mut offsets: Local<Option<(TilesCount, Vec<Point>)>>,
+) {
+    let Ok(player_transform) = player_query.single() else {
+        return;
+    };
+    if offsets.as_ref().is_none_or(|(radius, _)| *radius != settings.sight_radius_tiles) {
+        *offsets = Some((settings.sight_radius_tiles, sight_offsets(&settings)));
+    }
+    let Some((_, offsets)) = offsets.as_ref() else {
+        return;
+    };
+
+    let center = WorldPos(player_transform.translation.xy()).to_tile().0;
+    let mut writes = 0;
+    for offset in offsets {
+        if writes >= settings.max_writes_per_tick {
+            break;
+        }
+        let point = center + *offset;
+        let Some(level) = exploration.read(point) else {
+            continue; // Explored once the chunk is loaded and still in sight
+        };
+        if level == FULLY_EXPLORED {
+            continue;
+        }
+        exploration.write(point, level.saturating_add(settings.gain_per_tick));
+        writes += 1;
+    }
+}
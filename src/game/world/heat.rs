@@ -0,0 +1,110 @@
+use bevy::prelude::*;
+
+use crate::{
+    core::{
+        basics::Point,
+        chunks::{ChunkCoords, ChunkedMapConfig, DataChunk, DataMap, FlatGrid, MapDataProducer},
+        decay::{DecayPlugin, Decayable},
+        units::{TilesCount, WorldPos},
+    },
+    game::{Player, debug::insert_chunked_plugin_with_debug, inspector::{DebugSummarize, register_chunk_summary}, spawn::Dormant},
+};
+
+pub const MAX_HEAT: f32 = 100.0;
+
+/// Warmth left on a tile by whoever stood there, fading back to 0.0.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Heat(pub f32);
+
+impl Decayable for Heat {
+    fn decay(self, dt: f32, rate: f32) -> Self {
+        Heat(self.0.decay(dt, rate))
+    }
+
+    fn is_at_rest(&self) -> bool {
+        self.0.is_at_rest()
+    }
+}
+
+impl DebugSummarize for Heat {
+    const DEBUG_RANGE: (f32, f32) = (0.0, MAX_HEAT);
+
+    fn debug_value(&self) -> f32 {
+        self.0
+    }
+}
+
+// Heat DataProducer, every tile starts cold
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HeatProducer;
+
+impl MapDataProducer for HeatProducer {
+    type Item = Heat;
+    type GridType = FlatGrid<Heat>;
+
+    fn default_value(&self) -> Self::Item {
+        Heat::default()
+    }
+
+    fn generate_chunk(&self, _coords: ChunkCoords, dimension_tiles: TilesCount) -> DataChunk<Self::GridType> {
+        DataChunk::new(FlatGrid::new(dimension_tiles, Heat::default()))
+    }
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct HeatSettings {
+    pub radius_tiles: TilesCount, // Square around the player warmed each fixed tick
+    pub gain_per_tick: f32,
+    pub decay_rate: f32, // See `Decayable for f32`
+}
+
+impl Default for HeatSettings {
+    fn default() -> Self {
+        Self {
+            radius_tiles: 1,
+            gain_per_tick: 5.0,
+            decay_rate: 0.2, // About 5 seconds to lose two thirds
+        }
+    }
+}
+
+/// A heat map demoing `DecayPlugin`: the player warms the tiles around it, which cool down once
+/// it walks away. F7 and a click on the heat map in the chunk inspector show its histogram.
+pub struct HeatPlugin;
+
+impl Plugin for HeatPlugin {
+    fn build(&self, app: &mut App) {
+        let settings = HeatSettings::default();
+        insert_chunked_plugin_with_debug(app, HeatProducer, ChunkedMapConfig::default());
+        register_chunk_summary::<HeatProducer>(app);
+        app.add_plugins(DecayPlugin::<HeatProducer>::new(settings.decay_rate))
+            .insert_resource(settings)
+            .add_systems(FixedUpdate, heat_from_player_system);
+    }
+}
+
+fn heat_from_player_system(
+    settings: Res<HeatSettings>,
+    player_query: Query<&Transform, (With<Player>, Without<Dormant>)>,
+    mut heat: ResMut<DataMap<HeatProducer>>,
+) {
+    let Ok(player_transform) = player_query.single() else {
+        return;
+    };
+    let center = WorldPos(player_transform.translation.xy()).to_tile().0;
+    let radius = settings.radius_tiles as isize;
+    let bottom_left = Point {
+        x: center.x - radius,
+        y: center.y - radius,
+    };
+    // Written as one area, which stays out of the `modified` journal
+    let warmed = FlatGrid::from_fn(2 * settings.radius_tiles + 1, |x, y| {
+        let point = Point {
+            x: bottom_left.x + x as isize,
+            y: bottom_left.y + y as isize,
+        };
+        let current = heat.read(point).unwrap_or_default();
+        Heat((current.0 + settings.gain_per_tick).min(MAX_HEAT))
+    });
+    heat.write_area(bottom_left, &warmed);
+}
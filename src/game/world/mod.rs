@@ -1 +1,10 @@
-pub mod passability;
\ No newline at end of file
+pub mod editor;
+pub mod exploration;
+pub mod heat;
+pub mod passability;
+pub mod pathfinding;
+pub mod props;
+pub mod region;
+pub mod roads;
+pub mod tide;
+pub mod tile_types;
@@ -5,28 +5,135 @@ use bevy::{
     },
     transform::components::Transform,
 };
+use serde::{Deserialize, Serialize};
 
 use crate::{
     core::{
         basics::{Point, GAME_WORLD_CENTER_THRESHOLD},
-        chunks::{ChunkCoords, DataChunk, DataMap, FlatGrid, GridData, MapDataProducer},
-        constants::TILE_SIZE_IN_UNITS, units::TilesCount,
+        chunks::{
+            ChunkCoords, ChunkPostProcessor, DataChunk, DataMap, FlatGrid, GridData,
+            MapDataProducer, NeighborView, ScalarTileValue, TileBytes,
+        },
+        noise::{fbm, value_noise}, savegame::SaveableProducer,
+        units::{TilesCount, WorldPos},
+    },
+    game::{
+        Player,
+        inspector::DebugSummarize,
+        world::roads::{RoadNetwork, RoadParams},
     },
-    game::Player,
 };
 
 // Passability
-#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Passability(pub u8);
 
 impl Passability {
     pub const IMPASSABLE: Passability = Passability(0);
     pub const FREE: Passability = Passability(255);
+    pub const WALL_THRESHOLD: u8 = 10; // Tiles below this are walls, same as the default collision threshold
+
+    pub fn is_wall(&self) -> bool {
+        self.0 < Self::WALL_THRESHOLD
+    }
+}
+
+impl TileBytes for Passability {
+    const SIZE: usize = 1;
+
+    fn write_bytes(&self, out: &mut Vec<u8>) {
+        out.push(self.0);
+    }
+
+    fn read_bytes(bytes: &[u8]) -> Self {
+        Passability(bytes[0])
+    }
+}
+
+impl ScalarTileValue for Passability {
+    fn to_scalar(&self) -> f32 {
+        self.0 as f32
+    }
+}
+
+impl DebugSummarize for Passability {
+    const DEBUG_RANGE: (f32, f32) = (0.0, 256.0);
+
+    fn debug_value(&self) -> f32 {
+        self.0 as f32
+    }
+}
+
+/// Knobs of the passability terrain generator.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TerrainParams {
+    pub frequency: f32, // Noise lattice cells per tile of the first octave
+    pub octaves: u32,
+    pub threshold: f32,      // Noise below this is a wall
+    pub border_falloff: f32, // Noise range above the threshold over which walls fade into free tiles
+    pub biome_frequency: f32, // Large scale noise shifting the threshold, open plains vs. dense mazes
+    pub biome_strength: f32,
+    pub safe_zone_radius: Option<f32>, // Tiles around the origin that are always free, so the player never spawns in a wall
+    pub roads: Option<RoadParams>,     // Carved through the terrain as free tiles
+}
+
+impl Default for TerrainParams {
+    fn default() -> Self {
+        Self {
+            frequency: 1.0 / 16.0,
+            octaves: 3,
+            threshold: 0.45,
+            border_falloff: 0.08,
+            biome_frequency: 1.0 / 200.0,
+            biome_strength: 0.2,
+            safe_zone_radius: Some(GAME_WORLD_CENTER_THRESHOLD),
+            roads: Some(RoadParams::default()),
+        }
+    }
 }
 
 // Passability DataProducer
 #[derive(Default, Clone)]
-pub struct PassabilityProducer;
+pub struct PassabilityProducer {
+    pub seed: u64,
+    pub terrain: TerrainParams,
+}
+
+impl PassabilityProducer {
+    pub fn new(terrain: TerrainParams) -> Self {
+        Self { seed: 0, terrain }
+    }
+
+    /// Passability of a world tile, a pure function of seed and coordinates,
+    /// so independently generated neighbor chunks always agree.
+    pub fn passability_at(&self, world_tile_x: isize, world_tile_y: isize) -> Passability {
+        let terrain = &self.terrain;
+        let (x, y) = (world_tile_x as f32, world_tile_y as f32);
+        let distance_from_origin = (x * x + y * y).sqrt();
+        if terrain.safe_zone_radius.is_some_and(|radius| distance_from_origin <= radius)
+            || self.is_road(world_tile_x, world_tile_y)
+        {
+            return Passability::FREE;
+        }
+
+        let biome = value_noise(
+            self.seed ^ 0xB10E,
+            x * terrain.biome_frequency,
+            y * terrain.biome_frequency,
+        );
+        let threshold = terrain.threshold + (biome - 0.5) * terrain.biome_strength;
+        let noise = fbm(self.seed, x * terrain.frequency, y * terrain.frequency, terrain.octaves);
+        let openness = ((noise - threshold) / terrain.border_falloff.max(f32::EPSILON)).clamp(0.0, 1.0);
+        Passability((openness * 255.0) as u8)
+    }
+
+    /// Whether the terrain's road network crosses the tile, see `RoadNetwork`.
+    pub fn is_road(&self, world_tile_x: isize, world_tile_y: isize) -> bool {
+        self.terrain
+            .roads
+            .is_some_and(|params| RoadNetwork::new(self.seed, params).is_road(world_tile_x, world_tile_y))
+    }
+}
 
 impl MapDataProducer for PassabilityProducer {
     type Item = Passability;
@@ -36,46 +143,74 @@ impl MapDataProducer for PassabilityProducer {
         Passability::IMPASSABLE
     }
 
+    fn with_seed(&self, seed: u64) -> Self {
+        Self {
+            seed,
+            terrain: self.terrain,
+        }
+    }
+
     fn generate_chunk(
         &self,
         coords: ChunkCoords,
         dimension_tiles: TilesCount,
     ) -> DataChunk<Self::GridType> {
-        let mut grid = FlatGrid::new(dimension_tiles, Passability::FREE);
-        let chunk_center_x =
-            coords.x as f32 * dimension_tiles as f32 + dimension_tiles as f32 / 2.0;
-        let chunk_center_y =
-            coords.y as f32 * dimension_tiles as f32 + dimension_tiles as f32 / 2.0;
-
-        for y in 0..dimension_tiles {
-            for x in 0..dimension_tiles {
-                let world_tile_x = coords.x * dimension_tiles as isize + x as isize;
-                let world_tile_y = coords.y * dimension_tiles as isize + y as isize;
-
-                let dist_from_center =
-                    ((world_tile_x as f32).powi(2) + (world_tile_y as f32).powi(2)).sqrt();
-
-                // Make tiles impassable further from center
-                let mut passability = Passability::FREE;
-                if dist_from_center > GAME_WORLD_CENTER_THRESHOLD {
-                    let falloff = (dist_from_center - GAME_WORLD_CENTER_THRESHOLD) / 500.0;
-                    // info!("Falloff: {}", &falloff);
-                    passability = Passability((255.0 - (falloff * 255.0).min(255.0)) as u8);
-                    if passability.0 < 250 {
-                        passability = Passability(0);
-                    }
-                }
+        let grid = FlatGrid::from_fn(dimension_tiles, |x, y| {
+            let world_tile_x = coords.x * dimension_tiles as isize + x as isize;
+            let world_tile_y = coords.y * dimension_tiles as isize + y as isize;
+            self.passability_at(world_tile_x, world_tile_y)
+        });
 
-                // // Example: Add a small "river" (impassable)
-                // if world_tile_y > 100 && world_tile_y < 105 {
-                //     passability = Passability::IMPASSABLE;
-                // }
+        DataChunk::new(grid)
+    }
 
-                grid.set_item(x, y, passability);
+    // The least passable tile, so thin walls don't vanish from far away views
+    fn reduce_lod(&self, block: &[Passability]) -> Passability {
+        block.iter().copied().min_by_key(|p| p.0).unwrap_or(Passability::IMPASSABLE)
+    }
+}
+
+impl SaveableProducer for PassabilityProducer {
+    const SAVE_NAME: &'static str = "passability";
+}
+
+/// Removes single-tile holes: walls whose 4 direct neighbors are all free become as free as
+/// the least free of them. Border tiles wait for the neighbor chunk, so the pass re-runs when it loads.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PassabilityHoleFiller;
+
+impl ChunkPostProcessor<PassabilityProducer> for PassabilityHoleFiller {
+    fn process(
+        &self,
+        _coords: ChunkCoords,
+        grid: &mut FlatGrid<Passability>,
+        neighbors: &NeighborView<PassabilityProducer>,
+    ) {
+        let original = grid.clone();
+        let tile = |x: isize, y: isize| match (x >= 0 && y >= 0)
+            .then(|| original.get_item(x as TilesCount, y as TilesCount))
+            .flatten()
+        {
+            Some(&item) => Some(item),
+            None => neighbors.item_outside(x, y),
+        };
+
+        grid.map_in_place(|x, y, item| {
+            if !item.is_wall() {
+                return item;
             }
-        }
+            let (x, y) = (x as isize, y as isize);
+            let around = [tile(x + 1, y), tile(x - 1, y), tile(x, y + 1), tile(x, y - 1)];
+            if around.iter().all(|p| p.is_some_and(|p| !p.is_wall())) {
+                around.into_iter().flatten().min_by_key(|p| p.0).unwrap_or(item)
+            } else {
+                item
+            }
+        });
+    }
 
-        DataChunk { grid }
+    fn rerun_when_neighbors_load(&self) -> bool {
+        true
     }
 }
 
@@ -84,11 +219,10 @@ pub fn check_player_passability(
     mut passability_map: ResMut<DataMap<PassabilityProducer>>, // Needs mut to make requests
     mut last_checked_point: Local<Option<Point>>,
 ) {
-    let player_transform = player_query.single().unwrap();
-    let player_tile_point = Point {
-        x: (player_transform.translation.x / TILE_SIZE_IN_UNITS).round() as isize,
-        y: (player_transform.translation.y / TILE_SIZE_IN_UNITS).round() as isize,
+    let Ok(player_transform) = player_query.single() else {
+        return; // Not spawned yet, or despawned
     };
+    let player_tile_point = WorldPos(player_transform.translation.truncate()).to_tile().0;
 
     if last_checked_point.map_or(true, |p| p != player_tile_point) {
         *last_checked_point = Some(player_tile_point);
@@ -105,3 +239,226 @@ pub fn check_player_passability(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+
+    use crate::core::chunks::{ChunkCompressor, ContiguousGridData, GridData, RleCompressor, validate_producer_seams};
+
+    const DIMENSION: TilesCount = 32;
+    const FAR: ChunkCoords = ChunkCoords { x: 5, y: -3 }; // Well outside the safe zone
+
+    fn tiles(producer: &PassabilityProducer, coords: ChunkCoords) -> Vec<u8> {
+        let chunk = producer.generate_chunk(coords, DIMENSION);
+        chunk.grid.as_slice().iter().map(|p| p.0).collect()
+    }
+
+    #[test]
+    fn same_seed_generates_identical_chunks_and_other_seeds_differ() {
+        let producer = PassabilityProducer::default();
+        let a = tiles(&producer.with_seed(7), FAR);
+        assert_eq!(a, tiles(&producer.with_seed(7), FAR));
+        assert_ne!(a, tiles(&producer.with_seed(8), FAR));
+        assert!(a.contains(&Passability::IMPASSABLE.0), "no walls to compare");
+    }
+
+    #[test]
+    fn neighbor_chunks_agree_on_their_shared_edge() {
+        let producer = PassabilityProducer::default().with_seed(3);
+        let half = DIMENSION / 2;
+        let west = producer.generate_chunk(ChunkCoords { x: 10, y: 4 }, half);
+        let east = producer.generate_chunk(ChunkCoords { x: 11, y: 4 }, half);
+        // Covers both, generated in one go
+        let whole = producer.generate_chunk(ChunkCoords { x: 5, y: 2 }, DIMENSION);
+        for y in 0..half {
+            assert_eq!(west.grid.get_item(half - 1, y), whole.grid.get_item(half - 1, y), "west edge, row {y}");
+            assert_eq!(east.grid.get_item(0, y), whole.grid.get_item(half, y), "east edge, row {y}");
+        }
+
+        let pairs = [(ChunkCoords { x: 10, y: 4 }, ChunkCoords { x: 11, y: 4 })];
+        assert!(validate_producer_seams(&producer, half, &pairs).is_empty());
+    }
+
+    #[test]
+    fn safe_zone_is_free_with_any_seed() {
+        for seed in 0..8 {
+            let producer = PassabilityProducer::default().with_seed(seed);
+            assert_eq!(producer.passability_at(0, 0), Passability::FREE);
+            assert_eq!(producer.passability_at(-7, 7), Passability::FREE);
+        }
+    }
+
+    fn open_ground(walls: &[(TilesCount, TilesCount)]) -> FlatGrid<Passability> {
+        let mut grid = FlatGrid::new(8, Passability::FREE);
+        for &(x, y) in walls {
+            grid.set_item(x, y, Passability::IMPASSABLE);
+        }
+        grid
+    }
+
+    #[test]
+    fn lod_keeps_the_least_passable_tile_of_each_block() {
+        let producer = PassabilityProducer::default();
+        let mut grid = open_ground(&[(3, 3)]);
+        grid.set_item(4, 0, Passability(100));
+        grid.set_item(5, 1, Passability(40));
+        let lod = grid.downsample(2, |block| producer.reduce_lod(block));
+        assert_eq!((lod.width(), lod.height()), (4, 4));
+        assert_eq!(lod.get_item(1, 1), Some(&Passability::IMPASSABLE), "a single wall tile survives");
+        assert_eq!(lod.get_item(2, 0), Some(&Passability(40)));
+        assert_eq!(lod.get_item(0, 0), Some(&Passability::FREE));
+
+        // Generated at LOD 2, every sample is the minimum of its 2x2 block at full resolution
+        let full = producer.generate_chunk(FAR, DIMENSION).grid;
+        let far = producer.generate_chunk_lod(FAR, DIMENSION, 2).grid;
+        for y in 0..DIMENSION / 2 {
+            for x in 0..DIMENSION / 2 {
+                let block = [(0, 0), (1, 0), (0, 1), (1, 1)].map(|(dx, dy)| full.get_item(2 * x + dx, 2 * y + dy).unwrap().0);
+                assert_eq!(far.get_item(x, y).unwrap().0, *block.iter().min().unwrap(), "sample {x}, {y}");
+            }
+        }
+    }
+
+    #[test]
+    fn hole_filler_removes_single_tile_holes_only() {
+        let map = DataMap::new(PassabilityProducer::default(), 8, 1);
+        let mut grid = open_ground(&[(3, 3), (5, 5), (5, 6), (0, 4)]);
+        let center = ChunkCoords { x: 0, y: 0 };
+        PassabilityHoleFiller.process(center, &mut grid, &NeighborView::capture(&map, center));
+        assert_eq!(grid.get_item(3, 3), Some(&Passability::FREE));
+        assert!(grid.get_item(5, 5).unwrap().is_wall(), "two tile walls stay");
+        assert!(grid.get_item(5, 6).unwrap().is_wall(), "two tile walls stay");
+        assert!(grid.get_item(0, 4).unwrap().is_wall(), "border tiles wait for the neighbor");
+    }
+
+    #[test]
+    fn hole_filler_fills_border_holes_once_the_neighbor_is_known() {
+        let mut map = DataMap::new(PassabilityProducer::default(), 8, 1);
+        let mut west = open_ground(&[]);
+        west.set_item(7, 4, Passability(100)); // The least free tile around the hole
+        map.loaded_chunks.insert(ChunkCoords { x: -1, y: 0 }, DataChunk::new(west));
+
+        let mut grid = open_ground(&[(0, 4)]);
+        let center = ChunkCoords { x: 0, y: 0 };
+        PassabilityHoleFiller.process(center, &mut grid, &NeighborView::capture(&map, center));
+        assert_eq!(grid.get_item(0, 4), Some(&Passability(100)));
+
+        // Idempotent, as re-runs require
+        let once = grid.clone();
+        PassabilityHoleFiller.process(center, &mut grid, &NeighborView::capture(&map, center));
+        assert_eq!(grid.as_slice(), once.as_slice());
+    }
+
+    // Runs of random length and value, like terrain but without its structure
+    fn random_grid(seed: u64, width: TilesCount, height: TilesCount) -> FlatGrid<Passability> {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        let mut grid = FlatGrid::new_rect(width, height, Passability::FREE);
+        let mut value = Passability::FREE;
+        for y in 0..height {
+            for x in 0..width {
+                if rng.random_range(0..4) == 0 {
+                    value = Passability(rng.random());
+                }
+                grid.set_item(x, y, value);
+            }
+        }
+        grid
+    }
+
+    #[test]
+    fn rle_round_trips_random_grids() {
+        for seed in 0..20 {
+            let (width, height) = if seed % 2 == 0 { (32, 32) } else { (7, 3) };
+            let grid = random_grid(seed, width, height);
+            let bytes = RleCompressor.compress(&grid);
+            let restored: FlatGrid<Passability> = RleCompressor.decompress(&bytes).unwrap();
+            assert_eq!((restored.width(), restored.height()), (width, height));
+            assert_eq!(restored.as_slice(), grid.as_slice(), "seed {seed}");
+            for y in 0..height {
+                for x in 0..width {
+                    let item: Option<Passability> = RleCompressor.read_item(&bytes, x, y);
+                    assert_eq!(item.as_ref(), grid.get_item(x, y), "seed {seed}, tile {x}, {y}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn rle_splits_runs_longer_than_its_counter() {
+        let grid = FlatGrid::new(300, Passability::FREE); // 90000 tiles, one value
+        let bytes = RleCompressor.compress(&grid);
+        assert_eq!(bytes.len(), 8 + 2 * (2 + 1));
+        let restored: FlatGrid<Passability> = RleCompressor.decompress(&bytes).unwrap();
+        assert_eq!(restored.as_slice(), grid.as_slice());
+
+        let truncated = &bytes[..bytes.len() - 3];
+        assert!(ChunkCompressor::<FlatGrid<Passability>>::decompress(&RleCompressor, truncated).is_none());
+    }
+
+    #[test]
+    fn dormant_chunks_read_in_place_and_promote_on_get() {
+        let mut map = DataMap::new(PassabilityProducer::default(), DIMENSION, 1);
+        map.set_compressor(RleCompressor, 4);
+        map.focus_centers = vec![ChunkCoords { x: 0, y: 0 }];
+        // Open ground around a wall, with the falloff band on one side
+        let grid = FlatGrid::from_fn(DIMENSION, |x, y| match (x, y) {
+            (8..16, 8..16) => Passability::IMPASSABLE,
+            (16, 8..16) => Passability(100),
+            _ => Passability::FREE,
+        });
+        let tiles: Vec<u8> = grid.as_slice().iter().map(|p| p.0).collect();
+        map.loaded_chunks.insert(FAR, DataChunk::new(grid));
+        let loaded_bytes = map.loaded_bytes();
+
+        assert!(map.make_dormant(FAR));
+        assert!(map.loaded_chunks.is_empty());
+        assert!(map.dormant_bytes() * 4 < loaded_bytes, "{} vs {loaded_bytes}", map.dormant_bytes());
+
+        let origin = FAR.to_bottom_left_tile_point(DIMENSION);
+        let point = |i: usize| Point {
+            x: origin.x + (i % DIMENSION) as isize,
+            y: origin.y + (i / DIMENSION) as isize,
+        };
+        for (i, &tile) in tiles.iter().enumerate() {
+            assert_eq!(map.read(point(i)), Some(Passability(tile)), "tile {i}");
+        }
+        assert!(map.dormant_chunks.contains_key(&FAR), "read leaves it dormant");
+
+        assert_eq!(map.get(point(37)), Passability(tiles[37]));
+        assert!(map.dormant_chunks.is_empty());
+        assert_eq!(map.promoted_this_frame, [FAR]);
+        assert!(map.requested_chunks.is_empty(), "promoted, not generated again");
+        let restored = &map.loaded_chunks[&FAR].grid;
+        assert!(restored.as_slice().iter().map(|p| p.0).eq(tiles.iter().copied()));
+    }
+
+    #[test]
+    fn read_area_spans_a_dormant_chunk_without_promoting_it() {
+        let mut map = DataMap::new(PassabilityProducer::default(), DIMENSION, 1);
+        map.set_compressor(RleCompressor, 4);
+        map.focus_centers = vec![ChunkCoords { x: 0, y: 0 }];
+        let west = ChunkCoords { x: FAR.x - 1, y: FAR.y };
+        map.load_generated_chunk(west);
+        map.load_generated_chunk(FAR);
+        assert!(map.make_dormant(FAR));
+
+        // Four tiles on each side of the border, the two on the dormant side filled meanwhile
+        let origin = FAR.to_bottom_left_tile_point(DIMENSION);
+        let bottom_left = Point { x: origin.x - 4, y: origin.y };
+        map.fill_rect(Point { x: origin.x + 2, y: origin.y }, 2, 1, Passability::IMPASSABLE);
+        let requested = map.requested_chunks.clone(); // The fill's, to promote the chunk later
+        let area = map.read_area(bottom_left, 8, 2).expect("dormant chunks are readable");
+        for y in 0..2 {
+            for x in 0..8 {
+                let point = Point { x: bottom_left.x + x as isize, y: bottom_left.y + y as isize };
+                assert_eq!(area.get_item(x, y).copied(), map.read(point), "tile {x}, {y}");
+            }
+        }
+        assert_eq!(area.get_item(6, 0), Some(&Passability::IMPASSABLE));
+        assert!(map.dormant_chunks.contains_key(&FAR), "read_area leaves it dormant");
+        assert_eq!(map.requested_chunks, requested);
+    }
+}
@@ -0,0 +1,450 @@
+use std::{cmp::Ordering, collections::BinaryHeap};
+
+use bevy::{
+    app::{App, Update},
+    ecs::{
+        component::Component,
+        entity::Entity,
+        query::Without,
+        system::{Commands, Query, Res},
+    },
+    platform::collections::HashMap,
+    tasks::{AsyncComputeTaskPool, Task},
+};
+use futures_lite::future;
+
+use crate::{
+    core::{
+        basics::Point,
+        chunks::{DataMap, FlatGrid, GridData},
+        units::TilesCount,
+    },
+    game::world::passability::{Passability, PassabilityProducer},
+};
+
+#[derive(Debug, Clone, Copy)]
+pub struct PathOptions {
+    pub threshold: u8,             // Tiles with passability below this are walls
+    pub passability_cost: bool,    // Less passable tiles cost more to traverse
+    pub allow_diagonal: bool,      // Diagonal moves never cut wall corners
+    pub unloaded_is_blocked: bool, // Otherwise unknown tiles are free, with a penalty
+    pub unloaded_penalty: f32,     // Cost multiplier for stepping onto unknown tiles
+    pub max_explored_nodes: usize,
+}
+
+impl Default for PathOptions {
+    fn default() -> Self {
+        Self {
+            threshold: 10,
+            passability_cost: false,
+            allow_diagonal: true,
+            unloaded_is_blocked: false,
+            unloaded_penalty: 4.0,
+            max_explored_nodes: 10_000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PathResult {
+    Found {
+        path: Vec<Point>, // Start and goal included
+        cost: f32,
+        crossed_unknown: bool, // Some tiles on the path were not loaded
+    },
+    NotFound,     // Every reachable tile was explored
+    LimitReached, // Gave up after max_explored_nodes
+}
+
+/// Anything passability can be read from without side effects.
+pub trait PassabilitySource {
+    fn passability(&self, point: Point) -> Option<Passability>;
+}
+
+impl PassabilitySource for DataMap<PassabilityProducer> {
+    fn passability(&self, point: Point) -> Option<Passability> {
+        self.read(point)
+    }
+}
+
+/// A hand-built area with its bottom-left tile at the origin, nothing outside it is known.
+impl PassabilitySource for FlatGrid<Passability> {
+    fn passability(&self, point: Point) -> Option<Passability> {
+        if point.x < 0 || point.y < 0 {
+            return None;
+        }
+        self.get_item(point.x as TilesCount, point.y as TilesCount).copied()
+    }
+}
+
+/// A copy of the passability around a path request, so it can be searched off the main thread.
+/// Tiles outside the snapshot are unknown.
+#[derive(Debug, Clone)]
+pub struct PassabilitySnapshot {
+    pub bottom_left: Point,
+    pub width: TilesCount,
+    pub height: TilesCount,
+    pub tiles: Vec<Option<Passability>>,
+}
+
+impl PassabilitySnapshot {
+    /// Copies the bounding box of `start` and `goal`, grown by `margin` tiles on each side.
+    pub fn capture(
+        map: &DataMap<PassabilityProducer>,
+        start: Point,
+        goal: Point,
+        margin: TilesCount,
+    ) -> Self {
+        let margin = margin as isize;
+        let bottom_left = Point {
+            x: start.x.min(goal.x) - margin,
+            y: start.y.min(goal.y) - margin,
+        };
+        let width = ((start.x - goal.x).abs() + 2 * margin + 1) as TilesCount;
+        let height = ((start.y - goal.y).abs() + 2 * margin + 1) as TilesCount;
+        let tiles = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                map.read(Point {
+                    x: bottom_left.x + x as isize,
+                    y: bottom_left.y + y as isize,
+                })
+            })
+            .collect();
+        Self {
+            bottom_left,
+            width,
+            height,
+            tiles,
+        }
+    }
+}
+
+impl PassabilitySource for PassabilitySnapshot {
+    fn passability(&self, point: Point) -> Option<Passability> {
+        let x = point.x - self.bottom_left.x;
+        let y = point.y - self.bottom_left.y;
+        if x < 0 || y < 0 || x >= self.width as isize || y >= self.height as isize {
+            return None;
+        }
+        self.tiles[y as usize * self.width + x as usize]
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct OpenNode {
+    point: Point,
+    estimate: f32, // Cost so far plus heuristic
+}
+
+impl PartialEq for OpenNode {
+    fn eq(&self, other: &Self) -> bool {
+        self.estimate.total_cmp(&other.estimate) == Ordering::Equal
+    }
+}
+
+impl Eq for OpenNode {}
+
+impl PartialOrd for OpenNode {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for OpenNode {
+    // Reversed, so the BinaryHeap pops the cheapest node first
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.estimate.total_cmp(&self.estimate)
+    }
+}
+
+/// Octile distance, admissible for 8-directional movement.
+fn heuristic(from: Point, to: Point, allow_diagonal: bool) -> f32 {
+    let dx = (from.x - to.x).abs() as f32;
+    let dy = (from.y - to.y).abs() as f32;
+    if allow_diagonal {
+        dx.max(dy) + (std::f32::consts::SQRT_2 - 1.0) * dx.min(dy)
+    } else {
+        dx + dy
+    }
+}
+
+/// Cost multiplier for entering a tile, `None` if it can't be entered.
+fn tile_cost<S: PassabilitySource>(source: &S, point: Point, opts: &PathOptions) -> Option<f32> {
+    match source.passability(point) {
+        Some(p) if p.0 < opts.threshold => None,
+        Some(p) if opts.passability_cost => Some(255.0 / p.0.max(1) as f32),
+        Some(_) => Some(1.0),
+        None if opts.unloaded_is_blocked => None,
+        None => Some(opts.unloaded_penalty),
+    }
+}
+
+const NEIGHBORS: [(isize, isize); 8] = [
+    (0, 1),
+    (1, 0),
+    (0, -1),
+    (-1, 0),
+    (1, 1),
+    (1, -1),
+    (-1, -1),
+    (-1, 1),
+];
+
+/// A* over tile centers of the passability map. Does not request any chunks.
+pub fn find_path(
+    start: Point,
+    goal: Point,
+    map: &DataMap<PassabilityProducer>,
+    opts: PathOptions,
+) -> PathResult {
+    find_path_in(start, goal, map, opts)
+}
+
+/// Same as `find_path`, for any passability source (e.g. a `PassabilitySnapshot`).
+pub fn find_path_in<S: PassabilitySource>(
+    start: Point,
+    goal: Point,
+    source: &S,
+    opts: PathOptions,
+) -> PathResult {
+    let mut open = BinaryHeap::new();
+    let mut came_from: HashMap<Point, Point> = HashMap::new();
+    let mut cost_so_far: HashMap<Point, f32> = HashMap::new();
+    cost_so_far.insert(start, 0.0);
+    open.push(OpenNode {
+        point: start,
+        estimate: heuristic(start, goal, opts.allow_diagonal),
+    });
+
+    let neighbors = if opts.allow_diagonal {
+        &NEIGHBORS[..]
+    } else {
+        &NEIGHBORS[..4]
+    };
+    let mut explored = 0;
+
+    while let Some(OpenNode { point, estimate }) = open.pop() {
+        let current_cost = cost_so_far[&point];
+        if estimate > current_cost + heuristic(point, goal, opts.allow_diagonal) {
+            continue; // stale entry, a cheaper one was already processed
+        }
+        if point == goal {
+            return reconstruct(start, goal, &came_from, current_cost, source);
+        }
+        explored += 1;
+        if explored > opts.max_explored_nodes {
+            return PathResult::LimitReached;
+        }
+
+        for &(dx, dy) in neighbors {
+            let next = Point {
+                x: point.x + dx,
+                y: point.y + dy,
+            };
+            let Some(multiplier) = tile_cost(source, next, &opts) else {
+                continue;
+            };
+            let diagonal = dx != 0 && dy != 0;
+            if diagonal {
+                // No squeezing between two walls touching at a corner
+                let side_a = Point { x: point.x + dx, y: point.y };
+                let side_b = Point { x: point.x, y: point.y + dy };
+                if tile_cost(source, side_a, &opts).is_none()
+                    || tile_cost(source, side_b, &opts).is_none()
+                {
+                    continue;
+                }
+            }
+            let step = if diagonal { std::f32::consts::SQRT_2 } else { 1.0 };
+            let new_cost = current_cost + step * multiplier;
+            if cost_so_far.get(&next).is_none_or(|&c| new_cost < c) {
+                cost_so_far.insert(next, new_cost);
+                came_from.insert(next, point);
+                open.push(OpenNode {
+                    point: next,
+                    estimate: new_cost + heuristic(next, goal, opts.allow_diagonal),
+                });
+            }
+        }
+    }
+    PathResult::NotFound
+}
+
+fn reconstruct<S: PassabilitySource>(
+    start: Point,
+    goal: Point,
+    came_from: &HashMap<Point, Point>,
+    cost: f32,
+    source: &S,
+) -> PathResult {
+    let mut path = vec![goal];
+    let mut current = goal;
+    while current != start {
+        current = came_from[&current];
+        path.push(current);
+    }
+    path.reverse();
+    let crossed_unknown = path.iter().any(|&p| source.passability(p).is_none());
+    PathResult::Found {
+        path,
+        cost,
+        crossed_unknown,
+    }
+}
+
+// --- Async variant ---
+
+const PATH_SNAPSHOT_MARGIN_TILES: TilesCount = 32; // Room for detours around the start-goal bounding box
+
+/// Add to an entity to get a `PathReady` on it once the search completes.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct PathRequest {
+    pub start: Point,
+    pub goal: Point,
+    pub options: PathOptions,
+}
+
+#[derive(Component)]
+pub struct PathTask(pub Task<PathResult>);
+
+#[derive(Component, Debug, Clone)]
+pub struct PathReady(pub PathResult);
+
+// System to spawn background searches for new path requests
+pub fn path_spawn_tasks_system(
+    mut commands: Commands,
+    query: Query<(Entity, &PathRequest), Without<PathTask>>,
+    passability: Res<DataMap<PassabilityProducer>>,
+) {
+    let thread_pool = AsyncComputeTaskPool::get();
+    for (entity, request) in query.iter() {
+        let snapshot = PassabilitySnapshot::capture(
+            &passability,
+            request.start,
+            request.goal,
+            PATH_SNAPSHOT_MARGIN_TILES,
+        );
+        let request = *request;
+        let task = thread_pool.spawn(async move {
+            find_path_in(request.start, request.goal, &snapshot, request.options)
+        });
+        commands.entity(entity).insert(PathTask(task));
+    }
+}
+
+// System to hand finished searches back to their entities
+pub fn path_process_completed_tasks_system(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut PathTask)>,
+) {
+    for (entity, mut task) in query.iter_mut() {
+        if let Some(result) = future::block_on(future::poll_once(&mut task.0)) {
+            commands
+                .entity(entity)
+                .remove::<(PathRequest, PathTask)>()
+                .insert(PathReady(result));
+        }
+    }
+}
+
+pub fn insert_pathfinding_plugin(app: &mut App) -> &mut App {
+    app.add_systems(
+        Update,
+        (path_spawn_tasks_system, path_process_completed_tasks_system),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WALL: Passability = Passability(0);
+    const FREE: Passability = Passability(255);
+
+    /// `rows[y]` is the row at y, '#' for walls; the rows are as long as there are rows.
+    fn grid(rows: &[&str]) -> FlatGrid<Passability> {
+        let mut grid = FlatGrid::new(rows.len(), FREE);
+        for (y, row) in rows.iter().enumerate() {
+            for (x, tile) in row.bytes().enumerate() {
+                if tile == b'#' {
+                    grid.set_item(x, y, WALL);
+                }
+            }
+        }
+        grid
+    }
+
+    fn points(coords: &[(isize, isize)]) -> Vec<Point> {
+        coords.iter().map(|&(x, y)| Point { x, y }).collect()
+    }
+
+    fn known_only() -> PathOptions {
+        PathOptions {
+            unloaded_is_blocked: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn follows_a_winding_corridor() {
+        let map = grid(&["#####", "...##", "##.##", "##...", "#####"]);
+        let result = find_path_in(Point { x: 0, y: 1 }, Point { x: 4, y: 3 }, &map, known_only());
+        let PathResult::Found { path, cost, crossed_unknown } = result else {
+            panic!("no path through the corridor: {result:?}");
+        };
+        // The corridor turns at walls touching the path diagonally, so there are no shortcuts
+        assert_eq!(path, points(&[(0, 1), (1, 1), (2, 1), (2, 2), (2, 3), (3, 3), (4, 3)]));
+        assert_eq!(cost, 6.0);
+        assert!(!crossed_unknown);
+    }
+
+    #[test]
+    fn diagonals_never_cut_wall_corners() {
+        let one_wall = grid(&["..", "#."]);
+        let result = find_path_in(Point { x: 0, y: 0 }, Point { x: 1, y: 1 }, &one_wall, known_only());
+        let PathResult::Found { path, cost, .. } = result else {
+            panic!("no path around the corner: {result:?}");
+        };
+        assert_eq!(path, points(&[(0, 0), (1, 0), (1, 1)]));
+        assert_eq!(cost, 2.0);
+
+        let two_walls = grid(&[".#", "#."]);
+        let result = find_path_in(Point { x: 0, y: 0 }, Point { x: 1, y: 1 }, &two_walls, known_only());
+        assert_eq!(result, PathResult::NotFound);
+    }
+
+    #[test]
+    fn dead_end_is_not_found() {
+        let map = grid(&["..#", "###", "..."]);
+        let result = find_path_in(Point { x: 0, y: 0 }, Point { x: 0, y: 2 }, &map, known_only());
+        assert_eq!(result, PathResult::NotFound);
+    }
+
+    #[test]
+    fn unloaded_tiles_are_crossed_with_a_penalty() {
+        // The only way around the wall leads below the grid, through unknown tiles
+        let map = grid(&[".#.", "###", "###"]);
+        let (start, goal) = (Point { x: 0, y: 0 }, Point { x: 2, y: 0 });
+        let result = find_path_in(start, goal, &map, PathOptions::default());
+        let PathResult::Found { path, cost, crossed_unknown } = result else {
+            panic!("no path through unknown tiles: {result:?}");
+        };
+        assert_eq!(path, points(&[(0, 0), (0, -1), (1, -1), (2, -1), (2, 0)]));
+        assert_eq!(cost, 3.0 * PathOptions::default().unloaded_penalty + 1.0);
+        assert!(crossed_unknown);
+
+        assert_eq!(find_path_in(start, goal, &map, known_only()), PathResult::NotFound);
+    }
+
+    #[test]
+    fn enclosed_goal_in_unknown_territory_hits_the_limit() {
+        let map = grid(&["###", "#.#", "###"]);
+        let opts = PathOptions {
+            max_explored_nodes: 200,
+            ..Default::default()
+        };
+        let result = find_path_in(Point { x: 10, y: 10 }, Point { x: 1, y: 1 }, &map, opts);
+        assert_eq!(result, PathResult::LimitReached);
+    }
+}
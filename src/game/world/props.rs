@@ -0,0 +1,244 @@
+use bevy::{color::palettes::css, prelude::*};
+
+use crate::{
+    Pallete,
+    core::{
+        basics::Point,
+        chunks::{
+            ChunkCoords, ChunkSpawner, ChunkedMapConfig, DataChunk, FlatGrid, FocusPolicy, GridData, MapDataProducer,
+            insert_chunk_spawner_plugin,
+        },
+        noise::hash2,
+        units::TilesCount,
+    },
+    game::{
+        debug::insert_chunked_plugin_with_debug,
+        render::{
+            RenderLayerZ, spawn_at_layer,
+            light_sim::lights::{LightAnimation, LightEmitter},
+        },
+        spawn::tile_to_world,
+        world::passability::{Passability, PassabilityProducer, TerrainParams},
+    },
+};
+
+const PROP_SEED: u64 = 0x9809; // Mixed into the world seed, so props don't follow the terrain noise lattice
+const PROP_RADIUS: f32 = 4.0;
+const PROP_RENDER_DISTANCE_CHUNKS: usize = 2; // Prop entities exist while their chunk is loaded, so this close to the player
+
+/// What stands on a tile, `None` for most of them.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum PropKind {
+    #[default]
+    None = 0,
+    LampPost = 1,
+    GlowCrystal = 2,
+}
+
+impl PropKind {
+    /// Light of a prop of this kind, None for `PropKind::None`.
+    pub fn light(&self) -> Option<LightEmitter> {
+        match self {
+            PropKind::None => None,
+            PropKind::LampPost => Some(LightEmitter {
+                color: css::GOLD.into(),
+                intensity: 1.5,
+                animation: LightAnimation::Flicker {
+                    amplitude: 0.1,
+                    speed: 4.0,
+                },
+                ..default()
+            }),
+            PropKind::GlowCrystal => Some(LightEmitter {
+                color: css::MEDIUM_PURPLE.into(),
+                animation: LightAnimation::Pulse {
+                    period: 3.0,
+                    min: 0.5,
+                    max: 1.2,
+                },
+                ..default()
+            }),
+        }
+    }
+
+    /// Pallete entry of the prop's circle.
+    pub fn color_name(&self) -> &'static str {
+        match self {
+            PropKind::None => "",
+            PropKind::LampPost => "prop_lamp",
+            PropKind::GlowCrystal => "prop_crystal",
+        }
+    }
+}
+
+/// Knobs of the prop placement.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PropParams {
+    pub cell_tiles: isize,    // The world is split in cells this wide, each with at most one prop
+    pub fill_chance: f32,     // Chance that a cell gets a prop, if its candidate tile is free
+    pub crystal_share: f32,   // Part of the props that are crystals rather than lamp posts
+}
+
+impl Default for PropParams {
+    fn default() -> Self {
+        Self {
+            cell_tiles: 12,
+            fill_chance: 0.8,
+            crystal_share: 0.4,
+        }
+    }
+}
+
+// Props DataProducer, derived from the passability terrain so props only stand on free tiles
+#[derive(Default, Clone)]
+pub struct PropPlacementProducer {
+    pub passability: PassabilityProducer,
+    pub params: PropParams,
+}
+
+impl PropPlacementProducer {
+    pub fn new(terrain: TerrainParams, params: PropParams) -> Self {
+        Self {
+            passability: PassabilityProducer::new(terrain),
+            params,
+        }
+    }
+
+    /// Prop of a world tile. Each cell picks one candidate tile from the seed, which gets a prop
+    /// if it's fully free, so props are spread roughly `cell_tiles` apart and never in walls.
+    pub fn prop_at(&self, world_tile_x: isize, world_tile_y: isize) -> PropKind {
+        let cell_tiles = self.params.cell_tiles.max(1);
+        let (cell_x, cell_y) = (world_tile_x.div_euclid(cell_tiles), world_tile_y.div_euclid(cell_tiles));
+        let hash = hash2(self.passability.seed ^ PROP_SEED, cell_x, cell_y);
+        let candidate = Point {
+            x: cell_x * cell_tiles + (hash % cell_tiles as u64) as isize,
+            y: cell_y * cell_tiles + ((hash >> 16) % cell_tiles as u64) as isize,
+        };
+        if candidate != (Point { x: world_tile_x, y: world_tile_y }) {
+            return PropKind::None;
+        }
+        let roll = ((hash >> 32) & 0xFFFF) as f32 / 65536.0;
+        if roll >= self.params.fill_chance {
+            return PropKind::None;
+        }
+        // The hole filler only ever frees walls, so a free tile stays free after post-processing
+        if self.passability.passability_at(world_tile_x, world_tile_y) != Passability::FREE {
+            return PropKind::None;
+        }
+        if roll < self.params.fill_chance * self.params.crystal_share {
+            PropKind::GlowCrystal
+        } else {
+            PropKind::LampPost
+        }
+    }
+}
+
+impl MapDataProducer for PropPlacementProducer {
+    type Item = PropKind;
+    type GridType = FlatGrid<PropKind>;
+
+    fn default_value(&self) -> Self::Item {
+        PropKind::None
+    }
+
+    fn with_seed(&self, seed: u64) -> Self {
+        Self {
+            passability: self.passability.with_seed(seed),
+            params: self.params,
+        }
+    }
+
+    fn generate_chunk(
+        &self,
+        coords: ChunkCoords,
+        dimension_tiles: TilesCount,
+    ) -> DataChunk<Self::GridType> {
+        let grid = FlatGrid::from_fn(dimension_tiles, |x, y| {
+            let world_tile_x = coords.x * dimension_tiles as isize + x as isize;
+            let world_tile_y = coords.y * dimension_tiles as isize + y as isize;
+            self.prop_at(world_tile_x, world_tile_y)
+        });
+
+        DataChunk::new(grid)
+    }
+}
+
+/// A prop entity spawned from `DataMap<PropPlacementProducer>`.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Prop {
+    pub kind: PropKind,
+    pub tile: Point,
+}
+
+/// Spawns the props of a prop chunk. Their circles are added by `prop_visuals_system`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PropSpawner;
+
+impl ChunkSpawner<PropPlacementProducer> for PropSpawner {
+    fn spawn_for_chunk(
+        &self,
+        commands: &mut Commands,
+        coords: ChunkCoords,
+        chunk: &DataChunk<FlatGrid<PropKind>>,
+    ) -> Vec<Entity> {
+        let bottom_left = coords.to_bottom_left_tile_point(chunk.grid.dimension());
+        let mut entities = Vec::new();
+        for (x, y, kind) in chunk.grid.iter_indexed() {
+            let Some(light) = kind.light() else {
+                continue;
+            };
+            let tile = Point {
+                x: bottom_left.x + x as isize,
+                y: bottom_left.y + y as isize,
+            };
+            let prop = (Prop { kind: *kind, tile }, light);
+            let entity = spawn_at_layer(commands, prop, RenderLayerZ::Props, tile_to_world(tile)).id();
+            entities.push(entity);
+        }
+        entities
+    }
+}
+
+/// Registers the prop map and spawns light-emitting props around the player.
+pub struct PropPlugin {
+    pub terrain: TerrainParams, // Must be the passability map's, or props end up in walls
+    pub params: PropParams,
+}
+
+impl Plugin for PropPlugin {
+    fn build(&self, app: &mut App) {
+        insert_chunked_plugin_with_debug(
+            app,
+            PropPlacementProducer::new(self.terrain, self.params),
+            ChunkedMapConfig {
+                focus: FocusPolicy::FollowMapRevealActors {
+                    radius_chunks: PROP_RENDER_DISTANCE_CHUNKS,
+                },
+                ..Default::default()
+            },
+        );
+        insert_chunk_spawner_plugin(app, PropSpawner);
+        app.add_systems(Update, prop_visuals_system);
+    }
+}
+
+/// Gives newly spawned props the circle of their kind.
+pub fn prop_visuals_system(
+    mut commands: Commands,
+    pallete: Res<Pallete>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut mesh: Local<Option<Handle<Mesh>>>,
+    new_props: Query<(Entity, &Prop), Added<Prop>>,
+) {
+    if new_props.is_empty() {
+        return;
+    }
+    let mesh = mesh.get_or_insert_with(|| meshes.add(Circle::new(PROP_RADIUS))).clone();
+    for (entity, prop) in new_props.iter() {
+        let material = pallete.colors.get(prop.kind.color_name()).cloned().unwrap_or_default();
+        commands
+            .entity(entity)
+            .insert((Mesh2d(mesh.clone()), MeshMaterial2d(material)));
+    }
+}
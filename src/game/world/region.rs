@@ -0,0 +1,265 @@
+use std::collections::VecDeque;
+
+use bevy::platform::collections::HashSet;
+
+use crate::core::{
+    basics::Point,
+    chunks::{DataMap, MapDataProducer},
+    units::TilesCount,
+};
+
+/// Tile nearest to `from` (by euclidean distance) within `max_radius` for which `pred` holds.
+/// Walks square rings outwards and stops as soon as no farther ring can hold a closer tile.
+/// Ties are resolved the same way every run.
+pub fn find_nearest_point(
+    from: Point,
+    max_radius: TilesCount,
+    mut pred: impl FnMut(Point) -> bool,
+) -> Option<Point> {
+    let max_radius = max_radius as isize;
+    let mut best: Option<((isize, isize, isize), Point)> = None;
+    for ring in 0..=max_radius {
+        // Tiles of this ring are at least `ring` away
+        if best.is_some_and(|((distance_sq, _, _), _)| ring * ring > distance_sq) {
+            break;
+        }
+        let mut visit = |dx: isize, dy: isize| {
+            let distance_sq = dx * dx + dy * dy;
+            if distance_sq > max_radius * max_radius {
+                return;
+            }
+            let key = (distance_sq, dx, dy);
+            if best.is_some_and(|(best_key, _)| best_key <= key) {
+                return;
+            }
+            let point = Point {
+                x: from.x + dx,
+                y: from.y + dy,
+            };
+            if pred(point) {
+                best = Some((key, point));
+            }
+        };
+        if ring == 0 {
+            visit(0, 0);
+            continue;
+        }
+        for d in -ring..=ring {
+            visit(d, -ring);
+            visit(d, ring);
+        }
+        for d in -ring + 1..ring {
+            visit(-ring, d);
+            visit(ring, d);
+        }
+    }
+    best.map(|(_, point)| point)
+}
+
+/// Loaded tile nearest to `from` within `max_radius` whose item satisfies `pred`, see `find_nearest_point`.
+pub fn find_nearest<P: MapDataProducer>(
+    map: &DataMap<P>,
+    from: Point,
+    max_radius: TilesCount,
+    pred: impl Fn(P::Item) -> bool,
+) -> Option<Point> {
+    find_nearest_point(from, max_radius, |point| map.read(point).is_some_and(&pred))
+}
+
+/// Result of `flood_fill_reachable`.
+#[derive(Debug, Clone, Default)]
+pub struct ReachableArea {
+    pub tiles: HashSet<Point>,
+    pub capped: bool,       // Stopped at `max_tiles` with tiles left to explore
+    pub hit_unloaded: bool, // The area touches unloaded tiles, so it may go on beyond them
+}
+
+// One bit per tile of a rectangle, so visited tiles don't need a set lookup each
+struct VisitedBits {
+    bottom_left: Point,
+    width: usize,
+    height: usize,
+    bits: Vec<u64>,
+}
+
+impl VisitedBits {
+    fn new(bottom_left: Point, top_right: Point) -> Self {
+        let width = (top_right.x - bottom_left.x + 1).max(0) as usize;
+        let height = (top_right.y - bottom_left.y + 1).max(0) as usize;
+        Self {
+            bottom_left,
+            width,
+            height,
+            bits: vec![0; (width * height).div_ceil(64)],
+        }
+    }
+
+    /// Marks the tile, false if it was already marked or lies outside the rectangle.
+    fn insert(&mut self, point: Point) -> bool {
+        let (x, y) = (point.x - self.bottom_left.x, point.y - self.bottom_left.y);
+        if x < 0 || y < 0 || x as usize >= self.width || y as usize >= self.height {
+            return false;
+        }
+        let index = y as usize * self.width + x as usize;
+        let mask = 1 << (index % 64);
+        let word = &mut self.bits[index / 64];
+        let fresh = *word & mask == 0;
+        *word |= mask;
+        fresh
+    }
+}
+
+/// Tiles 4-connected to `from` through tiles whose item satisfies `passable`, at most `max_tiles`.
+/// Unloaded tiles are boundaries, reported by `hit_unloaded`. Empty if `from` itself isn't passable.
+pub fn flood_fill_reachable<P: MapDataProducer>(
+    map: &DataMap<P>,
+    from: Point,
+    max_tiles: usize,
+    passable: impl Fn(P::Item) -> bool,
+) -> ReachableArea {
+    let mut area = ReachableArea::default();
+    let Some(start) = map.read(from) else {
+        area.hit_unloaded = true;
+        return area;
+    };
+    if !passable(start) || max_tiles == 0 {
+        return area;
+    }
+
+    // Nothing beyond the loaded chunks (and the unloaded ring around them) or `max_tiles`
+    // steps away can be visited
+    let dimension = map.chunk_dimension_tiles as isize;
+    let reach = max_tiles as isize;
+    let (mut min, mut max) = (from, from);
+    for coords in map.loaded_chunks.keys() {
+        min.x = min.x.min(coords.x * dimension - 1);
+        min.y = min.y.min(coords.y * dimension - 1);
+        max.x = max.x.max((coords.x + 1) * dimension);
+        max.y = max.y.max((coords.y + 1) * dimension);
+    }
+    let mut visited = VisitedBits::new(
+        Point {
+            x: min.x.max(from.x - reach),
+            y: min.y.max(from.y - reach),
+        },
+        Point {
+            x: max.x.min(from.x + reach),
+            y: max.y.min(from.y + reach),
+        },
+    );
+
+    let mut queue = VecDeque::from([from]);
+    visited.insert(from);
+    while let Some(point) = queue.pop_front() {
+        if area.tiles.len() >= max_tiles {
+            area.capped = true;
+            break;
+        }
+        area.tiles.insert(point);
+        for neighbor in point.neighbors4() {
+            if !visited.insert(neighbor) {
+                continue;
+            }
+            match map.read(neighbor) {
+                Some(item) if passable(item) => queue.push_back(neighbor),
+                Some(_) => {}
+                None => area.hit_unloaded = true,
+            }
+        }
+    }
+    area
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        core::chunks::{ChunkCoords, DataChunk, FlatGrid},
+        game::world::passability::{Passability, PassabilityProducer},
+    };
+
+    const DIMENSION: TilesCount = 8;
+
+    /// A map with chunk (0, 0) loaded from `rows` (`rows[y]` is the row at y, '#' for walls).
+    fn map(rows: [&str; 8]) -> DataMap<PassabilityProducer> {
+        let mut map = DataMap::new(PassabilityProducer::default(), DIMENSION, 1);
+        let grid = FlatGrid::from_fn(DIMENSION, |x, y| match rows[y].as_bytes()[x] {
+            b'#' => Passability::IMPASSABLE,
+            _ => Passability::FREE,
+        });
+        map.loaded_chunks.insert(ChunkCoords { x: 0, y: 0 }, DataChunk::new(grid));
+        map
+    }
+
+    fn free(p: Passability) -> bool {
+        !p.is_wall()
+    }
+
+    const ROOM: [&str; 8] = [
+        "########", //
+        "#...#..#", //
+        "#...#..#", //
+        "#...####", //
+        "#####..#", //
+        "........", //
+        "........", //
+        "........", //
+    ];
+
+    #[test]
+    fn nearest_free_tile_from_inside_a_wall() {
+        let map = map(ROOM);
+        let start = Point { x: 4, y: 2 }; // The wall between both rooms
+        assert_eq!(map.read(start).map(free), Some(false));
+        let nearest = find_nearest(&map, start, 3, free).unwrap();
+        assert_eq!(start.manhattan_distance(nearest), 1);
+        // The same tile every time
+        assert_eq!(find_nearest(&map, start, 3, free), Some(nearest));
+
+        assert_eq!(find_nearest(&map, Point { x: 2, y: 2 }, 3, free), Some(Point { x: 2, y: 2 }));
+        assert_eq!(find_nearest(&map, start, 3, |p| p.0 == 7), None);
+    }
+
+    #[test]
+    fn nearest_is_by_euclidean_distance_within_the_radius() {
+        let target = |p: Point| p == Point { x: 3, y: 3 } || p == Point { x: 0, y: 4 };
+        // (3, 3) is on a nearer ring, but 4.2 away against 4
+        assert_eq!(find_nearest_point(Point { x: 0, y: 0 }, 5, target), Some(Point { x: 0, y: 4 }));
+        assert_eq!(find_nearest_point(Point { x: 0, y: 0 }, 3, target), None);
+    }
+
+    #[test]
+    fn flood_fill_stays_inside_a_room() {
+        let map = map(ROOM);
+        let area = flood_fill_reachable(&map, Point { x: 1, y: 1 }, 100, free);
+        assert_eq!(area.tiles.len(), 9);
+        assert!(area.tiles.contains(&Point { x: 3, y: 2 }));
+        assert!(!area.capped && !area.hit_unloaded);
+
+        let small = flood_fill_reachable(&map, Point { x: 5, y: 1 }, 100, free);
+        assert_eq!(small.tiles.len(), 4);
+    }
+
+    #[test]
+    fn flood_fill_from_a_wall_is_empty() {
+        let map = map(ROOM);
+        let area = flood_fill_reachable(&map, Point { x: 0, y: 0 }, 100, free);
+        assert!(area.tiles.is_empty());
+        assert!(!area.capped && !area.hit_unloaded);
+    }
+
+    #[test]
+    fn flood_fill_stops_at_the_cap_and_at_unloaded_chunks() {
+        let map = map(ROOM);
+        let open = Point { x: 3, y: 6 };
+        let capped = flood_fill_reachable(&map, open, 10, free);
+        assert_eq!(capped.tiles.len(), 10);
+        assert!(capped.capped);
+        assert!(capped.tiles.iter().all(|p| p.manhattan_distance(open) <= 2), "breadth first");
+
+        // The open rows run into the unloaded chunks around
+        let all = flood_fill_reachable(&map, open, 1000, free);
+        assert_eq!(all.tiles.len(), 24 + 2);
+        assert!(all.hit_unloaded && !all.capped);
+    }
+}
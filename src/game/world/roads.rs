@@ -0,0 +1,265 @@
+use bevy::math::Vec2;
+
+use crate::core::{
+    basics::Point,
+    chunks::{ChunkCoords, DataChunk, FlatGrid, MapDataProducer},
+    constants::DEFAULT_CHUNK_DIMENSION_TILES,
+    noise::hash2_unit,
+    units::TilesCount,
+};
+
+const ROAD_SEED_SALT: u64 = 0x40AD_5EED; // Roads don't repeat the terrain noise
+const ROAD_PIECES: usize = 32; // Straight pieces each road curve is measured along
+
+// Salts of the per super-cell hashes
+const POI_X_SALT: u64 = 0x01;
+const POI_Y_SALT: u64 = 0x02;
+const LINK_SALTS: [u64; 2] = [0x11, 0x12]; // Right, up
+const BEND_SALTS: [u64; 2] = [0x21, 0x22];
+const STEPS: [Point; 2] = [Point { x: 1, y: 0 }, Point { x: 0, y: 1 }];
+
+/// Knobs of the road network, see `RoadNetwork`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RoadParams {
+    pub super_cell_tiles: TilesCount, // Side of the coarse grid cells holding one point of interest each
+    pub jitter: f32,         // How far the point of interest may be off the cell center, 1.0 reaching the cell edges
+    pub bend: f32,           // Sideways offset of a road's control point, as a fraction of its length
+    pub connect_chance: f32, // Of each pair of neighboring points of interest being linked
+    pub half_width: f32,     // Tiles from the center line that are road
+}
+
+impl Default for RoadParams {
+    fn default() -> Self {
+        Self {
+            super_cell_tiles: 16 * DEFAULT_CHUNK_DIMENSION_TILES,
+            jitter: 0.6,
+            bend: 0.2,
+            connect_chance: 0.75,
+            half_width: 1.0,
+        }
+    }
+}
+
+/// Roads between points of interest, one per super-cell of `RoadParams::super_cell_tiles`,
+/// each linked to its right and upper neighbors by a curve. Everything is a pure function of
+/// the seed and the coordinates: a tile only looks at the roads of the super-cells around it,
+/// so chunks on either side of a road agree on it without knowing about each other.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RoadNetwork {
+    seed: u64,
+    params: RoadParams,
+}
+
+impl RoadNetwork {
+    pub fn new(seed: u64, params: RoadParams) -> Self {
+        Self {
+            seed: seed ^ ROAD_SEED_SALT,
+            params,
+        }
+    }
+
+    /// Super-cell holding the world tile.
+    pub fn super_cell(&self, world_tile_x: isize, world_tile_y: isize) -> Point {
+        let size = self.params.super_cell_tiles.max(1) as isize;
+        Point {
+            x: world_tile_x.div_euclid(size),
+            y: world_tile_y.div_euclid(size),
+        }
+    }
+
+    /// Point of interest of a super-cell, in world tiles, always inside the cell.
+    pub fn poi(&self, cell: Point) -> Vec2 {
+        let size = self.params.super_cell_tiles.max(1) as f32;
+        let jitter = Vec2::new(
+            hash2_unit(self.seed ^ POI_X_SALT, cell.x, cell.y),
+            hash2_unit(self.seed ^ POI_Y_SALT, cell.x, cell.y),
+        ) - Vec2::splat(0.5);
+        let jitter = jitter * self.params.jitter.clamp(0.0, 1.0);
+        (Vec2::new(cell.x as f32, cell.y as f32) + Vec2::splat(0.5) + jitter) * size
+    }
+
+    /// Road from the point of interest of `cell` to its right (`direction` 0) or upper
+    /// (`direction` 1) neighbor, as a quadratic curve: start, control point and end.
+    /// None if the two aren't linked.
+    pub fn road(&self, cell: Point, direction: usize) -> Option<[Vec2; 3]> {
+        if hash2_unit(self.seed ^ LINK_SALTS[direction], cell.x, cell.y) >= self.params.connect_chance {
+            return None;
+        }
+        let start = self.poi(cell);
+        let end = self.poi(cell + STEPS[direction]);
+        let offset = (hash2_unit(self.seed ^ BEND_SALTS[direction], cell.x, cell.y) - 0.5) * 2.0 * self.params.bend;
+        let control = (start + end) / 2.0 + (end - start).perp() * offset;
+        Some([start, control, end])
+    }
+
+    /// Whether the world tile is on a road. A road never leaves the super-cells of its ends and
+    /// the rows or columns next to them, so the 3x3 super-cells around the tile are enough.
+    pub fn is_road(&self, world_tile_x: isize, world_tile_y: isize) -> bool {
+        let tile = Vec2::new(world_tile_x as f32, world_tile_y as f32); // Tile centers are on whole coordinates
+        let center = self.super_cell(world_tile_x, world_tile_y);
+        let reach = self.params.half_width;
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                let cell = Point {
+                    x: center.x + dx,
+                    y: center.y + dy,
+                };
+                for direction in 0..STEPS.len() {
+                    let Some(curve) = self.road(cell, direction) else {
+                        continue;
+                    };
+                    // The curve stays inside the triangle of its three points
+                    let min = curve[0].min(curve[1]).min(curve[2]) - Vec2::splat(reach);
+                    let max = curve[0].max(curve[1]).max(curve[2]) + Vec2::splat(reach);
+                    if tile.cmplt(min).any() || tile.cmpgt(max).any() {
+                        continue;
+                    }
+                    if distance_to_curve(curve, tile) <= reach {
+                        return true;
+                    }
+                }
+            }
+        }
+        false
+    }
+}
+
+// Distance from `point` to the curve, measured along ROAD_PIECES straight pieces
+fn distance_to_curve(curve: [Vec2; 3], point: Vec2) -> f32 {
+    let at = |t: f32| curve[0].lerp(curve[1], t).lerp(curve[1].lerp(curve[2], t), t);
+    let mut from = curve[0];
+    let mut nearest = f32::INFINITY;
+    for i in 1..=ROAD_PIECES {
+        let to = at(i as f32 / ROAD_PIECES as f32);
+        let piece = to - from;
+        let t = ((point - from).dot(piece) / piece.length_squared().max(f32::EPSILON)).clamp(0.0, 1.0);
+        nearest = nearest.min(point.distance(from + piece * t));
+        from = to;
+    }
+    nearest
+}
+
+// Road DataProducer, true on road tiles
+#[derive(Debug, Clone, Default)]
+pub struct RoadNetworkProducer {
+    pub seed: u64,
+    pub params: RoadParams,
+}
+
+impl RoadNetworkProducer {
+    pub fn new(params: RoadParams) -> Self {
+        Self { seed: 0, params }
+    }
+
+    pub fn network(&self) -> RoadNetwork {
+        RoadNetwork::new(self.seed, self.params)
+    }
+}
+
+impl MapDataProducer for RoadNetworkProducer {
+    type Item = bool;
+    type GridType = FlatGrid<bool>;
+
+    fn default_value(&self) -> Self::Item {
+        false
+    }
+
+    fn with_seed(&self, seed: u64) -> Self {
+        Self {
+            seed,
+            params: self.params,
+        }
+    }
+
+    fn generate_chunk(&self, coords: ChunkCoords, dimension_tiles: TilesCount) -> DataChunk<Self::GridType> {
+        let network = self.network();
+        let grid = FlatGrid::from_fn(dimension_tiles, |x, y| {
+            let world_tile_x = coords.x * dimension_tiles as isize + x as isize;
+            let world_tile_y = coords.y * dimension_tiles as isize + y as isize;
+            network.is_road(world_tile_x, world_tile_y)
+        });
+
+        DataChunk::new(grid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::core::chunks::GridData;
+
+    const AREA: std::ops::Range<isize> = -64..64; // Tiles on each axis, across several super-cells
+
+    fn params() -> RoadParams {
+        RoadParams {
+            super_cell_tiles: 32, // Roads a few chunks long, crossing plenty of chunk borders
+            connect_chance: 1.0,
+            ..Default::default()
+        }
+    }
+
+    // Road tiles of `AREA`, row by row, each read from the chunk of `dimension` tiles holding it
+    fn generated(producer: &RoadNetworkProducer, dimension: TilesCount) -> Vec<bool> {
+        let mut chunks = HashMap::new();
+        let mut roads = Vec::new();
+        for y in AREA {
+            for x in AREA {
+                let point = Point { x, y };
+                let coords = ChunkCoords::from_point(point, dimension);
+                let chunk = chunks
+                    .entry(coords)
+                    .or_insert_with(|| producer.generate_chunk(coords, dimension));
+                let (local_x, local_y) = point.local_in_chunk(dimension);
+                roads.push(*chunk.grid.get_item(local_x, local_y).unwrap());
+            }
+        }
+        roads
+    }
+
+    #[test]
+    fn road_tiles_are_the_same_whichever_chunk_generates_them() {
+        let producer = RoadNetworkProducer::new(params()).with_seed(7);
+        let network = producer.network();
+        // Chunks of 16 and of 24 tiles split the area along different borders
+        let by_16 = generated(&producer, 16);
+        assert_eq!(by_16, generated(&producer, 24));
+        let tiles = AREA.flat_map(|y| AREA.map(move |x| (x, y)));
+        for ((x, y), road) in tiles.zip(by_16.iter()) {
+            assert_eq!(*road, network.is_road(x, y), "tile ({x}, {y})");
+        }
+
+        // Roads run on from one chunk into the next, across the borders of the chunks of 16
+        let borders = AREA.filter(|x| x.rem_euclid(16) == 0);
+        let crossings = borders
+            .flat_map(|border| AREA.map(move |along| (border, along)))
+            .filter(|&(border, along)| {
+                (network.is_road(border - 1, along) && network.is_road(border, along))
+                    || (network.is_road(along, border - 1) && network.is_road(along, border))
+            })
+            .count();
+        assert!(crossings > 0, "no road crosses a chunk border");
+        assert!(by_16.iter().filter(|road| **road).count() < by_16.len() / 2, "roads everywhere");
+    }
+
+    #[test]
+    fn points_of_interest_and_roads_depend_on_the_seed_only() {
+        let (first, second) = (RoadNetwork::new(42, params()), RoadNetwork::new(42, params()));
+        let other = RoadNetwork::new(43, params());
+        let cells: Vec<Point> = (-3..=3).flat_map(|x| (-3..=3).map(move |y| Point { x, y })).collect();
+        for cell in cells.iter().copied() {
+            let poi = first.poi(cell);
+            assert_eq!(poi, second.poi(cell));
+            assert_eq!(first.super_cell(poi.x.round() as isize, poi.y.round() as isize), cell, "off its cell");
+            for direction in 0..STEPS.len() {
+                assert_eq!(first.road(cell, direction), second.road(cell, direction));
+            }
+        }
+        assert!(cells.iter().any(|cell| first.poi(*cell) != other.poi(*cell)));
+
+        let producer = RoadNetworkProducer::new(params());
+        assert_eq!(generated(&producer.with_seed(42), 16), generated(&producer.with_seed(42), 16));
+        assert_ne!(generated(&producer.with_seed(42), 16), generated(&producer.with_seed(43), 16));
+    }
+}
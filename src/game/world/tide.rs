@@ -0,0 +1,203 @@
+use bevy::{platform::collections::HashMap, prelude::*};
+
+use crate::{
+    core::{
+        basics::Point,
+        chunks::{ChunkCoords, ChunkLoaded, ChunkWritten, DataMap, FlatGrid, GridData, MapDataProducer},
+        units::WorldPos,
+    },
+    game::{
+        Player,
+        physix::{Collider, PrevXY, Velocity},
+        render::light_sim::pbr_cell::{PbrCell, PbrCellProducer, sync_pbr_from_passability_system},
+        spawn::{find_spawn_tile, tile_to_world},
+        world::{
+            passability::{Passability, PassabilityProducer},
+            tile_types::{TileType, TileTypeProducer},
+        },
+    },
+};
+
+/// Knobs of the tides flooding the shore around the player.
+#[derive(Resource, Debug, Clone)]
+pub struct TideSettings {
+    pub period_secs: f32,       // Time between high and low tide
+    pub radius_tiles: isize,    // Around the player, only loaded tiles are flooded
+    pub band_width_tiles: isize, // Sand this close to water floods
+}
+
+impl Default for TideSettings {
+    fn default() -> Self {
+        Self {
+            period_secs: 5.0,
+            radius_tiles: 32,
+            band_width_tiles: 2,
+        }
+    }
+}
+
+/// Current tide, with what the flooded tiles were before the flood so the ebb can restore them.
+#[derive(Resource, Debug, Default)]
+pub struct TideState {
+    pub high: bool,
+    pub elapsed_secs: f32,
+    pub flooded: HashMap<Point, (Passability, TileType)>,
+}
+
+/// Floods and drains the shore on a timer, see `TideSettings`. Needs the passability,
+/// tile type and PbrCell maps, so it goes after `LightingPlugin`.
+pub struct TidePlugin;
+
+impl Plugin for TidePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TideSettings>()
+            .init_resource::<TideState>()
+            .add_systems(
+                Update,
+                (tide_system, tide_fog_system.after(sync_pbr_from_passability_system)),
+            );
+    }
+}
+
+/// Writes single tiles in bulk: loaded chunks get one `write_area` of the whole chunk, tiles of
+/// unloaded chunks are queued one by one. Neither goes to the `modified` journal, tides aren't saved.
+fn write_tiles<P>(map: &mut DataMap<P>, tiles: impl IntoIterator<Item = (Point, P::Item)>)
+where
+    P: MapDataProducer<GridType = FlatGrid<<P as MapDataProducer>::Item>>,
+    P::Item: std::fmt::Debug,
+{
+    let dimension = map.chunk_dimension_tiles;
+    let mut by_chunk: HashMap<ChunkCoords, Vec<(Point, P::Item)>> = HashMap::new();
+    for (point, value) in tiles {
+        by_chunk
+            .entry(ChunkCoords::from_point(point, dimension))
+            .or_default()
+            .push((point, value));
+    }
+    for (coords, tiles) in by_chunk {
+        let Some(chunk) = map.loaded_chunks.get(&coords) else {
+            for (point, value) in tiles {
+                map.write_area(point, &FlatGrid::new(1, value));
+            }
+            continue;
+        };
+        let mut grid = chunk.grid.clone();
+        for (point, value) in tiles {
+            let (local_x, local_y) = point.local_in_chunk(dimension);
+            grid.set_item(local_x, local_y, value);
+        }
+        map.write_area(coords.to_bottom_left_tile_point(dimension), &grid);
+    }
+}
+
+// Sand within the band of water, among the loaded tiles around `center`
+fn shore_band(tile_types: &DataMap<TileTypeProducer>, center: Point, settings: &TideSettings) -> Vec<Point> {
+    let radius = settings.radius_tiles;
+    let band = settings.band_width_tiles;
+    let near_water = |point: Point| {
+        (-band..=band).any(|dx| {
+            (-band..=band).any(|dy| {
+                tile_types.read(Point {
+                    x: point.x + dx,
+                    y: point.y + dy,
+                }) == Some(TileType::Water)
+            })
+        })
+    };
+    (-radius..=radius)
+        .flat_map(|dx| (-radius..=radius).map(move |dy| (dx, dy)))
+        .map(|(dx, dy)| Point {
+            x: center.x + dx,
+            y: center.y + dy,
+        })
+        .filter(|&point| tile_types.read(point) == Some(TileType::Sand) && near_water(point))
+        .collect()
+}
+
+/// Alternates high and low tide every `period_secs`. High tide turns the shore band into
+/// impassable water and pushes whatever stands there onto the nearest free spot.
+pub fn tide_system(
+    time: Res<Time>,
+    settings: Res<TideSettings>,
+    mut state: ResMut<TideState>,
+    mut passability: ResMut<DataMap<PassabilityProducer>>,
+    mut tile_types: ResMut<DataMap<TileTypeProducer>>,
+    player_query: Query<&GlobalTransform, With<Player>>, // Not Transform, which is moved below
+    mut colliders: Query<(&mut Transform, &Collider, Option<&mut PrevXY>, Option<&mut Velocity>)>,
+) {
+    state.elapsed_secs += time.delta_secs();
+    if state.elapsed_secs < settings.period_secs {
+        return;
+    }
+    state.elapsed_secs = 0.0;
+
+    if state.high {
+        // Ebb, back to what was there before
+        let flooded: Vec<(Point, (Passability, TileType))> = state.flooded.drain().collect();
+        write_tiles(&mut passability, flooded.iter().map(|(p, (pass, _))| (*p, *pass)));
+        write_tiles(&mut tile_types, flooded.iter().map(|(p, (_, tile))| (*p, *tile)));
+        state.high = false;
+        return;
+    }
+
+    let Ok(player_transform) = player_query.single() else {
+        return;
+    };
+    let center = WorldPos(player_transform.translation().xy()).to_tile().0;
+    let band = shore_band(&tile_types, center, &settings);
+    for point in band.iter() {
+        let (Some(pass), Some(tile)) = (passability.read(*point), tile_types.read(*point)) else {
+            continue;
+        };
+        state.flooded.insert(*point, (pass, tile));
+    }
+    let flooded: Vec<Point> = state.flooded.keys().copied().collect();
+    write_tiles(&mut passability, flooded.iter().map(|p| (*p, Passability::IMPASSABLE)));
+    write_tiles(&mut tile_types, flooded.iter().map(|p| (*p, TileType::Water)));
+    state.high = true;
+
+    // Nobody gets stuck in the water: the nearest spot a collider fits, like spawning
+    for (mut transform, collider, prev, velocity) in colliders.iter_mut() {
+        let tile = WorldPos(transform.translation.xy()).to_tile().0;
+        if !state.flooded.contains_key(&tile) {
+            continue;
+        }
+        let Some(free) = find_spawn_tile(&passability, tile, collider.threshold) else {
+            continue;
+        };
+        transform.translation = tile_to_world(free).extend(transform.translation.z);
+        if let Some(mut prev) = prev {
+            prev.0 = transform.translation;
+        }
+        if let Some(mut velocity) = velocity {
+            velocity.0 = Vec2::ZERO;
+        }
+    }
+    info!("High tide, {} shore tiles flooded", state.flooded.len());
+}
+
+/// Fogs the light over flooded tiles. Runs after `sync_pbr_from_passability_system`, which
+/// would otherwise turn them into solid walls whenever their passability chunk changes.
+pub fn tide_fog_system(
+    state: Res<TideState>,
+    passability: Res<DataMap<PassabilityProducer>>,
+    mut loaded_events: EventReader<ChunkLoaded<PassabilityProducer>>,
+    mut written_events: EventReader<ChunkWritten<PassabilityProducer>>,
+    mut pbr_cells: ResMut<DataMap<PbrCellProducer>>,
+) {
+    let dimension = passability.chunk_dimension_tiles;
+    let changed: Vec<ChunkCoords> = loaded_events
+        .read()
+        .map(|e| e.coords)
+        .chain(written_events.read().map(|e| e.coords))
+        .collect();
+    if changed.is_empty() || state.flooded.is_empty() {
+        return;
+    }
+    let fogged = state
+        .flooded
+        .keys()
+        .filter(|p| changed.contains(&ChunkCoords::from_point(**p, dimension)))
+        .map(|p| (*p, PbrCell::MEDIUM_FOG));
+    write_tiles(&mut pbr_cells, fogged);
+}
@@ -0,0 +1,193 @@
+use bevy::{
+    ecs::{
+        query::With,
+        system::{Local, Query, ResMut},
+    },
+    log::info,
+    transform::components::Transform,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    core::{
+        basics::Point,
+        chunks::{
+            ChunkCoords, ChunkPostProcessor, DataChunk, DataMap, FlatGrid, GridData,
+            MapDataProducer, NeighborView, TileBytes,
+        },
+        noise::value_noise,
+        savegame::SaveableProducer,
+        units::{TilesCount, WorldPos},
+    },
+    game::{
+        Player,
+        world::passability::{Passability, PassabilityProducer, TerrainParams},
+    },
+};
+
+const WATER_FREQUENCY: f32 = 1.0 / 64.0; // Lakes vs. rocky ridges among the walls
+
+// Terrain kind
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TileType {
+    #[default]
+    Grass = 0,
+    Water = 1,
+    Sand = 2,
+    Rock = 3,
+    Road = 4,
+}
+
+impl TileType {
+    pub fn is_passable(&self) -> bool {
+        matches!(self, TileType::Grass | TileType::Sand | TileType::Road)
+    }
+}
+
+impl TileBytes for TileType {
+    const SIZE: usize = 1;
+
+    fn write_bytes(&self, out: &mut Vec<u8>) {
+        out.push(*self as u8);
+    }
+
+    fn read_bytes(bytes: &[u8]) -> Self {
+        match bytes[0] {
+            1 => TileType::Water,
+            2 => TileType::Sand,
+            3 => TileType::Rock,
+            4 => TileType::Road,
+            _ => TileType::Grass,
+        }
+    }
+}
+
+// TileType DataProducer, derived from the passability terrain so both maps agree
+#[derive(Default, Clone)]
+pub struct TileTypeProducer {
+    pub passability: PassabilityProducer,
+}
+
+impl TileTypeProducer {
+    pub fn new(terrain: TerrainParams) -> Self {
+        Self {
+            passability: PassabilityProducer::new(terrain),
+        }
+    }
+
+    /// Walls are water or rock, free tiles on the fading border of water are sand.
+    /// Roads cross everything, as they do in the passability map.
+    pub fn tile_type_at(&self, world_tile_x: isize, world_tile_y: isize) -> TileType {
+        if self.passability.is_road(world_tile_x, world_tile_y) {
+            return TileType::Road;
+        }
+        let passability = self.passability.passability_at(world_tile_x, world_tile_y);
+        let wet = value_noise(
+            self.passability.seed ^ 0x7A7E,
+            world_tile_x as f32 * WATER_FREQUENCY,
+            world_tile_y as f32 * WATER_FREQUENCY,
+        ) < 0.5;
+        match passability {
+            p if p.is_wall() && wet => TileType::Water,
+            p if p.is_wall() => TileType::Rock,
+            p if p != Passability::FREE && wet => TileType::Sand,
+            _ => TileType::Grass,
+        }
+    }
+}
+
+impl MapDataProducer for TileTypeProducer {
+    type Item = TileType;
+    type GridType = FlatGrid<TileType>;
+
+    fn default_value(&self) -> Self::Item {
+        TileType::default()
+    }
+
+    fn with_seed(&self, seed: u64) -> Self {
+        Self {
+            passability: self.passability.with_seed(seed),
+        }
+    }
+
+    fn generate_chunk(
+        &self,
+        coords: ChunkCoords,
+        dimension_tiles: TilesCount,
+    ) -> DataChunk<Self::GridType> {
+        let grid = FlatGrid::from_fn(dimension_tiles, |x, y| {
+            let world_tile_x = coords.x * dimension_tiles as isize + x as isize;
+            let world_tile_y = coords.y * dimension_tiles as isize + y as isize;
+            self.tile_type_at(world_tile_x, world_tile_y)
+        });
+
+        DataChunk::new(grid)
+    }
+}
+
+impl SaveableProducer for TileTypeProducer {
+    const SAVE_NAME: &'static str = "tile_types";
+}
+
+/// Same holes as `PassabilityHoleFiller` removes, so both maps keep agreeing:
+/// water or rock tiles surrounded by passable ones take the type of one of them.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TileTypeHoleFiller;
+
+impl ChunkPostProcessor<TileTypeProducer> for TileTypeHoleFiller {
+    fn process(
+        &self,
+        _coords: ChunkCoords,
+        grid: &mut FlatGrid<TileType>,
+        neighbors: &NeighborView<TileTypeProducer>,
+    ) {
+        let original = grid.clone();
+        let tile = |x: isize, y: isize| match (x >= 0 && y >= 0)
+            .then(|| original.get_item(x as TilesCount, y as TilesCount))
+            .flatten()
+        {
+            Some(&item) => Some(item),
+            None => neighbors.item_outside(x, y),
+        };
+
+        grid.map_in_place(|x, y, item| {
+            if item.is_passable() {
+                return item;
+            }
+            let (x, y) = (x as isize, y as isize);
+            let around = [tile(x + 1, y), tile(x - 1, y), tile(x, y + 1), tile(x, y - 1)];
+            if around.iter().all(|t| t.is_some_and(|t| t.is_passable())) {
+                around[0].unwrap_or(item)
+            } else {
+                item
+            }
+        });
+    }
+
+    fn rerun_when_neighbors_load(&self) -> bool {
+        true
+    }
+}
+
+pub fn check_player_tile_type(
+    player_query: Query<&Transform, With<Player>>,
+    mut tile_type_map: ResMut<DataMap<TileTypeProducer>>, // Needs mut to make requests
+    mut last_checked: Local<Option<(Point, TileType)>>,
+) {
+    let Ok(player_transform) = player_query.single() else {
+        return;
+    };
+    let player_tile_point = WorldPos(player_transform.translation.truncate()).to_tile().0;
+    if last_checked.is_some_and(|(p, _)| p == player_tile_point) {
+        return;
+    }
+
+    let Some(tile_type) = tile_type_map.get_option(player_tile_point) else {
+        return; // Requested, checked again once loaded
+    };
+    if last_checked.is_none_or(|(_, t)| t != tile_type) {
+        info!("Player at tile {:?} stands on {:?}", player_tile_point, tile_type);
+    }
+    *last_checked = Some((player_tile_point, tile_type));
+}
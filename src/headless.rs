@@ -0,0 +1,36 @@
+use bevy::{
+    app::PluginGroupBuilder,
+    input::InputPlugin,
+    prelude::*,
+};
+
+use crate::{Pallete, game::render::RenderFeatures};
+
+/// `MinimalPlugins` plus what the world logic needs besides rendering: assets, input and
+/// transform propagation. Render-side systems are disabled through `RenderFeatures`, so tests
+/// can pump `App::update()` without a window or GPU. Add it before the game plugins.
+pub struct HeadlessPlugins;
+
+impl PluginGroup for HeadlessPlugins {
+    fn build(self) -> PluginGroupBuilder {
+        PluginGroupBuilder::start::<Self>()
+            .add_group(MinimalPlugins)
+            .add(AssetPlugin::default())
+            .add(InputPlugin)
+            .add(TransformPlugin)
+            .add(HeadlessStubsPlugin)
+    }
+}
+
+/// Resources and asset types the render plugins would otherwise provide.
+pub struct HeadlessStubsPlugin;
+
+impl Plugin for HeadlessStubsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(RenderFeatures { enabled: false })
+            .init_resource::<Pallete>()
+            .init_asset::<Image>()
+            .init_asset::<Mesh>()
+            .init_asset::<ColorMaterial>();
+    }
+}
@@ -0,0 +1,14 @@
+//! Chunked world simulation on Bevy: the `core` chunk system and the `game` built on it.
+//! `prelude` has what's needed to add a custom `MapDataProducer` to an `App`.
+
+use bevy::{asset::Handle, ecs::resource::Resource, platform::collections::HashMap, sprite::ColorMaterial};
+
+pub mod core;
+pub mod game;
+pub mod headless;
+pub mod prelude;
+
+#[derive(Debug, Default, Clone, Resource)]
+pub struct Pallete {
+    pub colors: HashMap::<String, Handle<ColorMaterial>>
+}
@@ -1,46 +1,37 @@
 // --- Example Concrete Data Types ---
 
 use bevy::{
-    app::{App, Startup, Update}, asset::{Assets, Handle}, color::{palettes::css::{LIMEGREEN, RED}, Color}, core_pipeline::core_2d::Camera2d, ecs::{
-        component::Component, query::{With, Without}, resource::Resource, system::{Commands, Query, Res, ResMut}
-    }, gizmos::gizmos::Gizmos, math::{primitives::Circle, Vec2, Vec3}, platform::collections::{HashMap, HashSet}, render::mesh::{Mesh, Mesh2d}, sprite::{ColorMaterial, MeshMaterial2d}, time::{Fixed, Time}, transform::components::{GlobalTransform, Transform}, DefaultPlugins
+    app::{App, FixedUpdate, Startup, Update}, log::warn, asset::Assets, color::{palettes::css::{GOLD, LIGHT_SKY_BLUE, LIMEGREEN, MEDIUM_PURPLE, ORANGE, RED, WHEAT}, Color}, core_pipeline::core_2d::Camera2d, ecs::{
+        schedule::{common_conditions::not, IntoScheduleConfigs}, system::{Commands, ResMut}
+    }, math::{primitives::Circle, Vec2, Vec3}, render::{mesh::{Mesh, Mesh2d}, view::Visibility}, sprite::{ColorMaterial, MeshMaterial2d}, time::{Fixed, Time}, transform::components::{GlobalTransform, Transform}, DefaultPlugins
 };
 
-use crate::{
+use rust_sim::{
+    Pallete,
     core::{
         basics::DEFAULT_RENDER_DISTANCE_CHUNKS,
-        chunks::{insert_chunked_plugin, DataMap}, constants::DEFAULT_CHUNK_DIMENSION_TILES,
+        chunks::{ChunkedMapConfig, DataMap, FocusPolicy, FsChunkStore, RleCompressor, WorldSeed},
+        savegame::{register_saveable_map, SaveGamePlugin},
+        validation::validate_world_config,
     },
     game::{
-        physix, render::{light_sim::lighting::Lighting, tilemap_render::{
-            background_load_required_chunks_system, background_load_unload_system, BackgroundHypertileTracker,
-        }}, world::passability::{check_player_passability, PassabilityProducer}, MapRevealActor, Player
+        ants::AntsPlugin,
+        bootstrap::{bootstrapped, BootstrapPlugin},
+        camera::{camera_follow_system, camera_zoom_system, CameraShake, CameraZoom, FollowCamera},
+        debug::{insert_chunked_plugin_with_debug, register_seam_debug, DebugOverlayPlugin},
+        input::{resolve_move_intent, InputBindings, MoveIntent},
+        inspector::register_chunk_summary,
+        npc::WanderPlugin,
+        projectile::ProjectilePlugin,
+        replay::{replaying, Replay, ReplayPlayer, ReplayPlugin},
+        required::require_single,
+        sim_speed::SimulationSpeedPlugin,
+        spawn::{finalize_spawn, Dormant, SpawnPoint},
+        teleport::TeleportPlugin,
+        self as game, physix, render::{RenderLayerZ, culling::CullingPlugin, fog::FogOfWarPlugin, lit_sprite::LitSprite, minimap::MinimapPlugin, light_sim::{lighting::LightingPlugin, lights::LightEmitter, pbr_cell::{PbrCellProducer, PbrFogColorizer}}, tilemap_render::{insert_tilemap_render_plugin, TileTypeColorizer}}, world::{editor::TileEditorPlugin, exploration::ExplorationProducer, heat::HeatPlugin, props::{PropParams, PropPlugin}, tide::TidePlugin, passability::{check_player_passability, PassabilityHoleFiller, PassabilityProducer, TerrainParams}, pathfinding::insert_pathfinding_plugin, tile_types::{check_player_tile_type, TileTypeHoleFiller, TileTypeProducer}}, MapRevealActor, Player
     },
 };
 
-pub mod core;
-pub mod game;
-
-#[derive(Component)]
-struct FollowCamera {
-    pub smoothing: f32, // Higher values = smoother but slower following
-    pub offset: Vec3,   // Optional offset from player position
-}
-
-impl Default for FollowCamera {
-    fn default() -> Self {
-        Self {
-            smoothing: 2.0,
-            offset: Vec3::ZERO,
-        }
-    }
-}
-
-#[derive(Debug, Default, Clone, Resource)]
-pub struct Pallete {
-    pub colors: HashMap::<String, Handle<ColorMaterial>>
-}
-
 fn setup_game(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
@@ -53,110 +44,177 @@ fn setup_game(
     let red = materials.add(ColorMaterial::from_color(Color::from(RED)));
     pallete.colors.insert("limegreen".to_string(), limegreen);
     pallete.colors.insert("red".to_string(), red);
+    let wanderer = materials.add(ColorMaterial::from_color(Color::from(GOLD)));
+    pallete.colors.insert("wanderer".to_string(), wanderer);
+    let frozen = materials.add(ColorMaterial::from_color(Color::from(LIGHT_SKY_BLUE)));
+    pallete.colors.insert("frozen".to_string(), frozen);
+    let prop_lamp = materials.add(ColorMaterial::from_color(Color::from(WHEAT)));
+    pallete.colors.insert("prop_lamp".to_string(), prop_lamp);
+    let prop_crystal = materials.add(ColorMaterial::from_color(Color::from(MEDIUM_PURPLE)));
+    pallete.colors.insert("prop_crystal".to_string(), prop_crystal);
+    let projectile = materials.add(ColorMaterial::from_color(Color::from(ORANGE)));
+    pallete.colors.insert("projectile".to_string(), projectile);
     commands.spawn((
         Player,
-        MapRevealActor,
-        crate::game::physix::PrevXY::default(),
-        Transform::from_translation(Vec3::new(0.0, 0.0, 10.0)),
+        Dormant, // Placed on a free tile by finalize_spawn
+        Visibility::Hidden,
+        MapRevealActor {
+            radius_chunks: DEFAULT_RENDER_DISTANCE_CHUNKS,
+            priority: u8::MAX,
+        },
+        physix::PrevXY::default(),
+        physix::RenderInterpolated::default(), // Drawn between fixed ticks, smooth at any frame rate
+        MoveIntent::default(),
+        physix::Velocity::default(),
+        physix::Collider {
+            radius: 5.0, // Same as the player circle
+            ..Default::default()
+        },
+        LightEmitter {
+            color: ORANGE.into(),
+            ..Default::default()
+        },
+        Transform::from_translation(Vec3::new(0.0, 0.0, RenderLayerZ::Actors.z_with(1.0))), // Over the NPCs
         GlobalTransform::default(),
         // Add visual for player
         Mesh2d(meshes.add(Circle::new(5.0))), // Circle directly from bevy::math
         MeshMaterial2d(pallete.colors.get("limegreen").unwrap().clone()), // Explicitly create and add ColorMaterial
+        LitSprite::default(), // Dimmed in the dark, the material above is only its base color
     ));
 }
 
-// System to visualize loaded chunks (optional, for debugging)
-fn visualize_loaded_chunks(mut gizmos: Gizmos, passability_map: Res<DataMap<PassabilityProducer>>) {
-    for (coords, _) in passability_map.loaded_chunks.iter() {
-        let chunk_world_pos = coords.to_world_pos(passability_map.chunk_size_units);
-        let center = chunk_world_pos + Vec2::splat(passability_map.chunk_size_units / 2.0);
-        let size = Vec2::splat(passability_map.chunk_size_units);
-        gizmos.rect_2d(Vec2::ZERO, size, Color::srgba(0.0, 1.0, 0.0, 0.1)); // Semi-transparent green
-    }
-}
-
-// System to visualize requested chunks (optional, for debugging)
-fn visualize_requested_chunks(
-    mut gizmos: Gizmos,
-    passability_map: Res<DataMap<PassabilityProducer>>,
-) {
-    for coords in passability_map.requested_chunks.iter() {
-        let chunk_world_pos = coords.to_world_pos(passability_map.chunk_size_units);
-        let center = chunk_world_pos + Vec2::splat(passability_map.chunk_size_units / 2.0);
-        let size = Vec2::splat(passability_map.chunk_size_units);
-        gizmos.rect_2d(Vec2::ZERO, size, Color::srgba(1.0, 1.0, 0.0, 0.2)); // Semi-transparent yellow
-    }
-    for (coords, _) in passability_map.pending_tasks.iter() {
-        let chunk_world_pos = coords.to_world_pos(passability_map.chunk_size_units);
-        let center = chunk_world_pos + Vec2::splat(passability_map.chunk_size_units / 2.0);
-        let size = Vec2::splat(passability_map.chunk_size_units);
-        gizmos.rect_2d(Vec2::ZERO, size, Color::srgba(1.0, 0.5, 0.0, 0.2)); // Semi-transparent orange
-    }
-}
-
 // Example: System to read passability for player's current tile
 
 fn main() {
     let mut app = App::new();
+    let terrain = TerrainParams::default(); // Tweak world generation here
 
     app.add_plugins(DefaultPlugins)
         .add_systems(Startup, setup_game)
+        .insert_resource(Pallete::default())
+        .init_resource::<physix::PhysixSettings>()
+        .add_plugins(physix::RenderInterpolationPlugin)
+        .add_plugins(BootstrapPlugin) // Startup chunks are generated behind a progress screen
+        .init_resource::<CameraZoom>()
+        .init_resource::<CameraShake>()
+        .init_resource::<InputBindings>() // Remap keys and gamepad buttons here
+        .init_resource::<SpawnPoint>()
+        // Movement reads its input only through MoveIntent, one tick at a time, so recordings replay exactly
         .add_systems(
-            Update,
+            FixedUpdate,
             (
-                background_load_required_chunks_system,
-                background_load_unload_system,
-            ),
+                resolve_move_intent.run_if(not(replaying)).run_if(bootstrapped),
+                game::player_movement,
+                physix::apply_velocity,
+                physix::resolve_tile_collisions,
+                physix::bounce_back,
+            )
+                .chain(),
         )
-        // Insert your DataMap resources
-        .insert_resource(DataMap::<PassabilityProducer>::new(
-            PassabilityProducer,
-            DEFAULT_CHUNK_DIMENSION_TILES,
-            DEFAULT_RENDER_DISTANCE_CHUNKS * 10,
-        ))
-        .insert_resource(BackgroundHypertileTracker {spawned: HashSet::new(), requested: HashSet::new()})
-        .insert_resource(Pallete::default())
         .insert_resource(Time::<Fixed>::from_hz(30.0))
         // Add systems to the Update schedule
         .add_systems(
             Update,
             (
-                game::player_movement,
-                physix::bounce_back,
-                // These run for each DataMap type
-                // Add these lines for each additional DataMap you create (e.g., TileTypeProducer)
-                // data_map_load_unload_system::<TileTypeProducer>,
-                // data_map_spawn_tasks_system::<TileTypeProducer>,
-                // data_map_process_completed_tasks_system::<TileTypeProducer>,
-
+                finalize_spawn,
                 // Game logic systems
                 check_player_passability,
-                visualize_loaded_chunks,    // Debug visualization
-                visualize_requested_chunks, // Debug visualization
+                check_player_tile_type,
                 // Camera
-                camera_follow_system,
+                camera_follow_system
+                    .after(physix::interpolation::interpolate_fixed_transforms)
+                    .run_if(bootstrapped),
+                camera_zoom_system,
             ),
         );
-    insert_chunked_plugin(&mut app, PassabilityProducer, 50);
-    app.add_plugins(Lighting);
-    app.run();
-}
-
-pub fn camera_follow_system(
-    player_query: Query<&Transform, (With<Player>, Without<FollowCamera>)>,
-    mut camera_query: Query<(&mut Transform, &FollowCamera), Without<Player>>,
-    time: Res<Time>,
-) {
-    if let Ok(player_transform) = player_query.single() {
-        for (mut camera_transform, follow_camera) in camera_query.iter_mut() {
-            let target_position = player_transform.translation + follow_camera.offset;
-
-            // Smooth interpolation using exponential decay
-            let smoothing_factor = 1.0 - (-follow_camera.smoothing * time.delta_secs()).exp();
-
-            camera_transform.translation = camera_transform
-                .translation
-                .lerp(target_position, smoothing_factor);
+    // REPLAY_FILE=<path> plays a recording back instead of the live input, in the world it was made in
+    let replay = std::env::var("REPLAY_FILE").ok().and_then(|path| match Replay::load(path.as_ref()) {
+        Ok(replay) => Some(replay),
+        Err(err) => {
+            warn!("Failed to load the replay {}: {}", path, err);
+            None
         }
+    });
+    let seed = replay
+        .as_ref()
+        .map_or_else(WorldSeed::from_env_or_random, |replay| WorldSeed(replay.world_seed));
+    app.insert_resource(seed);
+    app.add_plugins(ReplayPlugin); // F10 starts and stops recording the player's input
+    if let Some(replay) = replay {
+        app.insert_resource(ReplayPlayer::new(replay));
     }
+    insert_chunked_plugin_with_debug(
+        &mut app,
+        PassabilityProducer::new(terrain),
+        ChunkedMapConfig {
+            focus: FocusPolicy::FollowMapRevealActors {
+                radius_chunks: DEFAULT_RENDER_DISTANCE_CHUNKS * 10, // Far ahead for the background render
+            },
+            ..Default::default()
+        },
+    );
+    // Saves of different worlds must not mix
+    app.world_mut()
+        .resource_mut::<DataMap<PassabilityProducer>>()
+        .set_store(FsChunkStore::new(format!("saves/{}/passability", seed.0)));
+    app.world_mut()
+        .resource_mut::<DataMap<PassabilityProducer>>()
+        .add_post_processor(PassabilityHoleFiller);
+    // Chunks left behind stay compressed up to twice as far, walking back doesn't regenerate them
+    let mut passability = app.world_mut().resource_mut::<DataMap<PassabilityProducer>>();
+    let retention = passability.unload_distance_chunks * 2;
+    passability.set_compressor(RleCompressor, retention);
+    // Derived from the same terrain and seed, so water and rock are exactly the walls
+    insert_chunked_plugin_with_debug(&mut app, TileTypeProducer::new(terrain), ChunkedMapConfig::default());
+    app.world_mut()
+        .resource_mut::<DataMap<TileTypeProducer>>()
+        .add_post_processor(TileTypeHoleFiller);
+    let mut tile_types = app.world_mut().resource_mut::<DataMap<TileTypeProducer>>();
+    let retention = tile_types.unload_distance_chunks * 2;
+    tile_types.set_compressor(RleCompressor, retention);
+    insert_tilemap_render_plugin(&mut app, TileTypeColorizer, RenderLayerZ::Background);
+    insert_pathfinding_plugin(&mut app);
+    app.add_plugins(LightingPlugin::default());
+    app.add_plugins(FogOfWarPlugin); // Unexplored areas stay dark until the player has been near them
+    app.add_plugins(DebugOverlayPlugin);
+    // F4 outlines border tiles generated differently depending on their chunk
+    register_seam_debug::<PassabilityProducer>(&mut app);
+    register_seam_debug::<TileTypeProducer>(&mut app);
+    // F7 then a click shows a chunk's load state, the value histogram of these two as well
+    register_chunk_summary::<PassabilityProducer>(&mut app);
+    register_chunk_summary::<PbrCellProducer>(&mut app);
+    app.add_plugins(MinimapPlugin);
+    app.add_plugins(CullingPlugin); // Hides hypertiles and the light overlay outside the camera view
+    app.add_plugins(TileEditorPlugin);
+    app.add_plugins(TidePlugin); // The shore floods and drains every few seconds
+    app.add_plugins(WanderPlugin);
+    app.add_plugins(HeatPlugin); // The player warms the ground, which cools down over a few seconds
+    // A small colony foraging near the start, trails kept in their own chunked map
+    app.add_plugins(AntsPlugin {
+        nest: Vec2::new(320.0, 320.0),
+        ants: 50,
+    });
+    // Lamp posts and glowing crystals about every dozen tiles, lit through their LightEmitter
+    app.add_plugins(PropPlugin {
+        terrain,
+        params: PropParams::default(),
+    });
+    app.add_plugins(TeleportPlugin); // T jumps +500,+500 once the destination is loaded
+    app.add_plugins(ProjectilePlugin); // F fires a glowing bolt that stops at walls
+    // Space pauses, comma and period slow down and speed up, N steps one tick while paused
+    app.add_plugins(SimulationSpeedPlugin);
+    // F5 saves the player position and the edited tiles, F9 loads them
+    app.add_plugins(SaveGamePlugin);
+    // Logged once after Startup if missing, the systems reading them skip their work meanwhile
+    require_single::<Player>(&mut app, "player", "It is spawned by setup_game.");
+    require_single::<FollowCamera>(&mut app, "follow camera", "It is spawned by setup_game with the Camera2d.");
+    register_saveable_map::<PassabilityProducer>(&mut app);
+    register_saveable_map::<TileTypeProducer>(&mut app);
+    register_saveable_map::<ExplorationProducer>(&mut app);
+    insert_tilemap_render_plugin(&mut app, PbrFogColorizer, RenderLayerZ::TerrainDetail);
+    // Logs the chunk and hypertile sizes of every map and layer, refusing to start on misaligned ones
+    if let Err(err) = validate_world_config(&app) {
+        panic!("Invalid world config: {err}");
+    }
+    app.run();
 }
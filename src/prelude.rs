@@ -0,0 +1,24 @@
+//! Commonly needed items, `use rust_sim::prelude::*;` to implement and register a producer.
+
+pub use crate::{
+    Pallete,
+    headless::HeadlessPlugins,
+    core::{
+        basics::Point,
+        chunks::{
+            AreaSnapshot, BitGrid, BoolProducer, ChunkCoords, ChunkEntities, ChunkLoaded, ChunkMemoryBudget,
+            ChunkSpawner, ChunkSystems, ChunkVersions, ChunkedMapConfig, ContiguousGridData, DataChunk, DataMap, FlatGrid,
+            GridData, LoadShape, MapDataProducer, RenderDistanceChange, Stamp, StampMode, UnloadPolicy,
+            WorldSeed, insert_chunk_spawner_plugin, insert_chunked_plugin,
+        },
+        constants::{DEFAULT_CHUNK_DIMENSION_TILES, TILE_SIZE_IN_UNITS},
+        rng::SimRng,
+        units::{TileCoord, TilesCount, WorldPos},
+    },
+    game::{
+        MapRevealActor, Player,
+        debug::insert_chunked_plugin_with_debug,
+        render::{RenderFeatures, light_sim::lighting::{LightingConfig, LightingPlugin}, render_enabled},
+        world::passability::{Passability, PassabilityProducer},
+    },
+};
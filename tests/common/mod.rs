@@ -0,0 +1,40 @@
+//! Helpers shared by the integration tests, which drive a headless `App` frame by frame.
+
+#![allow(dead_code)] // Each test binary uses its own subset
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+use rust_sim::prelude::*;
+
+const MAX_FRAMES: usize = 1000; // Chunk tasks run on other threads, give them time to finish
+pub const FRAME: Duration = Duration::from_millis(33); // Every update advances the clocks by this much
+
+/// An `App` with `HeadlessPlugins` whose clocks advance by `FRAME` per update, however long the
+/// update took; `build` adds the plugins and resources under test.
+pub fn headless_app(build: impl FnOnce(&mut App)) -> App {
+    let mut app = App::new();
+    app.add_plugins(HeadlessPlugins)
+        .insert_resource(bevy::time::TimeUpdateStrategy::ManualDuration(FRAME));
+    build(&mut app);
+    app.finish();
+    app.cleanup();
+    app
+}
+
+/// Updates `app` until `done` holds, false if it didn't within `MAX_FRAMES`.
+pub fn update_until(app: &mut App, mut done: impl FnMut(&mut App) -> bool) -> bool {
+    for _ in 0..MAX_FRAMES {
+        app.update();
+        if done(app) {
+            return true;
+        }
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    false
+}
+
+/// Whether the square of chunks within `radius_chunks` around the chunk of `center` is loaded.
+pub fn loaded_around<P: MapDataProducer>(app: &App, center: Point, radius_chunks: usize) -> bool {
+    app.world().resource::<DataMap<P>>().is_loaded_around(center, radius_chunks)
+}
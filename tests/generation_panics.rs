@@ -0,0 +1,120 @@
+//! A producer panicking on one chunk leaves a hole there, retried a bounded number of times,
+//! and nothing else: the rest of the map loads as usual.
+
+mod common;
+
+use std::sync::{
+    Arc,
+    atomic::{AtomicU32, Ordering},
+};
+
+use bevy::prelude::*;
+use rust_sim::{
+    core::chunks_double_buf::{DataMapDoubleBuffered, insert_chunked_double_buffered_plugin},
+    prelude::*,
+};
+
+use common::{headless_app, update_until};
+
+const BROKEN: ChunkCoords = ChunkCoords { x: 1, y: 0 };
+const NEIGHBORS: [ChunkCoords; 3] = [
+    ChunkCoords { x: 0, y: 0 },
+    ChunkCoords { x: 2, y: 0 },
+    ChunkCoords { x: 1, y: 1 },
+];
+
+/// Panics whenever it's asked for `BROKEN`, counting the attempts.
+#[derive(Clone, Default)]
+struct PanickyProducer {
+    attempts: Arc<AtomicU32>,
+}
+
+impl MapDataProducer for PanickyProducer {
+    type Item = u8;
+    type GridType = FlatGrid<u8>;
+
+    fn default_value(&self) -> u8 {
+        0
+    }
+
+    fn generate_chunk(&self, coords: ChunkCoords, dimension_tiles: TilesCount) -> DataChunk<FlatGrid<u8>> {
+        if coords == BROKEN {
+            self.attempts.fetch_add(1, Ordering::SeqCst);
+            panic!("no terrain at {coords:?}");
+        }
+        DataChunk::new(FlatGrid::new(dimension_tiles, 1))
+    }
+}
+
+fn spawn_actor(app: &mut App) {
+    app.world_mut().spawn((
+        Transform::default(),
+        MapRevealActor {
+            radius_chunks: 2,
+            priority: 0,
+        },
+    ));
+}
+
+fn tile_of(coords: ChunkCoords) -> Point {
+    coords.to_bottom_left_tile_point(DEFAULT_CHUNK_DIMENSION_TILES)
+}
+
+#[test]
+fn data_map_gives_up_on_a_panicking_chunk() {
+    let producer = PanickyProducer::default();
+    let attempts = producer.attempts.clone();
+    let mut app = headless_app(|app| {
+        insert_chunked_plugin(
+            app,
+            producer,
+            ChunkedMapConfig {
+                init_radius_tiles: 0,
+                ..default()
+            },
+        );
+    });
+    spawn_actor(&mut app);
+
+    assert!(update_until(&mut app, |app| {
+        let map = app.world().resource::<DataMap<PanickyProducer>>();
+        map.failed_chunks.contains(&BROKEN) && NEIGHBORS.iter().all(|c| map.loaded_chunks.contains_key(c))
+    }));
+    for _ in 0..10 {
+        app.update(); // Given up for good, nothing requests it again
+    }
+
+    let map = app.world().resource::<DataMap<PanickyProducer>>();
+    assert_eq!(attempts.load(Ordering::SeqCst), 1 + map.max_generation_retries);
+    assert!(!map.generation_failures.contains_key(&BROKEN));
+    assert_eq!(map.read(tile_of(BROKEN)), None);
+    for coords in NEIGHBORS {
+        assert_eq!(map.read(tile_of(coords)), Some(1), "neighbor {coords:?}");
+    }
+}
+
+#[test]
+fn double_buffered_map_gives_up_on_a_panicking_chunk() {
+    let producer = PanickyProducer::default();
+    let attempts = producer.attempts.clone();
+    let mut app = headless_app(|app| {
+        insert_chunked_double_buffered_plugin(app, producer, 0);
+    });
+    spawn_actor(&mut app);
+
+    assert!(update_until(&mut app, |app| {
+        let map = app.world().resource::<DataMapDoubleBuffered<PanickyProducer>>();
+        map.failed_chunks.contains(&BROKEN) && NEIGHBORS.iter().all(|c| map.read_chunks().contains_key(c))
+    }));
+    for _ in 0..10 {
+        app.update();
+    }
+
+    let map = app.world().resource::<DataMapDoubleBuffered<PanickyProducer>>();
+    assert_eq!(attempts.load(Ordering::SeqCst), 1 + map.max_generation_retries);
+    assert!(!map.pending_tasks.contains_key(&BROKEN));
+    assert_eq!(map.read(tile_of(BROKEN)), None);
+    for coords in NEIGHBORS {
+        assert_eq!(map.read(tile_of(coords)), Some(1), "neighbor {coords:?}");
+    }
+}
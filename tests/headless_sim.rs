@@ -0,0 +1,1237 @@
+//! World logic driven frame by frame without a window, see `HeadlessPlugins`.
+
+mod common;
+
+use bevy::prelude::*;
+use bevy::platform::collections::HashMap;
+use rust_sim::{
+    core::chunks::{ChunkPostProcessor, ChunkStats, FocusPolicy, NeighborView},
+    game::{
+        Facing,
+        bootstrap::{BootstrapPlugin, BootstrapProgress, BootstrapSettings, WorldBootstrap, bootstrapped},
+        input::{InputBindings, MoveIntent, resolve_move_intent},
+        physix::{self, Collider, PrevXY, Velocity},
+        projectile::{Projectile, ProjectileSettings, projectile_movement_system},
+        npc::{WanderPlugin, Wanderer},
+        player_movement,
+        camera::FollowCamera,
+        sim_speed::{PendingSimulationSteps, SimulationSpeed, SimulationSpeedPlugin},
+        spawn::{Dormant, SpawnPoint, finalize_spawn, is_free_spot, tile_to_world},
+        teleport::{TeleportPlugin, TeleportRequest, TeleportSettings, Teleporting},
+        render::{
+            RenderLayerZ,
+            culling::{CullingPlugin, CullingStats},
+            light_sim::{
+                directions::Direction,
+                lights::{LightEmitter, LightModulation},
+                lights_map::LightsMapProducer,
+                pbr_cell::PbrCellProducer,
+                lighting::{DayNightCycle, GlobalAmbientLight, LIGHTING_APRON_TILES, LIGHTING_OVERLAY_TILES, sync_light_emitters},
+                simulation::{LightSimSettings, LightSimSnapshot, LightSimState, LightSimStats},
+            },
+            tilemap_render::{HypertileCache, HypertileTracker, PassabilityColorizer, insert_tilemap_render_plugin},
+        },
+        world::passability::TerrainParams,
+    },
+    prelude::*,
+};
+
+use common::{headless_app, loaded_around, update_until};
+
+const ORIGIN: Point = Point { x: 0, y: 0 };
+
+// Open terrain around the origin, so the tests only see what they write
+fn open_terrain() -> PassabilityProducer {
+    PassabilityProducer::new(TerrainParams {
+        safe_zone_radius: Some(1000.0),
+        ..default()
+    })
+}
+
+fn tile_center(x: isize, y: isize) -> Vec3 {
+    tile_to_world(Point { x, y }).extend(0.0)
+}
+
+// Tile whose center is nearest to `position`
+fn tile_at(position: Vec2) -> Point {
+    let tile = (position / TILE_SIZE_IN_UNITS).round();
+    Point {
+        x: tile.x as isize,
+        y: tile.y as isize,
+    }
+}
+
+#[test]
+fn chunks_load_and_unload_around_a_moving_actor() {
+    let mut app = headless_app(|app| {
+        insert_chunked_plugin(
+            app,
+            open_terrain(),
+            ChunkedMapConfig {
+                init_radius_tiles: 0,
+                unload: UnloadPolicy::Immediate,
+                ..default()
+            },
+        );
+    });
+    let actor = app
+        .world_mut()
+        .spawn((
+            Transform::default(),
+            MapRevealActor {
+                radius_chunks: 1,
+                priority: 0,
+            },
+        ))
+        .id();
+    assert!(update_until(&mut app, |app| loaded_around::<PassabilityProducer>(app, ORIGIN, 1)));
+
+    let dimension = DEFAULT_CHUNK_DIMENSION_TILES as isize;
+    let destination = Point { x: 10 * dimension, y: -4 * dimension };
+    app.world_mut().entity_mut(actor).insert(Transform::from_translation(tile_center(
+        destination.x,
+        destination.y,
+    )));
+    assert!(update_until(&mut app, |app| {
+        let map = app.world().resource::<DataMap<PassabilityProducer>>();
+        map.is_loaded_around(destination, 1) && map.read(ORIGIN).is_none()
+    }));
+
+    let map = app.world().resource::<DataMap<PassabilityProducer>>();
+    let actor_chunk = ChunkCoords::from_point(destination, DEFAULT_CHUNK_DIMENSION_TILES);
+    for coords in map.loaded_chunks.keys() {
+        // The jump resets the actor's motion, so nothing is prefetched: only its radius is left
+        assert!(
+            (coords.x - actor_chunk.x).abs() <= 1 && (coords.y - actor_chunk.y).abs() <= 1,
+            "{coords:?} is still loaded, the actor is at {actor_chunk:?}"
+        );
+    }
+}
+
+/// Takes its time per chunk, so tasks are still running when a fast actor has moved on.
+#[derive(Clone, Default)]
+struct SlowProducer;
+
+impl MapDataProducer for SlowProducer {
+    type Item = u8;
+    type GridType = FlatGrid<u8>;
+
+    fn default_value(&self) -> u8 {
+        0
+    }
+
+    fn generate_chunk(&self, _coords: ChunkCoords, dimension_tiles: TilesCount) -> DataChunk<FlatGrid<u8>> {
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        DataChunk::new(FlatGrid::new(dimension_tiles, 1))
+    }
+}
+
+// Chunks generated for nothing while an actor jumps far away every frame, then stops
+fn wasted_generations(cancel_irrelevant: bool) -> u64 {
+    let mut app = headless_app(|app| {
+        insert_chunked_plugin(
+            app,
+            SlowProducer,
+            ChunkedMapConfig {
+                init_radius_tiles: 0,
+                ..default()
+            },
+        );
+    });
+    app.world_mut().resource_mut::<DataMap<SlowProducer>>().cancel_irrelevant = cancel_irrelevant;
+    let actor = app
+        .world_mut()
+        .spawn((
+            Transform::default(),
+            MapRevealActor {
+                radius_chunks: 1,
+                priority: 0,
+            },
+        ))
+        .id();
+
+    let dimension = DEFAULT_CHUNK_DIMENSION_TILES as isize;
+    let mut destination = ORIGIN;
+    for _ in 0..20 {
+        destination.x += 20 * dimension;
+        let translation = tile_center(destination.x, destination.y);
+        app.world_mut().entity_mut(actor).insert(Transform::from_translation(translation));
+        app.update();
+    }
+    // Without cancellation every one of the 180 chunks is generated, 20 ms each, which outlasts
+    // `update_until` on a machine with a single task thread, so this waits by the clock
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(30);
+    loop {
+        app.update();
+        let map = app.world().resource::<DataMap<SlowProducer>>();
+        if map.is_loaded_around(destination, 1) && map.pending_tasks.is_empty() && map.requested_chunks.is_empty() {
+            break;
+        }
+        assert!(std::time::Instant::now() < deadline, "the destination never loaded");
+        std::thread::sleep(std::time::Duration::from_millis(1));
+    }
+    app.world().resource::<ChunkStats<SlowProducer>>().wasted
+}
+
+#[test]
+fn cancellation_cuts_wasted_generation_for_a_fast_actor() {
+    let without = wasted_generations(false);
+    let with = wasted_generations(true);
+    assert!(without > 0, "nothing was wasted even without cancellation");
+    assert!(with * 2 < without, "wasted {with} with cancellation, {without} without");
+}
+
+#[test]
+fn teleport_waits_for_the_destination_chunks_of_every_map() {
+    let mut app = headless_app(|app| {
+        insert_chunked_plugin(app, open_terrain(), ChunkedMapConfig::default());
+        insert_chunked_plugin(
+            app,
+            SlowProducer,
+            ChunkedMapConfig {
+                init_radius_tiles: 0,
+                ..default()
+            },
+        );
+        app.add_plugins(TeleportPlugin).insert_resource(TeleportSettings {
+            timeout_secs: 1000.0,
+            ..default()
+        });
+    });
+    let player = app.world_mut().spawn((Player, Transform::default())).id();
+    let dimension = DEFAULT_CHUNK_DIMENSION_TILES as isize;
+    let destination = Point { x: 100 * dimension + 8, y: 8 };
+    app.world_mut().send_event(TeleportRequest {
+        destination: tile_center(destination.x, destination.y).xy(),
+    });
+
+    let mut frozen_frames = 0;
+    assert!(update_until(&mut app, |app| {
+        let world = app.world();
+        let position = world.get::<Transform>(player).unwrap().translation;
+        if world.get::<Teleporting>(player).is_none() {
+            return true;
+        }
+        frozen_frames += 1;
+        assert_eq!(position, Vec3::ZERO, "moved before the destination was loaded");
+        false
+    }));
+    assert!(frozen_frames > 1, "nothing to wait for");
+    assert!(loaded_around::<SlowProducer>(&app, destination, 1));
+    assert!(loaded_around::<PassabilityProducer>(&app, destination, 1));
+    let position = app.world().get::<Transform>(player).unwrap().translation;
+    let tile = tile_at(position.xy());
+    assert!((tile.x - destination.x).abs() <= 2 && (tile.y - destination.y).abs() <= 2, "landed on {tile:?}");
+}
+
+/// Costs nothing to generate, so only the task overhead is measured.
+#[derive(Clone, Default)]
+struct InstantProducer;
+
+impl MapDataProducer for InstantProducer {
+    type Item = u8;
+    type GridType = FlatGrid<u8>;
+
+    fn default_value(&self) -> u8 {
+        0
+    }
+
+    fn generate_chunk(&self, _coords: ChunkCoords, dimension_tiles: TilesCount) -> DataChunk<FlatGrid<u8>> {
+        DataChunk::new(FlatGrid::new(dimension_tiles, 1))
+    }
+}
+
+#[test]
+fn configured_render_distance_survives_the_app_build() {
+    let mut app = headless_app(|app| {
+        insert_chunked_plugin(
+            app,
+            InstantProducer,
+            ChunkedMapConfig {
+                chunk_dimension_tiles: 32,
+                focus: FocusPolicy::FollowMapRevealActors { radius_chunks: 30 },
+                init_radius_tiles: 0,
+                unload: UnloadPolicy::Immediate,
+            },
+        );
+    });
+    app.update(); // Startup systems included
+    let map = app.world().resource::<DataMap<InstantProducer>>();
+    assert_eq!(map.render_distance_chunks, 30);
+    assert_eq!(map.chunk_dimension_tiles, 32);
+    assert_eq!(map.unload_policy, UnloadPolicy::Immediate);
+}
+
+#[test]
+fn chunk_stats_count_a_scripted_load_and_unload() {
+    let mut app = headless_app(|app| {
+        insert_chunked_plugin(
+            app,
+            InstantProducer,
+            ChunkedMapConfig {
+                init_radius_tiles: 0, // Still the origin chunk
+                unload: UnloadPolicy::Immediate,
+                ..default()
+            },
+        );
+    });
+    {
+        let mut map = app.world_mut().resource_mut::<DataMap<InstantProducer>>();
+        map.prefetch_distance_chunks = 0;
+        map.generation_batch_size = 1; // One task per chunk
+    }
+    let actor = app
+        .world_mut()
+        .spawn((
+            Transform::default(),
+            MapRevealActor {
+                radius_chunks: 1,
+                priority: 0,
+            },
+        ))
+        .id();
+    assert!(update_until(&mut app, |app| loaded_around::<InstantProducer>(app, ORIGIN, 1)));
+    let stats = app.world().resource::<ChunkStats<InstantProducer>>();
+    assert_eq!(stats.requested, 8, "the origin chunk was requested by init");
+    assert_eq!(stats.tasks_spawned, 9);
+    assert_eq!(stats.tasks_completed, 9);
+    assert_eq!((stats.loaded, stats.evicted, stats.cancelled, stats.wasted), (9, 0, 0, 0));
+    assert_eq!(stats.write_queue, 0);
+
+    // Far enough that nothing is shared, everything around the origin goes at once
+    let destination = Point { x: 10 * DEFAULT_CHUNK_DIMENSION_TILES as isize, y: 0 };
+    app.world_mut()
+        .entity_mut(actor)
+        .insert(Transform::from_translation(tile_center(destination.x, destination.y)));
+    assert!(update_until(&mut app, |app| loaded_around::<InstantProducer>(app, destination, 1)));
+    let stats = app.world().resource::<ChunkStats<InstantProducer>>();
+    assert_eq!(stats.requested, 17);
+    assert_eq!(stats.tasks_spawned, 18);
+    assert_eq!(stats.tasks_completed, 18);
+    assert_eq!((stats.loaded, stats.evicted, stats.cancelled, stats.wasted), (9, 9, 0, 0));
+    assert!(stats.loaded_bytes >= 9 * DEFAULT_CHUNK_DIMENSION_TILES * DEFAULT_CHUNK_DIMENSION_TILES);
+    assert!(stats.avg_generation_secs >= 0.0);
+}
+
+// Frames and tasks it takes to load the 225 chunks around an actor
+fn frames_and_tasks_to_load(batch_size: usize) -> (usize, u64) {
+    let radius = 7;
+    let mut app = headless_app(|app| {
+        insert_chunked_plugin(
+            app,
+            InstantProducer,
+            ChunkedMapConfig {
+                chunk_dimension_tiles: 16,
+                focus: FocusPolicy::FollowMapRevealActors { radius_chunks: radius },
+                init_radius_tiles: 0,
+                ..default()
+            },
+        );
+    });
+    {
+        let mut map = app.world_mut().resource_mut::<DataMap<InstantProducer>>();
+        map.generation_batch_size = batch_size;
+        map.max_tasks_in_flight = 4;
+        map.max_chunks_applied_per_frame = usize::MAX; // Only the task limit decides
+    }
+    app.world_mut().spawn((
+        Transform::default(),
+        MapRevealActor {
+            radius_chunks: radius,
+            priority: 0,
+        },
+    ));
+
+    let mut frames = 0;
+    assert!(update_until(&mut app, |app| {
+        frames += 1;
+        loaded_around::<InstantProducer>(app, ORIGIN, radius)
+    }));
+    assert_eq!(app.world().resource::<DataMap<InstantProducer>>().loaded_chunks.len(), 225);
+    (frames, app.world().resource::<ChunkStats<InstantProducer>>().tasks_spawned)
+}
+
+#[test]
+fn batched_generation_needs_fewer_tasks_and_frames() {
+    let (single_frames, single_tasks) = frames_and_tasks_to_load(1);
+    let (batched_frames, batched_tasks) = frames_and_tasks_to_load(8);
+    assert_eq!(single_tasks, 225);
+    assert!(batched_tasks <= 225 / 8 + 8, "{batched_tasks} tasks for batches of 8");
+    assert!(
+        batched_frames < single_frames,
+        "{batched_frames} frames batched, {single_frames} one chunk per task"
+    );
+}
+
+// How far the chunks loaded, pending or queued reach left and right of `focus`, in chunks
+fn wanted_reach(map: &DataMap<InstantProducer>, focus: ChunkCoords) -> (isize, isize) {
+    let wanted = map
+        .loaded_chunks
+        .keys()
+        .chain(map.pending_tasks.keys())
+        .chain(map.requested_chunks.iter());
+    wanted.fold((0, 0), |(left, right), coords| {
+        (left.max(focus.x - coords.x), right.max(coords.x - focus.x))
+    })
+}
+
+#[test]
+fn moving_actor_prefetches_ahead_and_collapses_when_it_stops() {
+    let mut app = headless_app(|app| {
+        insert_chunked_plugin(
+            app,
+            InstantProducer,
+            ChunkedMapConfig {
+                init_radius_tiles: 0,
+                unload: UnloadPolicy::Immediate,
+                ..default()
+            },
+        );
+    });
+    let mut position = Vec3::new(120.0, 120.0, 0.0); // Center of the origin chunk, tiles 0 to 15 span -8 to 248
+    let actor = app
+        .world_mut()
+        .spawn((
+            Transform::from_translation(position),
+            MapRevealActor {
+                radius_chunks: 1,
+                priority: 0,
+            },
+        ))
+        .id();
+    let origin_chunk = ChunkCoords { x: 0, y: 0 };
+    assert!(update_until(&mut app, |app| loaded_around::<InstantProducer>(app, ORIGIN, 1)));
+    let map = app.world().resource::<DataMap<InstantProducer>>();
+    assert_eq!(wanted_reach(map, origin_chunk), (1, 1), "stationary actor");
+
+    // About 240 units per second, two chunks of lookahead, ending at 240 without leaving the origin chunk
+    for _ in 0..15 {
+        position.x += 8.0;
+        app.world_mut().entity_mut(actor).insert(Transform::from_translation(position));
+        app.update();
+    }
+    let map = app.world().resource::<DataMap<InstantProducer>>();
+    assert_eq!(map.focus, vec![(origin_chunk, 0)]);
+    assert_eq!(wanted_reach(map, origin_chunk), (1, 3), "nothing extra behind the actor");
+
+    // Standing still, the smoothed speed drops below the threshold and the prefetch is dropped
+    assert!(update_until(&mut app, |app| {
+        let map = app.world().resource::<DataMap<InstantProducer>>();
+        wanted_reach(map, origin_chunk) == (1, 1)
+    }));
+    assert!(loaded_around::<InstantProducer>(&app, ORIGIN, 1));
+}
+
+/// Frame in which each probe tile was first readable from Update.
+#[derive(Resource, Default)]
+struct ReadableProbe {
+    frame: usize,
+    early_read: Option<usize>, // Requested in PreUpdate before the chunk systems, in frame 3
+    late_read: Option<usize>,  // Requested from Update, in frame 3
+}
+
+const EARLY_TILE: Point = Point { x: 1000, y: 0 };
+const LATE_TILE: Point = Point { x: -1000, y: 0 };
+
+fn request_before_chunk_systems(mut probe: ResMut<ReadableProbe>, mut map: ResMut<DataMap<InstantProducer>>) {
+    probe.frame += 1;
+    if probe.frame == 3 {
+        map.get(EARLY_TILE);
+    }
+}
+
+fn request_and_read_in_update(mut probe: ResMut<ReadableProbe>, mut map: ResMut<DataMap<InstantProducer>>) {
+    let frame = probe.frame;
+    if frame == 3 {
+        map.get(LATE_TILE);
+    }
+    if probe.early_read.is_none() && map.read(EARLY_TILE).is_some() {
+        probe.early_read = Some(frame);
+    }
+    if probe.late_read.is_none() && map.read(LATE_TILE).is_some() {
+        probe.late_read = Some(frame);
+    }
+}
+
+#[test]
+fn requested_chunks_are_readable_the_next_frame() {
+    let mut app = headless_app(|app| {
+        insert_chunked_plugin(
+            app,
+            InstantProducer,
+            ChunkedMapConfig {
+                init_radius_tiles: 0,
+                ..default()
+            },
+        );
+        app.init_resource::<ReadableProbe>()
+            .add_systems(
+                PreUpdate,
+                request_before_chunk_systems.before(ChunkSystems::<InstantProducer>::default()),
+            )
+            .add_systems(Update, request_and_read_in_update);
+    });
+    for _ in 0..10 {
+        app.update();
+        std::thread::sleep(std::time::Duration::from_millis(2)); // Time for the instant task, like a real frame
+    }
+    let probe = app.world().resource::<ReadableProbe>();
+    // Spawned in the frame of the request, inserted first thing the next frame
+    assert_eq!(probe.early_read, Some(4));
+    // Requests made in Update are spawned by the next frame's chunk systems
+    assert_eq!(probe.late_read, Some(5));
+}
+
+/// Every chunk loaded so far, in order.
+#[derive(Resource, Default)]
+struct LoadOrder(Vec<ChunkCoords>);
+
+fn record_load_order(mut loaded: EventReader<ChunkLoaded<InstantProducer>>, mut order: ResMut<LoadOrder>) {
+    order.0.extend(loaded.read().map(|event| event.coords));
+}
+
+#[test]
+fn chunks_around_a_teleported_actor_load_nearest_first() {
+    let radius = 10;
+    let mut app = headless_app(|app| {
+        insert_chunked_plugin(
+            app,
+            InstantProducer,
+            ChunkedMapConfig {
+                chunk_dimension_tiles: 16,
+                focus: FocusPolicy::FollowMapRevealActors { radius_chunks: radius },
+                init_radius_tiles: 0,
+                ..default()
+            },
+        );
+        app.init_resource::<LoadOrder>().add_systems(Update, record_load_order);
+    });
+    {
+        let mut map = app.world_mut().resource_mut::<DataMap<InstantProducer>>();
+        map.max_tasks_in_flight = 8; // Hundreds of requests, only a few generated at a time
+        map.max_chunks_applied_per_frame = usize::MAX;
+    }
+    let actor = app
+        .world_mut()
+        .spawn((
+            Transform::default(),
+            MapRevealActor {
+                radius_chunks: 1,
+                priority: 0,
+            },
+        ))
+        .id();
+    assert!(update_until(&mut app, |app| loaded_around::<InstantProducer>(app, ORIGIN, 1)));
+
+    let destination = ChunkCoords { x: 100, y: 0 };
+    let origin = destination.to_bottom_left_tile_point(16);
+    let center = Point { x: origin.x + 8, y: origin.y + 8 };
+    app.world_mut().entity_mut(actor).insert((
+        Transform::from_translation(tile_center(center.x, center.y)),
+        MapRevealActor {
+            radius_chunks: radius,
+            priority: 0,
+        },
+    ));
+    app.world_mut().resource_mut::<LoadOrder>().0.clear();
+    assert!(update_until(&mut app, |app| loaded_around::<InstantProducer>(app, center, radius)));
+
+    let order = &app.world().resource::<LoadOrder>().0;
+    let distance = |coords: &ChunkCoords| (coords.x - destination.x).abs().max(coords.y.abs());
+    let last_near = order.iter().rposition(|c| distance(c) <= 1).unwrap();
+    let first_ring = order.iter().position(|c| distance(c) == radius as isize).unwrap();
+    assert!(last_near < 9 + 8, "the 3x3 around the actor completed {last_near}th");
+    assert!(last_near < first_ring, "ring {radius} started {first_ring}th, the 3x3 completed {last_near}th");
+}
+
+#[test]
+fn hypertile_sprites_stay_bounded_on_a_long_walk() {
+    let mut app = headless_app(|app| {
+        insert_chunked_plugin(app, open_terrain(), ChunkedMapConfig::default());
+        insert_tilemap_render_plugin::<PassabilityProducer, _>(app, PassabilityColorizer::default(), RenderLayerZ::Background);
+        app.insert_resource(RenderFeatures { enabled: true });
+    });
+    let actor = app
+        .world_mut()
+        .spawn((
+            Transform::default(),
+            MapRevealActor {
+                radius_chunks: 1,
+                priority: 0,
+            },
+        ))
+        .id();
+
+    // Render distance 2 plus a margin of 2 hypertiles around the actor
+    let max_hypertiles = 9 * 9;
+    let mut most_sprites = 0;
+    for x in 0..400 {
+        app.world_mut().entity_mut(actor).insert(Transform::from_translation(tile_center(x, 0)));
+        app.update();
+        let world = app.world_mut();
+        let sprites = world.query::<&Sprite>().iter(world).count();
+        let images = world.resource::<Assets<Image>>().len();
+        assert!(sprites <= max_hypertiles, "{sprites} sprites at tile {x}");
+        assert!(images <= max_hypertiles, "{images} images at tile {x}");
+        most_sprites = most_sprites.max(sprites);
+    }
+    assert!(most_sprites > 0, "nothing was drawn");
+
+    // 100 hypertiles away from the start, where nothing is left
+    let cache = app.world().resource::<HypertileCache<PassabilityProducer>>();
+    assert!(!cache.hypertiles.is_empty());
+    assert!(!cache.hypertiles.contains_key(&ChunkCoords { x: 0, y: 0 }));
+    let tracker = app.world().resource::<HypertileTracker<PassabilityProducer>>();
+    assert!(tracker.spawned.len() <= max_hypertiles);
+}
+
+fn hypertile_visibility(app: &mut App) -> Vec<(Entity, Visibility)> {
+    let world = app.world_mut();
+    world
+        .query_filtered::<(Entity, &Visibility), With<Sprite>>()
+        .iter(world)
+        .map(|(entity, visibility)| (entity, *visibility))
+        .collect()
+}
+
+#[test]
+fn hypertiles_out_of_the_camera_view_are_hidden_but_kept() {
+    let mut app = headless_app(|app| {
+        insert_chunked_plugin(app, open_terrain(), ChunkedMapConfig::default());
+        insert_tilemap_render_plugin::<PassabilityProducer, _>(app, PassabilityColorizer::default(), RenderLayerZ::Background);
+        app.add_plugins(CullingPlugin).insert_resource(RenderFeatures { enabled: true });
+    });
+    app.world_mut().spawn((Window::default(), bevy::window::PrimaryWindow)); // 1280x720
+    let camera = app
+        .world_mut()
+        .spawn((
+            FollowCamera::default(),
+            Transform::default(),
+            Projection::Orthographic(OrthographicProjection::default_2d()),
+        ))
+        .id();
+    app.world_mut().spawn((Transform::default(), MapRevealActor::default()));
+    // The 5x5 hypertiles of 64 units around the actor, all in view
+    assert!(update_until(&mut app, |app| hypertile_visibility(app).len() == 25));
+    let sprites = hypertile_visibility(&mut app);
+    assert!(sprites.iter().all(|(_, visibility)| *visibility == Visibility::Inherited));
+
+    // The view is 560 to 1840 units along x, far to the right of every hypertile
+    let away = Transform::from_xyz(1200.0, 0.0, 0.0);
+    app.world_mut().entity_mut(camera).insert(away);
+    app.update();
+    let culled = hypertile_visibility(&mut app);
+    assert_eq!(culled.len(), sprites.len(), "off-screen hypertiles were despawned");
+    for (entity, visibility) in culled.iter() {
+        assert!(sprites.iter().any(|(e, _)| e == entity), "{entity} was respawned");
+        assert_eq!(*visibility, Visibility::Hidden, "{entity} is off-screen");
+    }
+    let stats = app.world().resource::<CullingStats>();
+    assert_eq!((stats.visible, stats.culled), (0, sprites.len()));
+
+    // Zoomed out 4 times, the same camera sees them again
+    let mut zoomed_out = OrthographicProjection::default_2d();
+    zoomed_out.scale = 4.0;
+    app.world_mut().entity_mut(camera).insert(Projection::Orthographic(zoomed_out));
+    app.update();
+    let shown = hypertile_visibility(&mut app);
+    assert!(shown.iter().all(|(_, visibility)| *visibility == Visibility::Inherited));
+    assert_eq!(app.world().resource::<CullingStats>().visible, sprites.len());
+}
+
+#[test]
+fn pause_stops_movement_but_not_chunk_loading() {
+    let mut app = headless_app(|app| {
+        insert_chunked_plugin(app, open_terrain(), ChunkedMapConfig::default());
+        app.add_plugins(SimulationSpeedPlugin).add_systems(FixedUpdate, physix::apply_velocity);
+    });
+    let mover = app.world_mut().spawn((Transform::default(), Velocity(Vec2::new(100.0, 0.0)))).id();
+    let x = |app: &App| app.world().get::<Transform>(mover).unwrap().translation.x;
+    app.update();
+    app.update();
+    assert!(x(&app) > 0.0, "doesn't move at normal speed");
+
+    *app.world_mut().resource_mut::<SimulationSpeed>() = SimulationSpeed::Paused;
+    app.update(); // This frame's fixed ticks ran before the speed was applied
+    let paused_at = x(&app);
+    let far = Point { x: 50 * DEFAULT_CHUNK_DIMENSION_TILES as isize, y: 0 };
+    assert!(app.world_mut().resource_mut::<DataMap<PassabilityProducer>>().get_option(far).is_none());
+    let mut frames = 0;
+    assert!(update_until(&mut app, |app| {
+        frames += 1;
+        assert_eq!(x(app), paused_at, "moved while paused");
+        app.world().resource::<DataMap<PassabilityProducer>>().read(far).is_some()
+    }));
+    assert!(frames > 1);
+
+    // A single step is one fixed tick at the default 64 Hz
+    app.world_mut().resource_mut::<PendingSimulationSteps>().0 = 1;
+    app.update();
+    assert!((x(&app) - paused_at - 100.0 / 64.0).abs() < 1e-3, "stepped to {}", x(&app));
+    let stepped_at = x(&app);
+    app.update();
+    assert_eq!(x(&app), stepped_at);
+
+    *app.world_mut().resource_mut::<SimulationSpeed>() = SimulationSpeed::Normal;
+    app.update();
+    app.update();
+    assert!(x(&app) > stepped_at, "doesn't move after resuming");
+}
+
+/// Walls the 7x7 tiles around the origin in, wherever the chunk borders are.
+struct WalledOrigin;
+
+impl ChunkPostProcessor<PassabilityProducer> for WalledOrigin {
+    fn process(&self, coords: ChunkCoords, grid: &mut FlatGrid<Passability>, _: &NeighborView<PassabilityProducer>) {
+        let origin = coords.to_bottom_left_tile_point(DEFAULT_CHUNK_DIMENSION_TILES);
+        grid.map_in_place(|x, y, item| {
+            let (x, y) = (origin.x + x as isize, origin.y + y as isize);
+            if x.abs() <= 3 && y.abs() <= 3 { Passability::IMPASSABLE } else { item }
+        });
+    }
+}
+
+#[test]
+fn player_spawns_on_the_nearest_free_tile_around_a_walled_origin() {
+    let mut app = headless_app(|app| {
+        insert_chunked_plugin(app, open_terrain(), ChunkedMapConfig::default());
+        app.world_mut()
+            .resource_mut::<DataMap<PassabilityProducer>>()
+            .add_post_processor(WalledOrigin);
+        app.init_resource::<SpawnPoint>().add_systems(Update, finalize_spawn);
+    });
+    let player = app
+        .world_mut()
+        .spawn((Player, Dormant, Transform::default(), Visibility::Hidden))
+        .id();
+    assert!(update_until(&mut app, |app| app.world().get::<Dormant>(player).is_none()));
+
+    let world = app.world();
+    let position = world.get::<Transform>(player).unwrap().translation;
+    let tile = tile_at(position.xy());
+    let passability = world.resource::<DataMap<PassabilityProducer>>();
+    assert_eq!(passability.read(ORIGIN), Some(Passability::IMPASSABLE));
+    assert!(is_free_spot(passability, tile, Passability::WALL_THRESHOLD), "spawned at {tile:?}");
+    // The closest tiles whose 3x3 clears the wall are 5 tiles out along an axis
+    assert_eq!(tile.x * tile.x + tile.y * tile.y, 25, "spawned at {tile:?}");
+    assert_eq!(*world.get::<Visibility>(player).unwrap(), Visibility::Inherited);
+}
+
+#[test]
+fn wanderers_never_stand_on_impassable_tiles() {
+    let mut app = headless_app(|app| {
+        let terrain = TerrainParams {
+            safe_zone_radius: None, // Walls right around the spawn
+            ..default()
+        };
+        let mut producer = PassabilityProducer::new(terrain);
+        producer.seed = 7;
+        insert_chunked_plugin(app, producer, ChunkedMapConfig::default());
+        app.add_plugins(WanderPlugin).add_systems(
+            FixedUpdate,
+            (physix::apply_velocity, physix::resolve_tile_collisions).chain(),
+        );
+    });
+    app.world_mut().spawn((
+        Transform::default(),
+        MapRevealActor {
+            radius_chunks: 2,
+            priority: 0,
+        },
+    ));
+    assert!(update_until(&mut app, |app| loaded_around::<PassabilityProducer>(app, ORIGIN, 2)));
+    let map = app.world().resource::<DataMap<PassabilityProducer>>();
+    let walls = (-16..=16)
+        .flat_map(|x| (-16..=16).map(move |y| Point { x, y }))
+        .filter(|tile| map.read(*tile).is_some_and(|p| p.0 < Passability::WALL_THRESHOLD))
+        .count();
+    assert!(walls > 0, "no walls around the origin to walk into");
+
+    let mut start = Vec::new();
+    // Two fixed steps per update at the default 64 Hz
+    for frame in 0..200 {
+        app.update();
+        let world = app.world_mut();
+        let mut query = world.query_filtered::<(&Transform, &Collider), With<Wanderer>>();
+        let map = world.resource::<DataMap<PassabilityProducer>>();
+        let wanderers: Vec<_> = query.iter(world).map(|(t, c)| (t.translation.xy(), c.threshold)).collect();
+        for (position, threshold) in wanderers.iter() {
+            let tile = tile_at(*position);
+            let passability = map.read(tile).unwrap();
+            assert!(passability.0 >= *threshold, "wanderer on {tile:?} ({passability:?}) in frame {frame}");
+        }
+        if start.is_empty() {
+            start = wanderers.into_iter().map(|(position, _)| position).collect();
+        } else if frame == 199 {
+            let moved = wanderers.iter().zip(start.iter()).filter(|((now, _), then)| now != *then).count();
+            assert!(moved > 0, "nobody wandered");
+        }
+    }
+    assert!(!start.is_empty(), "no wanderers spawned");
+}
+
+#[test]
+fn facing_follows_the_last_movement_input() {
+    let mut app = headless_app(|app| {
+        insert_chunked_plugin(app, open_terrain(), ChunkedMapConfig::default());
+        app.add_systems(Update, player_movement);
+    });
+    let player = app
+        .world_mut()
+        .spawn((
+            Player,
+            Transform::default(),
+            MoveIntent(Vec2::ZERO),
+            Velocity::default(),
+            MeshMaterial2d::<ColorMaterial>(Handle::default()),
+        ))
+        .id();
+
+    for (intent, facing) in [
+        (Vec2::new(-1.0, 1.0), Direction::NW),
+        (Vec2::new(0.0, -1.0), Direction::S),
+        (Vec2::ZERO, Direction::S), // Standing still keeps the last direction
+        (Vec2::new(1.0, 0.2), Direction::E),
+    ] {
+        app.world_mut().entity_mut(player).insert(MoveIntent(intent));
+        app.update();
+        assert_eq!(app.world().get::<Facing>(player).unwrap().0, facing, "intent {intent}");
+    }
+}
+
+
+#[test]
+fn bootstrap_holds_the_player_until_the_startup_chunks_are_in() {
+    let mut app = headless_app(|app| {
+        app.add_plugins(bevy::state::app::StatesPlugin)
+            .add_plugins(BootstrapPlugin)
+            .insert_resource(BootstrapSettings { timeout_secs: 1000.0 })
+            .init_resource::<InputBindings>();
+        insert_chunked_plugin(
+            app,
+            open_terrain(),
+            ChunkedMapConfig {
+                init_radius_tiles: 0,
+                ..default()
+            },
+        );
+        insert_chunked_plugin(
+            app,
+            SlowProducer,
+            ChunkedMapConfig {
+                init_radius_tiles: 2 * DEFAULT_CHUNK_DIMENSION_TILES,
+                ..default()
+            },
+        );
+        app.add_systems(
+            Update,
+            (resolve_move_intent.run_if(bootstrapped), player_movement, physix::apply_velocity).chain(),
+        );
+    });
+    let player = app
+        .world_mut()
+        .spawn((
+            Player,
+            Transform::default(),
+            MoveIntent(Vec2::ZERO),
+            Velocity::default(),
+            MeshMaterial2d::<ColorMaterial>(Handle::default()),
+        ))
+        .id();
+    app.world_mut().resource_mut::<ButtonInput<KeyCode>>().press(KeyCode::KeyD);
+
+    let mut loading_frames = 0;
+    assert!(update_until(&mut app, |app| {
+        let world = app.world();
+        if *world.resource::<State<WorldBootstrap>>().get() == WorldBootstrap::Playing {
+            return true;
+        }
+        loading_frames += 1;
+        let position = world.get::<Transform>(player).unwrap().translation;
+        assert_eq!(position, Vec3::ZERO, "moved while loading");
+        false
+    }));
+    assert!(loading_frames > 1, "nothing to wait for, {loading_frames} frames loading");
+    let progress = app.world().resource::<BootstrapProgress>();
+    assert_eq!(progress.remaining, 0, "started playing with chunks missing");
+    assert!(progress.total > 1);
+
+    app.update();
+    let position = app.world().get::<Transform>(player).unwrap().translation;
+    assert!(position.x > 0.0, "the player doesn't move once playing: {position}");
+}
+
+
+#[test]
+fn player_stops_at_a_wall() {
+    let mut app = headless_app(|app| {
+        insert_chunked_plugin(app, open_terrain(), ChunkedMapConfig::default());
+        app.add_systems(
+            Update,
+            (player_movement, physix::apply_velocity, physix::resolve_tile_collisions).chain(),
+        );
+    });
+    app.world_mut().spawn((
+        Transform::default(),
+        MapRevealActor {
+            radius_chunks: 1,
+            priority: 0,
+        },
+    ));
+    assert!(update_until(&mut app, |app| loaded_around::<PassabilityProducer>(app, ORIGIN, 1)));
+
+    // A wall across the path, 3 tiles east of the start
+    let wall_x = 3;
+    let mut map = app.world_mut().resource_mut::<DataMap<PassabilityProducer>>();
+    for y in -4..=4 {
+        map.write(Point { x: wall_x, y }, Passability::IMPASSABLE);
+    }
+    let collider = Collider {
+        radius: 4.0,
+        threshold: Passability::WALL_THRESHOLD,
+    };
+    // Recolored by the passability under it
+    let mut pallete = app.world_mut().resource_mut::<Pallete>();
+    for color in ["red", "limegreen"] {
+        pallete.colors.insert(color.to_string(), Handle::default());
+    }
+    let player = app
+        .world_mut()
+        .spawn((
+            Player,
+            Transform::default(),
+            MoveIntent(Vec2::X),
+            Velocity::default(),
+            PrevXY::default(),
+            MeshMaterial2d::<ColorMaterial>(Handle::default()),
+            collider,
+        ))
+        .id();
+    for _ in 0..60 {
+        app.update(); // 2 s at 200 units per second, far past the wall if nothing stopped it
+    }
+
+    let position = app.world().get::<Transform>(player).unwrap().translation;
+    let wall_edge = tile_center(wall_x, 0).x - TILE_SIZE_IN_UNITS / 2.0;
+    assert!(position.x > TILE_SIZE_IN_UNITS, "the player didn't move: {position}");
+    assert!(position.x + collider.radius <= wall_edge + TILE_SIZE_IN_UNITS / 2.0, "went into the wall: {position}");
+    assert_eq!(position.y, 0.0);
+    assert!(app.world().get::<Velocity>(player).unwrap().0.x > 0.0, "still pushing against it");
+}
+
+#[test]
+fn each_lighting_config_builds_its_own_resources() {
+    let static_overlay = LightingConfig::StaticOverlay {
+        size_units: 120.0,
+        color: Color::WHITE,
+    };
+    for config in [static_overlay, LightingConfig::default()] {
+        let mut app = headless_app(|app| {
+            insert_chunked_plugin(app, open_terrain(), ChunkedMapConfig::default());
+            app.add_plugins(LightingPlugin { config });
+        });
+        app.update();
+        let world = app.world();
+        assert_eq!(*world.resource::<LightingConfig>(), config);
+        // Both keep the light maps and the day cycle, other plugins write into them
+        assert!(world.contains_resource::<DataMap<LightsMapProducer>>());
+        assert!(world.contains_resource::<DataMap<PbrCellProducer>>());
+        assert!(world.contains_resource::<GlobalAmbientLight>());
+        assert!(world.contains_resource::<DayNightCycle>());
+        let simulated = config.is_simulated();
+        assert_eq!(world.contains_resource::<LightSimSettings>(), simulated, "{config:?}");
+        assert_eq!(world.contains_resource::<LightSimState>(), simulated, "{config:?}");
+        assert_eq!(world.contains_resource::<LightSimStats>(), simulated, "{config:?}");
+        if let Some(settings) = world.get_resource::<LightSimSettings>() {
+            assert_eq!(settings.overlay_tiles, LIGHTING_OVERLAY_TILES);
+            assert_eq!(settings.apron_tiles, LIGHTING_APRON_TILES);
+        }
+    }
+}
+
+// Lit planes of the light simulated around a light at the origin, in the world of `seed`
+fn simulated_light(seed: u64) -> Vec<Vec<Vec<Vec3>>> {
+    let mut app = headless_app(|app| {
+        app.insert_resource(WorldSeed(seed));
+        let terrain = TerrainParams {
+            safe_zone_radius: Some(2.0), // Walls from the seed right next to the light
+                ..default()
+        };
+        insert_chunked_plugin(app, PassabilityProducer::new(terrain), ChunkedMapConfig::default());
+        app.add_plugins(LightingPlugin::default());
+    });
+    app.world_mut().spawn((
+        Transform::default(),
+        MapRevealActor {
+            radius_chunks: 2,
+            priority: 0,
+        },
+    ));
+    app.world_mut().spawn((Transform::default(), FollowCamera::default()));
+    app.world_mut().spawn((Transform::default(), LightEmitter::default()));
+    assert!(update_until(&mut app, |app| {
+        loaded_around::<PassabilityProducer>(app, ORIGIN, 2)
+            && loaded_around::<PbrCellProducer>(app, ORIGIN, 1)
+            && loaded_around::<LightsMapProducer>(app, ORIGIN, 1)
+    }));
+    for _ in 0..5 {
+        app.update(); // The materials and emitters are copied in once their chunks are in
+    }
+
+    let world = app.world();
+    let size = 2 * DEFAULT_CHUNK_DIMENSION_TILES;
+    let half = DEFAULT_CHUNK_DIMENSION_TILES as isize;
+    let snapshot = LightSimSnapshot::capture(
+        world.resource::<DataMap<LightsMapProducer>>(),
+        world.resource::<DataMap<PbrCellProducer>>(),
+        Point { x: -half, y: -half },
+        size,
+    );
+    snapshot.simulate(10).lit.to_vec()
+}
+
+#[test]
+fn light_simulation_is_identical_for_the_same_seed() {
+    let first = simulated_light(42);
+    let second = simulated_light(42);
+    let center = DEFAULT_CHUNK_DIMENSION_TILES;
+    let at_light: Vec3 = first.iter().map(|plane| plane[center][center]).sum();
+    assert!(at_light.min_element() > 0.0, "the light at the origin is missing");
+    assert_eq!(first, second);
+}
+
+// World tiles lit by the cone light of `player` alone, simulated over what's loaded around it.
+// The cone is taken from the lights map, where `sync_light_emitters` put it.
+fn lit_by_cone(app: &App, player: Entity) -> Vec<Point> {
+    let world = app.world();
+    let tile = WorldPos(world.get::<Transform>(player).unwrap().translation.xy()).to_tile().0;
+    let half = DEFAULT_CHUNK_DIMENSION_TILES as isize;
+    let mut snapshot = LightSimSnapshot::capture(
+        world.resource::<DataMap<LightsMapProducer>>(),
+        world.resource::<DataMap<PbrCellProducer>>(),
+        Point { x: tile.x - half, y: tile.y - half },
+        2 * DEFAULT_CHUNK_DIMENSION_TILES,
+    );
+    let (x, y) = (half as usize, half as usize);
+    let cone = snapshot.emitters[x][y].directed_lights;
+    assert!(cone.is_some(), "no cone light at {tile:?}");
+    for column in snapshot.emitters.iter_mut() {
+        column.fill(default()); // Without the generated lights, like the one near the origin
+    }
+    snapshot.emitters[x][y].directed_lights = cone;
+
+    let lit = snapshot.simulate(10).lit;
+    let mut tiles = Vec::new();
+    for x in 0..snapshot.size {
+        for y in 0..snapshot.size {
+            if lit.iter().any(|plane| plane[x][y].element_sum() > 0.0) {
+                tiles.push(Point { x: snapshot.origin.x + x as isize, y: snapshot.origin.y + y as isize });
+            }
+        }
+    }
+    tiles
+}
+
+#[test]
+fn cone_light_of_a_player_facing_north_lights_the_tiles_above_it() {
+    let mut app = headless_app(|app| {
+        insert_chunked_plugin(app, open_terrain(), ChunkedMapConfig::default());
+        app.add_plugins(LightingPlugin::default())
+            .add_systems(Update, player_movement);
+    });
+    app.world_mut().spawn((Transform::default(), FollowCamera::default()));
+    let player = app
+        .world_mut()
+        .spawn((
+            Player,
+            Transform::default(),
+            MapRevealActor {
+                radius_chunks: 2,
+                priority: 0,
+            },
+            MoveIntent(Vec2::Y),
+            Velocity::default(),
+            MeshMaterial2d::<ColorMaterial>(Handle::default()),
+        ))
+        .id();
+    app.update();
+    let facing = app.world().get::<Facing>(player).unwrap().0;
+    assert_eq!(facing, Direction::N);
+    app.world_mut().entity_mut(player).insert((
+        MoveIntent(Vec2::ZERO),
+        LightEmitter {
+            direction: Some(facing),
+            spread: 0, // A single beam, so nothing is lit beside it by the cone's edges
+            ..default()
+        },
+    ));
+    assert!(update_until(&mut app, |app| {
+        loaded_around::<PbrCellProducer>(app, ORIGIN, 1) && loaded_around::<LightsMapProducer>(app, ORIGIN, 1)
+    }));
+    for _ in 0..5 {
+        app.update(); // The materials and the cone are copied in once their chunks are in
+    }
+
+    let lit = lit_by_cone(&app, player);
+    assert!(lit.iter().any(|tile| tile.y > ORIGIN.y), "nothing lit ahead: {lit:?}");
+    let behind: Vec<&Point> = lit.iter().filter(|tile| tile.y < ORIGIN.y).collect();
+    assert!(behind.is_empty(), "lit behind the player: {behind:?}");
+}
+
+// Tiles of the lights map that differ from what was generated there, in the rows around the
+// bolt's path. The generated lights, like the one near the origin, don't count.
+fn lit_tiles(app: &App) -> Vec<Point> {
+    let lights = app.world().resource::<DataMap<LightsMapProducer>>();
+    let dimension = lights.chunk_dimension_tiles;
+    let mut generated = HashMap::new();
+    let mut lit = Vec::new();
+    for x in 0..32 {
+        for y in -2..=2 {
+            let tile = Point { x, y };
+            let coords = ChunkCoords::from_point(tile, dimension);
+            let chunk = generated
+                .entry(coords)
+                .or_insert_with(|| lights.producer.generate_chunk(coords, dimension));
+            let (local_x, local_y) = tile.local_in_chunk(dimension);
+            let expected = chunk.grid.get_item(local_x, local_y).copied();
+            if lights.read(tile).is_some_and(|cell| Some(cell) != expected) {
+                lit.push(tile);
+            }
+        }
+    }
+    lit
+}
+
+#[test]
+fn projectile_stops_at_a_wall_across_a_chunk_border_without_leaving_light() {
+    let mut app = headless_app(|app| {
+        insert_chunked_plugin(app, open_terrain(), ChunkedMapConfig::default());
+        insert_chunked_plugin(app, LightsMapProducer, ChunkedMapConfig::default());
+        app.init_resource::<LightModulation>()
+            .insert_resource(ProjectileSettings {
+                flash: None, // Only the bolt's own light
+                ..default()
+            })
+            .add_systems(FixedUpdate, projectile_movement_system)
+            .add_systems(Update, sync_light_emitters);
+    });
+    let dimension = DEFAULT_CHUNK_DIMENSION_TILES as isize; // The chunk border is at tile x = dimension
+    let border = Point { x: dimension, y: 0 };
+    app.world_mut().spawn((
+        Transform::from_translation(tile_center(border.x, border.y)),
+        MapRevealActor {
+            radius_chunks: 1,
+            priority: 0,
+        },
+    ));
+    assert!(update_until(&mut app, |app| {
+        loaded_around::<PassabilityProducer>(app, border, 1) && loaded_around::<LightsMapProducer>(app, border, 1)
+    }));
+    assert!(lit_tiles(&app).is_empty());
+
+    // Fired from the first chunk at a wall in the next one
+    let wall_x = dimension + 4;
+    let mut map = app.world_mut().resource_mut::<DataMap<PassabilityProducer>>();
+    for y in -2..=2 {
+        map.write(Point { x: wall_x, y }, Passability::IMPASSABLE);
+    }
+    let settings = app.world().resource::<ProjectileSettings>().clone();
+    let projectile = app
+        .world_mut()
+        .spawn((
+            Projectile {
+                speed: settings.speed,
+                max_distance: settings.max_distance,
+                light: settings.light,
+                direction: Vec2::X,
+                traveled: 0.0,
+            },
+            Collider {
+                radius: 1.5,
+                ..default()
+            },
+            LightEmitter {
+                color: settings.light.unwrap(),
+                ..default()
+            },
+            Transform::from_translation(tile_center(dimension - 6, 0)),
+        ))
+        .id();
+
+    let mut last_x = f32::MIN;
+    let mut lit_while_flying = 0;
+    for _ in 0..60 {
+        app.update(); // 2 s at 400 units per second, far past the wall if nothing stopped it
+        let lit = lit_tiles(&app);
+        assert!(lit.len() <= 1, "light left behind: {lit:?}");
+        let Some(transform) = app.world().get::<Transform>(projectile) else {
+            continue;
+        };
+        let position = transform.translation;
+        assert!(position.x >= last_x);
+        last_x = position.x;
+        // The light follows the bolt, written the same frame it moved
+        assert_eq!(lit, vec![WorldPos(position.xy()).to_tile().0]);
+        lit_while_flying += 1;
+    }
+
+    assert!(app.world().get_entity(projectile).is_err(), "the bolt is still flying");
+    assert!(lit_while_flying > 1);
+    assert!(last_x > TileCoord::new(dimension, 0).corner().0.x, "never crossed the border: {last_x}");
+    // Passability is sampled bilinearly, so the wall only blocks from near its tile center on
+    assert!(last_x <= TileCoord::new(wall_x, 0).center().0.x, "went through the wall: {last_x}");
+    assert!(lit_tiles(&app).is_empty());
+}
+
+// Chunks loaded, pending or requested, sorted
+fn touched_chunks(app: &App) -> Vec<ChunkCoords> {
+    let map = app.world().resource::<DataMap<InstantProducer>>();
+    let mut touched: Vec<ChunkCoords> = map
+        .loaded_chunks
+        .keys()
+        .chain(map.pending_tasks.keys())
+        .chain(map.requested_chunks.iter())
+        .copied()
+        .collect();
+    touched.sort_by_key(|coords| (coords.y, coords.x));
+    touched.dedup();
+    touched
+}
+
+#[test]
+fn camera_window_loads_exactly_the_chunks_it_overlaps() {
+    let mut app = headless_app(|app| {
+        insert_chunked_plugin(
+            app,
+            InstantProducer,
+            ChunkedMapConfig {
+                focus: FocusPolicy::FollowCameraWindow { tiles: 32 },
+                init_radius_tiles: 0, // Still the origin chunk, outside both windows below
+                unload: UnloadPolicy::Immediate,
+                ..default()
+            },
+        );
+    });
+    let camera = app
+        .world_mut()
+        .spawn((Transform::from_translation(tile_center(40, -10)), FollowCamera::default()))
+        .id();
+
+    // Tiles 24..=55 by -26..=5 with chunks of 16, then -24..=7 by -8..=23
+    let windows = [((40, -10), (1..=3, -2..=0)), ((-8, 8), (-2..=0, -1..=1))];
+    for ((x, y), (chunks_x, chunks_y)) in windows {
+        app.world_mut().entity_mut(camera).insert(Transform::from_translation(tile_center(x, y)));
+        let expected: Vec<ChunkCoords> = chunks_y
+            .flat_map(|cy| chunks_x.clone().map(move |cx| ChunkCoords { x: cx, y: cy }))
+            .collect();
+        assert!(update_until(&mut app, |app| {
+            let map = app.world().resource::<DataMap<InstantProducer>>();
+            map.loaded_chunks.len() == expected.len() && touched_chunks(app) == expected
+        }));
+        // And stays that way while the camera rests
+        for _ in 0..3 {
+            app.update();
+            assert_eq!(touched_chunks(&app), expected, "window around ({x}, {y})");
+        }
+    }
+}
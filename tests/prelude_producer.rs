@@ -0,0 +1,82 @@
+//! A producer defined outside the crate, registered and read through `rust_sim::prelude` only.
+
+mod common;
+
+use bevy::prelude::*;
+use rust_sim::prelude::*;
+
+use common::{headless_app, loaded_around, update_until};
+
+/// Checkerboard of 1s and 2s, so an ungenerated tile (0) never passes for a generated one.
+#[derive(Clone, Default)]
+struct CheckerProducer;
+
+fn checker(point: Point) -> u8 {
+    (point.x + point.y).rem_euclid(2) as u8 + 1
+}
+
+impl MapDataProducer for CheckerProducer {
+    type Item = u8;
+    type GridType = FlatGrid<u8>;
+
+    fn default_value(&self) -> u8 {
+        0
+    }
+
+    fn generate_chunk(&self, coords: ChunkCoords, dimension_tiles: TilesCount) -> DataChunk<FlatGrid<u8>> {
+        let origin = coords.to_bottom_left_tile_point(dimension_tiles);
+        DataChunk::new(FlatGrid::from_fn(dimension_tiles, |x, y| {
+            checker(Point {
+                x: origin.x + x as isize,
+                y: origin.y + y as isize,
+            })
+        }))
+    }
+}
+
+#[test]
+fn custom_producer_generates_chunks_around_an_actor() {
+    let mut app = headless_app(|app| {
+        insert_chunked_plugin(
+            app,
+            CheckerProducer,
+            ChunkedMapConfig {
+                init_radius_tiles: DEFAULT_CHUNK_DIMENSION_TILES,
+                ..default()
+            },
+        );
+    });
+    app.world_mut().spawn((
+        Transform::default(),
+        MapRevealActor {
+            radius_chunks: 1,
+            priority: 0,
+        },
+    ));
+
+    let origin = Point { x: 0, y: 0 };
+    assert!(update_until(&mut app, |app| loaded_around::<CheckerProducer>(app, origin, 1)));
+
+    let map = app.world().resource::<DataMap<CheckerProducer>>();
+    let dimension = DEFAULT_CHUNK_DIMENSION_TILES as isize;
+    for (x, y) in [(0, 0), (1, 0), (-1, -1), (-dimension, 5), (dimension + 3, -dimension)] {
+        let point = Point { x, y };
+        assert_eq!(map.read(point), Some(checker(point)), "tile {point:?}");
+    }
+    // Out of every actor's reach, never generated
+    assert_eq!(map.read(Point { x: 10 * dimension, y: 0 }), None);
+}
+
+#[test]
+fn writes_through_the_prelude_are_read_back() {
+    let mut app = headless_app(|app| {
+        insert_chunked_plugin(app, CheckerProducer, ChunkedMapConfig::default());
+    });
+    app.world_mut().spawn((Transform::default(), MapRevealActor::default()));
+    let origin = Point { x: 0, y: 0 };
+    assert!(update_until(&mut app, |app| loaded_around::<CheckerProducer>(app, origin, 0)));
+
+    let mut map = app.world_mut().resource_mut::<DataMap<CheckerProducer>>();
+    map.write(Point { x: 3, y: -2 }, 9);
+    assert_eq!(map.read(Point { x: 3, y: -2 }), Some(9));
+}